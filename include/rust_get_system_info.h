@@ -0,0 +1,93 @@
+#ifndef RUST_GET_SYSTEM_INFO_H
+#define RUST_GET_SYSTEM_INFO_H
+
+/* Generated by cbindgen from src/ffi.rs; do not edit. */
+
+#include <stddef.h>
+#include <stdint.h>
+
+/*
+ Result of a C interface call. Apart from `Ok`, `NullPointer` and `Panic`,
+ the codes mirror the collector's errors.
+ */
+enum SysinfoStatus {
+  SYSINFO_STATUS_OK = 0,
+  SYSINFO_STATUS_FILE_CREATION = 1,
+  SYSINFO_STATUS_FILE_WRITE = 2,
+  SYSINFO_STATUS_JSON_SERIALIZATION = 3,
+  SYSINFO_STATUS_FILE_READ = 4,
+  SYSINFO_STATUS_INVALID_SPEC = 5,
+  SYSINFO_STATUS_EMPTY_COLLECTION = 6,
+  SYSINFO_STATUS_NIC_ERRORS = 7,
+  SYSINFO_STATUS_CHECK_FAILED = 8,
+  SYSINFO_STATUS_KEY_NOT_FOUND = 9,
+  SYSINFO_STATUS_UNKNOWN_METRIC = 10,
+  SYSINFO_STATUS_METRIC_UNAVAILABLE = 11,
+  SYSINFO_STATUS_INVALID_ARGUMENT = 12,
+  SYSINFO_STATUS_LISTEN = 13,
+  SYSINFO_STATUS_KEY_CONVERSION = 14,
+  SYSINFO_STATUS_SHARED_MEMORY = 15,
+  SYSINFO_STATUS_UPDATE = 16,
+  SYSINFO_STATUS_S3_UPLOAD = 17,
+  SYSINFO_STATUS_CORE_COLLECTION = 18,
+  SYSINFO_STATUS_TIMEOUT = 19,
+  SYSINFO_STATUS_MQTT = 20,
+  SYSINFO_STATUS_ALERTS_RAISED = 21,
+  SYSINFO_STATUS_SERVICE = 22,
+  SYSINFO_STATUS_NOT_SELECTED = 23,
+  /*
+   An output pointer was null
+   */
+  SYSINFO_STATUS_NULL_POINTER = 98,
+  /*
+   The collector panicked
+   */
+  SYSINFO_STATUS_PANIC = 99,
+};
+typedef int32_t SysinfoStatus;
+
+/*
+ Collects a snapshot as a NUL-terminated JSON string.
+
+ On success, `*out_json` points to the string, which the caller must
+ release with `sysinfo_free_string`; on failure it is set to null.
+
+ # Safety
+
+ `out_json` must be null or valid for writing a pointer.
+ */
+int32_t sysinfo_collect_json(char **out_json);
+
+/*
+ Collects a snapshot encoded as MessagePack.
+
+ On success, `*out` points to `*len` bytes, which the caller must release
+ with `sysinfo_free_buffer`; on failure they are set to null and 0.
+
+ # Safety
+
+ `out` and `len` must be null or valid for writing.
+ */
+int32_t sysinfo_collect_msgpack(uint8_t **out, size_t *len);
+
+/*
+ Releases a string returned by `sysinfo_collect_json`. Null is ignored.
+
+ # Safety
+
+ `json` must be null or a string from `sysinfo_collect_json` that has not
+ been released yet.
+ */
+void sysinfo_free_string(char *json);
+
+/*
+ Releases a buffer returned by `sysinfo_collect_msgpack`. Null is ignored.
+
+ # Safety
+
+ `buffer` must be null or a buffer from `sysinfo_collect_msgpack` that has
+ not been released yet, and `len` its length.
+ */
+void sysinfo_free_buffer(uint8_t *buffer, size_t len);
+
+#endif /* RUST_GET_SYSTEM_INFO_H */
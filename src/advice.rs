@@ -0,0 +1,259 @@
+//! `--advise`: plain-language guidance on a snapshot.
+//!
+//! Figures such as "62% memory used" mean little without context. Each rule
+//! pairs a predicate over the snapshot with a message template, and the
+//! rules that hold are printed below the console report: a cgroup close to
+//! its memory limit, swapping under pressure, or a nearly full root
+//! filesystem. The advice only reaches the JSON with `--advise-json`.
+
+use crate::locale::Locale;
+use crate::{format_bytes, SystemInfo};
+use serde::{Deserialize, Serialize};
+
+/// Share of the cgroup memory limit above which an OOM kill is near.
+const CGROUP_MEMORY_PERCENT: f64 = 85.0;
+
+/// Free space on the root filesystem, in percent, below which it is
+/// called out.
+const ROOT_DISK_FREE_PERCENT: f64 = 5.0;
+
+/// One piece of advice.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Advice {
+    /// The rule that gave it, e.g. "cgroup-oom"
+    pub rule: String,
+    pub message: String,
+}
+
+/// A condition on a snapshot and what to say when it holds.
+struct Rule {
+    name: &'static str,
+    /// Returns the values for the template's `{}` placeholders, in order,
+    /// if the rule applies. The second argument is `--swap-warn-rate`.
+    applies: fn(&SystemInfo, f64) -> Option<Vec<String>>,
+    template: &'static str,
+}
+
+/// The rules, in the order their advice is given.
+const RULES: &[Rule] = &[
+    Rule {
+        name: "cgroup-oom",
+        applies: cgroup_near_limit,
+        template: "Memory use is {} of this cgroup's limit ({} of {}); the kernel OOM-kills \
+                   processes in the cgroup when it reaches the limit",
+    },
+    Rule {
+        name: "swap-pressure",
+        applies: swapping_in,
+        template: "{} of swap is in use and {} pages/s are swapped in: the working set does \
+                   not fit in memory",
+    },
+    Rule {
+        name: "root-disk-full",
+        applies: root_disk_full,
+        template: "The root filesystem has only {} free; services fail in odd ways when it \
+                   fills up",
+    },
+];
+
+/// Returns the advice for `info`, with swap-in rates above `swap_warn_rate`
+/// pages per second counting as high.
+pub fn advise(info: &SystemInfo, swap_warn_rate: f64) -> Vec<Advice> {
+    RULES
+        .iter()
+        .filter_map(|rule| {
+            let values = (rule.applies)(info, swap_warn_rate)?;
+            Some(Advice {
+                rule: rule.name.to_string(),
+                message: fill(rule.template, &values),
+            })
+        })
+        .collect()
+}
+
+/// Replaces the `{}` placeholders in `template` with `values`, in order.
+/// Placeholders without a value are left as they are.
+fn fill(template: &str, values: &[String]) -> String {
+    let mut values = values.iter();
+    let mut parts = template.split("{}");
+    let mut message = parts.next().unwrap_or_default().to_string();
+    for part in parts {
+        message.push_str(values.next().map_or("{}", String::as_str));
+        message.push_str(part);
+    }
+    message
+}
+
+/// The cgroup uses more than `CGROUP_MEMORY_PERCENT` of its memory limit.
+fn cgroup_near_limit(info: &SystemInfo, _: f64) -> Option<Vec<String>> {
+    let cgroup = info.cgroup_memory?;
+    let limit = cgroup.limit.filter(|&limit| limit > 0)?;
+    let percent = cgroup.usage as f64 / limit as f64 * 100.0;
+    (percent > CGROUP_MEMORY_PERCENT).then(|| {
+        vec![
+            format!("{:.1}%", percent),
+            format_bytes(cgroup.usage, 0, Locale::default()),
+            format_bytes(limit, 0, Locale::default()),
+        ]
+    })
+}
+
+/// Swap is in use and pages are swapped in faster than `swap_warn_rate`,
+/// going by the rate between samples in interval mode, else the one
+/// sampled during collection.
+fn swapping_in(info: &SystemInfo, swap_warn_rate: f64) -> Option<Vec<String>> {
+    let rate = info.pages_swapped_in_per_sec.or_else(|| {
+        info.memory_activity
+            .map(|activity| activity.swap_in_per_sec)
+    })?;
+    (info.used_swap > 0 && rate > swap_warn_rate).then(|| {
+        vec![
+            format_bytes(info.used_swap, 0, Locale::default()),
+            format!("{:.0}", rate),
+        ]
+    })
+}
+
+/// The root filesystem has less than `ROOT_DISK_FREE_PERCENT` free.
+fn root_disk_full(info: &SystemInfo, _: f64) -> Option<Vec<String>> {
+    let free = 100.0 - info.root_disk_usage_percent?;
+    (free < ROOT_DISK_FREE_PERCENT).then(|| vec![format!("{:.1}%", free)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_advise() {
+        let info: SystemInfo = serde_json::from_value(serde_json::json!({
+            "total_memory": 17179869184u64,
+            "used_memory": 10651518156u64,
+            "cgroup_memory": {"usage": 1932735283, "limit": 2147483648u64},
+            "used_swap": 536870912,
+            "memory_activity": {
+                "interval_secs": 1.0,
+                "swap_in_per_sec": 850.0,
+                "swap_out_per_sec": 120.0,
+                "major_faults_per_sec": 900.0
+            },
+            "root_disk_usage_percent": 97.5
+        }))
+        .unwrap();
+        let rules: Vec<String> = advise(&info, 100.0)
+            .into_iter()
+            .map(|advice| advice.rule)
+            .collect();
+        assert_eq!(rules, ["cgroup-oom", "swap-pressure", "root-disk-full"]);
+        assert_eq!(
+            advise(&info, 100.0)[0].message,
+            "Memory use is 90.0% of this cgroup's limit (1.80 GB of 2.00 GB); the kernel \
+             OOM-kills processes in the cgroup when it reaches the limit"
+        );
+        // Swapping in below the warning rate is not pressure
+        assert_eq!(advise(&info, 1000.0).len(), 2);
+
+        // A healthy host, or one whose data is missing, gets no advice
+        let info: SystemInfo = serde_json::from_value(serde_json::json!({
+            "cgroup_memory": {"usage": 1932735283, "limit": null},
+            "used_swap": 0,
+            "pages_swapped_in_per_sec": 850.0,
+            "root_disk_usage_percent": 62.0
+        }))
+        .unwrap();
+        assert_eq!(advise(&info, 100.0), []);
+        assert_eq!(advise(&SystemInfo::default(), 100.0), []);
+    }
+
+    /// Builds a snapshot from the JSON fields in `value`.
+    fn snapshot(value: serde_json::Value) -> SystemInfo {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_cgroup_near_limit() {
+        let cgroup = |usage: u64, limit: Option<u64>| {
+            snapshot(serde_json::json!({"cgroup_memory": {"usage": usage, "limit": limit}}))
+        };
+        // 85% exactly is not yet near the limit
+        assert_eq!(cgroup_near_limit(&cgroup(850, Some(1000)), 0.0), None);
+        assert_eq!(
+            cgroup_near_limit(&cgroup(851, Some(1000)), 0.0),
+            Some(vec![
+                "85.1%".to_string(),
+                "851 B".to_string(),
+                "1000 B".to_string()
+            ])
+        );
+        // Over the limit still counts, an unlimited or zero limit never does
+        assert!(cgroup_near_limit(&cgroup(1200, Some(1000)), 0.0).is_some());
+        assert_eq!(cgroup_near_limit(&cgroup(1200, None), 0.0), None);
+        assert_eq!(cgroup_near_limit(&cgroup(1200, Some(0)), 0.0), None);
+    }
+
+    #[test]
+    fn test_swapping_in() {
+        let activity = serde_json::json!({
+            "interval_secs": 1.0,
+            "swap_in_per_sec": 50.0,
+            "swap_out_per_sec": 0.0,
+            "major_faults_per_sec": 0.0
+        });
+        // The rate between samples wins over the one from collection
+        let info = snapshot(serde_json::json!({
+            "used_swap": 1048576,
+            "pages_swapped_in_per_sec": 400.0,
+            "memory_activity": activity
+        }));
+        assert_eq!(
+            swapping_in(&info, 100.0),
+            Some(vec!["1.00 MB".to_string(), "400".to_string()])
+        );
+        assert_eq!(swapping_in(&info, 400.0), None);
+
+        let info = snapshot(serde_json::json!({
+            "used_swap": 1048576,
+            "memory_activity": activity
+        }));
+        assert_eq!(swapping_in(&info, 100.0), None);
+        assert!(swapping_in(&info, 10.0).is_some());
+
+        // Pages moving without swap in use is not swapping
+        let info = snapshot(serde_json::json!({
+            "used_swap": 0,
+            "pages_swapped_in_per_sec": 400.0
+        }));
+        assert_eq!(swapping_in(&info, 100.0), None);
+        let info = snapshot(serde_json::json!({"used_swap": 1048576}));
+        assert_eq!(swapping_in(&info, 100.0), None);
+    }
+
+    #[test]
+    fn test_root_disk_full() {
+        let root = |percent: f64| snapshot(serde_json::json!({"root_disk_usage_percent": percent}));
+        assert_eq!(root_disk_full(&root(95.0), 0.0), None);
+        assert_eq!(
+            root_disk_full(&root(99.5), 0.0),
+            Some(vec!["0.5%".to_string()])
+        );
+        assert_eq!(
+            advise(&root(100.0), 100.0),
+            [Advice {
+                rule: "root-disk-full".to_string(),
+                message: "The root filesystem has only 0.0% free; services fail in odd ways \
+                          when it fills up"
+                    .to_string(),
+            }]
+        );
+        assert_eq!(root_disk_full(&SystemInfo::default(), 0.0), None);
+    }
+
+    #[test]
+    fn test_fill() {
+        assert_eq!(
+            fill("{} of {} used", &["3 GB".to_string(), "4 GB".to_string()]),
+            "3 GB of 4 GB used"
+        );
+        assert_eq!(fill("{} and {}", &["one".to_string()]), "one and {}");
+    }
+}
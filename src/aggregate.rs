@@ -0,0 +1,179 @@
+//! Fleet overview built from archived snapshots (`--aggregate <DIR>`).
+//!
+//! Every `*.json` file directly inside the directory is read as one host's
+//! `system_info.json`. Hosts are listed in file name order; files that cannot
+//! be read or parsed are reported on stderr and skipped.
+
+use crate::locale::Locale;
+use crate::table::{Align, Table};
+use crate::{AppError, SystemInfo};
+use std::path::Path;
+
+/// One host's snapshot and the file it came from.
+pub struct HostSnapshot {
+    /// File name without the `.json` extension
+    pub source: String,
+    pub info: SystemInfo,
+}
+
+/// Loads every snapshot in `dir`.
+///
+/// # Errors
+///
+/// Returns `AppError::FileRead` if the directory cannot be listed, or
+/// `AppError::EmptyCollection` if it contains no readable snapshots.
+pub fn load(dir: &Path) -> Result<Vec<HostSnapshot>, AppError> {
+    let mut paths: Vec<_> = std::fs::read_dir(dir)
+        .map_err(AppError::FileRead)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    paths.sort();
+
+    let mut hosts = Vec::new();
+    for path in paths {
+        let parsed = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| serde_json::from_str(&text).map_err(|e| e.to_string()));
+        match parsed {
+            Ok(info) => hosts.push(HostSnapshot {
+                source: path
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+                info,
+            }),
+            Err(e) => eprintln!("Warning: skipping {}: {}", path.display(), e),
+        }
+    }
+
+    if hosts.is_empty() {
+        return Err(AppError::EmptyCollection("snapshots"));
+    }
+    Ok(hosts)
+}
+
+/// Renders the overview table: hostname, CPU cores, memory used%, disk used%.
+///
+/// Disk usage combines all of a host's disks. Snapshots that predate the
+/// `hostname` field are labelled by file name. Percentages that cannot be
+/// computed (no memory or disks reported) are shown as `-`.
+pub fn render(hosts: &[HostSnapshot], width: Option<usize>, locale: Locale) -> Vec<String> {
+    let mut table = Table::new(
+        &["Hostname", "Cores", "Mem%", "Disk%"],
+        &[Align::Left, Align::Right, Align::Right, Align::Right],
+    );
+    for host in hosts {
+        let info = &host.info;
+        let hostname = if info.hostname.is_empty() {
+            host.source.clone()
+        } else {
+            info.hostname.clone()
+        };
+        let disk_total: u64 = info.disks.iter().map(|d| d.total_space).sum();
+        let disk_used: u64 = info
+            .disks
+            .iter()
+            .map(|d| d.total_space.saturating_sub(d.available_space))
+            .sum();
+        table.push(vec![
+            hostname,
+            info.cpu_cores.to_string(),
+            percent(info.used_memory, info.total_memory, locale),
+            percent(disk_used, disk_total, locale),
+        ]);
+    }
+    table.render("", width, 0)
+}
+
+/// Formats `part` as a percentage of `total`, or `-` if `total` is zero.
+fn percent(part: u64, total: u64, locale: Locale) -> String {
+    if total == 0 {
+        "-".to_string()
+    } else {
+        crate::format_percent(part as f64 / total as f64 * 100.0, locale)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{self, disk, MockProvider};
+
+    fn host(source: &str, sys: &MockProvider) -> HostSnapshot {
+        let mut info = mock::snapshot(sys);
+        info.hostname = source.to_string();
+        HostSnapshot {
+            source: source.to_string(),
+            info,
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let web = MockProvider {
+            used_memory: 2 << 30,
+            disks: vec![
+                disk("/", "ext4", 100 << 30, 50 << 30),
+                disk("/data", "ext4", 300 << 30, 250 << 30),
+            ],
+            ..MockProvider::default()
+        };
+        let db = MockProvider {
+            used_memory: 6 << 30,
+            disks: vec![disk("/", "ext4", 100 << 30, 10 << 30)],
+            ..MockProvider::default()
+        };
+        let lines = render(
+            &[host("web", &web), host("db", &db)],
+            None,
+            Locale::default(),
+        );
+        let rows: Vec<Vec<&str>> = lines
+            .iter()
+            .map(|line| line.split_whitespace().collect())
+            .collect();
+        assert_eq!(rows[0], ["Hostname", "Cores", "Mem%", "Disk%"]);
+        // Disk usage is over all of a host's disks: 100 of 400 GiB
+        assert_eq!(rows[rows.len() - 2][2..], ["25.0%", "25.0%"]);
+        assert_eq!(rows[rows.len() - 1][2..], ["75.0%", "90.0%"]);
+    }
+
+    #[test]
+    fn test_render_empty() {
+        // Nothing to divide by: no memory and no disks, as in a snapshot
+        // written by another tool
+        let mut bare = host("bare", &MockProvider::default());
+        bare.info.hostname.clear();
+        bare.info.total_memory = 0;
+        bare.info.used_memory = 0;
+        bare.info.disks.clear();
+        let lines = render(&[bare], None, Locale::default());
+        let last: Vec<&str> = lines.last().unwrap().split_whitespace().collect();
+        assert_eq!(last[0], "bare");
+        assert_eq!(last[2..], ["-", "-"]);
+
+        // No hosts at all still renders the header
+        let lines = render(&[], None, Locale::default());
+        assert!(lines[0].starts_with("Hostname"));
+    }
+
+    #[test]
+    fn test_load() {
+        let dir = std::env::temp_dir().join(format!("aggregate-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        assert!(matches!(load(&dir), Err(AppError::EmptyCollection(_))));
+
+        let info = mock::snapshot(&MockProvider::default());
+        let json = serde_json::to_string(&info).unwrap();
+        std::fs::write(dir.join("b.json"), &json).unwrap();
+        std::fs::write(dir.join("a.json"), &json).unwrap();
+        std::fs::write(dir.join("broken.json"), "{").unwrap();
+        std::fs::write(dir.join("notes.txt"), &json).unwrap();
+        let hosts = load(&dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+        let sources: Vec<String> = hosts.unwrap().into_iter().map(|h| h.source).collect();
+        assert_eq!(sources, ["a", "b"]);
+    }
+}
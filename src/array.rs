@@ -0,0 +1,149 @@
+//! Multi-sample JSON array files (`--array`).
+//!
+//! Instead of overwriting the output file with each sample, every sample is
+//! appended as a new element of one top-level JSON array. The closing bracket
+//! is rewritten after each append, so the file is a complete, parseable
+//! document between samples and stays valid when the process is interrupted:
+//!
+//! ```text
+//! [
+//!   { ...first sample... },
+//!   { ...second sample... }
+//! ]
+//! ```
+
+use std::fs::File;
+use std::io::{self, Seek, SeekFrom, Write};
+
+/// Text that ends the file after every write.
+const CLOSING: &str = "\n]\n";
+
+/// An output file holding a JSON array of samples.
+pub struct ArrayFile {
+    file: File,
+    /// Whether no element has been written yet
+    empty: bool,
+    /// Length of the file after the last write
+    len: u64,
+}
+
+impl ArrayFile {
+    /// Writes an empty array to the newly created or truncated `file`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    pub fn new(mut file: File) -> io::Result<ArrayFile> {
+        const EMPTY: &[u8] = b"[]\n";
+        file.write_all(EMPTY)?;
+        Ok(ArrayFile {
+            file,
+            empty: true,
+            len: EMPTY.len() as u64,
+        })
+    }
+
+    /// Appends one JSON document as the last element of the array.
+    ///
+    /// Multi-line (pretty-printed) documents are indented one level. The
+    /// previous closing bracket is overwritten in place, so the rest of the
+    /// file is never rewritten.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if seeking in or writing to the file fails, or
+    /// `InvalidData` if the file no longer ends where the last write left
+    /// it, e.g. because it was truncated, in which case it is not written.
+    pub fn append(&mut self, json: &str) -> io::Result<()> {
+        let len = self.file.metadata()?.len();
+        if len != self.len {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "the array file changed since the last sample ({} bytes instead of {})",
+                    len, self.len
+                ),
+            ));
+        }
+        // Newlines inside JSON strings are escaped, so every raw newline
+        // separates lines of the document itself.
+        let element = json.trim_end().replace('\n', "\n  ");
+        if self.empty {
+            self.file.seek(SeekFrom::Start(0))?;
+            write!(self.file, "[\n  {}{}", element, CLOSING)?;
+        } else {
+            self.file.seek(SeekFrom::End(-(CLOSING.len() as i64)))?;
+            write!(self.file, ",\n  {}{}", element, CLOSING)?;
+        }
+        self.empty = false;
+        self.file.flush()?;
+        self.len = self.file.stream_position()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn temp_file(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "sysinfo-array-{}-{}.json",
+            name,
+            std::process::id()
+        ))
+    }
+
+    #[test]
+    fn test_append() {
+        let path = temp_file("append");
+        let mut array = ArrayFile::new(File::create(&path).unwrap()).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "[]\n");
+
+        array.append("{\n  \"sample\": 1\n}\n").unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(text, "[\n  {\n    \"sample\": 1\n  }\n]\n");
+
+        array.append(r#"{"sample": 2, "note": "a\nb"}"#).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let samples: Vec<serde_json::Value> = serde_json::from_str(&text).unwrap();
+        assert_eq!(
+            samples,
+            [
+                serde_json::json!({"sample": 1}),
+                serde_json::json!({"sample": 2, "note": "a\nb"}),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_append_changed_file() {
+        let path = temp_file("changed");
+        let mut array = ArrayFile::new(File::create(&path).unwrap()).unwrap();
+        array.append(r#"{"sample": 1}"#).unwrap();
+        let written = std::fs::read_to_string(&path).unwrap();
+
+        // A lost trailing newline
+        let truncate = |len: usize| {
+            let file = std::fs::OpenOptions::new().write(true).open(&path).unwrap();
+            file.set_len(len as u64).unwrap();
+        };
+        truncate(written.len() - 1);
+        let error = array.append(r#"{"sample": 2}"#).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            written[..written.len() - 1]
+        );
+
+        // A tail cut in the middle of an element
+        truncate(10);
+        let error = array.append(r#"{"sample": 2}"#).unwrap_err();
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(text, written[..10]);
+    }
+}
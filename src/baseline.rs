@@ -0,0 +1,155 @@
+//! Network traffic since a saved baseline (`--network-baseline <FILE>`).
+//!
+//! The baseline is a snapshot in the `system_info.json` format. If the file
+//! does not exist yet, the first sample is saved to it, so passing the same
+//! file at the start and at the end of a job measures the job's traffic.
+//! Every interface that is also in the baseline gets a `since_baseline`
+//! section with the counters' growth. A counter below its baseline value
+//! means the counters were reset, e.g. by a reboot, so the raw values are
+//! reported instead.
+
+#[cfg(feature = "history")]
+use crate::{AppError, NetworkInfo, SystemInfo};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "history")]
+use std::collections::HashMap;
+#[cfg(feature = "history")]
+use std::io;
+#[cfg(feature = "history")]
+use std::path::{Path, PathBuf};
+
+/// Traffic on one interface since the baseline.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct NetworkDelta {
+    pub bytes_received: u64,
+    pub bytes_transmitted: u64,
+    pub packets_received: u64,
+    pub packets_transmitted: u64,
+    /// Whether the counters were reset since the baseline, in which case the
+    /// values above are the raw counters
+    pub counter_reset: bool,
+}
+
+/// The counters compared, in the order of `NetworkDelta`'s fields.
+#[cfg(feature = "history")]
+type Counters = [u64; 4];
+
+/// Interface counters loaded from, or about to be saved to, a baseline file.
+#[cfg(feature = "history")]
+pub struct Baseline {
+    path: PathBuf,
+    /// Counters by interface name, or `None` until the first sample is saved
+    counters: Option<HashMap<String, Counters>>,
+}
+
+#[cfg(feature = "history")]
+impl Baseline {
+    /// Loads the baseline in `path`, if the file exists.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::FileRead` if the file exists but cannot be read or
+    /// is not a snapshot.
+    pub fn load(path: &Path) -> Result<Baseline, AppError> {
+        let counters = match std::fs::read_to_string(path) {
+            Ok(text) => {
+                let info: SystemInfo = serde_json::from_str(&text).map_err(|e| {
+                    AppError::FileRead(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("{} is not a snapshot: {}", path.display(), e),
+                    ))
+                })?;
+                Some(by_interface(&info))
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(AppError::FileRead(e)),
+        };
+        Ok(Baseline {
+            path: path.to_path_buf(),
+            counters,
+        })
+    }
+
+    /// Fills in `since_baseline` for every interface of `info` found in the
+    /// baseline, first saving `info` as the baseline if there is none yet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the baseline file cannot be written.
+    pub fn apply(&mut self, info: &mut SystemInfo) -> Result<(), AppError> {
+        if self.counters.is_none() {
+            let json = serde_json::to_string_pretty(info).map_err(AppError::JsonSerialization)?;
+            crate::write_atomically(&self.path, json.as_bytes(), None)
+                .map_err(AppError::FileWrite)?;
+        }
+        let counters = self.counters.get_or_insert_with(|| by_interface(info));
+        for network in &mut info.networks {
+            network.since_baseline = counters
+                .get(&network.name)
+                .map(|baseline| delta(baseline, &counters_of(network)));
+        }
+        Ok(())
+    }
+}
+
+/// Returns the counters of every interface of `info`, by name.
+#[cfg(feature = "history")]
+fn by_interface(info: &SystemInfo) -> HashMap<String, Counters> {
+    info.networks
+        .iter()
+        .map(|network| (network.name.clone(), counters_of(network)))
+        .collect()
+}
+
+/// Returns the counters of `network` compared against the baseline.
+#[cfg(feature = "history")]
+fn counters_of(network: &NetworkInfo) -> Counters {
+    [
+        network.bytes_received,
+        network.bytes_transmitted,
+        network.packets_received,
+        network.packets_transmitted,
+    ]
+}
+
+/// Computes the growth from `baseline` to `current`.
+#[cfg(feature = "history")]
+fn delta(baseline: &Counters, current: &Counters) -> NetworkDelta {
+    let reset = current.iter().zip(baseline).any(|(now, then)| now < then);
+    let value = |index: usize| {
+        if reset {
+            current[index]
+        } else {
+            current[index] - baseline[index]
+        }
+    };
+    NetworkDelta {
+        bytes_received: value(0),
+        bytes_transmitted: value(1),
+        packets_received: value(2),
+        packets_transmitted: value(3),
+        counter_reset: reset,
+    }
+}
+
+#[cfg(all(test, feature = "history"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delta() {
+        let growth = delta(&[1000, 500, 10, 5], &[4000, 600, 40, 6]);
+        assert_eq!(
+            (growth.bytes_received, growth.packets_transmitted),
+            (3000, 1)
+        );
+        assert!(!growth.counter_reset);
+        // any counter going backwards means a reset: report the raw counters
+        let growth = delta(&[1000, 500, 10, 5], &[200, 900, 2, 9]);
+        assert_eq!(
+            (growth.bytes_received, growth.bytes_transmitted),
+            (200, 900)
+        );
+        assert!(growth.counter_reset);
+    }
+}
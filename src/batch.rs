@@ -0,0 +1,275 @@
+//! Batching of pushed items (`--batch-size`, `--batch-interval`).
+//!
+//! Items are queued in memory and delivered together once the queue holds
+//! `size` items or the oldest queued item is `interval` old. The queue is
+//! bounded by `max_queue`: when delivery keeps failing, the oldest items are
+//! dropped and counted, and the count is reported with the next batch that
+//! gets through. Times are passed in by the caller, so the batching decisions
+//! do not depend on the real clock.
+
+use std::collections::VecDeque;
+use std::io;
+use std::time::{Duration, Instant};
+
+/// Queue length used when `--max-queue` is not given.
+pub const DEFAULT_MAX_QUEUE: usize = 1000;
+
+/// When queued items are delivered.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BatchConfig {
+    /// Deliver once this many items are queued
+    pub size: Option<usize>,
+    /// Deliver once the oldest queued item is this old
+    pub interval: Option<Duration>,
+    /// Most items kept while waiting; older ones are dropped beyond this
+    pub max_queue: usize,
+}
+
+/// Items delivered together.
+#[derive(Debug, PartialEq)]
+pub struct Batch<T> {
+    /// The items, oldest first
+    pub items: Vec<T>,
+    /// Items dropped from the queue since the previous delivered batch
+    pub dropped: u64,
+}
+
+/// A bounded queue that groups items into batches.
+pub struct Batcher<T> {
+    config: BatchConfig,
+    queue: VecDeque<(T, Instant)>,
+    dropped: u64,
+}
+
+impl<T> Batcher<T> {
+    pub fn new(config: BatchConfig) -> Batcher<T> {
+        Batcher {
+            config,
+            queue: VecDeque::new(),
+            dropped: 0,
+        }
+    }
+
+    /// Queues `item`, received at `now`, dropping the oldest item if the
+    /// queue is full.
+    pub fn push(&mut self, item: T, now: Instant) {
+        self.queue.push_back((item, now));
+        self.trim();
+    }
+
+    /// Returns whether a batch should be delivered at `now`.
+    pub fn due(&self, now: Instant) -> bool {
+        let Some((_, oldest)) = self.queue.front() else {
+            return false;
+        };
+        self.config
+            .size
+            .is_some_and(|size| self.queue.len() >= size)
+            || self
+                .config
+                .interval
+                .is_some_and(|interval| now.saturating_duration_since(*oldest) >= interval)
+    }
+
+    /// Delivers the queued items as one batch with `deliver`, if any are queued.
+    ///
+    /// On failure the items are put back at the front of the queue, subject
+    /// to `max_queue`, and the drop count carries over to the next attempt.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from `deliver`.
+    pub fn flush<F>(&mut self, mut deliver: F) -> io::Result<()>
+    where
+        F: FnMut(&Batch<T>) -> io::Result<()>,
+    {
+        if self.queue.is_empty() {
+            return Ok(());
+        }
+        let (items, received): (Vec<T>, Vec<Instant>) = self.queue.drain(..).unzip();
+        let batch = Batch {
+            items,
+            dropped: self.dropped,
+        };
+        match deliver(&batch) {
+            Ok(()) => {
+                self.dropped = 0;
+                Ok(())
+            }
+            Err(e) => {
+                for entry in batch.items.into_iter().zip(received).rev() {
+                    self.queue.push_front(entry);
+                }
+                self.trim();
+                Err(e)
+            }
+        }
+    }
+
+    /// Drops the oldest items until the queue fits `max_queue`.
+    fn trim(&mut self) {
+        while self.queue.len() > self.config.max_queue.max(1) {
+            self.queue.pop_front();
+            self.dropped += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A clock that only moves when told to.
+    struct FakeClock {
+        start: Instant,
+        elapsed: Duration,
+    }
+
+    impl FakeClock {
+        fn new() -> FakeClock {
+            FakeClock {
+                start: Instant::now(),
+                elapsed: Duration::ZERO,
+            }
+        }
+
+        fn now(&self) -> Instant {
+            self.start + self.elapsed
+        }
+
+        fn advance(&mut self, secs: u64) {
+            self.elapsed += Duration::from_secs(secs);
+        }
+    }
+
+    /// A sink that records delivered batches and fails the next `failures`
+    /// deliveries.
+    #[derive(Default)]
+    struct MockSink {
+        delivered: Vec<(Vec<u32>, u64)>,
+        attempts: usize,
+        failures: usize,
+    }
+
+    impl MockSink {
+        fn deliver(&mut self, batch: &Batch<u32>) -> io::Result<()> {
+            self.attempts += 1;
+            if self.failures > 0 {
+                self.failures -= 1;
+                return Err(io::Error::other("receiver down"));
+            }
+            self.delivered.push((batch.items.clone(), batch.dropped));
+            Ok(())
+        }
+    }
+
+    fn batcher(size: Option<usize>, interval: Option<u64>, max_queue: usize) -> Batcher<u32> {
+        Batcher::new(BatchConfig {
+            size,
+            interval: interval.map(Duration::from_secs),
+            max_queue,
+        })
+    }
+
+    #[test]
+    fn test_batcher_due() {
+        let mut clock = FakeClock::new();
+        let mut batcher = batcher(Some(3), Some(60), 10);
+        assert!(!batcher.due(clock.now()));
+
+        batcher.push(1, clock.now());
+        clock.advance(10);
+        batcher.push(2, clock.now());
+        assert!(!batcher.due(clock.now()));
+        clock.advance(49);
+        assert!(!batcher.due(clock.now()));
+        // The oldest item reached the interval
+        clock.advance(1);
+        assert!(batcher.due(clock.now()));
+
+        // The size is reached before the interval
+        let mut batcher = self::batcher(Some(3), Some(60), 10);
+        for n in 0..3 {
+            assert!(!batcher.due(clock.now()));
+            batcher.push(n, clock.now());
+        }
+        assert!(batcher.due(clock.now()));
+
+        // With only one trigger the other never fires
+        let mut by_size = self::batcher(Some(2), None, 10);
+        by_size.push(1, clock.now());
+        clock.advance(3600);
+        assert!(!by_size.due(clock.now()));
+        let mut by_age = self::batcher(None, Some(30), 10);
+        for n in 0..100 {
+            by_age.push(n, clock.now());
+        }
+        assert!(!by_age.due(clock.now()));
+        clock.advance(30);
+        assert!(by_age.due(clock.now()));
+    }
+
+    #[test]
+    fn test_batcher_flush() {
+        let mut clock = FakeClock::new();
+        let mut sink = MockSink::default();
+        let mut batcher = batcher(Some(2), Some(60), 10);
+
+        // Nothing queued, nothing sent
+        batcher.flush(|batch| sink.deliver(batch)).unwrap();
+        assert_eq!(sink.attempts, 0);
+
+        for n in 1..=2 {
+            batcher.push(n, clock.now());
+        }
+        assert!(batcher.due(clock.now()));
+        batcher.flush(|batch| sink.deliver(batch)).unwrap();
+        assert!(!batcher.due(clock.now()));
+
+        // A flush at shutdown sends a partial batch that is not due yet
+        clock.advance(5);
+        batcher.push(3, clock.now());
+        assert!(!batcher.due(clock.now()));
+        batcher.flush(|batch| sink.deliver(batch)).unwrap();
+        assert_eq!(sink.delivered, [(vec![1, 2], 0), (vec![3], 0)]);
+        assert!(!batcher.due(clock.now() + Duration::from_secs(3600)));
+    }
+
+    #[test]
+    fn test_batcher_drops() {
+        let mut clock = FakeClock::new();
+        let mut sink = MockSink {
+            failures: 2,
+            ..MockSink::default()
+        };
+        let mut batcher = batcher(Some(2), None, 3);
+
+        // Failed deliveries keep the items queued, oldest first
+        for n in 1..=2 {
+            batcher.push(n, clock.now());
+        }
+        assert!(batcher.flush(|batch| sink.deliver(batch)).is_err());
+        clock.advance(1);
+        batcher.push(3, clock.now());
+        assert!(batcher.flush(|batch| sink.deliver(batch)).is_err());
+
+        // Beyond max_queue the oldest are dropped and counted
+        batcher.push(4, clock.now());
+        batcher.push(5, clock.now());
+        batcher.flush(|batch| sink.deliver(batch)).unwrap();
+        assert_eq!(sink.attempts, 3);
+        assert_eq!(sink.delivered, [(vec![3, 4, 5], 2)]);
+
+        // The count is reported once
+        batcher.push(6, clock.now());
+        batcher.flush(|batch| sink.deliver(batch)).unwrap();
+        assert_eq!(sink.delivered[1], (vec![6], 0));
+
+        // A queue of zero still holds the newest item
+        let mut tiny = self::batcher(None, None, 0);
+        tiny.push(7, clock.now());
+        tiny.push(8, clock.now());
+        tiny.flush(|batch| sink.deliver(batch)).unwrap();
+        assert_eq!(sink.delivered[2], (vec![8], 1));
+    }
+}
@@ -0,0 +1,271 @@
+//! Support bundle export (`--bundle <PATH>`).
+//!
+//! Writes a ZIP archive for attaching to vendor support tickets, containing
+//! `system_info.json`, the human-readable report as `report.txt`, and on
+//! Linux copies of the raw kernel sources behind the numbers. Each raw file
+//! is streamed into the archive with a size cap. A `manifest.json` entry,
+//! written last, lists every intended file with its included/skipped status
+//! and the reason any was skipped.
+
+use crate::zip::ZipWriter;
+use serde::Serialize;
+use std::io::{self, Write};
+
+/// Maximum number of bytes copied from any single raw source file.
+#[cfg(target_os = "linux")]
+const MAX_SOURCE_BYTES: u64 = 4 * 1024 * 1024;
+
+/// Number of trailing kernel log lines included as `dmesg.txt`.
+#[cfg(target_os = "linux")]
+const DMESG_TAIL_LINES: usize = 500;
+
+/// Raw source files copied into the bundle on Linux, with their archive names.
+#[cfg(target_os = "linux")]
+const RAW_SOURCES: &[(&str, &str)] = &[
+    ("/proc/meminfo", "proc/meminfo"),
+    ("/proc/cpuinfo", "proc/cpuinfo"),
+    ("/proc/mounts", "proc/mounts"),
+    ("/proc/loadavg", "proc/loadavg"),
+];
+
+/// Whether an intended file made it into the bundle.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum Status {
+    Included,
+    Skipped,
+}
+
+/// One file the bundle intended to contain.
+#[derive(Serialize, Debug)]
+struct ManifestEntry {
+    /// Name of the entry inside the archive
+    name: String,
+    /// Where the content came from (a path, command, or "generated")
+    source: String,
+    status: Status,
+    /// Why the file was skipped, if it was
+    reason: Option<String>,
+    /// Number of bytes stored in the archive
+    bytes: u64,
+    /// Whether the content was cut off at the size cap
+    truncated: bool,
+}
+
+/// Writes a support bundle to `out`.
+///
+/// `json` and `report` are the already-rendered snapshot and console report.
+/// Raw sources that cannot be read are recorded as skipped in the manifest
+/// rather than failing the bundle. With `redacted` set, raw sources (which
+/// may contain host names and addresses) are skipped as well.
+///
+/// # Errors
+///
+/// Returns an error only if writing the archive itself fails.
+#[cfg_attr(not(target_os = "linux"), allow(unused_variables))]
+pub fn write_bundle<W: Write>(out: W, json: &str, report: &str, redacted: bool) -> io::Result<W> {
+    let mut zip = ZipWriter::new(out);
+    let mut manifest = Vec::new();
+
+    for (name, content) in [("system_info.json", json), ("report.txt", report)] {
+        let bytes = zip.add(name, content.as_bytes())?;
+        manifest.push(ManifestEntry {
+            name: name.to_string(),
+            source: "generated".to_string(),
+            status: Status::Included,
+            reason: None,
+            bytes,
+            truncated: false,
+        });
+    }
+
+    #[cfg(target_os = "linux")]
+    if redacted {
+        const REASON: &str = "raw sources are omitted when --redact is set";
+        for (path, name) in RAW_SOURCES {
+            manifest.push(skipped(name, path, REASON.to_string()));
+        }
+        manifest.push(skipped("dmesg.txt", "dmesg", REASON.to_string()));
+    } else {
+        for (path, name) in RAW_SOURCES {
+            let entry = match std::fs::File::open(path) {
+                Ok(file) => add_capped(&mut zip, name, path, file)?,
+                Err(e) => skipped(name, path, e.to_string()),
+            };
+            manifest.push(entry);
+        }
+        manifest.push(add_dmesg_tail(&mut zip)?);
+    }
+
+    let manifest_json = serde_json::to_vec_pretty(&manifest)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    zip.add("manifest.json", manifest_json.as_slice())?;
+    zip.finish()
+}
+
+/// Streams up to `MAX_SOURCE_BYTES` of `data` into the archive as `name`.
+#[cfg(target_os = "linux")]
+fn add_capped<W: Write, R: io::Read>(
+    zip: &mut ZipWriter<W>,
+    name: &str,
+    source: &str,
+    data: R,
+) -> io::Result<ManifestEntry> {
+    let mut limited = data.take(MAX_SOURCE_BYTES);
+    let bytes = zip.add(name, &mut limited)?;
+    let truncated = limited.into_inner().read(&mut [0u8; 1]).unwrap_or(0) > 0;
+
+    Ok(ManifestEntry {
+        name: name.to_string(),
+        source: source.to_string(),
+        status: Status::Included,
+        reason: None,
+        bytes,
+        truncated,
+    })
+}
+
+/// Builds the manifest entry for a file that could not be included.
+#[cfg(target_os = "linux")]
+fn skipped(name: &str, source: &str, reason: String) -> ManifestEntry {
+    ManifestEntry {
+        name: name.to_string(),
+        source: source.to_string(),
+        status: Status::Skipped,
+        reason: Some(reason),
+        bytes: 0,
+        truncated: false,
+    }
+}
+
+/// Adds the last `DMESG_TAIL_LINES` lines of the kernel log as `dmesg.txt`.
+///
+/// Reading the kernel log is commonly restricted (`kernel.dmesg_restrict`),
+/// in which case the entry is recorded as skipped.
+#[cfg(target_os = "linux")]
+fn add_dmesg_tail<W: Write>(zip: &mut ZipWriter<W>) -> io::Result<ManifestEntry> {
+    const NAME: &str = "dmesg.txt";
+    const SOURCE: &str = "dmesg";
+
+    let output = match std::process::Command::new("dmesg").output() {
+        Ok(output) => output,
+        Err(e) => return Ok(skipped(NAME, SOURCE, e.to_string())),
+    };
+    if !output.status.success() {
+        let reason = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Ok(skipped(NAME, SOURCE, reason));
+    }
+
+    let log = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = log.lines().collect();
+    let tail = lines[lines.len().saturating_sub(DMESG_TAIL_LINES)..].join("\n");
+    add_capped(zip, NAME, SOURCE, io::Cursor::new(tail.into_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zip::read_archive;
+    use serde_json::Value;
+
+    /// Writes a bundle and returns its entries and parsed manifest.
+    fn bundle(redacted: bool) -> (Vec<(String, Vec<u8>)>, Vec<Value>) {
+        let archive =
+            write_bundle(Vec::new(), "{\"hostname\": \"h\"}", "report\n", redacted).unwrap();
+        let entries = read_archive(&archive);
+        let (name, manifest) = entries.last().unwrap();
+        assert_eq!(name, "manifest.json");
+        let manifest: Vec<Value> = serde_json::from_slice(manifest).unwrap();
+        (entries, manifest)
+    }
+
+    /// Returns the number of manifest entries that were skipped.
+    fn skipped_count(manifest: &[Value]) -> usize {
+        manifest.iter().filter(|e| e["status"] == "skipped").count()
+    }
+
+    #[test]
+    fn test_write_bundle() {
+        let (entries, manifest) = bundle(false);
+        assert_eq!(
+            entries[0],
+            (
+                "system_info.json".to_string(),
+                b"{\"hostname\": \"h\"}".to_vec()
+            )
+        );
+        assert_eq!(entries[1], ("report.txt".to_string(), b"report\n".to_vec()));
+
+        // Every included file is in the archive with the recorded size, and
+        // nothing else is
+        for entry in &manifest {
+            let name = entry["name"].as_str().unwrap();
+            let stored = entries.iter().find(|(stored, _)| stored == name);
+            match entry["status"].as_str().unwrap() {
+                "included" => {
+                    let (_, data) = stored.unwrap_or_else(|| panic!("{} missing", name));
+                    assert_eq!(entry["bytes"], data.len() as u64);
+                    assert!(entry["reason"].is_null());
+                }
+                "skipped" => {
+                    assert!(stored.is_none(), "{} skipped but stored", name);
+                    assert!(entry["reason"].is_string());
+                }
+                status => panic!("unexpected status {}", status),
+            }
+        }
+        assert_eq!(manifest.len() + 1, entries.len() + skipped_count(&manifest));
+
+        #[cfg(target_os = "linux")]
+        {
+            let names: Vec<&str> = manifest
+                .iter()
+                .map(|e| e["name"].as_str().unwrap())
+                .collect();
+            assert_eq!(
+                names,
+                [
+                    "system_info.json",
+                    "report.txt",
+                    "proc/meminfo",
+                    "proc/cpuinfo",
+                    "proc/mounts",
+                    "proc/loadavg",
+                    "dmesg.txt"
+                ]
+            );
+            assert_eq!(manifest[2]["source"], "/proc/meminfo");
+            assert_eq!(manifest[2]["status"], "included");
+        }
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_write_bundle_redacted() {
+        let (entries, manifest) = bundle(true);
+        let names: Vec<&str> = entries.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["system_info.json", "report.txt", "manifest.json"]);
+        assert_eq!(skipped_count(&manifest), 5);
+        assert_eq!(
+            manifest[2]["reason"],
+            "raw sources are omitted when --redact is set"
+        );
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_add_capped() {
+        let mut zip = ZipWriter::new(Vec::new());
+        let big = io::Read::take(io::repeat(b'x'), MAX_SOURCE_BYTES + 10);
+        let entry = add_capped(&mut zip, "big.txt", "/dev/big", big).unwrap();
+        assert_eq!(entry.bytes, MAX_SOURCE_BYTES);
+        assert!(entry.truncated);
+        let entry = add_capped(&mut zip, "small.txt", "/dev/small", &b"abc"[..]).unwrap();
+        assert_eq!(entry.bytes, 3);
+        assert!(!entry.truncated);
+
+        let entries = read_archive(&zip.finish().unwrap());
+        assert_eq!(entries[0].1.len() as u64, MAX_SOURCE_BYTES);
+        assert_eq!(entries[1].1, b"abc");
+    }
+}
@@ -0,0 +1,219 @@
+//! CPU cache sizes.
+//!
+//! sysinfo does not report caches. On Linux the sizes come from
+//! `/sys/devices/system/cpu/cpu0/cache`, on macOS from the `hw.*cachesize`
+//! sysctls, and on Windows from `Win32_Processor` via PowerShell, which only
+//! reports L2 and L3. Sizes are those seen by the first CPU: L1 and usually
+//! L2 are per core, L3 is usually shared by a whole package. The sizes are
+//! only read with the `host-details` feature.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "host-details")]
+use std::path::Path;
+
+/// Cache sizes in bytes, by level.
+///
+/// A level is `None` when the platform does not report it or the CPU has no
+/// such cache.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct CacheInfo {
+    /// Level 1 data cache
+    pub l1d: Option<u64>,
+    /// Level 1 instruction cache
+    pub l1i: Option<u64>,
+    /// Level 2 cache
+    pub l2: Option<u64>,
+    /// Level 3 cache
+    pub l3: Option<u64>,
+}
+
+impl CacheInfo {
+    /// Returns the reported levels as `(label, bytes)` pairs, smallest first.
+    pub fn levels(&self) -> Vec<(&'static str, u64)> {
+        [
+            ("L1d", self.l1d),
+            ("L1i", self.l1i),
+            ("L2", self.l2),
+            ("L3", self.l3),
+        ]
+        .into_iter()
+        .filter_map(|(label, size)| Some((label, size?)))
+        .collect()
+    }
+}
+
+/// Reads the CPU cache sizes.
+///
+/// Returns `None` if the cache directory cannot be read or lists no caches.
+#[cfg(all(feature = "host-details", target_os = "linux"))]
+pub fn cache_info() -> Option<CacheInfo> {
+    read_cache_info(Path::new("/sys/devices/system/cpu/cpu0/cache"))
+}
+
+/// Reads the cache sizes from the sysfs cache directory of a CPU at `dir`.
+///
+/// Caches whose level, type or size cannot be read or parsed are skipped.
+#[cfg(feature = "host-details")]
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn read_cache_info(dir: &Path) -> Option<CacheInfo> {
+    let entries = std::fs::read_dir(dir).ok()?;
+    let mut info = CacheInfo::default();
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let read = |name: &str| std::fs::read_to_string(path.join(name)).ok();
+        let (Some(level), Some(kind), Some(size)) = (read("level"), read("type"), read("size"))
+        else {
+            continue;
+        };
+        let Some(size) = crate::parse_bytes(&size) else {
+            continue;
+        };
+        let slot = match (level.trim(), kind.trim()) {
+            ("1", "Data") => &mut info.l1d,
+            ("1", "Instruction") => &mut info.l1i,
+            ("2", _) => &mut info.l2,
+            ("3", _) => &mut info.l3,
+            _ => continue,
+        };
+        *slot = Some(size);
+    }
+
+    (info != CacheInfo::default()).then_some(info)
+}
+
+/// Reads the CPU cache sizes.
+///
+/// Returns `None` if the sysctls cannot be read.
+#[cfg(all(feature = "host-details", target_os = "macos"))]
+pub fn cache_info() -> Option<CacheInfo> {
+    let output = std::process::Command::new("sysctl")
+        .args([
+            "hw.l1dcachesize",
+            "hw.l1icachesize",
+            "hw.l2cachesize",
+            "hw.l3cachesize",
+        ])
+        .output()
+        .ok()?;
+    let mut info = CacheInfo::default();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let size = value.trim().parse().ok().filter(|&size| size > 0);
+        match key.trim() {
+            "hw.l1dcachesize" => info.l1d = size,
+            "hw.l1icachesize" => info.l1i = size,
+            "hw.l2cachesize" => info.l2 = size,
+            "hw.l3cachesize" => info.l3 = size,
+            _ => {}
+        }
+    }
+    (info != CacheInfo::default()).then_some(info)
+}
+
+/// Reads the CPU cache sizes.
+///
+/// Windows reports L2 and L3 only, in KB. Returns `None` if the query fails.
+#[cfg(all(feature = "host-details", windows))]
+pub fn cache_info() -> Option<CacheInfo> {
+    let output = std::process::Command::new("powershell")
+        .args([
+            "-NoProfile",
+            "-NonInteractive",
+            "-Command",
+            "$p = Get-CimInstance Win32_Processor | Select-Object -First 1; \
+             \"$($p.L2CacheSize) $($p.L3CacheSize)\"",
+        ])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let text = String::from_utf8_lossy(&output.stdout);
+    let mut sizes = text.split_whitespace().map(|kb| {
+        kb.parse::<u64>()
+            .ok()
+            .filter(|&kb| kb > 0)
+            .map(|kb| kb * 1024)
+    });
+    let info = CacheInfo {
+        l2: sizes.next().flatten(),
+        l3: sizes.next().flatten(),
+        ..CacheInfo::default()
+    };
+    (info != CacheInfo::default()).then_some(info)
+}
+
+/// Reads the CPU cache sizes.
+///
+/// Not available on this platform, so this always returns `None`.
+#[cfg(all(
+    feature = "host-details",
+    not(any(target_os = "linux", target_os = "macos", windows))
+))]
+pub fn cache_info() -> Option<CacheInfo> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_levels() {
+        let info = CacheInfo {
+            l1d: Some(32 << 10),
+            l2: Some(1 << 20),
+            ..CacheInfo::default()
+        };
+        assert_eq!(info.levels(), [("L1d", 32 << 10), ("L2", 1 << 20)]);
+        assert!(CacheInfo::default().levels().is_empty());
+    }
+
+    #[cfg(feature = "host-details")]
+    #[test]
+    fn test_read_cache_info() {
+        let dir = std::env::temp_dir().join(format!("cache-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let cache = |index: &str, level: &str, kind: &str, size: &str| {
+            let path = dir.join(index);
+            std::fs::create_dir_all(&path).unwrap();
+            std::fs::write(path.join("level"), format!("{}\n", level)).unwrap();
+            std::fs::write(path.join("type"), format!("{}\n", kind)).unwrap();
+            std::fs::write(path.join("size"), format!("{}\n", size)).unwrap();
+        };
+        assert_eq!(read_cache_info(&dir), None);
+
+        cache("index0", "1", "Data", "48K");
+        cache("index1", "1", "Instruction", "32K");
+        cache("index2", "2", "Unified", "2048K");
+        cache("index3", "3", "Unified", "36864K");
+        assert_eq!(
+            read_cache_info(&dir),
+            Some(CacheInfo {
+                l1d: Some(48 << 10),
+                l1i: Some(32 << 10),
+                l2: Some(2 << 20),
+                l3: Some(36 << 20),
+            })
+        );
+
+        // An unparseable size or an unknown level only loses that cache
+        cache("index2", "2", "Unified", "lots");
+        cache("index4", "4", "Unified", "128M");
+        std::fs::remove_file(dir.join("index3/type")).unwrap();
+        let info = read_cache_info(&dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            info,
+            Some(CacheInfo {
+                l1d: Some(48 << 10),
+                l1i: Some(32 << 10),
+                l2: None,
+                l3: None,
+            })
+        );
+    }
+}
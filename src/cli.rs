@@ -0,0 +1,1350 @@
+//! Command-line argument parsing.
+//!
+//! Arguments are parsed by hand into an `Options` value so the program
+//! stays free of additional dependencies.
+
+use crate::batch::{self, BatchConfig};
+use crate::events::Thresholds;
+use crate::keys::KeyCase;
+use crate::largest;
+use crate::plugins;
+use crate::redact::Field;
+use crate::timezone::TimeZone;
+use crate::{AppError, BYTE_UNITS};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Sampling interval used by rate measurements when `--sample-interval` is not given.
+pub const DEFAULT_SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Swap rate (pages/sec) above which the console highlights memory activity
+/// when `--swap-warn-rate` is not given.
+pub const DEFAULT_SWAP_WARN_RATE: f64 = 100.0;
+
+/// Projected days until full below which the console highlights a disk
+/// when `--warn-days-until-full` is not given.
+pub const DEFAULT_DAYS_UNTIL_FULL_WARNING: f64 = 30.0;
+
+/// Clock offset (milliseconds) from the `--check-clock` server above which the
+/// console warns about clock skew, when `--max-clock-offset` is not given.
+pub const DEFAULT_MAX_CLOCK_OFFSET_MS: u64 = 1000;
+
+/// Disk and memory usage (percent) at which `--events` reports high usage
+/// when `--disk-threshold` or `--memory-threshold` is not given.
+pub const DEFAULT_USAGE_THRESHOLD: f64 = 90.0;
+
+/// Percentage points a value must drop below its threshold before `--events`
+/// reports it as normal again, when `--hysteresis` is not given.
+pub const DEFAULT_HYSTERESIS: f64 = 5.0;
+
+/// Mount point highlighted as the root disk when `--root-disk` is not given.
+#[cfg(windows)]
+pub const DEFAULT_ROOT_DISK: &str = "C:\\";
+
+/// Mount point highlighted as the root disk when `--root-disk` is not given.
+#[cfg(not(windows))]
+pub const DEFAULT_ROOT_DISK: &str = "/";
+
+/// The action selected by the first positional argument.
+#[derive(Debug, Default, PartialEq)]
+pub enum Command {
+    /// Collect, display, and save system information (the default)
+    #[default]
+    Report,
+    /// Check for and install a newer release
+    SelfUpdate,
+    /// Compare detected hardware against an inventory spec
+    Verify,
+    /// Print the snapshot published to a `--shm` file
+    ReadShm,
+    /// Print the value of a single metric
+    Quick,
+    /// List processes, or print them as folded stacks
+    Processes,
+    /// Install, control or run the Windows service
+    Service(crate::service::Action),
+}
+
+/// Format of the report printed to stdout.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    /// Human-readable report (the default)
+    #[default]
+    Text,
+    /// One `key=value` line per value, for shell scripts
+    Flat,
+    /// InfluxDB line protocol
+    Influx,
+    /// Markdown tables
+    Markdown,
+    /// The snapshot as JSON, as in `system_info.json`
+    Json,
+}
+
+impl OutputFormat {
+    /// Parses a `--format` value.
+    fn parse(text: &str) -> Result<OutputFormat, AppError> {
+        match text {
+            "text" => Ok(OutputFormat::Text),
+            "flat" => Ok(OutputFormat::Flat),
+            "influx" => Ok(OutputFormat::Influx),
+            "markdown" => Ok(OutputFormat::Markdown),
+            "json" => Ok(OutputFormat::Json),
+            _ => Err(AppError::InvalidArgument(format!(
+                "unknown format '{}' (expected text, flat, influx, markdown or json)",
+                text
+            ))),
+        }
+    }
+}
+
+/// Format of an additional output file (`--output-json` and friends).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FileFormat {
+    /// Pretty-printed JSON, as in `system_info.json`
+    Json,
+    /// The human-readable report without colors
+    Text,
+    /// `key=value` lines
+    Flat,
+    /// InfluxDB line protocol
+    Influx,
+    /// Markdown tables
+    Markdown,
+}
+
+/// Runtime options selected on the command line.
+#[derive(Debug, Default)]
+pub struct Options {
+    /// The subcommand to run
+    pub command: Command,
+    /// Suppress all console output except errors
+    pub quiet: bool,
+    /// Format of the report printed to stdout
+    pub format: OutputFormat,
+    /// Prepended to every flat key and influx measurement name
+    pub metric_prefix: Option<String>,
+    /// Longest delay before the first collection, see `splay`
+    pub splay: Option<Duration>,
+    /// Pick the `--splay` delay at random instead of from the hostname
+    pub splay_random: bool,
+    /// Copy the text report to the clipboard
+    pub copy: bool,
+    /// Copy the JSON snapshot to the clipboard
+    pub copy_json: bool,
+    /// `s3://bucket/key` URL each sample is uploaded to
+    pub s3_url: Option<String>,
+    /// S3-compatible endpoint used instead of AWS, e.g. a MinIO server
+    pub s3_endpoint: Option<String>,
+    /// Proxy for HTTP requests, overriding the proxy environment variables
+    pub proxy: Option<String>,
+    /// PEM file of CA certificates trusted for HTTPS and `mqtts://`
+    pub ca_bundle: Option<PathBuf>,
+    /// Skip TLS certificate verification
+    pub insecure_skip_tls_verify: bool,
+    /// `mqtt://` or `mqtts://` broker each sample is published to
+    pub mqtt_url: Option<String>,
+    /// MQTT topic template; `{hostname}` is expanded
+    pub mqtt_topic: Option<String>,
+    /// MQTT quality of service level (0, 1 or 2)
+    pub mqtt_qos: u8,
+    /// Publish MQTT messages as the topic's retained message
+    pub mqtt_retain: bool,
+    /// CA certificates to verify an `mqtts://` broker with
+    pub mqtt_ca: Option<PathBuf>,
+    /// Client certificate for `mqtts://`
+    pub mqtt_cert: Option<PathBuf>,
+    /// Private key of `--mqtt-cert`
+    pub mqtt_key: Option<PathBuf>,
+    /// File the process ID is written to while running
+    pub pid_file: Option<PathBuf>,
+    /// Additional files to write each sample to, with their formats
+    pub outputs: Vec<(FileFormat, PathBuf)>,
+    /// Key naming convention for `system_info.json`
+    pub json_keys: KeyCase,
+    /// Append each sample to a JSON array instead of overwriting the file
+    pub array: bool,
+    /// Print only the value of this flat-format key
+    pub get: Option<String>,
+    /// Index into `BYTE_UNITS` of the smallest unit used for console sizes
+    pub min_unit: usize,
+    /// Thousands separator for console counts
+    pub thousands_separator: Option<&'static str>,
+    /// Number formatting conventions for the console
+    pub locale: Option<crate::locale::Locale>,
+    /// Maximum console line width for aligned tables
+    pub width: Option<usize>,
+    /// Identifying values to replace with "REDACTED" before output
+    pub redact: Vec<Field>,
+    /// Mount point of the disk highlighted as the root disk
+    pub root_disk: Option<String>,
+    /// Omit disks smaller than this many bytes
+    pub min_disk_size: Option<u64>,
+    /// Report disk mount points with symlinks resolved
+    pub canonical_mounts: bool,
+    /// Report only the disks whose mount point matches one of these patterns
+    pub disks: Vec<String>,
+    /// Report only the interfaces whose name matches one of these patterns
+    pub interfaces: Vec<String>,
+    /// Kernel parameters to report in addition to `sysctl::DEFAULT_KEYS`
+    pub sysctls: Vec<String>,
+    /// Snapshot that network traffic is reported relative to
+    pub network_baseline: Option<PathBuf>,
+    /// File the previous run's counters are kept in, for rates across runs
+    pub state_file: Option<PathBuf>,
+    /// Tag network interfaces with their role
+    pub normalize_interfaces: bool,
+    /// Omit network interfaces that have not sent or received any bytes
+    pub skip_idle_networks: bool,
+    /// Fail if no disks are detected
+    pub require_disks: bool,
+    /// Fail if no network interfaces are detected
+    pub require_networks: bool,
+    /// Fail if an interface has more errors and drops than this
+    pub fail_on_nic_errors: Option<u64>,
+    /// Warn when allocated file handles exceed this share of fs.file-max
+    pub warn_fd_global_pct: Option<f64>,
+    /// Entropy estimate in bits below which a warning is printed
+    pub warn_entropy: Option<u64>,
+    /// Warn when a disk is projected to be full in fewer days than this
+    pub warn_days_until_full: Option<f64>,
+    /// Alert conditions evaluated against every sample
+    pub checks: Vec<crate::expr::Check>,
+    /// File of named alert rules evaluated against every sample
+    pub compare_thresholds: Option<PathBuf>,
+    /// Sample the CPU time breakdown during collection
+    pub cpu_breakdown: bool,
+    /// Sample swap and page fault rates during collection
+    pub memory_activity: bool,
+    /// Check for pending OS updates and whether a reboot is required
+    pub updates: bool,
+    /// Check disk encryption and secure boot
+    pub security: bool,
+    /// Count TCP sockets by state, UDP sockets and top remote addresses
+    pub sockets_summary: bool,
+    /// Count processes by state
+    pub process_states: bool,
+    /// Scan the kernel log for storage errors
+    pub dmesg_scan: bool,
+    /// Report software RAID arrays and LVM volumes with their backing devices
+    pub storage_topology: bool,
+    /// Report the resource usage of each running container
+    pub containers: bool,
+    /// Record how the system data was refreshed in `debug_refresh_info`
+    pub debug_meta: bool,
+    /// Docker or Podman API socket to query for `--containers`
+    pub docker_socket: Option<PathBuf>,
+    /// Directory to scan for the largest files and subdirectories
+    pub scan_largest: Option<PathBuf>,
+    /// Number of paths `--scan-largest` lists
+    pub top: Option<usize>,
+    /// Levels below the `--scan-largest` directory that may be listed
+    pub max_depth: Option<usize>,
+    /// NTP server to measure the local clock's offset against
+    pub check_clock: Option<String>,
+    /// Clock offset in milliseconds above which clock skew is reported
+    pub max_clock_offset: Option<u64>,
+    /// Run the executables in this directory as collection plugins
+    pub plugin_dir: Option<PathBuf>,
+    /// Time each plugin may run before it is killed
+    pub plugin_timeout: Option<Duration>,
+    /// Interval over which CPU times and rates are sampled
+    pub sample_interval: Option<Duration>,
+    /// Repeat collection at this interval instead of running once
+    pub interval: Option<Duration>,
+    /// Collect again whenever this file is touched, instead of at an interval
+    pub watch_file: Option<PathBuf>,
+    /// Time the whole collection may take before partial data is written
+    pub timeout: Option<Duration>,
+    /// Number of samples to take in interval mode before exiting
+    pub count: Option<u64>,
+    /// Paging rate above which memory activity is highlighted
+    pub swap_warn_rate: Option<f64>,
+    /// Zone the collection time is shown in by the console and Markdown
+    /// reports
+    pub timezone: TimeZone,
+    /// Print guidance on the snapshot below the report
+    pub advise: bool,
+    /// Also record the guidance in the snapshot's `advice`
+    pub advise_json: bool,
+    /// Serve the latest snapshot on this Unix domain socket in interval mode
+    pub listen_unix: Option<PathBuf>,
+    /// Serve the latest snapshot on this Windows named pipe in interval mode
+    pub listen_pipe: Option<String>,
+    /// Permission bits for the `--listen-unix` socket
+    pub socket_mode: Option<u32>,
+    /// Permission bits for the output files (Unix)
+    pub mode: Option<u32>,
+    /// Group (name or ID) to own the `--listen-unix` socket
+    pub socket_group: Option<String>,
+    /// Publish each snapshot to this memory-mapped file (or read it with read-shm)
+    pub shm: Option<PathBuf>,
+    /// Emit change events to this target (`-`, a path, or a URL) in interval mode
+    pub events: Option<String>,
+    /// Disk usage percentage that triggers a high-usage event
+    pub disk_threshold: Option<f64>,
+    /// Memory usage percentage that triggers a high-usage event
+    pub memory_threshold: Option<f64>,
+    /// Percentage points below a threshold at which usage is normal again
+    pub hysteresis: Option<f64>,
+    /// Deliver webhook events in batches of this many
+    pub batch_size: Option<usize>,
+    /// Deliver webhook events once the oldest queued one is this old
+    pub batch_interval: Option<Duration>,
+    /// Most webhook events queued for a batch before the oldest are dropped
+    pub max_queue: Option<usize>,
+    /// Only report whether an update exists instead of installing it
+    pub check_only: bool,
+    /// Releases endpoint to query instead of the GitHub API
+    pub update_url: Option<String>,
+    /// Time this many collections instead of reporting
+    pub benchmark: Option<u64>,
+    /// Check that every collector returns plausible data instead of reporting
+    pub self_test: bool,
+    /// Describe every snapshot field instead of collecting
+    pub explain: bool,
+    /// Print the version and compiled-in features instead of collecting
+    pub version: bool,
+    /// Print a fleet overview of the snapshots in this directory instead of collecting
+    pub aggregate: Option<PathBuf>,
+    /// Also write a support bundle ZIP to this path
+    pub bundle: Option<PathBuf>,
+    /// Inventory spec file checked by the verify command
+    pub spec: Option<PathBuf>,
+    /// Metric printed by the quick command
+    pub metric: Option<String>,
+    /// Print the quick command's value with units
+    pub human: bool,
+    /// Print the processes command's output as folded stacks
+    pub folded: bool,
+    /// Weight of each process in folded stacks
+    pub process_metric: Option<crate::processes::Metric>,
+    /// Arguments the service runs the report loop with
+    pub service_args: Vec<String>,
+}
+
+impl Options {
+    /// Parses options from command-line arguments, excluding the program name.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::InvalidArgument` if an argument is not recognized,
+    /// a flag is missing its value, or a flag is used with the wrong command.
+    pub fn parse<I>(args: I) -> Result<Options, AppError>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut options = Options::default();
+        let mut args = args.into_iter().peekable();
+
+        // The service's own arguments are passed on unparsed
+        if args.next_if(|arg| arg == "service").is_some() {
+            let name = args.next().ok_or_else(|| {
+                AppError::InvalidArgument(
+                    "service requires an action (install, uninstall, start, stop or run)"
+                        .to_string(),
+                )
+            })?;
+            let action = crate::service::Action::parse(&name).ok_or_else(|| {
+                AppError::InvalidArgument(format!(
+                    "unknown service action '{}' (expected install, uninstall, start, stop or run)",
+                    name
+                ))
+            })?;
+            options.service_args = args.collect();
+            let takes_args = matches!(
+                action,
+                crate::service::Action::Install | crate::service::Action::Run
+            );
+            if !takes_args && !options.service_args.is_empty() {
+                return Err(AppError::InvalidArgument(format!(
+                    "service {} takes no arguments",
+                    name
+                )));
+            }
+            options.command = Command::Service(action);
+            return Ok(options);
+        }
+
+        let command = match args.peek().map(String::as_str) {
+            Some("self-update") => Some(Command::SelfUpdate),
+            Some("verify") => Some(Command::Verify),
+            Some("read-shm") => Some(Command::ReadShm),
+            Some("quick") => Some(Command::Quick),
+            Some("processes") => Some(Command::Processes),
+            _ => None,
+        };
+        if let Some(command) = command {
+            args.next();
+            options.command = command;
+        }
+        if options.command == Command::Quick {
+            options.metric = Some(args.next().ok_or_else(|| {
+                AppError::InvalidArgument(format!(
+                    "quick requires a metric (one of {})",
+                    crate::quick::metric_names().join(", ")
+                ))
+            })?);
+        }
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-q" | "--quiet" => options.quiet = true,
+                "--format" => options.format = OutputFormat::parse(&value(&mut args, &arg)?)?,
+                "--metric-prefix" => {
+                    let prefix = value(&mut args, &arg)?;
+                    // Kept to characters that are valid in shell variable
+                    // names and need no escaping in line protocol
+                    if prefix.is_empty()
+                        || !prefix
+                            .chars()
+                            .all(|c| c.is_ascii_alphanumeric() || c == '_')
+                    {
+                        return Err(AppError::InvalidArgument(format!(
+                            "--metric-prefix may only contain ASCII letters, digits and '_', got '{}'",
+                            prefix
+                        )));
+                    }
+                    options.metric_prefix = Some(prefix);
+                }
+                "--output-json" | "--output-text" | "--report-file" | "--output-flat"
+                | "--output-influx" | "--output-markdown" => {
+                    let format = match arg.as_str() {
+                        "--output-json" => FileFormat::Json,
+                        "--output-text" | "--report-file" => FileFormat::Text,
+                        "--output-flat" => FileFormat::Flat,
+                        "--output-markdown" => FileFormat::Markdown,
+                        _ => FileFormat::Influx,
+                    };
+                    let path = PathBuf::from(value(&mut args, &arg)?);
+                    options.outputs.push((format, path));
+                }
+                "--json-keys" => {
+                    let text = value(&mut args, &arg)?;
+                    options.json_keys = KeyCase::parse(&text).ok_or_else(|| {
+                        AppError::InvalidArgument(format!(
+                            "unknown key convention '{}' (expected snake, camel or kebab)",
+                            text
+                        ))
+                    })?;
+                }
+                "--array" => options.array = true,
+                "--human" => options.human = true,
+                "--folded" => options.folded = true,
+                "--metric" => {
+                    let text = value(&mut args, &arg)?;
+                    options.process_metric =
+                        Some(crate::processes::Metric::parse(&text).ok_or_else(|| {
+                            AppError::InvalidArgument(format!(
+                                "unknown metric '{}' (expected rss or cpu)",
+                                text
+                            ))
+                        })?);
+                }
+                "--copy" => options.copy = true,
+                "--copy-json" => options.copy_json = true,
+                "--s3-url" => options.s3_url = Some(value(&mut args, &arg)?),
+                "--s3-endpoint" => options.s3_endpoint = Some(value(&mut args, &arg)?),
+                "--proxy" => options.proxy = Some(value(&mut args, &arg)?),
+                "--ca-bundle" => options.ca_bundle = Some(PathBuf::from(value(&mut args, &arg)?)),
+                "--insecure-skip-tls-verify" => options.insecure_skip_tls_verify = true,
+                "--mqtt-url" => options.mqtt_url = Some(value(&mut args, &arg)?),
+                "--mqtt-topic" => options.mqtt_topic = Some(value(&mut args, &arg)?),
+                "--mqtt-qos" => {
+                    options.mqtt_qos = number(&mut args, &arg)?;
+                    if options.mqtt_qos > 2 {
+                        return Err(AppError::InvalidArgument(format!(
+                            "--mqtt-qos expects 0, 1 or 2, got {}",
+                            options.mqtt_qos
+                        )));
+                    }
+                }
+                "--mqtt-retain" => options.mqtt_retain = true,
+                "--mqtt-ca" => options.mqtt_ca = Some(PathBuf::from(value(&mut args, &arg)?)),
+                "--mqtt-cert" => options.mqtt_cert = Some(PathBuf::from(value(&mut args, &arg)?)),
+                "--mqtt-key" => options.mqtt_key = Some(PathBuf::from(value(&mut args, &arg)?)),
+                "--pid-file" => options.pid_file = Some(PathBuf::from(value(&mut args, &arg)?)),
+                "--get" => options.get = Some(value(&mut args, &arg)?),
+                "--thousands-separator" => {
+                    let text = value(&mut args, &arg)?;
+                    options.thousands_separator = Some(match text.as_str() {
+                        "comma" => ",",
+                        "period" => ".",
+                        "space" => "\u{2009}",
+                        "none" => "",
+                        _ => {
+                            return Err(AppError::InvalidArgument(format!(
+                            "unknown separator '{}' for {} (expected comma, period, space or none)",
+                            text, arg
+                        )))
+                        }
+                    });
+                }
+                "--locale" => {
+                    let text = value(&mut args, &arg)?;
+                    options.locale = Some(if text == "auto" {
+                        crate::locale::Locale::from_env()
+                    } else {
+                        crate::locale::Locale::parse(&text).ok_or_else(|| {
+                            AppError::InvalidArgument(format!(
+                                "unknown locale '{}' (expected e.g. de_DE, fr or auto)",
+                                text
+                            ))
+                        })?
+                    });
+                }
+                "--min-unit" => {
+                    let text = value(&mut args, &arg)?;
+                    options.min_unit = BYTE_UNITS
+                        .iter()
+                        .position(|unit| unit.eq_ignore_ascii_case(&text))
+                        .ok_or_else(|| {
+                            AppError::InvalidArgument(format!(
+                                "unknown unit '{}' for {} (expected one of {})",
+                                text,
+                                arg,
+                                BYTE_UNITS.join(", ")
+                            ))
+                        })?;
+                }
+                "--width" => options.width = Some(number(&mut args, &arg)?),
+                "--redact" => options.redact = Field::parse_list(&value(&mut args, &arg)?)?,
+                "--root-disk" => options.root_disk = Some(value(&mut args, &arg)?),
+                "--skip-idle-networks" => options.skip_idle_networks = true,
+                "--normalize-interfaces" => options.normalize_interfaces = true,
+                "--network-baseline" => {
+                    options.network_baseline = Some(PathBuf::from(value(&mut args, &arg)?))
+                }
+                "--state-file" => options.state_file = Some(PathBuf::from(value(&mut args, &arg)?)),
+                "--canonical-mounts" => options.canonical_mounts = true,
+                "--disk" => options.disks.push(value(&mut args, &arg)?),
+                "--interface" => options.interfaces.push(value(&mut args, &arg)?),
+                "--sysctl" => {
+                    let key = value(&mut args, &arg)?;
+                    #[cfg(feature = "host-details")]
+                    if !crate::sysctl::is_valid_key(&key) {
+                        return Err(AppError::InvalidArgument(format!(
+                            "'{}' is not a sysctl name such as vm.swappiness",
+                            key
+                        )));
+                    }
+                    options.sysctls.push(key);
+                }
+                "--min-disk-size" => {
+                    let text = value(&mut args, &arg)?;
+                    options.min_disk_size = Some(crate::parse_bytes(&text).ok_or_else(|| {
+                        AppError::InvalidArgument(format!(
+                            "{} expects a size such as 100MB, got '{}'",
+                            arg, text
+                        ))
+                    })?);
+                }
+                "--require-disks" => options.require_disks = true,
+                "--require-networks" => options.require_networks = true,
+                "--check-expr" => {
+                    let text = value(&mut args, &arg)?;
+                    let check = crate::expr::Check::parse(&text).map_err(|e| {
+                        AppError::InvalidArgument(format!("{} '{}': {}", arg, text, e))
+                    })?;
+                    options.checks.push(check);
+                }
+                "--compare-thresholds" => {
+                    options.compare_thresholds = Some(PathBuf::from(value(&mut args, &arg)?))
+                }
+                "--fail-on-nic-errors" => {
+                    options.fail_on_nic_errors = Some(number(&mut args, &arg)?)
+                }
+                "--warn-fd-global-pct" => {
+                    options.warn_fd_global_pct = Some(percent(&mut args, &arg)?)
+                }
+                "--warn-entropy" => options.warn_entropy = Some(number(&mut args, &arg)?),
+                "--warn-days-until-full" => {
+                    options.warn_days_until_full = Some(positive(&mut args, &arg)? as f64)
+                }
+                "--cpu-breakdown" => options.cpu_breakdown = true,
+                "--memory-activity" => options.memory_activity = true,
+                "--updates" => options.updates = true,
+                "--security" => options.security = true,
+                "--sockets-summary" => options.sockets_summary = true,
+                "--process-states" => options.process_states = true,
+                "--dmesg-scan" => options.dmesg_scan = true,
+                "--storage-topology" => options.storage_topology = true,
+                "--containers" => options.containers = true,
+                "--debug-meta" => options.debug_meta = true,
+                "--docker-socket" => {
+                    options.docker_socket = Some(PathBuf::from(value(&mut args, &arg)?))
+                }
+                "--scan-largest" => {
+                    options.scan_largest = Some(PathBuf::from(value(&mut args, &arg)?))
+                }
+                "--top" => {
+                    let top: usize = number(&mut args, &arg)?;
+                    if top == 0 {
+                        return Err(AppError::InvalidArgument(
+                            "--top must be at least 1".to_string(),
+                        ));
+                    }
+                    options.top = Some(top);
+                }
+                "--max-depth" => {
+                    let depth: usize = number(&mut args, &arg)?;
+                    if depth == 0 {
+                        return Err(AppError::InvalidArgument(
+                            "--max-depth must be at least 1".to_string(),
+                        ));
+                    }
+                    options.max_depth = Some(depth);
+                }
+                "--check-clock" => options.check_clock = Some(value(&mut args, &arg)?),
+                "--max-clock-offset" => options.max_clock_offset = Some(number(&mut args, &arg)?),
+                "--plugin-dir" => options.plugin_dir = Some(PathBuf::from(value(&mut args, &arg)?)),
+                "--plugin-timeout" => options.plugin_timeout = Some(seconds(&mut args, &arg)?),
+                "--sample-interval" => options.sample_interval = Some(seconds(&mut args, &arg)?),
+                "--interval" => options.interval = Some(seconds(&mut args, &arg)?),
+                "--watch-file" => options.watch_file = Some(PathBuf::from(value(&mut args, &arg)?)),
+                "--timeout" => options.timeout = Some(seconds(&mut args, &arg)?),
+                "--splay" => options.splay = Some(seconds(&mut args, &arg)?),
+                "--splay-random" => options.splay_random = true,
+                "--count" => {
+                    let count: u64 = number(&mut args, &arg)?;
+                    if count == 0 {
+                        return Err(AppError::InvalidArgument(
+                            "--count must be at least 1".to_string(),
+                        ));
+                    }
+                    options.count = Some(count);
+                }
+                "--swap-warn-rate" => options.swap_warn_rate = Some(number(&mut args, &arg)?),
+                "--timezone" => {
+                    options.timezone = TimeZone::load(&value(&mut args, &arg)?)
+                        .map_err(|e| AppError::InvalidArgument(format!("{}: {}", arg, e)))?
+                }
+                "--advise" => options.advise = true,
+                "--advise-json" => {
+                    options.advise = true;
+                    options.advise_json = true;
+                }
+                "--listen-unix" => {
+                    options.listen_unix = Some(PathBuf::from(value(&mut args, &arg)?))
+                }
+                "--listen-pipe" => options.listen_pipe = Some(value(&mut args, &arg)?),
+                "--socket-mode" => options.socket_mode = Some(mode(&mut args, &arg)?),
+                "--mode" => options.mode = Some(mode(&mut args, &arg)?),
+                "--socket-group" => options.socket_group = Some(value(&mut args, &arg)?),
+                "--shm" => options.shm = Some(PathBuf::from(value(&mut args, &arg)?)),
+                "--events" => options.events = Some(value(&mut args, &arg)?),
+                "--disk-threshold" => options.disk_threshold = Some(percent(&mut args, &arg)?),
+                "--memory-threshold" => options.memory_threshold = Some(percent(&mut args, &arg)?),
+                "--hysteresis" => options.hysteresis = Some(percent(&mut args, &arg)?),
+                "--batch-size" => options.batch_size = Some(positive(&mut args, &arg)?),
+                "--batch-interval" => options.batch_interval = Some(seconds(&mut args, &arg)?),
+                "--max-queue" => options.max_queue = Some(positive(&mut args, &arg)?),
+                "--check-only" => options.check_only = true,
+                "--update-url" => options.update_url = Some(value(&mut args, &arg)?),
+                "--benchmark" => {
+                    let runs: u64 = number(&mut args, &arg)?;
+                    if runs == 0 {
+                        return Err(AppError::InvalidArgument(
+                            "--benchmark must be at least 1".to_string(),
+                        ));
+                    }
+                    options.benchmark = Some(runs);
+                }
+                "--self-test" => options.self_test = true,
+                "--explain" => options.explain = true,
+                "--version" => options.version = true,
+                "--aggregate" => options.aggregate = Some(PathBuf::from(value(&mut args, &arg)?)),
+                "--bundle" => options.bundle = Some(PathBuf::from(value(&mut args, &arg)?)),
+                "--spec" => options.spec = Some(PathBuf::from(value(&mut args, &arg)?)),
+                _ => {
+                    return Err(AppError::InvalidArgument(format!(
+                        "unrecognized argument '{}'",
+                        arg
+                    )))
+                }
+            }
+        }
+
+        if let Some((flag, feature)) = missing_feature(&options) {
+            return Err(AppError::InvalidArgument(format!(
+                "{} is unavailable: compiled without feature '{}'",
+                flag, feature
+            )));
+        }
+
+        if options.command != Command::SelfUpdate
+            && (options.check_only || options.update_url.is_some())
+        {
+            return Err(AppError::InvalidArgument(
+                "--check-only and --update-url require the self-update command".to_string(),
+            ));
+        }
+
+        if options.plugin_timeout.is_some() && options.plugin_dir.is_none() {
+            return Err(AppError::InvalidArgument(
+                "--plugin-timeout requires --plugin-dir".to_string(),
+            ));
+        }
+
+        if options.max_clock_offset.is_some() && options.check_clock.is_none() {
+            return Err(AppError::InvalidArgument(
+                "--max-clock-offset requires --check-clock".to_string(),
+            ));
+        }
+
+        if options.watch_file.is_some()
+            && (options.command != Command::Report || options.interval.is_some())
+        {
+            return Err(AppError::InvalidArgument(
+                "--watch-file cannot be used with --interval or a subcommand".to_string(),
+            ));
+        }
+
+        if options.count.is_some() && !options.repeating() {
+            return Err(AppError::InvalidArgument(
+                "--count requires --interval or --watch-file".to_string(),
+            ));
+        }
+
+        if options.timeout.is_some() && (options.command != Command::Report || options.repeating())
+        {
+            return Err(AppError::InvalidArgument(
+                "--timeout only applies to a single collection, without --interval, --watch-file \
+                 or a subcommand"
+                    .to_string(),
+            ));
+        }
+
+        if (options.listen_unix.is_some() || options.listen_pipe.is_some()) && !options.repeating()
+        {
+            return Err(AppError::InvalidArgument(
+                "--listen-unix and --listen-pipe require --interval or --watch-file".to_string(),
+            ));
+        }
+
+        if options.listen_unix.is_none()
+            && (options.socket_mode.is_some() || options.socket_group.is_some())
+        {
+            return Err(AppError::InvalidArgument(
+                "--socket-mode and --socket-group require --listen-unix".to_string(),
+            ));
+        }
+
+        if options.command == Command::Report && options.shm.is_some() && !options.repeating() {
+            return Err(AppError::InvalidArgument(
+                "--shm requires --interval or --watch-file".to_string(),
+            ));
+        }
+
+        if options.events.is_some() && !options.repeating() {
+            return Err(AppError::InvalidArgument(
+                "--events requires --interval or --watch-file".to_string(),
+            ));
+        }
+
+        if let Some(url) = &options.s3_url {
+            let valid = url
+                .strip_prefix("s3://")
+                .and_then(|rest| rest.split_once('/'))
+                .is_some_and(|(bucket, key)| !bucket.is_empty() && !key.is_empty());
+            if !valid {
+                return Err(AppError::InvalidArgument(format!(
+                    "--s3-url expects s3://bucket/key, got '{}'",
+                    url
+                )));
+            }
+            if options.events.is_some() {
+                return Err(AppError::InvalidArgument(
+                    "--s3-url cannot be used with --events".to_string(),
+                ));
+            }
+        }
+        if options.s3_endpoint.is_some() && options.s3_url.is_none() {
+            return Err(AppError::InvalidArgument(
+                "--s3-endpoint requires --s3-url".to_string(),
+            ));
+        }
+
+        if let Some(proxy) = &options.proxy {
+            let schemes = [
+                "http://",
+                "https://",
+                "socks4://",
+                "socks5://",
+                "socks5h://",
+            ];
+            if !schemes.iter().any(|scheme| proxy.starts_with(scheme)) {
+                return Err(AppError::InvalidArgument(format!(
+                    "--proxy expects http://, https:// or socks5:// followed by host:port, got '{}'",
+                    proxy
+                )));
+            }
+        }
+
+        match &options.mqtt_url {
+            Some(url) => {
+                if !url.starts_with("mqtt://") && !url.starts_with("mqtts://") {
+                    return Err(AppError::InvalidArgument(format!(
+                        "--mqtt-url expects mqtt://host[:port] or mqtts://host[:port], got '{}'",
+                        url
+                    )));
+                }
+                let tls_files = options.mqtt_ca.is_some()
+                    || options.mqtt_cert.is_some()
+                    || options.mqtt_key.is_some();
+                if tls_files && !url.starts_with("mqtts://") {
+                    return Err(AppError::InvalidArgument(
+                        "--mqtt-ca, --mqtt-cert and --mqtt-key require an mqtts:// URL".to_string(),
+                    ));
+                }
+                if options.mqtt_cert.is_some() != options.mqtt_key.is_some() {
+                    return Err(AppError::InvalidArgument(
+                        "--mqtt-cert and --mqtt-key must be given together".to_string(),
+                    ));
+                }
+            }
+            None => {
+                let mqtt_flag = [
+                    ("--mqtt-topic", options.mqtt_topic.is_some()),
+                    ("--mqtt-qos", options.mqtt_qos > 0),
+                    ("--mqtt-retain", options.mqtt_retain),
+                    ("--mqtt-ca", options.mqtt_ca.is_some()),
+                    ("--mqtt-cert", options.mqtt_cert.is_some()),
+                    ("--mqtt-key", options.mqtt_key.is_some()),
+                ]
+                .into_iter()
+                .find_map(|(flag, given)| given.then_some(flag));
+                if let Some(flag) = mqtt_flag {
+                    return Err(AppError::InvalidArgument(format!(
+                        "{} requires --mqtt-url",
+                        flag
+                    )));
+                }
+            }
+        }
+
+        if options.warn_days_until_full.is_some() && options.state_file.is_none() {
+            return Err(AppError::InvalidArgument(
+                "--warn-days-until-full requires --state-file, which keeps the disk history"
+                    .to_string(),
+            ));
+        }
+
+        if options.splay_random && options.splay.is_none() {
+            return Err(AppError::InvalidArgument(
+                "--splay-random requires --splay".to_string(),
+            ));
+        }
+
+        if !options.outputs.is_empty() && options.events.is_some() {
+            return Err(AppError::InvalidArgument(
+                "--output-json, --output-text, --report-file, --output-flat and --output-influx cannot be used with --events"
+                    .to_string(),
+            ));
+        }
+
+        if options.array && options.events.is_some() {
+            return Err(AppError::InvalidArgument(
+                "--array cannot be used with --events".to_string(),
+            ));
+        }
+
+        if options.events.is_none()
+            && (options.disk_threshold.is_some()
+                || options.memory_threshold.is_some()
+                || options.hysteresis.is_some())
+        {
+            return Err(AppError::InvalidArgument(
+                "--disk-threshold, --memory-threshold and --hysteresis require --events"
+                    .to_string(),
+            ));
+        }
+
+        let webhook = options
+            .events
+            .as_deref()
+            .is_some_and(|target| target.starts_with("http://") || target.starts_with("https://"));
+        if !webhook
+            && (options.batch_size.is_some()
+                || options.batch_interval.is_some()
+                || options.max_queue.is_some())
+        {
+            return Err(AppError::InvalidArgument(
+                "--batch-size, --batch-interval and --max-queue require an --events webhook URL"
+                    .to_string(),
+            ));
+        }
+        if options.max_queue.is_some()
+            && options.batch_size.is_none()
+            && options.batch_interval.is_none()
+        {
+            return Err(AppError::InvalidArgument(
+                "--max-queue requires --batch-size or --batch-interval".to_string(),
+            ));
+        }
+
+        if options.aggregate.is_some() && options.command != Command::Report {
+            return Err(AppError::InvalidArgument(
+                "--aggregate cannot be used with a subcommand".to_string(),
+            ));
+        }
+
+        if options.benchmark.is_some()
+            && (options.command != Command::Report
+                || options.aggregate.is_some()
+                || options.repeating())
+        {
+            return Err(AppError::InvalidArgument(
+                "--benchmark cannot be used with a subcommand, --aggregate, --interval or \
+                 --watch-file"
+                    .to_string(),
+            ));
+        }
+
+        if options.self_test
+            && (options.command != Command::Report
+                || options.aggregate.is_some()
+                || options.repeating()
+                || options.benchmark.is_some())
+        {
+            return Err(AppError::InvalidArgument(
+                "--self-test cannot be used with a subcommand, --aggregate, --interval, \
+                 --watch-file or --benchmark"
+                    .to_string(),
+            ));
+        }
+        if options.self_test {
+            // Exercise the optional collectors that are cheap and compiled in
+            options.sockets_summary |= cfg!(feature = "probes");
+            options.process_states |= cfg!(feature = "processes");
+            options.cpu_breakdown = true;
+            options.memory_activity = true;
+        }
+
+        if (options.top.is_some() || options.max_depth.is_some()) && options.scan_largest.is_none()
+        {
+            return Err(AppError::InvalidArgument(
+                "--top and --max-depth require --scan-largest".to_string(),
+            ));
+        }
+        if options.scan_largest.is_some() && options.command != Command::Report {
+            return Err(AppError::InvalidArgument(
+                "--scan-largest cannot be used with a subcommand".to_string(),
+            ));
+        }
+
+        if options.advise && options.command != Command::Report {
+            return Err(AppError::InvalidArgument(
+                "--advise and --advise-json cannot be used with a subcommand".to_string(),
+            ));
+        }
+        if options.advise {
+            // The swap pressure rule needs a swap-in rate on the first sample
+            options.memory_activity = true;
+        }
+
+        if options.explain && options.command != Command::Report {
+            return Err(AppError::InvalidArgument(
+                "--explain cannot be used with a subcommand".to_string(),
+            ));
+        }
+
+        if options.version && options.command != Command::Report {
+            return Err(AppError::InvalidArgument(
+                "--version cannot be used with a subcommand".to_string(),
+            ));
+        }
+
+        let prefixable = matches!(options.format, OutputFormat::Flat | OutputFormat::Influx)
+            || options
+                .outputs
+                .iter()
+                .any(|(format, _)| matches!(format, FileFormat::Flat | FileFormat::Influx));
+        if options.metric_prefix.is_some() && !prefixable {
+            return Err(AppError::InvalidArgument(
+                "--metric-prefix requires --format flat or influx, or --output-flat or --output-influx"
+                    .to_string(),
+            ));
+        }
+
+        if options.version && !matches!(options.format, OutputFormat::Text | OutputFormat::Json) {
+            return Err(AppError::InvalidArgument(
+                "--version only supports --format text or json".to_string(),
+            ));
+        }
+
+        if options.command != Command::Quick && options.human {
+            return Err(AppError::InvalidArgument(
+                "--human requires the quick command".to_string(),
+            ));
+        }
+
+        if options.command != Command::Processes
+            && (options.folded || options.process_metric.is_some())
+        {
+            return Err(AppError::InvalidArgument(
+                "--folded and --metric require the processes command".to_string(),
+            ));
+        }
+
+        if options.process_metric.is_some() && !options.folded {
+            return Err(AppError::InvalidArgument(
+                "--metric requires --folded".to_string(),
+            ));
+        }
+
+        if options.command != Command::Verify && options.spec.is_some() {
+            return Err(AppError::InvalidArgument(
+                "--spec requires the verify command".to_string(),
+            ));
+        }
+
+        Ok(options)
+    }
+}
+
+/// Returns the first subcommand or flag in `options` that needs a cargo
+/// feature this binary was built without, along with that feature.
+fn missing_feature(options: &Options) -> Option<(&'static str, &'static str)> {
+    let webhook = options
+        .events
+        .as_deref()
+        .is_some_and(|target| target.starts_with("http://") || target.starts_with("https://"));
+    [
+        (
+            "self-update",
+            options.command == Command::SelfUpdate,
+            "self-update",
+            cfg!(feature = "self-update"),
+        ),
+        (
+            "processes",
+            options.command == Command::Processes,
+            "processes",
+            cfg!(feature = "processes"),
+        ),
+        (
+            "--process-states",
+            options.process_states,
+            "processes",
+            cfg!(feature = "processes"),
+        ),
+        (
+            "read-shm",
+            options.command == Command::ReadShm,
+            "serve",
+            cfg!(feature = "serve"),
+        ),
+        (
+            "--listen-unix",
+            options.listen_unix.is_some(),
+            "serve",
+            cfg!(feature = "serve"),
+        ),
+        (
+            "--listen-pipe",
+            options.listen_pipe.is_some(),
+            "serve",
+            cfg!(feature = "serve"),
+        ),
+        (
+            "--shm",
+            options.shm.is_some(),
+            "serve",
+            cfg!(feature = "serve"),
+        ),
+        (
+            "--state-file",
+            options.state_file.is_some(),
+            "history",
+            cfg!(feature = "history"),
+        ),
+        (
+            "--network-baseline",
+            options.network_baseline.is_some(),
+            "history",
+            cfg!(feature = "history"),
+        ),
+        (
+            "--sysctl",
+            !options.sysctls.is_empty(),
+            "host-details",
+            cfg!(feature = "host-details"),
+        ),
+        (
+            "--warn-fd-global-pct",
+            options.warn_fd_global_pct.is_some(),
+            "host-details",
+            cfg!(feature = "host-details"),
+        ),
+        (
+            "--warn-entropy",
+            options.warn_entropy.is_some(),
+            "host-details",
+            cfg!(feature = "host-details"),
+        ),
+        (
+            "--updates",
+            options.updates,
+            "probes",
+            cfg!(feature = "probes"),
+        ),
+        (
+            "--security",
+            options.security,
+            "probes",
+            cfg!(feature = "probes"),
+        ),
+        (
+            "--sockets-summary",
+            options.sockets_summary,
+            "probes",
+            cfg!(feature = "probes"),
+        ),
+        (
+            "--dmesg-scan",
+            options.dmesg_scan,
+            "probes",
+            cfg!(feature = "probes"),
+        ),
+        (
+            "--storage-topology",
+            options.storage_topology,
+            "probes",
+            cfg!(feature = "probes"),
+        ),
+        (
+            "--check-clock",
+            options.check_clock.is_some(),
+            "probes",
+            cfg!(feature = "probes"),
+        ),
+        (
+            "--plugin-dir",
+            options.plugin_dir.is_some(),
+            "probes",
+            cfg!(feature = "probes"),
+        ),
+        (
+            "--events with a webhook URL",
+            webhook,
+            "net-sinks",
+            cfg!(feature = "net-sinks"),
+        ),
+        (
+            "--s3-url",
+            options.s3_url.is_some(),
+            "s3",
+            cfg!(feature = "s3"),
+        ),
+        (
+            "--mqtt-url",
+            options.mqtt_url.is_some(),
+            "mqtt",
+            cfg!(feature = "mqtt"),
+        ),
+        (
+            "--containers",
+            options.containers,
+            "containers",
+            cfg!(feature = "containers"),
+        ),
+    ]
+    .into_iter()
+    .find_map(|(flag, given, feature, compiled)| (given && !compiled).then_some((flag, feature)))
+}
+
+impl Options {
+    /// Returns the interval over which rates are sampled.
+    pub fn sample_interval(&self) -> Duration {
+        self.sample_interval.unwrap_or(DEFAULT_SAMPLE_INTERVAL)
+    }
+
+    /// Returns the prefix of flat keys and influx measurement names; empty
+    /// without `--metric-prefix`.
+    pub fn metric_prefix(&self) -> &str {
+        self.metric_prefix.as_deref().unwrap_or_default()
+    }
+
+    /// Returns whether samples are collected repeatedly, at an `--interval`
+    /// or on `--watch-file` touches.
+    pub fn repeating(&self) -> bool {
+        self.interval.is_some() || self.watch_file.is_some()
+    }
+
+    /// Returns whether the CPU time breakdown is sampled: with
+    /// `--cpu-breakdown`, in interval mode, or when `--memory-activity`
+    /// waits for the sampling interval anyway.
+    pub fn sample_cpu(&self) -> bool {
+        self.cpu_breakdown || self.interval.is_some() || self.memory_activity
+    }
+
+    /// Returns the mount point of the root disk.
+    pub fn root_disk(&self) -> &str {
+        self.root_disk.as_deref().unwrap_or(DEFAULT_ROOT_DISK)
+    }
+
+    /// Returns the number of paths `--scan-largest` lists.
+    pub fn top(&self) -> usize {
+        self.top.unwrap_or(largest::DEFAULT_TOP)
+    }
+
+    /// Returns how many levels below the `--scan-largest` directory may be
+    /// listed.
+    pub fn max_depth(&self) -> usize {
+        self.max_depth.unwrap_or(largest::DEFAULT_MAX_DEPTH)
+    }
+
+    /// Returns the time each plugin may run.
+    pub fn plugin_timeout(&self) -> Duration {
+        self.plugin_timeout.unwrap_or(plugins::DEFAULT_TIMEOUT)
+    }
+
+    /// Returns the paging rate above which memory activity is highlighted.
+    pub fn swap_warn_rate(&self) -> f64 {
+        self.swap_warn_rate.unwrap_or(DEFAULT_SWAP_WARN_RATE)
+    }
+
+    /// Returns the projected days until full below which a disk is
+    /// highlighted.
+    pub fn days_until_full_warning(&self) -> f64 {
+        self.warn_days_until_full
+            .unwrap_or(DEFAULT_DAYS_UNTIL_FULL_WARNING)
+    }
+
+    /// Returns the clock offset in milliseconds above which clock skew is
+    /// reported.
+    pub fn max_clock_offset(&self) -> u64 {
+        self.max_clock_offset.unwrap_or(DEFAULT_MAX_CLOCK_OFFSET_MS)
+    }
+
+    /// Returns the webhook batching settings, or `None` if events are sent
+    /// one at a time.
+    pub fn batching(&self) -> Option<BatchConfig> {
+        if self.batch_size.is_none() && self.batch_interval.is_none() {
+            return None;
+        }
+        Some(BatchConfig {
+            size: self.batch_size,
+            interval: self.batch_interval,
+            max_queue: self.max_queue.unwrap_or(batch::DEFAULT_MAX_QUEUE),
+        })
+    }
+
+    /// Returns the locale console numbers are formatted for: that of
+    /// `--locale`, English-style by default.
+    pub fn locale(&self) -> crate::locale::Locale {
+        self.locale.unwrap_or_default()
+    }
+
+    /// Returns the thousands separator for console counts: the
+    /// `--thousands-separator`, else that of the `--locale` (`,` by default).
+    pub fn thousands_separator(&self) -> &'static str {
+        self.thousands_separator
+            .or(self.locale.map(|locale| locale.thousands))
+            .unwrap_or(",")
+    }
+
+    /// Returns the proxy and TLS settings for HTTP requests.
+    #[cfg(any(feature = "s3", feature = "self-update"))]
+    pub fn http_client(&self) -> crate::http::Client {
+        crate::http::Client {
+            proxy: self.proxy.clone(),
+            ca_bundle: self.ca_bundle.clone(),
+            insecure: self.insecure_skip_tls_verify,
+        }
+    }
+
+    /// Returns the usage thresholds used in `--events` mode.
+    pub fn thresholds(&self) -> Thresholds {
+        Thresholds {
+            disk_percent: self.disk_threshold.unwrap_or(DEFAULT_USAGE_THRESHOLD),
+            memory_percent: self.memory_threshold.unwrap_or(DEFAULT_USAGE_THRESHOLD),
+            hysteresis: self.hysteresis.unwrap_or(DEFAULT_HYSTERESIS),
+        }
+    }
+}
+
+/// Takes the value following `flag` from `args`.
+fn value<I>(args: &mut I, flag: &str) -> Result<String, AppError>
+where
+    I: Iterator<Item = String>,
+{
+    args.next()
+        .ok_or_else(|| AppError::InvalidArgument(format!("{} requires a value", flag)))
+}
+
+/// Takes the value following `flag` from `args` and parses it as a number.
+fn number<I, T>(args: &mut I, flag: &str) -> Result<T, AppError>
+where
+    I: Iterator<Item = String>,
+    T: FromStr,
+{
+    let text = value(args, flag)?;
+    text.parse().map_err(|_| {
+        AppError::InvalidArgument(format!("{} expects a number, got '{}'", flag, text))
+    })
+}
+
+/// Takes the value following `flag` from `args` as a count of at least 1.
+fn positive<I>(args: &mut I, flag: &str) -> Result<usize, AppError>
+where
+    I: Iterator<Item = String>,
+{
+    let count: usize = number(args, flag)?;
+    if count == 0 {
+        return Err(AppError::InvalidArgument(format!(
+            "{} must be at least 1",
+            flag
+        )));
+    }
+    Ok(count)
+}
+
+/// Takes the value following `flag` from `args` as a positive number of seconds.
+fn seconds<I>(args: &mut I, flag: &str) -> Result<Duration, AppError>
+where
+    I: Iterator<Item = String>,
+{
+    let seconds: f64 = number(args, flag)?;
+    if !(seconds > 0.0 && seconds.is_finite()) {
+        return Err(AppError::InvalidArgument(format!(
+            "{} must be a positive number of seconds",
+            flag
+        )));
+    }
+    Ok(Duration::from_secs_f64(seconds))
+}
+
+/// Takes the value following `flag` from `args` as octal Unix permission bits.
+fn mode<I>(args: &mut I, flag: &str) -> Result<u32, AppError>
+where
+    I: Iterator<Item = String>,
+{
+    let text = value(args, flag)?;
+    u32::from_str_radix(&text, 8)
+        .ok()
+        .filter(|&mode| mode <= 0o777)
+        .ok_or_else(|| {
+            AppError::InvalidArgument(format!(
+                "{} expects octal permission bits such as 0660, got '{}'",
+                flag, text
+            ))
+        })
+}
+
+/// Takes the value following `flag` from `args` as a percentage from 0 to 100.
+fn percent<I>(args: &mut I, flag: &str) -> Result<f64, AppError>
+where
+    I: Iterator<Item = String>,
+{
+    let percent: f64 = number(args, flag)?;
+    if !(0.0..=100.0).contains(&percent) {
+        return Err(AppError::InvalidArgument(format!(
+            "{} must be a percentage from 0 to 100",
+            flag
+        )));
+    }
+    Ok(percent)
+}
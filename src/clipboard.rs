@@ -0,0 +1,168 @@
+//! Copying the report to the clipboard (`--copy`, `--copy-json`).
+//!
+//! In an SSH session the text is sent to the operator's terminal as an OSC 52
+//! escape sequence, so it lands on their local clipboard; terminals cap the
+//! sequence length, so longer text is truncated with a warning. The sequence
+//! goes to the controlling terminal rather than stdout, so redirected output
+//! (`--copy > report.txt`) stays clean. Otherwise the
+//! platform's clipboard tool is used: `clip` on Windows, `pbcopy` on macOS,
+//! and `wl-copy`, `xclip` or `xsel` elsewhere. A missing clipboard only
+//! produces a warning.
+
+use std::io::{self, IsTerminal, Write};
+use std::process::{Command, Stdio};
+
+/// The controlling terminal, which receives OSC 52 sequences.
+const TERMINAL: &str = if cfg!(windows) { "CONOUT$" } else { "/dev/tty" };
+
+/// Longest base64 payload sent in an OSC 52 sequence; many terminals ignore
+/// longer ones.
+const MAX_OSC52_BASE64: usize = 100_000;
+
+/// Places `text` on the clipboard, warning on stderr if that fails.
+pub fn copy(text: &str) {
+    let remote = ["SSH_CONNECTION", "SSH_CLIENT", "SSH_TTY"]
+        .iter()
+        .any(|name| std::env::var_os(name).is_some());
+    if remote {
+        let (sequence, truncated) = osc52(text);
+        if truncated {
+            eprintln!(
+                "Warning: copied text truncated to fit the {} KB terminal clipboard limit",
+                MAX_OSC52_BASE64 / 1000
+            );
+        }
+        if let Err(e) = write_terminal(&sequence) {
+            eprintln!("Warning: could not copy to the clipboard: {}", e);
+        }
+        return;
+    }
+
+    if let Err(e) = copy_local(text) {
+        eprintln!("Warning: could not copy to the clipboard: {}", e);
+    }
+}
+
+/// Writes `sequence` to the controlling terminal, or to stdout if there is
+/// none but stdout is a terminal itself. Never writes to redirected stdout.
+fn write_terminal(sequence: &str) -> io::Result<()> {
+    match std::fs::OpenOptions::new().write(true).open(TERMINAL) {
+        Ok(mut terminal) => {
+            terminal.write_all(sequence.as_bytes())?;
+            terminal.flush()
+        }
+        Err(_) if io::stdout().is_terminal() => {
+            let mut stdout = io::stdout();
+            stdout.write_all(sequence.as_bytes())?;
+            stdout.flush()
+        }
+        Err(e) => Err(io::Error::new(
+            e.kind(),
+            format!("no terminal to send it to ({})", e),
+        )),
+    }
+}
+
+/// Pipes `text` into the first clipboard tool that is available.
+fn copy_local(text: &str) -> io::Result<()> {
+    let tools: &[&[&str]] = if cfg!(windows) {
+        &[&["clip"]]
+    } else if cfg!(target_os = "macos") {
+        &[&["pbcopy"]]
+    } else {
+        &[
+            &["wl-copy"],
+            &["xclip", "-selection", "clipboard"],
+            &["xsel", "--clipboard", "--input"],
+        ]
+    };
+    for tool in tools {
+        let child = Command::new(tool[0])
+            .args(&tool[1..])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        let mut child = match child {
+            Ok(child) => child,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes())?;
+        }
+        let status = child.wait()?;
+        return if status.success() {
+            Ok(())
+        } else {
+            Err(io::Error::other(format!(
+                "{} failed with {}",
+                tool[0], status
+            )))
+        };
+    }
+    Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        "no clipboard tool found",
+    ))
+}
+
+/// Builds the OSC 52 sequence that sets the clipboard to `text`.
+///
+/// # Returns
+///
+/// The sequence, and whether `text` had to be truncated (at a character
+/// boundary) to stay within `MAX_OSC52_BASE64`.
+pub fn osc52(text: &str) -> (String, bool) {
+    // Every 3 input bytes become 4 base64 characters
+    let max_bytes = MAX_OSC52_BASE64 / 4 * 3;
+    let mut end = text.len().min(max_bytes);
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    let sequence = format!("\x1b]52;c;{}\x07", base64(&text.as_bytes()[..end]));
+    (sequence, end < text.len())
+}
+
+/// Encodes `bytes` as standard, padded base64.
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = (u32::from(chunk[0]) << 16)
+            | (u32::from(chunk.get(1).copied().unwrap_or(0)) << 8)
+            | u32::from(chunk.get(2).copied().unwrap_or(0));
+        for index in 0..4 {
+            if index <= chunk.len() {
+                encoded.push(ALPHABET[(group >> (18 - 6 * index) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_osc52() {
+        assert_eq!(osc52("hi"), ("\x1b]52;c;aGk=\x07".to_string(), false));
+        let (sequence, truncated) = osc52(&"x".repeat(80_000));
+        assert!(truncated);
+        assert_eq!(sequence.len(), "\x1b]52;c;\x07".len() + 100_000);
+    }
+
+    #[test]
+    fn test_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foob"), "Zm9vYg==");
+        assert_eq!(base64(&[0xfb, 0xff]), "+/8=");
+    }
+}
@@ -0,0 +1,252 @@
+//! Graceful degradation of optional collectors.
+//!
+//! One collector failing on an exotic platform, say a temperature driver
+//! returning garbage, should not cost the rest of the snapshot. Optional
+//! collectors run through `CollectionErrors`, which turns their errors and
+//! panics into entries of the snapshot's `collection_errors` and leaves the
+//! field they fill `null`. Core data (memory, disks and interfaces) is not
+//! optional, so its failure still fails the run.
+//!
+//! Optional collectors run on `Worker` threads while the core data is read,
+//! and are waited for until the `--timeout` deadline. A collector still
+//! running then is abandoned: its field is `null` and it is recorded as
+//! timed out, so a snapshot is written on time even if, say, a hung NFS
+//! mount blocks a probe. The provider's refresh and the disk reads, which
+//! a hung mount blocks just as well, run on workers for the same reason.
+
+use std::any::Any;
+use std::fmt::Display;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// Failures of optional collectors during one collection.
+///
+/// Shared with the worker threads of a collection through an `Arc`.
+#[derive(Default)]
+pub struct CollectionErrors {
+    failures: Mutex<Vec<String>>,
+    timed_out: AtomicBool,
+}
+
+impl CollectionErrors {
+    /// Runs the optional collector `name`, for which `None` means the data
+    /// is not available rather than an error.
+    ///
+    /// # Returns
+    ///
+    /// `None` if the collector returned `None` or panicked; a panic is
+    /// recorded.
+    pub fn optional<T>(&self, name: &str, collect: impl FnOnce() -> Option<T>) -> Option<T> {
+        self.fallible(name, || Ok::<_, String>(collect())).flatten()
+    }
+
+    /// Runs the optional collector `name`.
+    ///
+    /// # Returns
+    ///
+    /// `None` if the collector failed or panicked, after recording why as
+    /// "<name>: <reason>" and warning on stderr.
+    pub fn fallible<T, E: Display>(
+        &self,
+        name: &str,
+        collect: impl FnOnce() -> Result<T, E>,
+    ) -> Option<T> {
+        match panic::catch_unwind(AssertUnwindSafe(collect)) {
+            Ok(Ok(value)) => Some(value),
+            Ok(Err(e)) => {
+                self.record(name, e.to_string());
+                None
+            }
+            Err(payload) => {
+                self.record(
+                    name,
+                    format!("panicked: {}", panic_message(payload.as_ref())),
+                );
+                None
+            }
+        }
+    }
+
+    /// Returns whether a collector was abandoned at the deadline.
+    pub fn timed_out(&self) -> bool {
+        self.timed_out.load(Ordering::Relaxed)
+    }
+
+    /// Takes the recorded failures, sorted: collectors run concurrently, so
+    /// the order they fail in varies between runs.
+    pub fn take(&self) -> Vec<String> {
+        let mut failures = std::mem::take(&mut *self.failures());
+        failures.sort();
+        failures
+    }
+
+    /// Records that collector `name` failed for `reason`, warning on stderr.
+    fn record(&self, name: &str, reason: String) {
+        eprintln!("Warning: {} collection failed: {}", name, reason);
+        self.failures().push(format!("{}: {}", name, reason));
+    }
+
+    fn failures(&self) -> std::sync::MutexGuard<'_, Vec<String>> {
+        self.failures
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// An optional collector running on its own thread.
+pub struct Worker<T> {
+    name: &'static str,
+    result: mpsc::Receiver<Option<T>>,
+}
+
+impl<T: Send + 'static> Worker<T> {
+    /// Starts the optional collector `name` on a new thread; see
+    /// `CollectionErrors::optional`.
+    pub fn spawn(
+        errors: &Arc<CollectionErrors>,
+        name: &'static str,
+        collect: impl FnOnce() -> Option<T> + Send + 'static,
+    ) -> Worker<T> {
+        Worker::start(errors, name, move |errors| errors.optional(name, collect))
+    }
+
+    /// Starts the optional collector `name` on a new thread; see
+    /// `CollectionErrors::fallible`.
+    pub fn spawn_fallible<E: Display>(
+        errors: &Arc<CollectionErrors>,
+        name: &'static str,
+        collect: impl FnOnce() -> Result<T, E> + Send + 'static,
+    ) -> Worker<T> {
+        Worker::start(errors, name, move |errors| errors.fallible(name, collect))
+    }
+
+    fn start(
+        errors: &Arc<CollectionErrors>,
+        name: &'static str,
+        run: impl FnOnce(&CollectionErrors) -> Option<T> + Send + 'static,
+    ) -> Worker<T> {
+        let (sender, result) = mpsc::channel();
+        let errors = Arc::clone(errors);
+        std::thread::spawn(move || {
+            // The receiver is gone if the collection no longer waits
+            let _ = sender.send(run(&errors));
+        });
+        Worker { name, result }
+    }
+}
+
+impl<T> Worker<T> {
+    /// Waits for the collector's result until `deadline`, or for as long as
+    /// it takes without one.
+    ///
+    /// # Returns
+    ///
+    /// `None` if the collector found nothing, failed, or was still running
+    /// at the deadline, which is recorded as "<name>: timed out". The
+    /// thread of an abandoned collector is left to finish on its own.
+    pub fn wait(self, errors: &CollectionErrors, deadline: Option<Instant>) -> Option<T> {
+        let received = match deadline {
+            Some(deadline) => self
+                .result
+                .recv_timeout(deadline.saturating_duration_since(Instant::now())),
+            None => self
+                .result
+                .recv()
+                .map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        match received {
+            Ok(value) => value,
+            Err(RecvTimeoutError::Timeout) => {
+                errors.timed_out.store(true, Ordering::Relaxed);
+                errors.record(self.name, "timed out".to_string());
+                None
+            }
+            // Panics are caught in the thread, so it always sends
+            Err(RecvTimeoutError::Disconnected) => None,
+        }
+    }
+}
+
+/// Extracts the message of a panic payload, which is a `&str` or `String`
+/// for `panic!` with a message.
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "unknown cause"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collection_errors_optional() {
+        let errors = CollectionErrors::default();
+        assert_eq!(errors.optional("sockets", || Some(3)), Some(3));
+        assert_eq!(errors.optional("sockets", || None::<u32>), None);
+        assert!(errors.take().is_empty());
+    }
+
+    #[test]
+    fn test_collection_errors_fallible() {
+        let errors = CollectionErrors::default();
+        assert_eq!(errors.fallible("clock", || Ok::<_, String>(-3)), Some(-3));
+        assert_eq!(
+            errors.fallible("clock", || Err::<i64, _>("timed out")),
+            None
+        );
+        assert_eq!(
+            errors.fallible("thermal", || -> Result<u32, String> {
+                panic!("bad sensor")
+            }),
+            None
+        );
+        assert_eq!(
+            errors.take(),
+            ["clock: timed out", "thermal: panicked: bad sensor"]
+        );
+        assert!(!errors.timed_out());
+
+        // Sorted, whatever order the collectors failed in
+        for name in ["updates", "clock", "sockets"] {
+            errors.fallible(name, || Err::<(), _>("unavailable"));
+        }
+        assert_eq!(
+            errors.take(),
+            [
+                "clock: unavailable",
+                "sockets: unavailable",
+                "updates: unavailable"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_worker_wait() {
+        use std::time::{Duration, Instant};
+
+        let errors = Arc::new(CollectionErrors::default());
+        let fast = Worker::spawn(&errors, "machine_id", || Some("abc"));
+        let slow = Worker::spawn(&errors, "updates", || {
+            std::thread::sleep(Duration::from_secs(5));
+            Some("done")
+        });
+        let deadline = Some(Instant::now() + Duration::from_millis(100));
+        assert_eq!(fast.wait(&errors, deadline), Some("abc"));
+        assert_eq!(slow.wait(&errors, deadline), None);
+        assert!(errors.timed_out());
+        assert_eq!(errors.take(), ["updates: timed out"]);
+
+        // past the deadline, finished collectors still count
+        let done = Worker::spawn(&errors, "sysctls", || Some(1));
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(done.wait(&errors, Some(Instant::now())), Some(1));
+    }
+}
@@ -0,0 +1,131 @@
+//! Running external commands with a time limit.
+
+use std::io::{self, Read};
+use std::process::{Command, Output, Stdio};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+/// How often a running command is checked for completion.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Most stdout kept from a command run by `run_with_timeout`.
+const MAX_OUTPUT_BYTES: usize = 16 * 1024 * 1024;
+
+/// Runs `command`, killing it if it does not finish within `timeout`.
+///
+/// Stdout is captured up to 16 MiB; stdin and stderr are discarded. See
+/// `run_limited`.
+///
+/// # Errors
+///
+/// As for `run_limited`.
+pub fn run_with_timeout(command: &mut Command, timeout: Duration) -> io::Result<Output> {
+    run_limited(command, timeout, MAX_OUTPUT_BYTES)
+}
+
+/// Runs `command`, killing it if it does not finish within `timeout`, and
+/// keeps at most `max_output + 1` bytes of its stdout.
+///
+/// Stdin and stderr are discarded. Once the limit is passed stdout is
+/// closed, so a command still writing gets a broken pipe; callers can tell
+/// the output was cut short by its length. A background process the command
+/// leaves behind may keep stdout open after the command exits; its output is
+/// waited for only until the same deadline.
+///
+/// # Errors
+///
+/// Returns an error if the command cannot be started, or one of kind
+/// `TimedOut` if it had to be killed or its stdout was still open at the
+/// deadline.
+pub fn run_limited(
+    command: &mut Command,
+    timeout: Duration,
+    max_output: usize,
+) -> io::Result<Output> {
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+
+    // Drain stdout concurrently so a chatty command cannot block on a full
+    // pipe. The reader is never joined: it ends when the last holder of the
+    // pipe exits, which may be after the deadline
+    let mut stdout = child.stdout.take();
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut buffer = Vec::new();
+        if let Some(stdout) = stdout.take() {
+            let limit = (max_output as u64).saturating_add(1);
+            let _ = stdout.take(limit).read_to_end(&mut buffer);
+        }
+        let _ = sender.send(buffer);
+    });
+
+    let deadline = Instant::now() + timeout;
+    let timed_out = || {
+        io::Error::new(
+            io::ErrorKind::TimedOut,
+            format!("timed out after {}s", timeout.as_secs_f64()),
+        )
+    };
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(timed_out());
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    };
+
+    let stdout = receiver
+        .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+        .map_err(|_| timed_out())?;
+    Ok(Output {
+        status,
+        stdout,
+        stderr: Vec::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(unix)]
+    fn test_run_with_timeout() {
+        let sh = |script: &str| {
+            let mut command = Command::new("sh");
+            command.args(["-c", script]);
+            command
+        };
+        let timeout = Duration::from_millis(500);
+
+        let output = run_with_timeout(&mut sh("echo hello; exit 3"), timeout).unwrap();
+        assert_eq!(output.stdout, b"hello\n");
+        assert_eq!(output.status.code(), Some(3));
+
+        let start = Instant::now();
+        let error = run_with_timeout(&mut sh("sleep 10"), timeout).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        // A background process holding stdout open does not outlast the deadline
+        let start = Instant::now();
+        let error = run_with_timeout(&mut sh("sleep 10 & echo started"), timeout).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        // Output past the limit is cut off and the writer stopped
+        let output = run_limited(&mut sh("yes"), timeout, 1000).unwrap();
+        assert_eq!(output.stdout.len(), 1001);
+        assert!(!output.status.success());
+
+        let error = run_with_timeout(&mut Command::new("/nonexistent/probe"), timeout);
+        assert_eq!(error.unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+}
@@ -0,0 +1,387 @@
+//! Per-container resource usage on Docker and Podman hosts (`--containers`).
+//!
+//! Talks HTTP to the container engine's API over its Unix socket:
+//! `/containers/json` lists the containers, and
+//! `/containers/{id}/stats?stream=false` reports a running container's
+//! memory, CPU and network usage. Podman serves the same API on its
+//! Docker-compatible socket. Other platforms report no containers.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Socket used when neither `--docker-socket` nor `DOCKER_HOST` names one.
+pub const DEFAULT_SOCKET: &str = "/var/run/docker.sock";
+
+/// Containers on the host.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct Containers {
+    /// Running containers, by name
+    pub running: Vec<Container>,
+    /// Containers that exist but are not running, e.g. exited or created
+    pub stopped: usize,
+}
+
+/// Resource usage of one running container.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Container {
+    /// Name without the leading slash, e.g. "web"
+    pub name: String,
+    /// Image the container was created from, e.g. "nginx:1.25"
+    pub image: String,
+    /// Memory in use in bytes, excluding reclaimable page cache as
+    /// `docker stats` does
+    pub memory_usage: Option<u64>,
+    /// Memory limit in bytes; the host's memory for unlimited containers
+    pub memory_limit: Option<u64>,
+    /// CPU usage since the engine's previous sample, where 100% is one
+    /// whole CPU
+    pub cpu_percent: Option<f64>,
+    /// Bytes received on all of the container's interfaces
+    pub network_bytes_received: Option<u64>,
+    /// Bytes transmitted on all of the container's interfaces
+    pub network_bytes_transmitted: Option<u64>,
+}
+
+/// A container as `/containers/json` lists it.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+struct Listed {
+    id: String,
+    #[serde(default)]
+    names: Vec<String>,
+    #[serde(default)]
+    image: String,
+    #[serde(default)]
+    state: String,
+}
+
+/// The parts of `/containers/{id}/stats` used here. Fields the engine
+/// leaves out or sets to null, e.g. for a container that just stopped,
+/// are `None` or zero.
+#[derive(Deserialize, Debug, Default)]
+struct Stats {
+    #[serde(default)]
+    cpu_stats: CpuStats,
+    #[serde(default)]
+    precpu_stats: CpuStats,
+    #[serde(default)]
+    memory_stats: MemoryStats,
+    networks: Option<HashMap<String, NetworkStats>>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct CpuStats {
+    #[serde(default)]
+    cpu_usage: CpuUsage,
+    system_cpu_usage: Option<u64>,
+    online_cpus: Option<u32>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct CpuUsage {
+    #[serde(default)]
+    total_usage: u64,
+    percpu_usage: Option<Vec<u64>>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct MemoryStats {
+    usage: Option<u64>,
+    limit: Option<u64>,
+    #[serde(default)]
+    stats: HashMap<String, u64>,
+}
+
+#[derive(Deserialize, Debug)]
+struct NetworkStats {
+    #[serde(default)]
+    rx_bytes: u64,
+    #[serde(default)]
+    tx_bytes: u64,
+}
+
+/// Picks the engine socket: `configured` if set, else the path of a
+/// `unix://` `DOCKER_HOST`, else `DEFAULT_SOCKET`.
+pub fn socket_path(configured: Option<&Path>) -> PathBuf {
+    if let Some(path) = configured {
+        return path.to_path_buf();
+    }
+    std::env::var("DOCKER_HOST")
+        .ok()
+        .and_then(|host| host.strip_prefix("unix://").map(PathBuf::from))
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_SOCKET))
+}
+
+/// Lists the containers and fetches the usage of the running ones, all at
+/// once as the engine takes about a second to sample each.
+///
+/// # Errors
+///
+/// Returns why the containers could not be listed, e.g. that nothing
+/// listens on `socket`. A running container whose usage cannot be read is
+/// still reported, without usage.
+#[cfg(unix)]
+pub fn query(socket: &Path) -> Result<Containers, String> {
+    let listed = parse_list(&get(socket, "/containers/json?all=true")?)?;
+    let (running, stopped): (Vec<Listed>, Vec<Listed>) = listed
+        .into_iter()
+        .partition(|container| container.state == "running");
+    let mut usages: Vec<Container> = std::thread::scope(|scope| {
+        let requests: Vec<_> = running
+            .iter()
+            .map(|container| {
+                scope.spawn(|| {
+                    let path = format!("/containers/{}/stats?stream=false", container.id);
+                    get(socket, &path).ok()
+                })
+            })
+            .collect();
+        running
+            .iter()
+            .zip(requests)
+            .map(|(container, request)| {
+                let stats = request.join().ok().flatten();
+                usage(container, stats.as_deref())
+            })
+            .collect()
+    });
+    usages.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(Containers {
+        running: usages,
+        stopped: stopped.len(),
+    })
+}
+
+/// Lists the containers and fetches the usage of the running ones.
+///
+/// # Errors
+///
+/// Always fails: container engines are only queried over Unix sockets.
+#[cfg(not(unix))]
+pub fn query(socket: &Path) -> Result<Containers, String> {
+    let _ = socket;
+    Err("container engines are only queried over Unix sockets".to_string())
+}
+
+/// Sends a GET request for `path` to the API on `socket`.
+///
+/// # Returns
+///
+/// The response body.
+///
+/// # Errors
+///
+/// Returns why the request failed, or the status if it was not 200.
+#[cfg(unix)]
+fn get(socket: &Path, path: &str) -> Result<String, String> {
+    use std::io::{Read, Write};
+    use std::os::unix::net::UnixStream;
+    use std::time::Duration;
+
+    let mut stream = UnixStream::connect(socket)
+        .map_err(|e| format!("cannot connect to {}: {}", socket.display(), e))?;
+    stream
+        .set_read_timeout(Some(Duration::from_secs(10)))
+        .map_err(|e| e.to_string())?;
+    // HTTP/1.0 makes the engine close the connection after an unchunked
+    // body, so reading to the end reads exactly the response
+    write!(stream, "GET {} HTTP/1.0\r\nHost: localhost\r\n\r\n", path)
+        .map_err(|e| format!("cannot send request to {}: {}", socket.display(), e))?;
+    let mut response = Vec::new();
+    stream
+        .read_to_end(&mut response)
+        .map_err(|e| format!("cannot read response from {}: {}", socket.display(), e))?;
+    let response = String::from_utf8_lossy(&response);
+    let (head, body) = response
+        .split_once("\r\n\r\n")
+        .ok_or_else(|| format!("malformed response to {}", path))?;
+    let status = head.split_whitespace().nth(1).unwrap_or_default();
+    if status != "200" {
+        return Err(format!("{} returned HTTP {}", path, status));
+    }
+    Ok(body.to_string())
+}
+
+/// Parses the body of `/containers/json`.
+fn parse_list(body: &str) -> Result<Vec<Listed>, String> {
+    serde_json::from_str(body).map_err(|e| format!("cannot parse container list: {}", e))
+}
+
+/// Builds the report for `container` from the body of its stats request,
+/// if there was one.
+fn usage(container: &Listed, stats: Option<&str>) -> Container {
+    let name = container
+        .names
+        .first()
+        .map(|name| name.trim_start_matches('/').to_string())
+        // Listed containers always have a name, but the ID is unique too
+        .unwrap_or_else(|| container.id.chars().take(12).collect());
+    let stats: Option<Stats> = stats.and_then(|stats| serde_json::from_str(stats).ok());
+    let networks = stats.as_ref().and_then(|stats| stats.networks.as_ref());
+    Container {
+        name,
+        image: container.image.clone(),
+        memory_usage: stats
+            .as_ref()
+            .and_then(|stats| memory_usage(&stats.memory_stats)),
+        memory_limit: stats.as_ref().and_then(|stats| stats.memory_stats.limit),
+        cpu_percent: stats.as_ref().and_then(cpu_percent),
+        network_bytes_received: networks
+            .map(|networks| networks.values().map(|network| network.rx_bytes).sum()),
+        network_bytes_transmitted: networks
+            .map(|networks| networks.values().map(|network| network.tx_bytes).sum()),
+    }
+}
+
+/// Memory in use excluding inactive page cache, which the kernel reclaims
+/// before the container would hit its limit: `total_inactive_file` under
+/// cgroup v1, `inactive_file` under v2.
+fn memory_usage(memory: &MemoryStats) -> Option<u64> {
+    let inactive = ["total_inactive_file", "inactive_file"]
+        .iter()
+        .find_map(|key| memory.stats.get(*key))
+        .copied()
+        .unwrap_or(0);
+    memory.usage.map(|usage| usage.saturating_sub(inactive))
+}
+
+/// CPU usage between the engine's previous and current sample, as
+/// `docker stats` computes it: the container's share of the host's CPU
+/// time, times the number of CPUs.
+///
+/// # Returns
+///
+/// `None` if the engine has no previous sample, or no host CPU time passed
+/// between the two.
+fn cpu_percent(stats: &Stats) -> Option<f64> {
+    let (current, previous) = (&stats.cpu_stats, &stats.precpu_stats);
+    let system_delta = current
+        .system_cpu_usage?
+        .checked_sub(previous.system_cpu_usage?)
+        .filter(|&delta| delta > 0)?;
+    let cpu_delta = current
+        .cpu_usage
+        .total_usage
+        .saturating_sub(previous.cpu_usage.total_usage);
+    let cpus = current.online_cpus.map(|cpus| cpus as usize).or_else(|| {
+        current
+            .cpu_usage
+            .percpu_usage
+            .as_ref()
+            .map(|usage| usage.len())
+    })?;
+    Some(cpu_delta as f64 / system_delta as f64 * cpus as f64 * 100.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_list() {
+        let listed = parse_list(
+    r#"[{"Id":"4f1c","Names":["/web"],"Image":"nginx:1.25","State":"running","Status":"Up 3 hours"},
+        {"Id":"9a0e","Names":["/migrate"],"Image":"app:2.1","State":"exited","Status":"Exited (0) 2 days ago"}]"#,
+)
+.unwrap();
+        assert_eq!(listed.len(), 2);
+        assert_eq!(listed[1].names, ["/migrate"]);
+        assert_eq!(listed[1].state, "exited");
+        assert!(parse_list("{}")
+            .unwrap_err()
+            .starts_with("cannot parse container list"));
+    }
+
+    #[test]
+    fn test_usage() {
+        let listed = &parse_list(
+            r#"[{"Id":"4f1c","Names":["/web"],"Image":"nginx:1.25","State":"running"}]"#,
+        )
+        .unwrap()[0];
+        let stats = r#"{
+    "cpu_stats": {"cpu_usage": {"total_usage": 1500000000}, "system_cpu_usage": 40000000000, "online_cpus": 4},
+    "precpu_stats": {"cpu_usage": {"total_usage": 1000000000}, "system_cpu_usage": 36000000000, "online_cpus": 4},
+    "memory_stats": {"usage": 73400320, "limit": 536870912, "stats": {"inactive_file": 10485760}},
+    "networks": {"eth0": {"rx_bytes": 1000, "tx_bytes": 200}, "eth1": {"rx_bytes": 24, "tx_bytes": 6}}
+}"#;
+        let container = usage(listed, Some(stats));
+        assert_eq!(container.name, "web");
+        assert_eq!(container.image, "nginx:1.25");
+        assert_eq!(container.memory_usage, Some(62914560));
+        assert_eq!(container.memory_limit, Some(536870912));
+        assert_eq!(container.cpu_percent, Some(50.0));
+        assert_eq!(container.network_bytes_received, Some(1024));
+        assert_eq!(container.network_bytes_transmitted, Some(206));
+
+        // Without stats, or with stats that do not parse, only the name is known
+        let container = usage(listed, Some("not json"));
+        assert_eq!(
+            (container.memory_usage, container.cpu_percent),
+            (None, None)
+        );
+        assert_eq!(usage(listed, None), container);
+    }
+
+    #[test]
+    fn test_memory_usage() {
+        let stats = |json| serde_json::from_str::<MemoryStats>(json).unwrap();
+        assert_eq!(
+            memory_usage(&stats(
+                r#"{"usage": 1000, "stats": {"inactive_file": 300}}"#
+            )),
+            Some(700)
+        );
+        assert_eq!(
+            memory_usage(&stats(
+                r#"{"usage": 1000, "stats": {"total_inactive_file": 400, "cache": 600}}"#
+            )),
+            Some(600)
+        );
+        assert_eq!(
+            memory_usage(&stats(
+                r#"{"usage": 1000, "stats": {"inactive_file": 2000}}"#
+            )),
+            Some(0)
+        );
+        assert_eq!(memory_usage(&stats(r#"{"usage": 1000}"#)), Some(1000));
+        assert_eq!(memory_usage(&stats("{}")), None);
+    }
+
+    #[test]
+    fn test_cpu_percent() {
+        let stats = |json| serde_json::from_str::<Stats>(json).unwrap();
+        // A quarter of 4 CPUs' time: one whole CPU
+        let busy = stats(
+            r#"{
+    "cpu_stats": {"cpu_usage": {"total_usage": 2000}, "system_cpu_usage": 8000, "online_cpus": 4},
+    "precpu_stats": {"cpu_usage": {"total_usage": 1000}, "system_cpu_usage": 4000}
+}"#,
+        );
+        assert_eq!(cpu_percent(&busy), Some(100.0));
+        // Older engines count the CPUs only through percpu_usage
+        let old = stats(
+            r#"{
+    "cpu_stats": {"cpu_usage": {"total_usage": 1500, "percpu_usage": [750, 750]}, "system_cpu_usage": 8000},
+    "precpu_stats": {"cpu_usage": {"total_usage": 1000}, "system_cpu_usage": 4000}
+}"#,
+        );
+        assert_eq!(cpu_percent(&old), Some(25.0));
+        // The first sample after a start has no previous one
+        let first = stats(
+            r#"{
+    "cpu_stats": {"cpu_usage": {"total_usage": 1000}, "system_cpu_usage": 4000, "online_cpus": 4},
+    "precpu_stats": {"cpu_usage": {"total_usage": 0}}
+}"#,
+        );
+        assert_eq!(cpu_percent(&first), None);
+        // An idle container
+        let idle = stats(
+            r#"{
+    "cpu_stats": {"cpu_usage": {"total_usage": 1000}, "system_cpu_usage": 8000, "online_cpus": 2},
+    "precpu_stats": {"cpu_usage": {"total_usage": 1000}, "system_cpu_usage": 4000}
+}"#,
+        );
+        assert_eq!(cpu_percent(&idle), Some(0.0));
+    }
+}
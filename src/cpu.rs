@@ -0,0 +1,304 @@
+//! CPU time counters and their breakdown over an interval.
+//!
+//! sysinfo reports a single usage figure; on virtual machines the split
+//! between user, system, iowait and especially steal time says far more. On
+//! Linux every snapshot has the cumulative tick counters of `/proc/stat`,
+//! read without waiting, so consumers can compute their own deltas. The
+//! percentage breakdown needs a second read after the sampling interval and
+//! is only taken when asked for; it covers all CPUs and each core. Windows
+//! has no tick counters but samples the breakdown from the
+//! `\Processor(_Total)` performance counters via `typeperf`. Values a
+//! platform does not report are `None`, and other platforms report nothing.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Percentage of CPU time spent in each state over the sampling interval.
+///
+/// The percentages of one breakdown add up to 100 (within rounding).
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+pub struct CpuBreakdown {
+    /// Time spent in user mode
+    pub user: Option<f64>,
+    /// Time spent in user mode with low priority (nice)
+    pub nice: Option<f64>,
+    /// Time spent in kernel mode
+    pub system: Option<f64>,
+    /// Time spent idle
+    pub idle: Option<f64>,
+    /// Time spent idle while waiting for I/O to complete
+    pub iowait: Option<f64>,
+    /// Time spent servicing hardware interrupts
+    pub irq: Option<f64>,
+    /// Time spent servicing software interrupts
+    pub softirq: Option<f64>,
+    /// Time the hypervisor ran other guests while this one wanted to run
+    pub steal: Option<f64>,
+}
+
+/// The breakdown for one logical CPU.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CoreTimes {
+    /// Kernel name of the CPU, e.g. "cpu0"
+    pub name: String,
+    #[serde(flatten)]
+    pub times: CpuBreakdown,
+}
+
+/// Cumulative CPU time since boot, summed across all CPUs.
+///
+/// Values are in clock ticks (`USER_HZ`, normally 1/100th of a second) as
+/// reported by the kernel. Consumers compute utilization from the deltas
+/// between two snapshots.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+pub struct CpuTicks {
+    /// Time spent in user mode
+    pub user: u64,
+    /// Time spent in user mode with low priority (nice)
+    pub nice: u64,
+    /// Time spent in kernel mode
+    pub system: u64,
+    /// Time spent idle
+    pub idle: u64,
+    /// Time spent idle while waiting for I/O to complete
+    pub iowait: u64,
+    /// Time spent servicing hardware interrupts
+    pub irq: u64,
+    /// Time spent servicing software interrupts
+    pub softirq: u64,
+    /// Time the hypervisor ran other guests while this one wanted to run
+    pub steal: u64,
+}
+
+/// CPU time counters, with the breakdown over a sampling interval if one
+/// was taken.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct CpuTimes {
+    /// Cumulative ticks across all CPUs at the end of collection (Linux only)
+    pub ticks: Option<CpuTicks>,
+    /// Length of the sampling interval in seconds, if the breakdown was sampled
+    pub interval_secs: Option<f64>,
+    /// Breakdown across all CPUs; every share is `None` unless sampled
+    #[serde(flatten)]
+    pub total: CpuBreakdown,
+    /// Breakdown for each logical CPU, where sampled and available
+    pub per_core: Vec<CoreTimes>,
+}
+
+/// Reads the CPU time counters, and with `sample` also the breakdown over
+/// that interval, blocking for its duration.
+///
+/// Returns `None` if `/proc/stat` cannot be read or parsed.
+#[cfg(target_os = "linux")]
+pub fn read_cpu_times(sample: Option<Duration>) -> Option<CpuTimes> {
+    use std::time::Instant;
+
+    let read = || std::fs::read_to_string("/proc/stat").ok();
+    let aggregate = |lines: &[StatLine]| lines.iter().find(|c| c.name == "cpu").map(|c| c.ticks);
+
+    let before = parse_stat(&read()?);
+    let Some(interval) = sample else {
+        return Some(CpuTimes {
+            ticks: Some(aggregate(&before)?),
+            ..CpuTimes::default()
+        });
+    };
+    let start = Instant::now();
+    std::thread::sleep(interval);
+    let after = parse_stat(&read()?);
+    let elapsed = start.elapsed();
+
+    let total = breakdown(&aggregate(&before)?, &aggregate(&after)?);
+    let per_core = after
+        .iter()
+        .filter(|c| c.name != "cpu")
+        .filter_map(|current| {
+            let previous = before.iter().find(|c| c.name == current.name)?;
+            Some(CoreTimes {
+                name: current.name.clone(),
+                times: breakdown(&previous.ticks, &current.ticks),
+            })
+        })
+        .collect();
+
+    Some(CpuTimes {
+        ticks: aggregate(&after),
+        interval_secs: Some(elapsed.as_secs_f64()),
+        total,
+        per_core,
+    })
+}
+
+/// Reads the CPU time breakdown over `sample`, blocking for its duration.
+///
+/// Windows has no tick counters, so without `sample` this returns `None`.
+/// It reports user, privileged (system), idle and interrupt time; the other
+/// states are `None` and no per-core breakdown is collected. Returns `None`
+/// if the performance counters cannot be read.
+#[cfg(windows)]
+pub fn read_cpu_times(sample: Option<Duration>) -> Option<CpuTimes> {
+    use std::process::Command;
+
+    let interval = sample?;
+    let seconds = interval.as_secs().max(1);
+    let output = Command::new("typeperf")
+        .args([
+            "\\Processor(_Total)\\% User Time",
+            "\\Processor(_Total)\\% Privileged Time",
+            "\\Processor(_Total)\\% Idle Time",
+            "\\Processor(_Total)\\% Interrupt Time",
+            "-si",
+            &seconds.to_string(),
+            "-sc",
+            "2",
+        ])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let values = crate::memory::parse_typeperf(&String::from_utf8_lossy(&output.stdout))?;
+    Some(CpuTimes {
+        ticks: None,
+        interval_secs: Some(seconds as f64),
+        total: CpuBreakdown {
+            user: Some(*values.first()?),
+            system: Some(*values.get(1)?),
+            idle: Some(*values.get(2)?),
+            irq: Some(*values.get(3)?),
+            ..CpuBreakdown::default()
+        },
+        per_core: Vec::new(),
+    })
+}
+
+/// Reads the CPU time counters and breakdown.
+///
+/// Not available on this platform, so this always returns `None`.
+#[cfg(not(any(target_os = "linux", windows)))]
+pub fn read_cpu_times(_sample: Option<Duration>) -> Option<CpuTimes> {
+    None
+}
+
+/// Cumulative tick counters from one `cpu` line of `/proc/stat`.
+#[cfg(target_os = "linux")]
+#[derive(Debug, Default, Clone, PartialEq)]
+struct StatLine {
+    name: String,
+    ticks: CpuTicks,
+}
+
+/// Parses the aggregate `cpu` line and the per-CPU `cpuN` lines of `/proc/stat`.
+///
+/// Lines have the form `cpu0 user nice system idle iowait irq softirq steal
+/// guest guest_nice`. Guest time is already included in user time and is
+/// ignored. Counters missing on older kernels are treated as zero; lines with
+/// fewer than the first four counters are skipped.
+#[cfg(target_os = "linux")]
+fn parse_stat(stat: &str) -> Vec<StatLine> {
+    stat.lines()
+        .filter(|line| line.starts_with("cpu"))
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let name = fields.next()?.to_string();
+            let counters: Vec<u64> = fields.map_while(|field| field.parse().ok()).collect();
+            if counters.len() < 4 {
+                return None;
+            }
+            let counter = |index: usize| counters.get(index).copied().unwrap_or(0);
+            Some(StatLine {
+                name,
+                ticks: CpuTicks {
+                    user: counter(0),
+                    nice: counter(1),
+                    system: counter(2),
+                    idle: counter(3),
+                    iowait: counter(4),
+                    irq: counter(5),
+                    softirq: counter(6),
+                    steal: counter(7),
+                },
+            })
+        })
+        .collect()
+}
+
+/// Computes the percentage breakdown between two reads of the same CPU's ticks.
+///
+/// Each counter's increase is clamped with `counter_delta`, so a counter that
+/// wrapped or went backwards (the iowait counter is known to on some kernels)
+/// contributes zero instead of a bogus huge share. If no ticks elapsed at all
+/// every percentage is `None`.
+#[cfg(target_os = "linux")]
+fn breakdown(previous: &CpuTicks, current: &CpuTicks) -> CpuBreakdown {
+    use crate::rates::counter_delta;
+
+    let deltas = [
+        counter_delta(previous.user, current.user),
+        counter_delta(previous.nice, current.nice),
+        counter_delta(previous.system, current.system),
+        counter_delta(previous.idle, current.idle),
+        counter_delta(previous.iowait, current.iowait),
+        counter_delta(previous.irq, current.irq),
+        counter_delta(previous.softirq, current.softirq),
+        counter_delta(previous.steal, current.steal),
+    ];
+    let total: u64 = deltas.iter().sum();
+    let percent = |index: usize| (total > 0).then(|| deltas[index] as f64 / total as f64 * 100.0);
+
+    CpuBreakdown {
+        user: percent(0),
+        nice: percent(1),
+        system: percent(2),
+        idle: percent(3),
+        iowait: percent(4),
+        irq: percent(5),
+        softirq: percent(6),
+        steal: percent(7),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_stat() {
+        let stat = "cpu  100 0 50 800 20 0 5 25 0 0\n\
+            cpu0 60 0 30 400 10 0 5 15 0 0\n\
+            cpu1 40 0 20 400 10 0 0 10\n\
+            intr 12345 0 0\n";
+        let lines = parse_stat(stat);
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0].name, "cpu");
+        assert_eq!(lines[0].ticks.steal, 25);
+        assert_eq!(lines[2].name, "cpu1");
+        assert_eq!(lines[2].ticks.idle, 400);
+    }
+
+    #[test]
+    fn test_breakdown() {
+        let before = CpuTicks {
+            user: 100,
+            system: 50,
+            idle: 800,
+            iowait: 30,
+            steal: 20,
+            ..Default::default()
+        };
+        let after = CpuTicks {
+            user: 142,
+            system: 61,
+            idle: 835,
+            iowait: 25,
+            steal: 29,
+            ..Default::default()
+        };
+        let times = breakdown(&before, &after);
+        // 42 + 11 + 35 + 0 (iowait went backwards) + 9 = 97 ticks
+        assert_eq!(times.user.map(|p| p.round()), Some(43.0));
+        assert_eq!(times.iowait, Some(0.0));
+        assert_eq!(times.steal.map(|p| p.round()), Some(9.0));
+    }
+}
@@ -0,0 +1,82 @@
+//! Structural differences between two snapshots.
+//!
+//! Compares the set of disks, the set of network interfaces, and the default
+//! gateway of two `SystemInfo` values. Numeric values such as usage percentages
+//! are not compared here; thresholds on them are tracked by the `events`
+//! module.
+
+use crate::SystemInfo;
+
+/// One structural difference between two snapshots.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    /// A disk mounted at this path appeared
+    DiskAdded(String),
+    /// A disk mounted at this path went away
+    DiskRemoved(String),
+    /// A network interface with this name appeared
+    InterfaceAdded(String),
+    /// A network interface with this name went away
+    InterfaceRemoved(String),
+    /// The default gateway changed (either side may be absent)
+    GatewayChanged {
+        from: Option<String>,
+        to: Option<String>,
+    },
+}
+
+/// Lists the structural changes from `previous` to `current`.
+///
+/// Additions are reported in the order they appear in `current`, removals in
+/// the order they appeared in `previous`.
+pub fn diff(previous: &SystemInfo, current: &SystemInfo) -> Vec<Change> {
+    let mut changes = Vec::new();
+
+    let previous_disks: Vec<&str> = previous.disks.iter().map(|d| d.name.as_str()).collect();
+    let current_disks: Vec<&str> = current.disks.iter().map(|d| d.name.as_str()).collect();
+    let (added, removed) = membership(&previous_disks, &current_disks);
+    changes.extend(removed.into_iter().map(Change::DiskRemoved));
+    changes.extend(added.into_iter().map(Change::DiskAdded));
+
+    let previous_networks: Vec<&str> = previous.networks.iter().map(|n| n.name.as_str()).collect();
+    let current_networks: Vec<&str> = current.networks.iter().map(|n| n.name.as_str()).collect();
+    let (added, removed) = membership(&previous_networks, &current_networks);
+    changes.extend(removed.into_iter().map(Change::InterfaceRemoved));
+    changes.extend(added.into_iter().map(Change::InterfaceAdded));
+
+    if previous.default_gateway != current.default_gateway {
+        changes.push(Change::GatewayChanged {
+            from: previous.default_gateway.clone(),
+            to: current.default_gateway.clone(),
+        });
+    }
+
+    changes
+}
+
+/// Returns the names only in `current` (added) and only in `previous` (removed).
+fn membership(previous: &[&str], current: &[&str]) -> (Vec<String>, Vec<String>) {
+    let added = current
+        .iter()
+        .filter(|name| !previous.contains(name))
+        .map(|name| name.to_string())
+        .collect();
+    let removed = previous
+        .iter()
+        .filter(|name| !current.contains(name))
+        .map(|name| name.to_string())
+        .collect();
+    (added, removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_membership() {
+        let (added, removed) = membership(&["/", "/data"], &["/", "/backup"]);
+        assert_eq!(added, vec!["/backup"]);
+        assert_eq!(removed, vec!["/data"]);
+    }
+}
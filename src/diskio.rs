@@ -0,0 +1,137 @@
+//! Block device I/O counters, for system-wide disk throughput.
+//!
+//! On Linux the cumulative bytes read and written per device come from
+//! `/proc/diskstats`. Only whole physical devices are counted: partitions,
+//! device-mapper and software RAID volumes would count the same I/O twice,
+//! and loop and RAM disks are not storage. Other platforms report `None`.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// `/proc/diskstats` counts sectors of 512 bytes, whatever the device's
+/// actual sector size.
+const SECTOR_SIZE: u64 = 512;
+
+/// Prefixes of devices that are not counted.
+const SKIPPED_PREFIXES: &[&str] = &["loop", "ram", "zram", "dm-", "md", "sr", "fd"];
+
+/// Cumulative I/O of one device since boot.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DiskCounters {
+    pub read_bytes: u64,
+    pub written_bytes: u64,
+}
+
+/// Counters of every counted device, by device name.
+pub type Counters = HashMap<String, DiskCounters>;
+
+/// Reads the cumulative I/O counters of every whole physical device.
+///
+/// Used in interval mode to compute throughput between consecutive samples
+/// without blocking. Returns `None` if the counters cannot be read.
+#[cfg(target_os = "linux")]
+pub fn read_counters() -> Option<Counters> {
+    Some(parse_diskstats(
+        &std::fs::read_to_string("/proc/diskstats").ok()?,
+        |name| std::path::Path::new("/sys/block").join(name).exists(),
+    ))
+}
+
+/// Reads the cumulative I/O counters of every whole physical device.
+///
+/// Not available on this platform, so this always returns `None`.
+#[cfg(not(target_os = "linux"))]
+pub fn read_counters() -> Option<Counters> {
+    None
+}
+
+/// Parses `/proc/diskstats`, keeping the devices for which `is_whole` is
+/// true (partitions are not listed in `/sys/block`) and that are not
+/// virtual.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_diskstats(contents: &str, is_whole: impl Fn(&str) -> bool) -> Counters {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let name = *fields.get(2)?;
+            if SKIPPED_PREFIXES
+                .iter()
+                .any(|prefix| name.starts_with(prefix))
+                || !is_whole(name)
+            {
+                return None;
+            }
+            let sectors = |index: usize| -> Option<u64> { fields.get(index)?.parse().ok() };
+            Some((
+                name.to_string(),
+                DiskCounters {
+                    read_bytes: sectors(5)? * SECTOR_SIZE,
+                    written_bytes: sectors(9)? * SECTOR_SIZE,
+                },
+            ))
+        })
+        .collect()
+}
+
+/// Returns the bytes read and written per second between two readings,
+/// summed over the devices present in both.
+///
+/// Each device's delta is computed on its own, so a device that was
+/// removed, added or had its counters reset does not skew the total.
+pub fn total_rates(previous: &Counters, current: &Counters, elapsed: Duration) -> (f64, f64) {
+    use crate::rates::counter_rate;
+
+    current
+        .iter()
+        .filter_map(|(name, now)| Some((previous.get(name)?, now)))
+        .fold((0.0, 0.0), |(read, written), (then, now)| {
+            (
+                read + counter_rate(then.read_bytes, now.read_bytes, elapsed),
+                written + counter_rate(then.written_bytes, now.written_bytes, elapsed),
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_diskstats() {
+        let diskstats = "\
+ 259       0 nvme0n1 5000 10 80000 900 7000 20 40000 1200 0 3000 2100 0 0 0 0\n\
+ 259       1 nvme0n1p1 4000 10 60000 800 6000 20 30000 1100 0 2000 1900 0 0 0 0\n\
+   7       0 loop0 100 0 2000 10 0 0 0 0 0 5 10 0 0 0 0\n\
+ 253       0 dm-0 3000 0 50000 700 5000 0 30000 900 0 1000 1600 0 0 0 0\n";
+        let counters = parse_diskstats(diskstats, |name| !name.ends_with("p1"));
+        assert_eq!(counters.len(), 1);
+        assert_eq!(
+            counters["nvme0n1"],
+            DiskCounters {
+                read_bytes: 80_000 * 512,
+                written_bytes: 40_000 * 512
+            }
+        );
+    }
+
+    #[test]
+    fn test_total_rates() {
+        let disk = |read_bytes, written_bytes| DiskCounters {
+            read_bytes,
+            written_bytes,
+        };
+        let previous = HashMap::from([
+            ("sda".to_string(), disk(1_000, 5_000)),
+            ("sdb".to_string(), disk(0, 0)),
+        ]);
+        let current = HashMap::from([
+            ("sda".to_string(), disk(3_000, 9_000)),
+            ("sdc".to_string(), disk(1 << 40, 1 << 40)),
+        ]);
+        assert_eq!(
+            total_rates(&previous, &current, Duration::from_secs(2)),
+            (1_000.0, 2_000.0)
+        );
+    }
+}
@@ -0,0 +1,232 @@
+//! The kernel's entropy pool and random number generators (Linux only).
+//!
+//! Crypto appliances that generate keys need the kernel's RNG to be seeded.
+//! The pool estimate comes from `/proc/sys/kernel/random`, a hardware RNG
+//! from `/sys/class/misc/hw_random`, and whether `getrandom(2)` would block
+//! from a non-blocking call to it. Since Linux 5.18 the estimate is pinned
+//! at 256 of 256 bits, so it is marked as fixed rather than read as a pool
+//! that never drains or fills. The state is only read with the
+//! `host-details` feature.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "host-details")]
+use std::path::Path;
+
+/// Pool size and estimate that Linux 5.18 and later always report.
+#[cfg(feature = "host-details")]
+const FIXED_ESTIMATE_BITS: u64 = 256;
+
+/// Entropy pool and RNG state.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct Entropy {
+    /// Bits of entropy the kernel estimates the input pool holds
+    pub entropy_avail: Option<u64>,
+    /// Size of the input pool in bits
+    pub pool_size: Option<u64>,
+    /// Whether the kernel reports a fixed 256 of 256 bits, as Linux 5.18
+    /// and later do, so `entropy_avail` says nothing about starvation
+    pub fixed_estimate: bool,
+    /// Whether `getrandom(2)` would block because the kernel's RNG is not
+    /// yet seeded
+    pub getrandom_would_block: Option<bool>,
+    /// Hardware RNG feeding the kernel, e.g. "tpm-rng-0", or `None` without
+    /// one
+    pub hw_rng: Option<String>,
+}
+
+impl Entropy {
+    /// Returns why the RNG counts as starved with `threshold` bits as the
+    /// lowest acceptable estimate: `getrandom(2)` would block, or the pool
+    /// estimate is below `threshold`. A fixed estimate is never below it.
+    pub fn starved(&self, threshold: u64) -> Option<String> {
+        if self.getrandom_would_block == Some(true) {
+            return Some("getrandom would block".to_string());
+        }
+        self.entropy_avail
+            .filter(|&bits| !self.fixed_estimate && bits < threshold)
+            .map(|bits| format!("{} bits of entropy available", bits))
+    }
+}
+
+/// Reads the entropy pool and RNG state.
+#[cfg(all(feature = "host-details", target_os = "linux"))]
+pub fn query() -> Option<Entropy> {
+    read_entropy(
+        Path::new("/proc/sys/kernel/random"),
+        Path::new("/sys/class/misc/hw_random"),
+        getrandom_would_block(),
+    )
+}
+
+/// Reads the entropy pool and RNG state.
+///
+/// Not available on this platform, so this always returns `None`.
+#[cfg(all(feature = "host-details", not(target_os = "linux")))]
+pub fn query() -> Option<Entropy> {
+    None
+}
+
+/// Reads the pool estimate and size from the `random` directory (normally
+/// `/proc/sys/kernel/random`) and the current hardware RNG from the
+/// `hw_random` directory (normally `/sys/class/misc/hw_random`). A missing
+/// or malformed file leaves its field `None`.
+///
+/// # Returns
+///
+/// `None` if neither the pool nor `getrandom_would_block` is known.
+#[cfg(feature = "host-details")]
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn read_entropy(
+    random: &Path,
+    hw_random: &Path,
+    getrandom_would_block: Option<bool>,
+) -> Option<Entropy> {
+    let number = |name: &str| -> Option<u64> {
+        std::fs::read_to_string(random.join(name))
+            .ok()?
+            .trim()
+            .parse()
+            .ok()
+    };
+    let entropy_avail = number("entropy_avail");
+    let pool_size = number("poolsize");
+    if entropy_avail.is_none() && pool_size.is_none() && getrandom_would_block.is_none() {
+        return None;
+    }
+    let hw_rng = std::fs::read_to_string(hw_random.join("rng_current"))
+        .ok()
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty() && name != "none");
+    Some(Entropy {
+        entropy_avail,
+        pool_size,
+        fixed_estimate: entropy_avail == Some(FIXED_ESTIMATE_BITS)
+            && pool_size == Some(FIXED_ESTIMATE_BITS),
+        getrandom_would_block,
+        hw_rng,
+    })
+}
+
+/// Asks `getrandom(2)` for one byte without blocking.
+///
+/// # Returns
+///
+/// `None` if the C library or kernel lacks `getrandom`.
+#[cfg(all(feature = "host-details", target_os = "linux"))]
+fn getrandom_would_block() -> Option<bool> {
+    use std::ffi::c_void;
+
+    const GRND_NONBLOCK: u32 = 0x1;
+    const EAGAIN: i32 = 11;
+    const EINTR: i32 = 4;
+
+    extern "C" {
+        fn getrandom(buf: *mut c_void, buflen: usize, flags: u32) -> isize;
+    }
+
+    let mut byte = 0u8;
+    loop {
+        // SAFETY: the buffer is one valid, writable byte.
+        let result = unsafe { getrandom(&mut byte as *mut u8 as *mut c_void, 1, GRND_NONBLOCK) };
+        if result >= 0 {
+            return Some(false);
+        }
+        match std::io::Error::last_os_error().raw_os_error() {
+            Some(EINTR) => continue,
+            Some(EAGAIN) => return Some(true),
+            _ => return None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entropy_starved() {
+        let entropy = Entropy {
+            entropy_avail: Some(180),
+            pool_size: Some(4096),
+            getrandom_would_block: Some(false),
+            ..Entropy::default()
+        };
+        assert_eq!(
+            entropy.starved(1000),
+            Some("180 bits of entropy available".to_string())
+        );
+        assert_eq!(entropy.starved(128), None);
+        // The threshold is the lowest acceptable estimate
+        assert_eq!(entropy.starved(180), None);
+        assert!(entropy.starved(181).is_some());
+
+        let modern = Entropy {
+            entropy_avail: Some(256),
+            pool_size: Some(256),
+            fixed_estimate: true,
+            getrandom_would_block: Some(false),
+            ..Entropy::default()
+        };
+        assert_eq!(modern.starved(1000), None);
+
+        let unseeded = Entropy {
+            getrandom_would_block: Some(true),
+            ..modern
+        };
+        assert_eq!(
+            unseeded.starved(1000),
+            Some("getrandom would block".to_string())
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "host-details")]
+    fn test_read_entropy() {
+        let dir = std::env::temp_dir().join(format!("entropy-{}", std::process::id()));
+        let random = dir.join("random");
+        let hw_random = dir.join("hw_random");
+        std::fs::create_dir_all(&random).unwrap();
+        std::fs::create_dir_all(&hw_random).unwrap();
+
+        // An older kernel with a 4096-bit pool and a TPM RNG
+        std::fs::write(random.join("entropy_avail"), "3412\n").unwrap();
+        std::fs::write(random.join("poolsize"), "4096\n").unwrap();
+        std::fs::write(hw_random.join("rng_current"), "tpm-rng-0\n").unwrap();
+        let entropy = read_entropy(&random, &hw_random, Some(false)).unwrap();
+        assert_eq!(entropy.entropy_avail, Some(3412));
+        assert_eq!(entropy.pool_size, Some(4096));
+        assert!(!entropy.fixed_estimate);
+        assert_eq!(entropy.hw_rng.as_deref(), Some("tpm-rng-0"));
+
+        // Linux 5.18 and later, without a hardware RNG
+        std::fs::write(random.join("entropy_avail"), "256\n").unwrap();
+        std::fs::write(random.join("poolsize"), "256\n").unwrap();
+        std::fs::write(hw_random.join("rng_current"), "none\n").unwrap();
+        let entropy = read_entropy(&random, &hw_random, Some(false)).unwrap();
+        assert!(entropy.fixed_estimate);
+        assert_eq!(entropy.hw_rng, None);
+        assert_eq!(entropy.starved(1000), None);
+
+        // Missing files, e.g. without the hw_random driver or in a sandbox
+        std::fs::remove_file(random.join("poolsize")).unwrap();
+        let entropy = read_entropy(&random, &dir.join("missing"), None).unwrap();
+        assert_eq!(entropy.pool_size, None);
+        assert_eq!(entropy.entropy_avail, Some(256));
+        assert!(!entropy.fixed_estimate);
+        assert_eq!(entropy.hw_rng, None);
+        assert_eq!(read_entropy(&dir.join("missing"), &hw_random, None), None);
+
+        // Malformed files count as missing, but getrandom alone is enough
+        std::fs::write(random.join("entropy_avail"), "plenty\n").unwrap();
+        std::fs::write(hw_random.join("rng_current"), "\n").unwrap();
+        let entropy = read_entropy(&random, &hw_random, Some(true)).unwrap();
+        assert_eq!(entropy.entropy_avail, None);
+        assert_eq!(entropy.hw_rng, None);
+        assert_eq!(
+            entropy.starved(1000),
+            Some("getrandom would block".to_string())
+        );
+        assert_eq!(read_entropy(&random, &hw_random, None), None);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,576 @@
+//! Change events for the long-running `--events` mode.
+//!
+//! Each collection is compared against the previous one and only what changed
+//! is emitted, as one JSON object per line (NDJSON):
+//!
+//! ```text
+//! {"timestamp":1760400000,"type":"disk_usage_high","disk":"/data","percent":91.2,"threshold":90.0}
+//! {"timestamp":1760400060,"type":"interface_removed","interface":"eth1"}
+//! {"timestamp":1760400120,"type":"memory_usage_normal","percent":71.5,"threshold":90.0}
+//! ```
+//!
+//! Disk and memory usage thresholds use hysteresis: once a value has risen to
+//! its threshold, the "normal" event is only emitted after it drops a margin
+//! below the threshold, and only then can it fire again. A value oscillating
+//! around the threshold therefore produces a single event.
+
+use crate::batch::Batch;
+use crate::cli::Options;
+use crate::diff::{self, Change};
+#[cfg(feature = "net-sinks")]
+use crate::http;
+use crate::SystemInfo;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+#[cfg(feature = "net-sinks")]
+use std::process::Stdio;
+
+/// A change event with the time it was observed.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Event {
+    /// Seconds since the Unix epoch when the sample was collected
+    pub timestamp: u64,
+    #[serde(flatten)]
+    pub kind: EventKind,
+}
+
+/// What changed. Serialized with a `type` field holding the snake_case variant name.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EventKind {
+    /// A disk's usage rose to or above the threshold
+    DiskUsageHigh {
+        disk: String,
+        percent: f64,
+        threshold: f64,
+    },
+    /// A disk's usage dropped the hysteresis margin below the threshold
+    DiskUsageNormal {
+        disk: String,
+        percent: f64,
+        threshold: f64,
+    },
+    /// Memory usage rose to or above the threshold
+    MemoryUsageHigh { percent: f64, threshold: f64 },
+    /// Memory usage dropped the hysteresis margin below the threshold
+    MemoryUsageNormal { percent: f64, threshold: f64 },
+    /// A disk appeared
+    DiskAdded { disk: String },
+    /// A disk went away
+    DiskRemoved { disk: String },
+    /// A network interface appeared
+    InterfaceAdded { interface: String },
+    /// A network interface went away
+    InterfaceRemoved { interface: String },
+    /// The default gateway changed
+    DefaultGatewayChanged {
+        from: Option<String>,
+        to: Option<String>,
+    },
+}
+
+/// Usage thresholds, in percent, that trigger events.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Thresholds {
+    /// Disk usage at or above which `disk_usage_high` fires
+    pub disk_percent: f64,
+    /// Memory usage at or above which `memory_usage_high` fires
+    pub memory_percent: f64,
+    /// How far below a threshold a value must drop before it re-arms
+    pub hysteresis: f64,
+}
+
+/// Hysteresis state for one thresholded value.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Alarm {
+    /// Below the threshold; rising to it raises the alarm
+    #[default]
+    Armed,
+    /// At or above the threshold; dropping below the margin clears the alarm
+    Raised,
+}
+
+/// A state change reported by `Alarm::update`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Transition {
+    Raised,
+    Cleared,
+}
+
+impl Alarm {
+    /// Feeds a new value into the state machine.
+    ///
+    /// # Returns
+    ///
+    /// The transition taken, or `None` if the state did not change.
+    pub fn update(&mut self, value: f64, threshold: f64, margin: f64) -> Option<Transition> {
+        match self {
+            Alarm::Armed if value >= threshold => {
+                *self = Alarm::Raised;
+                Some(Transition::Raised)
+            }
+            Alarm::Raised if value <= threshold - margin => {
+                *self = Alarm::Armed;
+                Some(Transition::Cleared)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Turns a sequence of snapshots into change events.
+pub struct Monitor {
+    thresholds: Thresholds,
+    previous: Option<SystemInfo>,
+    memory: Alarm,
+    disks: HashMap<String, Alarm>,
+}
+
+impl Monitor {
+    pub fn new(thresholds: Thresholds) -> Monitor {
+        Monitor {
+            thresholds,
+            previous: None,
+            memory: Alarm::default(),
+            disks: HashMap::new(),
+        }
+    }
+
+    /// Records a new snapshot and returns the events it caused.
+    ///
+    /// The first snapshot produces no added/removed events, only threshold
+    /// events for values that already start above their threshold. Structural
+    /// changes are listed before threshold events.
+    pub fn observe(&mut self, info: SystemInfo, timestamp: u64) -> Vec<Event> {
+        let mut kinds = Vec::new();
+
+        if let Some(previous) = &self.previous {
+            for change in diff::diff(previous, &info) {
+                kinds.push(match change {
+                    Change::DiskAdded(disk) => EventKind::DiskAdded { disk },
+                    Change::DiskRemoved(disk) => {
+                        self.disks.remove(&disk);
+                        EventKind::DiskRemoved { disk }
+                    }
+                    Change::InterfaceAdded(interface) => EventKind::InterfaceAdded { interface },
+                    Change::InterfaceRemoved(interface) => {
+                        EventKind::InterfaceRemoved { interface }
+                    }
+                    Change::GatewayChanged { from, to } => {
+                        EventKind::DefaultGatewayChanged { from, to }
+                    }
+                });
+            }
+        }
+
+        let Thresholds {
+            disk_percent,
+            memory_percent,
+            hysteresis,
+        } = self.thresholds;
+
+        if let Some(percent) = percent_of(info.used_memory, info.total_memory) {
+            match self.memory.update(percent, memory_percent, hysteresis) {
+                Some(Transition::Raised) => kinds.push(EventKind::MemoryUsageHigh {
+                    percent,
+                    threshold: memory_percent,
+                }),
+                Some(Transition::Cleared) => kinds.push(EventKind::MemoryUsageNormal {
+                    percent,
+                    threshold: memory_percent,
+                }),
+                None => {}
+            }
+        }
+
+        for disk in &info.disks {
+            let used = disk.total_space.saturating_sub(disk.available_space);
+            let Some(percent) = percent_of(used, disk.total_space) else {
+                continue;
+            };
+            let alarm = self.disks.entry(disk.name.clone()).or_default();
+            match alarm.update(percent, disk_percent, hysteresis) {
+                Some(Transition::Raised) => kinds.push(EventKind::DiskUsageHigh {
+                    disk: disk.name.clone(),
+                    percent,
+                    threshold: disk_percent,
+                }),
+                Some(Transition::Cleared) => kinds.push(EventKind::DiskUsageNormal {
+                    disk: disk.name.clone(),
+                    percent,
+                    threshold: disk_percent,
+                }),
+                None => {}
+            }
+        }
+
+        self.previous = Some(info);
+        kinds
+            .into_iter()
+            .map(|kind| Event { timestamp, kind })
+            .collect()
+    }
+}
+
+/// Returns `part` as a percentage of `total`, rounded to one decimal place,
+/// or `None` if `total` is zero.
+fn percent_of(part: u64, total: u64) -> Option<f64> {
+    if total == 0 {
+        return None;
+    }
+    Some((part as f64 / total as f64 * 1000.0).round() / 10.0)
+}
+
+/// Where events are delivered.
+#[derive(Debug)]
+pub enum Sink {
+    Stdout,
+    /// Appended to a file
+    File(File),
+    /// POSTed to an HTTP(S) URL using `curl`, one event or batch per request
+    #[cfg(feature = "net-sinks")]
+    Webhook(String, http::Client),
+}
+
+impl Sink {
+    /// Opens the sink named by an `--events` target: `-` for stdout, an
+    /// `http://` or `https://` URL for a webhook, posted to with the proxy
+    /// and TLS settings of `options`, or a file path.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be opened for appending, or for a
+    /// URL if the binary was built without the `net-sinks` feature.
+    pub fn open(target: &str, options: &Options) -> io::Result<Sink> {
+        if target == "-" {
+            Ok(Sink::Stdout)
+        } else if target.starts_with("http://") || target.starts_with("https://") {
+            #[cfg(feature = "net-sinks")]
+            return Ok(Sink::Webhook(target.to_string(), options.http_client()));
+            #[cfg(not(feature = "net-sinks"))]
+            {
+                let _ = options;
+                Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "webhook targets are unavailable: compiled without feature 'net-sinks'",
+                ))
+            }
+        } else {
+            let file = OpenOptions::new().create(true).append(true).open(target)?;
+            Ok(Sink::File(file))
+        }
+    }
+
+    /// Whether events go to a webhook, whose delivery failures are only
+    /// warned about.
+    pub fn is_webhook(&self) -> bool {
+        #[cfg(feature = "net-sinks")]
+        return matches!(self, Sink::Webhook(..));
+        #[cfg(not(feature = "net-sinks"))]
+        false
+    }
+
+    /// Delivers one event.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing fails or the webhook does not accept the event.
+    pub fn send(&mut self, event: &Event) -> io::Result<()> {
+        let line = serde_json::to_string(event)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        match self {
+            Sink::Stdout => {
+                let mut stdout = io::stdout().lock();
+                writeln!(stdout, "{}", line)?;
+                stdout.flush()
+            }
+            Sink::File(file) => writeln!(file, "{}", line),
+            #[cfg(feature = "net-sinks")]
+            Sink::Webhook(url, client) => post(client, url, &line),
+        }
+    }
+
+    /// Delivers several events at once.
+    ///
+    /// Webhooks receive a single request with the body
+    /// `{"dropped":N,"events":[...]}`, where `dropped` counts events discarded
+    /// from a full queue since the previous batch. Other sinks write the
+    /// events one per line as `send` does.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing fails or the webhook does not accept the batch.
+    pub fn send_batch(&mut self, batch: &Batch<Event>) -> io::Result<()> {
+        match self {
+            #[cfg(feature = "net-sinks")]
+            Sink::Webhook(url, client) => {
+                let body = serde_json::json!({
+                    "dropped": batch.dropped,
+                    "events": batch.items,
+                });
+                post(client, url, &body.to_string())
+            }
+            _ => batch.items.iter().try_for_each(|event| self.send(event)),
+        }
+    }
+}
+
+/// POSTs `body` as JSON to `url`.
+#[cfg(feature = "net-sinks")]
+fn post(client: &http::Client, url: &str, body: &str) -> io::Result<()> {
+    let mut child = client
+        .curl(url)
+        .args(["-fsS", "-X", "POST", "-H", "Content-Type: application/json"])
+        .args(["--data-binary", "@-", url])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(body.as_bytes())?;
+    }
+    let output = child.wait_with_output()?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{self, disk, network, MockProvider};
+
+    const THRESHOLDS: Thresholds = Thresholds {
+        disk_percent: 90.0,
+        memory_percent: 90.0,
+        hysteresis: 5.0,
+    };
+
+    /// Returns a snapshot with `/data` at `data_percent` usage (or no
+    /// `/data` disk), memory at `memory_percent` and the given interfaces.
+    fn snapshot(data_percent: Option<u64>, memory_percent: u64, interfaces: &[&str]) -> SystemInfo {
+        let mut disks = vec![disk("/", "ext4", 1000, 500)];
+        if let Some(percent) = data_percent {
+            disks.push(disk("/data", "xfs", 1000, 1000 - percent * 10));
+        }
+        mock::snapshot(&MockProvider {
+            total_memory: 1000,
+            used_memory: memory_percent * 10,
+            disks,
+            networks: interfaces.iter().map(|name| network(name, 0, 0)).collect(),
+            ..MockProvider::default()
+        })
+    }
+
+    /// Feeds `snapshots` to a new monitor, one second apart, and returns the
+    /// kinds of events each produced.
+    fn observe_all(snapshots: Vec<SystemInfo>) -> Vec<Vec<EventKind>> {
+        let mut monitor = Monitor::new(THRESHOLDS);
+        snapshots
+            .into_iter()
+            .zip(1..)
+            .map(|(info, timestamp)| {
+                let events = monitor.observe(info, timestamp);
+                assert!(events.iter().all(|event| event.timestamp == timestamp));
+                events.into_iter().map(|event| event.kind).collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_monitor_disk_hysteresis() {
+        let events = observe_all(
+            [
+                Some(85),
+                Some(92),
+                Some(88),
+                Some(91),
+                Some(80),
+                Some(90),
+                None,
+            ]
+            .into_iter()
+            .map(|percent| snapshot(percent, 50, &["mock0"]))
+            .collect(),
+        );
+        let high = |percent| EventKind::DiskUsageHigh {
+            disk: "/data".to_string(),
+            percent,
+            threshold: 90.0,
+        };
+        assert_eq!(
+            events,
+            [
+                vec![],
+                vec![high(92.0)],
+                vec![], // within the margin, so still raised
+                vec![],
+                vec![EventKind::DiskUsageNormal {
+                    disk: "/data".to_string(),
+                    percent: 80.0,
+                    threshold: 90.0,
+                }],
+                vec![high(90.0)],
+                vec![EventKind::DiskRemoved {
+                    disk: "/data".to_string()
+                }],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_monitor_memory_and_interfaces() {
+        let events = observe_all(vec![
+            // Already above the threshold on the first sample
+            snapshot(None, 95, &["mock0", "mock1"]),
+            snapshot(None, 86, &["mock0"]),
+            snapshot(Some(10), 85, &["mock0", "mock2"]),
+        ]);
+        assert_eq!(
+            events,
+            [
+                vec![EventKind::MemoryUsageHigh {
+                    percent: 95.0,
+                    threshold: 90.0
+                }],
+                vec![EventKind::InterfaceRemoved {
+                    interface: "mock1".to_string()
+                }],
+                // Structural changes come before threshold events
+                vec![
+                    EventKind::DiskAdded {
+                        disk: "/data".to_string()
+                    },
+                    EventKind::InterfaceAdded {
+                        interface: "mock2".to_string()
+                    },
+                    EventKind::MemoryUsageNormal {
+                        percent: 85.0,
+                        threshold: 90.0
+                    },
+                ],
+            ]
+        );
+
+        let mut changed = snapshot(None, 50, &["mock0"]);
+        changed.default_gateway = Some("192.0.2.1".to_string());
+        let events = observe_all(vec![snapshot(None, 50, &["mock0"]), changed]);
+        assert_eq!(
+            events[1],
+            [EventKind::DefaultGatewayChanged {
+                from: None,
+                to: Some("192.0.2.1".to_string())
+            }]
+        );
+    }
+
+    #[test]
+    fn test_event_serialize() {
+        let event = |kind| {
+            serde_json::to_string(&Event {
+                timestamp: 1760400000,
+                kind,
+            })
+            .unwrap()
+        };
+        assert_eq!(
+            event(EventKind::DiskUsageHigh {
+                disk: "/data".to_string(),
+                percent: 91.2,
+                threshold: 90.0
+            }),
+            r#"{"timestamp":1760400000,"type":"disk_usage_high","disk":"/data","percent":91.2,"threshold":90.0}"#
+        );
+        assert_eq!(
+            event(EventKind::InterfaceRemoved {
+                interface: "eth1".to_string()
+            }),
+            r#"{"timestamp":1760400000,"type":"interface_removed","interface":"eth1"}"#
+        );
+        assert_eq!(
+            event(EventKind::MemoryUsageNormal {
+                percent: 71.5,
+                threshold: 90.0
+            }),
+            r#"{"timestamp":1760400000,"type":"memory_usage_normal","percent":71.5,"threshold":90.0}"#
+        );
+        assert_eq!(
+            event(EventKind::DefaultGatewayChanged {
+                from: Some("192.0.2.1".to_string()),
+                to: None
+            }),
+            r#"{"timestamp":1760400000,"type":"default_gateway_changed","from":"192.0.2.1","to":null}"#
+        );
+    }
+
+    #[test]
+    fn test_sink_file() {
+        let path =
+            std::env::temp_dir().join(format!("sysinfo-events-{}.ndjson", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let event = |timestamp| Event {
+            timestamp,
+            kind: EventKind::DiskAdded {
+                disk: "/data".to_string(),
+            },
+        };
+
+        let mut sink = Sink::open(path.to_str().unwrap(), &mock::options()).unwrap();
+        assert!(!sink.is_webhook());
+        sink.send(&event(1)).unwrap();
+        sink.send_batch(&Batch {
+            items: vec![event(2), event(3)],
+            dropped: 4,
+        })
+        .unwrap();
+        // Reopening appends
+        let mut sink = Sink::open(path.to_str().unwrap(), &mock::options()).unwrap();
+        sink.send(&event(4)).unwrap();
+
+        let lines = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let timestamps: Vec<u64> = lines
+            .lines()
+            .map(|line| {
+                serde_json::from_str::<serde_json::Value>(line).unwrap()["timestamp"]
+                    .as_u64()
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(timestamps, [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_percent_of() {
+        assert_eq!(percent_of(1, 3), Some(33.3));
+        assert_eq!(percent_of(0, 0), None);
+        assert_eq!(percent_of(u64::MAX, u64::MAX), Some(100.0));
+    }
+
+    #[test]
+    fn test_alarm_update() {
+        let mut alarm = Alarm::default();
+        let updates: Vec<_> = [89.0, 90.0, 89.0, 91.0, 86.0, 85.0, 92.0]
+            .iter()
+            .map(|&value| alarm.update(value, 90.0, 5.0))
+            .collect();
+        assert_eq!(
+            updates,
+            [
+                None,
+                Some(Transition::Raised),
+                None, // still within the margin
+                None,
+                None,
+                Some(Transition::Cleared), // 5 below the threshold
+                Some(Transition::Raised),
+            ]
+        );
+    }
+}
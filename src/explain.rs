@@ -0,0 +1,502 @@
+//! Descriptions of the snapshot's fields (`--explain`).
+//!
+//! Prints what every field of `system_info.json` means and where it comes
+//! from, so the JSON can be interpreted without reading the source. Nested
+//! fields are written as paths, with `[]` marking array elements.
+
+use crate::keys::KeyCase;
+
+/// A documented snapshot field.
+struct Field {
+    /// Path of the field in snake_case, e.g. "disks[].total_space"
+    path: &'static str,
+    /// What the value means, including its unit
+    meaning: &'static str,
+    /// Where the value comes from
+    source: &'static str,
+}
+
+/// Every top-level field, in snapshot order, followed by its nested fields.
+const FIELDS: &[Field] = &[
+    Field {
+        path: "os_name",
+        meaning: "Operating system name, e.g. \"Ubuntu\" or \"Windows\".",
+        source: "sysinfo (/etc/os-release on Linux)",
+    },
+    Field {
+        path: "os_version",
+        meaning: "Operating system version string.",
+        source: "sysinfo",
+    },
+    Field {
+        path: "hostname",
+        meaning: "Host name of the machine.",
+        source: "sysinfo",
+    },
+    Field {
+        path: "machine_id",
+        meaning: "Stable per-installation identifier; null where the OS has none.",
+        source: "/etc/machine-id on Linux, MachineGuid registry value on Windows",
+    },
+    Field {
+        path: "collected_at",
+        meaning: "When collection started, as an RFC 3339 time in UTC, e.g. \
+                  \"2026-10-15T12:34:56Z\"; --timezone only changes how the reports show it.",
+        source: "the system clock",
+    },
+    Field {
+        path: "cpu_cores",
+        meaning: "Number of physical CPU cores (hyperthreads not counted).",
+        source: "sysinfo",
+    },
+    Field {
+        path: "cpu_times",
+        meaning: "Cumulative CPU ticks since boot under `ticks`, and with --cpu-breakdown \
+                  the percentage of CPU time spent in each state (user, system, idle, \
+                  iowait, steal, ...) over the sampling interval, in total and per logical CPU.",
+        source: "/proc/stat on Linux, read again --sample-interval later for the \
+                 breakdown; typeperf on Windows",
+    },
+    Field {
+        path: "cpu_breakdown",
+        meaning: "Percentage of CPU time across all CPUs spent in user, system, idle, \
+                  iowait, irq and the other states over the sampling interval; the same \
+                  shares as cpu_times, null unless the breakdown was sampled.",
+        source: "the same /proc/stat or typeperf sample as cpu_times",
+    },
+    Field {
+        path: "cpu_cache",
+        meaning: "CPU cache sizes in bytes by level, as seen by the first CPU.",
+        source: "/sys/devices/system/cpu/cpu0/cache on Linux, hw.*cachesize sysctls on \
+                 macOS, Win32_Processor on Windows",
+    },
+    Field {
+        path: "total_memory",
+        meaning: "Installed memory usable by the OS, in bytes.",
+        source: "sysinfo",
+    },
+    Field {
+        path: "core_temperatures",
+        meaning: "Temperature of each logical CPU in degrees Celsius, in CPU number order.",
+        source: "coretemp hwmon driver (Linux)",
+    },
+    Field {
+        path: "component_temperatures",
+        meaning: "All temperature sensors in degrees Celsius, reported instead of \
+                  core_temperatures when CPUs cannot be matched to sensors.",
+        source: "sysinfo",
+    },
+    Field {
+        path: "thermal",
+        meaning: "Hottest temperature seen, time above critical and throttling state \
+                  across the samples of an --interval run.",
+        source: "sensors above, plus thermal_throttle counters and cpufreq on Linux",
+    },
+    Field {
+        path: "used_memory",
+        meaning: "Memory in use by applications and the kernel, in bytes. Page cache \
+                  that can be reclaimed on demand is not counted as used.",
+        source: "sysinfo (MemTotal minus MemAvailable from /proc/meminfo on Linux)",
+    },
+    Field {
+        path: "peak_used_memory",
+        meaning: "Highest used_memory seen by any sample of this run, in bytes.",
+        source: "computed across samples",
+    },
+    Field {
+        path: "total_swap",
+        meaning: "Configured swap space, in bytes.",
+        source: "sysinfo",
+    },
+    Field {
+        path: "used_swap",
+        meaning: "Swap space in use, in bytes. Swap that is used but not actively paged \
+                  is harmless; see memory_activity.",
+        source: "sysinfo",
+    },
+    Field {
+        path: "memory_activity",
+        meaning: "Pages swapped in and out and major page faults per second during \
+                  collection (with --memory-activity).",
+        source: "/proc/vmstat on Linux, \\Memory performance counters on Windows",
+    },
+    Field {
+        path: "cgroup_memory",
+        meaning: "Bytes in use by the cgroup this process runs in, e.g. its container, \
+                  without reclaimable inactive page cache, and the cgroup's memory limit \
+                  (null when unlimited); null outside Linux.",
+        source: "memory.current and memory.max (cgroup v2) or memory.usage_in_bytes and \
+                 memory.limit_in_bytes (cgroup v1) below /sys/fs/cgroup",
+    },
+    Field {
+        path: "major_page_faults_per_sec",
+        meaning: "Major page faults (reads from disk) per second since the previous \
+                  sample of an --interval run.",
+        source: "/proc/vmstat (Linux)",
+    },
+    Field {
+        path: "pages_swapped_in_per_sec",
+        meaning: "Pages swapped in from disk per second since the previous sample of an \
+                  --interval run. Sustained swap-in means memory pressure, unlike a high \
+                  used_swap alone.",
+        source: "pswpin in /proc/vmstat (Linux)",
+    },
+    Field {
+        path: "pages_swapped_out_per_sec",
+        meaning: "Pages swapped out to disk per second since the previous sample of an \
+                  --interval run.",
+        source: "pswpout in /proc/vmstat (Linux)",
+    },
+    Field {
+        path: "disks",
+        meaning: "One entry per mounted file system.",
+        source: "sysinfo",
+    },
+    Field {
+        path: "disks[].name",
+        meaning: "Mount point (drive letter on Windows).",
+        source: "sysinfo",
+    },
+    Field {
+        path: "disks[].name_raw",
+        meaning: "Percent-encoded bytes of a mount point that is not valid UTF-8.",
+        source: "sysinfo",
+    },
+    Field {
+        path: "disks[].path_is_lossy",
+        meaning: "Whether name differs from the real mount point (see name_raw).",
+        source: "sysinfo",
+    },
+    Field {
+        path: "disks[].file_system",
+        meaning: "File system type, e.g. \"ext4\" or \"NTFS\".",
+        source: "sysinfo",
+    },
+    Field {
+        path: "disks[].mount_options",
+        meaning: "Options the file system is mounted with, e.g. \"ro\", \"noatime\" or \
+                  \"nodev\"; null where the platform has no mount table.",
+        source: "/proc/mounts on Linux",
+    },
+    Field {
+        path: "disks[].label",
+        meaning: "File system label, if any.",
+        source: "/dev/disk/by-label on Linux, diskutil on macOS, volume label on Windows",
+    },
+    Field {
+        path: "disks[].uuid",
+        meaning: "File system UUID (volume serial number on Windows), if any.",
+        source: "/dev/disk/by-uuid on Linux, diskutil on macOS, volume serial on Windows",
+    },
+    Field {
+        path: "disks[].total_space",
+        meaning: "Size of the file system, in bytes.",
+        source: "sysinfo (statvfs on Unix)",
+    },
+    Field {
+        path: "disks[].available_space",
+        meaning: "Space available to unprivileged users, in bytes. Blocks reserved for \
+                  root are not included, so used plus available can be less than total.",
+        source: "sysinfo (statvfs on Unix)",
+    },
+    Field {
+        path: "disks[].growth_bytes_per_day",
+        meaning: "Growth of used space in bytes per day, fitted to the available space of \
+                  the last 30 runs; negative when space is being freed. Needs at least 3 \
+                  runs with --state-file.",
+        source: "least-squares trend over the --state-file history",
+    },
+    Field {
+        path: "disks[].estimated_days_until_full",
+        meaning: "Days until the file system is full if it keeps growing at \
+                  growth_bytes_per_day, capped at 3650; null unless it is growing.",
+        source: "computed from growth_bytes_per_day and available_space",
+    },
+    Field {
+        path: "disks[].recent_fs_errors",
+        meaning: "Whether the recent kernel log has storage errors for the disk's device \
+                  (with --dmesg-scan).",
+        source: "computed from kernel_errors",
+    },
+    Field {
+        path: "root_disk_usage_percent",
+        meaning: "Used share of the --root-disk file system, in percent.",
+        source: "computed from disks",
+    },
+    Field {
+        path: "largest_paths",
+        meaning: "The --top largest files and directories at most --max-depth levels below \
+                  the --scan-largest path, largest first, each with its size in bytes \
+                  (everything below a directory included), plus the total size and the \
+                  number of entries that could not be read.",
+        source: "a walk of the directory tree that stays on its file system",
+    },
+    Field {
+        path: "total_disk_read_per_sec",
+        meaning: "Bytes read per second from all physical disks since the previous sample \
+                  of an --interval run. Partitions and volumes layered on other disks \
+                  are not counted twice.",
+        source: "/proc/diskstats (Linux)",
+    },
+    Field {
+        path: "total_disk_write_per_sec",
+        meaning: "Bytes written per second to all physical disks since the previous \
+                  sample of an --interval run.",
+        source: "/proc/diskstats (Linux)",
+    },
+    Field {
+        path: "networks",
+        meaning: "One entry per network interface. Counters are totals since boot, \
+                  not rates.",
+        source: "sysinfo",
+    },
+    Field {
+        path: "networks[].name",
+        meaning: "Interface name, e.g. \"eth0\" or \"Ethernet\".",
+        source: "sysinfo",
+    },
+    Field {
+        path: "networks[].name_raw",
+        meaning: "Percent-encoded bytes of an interface name that is not valid UTF-8.",
+        source: "/sys/class/net on Linux",
+    },
+    Field {
+        path: "networks[].name_is_lossy",
+        meaning: "Whether name differs from the real interface name (see name_raw).",
+        source: "/sys/class/net on Linux",
+    },
+    Field {
+        path: "networks[].bytes_received",
+        meaning: "Bytes received since boot.",
+        source: "sysinfo",
+    },
+    Field {
+        path: "networks[].bytes_transmitted",
+        meaning: "Bytes transmitted since boot.",
+        source: "sysinfo",
+    },
+    Field {
+        path: "networks[].packets_received",
+        meaning: "Packets received since boot.",
+        source: "sysinfo",
+    },
+    Field {
+        path: "networks[].packets_transmitted",
+        meaning: "Packets transmitted since boot.",
+        source: "sysinfo",
+    },
+    Field {
+        path: "networks[].errors_received",
+        meaning: "Receive errors since boot.",
+        source: "sysinfo",
+    },
+    Field {
+        path: "networks[].errors_transmitted",
+        meaning: "Transmit errors since boot.",
+        source: "sysinfo",
+    },
+    Field {
+        path: "networks[].drops_received",
+        meaning: "Received packets dropped since boot.",
+        source: "/sys/class/net/<interface>/statistics (Linux)",
+    },
+    Field {
+        path: "networks[].drops_transmitted",
+        meaning: "Packets dropped before transmission since boot.",
+        source: "/sys/class/net/<interface>/statistics (Linux)",
+    },
+    Field {
+        path: "networks[].wireless",
+        meaning: "SSID, signal and link speed of a wireless interface.",
+        source: "iw or /proc/net/wireless on Linux, netsh wlan on Windows",
+    },
+    Field {
+        path: "networks[].kind",
+        meaning: "Role of the interface, e.g. \"ethernet\" or \"loopback\" (with \
+                  --normalize-interfaces).",
+        source: "sysfs on Linux, the interface name elsewhere",
+    },
+    Field {
+        path: "networks[].since_baseline",
+        meaning: "Traffic since the --network-baseline snapshot.",
+        source: "computed from the baseline file",
+    },
+    Field {
+        path: "total_bytes_received",
+        meaning: "Bytes received, summed over the reported interfaces.",
+        source: "computed from networks",
+    },
+    Field {
+        path: "total_bytes_transmitted",
+        meaning: "Bytes transmitted, summed over the reported interfaces.",
+        source: "computed from networks",
+    },
+    Field {
+        path: "rates",
+        meaning: "Bytes and packets per second for every interface and used space growth \
+                  per second for every disk since the previous run, or a note why none were \
+                  computed (with --state-file).",
+        source: "computed from the counters saved in the state file",
+    },
+    Field {
+        path: "default_gateway",
+        meaning: "Gateway of the primary IPv4 default route.",
+        source: "/proc/net/route on Linux, route print on Windows",
+    },
+    Field {
+        path: "dns_servers",
+        meaning: "Configured DNS servers in resolver order.",
+        source: "/etc/resolv.conf on Unix, ipconfig /all on Windows",
+    },
+    Field {
+        path: "clock_offset_ms",
+        meaning: "How far the local clock is ahead of the --check-clock NTP server, in \
+                  milliseconds (negative if behind).",
+        source: "one SNTP query",
+    },
+    Field {
+        path: "sysctls",
+        meaning: "Kernel parameters by name, e.g. \"vm.swappiness\".",
+        source: "/proc/sys (Linux)",
+    },
+    Field {
+        path: "updates",
+        meaning: "Pending OS updates and whether a reboot is required (with --updates).",
+        source: "apt-get, dnf or needs-restarting on Linux, Windows Update on Windows",
+    },
+    Field {
+        path: "security",
+        meaning: "Whether the root volume is encrypted and secure boot is enabled (with \
+                  --security).",
+        source: "lsblk and efivars on Linux, manage-bde and the registry on Windows, \
+                 fdesetup on macOS",
+    },
+    Field {
+        path: "sockets",
+        meaning: "TCP sockets by state, UDP socket count and the remote addresses with \
+                  the most connections (with --sockets-summary).",
+        source: "/proc/net/tcp, tcp6, udp and udp6 on Linux, GetExtendedTcpTable on Windows",
+    },
+    Field {
+        path: "process_states",
+        meaning: "Number of processes in each state: running, sleeping, disk_sleep, idle, \
+                  stopped, traced, zombie, dead or other (with --process-states).",
+        source: "/proc/<pid>/stat on Linux, the process table on macOS and FreeBSD",
+    },
+    Field {
+        path: "kernel_errors",
+        meaning: "ext4, XFS, buffer and block I/O errors and NVMe timeouts among the 2000 \
+                  most recent kernel log records, with the 10 most recent lines, the devices \
+                  they name and the seconds since the last one (with --dmesg-scan).",
+        source: "/dev/kmsg on Linux",
+    },
+    Field {
+        path: "storage_topology",
+        meaning: "Software RAID arrays with their level, size, working, failed and spare \
+                  members and whether they are degraded, and LVM volume groups with their \
+                  physical volumes and active logical volumes (with --storage-topology).",
+        source: "/proc/mdstat and the device mapper devices in /sys/block on Linux",
+    },
+    Field {
+        path: "containers",
+        meaning: "Name, image, memory usage and limit, CPU percent (100 is one whole CPU) and \
+                  network totals of each running container, and the number of stopped ones \
+                  (with --containers).",
+        source: "the Docker or Podman API: /containers/json and /containers/{id}/stats",
+    },
+    Field {
+        path: "kubernetes",
+        meaning: "Node, pod and namespace of the pod this runs in, and the kubelet's cgroup \
+                  driver (systemd or cgroupfs) where the cgroup path shows it; null outside \
+                  Kubernetes.",
+        source: "NODE_NAME, POD_NAME and POD_NAMESPACE, the service account namespace file \
+                 and /proc/self/cgroup",
+    },
+    Field {
+        path: "limits_usage",
+        meaning: "Allocated file handles, processes and threads in use, each with the \
+                  global kernel limit and the percentage used where the platform has one.",
+        source: "/proc/sys/fs/file-nr, /proc/loadavg, kernel.pid_max and kernel.threads-max \
+                 on Linux, performance counters on Windows",
+    },
+    Field {
+        path: "entropy",
+        meaning: "Entropy pool estimate and size in bits, whether the kernel pins them at 256 \
+                  of 256 (fixed_estimate, Linux 5.18 and later), whether getrandom(2) would \
+                  block because the RNG is not yet seeded, and the hardware RNG feeding the \
+                  kernel, if any; null outside Linux.",
+        source: "/proc/sys/kernel/random/entropy_avail and poolsize, \
+                 /sys/class/misc/hw_random/rng_current and a non-blocking getrandom(2) call",
+    },
+    Field {
+        path: "alerts",
+        meaning: "The --compare-thresholds rules that matched this sample, each with its \
+                  name, severity (warn or crit) and the comparison that held.",
+        source: "computed from the rules file and the other fields",
+    },
+    Field {
+        path: "advice",
+        meaning: "Guidance on this sample (with --advise-json), each with the rule that \
+                  gave it (cgroup-oom, swap-pressure or root-disk-full) and a message.",
+        source: "computed from the other fields",
+    },
+    Field {
+        path: "plugins",
+        meaning: "Output, exit status and run time of each --plugin-dir plugin.",
+        source: "the plugins' JSON output",
+    },
+    Field {
+        path: "debug_refresh_info",
+        meaning: "The sysinfo RefreshKind flags the core data was refreshed with, the \
+                  refresh calls they make, and whether the refresh left some kinds of data \
+                  out (with --debug-meta).",
+        source: "this tool's collection strategy",
+    },
+    Field {
+        path: "collection_errors",
+        meaning: "Optional collectors that failed or panicked, as \"<field>: <reason>\", \
+                  sorted; their fields are null and the rest of the snapshot is complete.",
+        source: "the collectors above",
+    },
+];
+
+/// Renders the description of every field, with keys in `case`.
+pub fn render(case: KeyCase) -> String {
+    let mut out = String::new();
+    for field in FIELDS {
+        let path: Vec<String> = field
+            .path
+            .split('.')
+            .map(|segment| case.convert(segment))
+            .collect();
+        out.push_str(&format!(
+            "{}\n  {}\n  Source: {}\n\n",
+            path.join("."),
+            field.meaning,
+            field.source
+        ));
+    }
+    out.pop();
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let text = render(KeyCase::Camel);
+        assert!(text.starts_with("osName\n  Operating system name"));
+        assert!(text.contains("\ndisks[].availableSpace\n"));
+        // every top-level field is described
+        let snapshot = serde_json::to_value(crate::SystemInfo::default()).unwrap();
+        for key in snapshot.as_object().unwrap().keys() {
+            assert!(
+                FIELDS.iter().any(|field| field.path == key),
+                "{} is not described",
+                key
+            );
+        }
+    }
+}
@@ -0,0 +1,774 @@
+//! Alert conditions over the snapshot (`--check-expr`).
+//!
+//! An expression is a condition that should raise an alert, tagged with its
+//! severity:
+//!
+//! ```text
+//! warn: disks["/var"].available_space < 5GB and memory_activity.swap_out_per_sec > 0
+//! crit: not security.root_encryption.enabled
+//! ```
+//!
+//! Field references follow the JSON snapshot: `.name` selects a key,
+//! `[2]` an array element, and `["/var"]` the array element whose `name` is
+//! "/var" (or the key "/var" of an object, e.g. in `sysctls`). Numbers may
+//! carry a byte suffix such as `5GB` or `1.5GiB` (1024-based, like sizes
+//! elsewhere). Conditions combine with `and`, `or` and `not`, in that order
+//! of precedence from loosest to tightest, and with parentheses.
+//!
+//! A reference to a field that is missing or null makes a comparison
+//! unknown. Unknown values follow three-valued logic, so `false and unknown`
+//! is still false; an expression that is unknown as a whole is skipped with a
+//! warning rather than treated as passing or failing.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+
+/// How a condition that holds is reported.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Printed as a warning
+    #[serde(rename = "warn", alias = "warning")]
+    Warning,
+    /// Fails the run
+    #[serde(rename = "crit", alias = "critical")]
+    Critical,
+}
+
+impl Severity {
+    /// Returns the exit code of a run whose worst matched
+    /// `--compare-thresholds` rule has this severity: 3 for a warning and 4
+    /// for a critical rule.
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Severity::Warning => 3,
+            Severity::Critical => 4,
+        }
+    }
+}
+
+/// A parsed `--check-expr` condition.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Check {
+    pub severity: Severity,
+    /// The expression as written, without the severity prefix
+    pub source: String,
+    expr: Expr,
+}
+
+/// Why an expression could not be parsed.
+#[derive(Debug, PartialEq)]
+pub struct ParseError {
+    /// Column (1-based, in characters) where parsing failed
+    pub column: usize,
+    /// What would have been accepted there
+    pub expected: Vec<String>,
+    /// What was found instead
+    pub found: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let expected = match self.expected.as_slice() {
+            [only] => only.clone(),
+            [rest @ .., last] => format!("{} or {}", rest.join(", "), last),
+            [] => "nothing".to_string(),
+        };
+        write!(
+            f,
+            "at column {}: expected {}, found {}",
+            self.column, expected, self.found
+        )
+    }
+}
+
+/// A boolean expression.
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare(Term, &'static str, Term),
+    /// A term used as a condition on its own, e.g. a boolean field
+    Term(Term),
+}
+
+/// A value in an expression.
+#[derive(Debug, Clone, PartialEq)]
+enum Term {
+    Literal(Scalar),
+    /// A field reference, with the text it was written as
+    Field(Vec<Segment>, String),
+}
+
+/// One step of a field reference.
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    /// `.key`
+    Key(String),
+    /// `[index]`
+    Index(usize),
+    /// `["name"]`
+    Name(String),
+}
+
+/// A value a term evaluates to.
+#[derive(Debug, Clone, PartialEq)]
+enum Scalar {
+    Number(f64),
+    Text(String),
+    Bool(bool),
+}
+
+/// Comparison operators, longest first so `<=` is not read as `<`.
+pub const OPERATORS: &[&str] = &["<=", ">=", "==", "!=", "<", ">"];
+
+/// Words with a meaning of their own, which cannot start a field reference.
+const KEYWORDS: &[&str] = &["and", "or", "not", "true", "false"];
+
+impl Check {
+    /// Parses a condition such as `warn: load_average > 4`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `ParseError` pointing at the first token that does not fit
+    /// the grammar, or at the start if the `warn:`/`crit:` prefix is missing.
+    pub fn parse(text: &str) -> Result<Check, ParseError> {
+        let trimmed = text.trim_start();
+        let offset = text.len() - trimmed.len();
+        let (severity, rest) = if let Some(rest) = trimmed.strip_prefix("warn:") {
+            (Severity::Warning, rest)
+        } else if let Some(rest) = trimmed.strip_prefix("crit:") {
+            (Severity::Critical, rest)
+        } else {
+            return Err(ParseError {
+                column: column(text, offset),
+                expected: vec!["warn:".to_string(), "crit:".to_string()],
+                found: describe_text(trimmed),
+            });
+        };
+        let start = text.len() - rest.len();
+
+        let mut parser = Parser {
+            text,
+            tokens: tokenize(text, start)?,
+            index: 0,
+        };
+        let expr = parser.or()?;
+        match parser.peek() {
+            Token::End => Ok(Check {
+                severity,
+                source: rest.trim().to_string(),
+                expr,
+            }),
+            _ => Err(parser.error(&["and", "or", "end of expression"])),
+        }
+    }
+
+    /// Evaluates the condition against a snapshot serialized with snake_case
+    /// keys.
+    ///
+    /// # Returns
+    ///
+    /// Whether the condition holds.
+    ///
+    /// # Errors
+    ///
+    /// Returns why the result is unknown, e.g. a field that does not exist.
+    pub fn evaluate(&self, info: &Value) -> Result<bool, String> {
+        truth(&self.expr, info)
+    }
+}
+
+/// Returns the 1-based character column of byte offset `offset` in `text`.
+fn column(text: &str, offset: usize) -> usize {
+    text[..offset].chars().count() + 1
+}
+
+/// Describes the start of `text` for an error message.
+fn describe_text(text: &str) -> String {
+    match text.split_whitespace().next() {
+        Some(word) => word.to_string(),
+        None => "end of expression".to_string(),
+    }
+}
+
+/// A token and the byte offset it starts at.
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Text(String),
+    Word(String),
+    Operator(&'static str),
+    Open,
+    Close,
+    OpenBracket,
+    CloseBracket,
+    Dot,
+    End,
+}
+
+impl Token {
+    /// Describes the token for an error message.
+    fn describe(&self, text: &str, offset: usize) -> String {
+        match self {
+            Token::End => "end of expression".to_string(),
+            Token::Text(_) => "a string".to_string(),
+            _ => describe_text(&text[offset..]),
+        }
+    }
+}
+
+/// Splits `text`, from byte offset `start` on, into tokens.
+fn tokenize(text: &str, start: usize) -> Result<Vec<(usize, Token)>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut chars = text[start..]
+        .char_indices()
+        .map(|(index, c)| (start + index, c))
+        .peekable();
+    while let Some(&(offset, c)) = chars.peek() {
+        let error = |expected: &str, found: String| ParseError {
+            column: column(text, offset),
+            expected: vec![expected.to_string()],
+            found,
+        };
+        let token = match c {
+            _ if c.is_whitespace() => {
+                chars.next();
+                continue;
+            }
+            '(' | ')' | '[' | ']' | '.' => {
+                chars.next();
+                match c {
+                    '(' => Token::Open,
+                    ')' => Token::Close,
+                    '[' => Token::OpenBracket,
+                    ']' => Token::CloseBracket,
+                    _ => Token::Dot,
+                }
+            }
+            '"' => {
+                chars.next();
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '"')) => break,
+                        Some((_, '\\')) => match chars.next() {
+                            Some((_, escaped)) => value.push(escaped),
+                            None => {
+                                return Err(error("a closing \"", "end of expression".to_string()))
+                            }
+                        },
+                        Some((_, c)) => value.push(c),
+                        None => return Err(error("a closing \"", "end of expression".to_string())),
+                    }
+                }
+                Token::Text(value)
+            }
+            _ if c.is_ascii_digit() || c == '-' => {
+                let mut end = offset;
+                while let Some(&(index, c)) = chars.peek() {
+                    if !(c.is_ascii_alphanumeric() || c == '.' || (index == offset && c == '-')) {
+                        break;
+                    }
+                    end = index + c.len_utf8();
+                    chars.next();
+                }
+                let literal = &text[offset..end];
+                Token::Number(parse_number(literal).ok_or_else(|| {
+                    error(
+                        "a number with a byte suffix such as GB or GiB",
+                        literal.to_string(),
+                    )
+                })?)
+            }
+            _ if c.is_alphabetic() || c == '_' => {
+                let mut word = String::new();
+                while let Some(&(_, c)) = chars.peek() {
+                    if !(c.is_alphanumeric() || c == '_') {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                Token::Word(word)
+            }
+            _ => {
+                let rest = &text[offset..];
+                match OPERATORS.iter().find(|op| rest.starts_with(*op)) {
+                    Some(op) => {
+                        for _ in 0..op.len() {
+                            chars.next();
+                        }
+                        Token::Operator(op)
+                    }
+                    None => {
+                        return Err(ParseError {
+                            column: column(text, offset),
+                            expected: vec![
+                                "a comparison operator".to_string(),
+                                "a value".to_string(),
+                            ],
+                            found: c.to_string(),
+                        })
+                    }
+                }
+            }
+        };
+        tokens.push((offset, token));
+    }
+    tokens.push((text.len(), Token::End));
+    Ok(tokens)
+}
+
+/// Parses a number literal, with an optional byte suffix.
+pub fn parse_number(literal: &str) -> Option<f64> {
+    if literal.ends_with(|c: char| c.is_ascii_digit()) {
+        return literal
+            .parse()
+            .ok()
+            .filter(|number: &f64| number.is_finite());
+    }
+    // Sizes cannot be negative
+    if literal.starts_with('-') {
+        return None;
+    }
+    crate::parse_bytes(literal).map(|bytes| bytes as f64)
+}
+
+/// Recursive-descent parser over the tokens of one expression.
+struct Parser<'a> {
+    text: &'a str,
+    tokens: Vec<(usize, Token)>,
+    index: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.index].1
+    }
+
+    fn next(&mut self) -> Token {
+        let token = self.tokens[self.index].1.clone();
+        if token != Token::End {
+            self.index += 1;
+        }
+        token
+    }
+
+    fn is_word(&self, word: &str) -> bool {
+        matches!(self.peek(), Token::Word(found) if found == word)
+    }
+
+    /// Builds an error at the current token.
+    fn error(&self, expected: &[&str]) -> ParseError {
+        let (offset, token) = &self.tokens[self.index];
+        ParseError {
+            column: column(self.text, *offset),
+            expected: expected.iter().map(|s| s.to_string()).collect(),
+            found: token.describe(self.text, *offset),
+        }
+    }
+
+    /// or := and ("or" and)*
+    fn or(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.and()?;
+        while self.is_word("or") {
+            self.next();
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    /// and := not ("and" not)*
+    fn and(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.not()?;
+        while self.is_word("and") {
+            self.next();
+            expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+        }
+        Ok(expr)
+    }
+
+    /// not := "not" not | "(" or ")" | comparison
+    fn not(&mut self) -> Result<Expr, ParseError> {
+        if self.is_word("not") {
+            self.next();
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        if *self.peek() == Token::Open {
+            self.next();
+            let expr = self.or()?;
+            if *self.peek() != Token::Close {
+                return Err(self.error(&[")", "and", "or"]));
+            }
+            self.next();
+            return Ok(expr);
+        }
+        self.comparison()
+    }
+
+    /// comparison := term (operator term)?
+    fn comparison(&mut self) -> Result<Expr, ParseError> {
+        let left = self.term(&["not", "("])?;
+        match self.peek() {
+            Token::Operator(op) => {
+                let op = *op;
+                self.next();
+                Ok(Expr::Compare(left, op, self.term(&[])?))
+            }
+            Token::Close | Token::End => Ok(Expr::Term(left)),
+            Token::Word(word) if word == "and" || word == "or" => Ok(Expr::Term(left)),
+            _ => {
+                let mut expected = OPERATORS.to_vec();
+                expected.extend(["and", "or"]);
+                Err(self.error(&expected))
+            }
+        }
+    }
+
+    /// term := number | string | "true" | "false" | field
+    ///
+    /// `also` lists other tokens acceptable at this point, for the error.
+    fn term(&mut self, also: &[&str]) -> Result<Term, ParseError> {
+        let start = self.tokens[self.index].0;
+        match self.peek().clone() {
+            Token::Number(number) => {
+                self.next();
+                Ok(Term::Literal(Scalar::Number(number)))
+            }
+            Token::Text(text) => {
+                self.next();
+                Ok(Term::Literal(Scalar::Text(text)))
+            }
+            Token::Word(word) if word == "true" || word == "false" => {
+                self.next();
+                Ok(Term::Literal(Scalar::Bool(word == "true")))
+            }
+            Token::Word(word) if !KEYWORDS.contains(&word.as_str()) => {
+                self.next();
+                let mut path = vec![Segment::Key(word)];
+                loop {
+                    match self.peek() {
+                        Token::Dot => {
+                            self.next();
+                            let Token::Word(key) = self.peek().clone() else {
+                                return Err(self.error(&["a field name"]));
+                            };
+                            self.next();
+                            path.push(Segment::Key(key));
+                        }
+                        Token::OpenBracket => {
+                            self.next();
+                            let segment = match self.peek().clone() {
+                                Token::Text(name) => Segment::Name(name),
+                                Token::Number(index) if index >= 0.0 && index.fract() == 0.0 => {
+                                    Segment::Index(index as usize)
+                                }
+                                _ => return Err(self.error(&["a quoted name", "an index"])),
+                            };
+                            self.next();
+                            if *self.peek() != Token::CloseBracket {
+                                return Err(self.error(&["]"]));
+                            }
+                            self.next();
+                            path.push(segment);
+                        }
+                        _ => break,
+                    }
+                }
+                let end = self.tokens[self.index].0;
+                Ok(Term::Field(
+                    path,
+                    self.text[start..end].trim_end().to_string(),
+                ))
+            }
+            _ => {
+                let mut expected = vec!["a number", "a string", "true", "false", "a field name"];
+                expected.extend(also);
+                Err(self.error(&expected))
+            }
+        }
+    }
+}
+
+/// Evaluates `expr` as a condition, with three-valued `and`/`or`.
+fn truth(expr: &Expr, info: &Value) -> Result<bool, String> {
+    match expr {
+        Expr::Or(left, right) => {
+            let left = truth(left, info);
+            if left == Ok(true) {
+                return Ok(true);
+            }
+            let right = truth(right, info);
+            if right == Ok(true) {
+                return Ok(true);
+            }
+            left.and(right)
+        }
+        Expr::And(left, right) => {
+            let left = truth(left, info);
+            if left == Ok(false) {
+                return Ok(false);
+            }
+            let right = truth(right, info);
+            if right == Ok(false) {
+                return Ok(false);
+            }
+            left.and(right)
+        }
+        Expr::Not(inner) => truth(inner, info).map(|value| !value),
+        Expr::Compare(left, op, right) => compare(&value(left, info)?, op, &value(right, info)?),
+        Expr::Term(term) => match value(term, info)? {
+            Scalar::Bool(value) => Ok(value),
+            _ => Err(format!("{} is not true or false", describe_term(term))),
+        },
+    }
+}
+
+/// Applies the comparison operator `op`.
+fn compare(left: &Scalar, op: &str, right: &Scalar) -> Result<bool, String> {
+    let ordering = match (left, right) {
+        (Scalar::Number(left), Scalar::Number(right)) => left.partial_cmp(right),
+        (Scalar::Text(left), Scalar::Text(right)) if op == "==" || op == "!=" => {
+            Some(left.cmp(right))
+        }
+        (Scalar::Bool(left), Scalar::Bool(right)) if op == "==" || op == "!=" => {
+            Some(left.cmp(right))
+        }
+        _ => {
+            return Err(format!(
+                "cannot compare {} with {}",
+                describe_scalar(left),
+                describe_scalar(right)
+            ))
+        }
+    };
+    let Some(ordering) = ordering else {
+        return Err("cannot compare NaN".to_string());
+    };
+    Ok(match op {
+        "<" => ordering.is_lt(),
+        "<=" => ordering.is_le(),
+        ">" => ordering.is_gt(),
+        ">=" => ordering.is_ge(),
+        "==" => ordering.is_eq(),
+        _ => ordering.is_ne(),
+    })
+}
+
+/// Resolves a term to a scalar.
+fn value(term: &Term, info: &Value) -> Result<Scalar, String> {
+    let (path, text) = match term {
+        Term::Literal(scalar) => return Ok(scalar.clone()),
+        Term::Field(path, text) => (path, text),
+    };
+    let mut current = info;
+    for (depth, segment) in path.iter().enumerate() {
+        let next = match (segment, current) {
+            (Segment::Key(key) | Segment::Name(key), Value::Object(map)) => map.get(key),
+            (Segment::Index(index), Value::Array(items)) => items.get(*index),
+            (Segment::Name(name), Value::Array(items)) => items
+                .iter()
+                .find(|item| item.get("name").and_then(Value::as_str) == Some(name)),
+            _ => None,
+        };
+        let reached = render_path(&path[..=depth]);
+        current = match next {
+            Some(Value::Null) => return Err(format!("{} is null", reached)),
+            Some(next) => next,
+            None => return Err(format!("{} does not exist", reached)),
+        };
+    }
+    match current {
+        Value::Number(number) => number
+            .as_f64()
+            .map(Scalar::Number)
+            .ok_or_else(|| format!("{} is not a number", text)),
+        Value::String(text) => Ok(Scalar::Text(text.clone())),
+        Value::Bool(value) => Ok(Scalar::Bool(*value)),
+        _ => Err(format!("{} is not a single value", text)),
+    }
+}
+
+/// Writes a field reference back as text, e.g. `disks["/var"].name`.
+fn render_path(path: &[Segment]) -> String {
+    let mut text = String::new();
+    for segment in path {
+        match segment {
+            Segment::Key(key) if text.is_empty() => text.push_str(key),
+            Segment::Key(key) => {
+                text.push('.');
+                text.push_str(key);
+            }
+            Segment::Index(index) => text.push_str(&format!("[{}]", index)),
+            Segment::Name(name) => text.push_str(&format!("[{:?}]", name)),
+        }
+    }
+    text
+}
+
+/// Describes a term for an error message.
+fn describe_term(term: &Term) -> String {
+    match term {
+        Term::Field(_, text) => text.clone(),
+        Term::Literal(scalar) => describe_scalar(scalar).to_string(),
+    }
+}
+
+/// Describes the type of a scalar for an error message.
+fn describe_scalar(scalar: &Scalar) -> &'static str {
+    match scalar {
+        Scalar::Number(_) => "a number",
+        Scalar::Text(_) => "a string",
+        Scalar::Bool(_) => "true or false",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_parse() {
+        let check = Check::parse("crit: disks[\"/\"].available_space < 5GB").unwrap();
+        assert_eq!(check.severity, Severity::Critical);
+        assert_eq!(check.source, "disks[\"/\"].available_space < 5GB");
+
+        let error = Check::parse("used_memory > 1GB").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "at column 1: expected warn: or crit:, found used_memory"
+        );
+        let error = Check::parse("warn: used_memory >").unwrap_err();
+        assert_eq!(error.to_string(), "at column 20: expected a number, a string, true, false or a field name, found end of expression");
+        let error = Check::parse("warn: used_memory 5").unwrap_err();
+        assert_eq!(error.column, 19);
+        assert!(error.expected.contains(&"<=".to_string()));
+        let error = Check::parse("warn: (cpu_cores > 4").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "at column 21: expected ), and or or, found end of expression"
+        );
+        let error = Check::parse("warn: disks[\"/var].total_space > 0").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "at column 13: expected a closing \", found end of expression"
+        );
+        let error = Check::parse("warn: used_memory > 5 parsecs").unwrap_err();
+        assert_eq!(error.column, 23);
+        let error = Check::parse("warn: used_memory > 5XB").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "at column 21: expected a number with a byte suffix such as GB or GiB, found 5XB"
+        );
+        // Columns count characters, not bytes
+        let error = Check::parse("warn: os_name == \"Zürich\" x").unwrap_err();
+        assert_eq!(error.column, 27);
+        // Keywords cannot be field names, and a prefix alone is not a condition
+        assert!(Check::parse("warn: and > 1").is_err());
+        assert!(Check::parse("crit:").is_err());
+        assert!(Check::parse("  crit:cpu_cores>4").is_ok());
+    }
+
+    #[test]
+    fn test_check_evaluate() {
+        let info = serde_json::json!({
+            "cpu_cores": 8,
+            "os_name": "Ubuntu",
+            "clock_offset_ms": -40,
+            "disks": [
+                {"name": "/", "available_space": 10737418240u64},
+                {"name": "/var", "available_space": 2147483648u64}
+            ],
+            "sysctls": {"vm.swappiness": "60"},
+            "security": {"secure_boot": {"enabled": false}},
+            "thermal": null
+        });
+        let eval = |text: &str| Check::parse(text).unwrap().evaluate(&info);
+
+        // comparisons, byte suffixes and field references
+        assert_eq!(eval("warn: cpu_cores >= 8"), Ok(true));
+        assert_eq!(
+            eval("warn: disks[\"/var\"].available_space < 5GB"),
+            Ok(true)
+        );
+        assert_eq!(eval("warn: disks[0].available_space < 10GiB"), Ok(false));
+        assert_eq!(eval("warn: disks[0].available_space <= 10GiB"), Ok(true));
+        assert_eq!(eval("warn: clock_offset_ms < -30"), Ok(true));
+        assert_eq!(eval("warn: os_name == \"Ubuntu\""), Ok(true));
+        assert_eq!(eval("warn: sysctls[\"vm.swappiness\"] != \"10\""), Ok(true));
+        assert_eq!(
+            eval("warn: security.secure_boot.enabled == false"),
+            Ok(true)
+        );
+        assert_eq!(eval("warn: not security.secure_boot.enabled"), Ok(true));
+
+        // precedence: not binds tighter than and, and tighter than or
+        assert_eq!(
+            eval("warn: cpu_cores > 99 and cpu_cores > 99 or cpu_cores == 8"),
+            Ok(true)
+        );
+        assert_eq!(
+            eval("warn: cpu_cores == 8 or cpu_cores > 99 and cpu_cores > 99"),
+            Ok(true)
+        );
+        assert_eq!(
+            eval("warn: (cpu_cores == 8 or cpu_cores > 99) and cpu_cores > 99"),
+            Ok(false)
+        );
+        assert_eq!(
+            eval("warn: not cpu_cores > 99 and cpu_cores == 8"),
+            Ok(true)
+        );
+        assert_eq!(
+            eval("warn: not (cpu_cores == 8 and cpu_cores == 8)"),
+            Ok(false)
+        );
+
+        // missing and null fields are unknown, unless the other side decides
+        assert_eq!(
+            eval("warn: disks[\"/data\"].available_space < 5GB"),
+            Err("disks[\"/data\"] does not exist".to_string())
+        );
+        assert_eq!(
+            eval("warn: thermal.max_temp_seen > 90"),
+            Err("thermal is null".to_string())
+        );
+        assert_eq!(
+            eval("warn: not disks[9].name == \"/\""),
+            Err("disks[9] does not exist".to_string())
+        );
+        assert_eq!(eval("warn: cpu_cores > 99 and missing > 1"), Ok(false));
+        assert_eq!(eval("warn: cpu_cores == 8 or missing > 1"), Ok(true));
+        assert!(eval("warn: cpu_cores == 8 and missing > 1").is_err());
+
+        // mismatched types are unknown too
+        assert_eq!(
+            eval("warn: os_name > 3"),
+            Err("cannot compare a string with a number".to_string())
+        );
+        assert_eq!(
+            eval("warn: cpu_cores"),
+            Err("cpu_cores is not true or false".to_string())
+        );
+        assert_eq!(
+            eval("warn: disks > 1"),
+            Err("disks is not a single value".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_number() {
+        assert_eq!(parse_number("80"), Some(80.0));
+        assert_eq!(parse_number("0.5"), Some(0.5));
+        assert_eq!(parse_number("-500"), Some(-500.0));
+        assert_eq!(parse_number("5GB"), Some(5368709120.0));
+        assert_eq!(parse_number("1.5gib"), Some(1610612736.0));
+        assert_eq!(parse_number("2TiB"), Some(2199023255552.0));
+        assert_eq!(parse_number("512B"), Some(512.0));
+        assert_eq!(parse_number("5XB"), None);
+        assert_eq!(parse_number("-5GB"), None);
+        assert_eq!(parse_number("1.2.3"), None);
+    }
+}
@@ -0,0 +1,188 @@
+//! C interface to the collector (`ffi` feature).
+//!
+//! Lets agents written in other languages embed the collector instead of
+//! running the binary. Every function returns a `SysinfoStatus` code, and
+//! panics are caught at the boundary and reported as
+//! `SysinfoStatus::Panic`, since unwinding into C is undefined behavior.
+//! Snapshots are collected with the default options, so each call blocks
+//! for the default sample interval while CPU times are measured.
+//!
+//! The C declarations are in `include/rust_get_system_info.h`, generated
+//! with `cbindgen --config cbindgen.toml`.
+
+use crate::cli::Options;
+use crate::{collect_system_info, msgpack, AppError, SystemProvider};
+use std::ffi::{c_char, CString};
+use std::panic::{self, AssertUnwindSafe};
+use sysinfo::{System, SystemExt};
+
+/// Result of a C interface call. Apart from `Ok`, `NullPointer` and `Panic`,
+/// the codes mirror the collector's errors.
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SysinfoStatus {
+    Ok = 0,
+    FileCreation = 1,
+    FileWrite = 2,
+    JsonSerialization = 3,
+    FileRead = 4,
+    InvalidSpec = 5,
+    EmptyCollection = 6,
+    NicErrors = 7,
+    CheckFailed = 8,
+    KeyNotFound = 9,
+    UnknownMetric = 10,
+    MetricUnavailable = 11,
+    InvalidArgument = 12,
+    // Codes of errors behind features are only defined with their feature,
+    // and stay reserved without it
+    #[cfg(feature = "serve")]
+    Listen = 13,
+    KeyConversion = 14,
+    #[cfg(feature = "serve")]
+    SharedMemory = 15,
+    #[cfg(feature = "self-update")]
+    Update = 16,
+    #[cfg(feature = "s3")]
+    S3Upload = 17,
+    CoreCollection = 18,
+    Timeout = 19,
+    #[cfg(feature = "mqtt")]
+    Mqtt = 20,
+    AlertsRaised = 21,
+    #[cfg_attr(not(windows), allow(dead_code))]
+    Service = 22,
+    NotSelected = 23,
+    /// An output pointer was null
+    NullPointer = 98,
+    /// The collector panicked
+    Panic = 99,
+}
+
+impl From<&AppError> for SysinfoStatus {
+    fn from(error: &AppError) -> SysinfoStatus {
+        match error {
+            AppError::FileCreation(_) => SysinfoStatus::FileCreation,
+            AppError::FileWrite(_) => SysinfoStatus::FileWrite,
+            AppError::JsonSerialization(_) => SysinfoStatus::JsonSerialization,
+            AppError::FileRead(_) => SysinfoStatus::FileRead,
+            AppError::InvalidSpec(_) => SysinfoStatus::InvalidSpec,
+            AppError::EmptyCollection(_) => SysinfoStatus::EmptyCollection,
+            AppError::NicErrors(_) => SysinfoStatus::NicErrors,
+            AppError::CheckFailed(_) => SysinfoStatus::CheckFailed,
+            AppError::AlertsRaised { .. } => SysinfoStatus::AlertsRaised,
+            AppError::KeyNotFound(_) => SysinfoStatus::KeyNotFound,
+            AppError::NotSelected { .. } => SysinfoStatus::NotSelected,
+            AppError::UnknownMetric(_) => SysinfoStatus::UnknownMetric,
+            AppError::MetricUnavailable(_) => SysinfoStatus::MetricUnavailable,
+            AppError::InvalidArgument(_) => SysinfoStatus::InvalidArgument,
+            #[cfg(feature = "serve")]
+            AppError::Listen(_) => SysinfoStatus::Listen,
+            AppError::KeyConversion(_) => SysinfoStatus::KeyConversion,
+            #[cfg(feature = "serve")]
+            AppError::SharedMemory(_) => SysinfoStatus::SharedMemory,
+            #[cfg(windows)]
+            AppError::Service(_) => SysinfoStatus::Service,
+            AppError::CoreCollection(_) => SysinfoStatus::CoreCollection,
+            AppError::Timeout(_) => SysinfoStatus::Timeout,
+            #[cfg(feature = "self-update")]
+            AppError::Update(_) => SysinfoStatus::Update,
+            #[cfg(feature = "s3")]
+            AppError::S3Upload { .. } => SysinfoStatus::S3Upload,
+            #[cfg(feature = "mqtt")]
+            AppError::Mqtt(_) => SysinfoStatus::Mqtt,
+        }
+    }
+}
+
+/// Collects a snapshot as a NUL-terminated JSON string.
+///
+/// On success, `*out_json` points to the string, which the caller must
+/// release with `sysinfo_free_string`; on failure it is set to null.
+///
+/// # Safety
+///
+/// `out_json` must be null or valid for writing a pointer.
+#[no_mangle]
+pub unsafe extern "C" fn sysinfo_collect_json(out_json: *mut *mut c_char) -> i32 {
+    if out_json.is_null() {
+        return SysinfoStatus::NullPointer as i32;
+    }
+    *out_json = std::ptr::null_mut();
+    guard(|| {
+        let json = serde_json::to_string(&snapshot()?).map_err(AppError::JsonSerialization)?;
+        // serde_json escapes NUL, so the JSON cannot contain one
+        let json = CString::new(json).expect("JSON contains no NUL bytes");
+        *out_json = json.into_raw();
+        Ok(())
+    })
+}
+
+/// Collects a snapshot encoded as MessagePack.
+///
+/// On success, `*out` points to `*len` bytes, which the caller must release
+/// with `sysinfo_free_buffer`; on failure they are set to null and 0.
+///
+/// # Safety
+///
+/// `out` and `len` must be null or valid for writing.
+#[no_mangle]
+pub unsafe extern "C" fn sysinfo_collect_msgpack(out: *mut *mut u8, len: *mut usize) -> i32 {
+    if out.is_null() || len.is_null() {
+        return SysinfoStatus::NullPointer as i32;
+    }
+    *out = std::ptr::null_mut();
+    *len = 0;
+    guard(|| {
+        let buffer = msgpack::encode(&snapshot()?).into_boxed_slice();
+        *len = buffer.len();
+        *out = Box::into_raw(buffer).cast::<u8>();
+        Ok(())
+    })
+}
+
+/// Releases a string returned by `sysinfo_collect_json`. Null is ignored.
+///
+/// # Safety
+///
+/// `json` must be null or a string from `sysinfo_collect_json` that has not
+/// been released yet.
+#[no_mangle]
+pub unsafe extern "C" fn sysinfo_free_string(json: *mut c_char) {
+    if !json.is_null() {
+        drop(CString::from_raw(json));
+    }
+}
+
+/// Releases a buffer returned by `sysinfo_collect_msgpack`. Null is ignored.
+///
+/// # Safety
+///
+/// `buffer` must be null or a buffer from `sysinfo_collect_msgpack` that has
+/// not been released yet, and `len` its length.
+#[no_mangle]
+pub unsafe extern "C" fn sysinfo_free_buffer(buffer: *mut u8, len: usize) {
+    if !buffer.is_null() {
+        drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(
+            buffer, len,
+        )));
+    }
+}
+
+/// Collects a snapshot with the default options.
+fn snapshot() -> Result<serde_json::Value, AppError> {
+    let mut sys = System::new();
+    SystemProvider::refresh(&mut sys);
+    let info = collect_system_info(&sys, &Options::default(), None)?;
+    serde_json::to_value(&info).map_err(AppError::JsonSerialization)
+}
+
+/// Runs `call`, turning its error or a panic into a status code.
+fn guard(call: impl FnOnce() -> Result<(), AppError>) -> i32 {
+    let status = match panic::catch_unwind(AssertUnwindSafe(call)) {
+        Ok(Ok(())) => SysinfoStatus::Ok,
+        Ok(Err(error)) => SysinfoStatus::from(&error),
+        Err(_) => SysinfoStatus::Panic,
+    };
+    status as i32
+}
@@ -0,0 +1,224 @@
+//! The `flat` output format: one `key=value` line per scalar value.
+//!
+//! Intended for shell scripts on minimal images without `jq`. Keys are the
+//! dot-separated path to each value in the JSON output:
+//!
+//! ```text
+//! total_memory=34359738368
+//! cpu_times.user=12.5
+//! disk./var.available_space=1234
+//! network.eth0.bytes_received=567
+//! ```
+//!
+//! Elements of arrays whose items have a `name` field (disks, networks) are
+//! keyed by that name under the singular section name; other arrays are keyed
+//! by index. Every array can also be addressed by index under its own field
+//! name, e.g. `disks.0.available_space`, when looking up a single key.
+//!
+//! Values are either raw numbers, `true`/`false`, or double-quoted strings
+//! with `"`, `\`, and newlines backslash-escaped. Null values are omitted. Key segments
+//! are percent-encoded so that characters other than ASCII letters, digits,
+//! `_`, `-`, `/`, and `:` (notably `.`, spaces, `=`, `\`, and `%`) cannot be
+//! confused with the key syntax; `unescape_segment` reverses the encoding.
+
+use serde_json::Value;
+
+/// How elements of arrays are keyed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArrayKeys {
+    /// Key elements by their `name` field when every element has one
+    ByName,
+    /// Key elements by their position
+    ByIndex,
+}
+
+/// Flattens `value` into `(key, value)` pairs.
+///
+/// Object fields are visited in sorted order and array elements in array
+/// order, so the output is stable between runs. Values are rendered as they appear in the flat format: numbers and
+/// booleans raw, strings quoted and escaped.
+pub fn flatten(value: &Value, arrays: ArrayKeys) -> Vec<(String, String)> {
+    let mut entries = Vec::new();
+    walk(value, String::new(), arrays, &mut |path, scalar| {
+        entries.push((path.to_string(), quoted_value(scalar)));
+    });
+    entries
+}
+
+/// Renders `value` as flat-format text, one `key=value` line per entry,
+/// with `prefix` (from `--metric-prefix`) prepended to every key.
+pub fn render(value: &Value, prefix: &str) -> String {
+    flatten(value, ArrayKeys::ByName)
+        .into_iter()
+        .map(|(key, value)| format!("{}{}={}\n", prefix, key, value))
+        .collect()
+}
+
+/// Looks up a single flat key in `value`, in either array keying form.
+///
+/// # Returns
+///
+/// The value with strings unquoted, suitable for printing on its own, or
+/// `None` if no scalar exists at `key`. Escapes in `key` are normalized
+/// first, so `%2e` and `%2E` are equivalent.
+pub fn lookup(value: &Value, key: &str) -> Option<String> {
+    let key = key
+        .split('.')
+        .map(|segment| unescape_segment(segment).map(|s| escape_segment(&s)))
+        .collect::<Option<Vec<_>>>()?
+        .join(".");
+
+    [ArrayKeys::ByName, ArrayKeys::ByIndex]
+        .into_iter()
+        .find_map(|arrays| {
+            let mut found = None;
+            walk(value, String::new(), arrays, &mut |path, scalar| {
+                if found.is_none() && path == key {
+                    found = Some(raw_value(scalar));
+                }
+            });
+            found
+        })
+}
+
+/// Percent-encodes characters in a key segment that have meaning in keys.
+pub fn escape_segment(segment: &str) -> String {
+    let mut escaped = String::with_capacity(segment.len());
+    for byte in segment.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'_' | b'-' | b'/' | b':' => {
+                escaped.push(byte as char)
+            }
+            _ => escaped.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    escaped
+}
+
+/// Reverses `escape_segment`.
+///
+/// Returns `None` if the segment contains an invalid escape or does not
+/// decode to UTF-8.
+pub fn unescape_segment(segment: &str) -> Option<String> {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = segment.get(i + 1..i + 3)?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+/// Calls `visit` with the flat key and value of every non-null scalar.
+fn walk(value: &Value, prefix: String, arrays: ArrayKeys, visit: &mut dyn FnMut(&str, &Value)) {
+    match value {
+        Value::Null => {}
+        Value::Object(map) => {
+            for (key, child) in map {
+                match child {
+                    Value::Array(items) => walk_array(items, &prefix, key, arrays, visit),
+                    _ => walk(child, child_key(&prefix, key), arrays, visit),
+                }
+            }
+        }
+        Value::Array(items) => {
+            for (index, item) in items.iter().enumerate() {
+                walk(item, child_key(&prefix, &index.to_string()), arrays, visit);
+            }
+        }
+        scalar => visit(&prefix, scalar),
+    }
+}
+
+/// Walks the array stored under `field`, keying elements as `arrays` selects.
+fn walk_array(
+    items: &[Value],
+    prefix: &str,
+    field: &str,
+    arrays: ArrayKeys,
+    visit: &mut dyn FnMut(&str, &Value),
+) {
+    let names: Option<Vec<&str>> = items
+        .iter()
+        .map(|item| item.get("name").and_then(Value::as_str))
+        .collect();
+
+    match names {
+        Some(names) if arrays == ArrayKeys::ByName && !names.is_empty() => {
+            let singular = field.strip_suffix('s').unwrap_or(field);
+            for (item, name) in items.iter().zip(names) {
+                let path = child_key(&child_key(prefix, singular), name);
+                walk(item, path, arrays, visit);
+            }
+        }
+        _ => {
+            for (index, item) in items.iter().enumerate() {
+                let path = child_key(&child_key(prefix, field), &index.to_string());
+                walk(item, path, arrays, visit);
+            }
+        }
+    }
+}
+
+/// Appends the escaped `segment` to the key `prefix`.
+fn child_key(prefix: &str, segment: &str) -> String {
+    if prefix.is_empty() {
+        escape_segment(segment)
+    } else {
+        format!("{}.{}", prefix, escape_segment(segment))
+    }
+}
+
+/// Renders a scalar for a `key=value` line.
+fn quoted_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => format!(
+            "\"{}\"",
+            s.replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n")
+        ),
+        other => other.to_string(),
+    }
+}
+
+/// Renders a scalar on its own, without quoting strings.
+fn raw_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let value =
+            serde_json::json!({"total_memory": 1024, "disks": [{"name": "/", "total_space": 5}]});
+        assert_eq!(
+            render(&value, ""),
+            "disk./.name=\"/\"\ndisk./.total_space=5\ntotal_memory=1024\n"
+        );
+        assert_eq!(
+            render(&value, "rgsi_"),
+            "rgsi_disk./.name=\"/\"\nrgsi_disk./.total_space=5\nrgsi_total_memory=1024\n"
+        );
+    }
+
+    #[test]
+    fn test_escape_segment() {
+        assert_eq!(escape_segment("/var"), "/var");
+        assert_eq!(escape_segment("/mnt/my disk.img"), "/mnt/my%20disk%2Eimg");
+        assert_eq!(escape_segment("C:\\"), "C:%5C");
+    }
+}
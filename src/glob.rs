@@ -0,0 +1,98 @@
+//! Shell-style wildcard matching for `--disk` and `--interface`.
+//!
+//! `*` matches any run of characters within one path component and `?`
+//! matches one character; every other character, including `\`, matches
+//! itself, so Windows paths such as `C:\` need no escaping. Mount points
+//! and interface names are case-insensitive on Windows, so the names are
+//! compared ignoring ASCII case there.
+
+/// Returns whether `name` matches any of `patterns`.
+pub fn matches_any(patterns: &[String], name: &str) -> bool {
+    patterns
+        .iter()
+        .any(|pattern| matches(pattern, name, cfg!(windows)))
+}
+
+/// Returns whether `name` matches the wildcard `pattern`, comparing ASCII
+/// letters without case if `ignore_case` is set. A `*` never matches `/`
+/// or `\`, so `/srv/*` matches `/srv/data` but not `/srv/data/cache`.
+pub fn matches(pattern: &str, name: &str, ignore_case: bool) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let same = |a: char, b: char| a == b || (ignore_case && a.eq_ignore_ascii_case(&b));
+    let separator = |c: char| c == '/' || c == '\\';
+
+    // Match greedily, going back to the last `*` on a mismatch; the `*`
+    // may only take more characters up to the next separator
+    let (mut p, mut n) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if (c == '?' && !separator(name[n])) || same(c, name[n]) => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) if !separator(name[star_n]) => {
+                    star = Some((star_p, star_n + 1));
+                    p = star_p + 1;
+                    n = star_n + 1;
+                }
+                _ => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches() {
+        assert!(matches("/data", "/data", false));
+        assert!(!matches("/data", "/data2", false));
+        assert!(matches("/srv/*", "/srv/data", false));
+        assert!(matches("/srv/*", "/srv/", false));
+        assert!(!matches("/srv/*", "/srv", false));
+        assert!(!matches("/srv/*", "/srv/data/cache", false));
+        assert!(matches("/srv/*/*", "/srv/data/cache", false));
+        assert!(matches("/mnt/disk?", "/mnt/disk1", false));
+        assert!(!matches("/mnt/disk?", "/mnt/disk10", false));
+        assert!(matches("*", "eth0", false));
+        assert!(matches("en*s*", "enp0s31f6", false));
+        assert!(!matches("eth*", "veth1a2b", false));
+
+        // Windows drive letters and paths; a backslash is not an escape
+        assert!(matches("C:\\", "C:\\", false));
+        assert!(!matches("C:\\", "D:\\", false));
+        assert!(matches("?:\\", "D:\\", false));
+        assert!(matches("c:\\", "C:\\", true));
+        assert!(!matches("c:\\", "C:\\", false));
+        assert!(matches("C:\\Mount\\*", "C:\\Mount\\Backup", true));
+        assert!(!matches("C:\\*", "C:\\Mount\\Backup", true));
+        assert!(matches("Ethernet*", "ethernet 2", true));
+        assert!(matches("*:\\", "E:\\", false));
+        assert!(!matches("?:\\", "C:", false));
+        assert!(!matches("C:", "C:\\", false));
+    }
+
+    #[test]
+    fn test_matches_any() {
+        let patterns = ["/".to_string(), "/srv/*".to_string()];
+        assert!(matches_any(&patterns, "/"));
+        assert!(matches_any(&patterns, "/srv/data"));
+        assert!(!matches_any(&patterns, "/var"));
+        assert!(!matches_any(&[], "/"));
+        // Only Windows compares without case
+        assert_eq!(
+            matches_any(&["/SRV/*".to_string()], "/srv/data"),
+            cfg!(windows)
+        );
+    }
+}
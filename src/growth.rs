@@ -0,0 +1,135 @@
+//! Disk growth trends and days-until-full projections.
+//!
+//! Capacity planning wants "at the current rate, /var fills in 9 days"
+//! rather than a usage percentage. The trend is a least-squares line
+//! through a disk's available space over the recent runs kept in the state
+//! file, so runs need not be evenly spaced, and one unusual sample, such as
+//! a large temporary file, moves the line less than it would a rate
+//! between two runs.
+
+/// Fewest samples a trend is fitted to.
+#[cfg(feature = "history")]
+const MIN_SAMPLES: usize = 3;
+
+/// Longest projection reported, in days (about ten years).
+pub const MAX_DAYS_UNTIL_FULL: f64 = 3650.0;
+
+#[cfg(feature = "history")]
+const MS_PER_DAY: f64 = 86_400_000.0;
+
+/// The growth trend of one disk.
+#[cfg(feature = "history")]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Projection {
+    /// Growth of used space in bytes per day, negative when space is freed
+    pub growth_bytes_per_day: f64,
+    /// Days until the disk is full at that growth, capped at
+    /// `MAX_DAYS_UNTIL_FULL`; `None` unless the disk is filling up
+    pub days_until_full: Option<f64>,
+}
+
+/// Fits a trend to `samples` of `[timestamp_ms, available_space]`, in any
+/// order, and projects when the disk fills up from the available space of
+/// the latest sample.
+///
+/// # Returns
+///
+/// `None` with fewer than 3 samples, or if they were all taken at the same
+/// time.
+#[cfg(feature = "history")]
+pub fn project(samples: &[[u64; 2]]) -> Option<Projection> {
+    if samples.len() < MIN_SAMPLES {
+        return None;
+    }
+    // Relative to the first sample, to keep the sums small
+    let origin = samples[0][0] as f64;
+    let points: Vec<(f64, f64)> = samples
+        .iter()
+        .map(|&[timestamp_ms, available]| {
+            (
+                (timestamp_ms as f64 - origin) / MS_PER_DAY,
+                available as f64,
+            )
+        })
+        .collect();
+    let count = points.len() as f64;
+    let mean_x = points.iter().map(|&(x, _)| x).sum::<f64>() / count;
+    let mean_y = points.iter().map(|&(_, y)| y).sum::<f64>() / count;
+    let (covariance, variance) = points.iter().fold((0.0, 0.0), |(cov, var), &(x, y)| {
+        let dx = x - mean_x;
+        (cov + dx * (y - mean_y), var + dx * dx)
+    });
+    if variance == 0.0 {
+        return None;
+    }
+
+    // Available space shrinks as used space grows; a flat trend is 0, not -0
+    let growth_bytes_per_day = if covariance == 0.0 {
+        0.0
+    } else {
+        -covariance / variance
+    };
+    let latest = samples
+        .iter()
+        .max_by_key(|&&[timestamp_ms, _]| timestamp_ms)?[1];
+    let days_until_full = (growth_bytes_per_day > 0.0)
+        .then(|| (latest as f64 / growth_bytes_per_day).min(MAX_DAYS_UNTIL_FULL));
+    Some(Projection {
+        growth_bytes_per_day,
+        days_until_full,
+    })
+}
+
+#[cfg(all(test, feature = "history"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_project() {
+        const DAY: u64 = 86_400_000;
+        const GB: u64 = 1_000_000_000;
+
+        // irregular spacing: 10 GB a day are used up
+        let samples = [[0, 100 * GB], [DAY, 90 * GB], [3 * DAY, 70 * GB]];
+        let projection = project(&samples).unwrap();
+        assert_eq!(projection.growth_bytes_per_day.round(), (10 * GB) as f64);
+        assert_eq!(projection.days_until_full.map(f64::round), Some(7.0));
+
+        // the least-squares line evens out noise
+        let samples = [
+            [0, 100 * GB],
+            [DAY, 92 * GB],
+            [2 * DAY, 78 * GB],
+            [3 * DAY, 70 * GB],
+        ];
+        let projection = project(&samples).unwrap();
+        assert_eq!(projection.growth_bytes_per_day.round(), 10.4e9);
+
+        // flat or shrinking usage never fills the disk
+        let flat = project(&[[0, GB], [DAY, GB], [2 * DAY, GB]]).unwrap();
+        assert_eq!(
+            (flat.growth_bytes_per_day, flat.days_until_full),
+            (0.0, None)
+        );
+        let freed = project(&[[0, GB], [DAY, 2 * GB], [2 * DAY, 3 * GB]]).unwrap();
+        assert!(freed.growth_bytes_per_day < 0.0 && freed.days_until_full.is_none());
+
+        // very slow growth is capped
+        let slow = project(&[[0, 100 * GB], [DAY, 100 * GB - 1], [2 * DAY, 100 * GB - 2]]).unwrap();
+        assert_eq!(slow.days_until_full, Some(MAX_DAYS_UNTIL_FULL));
+
+        // samples in any order project from the latest one
+        let samples = [[3 * DAY, 70 * GB], [0, 100 * GB], [DAY, 90 * GB]];
+        assert_eq!(
+            project(&samples),
+            project(&[[0, 100 * GB], [DAY, 90 * GB], [3 * DAY, 70 * GB]])
+        );
+        // a full disk is full now
+        let full = project(&[[0, 2 * GB], [DAY, GB], [2 * DAY, 0]]).unwrap();
+        assert_eq!(full.days_until_full, Some(0.0));
+
+        // too few samples, or no time between them
+        assert_eq!(project(&[[0, 100 * GB], [DAY, 90 * GB]]), None);
+        assert_eq!(project(&[[DAY, 3 * GB], [DAY, 2 * GB], [DAY, GB]]), None);
+    }
+}
@@ -0,0 +1,145 @@
+//! Interface roles for `--normalize-interfaces`.
+//!
+//! Interface names differ between platforms ("eth0", "enp3s0", "Ethernet 2",
+//! "en0"), so each interface is tagged with a role fleet tooling can group
+//! by. On Linux the role comes from sysfs: the loopback device type, the
+//! wireless subdirectory, and whether the interface is backed by hardware at
+//! all. Elsewhere it is guessed from the name.
+
+use serde::{Deserialize, Serialize};
+use std::ffi::OsStr;
+
+/// The role of a network interface.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum InterfaceKind {
+    Ethernet,
+    Wifi,
+    Loopback,
+    /// Bridges, tunnels, container and VM interfaces
+    Virtual,
+}
+
+impl InterfaceKind {
+    /// Returns the name used in JSON, e.g. "wifi".
+    pub fn as_str(self) -> &'static str {
+        match self {
+            InterfaceKind::Ethernet => "ethernet",
+            InterfaceKind::Wifi => "wifi",
+            InterfaceKind::Loopback => "loopback",
+            InterfaceKind::Virtual => "virtual",
+        }
+    }
+}
+
+/// Infers the role of `interface`; `wireless` is whether wireless link
+/// details were found for it.
+#[cfg(target_os = "linux")]
+pub fn infer(interface: &OsStr, wireless: bool) -> Option<InterfaceKind> {
+    use std::path::Path;
+
+    /// ARPHRD_LOOPBACK from `<linux/if_arp.h>`
+    const ARPHRD_LOOPBACK: &str = "772";
+
+    let sys_path = Path::new("/sys/class/net").join(interface);
+    if !sys_path.exists() {
+        return from_name(&interface.to_string_lossy(), wireless);
+    }
+    let device_type = std::fs::read_to_string(sys_path.join("type")).unwrap_or_default();
+    Some(if device_type.trim() == ARPHRD_LOOPBACK {
+        InterfaceKind::Loopback
+    } else if wireless || sys_path.join("wireless").exists() || sys_path.join("phy80211").exists() {
+        InterfaceKind::Wifi
+    } else if !sys_path.join("device").exists() {
+        // Only interfaces backed by a bus device have a `device` link
+        InterfaceKind::Virtual
+    } else {
+        InterfaceKind::Ethernet
+    })
+}
+
+/// Infers the role of `interface`; `wireless` is whether wireless link
+/// details were found for it.
+#[cfg(not(target_os = "linux"))]
+pub fn infer(interface: &OsStr, wireless: bool) -> Option<InterfaceKind> {
+    from_name(&interface.to_string_lossy(), wireless)
+}
+
+/// Guesses the role of an interface from its name.
+///
+/// # Returns
+///
+/// `None` if the name matches no known pattern.
+fn from_name(interface: &str, wireless: bool) -> Option<InterfaceKind> {
+    const VIRTUAL_PREFIXES: &[&str] = &[
+        "vethernet",
+        "veth",
+        "virbr",
+        "vmnet",
+        "vboxnet",
+        "docker",
+        "br-",
+        "bridge",
+        "tun",
+        "tap",
+        "utun",
+        "awdl",
+        "llw",
+        "wg",
+        "zt",
+        "tailscale",
+    ];
+
+    let name = interface.to_ascii_lowercase();
+    if wireless {
+        return Some(InterfaceKind::Wifi);
+    }
+    if name == "lo" || name.starts_with("lo0") || name.contains("loopback") {
+        Some(InterfaceKind::Loopback)
+    } else if VIRTUAL_PREFIXES
+        .iter()
+        .any(|prefix| name.starts_with(prefix))
+        || name.contains("virtual")
+    {
+        Some(InterfaceKind::Virtual)
+    } else if ["wi-fi", "wifi", "wlan", "wlp", "wireless"]
+        .iter()
+        .any(|pattern| name.contains(pattern))
+    {
+        Some(InterfaceKind::Wifi)
+    } else if ["ethernet", "eth", "en"]
+        .iter()
+        .any(|prefix| name.starts_with(prefix))
+    {
+        Some(InterfaceKind::Ethernet)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_name() {
+        assert_eq!(
+            from_name("Ethernet 2", false),
+            Some(InterfaceKind::Ethernet)
+        );
+        assert_eq!(from_name("Wi-Fi", false), Some(InterfaceKind::Wifi));
+        assert_eq!(
+            from_name("Loopback Pseudo-Interface 1", false),
+            Some(InterfaceKind::Loopback)
+        );
+        assert_eq!(from_name("lo0", false), Some(InterfaceKind::Loopback));
+        assert_eq!(
+            from_name("vEthernet (WSL)", false),
+            Some(InterfaceKind::Virtual)
+        );
+        assert_eq!(from_name("utun3", false), Some(InterfaceKind::Virtual));
+        assert_eq!(from_name("en0", true), Some(InterfaceKind::Wifi));
+        assert_eq!(from_name("en0", false), Some(InterfaceKind::Ethernet));
+        assert_eq!(from_name("ppp0", false), None);
+    }
+}
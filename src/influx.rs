@@ -0,0 +1,344 @@
+//! The `influx` output format: InfluxDB line protocol.
+//!
+//! Each snapshot becomes one `system` line, a `cpu` line when the CPU time
+//! breakdown was sampled, a `processes` line of state counts with
+//! `--process-states`, and one `disk` and `network` line per disk and
+//! interface, all tagged with the hostname (and in a Kubernetes pod with the
+//! node, pod and namespace) and sharing one timestamp in nanoseconds since
+//! the Unix epoch:
+//!
+//! ```text
+//! system,host=db-1 cpu_cores=16i,total_memory=68719476736i,used_memory=... 1760400000000000000
+//! cpu,host=db-1 user=12.5,system=3.1,idle=83.9,iowait=0.5 1760400000000000000
+//! disk,host=db-1,mount=/data,fs=ext4 total_space=...i,available_space=...i,used_space=...i 1760400000000000000
+//! network,host=db-1,interface=eth0 bytes_received=...i,... 1760400000000000000
+//! ```
+//!
+//! Byte and packet counters are integer fields (suffixed with `i`), rates and
+//! percentages are floats, and values that were not collected are omitted.
+//! The output can be piped straight into `influx write`. `--metric-prefix`
+//! namespaces the measurement names, e.g. `rgsi_system`.
+
+use crate::SystemInfo;
+
+/// A field value with its line protocol type.
+///
+/// Unsigned values are written as signed integers, capped at `i64::MAX`:
+/// the `u` suffix for unsigned fields is not accepted by InfluxDB 1.x
+/// without `influxdb.unsigned` enabled, and a counter that large only comes
+/// from a broken or wrapped source.
+enum Field {
+    Integer(u64),
+    SignedInteger(i64),
+    Float(f64),
+    Text(String),
+}
+
+/// Renders `info` as line protocol, one line per measurement, with
+/// `prefix` (from `--metric-prefix`) prepended to each measurement name.
+pub fn render(info: &SystemInfo, prefix: &str, timestamp_ns: u128) -> String {
+    let measurement = |name: &str| format!("{}{}", prefix, name);
+    let host = host_tags(info);
+    let mut lines = Vec::new();
+
+    let mut system = vec![
+        ("os_name", Field::Text(info.os_name.clone())),
+        ("os_version", Field::Text(info.os_version.clone())),
+        ("cpu_cores", Field::Integer(info.cpu_cores as u64)),
+        ("total_memory", Field::Integer(info.total_memory)),
+        ("used_memory", Field::Integer(info.used_memory)),
+        ("total_swap", Field::Integer(info.total_swap)),
+        ("used_swap", Field::Integer(info.used_swap)),
+        (
+            "total_bytes_received",
+            Field::Integer(info.total_bytes_received),
+        ),
+        (
+            "total_bytes_transmitted",
+            Field::Integer(info.total_bytes_transmitted),
+        ),
+    ];
+    if let Some(peak) = info.peak_used_memory {
+        system.push(("peak_used_memory", Field::Integer(peak)));
+    }
+    if let Some(offset) = info.clock_offset_ms {
+        system.push(("clock_offset_ms", Field::SignedInteger(offset)));
+    }
+    if let Some(percent) = info.root_disk_usage_percent {
+        system.push(("root_disk_usage_percent", Field::Float(percent)));
+    }
+    if let Some(read) = info.total_disk_read_per_sec {
+        system.push(("total_disk_read_per_sec", Field::Float(read)));
+    }
+    if let Some(written) = info.total_disk_write_per_sec {
+        system.push(("total_disk_write_per_sec", Field::Float(written)));
+    }
+    if let Some(limits) = &info.limits_usage {
+        for (name, usage) in [
+            ("open_files", &limits.open_files),
+            ("processes", &limits.processes),
+            ("threads", &limits.threads),
+        ] {
+            if let Some(usage) = usage {
+                system.push((name, Field::Integer(usage.used)));
+            }
+        }
+    }
+    if let Some(faults) = info.major_page_faults_per_sec {
+        system.push(("major_page_faults_per_sec", Field::Float(faults)));
+    }
+    if let Some(pages) = info.pages_swapped_in_per_sec {
+        system.push(("pages_swapped_in_per_sec", Field::Float(pages)));
+    }
+    if let Some(pages) = info.pages_swapped_out_per_sec {
+        system.push(("pages_swapped_out_per_sec", Field::Float(pages)));
+    }
+    if let Some(activity) = &info.memory_activity {
+        system.push(("swap_in_per_sec", Field::Float(activity.swap_in_per_sec)));
+        system.push(("swap_out_per_sec", Field::Float(activity.swap_out_per_sec)));
+        system.push((
+            "major_faults_per_sec",
+            Field::Float(activity.major_faults_per_sec),
+        ));
+    }
+    lines.push(line(&measurement("system"), &host, system, timestamp_ns));
+
+    if let Some(times) = &info.cpu_times {
+        let total = &times.total;
+        let cpu: Vec<(&str, Field)> = [
+            ("user", total.user),
+            ("nice", total.nice),
+            ("system", total.system),
+            ("idle", total.idle),
+            ("iowait", total.iowait),
+            ("irq", total.irq),
+            ("softirq", total.softirq),
+            ("steal", total.steal),
+        ]
+        .into_iter()
+        .filter_map(|(name, percent)| Some((name, Field::Float(percent?))))
+        .collect();
+        lines.push(line(&measurement("cpu"), &host, cpu, timestamp_ns));
+    }
+
+    if let Some(states) = &info.process_states {
+        let states: Vec<(&str, Field)> = states
+            .iter()
+            .map(|(state, count)| (state.as_str(), Field::Integer(*count as u64)))
+            .collect();
+        lines.push(line(&measurement("processes"), &host, states, timestamp_ns));
+    }
+
+    for disk in &info.disks {
+        let tags = [
+            host.as_slice(),
+            &[
+                ("mount", disk.name.as_str()),
+                ("fs", disk.file_system.as_str()),
+            ],
+        ]
+        .concat();
+        let mut fields = vec![
+            ("total_space", Field::Integer(disk.total_space)),
+            ("available_space", Field::Integer(disk.available_space)),
+            (
+                "used_space",
+                Field::Integer(disk.total_space.saturating_sub(disk.available_space)),
+            ),
+        ];
+        if let Some(growth) = disk.growth_bytes_per_day {
+            fields.push(("growth_bytes_per_day", Field::Float(growth)));
+        }
+        if let Some(days) = disk.estimated_days_until_full {
+            fields.push(("estimated_days_until_full", Field::Float(days)));
+        }
+        lines.push(line(&measurement("disk"), &tags, fields, timestamp_ns));
+    }
+
+    for network in &info.networks {
+        let tags = [
+            host.as_slice(),
+            &[
+                ("interface", network.name.as_str()),
+                ("kind", network.kind.map_or("", |kind| kind.as_str())),
+            ],
+        ]
+        .concat();
+        let mut fields = vec![
+            ("bytes_received", Field::Integer(network.bytes_received)),
+            (
+                "bytes_transmitted",
+                Field::Integer(network.bytes_transmitted),
+            ),
+            ("packets_received", Field::Integer(network.packets_received)),
+            (
+                "packets_transmitted",
+                Field::Integer(network.packets_transmitted),
+            ),
+            ("errors_received", Field::Integer(network.errors_received)),
+            (
+                "errors_transmitted",
+                Field::Integer(network.errors_transmitted),
+            ),
+        ];
+        if let Some(drops) = network.drops_received {
+            fields.push(("drops_received", Field::Integer(drops)));
+        }
+        if let Some(drops) = network.drops_transmitted {
+            fields.push(("drops_transmitted", Field::Integer(drops)));
+        }
+        if let Some(delta) = &network.since_baseline {
+            fields.push((
+                "bytes_received_since_baseline",
+                Field::Integer(delta.bytes_received),
+            ));
+            fields.push((
+                "bytes_transmitted_since_baseline",
+                Field::Integer(delta.bytes_transmitted),
+            ));
+        }
+        lines.push(line(&measurement("network"), &tags, fields, timestamp_ns));
+    }
+
+    lines.into_iter().flatten().collect()
+}
+
+/// Tags of every line: the hostname, and in a Kubernetes pod the node, pod
+/// and namespace.
+fn host_tags(info: &SystemInfo) -> Vec<(&str, &str)> {
+    let mut tags = vec![("host", info.hostname.as_str())];
+    if let Some(kubernetes) = &info.kubernetes {
+        for (key, value) in [
+            ("node", &kubernetes.node_name),
+            ("pod", &kubernetes.pod_name),
+            ("namespace", &kubernetes.namespace),
+        ] {
+            if let Some(value) = value {
+                tags.push((key, value.as_str()));
+            }
+        }
+    }
+    tags
+}
+
+/// Formats one line, or `None` if there are no fields to write.
+///
+/// Tags with empty values are left out, since line protocol does not allow
+/// them.
+fn line(
+    measurement: &str,
+    tags: &[(&str, &str)],
+    fields: Vec<(&str, Field)>,
+    timestamp_ns: u128,
+) -> Option<String> {
+    let fields: Vec<String> = fields
+        .into_iter()
+        .filter_map(|(key, value)| {
+            let value = match value {
+                Field::Integer(value) => format!("{}i", value.min(i64::MAX as u64)),
+                Field::SignedInteger(value) => format!("{}i", value),
+                // Line protocol has no representation for NaN or infinity
+                Field::Float(value) if !value.is_finite() => return None,
+                Field::Float(value) => format!("{}", value),
+                Field::Text(value) => {
+                    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+                }
+            };
+            Some(format!("{}={}", escape(key), value))
+        })
+        .collect();
+    if fields.is_empty() {
+        return None;
+    }
+
+    let mut text = escape(measurement);
+    for (key, value) in tags {
+        if !value.is_empty() {
+            text.push_str(&format!(",{}={}", escape(key), escape(value)));
+        }
+    }
+    Some(format!("{} {} {}\n", text, fields.join(","), timestamp_ns))
+}
+
+/// Escapes a measurement name, tag key, tag value, or field key.
+///
+/// Commas, equals signs, spaces, and backslashes are backslash-escaped, and
+/// line breaks (which cannot be escaped) are replaced by spaces.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            ',' | '=' | ' ' | '\\' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' | '\r' => escaped.push_str("\\ "),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        // In a Kubernetes pod, every line also carries the node, pod and
+        // namespace:
+        let info = SystemInfo {
+            hostname: "web-1".to_string(),
+            kubernetes: Some(crate::kubernetes::KubernetesInfo {
+                node_name: Some("worker 3".to_string()),
+                pod_name: Some("odd\"pod\\name".to_string()),
+                namespace: Some("a=b,c".to_string()),
+                cgroup_driver: None,
+            }),
+            ..SystemInfo::default()
+        };
+        let text = render(&info, "", 1760400000000000000);
+        assert!(text.starts_with(
+    "system,host=web-1,node=worker\\ 3,pod=odd\"pod\\\\name,namespace=a\\=b\\,c os_name=\"\","
+));
+    }
+
+    #[test]
+    fn test_line() {
+        let text = line(
+            "disk",
+            &[("host", "web 1"), ("mount", "C:\\")],
+            vec![
+                ("total_space", Field::Integer(1024)),
+                ("note", Field::Text("a \"b\"".into())),
+            ],
+            1760400000000000000,
+        );
+        assert_eq!(
+    text.as_deref(),
+    Some("disk,host=web\\ 1,mount=C:\\\\ total_space=1024i,note=\"a \\\"b\\\"\" 1760400000000000000\n")
+);
+    }
+
+    #[test]
+    fn test_line_integer_range() {
+        let text = line(
+            "network",
+            &[],
+            vec![
+                ("bytes_received", Field::Integer(u64::MAX)),
+                ("packets_received", Field::Integer(i64::MAX as u64)),
+                ("clock_offset_ms", Field::SignedInteger(i64::MIN)),
+            ],
+            0,
+        );
+        assert_eq!(
+            text.as_deref(),
+            Some(
+                "network bytes_received=9223372036854775807i,\
+                 packets_received=9223372036854775807i,\
+                 clock_offset_ms=-9223372036854775808i 0\n"
+            )
+        );
+    }
+}
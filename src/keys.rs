@@ -0,0 +1,688 @@
+//! JSON key naming conventions (`--json-keys`).
+//!
+//! Snapshots are serialized with snake_case keys. For consumers that expect
+//! another convention, the snapshot is serialized into a `Node` tree instead
+//! of a `serde_json::Value`, which would sort the keys. The tree keeps the
+//! struct field order and records which objects are structs, so only field
+//! names are renamed, including those pulled in by `#[serde(flatten)]` and
+//! enum tags. The keys of maps, such as sysctl names, process states or
+//! plugin output, are data and stay as they are.
+
+use serde::ser::{self, Serialize, Serializer};
+use serde_json::{Number, Value};
+
+/// A key naming convention.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum KeyCase {
+    /// `bytes_received` (the default)
+    #[default]
+    Snake,
+    /// `bytesReceived`
+    Camel,
+    /// `bytes-received`
+    Kebab,
+}
+
+impl KeyCase {
+    /// Parses a `--json-keys` value.
+    pub fn parse(text: &str) -> Option<KeyCase> {
+        match text {
+            "snake" => Some(KeyCase::Snake),
+            "camel" => Some(KeyCase::Camel),
+            "kebab" => Some(KeyCase::Kebab),
+            _ => None,
+        }
+    }
+
+    /// Converts one snake_case key.
+    pub fn convert(self, key: &str) -> String {
+        match self {
+            KeyCase::Snake => key.to_string(),
+            KeyCase::Kebab => key.replace('_', "-"),
+            KeyCase::Camel => {
+                let mut converted = String::with_capacity(key.len());
+                let mut upper = false;
+                for c in key.chars() {
+                    if c == '_' {
+                        upper = !converted.is_empty();
+                    } else if upper {
+                        converted.extend(c.to_uppercase());
+                        upper = false;
+                    } else {
+                        converted.push(c);
+                    }
+                }
+                converted
+            }
+        }
+    }
+}
+
+/// A serialized value whose objects keep their keys in order.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    /// Null, a boolean, a number or a string
+    Scalar(Value),
+    Array(Vec<Node>),
+    /// An object's entries in serialization order
+    Object(Vec<(String, Node)>, Keys),
+}
+
+/// What the keys of an object are.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Keys {
+    /// Struct field names (and enum variant names and tags)
+    Fields,
+    /// Map keys, which are data
+    Data,
+}
+
+impl Serialize for Node {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use ser::{SerializeMap, SerializeSeq};
+
+        match self {
+            Node::Scalar(value) => value.serialize(serializer),
+            Node::Array(items) => {
+                let mut seq = serializer.serialize_seq(Some(items.len()))?;
+                for item in items {
+                    seq.serialize_element(item)?;
+                }
+                seq.end()
+            }
+            Node::Object(entries, _) => {
+                let mut map = serializer.serialize_map(Some(entries.len()))?;
+                for (key, value) in entries {
+                    map.serialize_entry(key, value)?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+/// Serializes `value` into a `Node` tree.
+///
+/// # Errors
+///
+/// Returns an error where `serde_json` would, e.g. for a map key that is
+/// not a string, number or boolean.
+pub fn to_node<T: Serialize + ?Sized>(value: &T) -> Result<Node, serde_json::Error> {
+    value.serialize(NodeSerializer)
+}
+
+/// Renames the struct field names in `node` to `case`, recursively.
+///
+/// # Errors
+///
+/// Returns a description of the conflict if two keys of the same object
+/// would have the same name after conversion.
+pub fn rename_keys(node: Node, case: KeyCase) -> Result<Node, String> {
+    if case == KeyCase::Snake {
+        return Ok(node);
+    }
+    rename(node, case, "")
+}
+
+fn rename(node: Node, case: KeyCase, path: &str) -> Result<Node, String> {
+    match node {
+        Node::Object(entries, keys) => {
+            let mut renamed: Vec<(String, Node)> = Vec::with_capacity(entries.len());
+            let mut sources: Vec<String> = Vec::with_capacity(entries.len());
+            for (key, child) in entries {
+                let converted = match keys {
+                    Keys::Fields => case.convert(&key),
+                    Keys::Data => key.clone(),
+                };
+                if let Some(index) = renamed.iter().position(|(c, _)| *c == converted) {
+                    return Err(format!(
+                        "keys '{}' and '{}' in {} would both become '{}'",
+                        sources[index],
+                        key,
+                        if path.is_empty() {
+                            "the top level"
+                        } else {
+                            path
+                        },
+                        converted
+                    ));
+                }
+                let child_path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                renamed.push((converted, rename(child, case, &child_path)?));
+                sources.push(key);
+            }
+            Ok(Node::Object(renamed, keys))
+        }
+        Node::Array(items) => items
+            .into_iter()
+            .enumerate()
+            .map(|(index, item)| rename(item, case, &format!("{}[{}]", path, index)))
+            .collect::<Result<Vec<_>, _>>()
+            .map(Node::Array),
+        scalar => Ok(scalar),
+    }
+}
+
+/// Builds a `Node` tree, representing values the way `serde_json` does.
+struct NodeSerializer;
+
+/// Collects the elements of a sequence, tuple or tuple variant.
+struct ArrayBuilder {
+    items: Vec<Node>,
+    variant: Option<&'static str>,
+}
+
+/// Collects the entries of a map, struct or struct variant.
+struct ObjectBuilder {
+    entries: Vec<(String, Node)>,
+    keys: Keys,
+    key: Option<String>,
+    variant: Option<&'static str>,
+}
+
+/// Wraps `node` in an object keyed by the enum variant, if there is one.
+fn tagged(variant: Option<&'static str>, node: Node) -> Node {
+    match variant {
+        Some(variant) => Node::Object(vec![(variant.to_string(), node)], Keys::Fields),
+        None => node,
+    }
+}
+
+impl Serializer for NodeSerializer {
+    type Ok = Node;
+    type Error = serde_json::Error;
+    type SerializeSeq = ArrayBuilder;
+    type SerializeTuple = ArrayBuilder;
+    type SerializeTupleStruct = ArrayBuilder;
+    type SerializeTupleVariant = ArrayBuilder;
+    type SerializeMap = ObjectBuilder;
+    type SerializeStruct = ObjectBuilder;
+    type SerializeStructVariant = ObjectBuilder;
+
+    fn serialize_bool(self, v: bool) -> Result<Node, Self::Error> {
+        Ok(Node::Scalar(Value::Bool(v)))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Node, Self::Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Node, Self::Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Node, Self::Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Node, Self::Error> {
+        Ok(Node::Scalar(Value::from(v)))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Node, Self::Error> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Node, Self::Error> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Node, Self::Error> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Node, Self::Error> {
+        Ok(Node::Scalar(Value::from(v)))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Node, Self::Error> {
+        // Going through the shortest decimal form keeps 0.1f32 as 0.1, as
+        // serde_json writes it, rather than 0.10000000149011612
+        self.serialize_f64(v.to_string().parse().unwrap_or(v.into()))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Node, Self::Error> {
+        Ok(Node::Scalar(
+            Number::from_f64(v).map_or(Value::Null, Value::Number),
+        ))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Node, Self::Error> {
+        Ok(Node::Scalar(Value::String(v.to_string())))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Node, Self::Error> {
+        Ok(Node::Scalar(Value::String(v.to_string())))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Node, Self::Error> {
+        Ok(Node::Array(
+            v.iter().map(|&byte| Node::Scalar(byte.into())).collect(),
+        ))
+    }
+
+    fn serialize_none(self) -> Result<Node, Self::Error> {
+        Ok(Node::Scalar(Value::Null))
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Node, Self::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Node, Self::Error> {
+        Ok(Node::Scalar(Value::Null))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Node, Self::Error> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<Node, Self::Error> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Node, Self::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Node, Self::Error> {
+        Ok(tagged(Some(variant), value.serialize(self)?))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<ArrayBuilder, Self::Error> {
+        Ok(ArrayBuilder {
+            items: Vec::with_capacity(len.unwrap_or(0)),
+            variant: None,
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<ArrayBuilder, Self::Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<ArrayBuilder, Self::Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<ArrayBuilder, Self::Error> {
+        Ok(ArrayBuilder {
+            items: Vec::with_capacity(len),
+            variant: Some(variant),
+        })
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<ObjectBuilder, Self::Error> {
+        // Maps and sets always know their length; serde only starts a map
+        // of unknown length for a struct with `#[serde(flatten)]` fields,
+        // whose keys are field names
+        Ok(ObjectBuilder {
+            entries: Vec::with_capacity(len.unwrap_or(0)),
+            keys: if len.is_some() {
+                Keys::Data
+            } else {
+                Keys::Fields
+            },
+            key: None,
+            variant: None,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<ObjectBuilder, Self::Error> {
+        Ok(ObjectBuilder {
+            entries: Vec::with_capacity(len),
+            keys: Keys::Fields,
+            key: None,
+            variant: None,
+        })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<ObjectBuilder, Self::Error> {
+        Ok(ObjectBuilder {
+            entries: Vec::with_capacity(len),
+            keys: Keys::Fields,
+            key: None,
+            variant: Some(variant),
+        })
+    }
+}
+
+impl ser::SerializeSeq for ArrayBuilder {
+    type Ok = Node;
+    type Error = serde_json::Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        self.items.push(value.serialize(NodeSerializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Node, Self::Error> {
+        Ok(tagged(self.variant, Node::Array(self.items)))
+    }
+}
+
+impl ser::SerializeTuple for ArrayBuilder {
+    type Ok = Node;
+    type Error = serde_json::Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Node, Self::Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for ArrayBuilder {
+    type Ok = Node;
+    type Error = serde_json::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Node, Self::Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleVariant for ArrayBuilder {
+    type Ok = Node;
+    type Error = serde_json::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Node, Self::Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeMap for ObjectBuilder {
+    type Ok = Node;
+    type Error = serde_json::Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Self::Error> {
+        // JSON keys are strings; numbers and booleans are written as text
+        self.key = Some(match key.serialize(NodeSerializer)? {
+            Node::Scalar(Value::String(key)) => key,
+            Node::Scalar(key @ (Value::Number(_) | Value::Bool(_))) => key.to_string(),
+            _ => return Err(ser::Error::custom("key must be a string")),
+        });
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| <Self::Error as ser::Error>::custom("value without a key"))?;
+        self.entries.push((key, value.serialize(NodeSerializer)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Node, Self::Error> {
+        Ok(tagged(self.variant, Node::Object(self.entries, self.keys)))
+    }
+}
+
+impl ser::SerializeStruct for ObjectBuilder {
+    type Ok = Node;
+    type Error = serde_json::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        self.entries
+            .push((key.to_string(), value.serialize(NodeSerializer)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Node, Self::Error> {
+        ser::SerializeMap::end(self)
+    }
+}
+
+impl ser::SerializeStructVariant for ObjectBuilder {
+    type Ok = Node;
+    type Error = serde_json::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        ser::SerializeStruct::serialize_field(self, key, value)
+    }
+
+    fn end(self) -> Result<Node, Self::Error> {
+        ser::SerializeMap::end(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Serialize;
+    use serde_json::json;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_key_case_convert() {
+        assert_eq!(KeyCase::Camel.convert("bytes_received"), "bytesReceived");
+        assert_eq!(KeyCase::Camel.convert("per_core"), "perCore");
+        assert_eq!(KeyCase::Kebab.convert("swap_in_per_sec"), "swap-in-per-sec");
+        assert_eq!(KeyCase::Snake.convert("os_name"), "os_name");
+    }
+
+    /// A deeply nested fixture with every shape a snapshot uses: nested
+    /// structs, arrays of structs, options, flattened fields, enums and maps
+    /// whose keys are data.
+    #[derive(Serialize)]
+    struct Fixture {
+        os_name: &'static str,
+        sysctls: BTreeMap<&'static str, &'static str>,
+        cpu_times: Times,
+        disks: Vec<Disk>,
+        plugins: BTreeMap<&'static str, Plugin>,
+        last_event: Event,
+        power_source: Source,
+    }
+
+    #[derive(Serialize)]
+    struct Times {
+        interval_secs: Option<f64>,
+        #[serde(flatten)]
+        total: Shares,
+        per_core: Vec<Core>,
+    }
+
+    #[derive(Serialize)]
+    struct Shares {
+        soft_irq: f32,
+    }
+
+    #[derive(Serialize)]
+    struct Core {
+        core_name: &'static str,
+        #[serde(flatten)]
+        times: Shares,
+    }
+
+    #[derive(Serialize)]
+    struct Disk {
+        mount_point: &'static str,
+        io_stats: Option<IoStats>,
+    }
+
+    #[derive(Serialize)]
+    struct IoStats {
+        read_bytes: u64,
+        process_states: BTreeMap<&'static str, u64>,
+    }
+
+    #[derive(Serialize)]
+    struct Plugin {
+        exit_code: i32,
+        data: Value,
+    }
+
+    #[derive(Serialize)]
+    #[serde(tag = "event_type", rename_all = "snake_case")]
+    enum Event {
+        DiskFull { mount_point: &'static str },
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "snake_case")]
+    enum Source {
+        BatteryLevel { percent_left: u8 },
+    }
+
+    fn fixture() -> Fixture {
+        let core = |core_name| Core {
+            core_name,
+            times: Shares { soft_irq: 0.1 },
+        };
+        Fixture {
+            os_name: "Linux",
+            sysctls: BTreeMap::from([("vm.overcommit_memory", "0"), ("vm.swappiness", "60")]),
+            cpu_times: Times {
+                interval_secs: None,
+                total: Shares { soft_irq: 1.5 },
+                per_core: vec![core("cpu0"), core("cpu1")],
+            },
+            disks: vec![
+                Disk {
+                    mount_point: "/",
+                    io_stats: Some(IoStats {
+                        read_bytes: u64::MAX,
+                        process_states: BTreeMap::from([("disk_sleep", 1)]),
+                    }),
+                },
+                Disk {
+                    mount_point: "/boot",
+                    io_stats: None,
+                },
+            ],
+            plugins: BTreeMap::from([(
+                "gpu_stats",
+                Plugin {
+                    exit_code: 0,
+                    data: json!({"fan_speed": {"gpu_0": 40}}),
+                },
+            )]),
+            last_event: Event::DiskFull { mount_point: "/" },
+            power_source: Source::BatteryLevel { percent_left: 80 },
+        }
+    }
+
+    #[test]
+    fn test_rename_keys() {
+        let node = to_node(&fixture()).unwrap();
+        let camel = rename_keys(node.clone(), KeyCase::Camel).unwrap();
+        // Field names are renamed in struct order; sysctl, process state,
+        // plugin and plugin data keys are left alone
+        assert_eq!(
+            serde_json::to_string(&camel).unwrap(),
+            concat!(
+                r#"{"osName":"Linux","#,
+                r#""sysctls":{"vm.overcommit_memory":"0","vm.swappiness":"60"},"#,
+                r#""cpuTimes":{"intervalSecs":null,"softIrq":1.5,"perCore":["#,
+                r#"{"coreName":"cpu0","softIrq":0.1},{"coreName":"cpu1","softIrq":0.1}]},"#,
+                r#""disks":[{"mountPoint":"/","ioStats":{"readBytes":18446744073709551615,"#,
+                r#""processStates":{"disk_sleep":1}}},{"mountPoint":"/boot","ioStats":null}],"#,
+                r#""plugins":{"gpu_stats":{"exitCode":0,"data":{"fan_speed":{"gpu_0":40}}}},"#,
+                r#""lastEvent":{"eventType":"disk_full","mountPoint":"/"},"#,
+                r#""powerSource":{"batteryLevel":{"percentLeft":80}}}"#
+            )
+        );
+        let kebab = rename_keys(node.clone(), KeyCase::Kebab).unwrap();
+        assert!(serde_json::to_string(&kebab)
+            .unwrap()
+            .contains(r#""cpu-times":{"interval-secs":null,"soft-irq":1.5"#));
+
+        // Unrenamed, the tree writes exactly what serde_json would
+        assert_eq!(
+            rename_keys(node, KeyCase::Snake).unwrap(),
+            to_node(&fixture()).unwrap()
+        );
+        assert_eq!(
+            serde_json::to_string_pretty(&to_node(&fixture()).unwrap()).unwrap(),
+            serde_json::to_string_pretty(&fixture()).unwrap()
+        );
+
+        #[derive(Serialize)]
+        struct Clash {
+            used_space: u64,
+            #[serde(rename = "usedSpace")]
+            used: u64,
+        }
+        let clash = to_node(&BTreeMap::from([(
+            "outer",
+            Clash {
+                used_space: 1,
+                used: 2,
+            },
+        )]));
+        assert_eq!(
+            rename_keys(clash.unwrap(), KeyCase::Camel),
+            Err(
+                "keys 'used_space' and 'usedSpace' in outer would both become 'usedSpace'"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_to_node() {
+        let info = crate::mock::snapshot(&crate::mock::MockProvider::default());
+        assert_eq!(
+            serde_json::to_string_pretty(&to_node(&info).unwrap()).unwrap(),
+            serde_json::to_string_pretty(&info).unwrap()
+        );
+        assert!(to_node(&BTreeMap::from([((1, 2), 3)])).is_err());
+    }
+}
@@ -0,0 +1,357 @@
+//! Storage errors in the kernel log (`--dmesg-scan`).
+//!
+//! A file system that remounted read-only or a disk that stopped answering
+//! shows up in the kernel log long before anything else notices. On Linux
+//! the most recent records of the ring buffer are read from `/dev/kmsg`
+//! without blocking and scanned for ext4 and XFS errors, buffer and block
+//! layer I/O errors and NVMe command timeouts. Reading `/dev/kmsg` needs
+//! root or `kernel.dmesg_restrict = 0`; other platforms have no scan.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::ffi::OsStr;
+
+/// Number of most recent log records scanned.
+const SCAN_RECORDS: usize = 2000;
+
+/// Number of matched lines kept in `recent`.
+const MAX_RECENT: usize = 10;
+
+/// Storage errors found in the recent kernel log.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct KernelErrors {
+    /// Log records scanned, at most the 2000 most recent
+    pub records_scanned: usize,
+    /// Records that matched a storage error pattern
+    pub total_matches: usize,
+    /// Seconds since the most recent match, if any
+    pub seconds_since_last_error: Option<f64>,
+    /// Devices named by the matches, e.g. "sda1" or "nvme0"
+    pub devices: BTreeSet<String>,
+    /// The most recent matches, oldest first, at most 10
+    pub recent: Vec<KernelError>,
+}
+
+/// One kernel log record reporting a storage error.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct KernelError {
+    /// Time of the record in seconds since boot, as `dmesg` prints it
+    pub seconds_since_boot: f64,
+    /// "ext4", "xfs", "buffer_io", "block_io" or "nvme_timeout"
+    pub kind: String,
+    /// Device the record names, e.g. "sda1", "dm-0" or "nvme0"
+    pub device: String,
+    pub message: String,
+}
+
+/// A parsed `/dev/kmsg` record.
+#[derive(Debug, PartialEq)]
+struct Record {
+    /// Log level, 0 (emergency) to 7 (debug)
+    level: u8,
+    seconds_since_boot: f64,
+    /// The first line of the record, without the key/value continuation
+    message: String,
+}
+
+impl KernelErrors {
+    /// Returns whether a match names the disk on `device`, e.g. "/dev/sda1".
+    ///
+    /// Device mapper paths such as `/dev/mapper/vg-root` are resolved to
+    /// the `dm-N` name the kernel logs. A match on a whole disk or NVMe
+    /// controller also affects its partitions and namespaces.
+    pub fn affects(&self, device: &OsStr) -> bool {
+        let path = std::path::Path::new(device);
+        let resolved = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        let Some(name) = resolved.file_name().and_then(OsStr::to_str) else {
+            return false;
+        };
+        self.devices.iter().any(|logged| {
+            let Some(rest) = name.strip_prefix(logged.as_str()) else {
+                return false;
+            };
+            // sda → sda1; after a digit a separator: nvme0 → nvme0n1,
+            // nvme0n1 → nvme0n1p2, mmcblk0 → mmcblk0p1
+            let suffix = if logged.ends_with(|c: char| c.is_ascii_digit()) {
+                rest.strip_prefix(['p', 'n'])
+            } else {
+                Some(rest)
+            };
+            rest.is_empty()
+                || suffix.is_some_and(|suffix| suffix.starts_with(|c: char| c.is_ascii_digit()))
+        })
+    }
+}
+
+/// Scans the most recent kernel log records for storage errors.
+///
+/// # Errors
+///
+/// Returns why `/dev/kmsg` could not be read, e.g. for lack of permission.
+#[cfg(target_os = "linux")]
+pub fn scan() -> Result<KernelErrors, String> {
+    use std::io::{ErrorKind, Read};
+    use std::os::unix::fs::OpenOptionsExt;
+
+    // O_NONBLOCK on every common Linux architecture: a read at the end of
+    // the buffer fails with EAGAIN instead of waiting for new records
+    const O_NONBLOCK: i32 = 0o4000;
+    let mut kmsg = std::fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(O_NONBLOCK)
+        .open("/dev/kmsg")
+        .map_err(|e| format!("cannot read /dev/kmsg: {}", e))?;
+    // Each read returns one record, and a record is at most about 8 KiB
+    let mut buffer = vec![0; 16 * 1024];
+    let mut records = std::collections::VecDeque::with_capacity(SCAN_RECORDS);
+    loop {
+        match kmsg.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(length) => {
+                if let Some(record) = parse_record(&String::from_utf8_lossy(&buffer[..length])) {
+                    if records.len() == SCAN_RECORDS {
+                        records.pop_front();
+                    }
+                    records.push_back(record);
+                }
+            }
+            Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+            // EPIPE: the next record was overwritten while reading
+            Err(e) if e.kind() == ErrorKind::BrokenPipe => continue,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(format!("cannot read /dev/kmsg: {}", e)),
+        }
+    }
+    let uptime = std::fs::read_to_string("/proc/uptime")
+        .ok()
+        .and_then(|text| text.split_whitespace().next()?.parse().ok());
+    Ok(summarize(records.make_contiguous(), uptime))
+}
+
+/// Scans the most recent kernel log records for storage errors.
+///
+/// # Errors
+///
+/// Always fails: only Linux kernel logs are scanned.
+#[cfg(not(target_os = "linux"))]
+pub fn scan() -> Result<KernelErrors, String> {
+    Err("the kernel log is only scanned on Linux".to_string())
+}
+
+/// Collects the storage errors in `records`, oldest first, measuring the
+/// time since the last one against `uptime` in seconds.
+fn summarize(records: &[Record], uptime: Option<f64>) -> KernelErrors {
+    let mut errors = KernelErrors {
+        records_scanned: records.len(),
+        ..KernelErrors::default()
+    };
+    let mut last = None;
+    for record in records {
+        let Some((kind, device)) = classify(record.level, &record.message) else {
+            continue;
+        };
+        errors.total_matches += 1;
+        errors.devices.insert(device.to_string());
+        last = Some(record.seconds_since_boot);
+        if errors.recent.len() == MAX_RECENT {
+            errors.recent.remove(0);
+        }
+        errors.recent.push(KernelError {
+            seconds_since_boot: record.seconds_since_boot,
+            kind: kind.to_string(),
+            device: device.to_string(),
+            message: record.message.clone(),
+        });
+    }
+    errors.seconds_since_last_error = last
+        .zip(uptime)
+        .map(|(last, uptime)| (uptime - last).max(0.0));
+    errors
+}
+
+/// Parses one `/dev/kmsg` record: `<priority>,<sequence>,<microseconds
+/// since boot>,<flags>[,...];<message>`, optionally followed by
+/// continuation lines of the form ` KEY=value`.
+fn parse_record(record: &str) -> Option<Record> {
+    let (header, text) = record.split_once(';')?;
+    let mut fields = header.split(',');
+    let priority: u32 = fields.next()?.parse().ok()?;
+    let _sequence = fields.next()?;
+    let microseconds: u64 = fields.next()?.parse().ok()?;
+    Some(Record {
+        level: (priority & 7) as u8,
+        seconds_since_boot: microseconds as f64 / 1e6,
+        message: text.lines().next().unwrap_or("").to_string(),
+    })
+}
+
+/// Returns the kind of storage error a record of `level` reports and the
+/// device it names, or `None` if it reports none.
+fn classify(level: u8, message: &str) -> Option<(&'static str, &str)> {
+    // KERN_ERR and more severe
+    const ERROR_LEVEL: u8 = 3;
+    if let Some(rest) = message.strip_prefix("EXT4-fs error (device ") {
+        return Some(("ext4", rest.split(')').next()?));
+    }
+    if let Some(rest) = message.strip_prefix("EXT4-fs (") {
+        let (device, text) = rest.split_once("): ")?;
+        return text.contains("read-only").then_some(("ext4", device));
+    }
+    if let Some(rest) = message.strip_prefix("XFS (") {
+        let (device, text) = rest.split_once("): ")?;
+        let error = level <= ERROR_LEVEL
+            || text.contains("error")
+            || text.contains("Corruption")
+            || text.contains("Shutting down");
+        return error.then_some(("xfs", device));
+    }
+    if let Some(rest) = message.strip_prefix("Buffer I/O error on dev ") {
+        return Some(("buffer_io", rest.split(',').next()?));
+    }
+    if let Some((_, rest)) = message.split_once("I/O error, dev ") {
+        return Some(("block_io", rest.split(',').next()?));
+    }
+    if let Some(rest) = message.strip_prefix("nvme ") {
+        let (device, text) = rest.split_once(": ")?;
+        return (text.starts_with("I/O ") && text.contains(" timeout"))
+            .then_some(("nvme_timeout", device));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kernel_errors_affects() {
+        let errors = KernelErrors {
+            devices: ["sdb".to_string(), "nvme0".to_string(), "dm-3".to_string()].into(),
+            ..KernelErrors::default()
+        };
+        assert!(errors.affects(std::ffi::OsStr::new("/dev/sdb1")));
+        assert!(errors.affects(std::ffi::OsStr::new("/dev/nvme0n1p2")));
+        assert!(errors.affects(std::ffi::OsStr::new("dm-3")));
+        assert!(!errors.affects(std::ffi::OsStr::new("/dev/sda1")));
+        assert!(!errors.affects(std::ffi::OsStr::new("/dev/sdba1")));
+        assert!(!errors.affects(std::ffi::OsStr::new("/dev/nvme01n1")));
+        assert!(!errors.affects(std::ffi::OsStr::new("/dev/dm-30")));
+    }
+
+    #[test]
+    fn test_summarize() {
+        let records: Vec<Record> = [
+    "6,1020,2841063,-;EXT4-fs (sda1): mounted filesystem with ordered data mode. Quota mode: none.",
+    "3,1187,5182736401,-;EXT4-fs error (device sda1): ext4_find_entry:1455: inode #2: comm ls: reading directory lblock 0",
+    "3,1188,5182736590,-;Buffer I/O error on dev sda1, logical block 0, lost async page write",
+    "4,1201,5190017236,-;nvme nvme0: I/O 24 QID 3 timeout, aborting",
+]
+.iter()
+.filter_map(|line| parse_record(line))
+.collect();
+        let errors = summarize(&records, Some(5200.0));
+        assert_eq!(errors.records_scanned, 4);
+        assert_eq!(errors.total_matches, 3);
+        assert_eq!(
+            errors.devices,
+            ["nvme0".to_string(), "sda1".to_string()].into()
+        );
+        assert_eq!(errors.recent[0].kind, "ext4");
+        assert_eq!(errors.recent[0].seconds_since_boot, 5182.736401);
+        assert_eq!(
+            errors.recent[2].message,
+            "nvme nvme0: I/O 24 QID 3 timeout, aborting"
+        );
+        assert!((errors.seconds_since_last_error.unwrap() - 9.982764).abs() < 1e-6);
+
+        let quiet = summarize(&records[..1], Some(5200.0));
+        assert_eq!(quiet.total_matches, 0);
+        assert_eq!(quiet.seconds_since_last_error, None);
+        assert!(quiet.recent.is_empty());
+    }
+
+    #[test]
+    fn test_parse_record() {
+        let record = parse_record(
+    "3,1187,5182736401,-;EXT4-fs error (device sda1): ext4_find_entry:1455: inode #2: comm ls: reading directory lblock 0\n SUBSYSTEM=block\n DEVICE=b8:1\n",
+)
+.unwrap();
+        assert_eq!(record.level, 3);
+        assert_eq!(record.seconds_since_boot, 5182.736401);
+        assert_eq!(
+    record.message,
+    "EXT4-fs error (device sda1): ext4_find_entry:1455: inode #2: comm ls: reading directory lblock 0"
+);
+        // Facility 3 (daemon), level 6 (info)
+        assert_eq!(
+            parse_record("30,2204,91823007,-,caller=T1;systemd[1]: Started Journal Service.")
+                .unwrap()
+                .level,
+            6
+        );
+        assert_eq!(parse_record("not a record"), None);
+        assert_eq!(parse_record("3,1187,soon,-;message"), None);
+    }
+
+    #[test]
+    fn test_classify() {
+        let classify = |level, message| {
+            classify(level, message).map(|(kind, device)| (kind, device.to_string()))
+        };
+        assert_eq!(
+    classify(3, "EXT4-fs error (device sda1): ext4_find_entry:1455: inode #2: comm ls: reading directory lblock 0"),
+    Some(("ext4", "sda1".to_string()))
+);
+        assert_eq!(
+            classify(2, "EXT4-fs (dm-0): Remounting filesystem read-only"),
+            Some(("ext4", "dm-0".to_string()))
+        );
+        assert_eq!(
+            classify(
+                6,
+                "EXT4-fs (sda1): mounted filesystem with ordered data mode. Quota mode: none."
+            ),
+            None
+        );
+        assert_eq!(
+    classify(3, "XFS (dm-0): metadata I/O error in \"xfs_trans_read_buf_map\" at daddr 0x2 len 1 error 5"),
+    Some(("xfs", "dm-0".to_string()))
+);
+        assert_eq!(
+    classify(1, "XFS (sdb1): Corruption of in-memory data (0x8) detected at xfs_trans_cancel+0x12c/0x150 (fs/xfs/xfs_trans.c:1096).  Shutting down filesystem."),
+    Some(("xfs", "sdb1".to_string()))
+);
+        assert_eq!(classify(5, "XFS (sdb1): Mounting V5 Filesystem"), None);
+        assert_eq!(
+            classify(
+                3,
+                "Buffer I/O error on dev sda1, logical block 0, lost async page write"
+            ),
+            Some(("buffer_io", "sda1".to_string()))
+        );
+        assert_eq!(
+    classify(3, "blk_update_request: I/O error, dev sdb, sector 2048 op 0x1:(WRITE) flags 0x800 phys_seg 1 prio class 0"),
+    Some(("block_io", "sdb".to_string()))
+);
+        assert_eq!(
+    classify(3, "I/O error, dev nvme0n1, sector 4096 op 0x1:(WRITE) flags 0x0 phys_seg 1 prio class 2"),
+    Some(("block_io", "nvme0n1".to_string()))
+);
+        assert_eq!(
+            classify(4, "nvme nvme0: I/O 24 QID 3 timeout, aborting"),
+            Some(("nvme_timeout", "nvme0".to_string()))
+        );
+        assert_eq!(
+            classify(4, "nvme nvme0: I/O 24 QID 3 timeout, reset controller"),
+            Some(("nvme_timeout", "nvme0".to_string()))
+        );
+        assert_eq!(
+            classify(6, "nvme nvme0: 8/0/0 default/read/poll queues"),
+            None
+        );
+        assert_eq!(
+            classify(3, "usb 1-1: device descriptor read/64, error -71"),
+            None
+        );
+    }
+}
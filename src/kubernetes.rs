@@ -0,0 +1,130 @@
+//! Kubernetes pod identity, for snapshots taken by a DaemonSet.
+//!
+//! The node, pod and namespace come from the downward-API environment
+//! variables `NODE_NAME`, `POD_NAME` and `POD_NAMESPACE`, which the pod spec
+//! has to set; the namespace falls back to the service account's namespace
+//! file. The kubelet's cgroup driver is inferred from the pod's cgroup path.
+//! The pod is only detected with the `host-details` feature.
+
+use serde::{Deserialize, Serialize};
+
+/// Service account namespace file mounted into every pod by default.
+#[cfg(feature = "host-details")]
+const NAMESPACE_FILE: &str = "/var/run/secrets/kubernetes.io/serviceaccount/namespace";
+
+/// The pod this process runs in.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct KubernetesInfo {
+    /// Node the pod is scheduled on, from `NODE_NAME`
+    pub node_name: Option<String>,
+    /// Name of the pod, from `POD_NAME`
+    pub pod_name: Option<String>,
+    /// Namespace of the pod, from `POD_NAMESPACE` or the service account
+    pub namespace: Option<String>,
+    /// The kubelet's cgroup driver, "systemd" or "cgroupfs", where the
+    /// pod's cgroup path shows it
+    pub cgroup_driver: Option<String>,
+}
+
+/// Detects whether this process runs in a Kubernetes pod.
+///
+/// # Returns
+///
+/// `None` outside Kubernetes, i.e. when neither `KUBERNETES_SERVICE_HOST`
+/// (set in every pod) nor the service account namespace file exists.
+#[cfg(feature = "host-details")]
+pub fn detect() -> Option<KubernetesInfo> {
+    from_sources(
+        |name| std::env::var(name).ok(),
+        std::fs::read_to_string(NAMESPACE_FILE).ok().as_deref(),
+        std::fs::read_to_string("/proc/self/cgroup").ok().as_deref(),
+    )
+}
+
+/// Builds the pod identity from environment variables (looked up with
+/// `env`), the service account namespace file and `/proc/self/cgroup`.
+#[cfg(feature = "host-details")]
+fn from_sources(
+    env: impl Fn(&str) -> Option<String>,
+    namespace_file: Option<&str>,
+    cgroup: Option<&str>,
+) -> Option<KubernetesInfo> {
+    let var = |name| env(name).filter(|value| !value.is_empty());
+    let namespace_file = namespace_file
+        .map(str::trim)
+        .filter(|namespace| !namespace.is_empty());
+    if var("KUBERNETES_SERVICE_HOST").is_none() && namespace_file.is_none() {
+        return None;
+    }
+    Some(KubernetesInfo {
+        node_name: var("NODE_NAME"),
+        pod_name: var("POD_NAME"),
+        namespace: var("POD_NAMESPACE").or_else(|| namespace_file.map(str::to_string)),
+        cgroup_driver: cgroup.and_then(cgroup_driver).map(str::to_string),
+    })
+}
+
+/// Infers the kubelet's cgroup driver from `/proc/self/cgroup`: the
+/// systemd driver nests pods in `kubepods.slice`, cgroupfs in `/kubepods/`.
+///
+/// # Returns
+///
+/// `None` if the path does not show it, e.g. in a private cgroup
+/// namespace, where the path is just `/`.
+#[cfg(feature = "host-details")]
+fn cgroup_driver(cgroup: &str) -> Option<&'static str> {
+    cgroup.lines().find_map(|line| {
+        let path = line.splitn(3, ':').nth(2)?;
+        if path.contains("/kubepods.slice/") {
+            Some("systemd")
+        } else if path.starts_with("/kubepods/") {
+            Some("cgroupfs")
+        } else {
+            None
+        }
+    })
+}
+
+#[cfg(all(test, feature = "host-details"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_sources() {
+        let env = |name: &str| match name {
+            "KUBERNETES_SERVICE_HOST" => Some("10.96.0.1".to_string()),
+            "NODE_NAME" => Some("worker-3".to_string()),
+            "POD_NAME" => Some("sysinfo-x7k2p".to_string()),
+            _ => None,
+        };
+        let cgroup = "0::/kubepods.slice/kubepods-besteffort.slice/kubepods-besteffort-pod1f2e.slice/cri-containerd-9ab3.scope\n";
+        let info = from_sources(env, Some("monitoring\n"), Some(cgroup)).unwrap();
+        assert_eq!(info.node_name.as_deref(), Some("worker-3"));
+        assert_eq!(info.pod_name.as_deref(), Some("sysinfo-x7k2p"));
+        assert_eq!(info.namespace.as_deref(), Some("monitoring"));
+        assert_eq!(info.cgroup_driver.as_deref(), Some("systemd"));
+
+        // POD_NAMESPACE wins over the service account, and empty values count as unset
+        let env = |name: &str| match name {
+            "POD_NAMESPACE" => Some("kube-system".to_string()),
+            "NODE_NAME" => Some(String::new()),
+            _ => None,
+        };
+        let info = from_sources(env, Some("default"), None).unwrap();
+        assert_eq!(info.namespace.as_deref(), Some("kube-system"));
+        assert_eq!(info.node_name, None);
+
+        // Outside Kubernetes
+        assert_eq!(from_sources(|_| None, None, Some("0::/user.slice\n")), None);
+    }
+
+    #[test]
+    fn test_cgroup_driver() {
+        assert_eq!(
+            cgroup_driver("0::/kubepods.slice/kubepods-pod1f2e.slice/cri-containerd-9ab3.scope\n"),
+            Some("systemd")
+        );
+        assert_eq!(cgroup_driver("12:memory:/kubepods/burstable/pod1f2e/9ab3\n11:cpu:/kubepods/burstable/pod1f2e/9ab3\n"), Some("cgroupfs"));
+        assert_eq!(cgroup_driver("0::/\n"), None);
+    }
+}
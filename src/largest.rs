@@ -0,0 +1,206 @@
+//! `--scan-largest`: the largest files and directories below a path.
+//!
+//! Walks the tree the way `du -x` does: sizes are the space allocated on
+//! disk (the file length where the platform does not report blocks),
+//! symbolic links are not followed and other file systems mounted below
+//! the path are skipped, so the sizes add up to what fills that disk.
+//! Directory sizes always include everything below them; `--max-depth`
+//! only limits which paths can be listed. Entries that cannot be read are
+//! counted and skipped.
+
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::Metadata;
+use std::io;
+use std::path::Path;
+
+/// Number of paths listed without `--top`.
+pub const DEFAULT_TOP: usize = 10;
+
+/// Levels below the scanned path that are listed without `--max-depth`.
+pub const DEFAULT_MAX_DEPTH: usize = 3;
+
+/// The result of a scan.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LargestPaths {
+    /// The scanned path
+    pub root: String,
+    /// Bytes used by everything below `root`
+    pub total_size: u64,
+    /// The largest files and directories, largest first
+    pub entries: Vec<PathSize>,
+    /// Files and directories that could not be read, e.g. for lack of
+    /// permission; their sizes are missing from the totals
+    pub unreadable: u64,
+}
+
+/// A file or directory and the space it uses.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PathSize {
+    pub path: String,
+    /// Bytes used, including everything below a directory
+    pub size: u64,
+    pub is_dir: bool,
+}
+
+/// State of a walk in progress.
+struct Walk {
+    top: usize,
+    max_depth: usize,
+    /// Device of the root, to stay on its file system
+    device: Option<u64>,
+    /// The `top` largest entries so far, smallest at the top of the heap;
+    /// ties go to the path that sorts first
+    largest: BinaryHeap<Reverse<(u64, Reverse<String>, bool)>>,
+    unreadable: u64,
+}
+
+/// Scans `root` and returns its `top` largest files and directories no
+/// more than `max_depth` levels below it (1 lists only its children).
+///
+/// # Errors
+///
+/// Returns why `root` itself cannot be read.
+pub fn scan(root: &Path, max_depth: usize, top: usize) -> io::Result<LargestPaths> {
+    let metadata = std::fs::metadata(root)?;
+    // Fail early, rather than counting the root as unreadable
+    if metadata.is_dir() {
+        std::fs::read_dir(root)?;
+    }
+    let mut walk = Walk {
+        top,
+        max_depth,
+        device: device(&metadata),
+        largest: BinaryHeap::new(),
+        unreadable: 0,
+    };
+    let total_size = walk.size(root, &metadata, 0);
+    let mut entries: Vec<PathSize> = walk
+        .largest
+        .into_iter()
+        .map(|Reverse((size, Reverse(path), is_dir))| PathSize { path, size, is_dir })
+        .collect();
+    entries.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+    Ok(LargestPaths {
+        root: root.to_string_lossy().into_owned(),
+        total_size,
+        entries,
+        unreadable: walk.unreadable,
+    })
+}
+
+impl Walk {
+    /// Returns the space used by `path`, which is `depth` levels below the
+    /// root, and everything below it, recording it as a candidate.
+    fn size(&mut self, path: &Path, metadata: &Metadata, depth: usize) -> u64 {
+        let mut size = allocated(metadata);
+        if metadata.is_dir() {
+            match std::fs::read_dir(path) {
+                Ok(entries) => {
+                    for entry in entries {
+                        let Ok(entry) = entry else {
+                            self.unreadable += 1;
+                            continue;
+                        };
+                        let path = entry.path();
+                        // Does not follow symbolic links
+                        let Ok(metadata) = std::fs::symlink_metadata(&path) else {
+                            self.unreadable += 1;
+                            continue;
+                        };
+                        if metadata.is_dir() && device(&metadata) != self.device {
+                            continue;
+                        }
+                        size = size.saturating_add(self.size(&path, &metadata, depth + 1));
+                    }
+                }
+                Err(_) => self.unreadable += 1,
+            }
+        }
+        if depth > 0 && depth <= self.max_depth && self.top > 0 {
+            self.largest.push(Reverse((
+                size,
+                Reverse(path.to_string_lossy().into_owned()),
+                metadata.is_dir(),
+            )));
+            if self.largest.len() > self.top {
+                self.largest.pop();
+            }
+        }
+        size
+    }
+}
+
+/// Returns the space `metadata`'s file occupies on disk.
+#[cfg(unix)]
+fn allocated(metadata: &Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.blocks().saturating_mul(512)
+}
+
+/// Returns the space `metadata`'s file occupies on disk.
+#[cfg(not(unix))]
+fn allocated(metadata: &Metadata) -> u64 {
+    metadata.len()
+}
+
+/// Returns the device `metadata`'s file is on.
+#[cfg(unix)]
+fn device(metadata: &Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.dev())
+}
+
+/// Returns the device `metadata`'s file is on.
+///
+/// Not available on this platform, so mounted volumes are not told apart.
+#[cfg(not(unix))]
+fn device(_metadata: &Metadata) -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan() {
+        let root = std::env::temp_dir().join(format!("largest-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("logs/old")).unwrap();
+        std::fs::write(root.join("logs/old/app.1"), vec![0; 300_000]).unwrap();
+        std::fs::write(root.join("logs/app"), vec![0; 100_000]).unwrap();
+        std::fs::write(root.join("data.bin"), vec![0; 200_000]).unwrap();
+
+        let largest = scan(&root, 2, 10).unwrap();
+        let listed: Vec<(String, bool)> = largest
+            .entries
+            .iter()
+            .map(|entry| {
+                (
+                    entry.path.strip_prefix(&largest.root).unwrap().to_string(),
+                    entry.is_dir,
+                )
+            })
+            .collect();
+        let sep = std::path::MAIN_SEPARATOR;
+        // logs/old/app.1 is 3 levels down
+        assert_eq!(
+            listed,
+            [
+                (format!("{sep}logs"), true),
+                (format!("{sep}logs{sep}old"), true),
+                (format!("{sep}data.bin"), false),
+                (format!("{sep}logs{sep}app"), false),
+            ]
+        );
+        assert!(largest.entries[0].size >= 400_000);
+        assert!(largest.total_size >= 600_000);
+        assert_eq!(largest.unreadable, 0);
+
+        assert_eq!(scan(&root, 2, 1).unwrap().entries.len(), 1);
+        assert!(scan(&root.join("missing"), 2, 1).is_err());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
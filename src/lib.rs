@@ -0,0 +1,4328 @@
+//! # RustGetSystemInfo
+//!
+//! A system information utility that collects and displays system metrics
+//! including OS details, CPU cores, memory usage, and swap information.
+//!
+//! The program displays information in a human-readable format to the console
+//! and exports the raw data as JSON to a file for programmatic use.
+//!
+//! The `RustGetSystemInfo` binary only calls `main`. With the `ffi` feature,
+//! the library is also built as a shared library exporting a C interface to
+//! the collector (see `include/rust_get_system_info.h`).
+
+mod advice;
+mod aggregate;
+mod array;
+mod baseline;
+mod batch;
+mod bundle;
+mod cache;
+mod cli;
+mod clipboard;
+mod collection;
+mod command;
+mod containers;
+mod cpu;
+mod diff;
+mod diskio;
+mod entropy;
+mod events;
+mod explain;
+mod expr;
+#[cfg(feature = "ffi")]
+mod ffi;
+mod flat;
+mod glob;
+mod growth;
+#[cfg(any(feature = "s3", feature = "self-update"))]
+mod http;
+mod ifkind;
+mod influx;
+mod keys;
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+mod kmsg;
+mod kubernetes;
+mod largest;
+mod limits;
+mod locale;
+mod machine;
+mod markdown;
+mod memory;
+#[cfg(test)]
+mod mock;
+mod mounts;
+#[cfg(feature = "mqtt")]
+mod mqtt;
+#[cfg(feature = "ffi")]
+mod msgpack;
+#[cfg(feature = "host-details")]
+mod netconfig;
+mod nicstats;
+mod ntp;
+mod osstr;
+mod patches;
+mod pidfile;
+mod plugins;
+mod processes;
+mod provider;
+mod quick;
+mod rates;
+mod redact;
+mod rules;
+#[cfg(feature = "s3")]
+mod s3;
+mod security;
+mod selftest;
+#[cfg(feature = "serve")]
+mod server;
+#[cfg_attr(not(windows), allow(dead_code))]
+mod service;
+#[cfg(any(feature = "self-update", feature = "s3"))]
+mod sha256;
+#[cfg(feature = "serve")]
+mod shm;
+mod shutdown;
+mod sockets;
+mod spinner;
+mod splay;
+mod state;
+mod style;
+#[cfg(feature = "host-details")]
+mod sysctl;
+mod table;
+mod terminal;
+mod thermal;
+mod timezone;
+mod topology;
+mod trigger;
+#[cfg(feature = "self-update")]
+mod update;
+mod verify;
+mod volume;
+mod wireless;
+mod zip;
+
+use cache::CacheInfo;
+use cli::{Command, FileFormat, Options, OutputFormat};
+use collection::{CollectionErrors, Worker};
+use cpu::{CpuBreakdown, CpuTimes};
+use keys::KeyCase;
+use locale::Locale;
+use memory::MemoryActivity;
+use patches::UpdateStatus;
+use plugins::PluginResult;
+use provider::{DiskData, SystemProvider};
+use serde::{Deserialize, Serialize};
+use spinner::Spinner;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Instant;
+use style::Color;
+use sysinfo::{System, SystemExt};
+use table::{Align, Table};
+use terminal::Layout;
+use wireless::WirelessInfo;
+
+/// Custom error types for application-specific error handling.
+///
+/// Provides descriptive error messages for common failure scenarios
+/// when collecting system information and writing output files.
+#[derive(Debug)]
+enum AppError {
+    /// Failed to create the output JSON file
+    FileCreation(std::io::Error),
+    /// Failed to write data to the output file
+    FileWrite(std::io::Error),
+    /// Failed to serialize system information to JSON format
+    JsonSerialization(serde_json::Error),
+    /// Failed to read an input file
+    FileRead(std::io::Error),
+    /// A hardware spec file could not be parsed
+    InvalidSpec(String),
+    /// A collection required by the command line (e.g. disks) came back empty
+    EmptyCollection(&'static str),
+    /// A network interface exceeded `--fail-on-nic-errors`
+    NicErrors(String),
+    /// A critical `--check-expr` condition holds
+    CheckFailed(String),
+    /// `--compare-thresholds` rules matched; `severity` is the worst of them
+    AlertsRaised {
+        severity: expr::Severity,
+        rules: Vec<String>,
+    },
+    /// The key requested with `--get` does not exist in the output
+    KeyNotFound(String),
+    /// A `--disk` or `--interface` pattern matched nothing; `available`
+    /// lists the names that were detected
+    NotSelected {
+        what: &'static str,
+        pattern: String,
+        available: Vec<String>,
+    },
+    /// The metric requested with `quick` does not exist
+    UnknownMetric(String),
+    /// The metric requested with `quick` could not be read
+    MetricUnavailable(String),
+    /// An unrecognized or malformed command-line argument was supplied
+    InvalidArgument(String),
+    /// The local query server could not be started
+    #[cfg(feature = "serve")]
+    Listen(String),
+    /// JSON keys could not be renamed to the `--json-keys` convention
+    KeyConversion(String),
+    /// Publishing or reading a memory-mapped snapshot failed
+    #[cfg(feature = "serve")]
+    SharedMemory(std::io::Error),
+    /// Data every snapshot needs (e.g. memory) could not be collected
+    CoreCollection(&'static str),
+    /// Collection hit the `--timeout` deadline; partial data was written,
+    /// unless the system data itself could not be refreshed in time
+    Timeout(std::time::Duration),
+    /// Checking for, downloading, or installing an update failed
+    #[cfg(feature = "self-update")]
+    Update(String),
+    /// Uploading a snapshot to object storage failed
+    #[cfg(feature = "s3")]
+    S3Upload {
+        bucket: String,
+        key: String,
+        message: String,
+    },
+    /// Registering, controlling or running the Windows service failed
+    #[cfg(windows)]
+    Service(std::io::Error),
+    /// Publishing a snapshot to an MQTT broker failed
+    #[cfg(feature = "mqtt")]
+    Mqtt(String),
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::FileCreation(e) => write!(f, "Failed to create file: {}", e),
+            AppError::FileWrite(e) => write!(f, "Failed to write to file: {}", e),
+            AppError::JsonSerialization(e) => write!(f, "Failed to serialize data to JSON: {}", e),
+            AppError::FileRead(e) => write!(f, "Failed to read file: {}", e),
+            AppError::InvalidSpec(msg) => write!(f, "Invalid spec: {}", msg),
+            AppError::EmptyCollection(what) => write!(f, "No {} detected", what),
+            AppError::NicErrors(msg) => write!(f, "Network interface errors: {}", msg),
+            AppError::CheckFailed(msg) => write!(f, "Critical check failed: {}", msg),
+            AppError::AlertsRaised { rules, .. } => {
+                write!(f, "Alert rules matched: {}", rules.join(", "))
+            }
+            AppError::KeyNotFound(key) => write!(f, "No such key: {}", key),
+            AppError::NotSelected {
+                what,
+                pattern,
+                available,
+            } => write!(
+                f,
+                "No {} matches '{}' (available: {})",
+                what,
+                pattern,
+                available.join(", ")
+            ),
+            AppError::UnknownMetric(name) => write!(
+                f,
+                "Unknown metric '{}' (expected one of {})",
+                name,
+                quick::metric_names().join(", ")
+            ),
+            AppError::MetricUnavailable(msg) => write!(f, "Metric unavailable: {}", msg),
+            AppError::InvalidArgument(msg) => write!(f, "Invalid argument: {}", msg),
+            #[cfg(feature = "serve")]
+            AppError::Listen(msg) => write!(f, "Failed to start local server: {}", msg),
+            AppError::KeyConversion(msg) => write!(f, "Cannot rename JSON keys: {}", msg),
+            #[cfg(feature = "serve")]
+            AppError::SharedMemory(e) => write!(f, "Shared memory snapshot failed: {}", e),
+            #[cfg(windows)]
+            AppError::Service(e) => write!(f, "Service operation failed: {}", e),
+            AppError::CoreCollection(what) => write!(f, "Failed to collect {}", what),
+            AppError::Timeout(timeout) => write!(
+                f,
+                "Collection timed out after {:.1}s",
+                timeout.as_secs_f64()
+            ),
+            #[cfg(feature = "self-update")]
+            AppError::Update(msg) => write!(f, "Self-update failed: {}", msg),
+            #[cfg(feature = "s3")]
+            AppError::S3Upload {
+                bucket,
+                key,
+                message,
+            } => write!(f, "Upload to s3://{}/{} failed: {}", bucket, key, message),
+            #[cfg(feature = "mqtt")]
+            AppError::Mqtt(msg) => write!(f, "MQTT publish failed: {}", msg),
+        }
+    }
+}
+
+impl AppError {
+    /// Returns the process exit code for this error.
+    ///
+    /// Most errors exit with 1. The others, so scripts can tell them apart
+    /// from a collection failure:
+    ///
+    /// | Code | Meaning |
+    /// |------|---------|
+    /// | 2 | unknown `quick` metric |
+    /// | 3 | a warning `--compare-thresholds` rule matched (`Severity::exit_code`) |
+    /// | 4 | a critical `--compare-thresholds` rule matched |
+    /// | 5 | the `--get` key does not exist |
+    /// | 6 | a `--disk` or `--interface` pattern matches nothing |
+    /// | 7 | partial data after `--timeout` |
+    fn exit_code(&self) -> i32 {
+        match self {
+            AppError::UnknownMetric(_) => 2,
+            AppError::AlertsRaised { severity, .. } => severity.exit_code(),
+            AppError::KeyNotFound(_) => 5,
+            AppError::NotSelected { .. } => 6,
+            AppError::Timeout(_) => 7,
+            _ => 1,
+        }
+    }
+}
+
+impl Error for AppError {}
+
+/// Fewest characters an interface name is shortened to on narrow terminals.
+const MIN_INTERFACE_NAME: usize = 12;
+
+/// Units used by `format_bytes`, smallest first.
+const BYTE_UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB", "PB", "EB"];
+
+/// Converts raw byte values to human-readable format with appropriate units.
+///
+/// Uses binary prefixes (1024-based) to convert bytes into the most appropriate
+/// unit (B, KB, MB, GB, TB, PB, EB) with 2 decimal places for units larger
+/// than bytes. The unit is chosen with integer comparisons against powers of
+/// 1024, so values exactly on a unit boundary never fall into the smaller unit
+/// through floating-point error, and a value that would round up to "1024.00"
+/// moves to the next unit instead.
+///
+/// # Arguments
+///
+/// * `bytes` - The number of bytes to format
+/// * `min_unit` - Index into `BYTE_UNITS` of the smallest unit to use; `0`
+///   picks the unit automatically from bytes up
+///
+/// # Returns
+///
+/// A formatted string with the value and appropriate unit
+fn format_bytes(bytes: u64, min_unit: usize, locale: Locale) -> String {
+    const THRESHOLD: u64 = 1024;
+
+    let mut unit_index = min_unit.min(BYTE_UNITS.len() - 1);
+    let mut divisor: u64 = THRESHOLD.pow(unit_index as u32);
+    while unit_index < BYTE_UNITS.len() - 1 && bytes / divisor >= THRESHOLD {
+        divisor *= THRESHOLD;
+        unit_index += 1;
+    }
+
+    if unit_index == 0 {
+        return format!("{} {}", bytes, BYTE_UNITS[unit_index]);
+    }
+
+    let mut value = bytes as f64 / divisor as f64;
+    // e.g. 1048575 bytes is 1023.999 KB, which would print as "1024.00 KB"
+    if unit_index < BYTE_UNITS.len() - 1 && (value * 100.0).round() >= 102_400.0 {
+        value /= THRESHOLD as f64;
+        unit_index += 1;
+    }
+    format!(
+        "{} {}",
+        locale.decimal(format!("{:.2}", value)),
+        BYTE_UNITS[unit_index]
+    )
+}
+
+/// Formats a count with `separator` between groups of three digits.
+fn format_count(count: u64, separator: &str) -> String {
+    let digits = count.to_string();
+    let mut text = String::with_capacity(digits.len() + digits.len() / 3 * separator.len());
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(3) {
+            text.push_str(separator);
+        }
+        text.push(digit);
+    }
+    text
+}
+
+/// Formats a percentage with one decimal, clamped to 0–100.
+///
+/// # Returns
+///
+/// "n/a" for NaN and infinite values.
+fn format_percent(percent: f64, locale: Locale) -> String {
+    if !percent.is_finite() {
+        return "n/a".to_string();
+    }
+    // Adding 0.0 turns -0.0 into 0.0, which would otherwise print as "-0.0%"
+    locale.decimal(format!("{:.1}%", percent.clamp(0.0, 100.0) + 0.0))
+}
+
+/// Parses a human-readable size such as "64GB", "1.5 TiB", or "512" into bytes.
+///
+/// Unit suffixes are case-insensitive and, like `format_bytes`, 1024-based:
+/// `K`/`KB`/`KiB`, `M`/`MB`/`MiB`, `G`/`GB`/`GiB`, `T`/`TB`/`TiB`,
+/// `P`/`PB`/`PiB` and `E`/`EB`/`EiB` are accepted, and a bare number or a `B`
+/// suffix means bytes. Whitespace between the number and the unit is
+/// optional.
+///
+/// # Returns
+///
+/// The size in bytes, or `None` if the value is negative, not a number, has
+/// an unknown unit, or does not fit in a `u64` (16 EiB and up).
+fn parse_bytes(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+
+    let exponent = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 0,
+        "k" | "kb" | "kib" => 1,
+        "m" | "mb" | "mib" => 2,
+        "g" | "gb" | "gib" => 3,
+        "t" | "tb" | "tib" => 4,
+        "p" | "pb" | "pib" => 5,
+        "e" | "eb" | "eib" => 6,
+        _ => return None,
+    };
+    // Whole byte counts are exact, even above the 2^53 an f64 holds exactly
+    if exponent == 0 && !number.contains('.') {
+        return number.parse().ok();
+    }
+
+    let number: f64 = number.parse().ok()?;
+    let bytes = (number * 1024f64.powi(exponent)).round();
+    // u64::MAX + 1 is exactly representable, so this rejects anything that
+    // `as` would otherwise saturate
+    if bytes >= 18_446_744_073_709_551_616.0 {
+        return None;
+    }
+    Some(bytes as u64)
+}
+
+/// Disk usage information for a single disk/partition.
+#[derive(Serialize, Deserialize)]
+struct DiskInfo {
+    /// Disk name or mount point, with invalid UTF-8 replaced by U+FFFD
+    name: String,
+    /// Percent-encoded bytes of the mount point when `name` is lossy
+    #[serde(default)]
+    name_raw: Option<String>,
+    /// Whether `name` differs from the real mount point (see `name_raw`)
+    #[serde(default)]
+    path_is_lossy: bool,
+    /// File system type (e.g., "NTFS", "ext4", "APFS")
+    file_system: String,
+    /// Mount options, e.g. "ro" or "noatime"; `None` where the platform
+    /// has no mount table (Linux only)
+    #[serde(default)]
+    mount_options: Option<Vec<String>>,
+    /// File system label, where the platform reports one
+    #[serde(default)]
+    label: Option<String>,
+    /// File system UUID (the volume serial number on Windows), where the
+    /// platform reports one
+    #[serde(default)]
+    uuid: Option<String>,
+    /// Total disk space in bytes
+    total_space: u64,
+    /// Available disk space in bytes
+    available_space: u64,
+    /// Growth of used space in bytes per day over the recent runs in the
+    /// `--state-file`
+    #[serde(default)]
+    growth_bytes_per_day: Option<f64>,
+    /// Days until the disk is full at that growth, capped at 3650; `None`
+    /// unless it is filling up
+    #[serde(default)]
+    estimated_days_until_full: Option<f64>,
+    /// Whether the recent kernel log has storage errors for the disk's
+    /// device (with `--dmesg-scan`)
+    #[serde(default)]
+    recent_fs_errors: Option<bool>,
+}
+
+impl DiskInfo {
+    /// Returns the used share of the disk in percent, or 0 for an empty disk.
+    fn usage_percent(&self) -> f64 {
+        if self.total_space > 0 {
+            let used_space = self.total_space.saturating_sub(self.available_space);
+            used_space as f64 / self.total_space as f64 * 100.0
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Returns the console note for a disk projected to fill up, e.g.
+/// "(~9 days until full)", in red below `threshold` days.
+///
+/// Disks that are not filling up, or only at the capped rate, get none.
+fn until_full_note(disk: &DiskInfo, threshold: f64, colored: bool) -> Option<String> {
+    let days = disk
+        .estimated_days_until_full
+        .filter(|&days| days < growth::MAX_DAYS_UNTIL_FULL)?;
+    let rounded = days.round();
+    let text = if rounded < 1.0 {
+        "(< 1 day until full)".to_string()
+    } else if rounded < 2.0 {
+        "(~1 day until full)".to_string()
+    } else {
+        format!("(~{:.0} days until full)", rounded)
+    };
+    Some(style::paint(&text, Color::Red, colored && days < threshold))
+}
+
+/// Resolves symlinks in a mount point (`--canonical-mounts`).
+///
+/// Falls back to `mount_point` as reported if it cannot be resolved, e.g.
+/// for lack of permissions. The `\\?\` prefix Windows adds to resolved drive
+/// paths is removed again.
+fn canonical_mount(mount_point: &std::ffi::OsStr) -> std::ffi::OsString {
+    let Ok(path) = std::fs::canonicalize(mount_point) else {
+        return mount_point.to_os_string();
+    };
+    match path.to_str().and_then(|path| path.strip_prefix(r"\\?\")) {
+        Some(path) if path.as_bytes().get(1) == Some(&b':') => path.into(),
+        _ => path.into_os_string(),
+    }
+}
+
+/// Returns whether the list of mounted disks is unreadable for lack of
+/// permissions, which also leaves the disk list empty.
+///
+/// Only checked on Linux, by reading `/proc/mounts`, which sysinfo
+/// enumerates disks from.
+#[cfg(target_os = "linux")]
+fn disks_permission_denied() -> bool {
+    std::fs::read("/proc/mounts").is_err_and(|e| e.kind() == io::ErrorKind::PermissionDenied)
+}
+
+/// Returns whether the list of mounted disks is unreadable for lack of
+/// permissions.
+///
+/// Not detectable on this platform, so always `false`.
+#[cfg(not(target_os = "linux"))]
+fn disks_permission_denied() -> bool {
+    false
+}
+
+/// Network interface information.
+#[derive(Serialize, Deserialize)]
+struct NetworkInfo {
+    /// Interface name (e.g., "eth0", "wlan0", "Ethernet"), with invalid
+    /// UTF-8 replaced by U+FFFD
+    name: String,
+    /// Percent-encoded bytes of the interface name when `name` is lossy
+    #[serde(default)]
+    name_raw: Option<String>,
+    /// Whether `name` differs from the real interface name (see `name_raw`)
+    #[serde(default)]
+    name_is_lossy: bool,
+    /// Total bytes received since boot
+    bytes_received: u64,
+    /// Total bytes transmitted since boot
+    bytes_transmitted: u64,
+    /// Total packets received since boot
+    packets_received: u64,
+    /// Total packets transmitted since boot
+    packets_transmitted: u64,
+    /// Receive errors since boot
+    #[serde(default)]
+    errors_received: u64,
+    /// Transmit errors since boot
+    #[serde(default)]
+    errors_transmitted: u64,
+    /// Received packets dropped since boot, where the platform reports them
+    #[serde(default)]
+    drops_received: Option<u64>,
+    /// Packets dropped before transmission since boot, where the platform
+    /// reports them
+    #[serde(default)]
+    drops_transmitted: Option<u64>,
+    /// Wireless link details, or `None` for wired and virtual interfaces
+    wireless: Option<WirelessInfo>,
+    /// Role of the interface, with `--normalize-interfaces`
+    #[serde(default)]
+    kind: Option<ifkind::InterfaceKind>,
+    /// Traffic since the `--network-baseline` snapshot, if the interface is
+    /// in it
+    #[serde(default)]
+    since_baseline: Option<baseline::NetworkDelta>,
+}
+
+impl NetworkInfo {
+    /// Returns the errors and drops in both directions combined.
+    fn faults(&self) -> u64 {
+        [
+            self.errors_received,
+            self.errors_transmitted,
+            self.drops_received.unwrap_or(0),
+            self.drops_transmitted.unwrap_or(0),
+        ]
+        .into_iter()
+        .fold(0u64, u64::saturating_add)
+    }
+}
+
+/// System information data structure for serialization and display.
+///
+/// Contains comprehensive system metrics including operating system details,
+/// CPU information, memory/swap usage statistics, disk usage, and network interfaces.
+/// All memory and disk values are stored as raw bytes for accuracy and consistency.
+/// Fields missing from a deserialized snapshot (e.g. one written by an older
+/// version) take their default values.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct SystemInfo {
+    /// Operating system name (e.g., "Windows", "Linux", "macOS")
+    os_name: String,
+    /// Operating system version string
+    os_version: String,
+    /// Host name of the machine
+    hostname: String,
+    /// Stable per-installation machine identifier, where the OS provides one
+    machine_id: Option<String>,
+    /// When collection started, as an RFC 3339 time in UTC
+    collected_at: Option<timezone::Timestamp>,
+    /// Number of physical CPU cores
+    cpu_cores: usize,
+    /// Cumulative CPU ticks, with the breakdown in percent over the sampling
+    /// interval when sampled
+    cpu_times: Option<CpuTimes>,
+    /// User, system, idle, iowait and irq time in percent across all CPUs
+    /// over the sampling interval, when the breakdown was sampled
+    cpu_breakdown: Option<CpuBreakdown>,
+    /// CPU cache sizes by level, where the platform reports them
+    cpu_cache: Option<CacheInfo>,
+    /// Total system memory in bytes
+    total_memory: u64,
+    /// Temperature of each logical CPU in degrees Celsius, in CPU number
+    /// order, where every CPU can be matched to a core sensor
+    core_temperatures: Option<Vec<f32>>,
+    /// All temperature sensors, reported instead of `core_temperatures`
+    /// when those are unavailable
+    component_temperatures: Option<Vec<thermal::ComponentTemperature>>,
+    /// Temperature history and throttling, with `--interval`
+    thermal: Option<thermal::ThermalInfo>,
+    /// Currently used memory in bytes
+    used_memory: u64,
+    /// Highest used memory in bytes seen by any sample of this run so far,
+    /// this one included; every run starts over
+    peak_used_memory: Option<u64>,
+    /// Total swap space in bytes
+    total_swap: u64,
+    /// Currently used swap space in bytes
+    used_swap: u64,
+    /// Paging activity sampled during collection (with `--memory-activity`)
+    memory_activity: Option<MemoryActivity>,
+    /// Memory use and limit of the cgroup this process runs in (Linux only)
+    cgroup_memory: Option<memory::CgroupMemory>,
+    /// Major page faults per second since the previous sample (interval mode)
+    major_page_faults_per_sec: Option<f64>,
+    /// Pages swapped in per second since the previous sample (interval mode)
+    pages_swapped_in_per_sec: Option<f64>,
+    /// Pages swapped out per second since the previous sample (interval mode)
+    pages_swapped_out_per_sec: Option<f64>,
+    /// Disk usage information for all detected disks
+    disks: Vec<DiskInfo>,
+    /// Usage of the `--root-disk` disk in percent, if it was found
+    root_disk_usage_percent: Option<f64>,
+    /// The largest files and directories below the `--scan-largest` path
+    largest_paths: Option<largest::LargestPaths>,
+    /// Bytes read per second from all physical disks since the previous
+    /// sample (interval mode)
+    total_disk_read_per_sec: Option<f64>,
+    /// Bytes written per second to all physical disks since the previous
+    /// sample (interval mode)
+    total_disk_write_per_sec: Option<f64>,
+    /// Network interface statistics
+    networks: Vec<NetworkInfo>,
+    /// Bytes received, summed over all reported interfaces
+    total_bytes_received: u64,
+    /// Bytes transmitted, summed over all reported interfaces
+    total_bytes_transmitted: u64,
+    /// Traffic and disk growth since the previous run (with `--state-file`)
+    rates: Option<state::Rates>,
+    /// Gateway of the primary IPv4 default route, if any
+    default_gateway: Option<String>,
+    /// Configured DNS servers in resolver order
+    dns_servers: Vec<String>,
+    /// Offset of the local clock from the `--check-clock` NTP server in
+    /// milliseconds, positive when the local clock is ahead
+    clock_offset_ms: Option<i64>,
+    /// Kernel parameters by name, e.g. "vm.swappiness" (Linux only)
+    sysctls: Option<BTreeMap<String, String>>,
+    /// Pending updates and reboot state (with `--updates`)
+    updates: Option<UpdateStatus>,
+    /// Disk encryption and secure boot state (with `--security`)
+    security: Option<security::SecurityStatus>,
+    /// TCP socket states and UDP socket count (with `--sockets-summary`)
+    sockets: Option<sockets::SocketSummary>,
+    /// Number of processes in each state, e.g. "sleeping" or "zombie" (with
+    /// `--process-states`)
+    process_states: Option<BTreeMap<String, usize>>,
+    /// Storage errors in the recent kernel log (with `--dmesg-scan`)
+    kernel_errors: Option<kmsg::KernelErrors>,
+    /// Software RAID arrays and LVM volume groups (with `--storage-topology`)
+    storage_topology: Option<topology::StorageTopology>,
+    /// Resource usage of each running container (with `--containers`)
+    containers: Option<containers::Containers>,
+    /// Node, pod and namespace when running in a Kubernetes pod
+    kubernetes: Option<kubernetes::KubernetesInfo>,
+    /// Open files, processes and threads against the kernel's global limits
+    limits_usage: Option<limits::LimitsUsage>,
+    /// Entropy pool and RNG state (Linux only)
+    entropy: Option<entropy::Entropy>,
+    /// `--compare-thresholds` rules that matched this sample
+    alerts: Option<Vec<rules::Alert>>,
+    /// Guidance on this sample (with `--advise-json`)
+    advice: Option<Vec<advice::Advice>>,
+    /// Results of the `--plugin-dir` plugins, keyed by file name
+    plugins: Option<BTreeMap<String, PluginResult>>,
+    /// How the core data was refreshed (with `--debug-meta`)
+    debug_refresh_info: Option<provider::RefreshInfo>,
+    /// Optional collectors that failed, as "<field>: <reason>"; their
+    /// fields are null
+    collection_errors: Vec<String>,
+    /// Whether collectors were abandoned at the `--timeout` deadline
+    #[serde(skip)]
+    partial: bool,
+}
+
+/// Collects a snapshot of system metrics from a refreshed provider.
+///
+/// Converts the provider's data into the serializable `SystemInfo` structure,
+/// substituting "N/A" or zero for values the platform does not report.
+/// Interfaces that have neither sent nor received any bytes are omitted
+/// when `options.skip_idle_networks` is set, and with `--disk` or
+/// `--interface` only the matching disks and interfaces are kept, before
+/// the root disk and the network totals are worked out.
+///
+/// Optional collectors still running at `deadline` are abandoned, leaving
+/// their fields null; the snapshot is then marked `partial`.
+///
+/// Whatever order the provider lists them in, disks are sorted by mount
+/// point, interfaces by name and temperature sensors by label, so
+/// snapshots of an unchanged host serialize identically.
+///
+/// # Errors
+///
+/// Returns `AppError::CoreCollection` if the provider reports no memory,
+/// or `AppError::NotSelected` if a `--disk` or `--interface` pattern
+/// matches no disk or interface.
+fn collect_system_info<P: SystemProvider>(
+    sys: &P,
+    options: &Options,
+    deadline: Option<Instant>,
+) -> Result<SystemInfo, AppError> {
+    // Memory is core data: without it the snapshot is not worth writing
+    if sys.total_memory() == 0 {
+        return Err(AppError::CoreCollection("memory"));
+    }
+    let errors = Arc::new(CollectionErrors::default());
+    let collected_at = timezone::Timestamp::now();
+
+    // Start the optional collectors, which sample CPU times (when asked)
+    // and paging activity over the same interval, while the core data is
+    // read. Those behind a cargo feature are guarded by a `cfg!` that is
+    // constant false without it, so the optimizer leaves them out of the
+    // binary
+    let interval = options.sample_interval();
+    let cpu_sample = options.sample_cpu().then_some(interval);
+    let cpu_times = Worker::spawn(&errors, "cpu_times", move || {
+        cpu::read_cpu_times(cpu_sample)
+    });
+    let memory_activity = options.memory_activity.then(|| {
+        Worker::spawn(&errors, "memory_activity", move || {
+            memory::sample_memory_activity(interval)
+        })
+    });
+    let updates = (cfg!(feature = "probes") && options.updates)
+        .then(|| Worker::spawn(&errors, "updates", || Some(patches::query())));
+    let clock = options
+        .check_clock
+        .clone()
+        .filter(|_| cfg!(feature = "probes"))
+        .map(|server| {
+            let max_offset = options.max_clock_offset();
+            Worker::spawn_fallible(&errors, "clock_offset_ms", move || {
+                clock_offset(&server, max_offset)
+            })
+        });
+    let plugins = options
+        .plugin_dir
+        .clone()
+        .filter(|_| cfg!(feature = "probes"))
+        .map(|dir| {
+            let timeout = options.plugin_timeout();
+            Worker::spawn_fallible(&errors, "plugins", move || {
+                plugins::run_all(&dir, timeout).map_err(|e| {
+                    format!("could not read plugin directory {}: {}", dir.display(), e)
+                })
+            })
+        });
+    let core_temperatures = cfg!(feature = "sensors")
+        .then(|| Worker::spawn(&errors, "core_temperatures", thermal::core_temperatures));
+    let machine_id = Worker::spawn(&errors, "machine_id", machine::machine_id);
+    let host_details = spawn_host_details(&errors, options);
+    let security = (cfg!(feature = "probes") && options.security)
+        .then(|| Worker::spawn(&errors, "security", || Some(security::query())));
+    let sockets = (cfg!(feature = "probes") && options.sockets_summary)
+        .then(|| Worker::spawn(&errors, "sockets", sockets::query));
+    let process_states = (cfg!(feature = "processes") && options.process_states)
+        .then(|| Worker::spawn(&errors, "process_states", processes::count_states));
+    let docker_socket = containers::socket_path(options.docker_socket.as_deref());
+    let containers = (cfg!(feature = "containers") && options.containers).then(|| {
+        Worker::spawn_fallible(&errors, "containers", move || {
+            containers::query(&docker_socket)
+        })
+    });
+    let largest_paths = options.scan_largest.clone().map(|path| {
+        let (max_depth, top) = (options.max_depth(), options.top());
+        Worker::spawn_fallible(&errors, "largest_paths", move || {
+            largest::scan(&path, max_depth, top)
+                .map_err(|e| format!("could not scan {}: {}", path.display(), e))
+        })
+    });
+    let storage_topology = (cfg!(feature = "probes") && options.storage_topology)
+        .then(|| Worker::spawn_fallible(&errors, "storage_topology", topology::query));
+    let kernel_errors = (cfg!(feature = "probes") && options.dmesg_scan)
+        .then(|| Worker::spawn_fallible(&errors, "kernel_errors", kmsg::scan));
+    // Disks are marked with the errors found, so wait for the scan first; it
+    // only reads the in-memory log buffer
+    let kernel_errors = kernel_errors.and_then(|scan| scan.wait(&errors, deadline));
+
+    // Mount options, volume IDs and canonical mount points are read from
+    // the file systems themselves, where a hung network mount blocks, so
+    // they are read on a worker too. Disks are core data, but on a timeout
+    // the snapshot is written without them
+    let disk_errors = Arc::clone(&errors);
+    let disk_data = sys.disks();
+    let (min_disk_size, canonical_mounts) =
+        (options.min_disk_size.unwrap_or(0), options.canonical_mounts);
+    let disks = Worker::spawn(&errors, "disks", move || {
+        Some(read_disks(
+            disk_data,
+            min_disk_size,
+            canonical_mounts,
+            &disk_errors,
+        ))
+    });
+    let disks: Vec<DiskInfo> = match disks.wait(&errors, deadline) {
+        Some(disks) => {
+            let mut disks: Vec<DiskInfo> = disks
+                .into_iter()
+                .map(|(device, disk)| DiskInfo {
+                    recent_fs_errors: kernel_errors
+                        .as_ref()
+                        .map(|kernel_errors| kernel_errors.affects(&device)),
+                    ..disk
+                })
+                .collect();
+            // Mount points can repeat, e.g. with a file system mounted over
+            // another
+            disks.sort_by(|a, b| {
+                (&a.name, &a.file_system, a.total_space).cmp(&(
+                    &b.name,
+                    &b.file_system,
+                    b.total_space,
+                ))
+            });
+            select(&mut disks, &options.disks, "mount point", |disk| &disk.name)?;
+            disks
+        }
+        None => Vec::new(),
+    };
+
+    let root_disk_usage_percent = match disks.iter().find(|d| d.name == options.root_disk()) {
+        Some(disk) => Some(disk.usage_percent()),
+        // Only a mount point the user asked for is worth a warning
+        None if options.root_disk.is_none() => None,
+        None => {
+            static WARNED: std::sync::Once = std::sync::Once::new();
+            WARNED.call_once(|| {
+                eprintln!(
+                    "Warning: root disk '{}' not found among the detected disks",
+                    options.root_disk()
+                )
+            });
+            None
+        }
+    };
+
+    // Collect network information
+    let mut networks: Vec<NetworkInfo> = sys
+        .networks()
+        .into_iter()
+        .filter(|network| {
+            !options.skip_idle_networks
+                || network.bytes_received > 0
+                || network.bytes_transmitted > 0
+        })
+        .map(|network| {
+            let (name, name_raw) = osstr::lossless(&network.name);
+            let drops = errors.optional("networks", || nicstats::query(&network.name));
+            let wireless = cfg!(feature = "sensors")
+                .then(|| errors.optional("networks", || wireless::query(&network.name)))
+                .flatten();
+            let kind = if options.normalize_interfaces {
+                ifkind::infer(&network.name, wireless.is_some())
+            } else {
+                None
+            };
+            NetworkInfo {
+                wireless,
+                kind,
+                name,
+                name_is_lossy: name_raw.is_some(),
+                name_raw,
+                bytes_received: network.bytes_received,
+                bytes_transmitted: network.bytes_transmitted,
+                packets_received: network.packets_received,
+                packets_transmitted: network.packets_transmitted,
+                errors_received: network.errors_received,
+                errors_transmitted: network.errors_transmitted,
+                drops_received: drops.map(|drops| drops.received),
+                drops_transmitted: drops.map(|drops| drops.transmitted),
+                since_baseline: None,
+            }
+        })
+        .collect();
+    networks.sort_by(|a, b| a.name.cmp(&b.name));
+    select(
+        &mut networks,
+        &options.interfaces,
+        "network interface",
+        |network| &network.name,
+    )?;
+
+    // Totals cover the interfaces left after filtering; counters of busy
+    // hosts are large, so saturate rather than overflow
+    let total_bytes_received = networks.iter().fold(0u64, |total, network| {
+        total.saturating_add(network.bytes_received)
+    });
+    let total_bytes_transmitted = networks.iter().fold(0u64, |total, network| {
+        total.saturating_add(network.bytes_transmitted)
+    });
+
+    // Wait for the optional collectors, abandoning those still running at
+    // the deadline
+    let cpu_times = cpu_times.wait(&errors, deadline);
+    let cpu_breakdown = cpu_times
+        .as_ref()
+        .filter(|times| times.interval_secs.is_some())
+        .map(|times| times.total);
+    let memory_activity = memory_activity.and_then(|activity| activity.wait(&errors, deadline));
+    let updates = updates.and_then(|updates| updates.wait(&errors, deadline));
+    let clock = clock.and_then(|clock| clock.wait(&errors, deadline));
+    let plugins = plugins.and_then(|plugins| plugins.wait(&errors, deadline));
+    let core_temperatures =
+        core_temperatures.and_then(|temperatures| temperatures.wait(&errors, deadline));
+    let security = security.and_then(|security| security.wait(&errors, deadline));
+    let sockets = sockets.and_then(|sockets| sockets.wait(&errors, deadline));
+    let process_states = process_states.and_then(|states| states.wait(&errors, deadline));
+    let containers = containers.and_then(|containers| containers.wait(&errors, deadline));
+    let largest_paths = largest_paths.and_then(|scan| scan.wait(&errors, deadline));
+    let storage_topology = storage_topology.and_then(|topology| topology.wait(&errors, deadline));
+    let host_details = host_details(deadline);
+
+    Ok(SystemInfo {
+        os_name: sys.os_name().unwrap_or_else(|| "N/A".to_string()),
+        os_version: sys.os_version().unwrap_or_else(|| "N/A".to_string()),
+        hostname: sys.host_name().unwrap_or_else(|| "N/A".to_string()),
+        machine_id: machine_id.wait(&errors, deadline),
+        collected_at: Some(collected_at),
+        cpu_cores: sys.physical_core_count().unwrap_or(0),
+        cpu_times,
+        cpu_breakdown,
+        cpu_cache: host_details.cpu_cache,
+        total_memory: sys.total_memory(),
+        used_memory: sys.used_memory(),
+        peak_used_memory: None,
+        component_temperatures: match core_temperatures {
+            Some(_) => None,
+            None if cfg!(feature = "sensors") => {
+                let mut components = sys.components();
+                components
+                    .sort_by(|a, b| a.label.cmp(&b.label).then(a.celsius.total_cmp(&b.celsius)));
+                Some(components).filter(|components| !components.is_empty())
+            }
+            None => None,
+        },
+        core_temperatures,
+        thermal: None,
+        total_swap: sys.total_swap(),
+        used_swap: sys.used_swap(),
+        memory_activity,
+        cgroup_memory: host_details.cgroup_memory,
+        major_page_faults_per_sec: None,
+        pages_swapped_in_per_sec: None,
+        pages_swapped_out_per_sec: None,
+        disks,
+        root_disk_usage_percent,
+        largest_paths,
+        total_disk_read_per_sec: None,
+        total_disk_write_per_sec: None,
+        networks,
+        total_bytes_received,
+        total_bytes_transmitted,
+        rates: None,
+        default_gateway: host_details.default_gateway,
+        dns_servers: host_details.dns_servers,
+        clock_offset_ms: clock,
+        sysctls: host_details.sysctls,
+        updates,
+        security,
+        sockets,
+        process_states,
+        kernel_errors,
+        storage_topology,
+        containers,
+        kubernetes: host_details.kubernetes,
+        limits_usage: host_details.limits_usage,
+        entropy: host_details.entropy,
+        alerts: None,
+        advice: None,
+        plugins,
+        debug_refresh_info: options.debug_meta.then(|| sys.refresh_info()).flatten(),
+        collection_errors: errors.take(),
+        partial: errors.timed_out(),
+    })
+}
+
+/// The sections of the `host-details` feature, all `None` or empty
+/// without it.
+#[derive(Default)]
+struct HostDetails {
+    cpu_cache: Option<CacheInfo>,
+    cgroup_memory: Option<memory::CgroupMemory>,
+    default_gateway: Option<String>,
+    dns_servers: Vec<String>,
+    sysctls: Option<BTreeMap<String, String>>,
+    kubernetes: Option<kubernetes::KubernetesInfo>,
+    limits_usage: Option<limits::LimitsUsage>,
+    entropy: Option<entropy::Entropy>,
+}
+
+/// Starts the collectors of the `host-details` sections, returning a
+/// function that waits for them until a deadline.
+#[cfg(feature = "host-details")]
+fn spawn_host_details(
+    errors: &Arc<CollectionErrors>,
+    options: &Options,
+) -> impl FnOnce(Option<Instant>) -> HostDetails {
+    let cpu_cache = Worker::spawn(errors, "cpu_cache", cache::cache_info);
+    let cgroup_memory = Worker::spawn(errors, "cgroup_memory", memory::cgroup_memory);
+    let default_gateway = Worker::spawn(errors, "default_gateway", netconfig::default_gateway);
+    let dns_servers = Worker::spawn(errors, "dns_servers", || Some(netconfig::dns_servers()));
+    let extra_sysctls = options.sysctls.clone();
+    let sysctls = Worker::spawn(errors, "sysctls", move || sysctl::read(&extra_sysctls));
+    let kubernetes = Worker::spawn(errors, "kubernetes", kubernetes::detect);
+    let limits_usage = Worker::spawn(errors, "limits_usage", limits::query);
+    let entropy = Worker::spawn(errors, "entropy", entropy::query);
+    let errors = Arc::clone(errors);
+    move |deadline| HostDetails {
+        cpu_cache: cpu_cache.wait(&errors, deadline),
+        cgroup_memory: cgroup_memory.wait(&errors, deadline),
+        default_gateway: default_gateway.wait(&errors, deadline),
+        dns_servers: dns_servers.wait(&errors, deadline).unwrap_or_default(),
+        sysctls: sysctls.wait(&errors, deadline),
+        kubernetes: kubernetes.wait(&errors, deadline),
+        limits_usage: limits_usage.wait(&errors, deadline),
+        entropy: entropy.wait(&errors, deadline),
+    }
+}
+
+/// Leaves the `host-details` sections empty, as the feature is disabled.
+#[cfg(not(feature = "host-details"))]
+fn spawn_host_details(
+    _errors: &Arc<CollectionErrors>,
+    _options: &Options,
+) -> impl FnOnce(Option<Instant>) -> HostDetails {
+    |_| HostDetails::default()
+}
+
+/// Converts the provider's `disks` of at least `min_size` bytes, reading
+/// their mount options and volume IDs, and resolving their mount points if
+/// `canonical` is set. Each disk is returned with its device, and without
+/// `recent_fs_errors`.
+fn read_disks(
+    disks: Vec<DiskData>,
+    min_size: u64,
+    canonical: bool,
+    errors: &CollectionErrors,
+) -> Vec<(std::ffi::OsString, DiskInfo)> {
+    let volumes = volume::Identifier::new();
+    let mount_options = errors.optional("disks", mounts::read);
+    disks
+        .into_iter()
+        .filter(|disk| disk.total_space >= min_size)
+        .map(|disk| {
+            let mount_point = if canonical {
+                canonical_mount(&disk.mount_point)
+            } else {
+                disk.mount_point.clone()
+            };
+            let (name, name_raw) = osstr::lossless(&mount_point);
+            let ids = errors
+                .optional("disks", || {
+                    Some(volumes.ids(&disk.device, Path::new(&disk.mount_point)))
+                })
+                .unwrap_or_default();
+            let info = DiskInfo {
+                label: ids.label,
+                uuid: ids.uuid,
+                name,
+                path_is_lossy: name_raw.is_some(),
+                name_raw,
+                file_system: String::from_utf8_lossy(&disk.file_system).to_string(),
+                mount_options: mount_options
+                    .as_ref()
+                    .and_then(|options| options.get(&disk.mount_point).cloned()),
+                total_space: disk.total_space,
+                available_space: disk.available_space,
+                growth_bytes_per_day: None,
+                estimated_days_until_full: None,
+                recent_fs_errors: None,
+            };
+            (disk.device, info)
+        })
+        .collect()
+}
+
+/// Keeps the `items` whose name matches one of the wildcard `patterns`, or
+/// all of them if there are no patterns.
+///
+/// # Errors
+///
+/// Returns `AppError::NotSelected` for the first pattern that matches no
+/// item, listing the names of all of them.
+fn select<T>(
+    items: &mut Vec<T>,
+    patterns: &[String],
+    what: &'static str,
+    name: impl Fn(&T) -> &str,
+) -> Result<(), AppError> {
+    if let Some(pattern) = patterns.iter().find(|pattern| {
+        !items
+            .iter()
+            .any(|item| glob::matches(pattern, name(item), cfg!(windows)))
+    }) {
+        return Err(AppError::NotSelected {
+            what,
+            pattern: pattern.clone(),
+            available: items.iter().map(|item| name(item).to_string()).collect(),
+        });
+    }
+    if !patterns.is_empty() {
+        items.retain(|item| glob::matches_any(patterns, name(item)));
+    }
+    Ok(())
+}
+
+/// Measures the local clock's offset from the NTP `server` in milliseconds.
+///
+/// Warns on stderr if the offset exceeds `max_offset_ms`.
+///
+/// # Errors
+///
+/// Returns why the server could not be queried.
+fn clock_offset(server: &str, max_offset_ms: u64) -> Result<i64, String> {
+    let offset = ntp::clock_offset_ms(server)
+        .map_err(|e| format!("could not query NTP server {}: {}", server, e))?;
+    if offset.unsigned_abs() > max_offset_ms {
+        eprintln!(
+            "Warning: local clock is off by {:+} ms from NTP server {}",
+            offset, server
+        );
+    }
+    Ok(offset)
+}
+
+/// Refreshes all system data, including the disk and network lists, and
+/// collects a `SystemInfo` snapshot within the `--timeout`, if one is given.
+///
+/// The same provider is reused across interval iterations. A progress
+/// spinner is shown during the refresh when stdout is a terminal and quiet
+/// mode is off.
+///
+/// # Errors
+///
+/// Returns an error if core data such as memory cannot be collected.
+fn collect<P: SystemProvider + Default + Send + 'static>(
+    sys: &mut P,
+    options: &Options,
+) -> Result<SystemInfo, AppError> {
+    let spinner = if options.quiet || options.events.is_some() {
+        None
+    } else {
+        Spinner::start("Collecting system information...")
+    };
+
+    let deadline = options.timeout.map(|timeout| Instant::now() + timeout);
+    let info =
+        refresh(sys, options, deadline).and_then(|()| collect_system_info(sys, options, deadline));
+
+    if let Some(spinner) = spinner {
+        spinner.stop();
+    }
+    info
+}
+
+/// Refreshes `sys` on a worker thread, waiting for it until `deadline`.
+///
+/// The provider is moved to the worker and back, so a refresh blocked by,
+/// say, a hung NFS mount can be abandoned; `sys` is then left a new,
+/// unrefreshed provider.
+///
+/// # Errors
+///
+/// Returns `AppError::Timeout` if the refresh is still running at the
+/// deadline, or `AppError::CoreCollection` if it panicked.
+fn refresh<P: SystemProvider + Default + Send + 'static>(
+    sys: &mut P,
+    options: &Options,
+    deadline: Option<Instant>,
+) -> Result<(), AppError> {
+    let errors = Arc::new(CollectionErrors::default());
+    let mut provider = std::mem::take(sys);
+    let refresh = Worker::spawn(&errors, "refresh", move || {
+        provider.refresh();
+        Some(provider)
+    });
+    match (refresh.wait(&errors, deadline), options.timeout) {
+        (Some(provider), _) => {
+            *sys = provider;
+            Ok(())
+        }
+        (None, Some(timeout)) if errors.timed_out() => Err(AppError::Timeout(timeout)),
+        (None, _) => Err(AppError::CoreCollection("system data")),
+    }
+}
+
+/// Prints the human-readable system information report to the console.
+///
+/// Colors are used when stdout is a terminal, and lines are fitted to its
+/// width unless `--width` is given. Write errors are ignored, since a closed
+/// stdout (e.g. when piped into `head`) is not a collection failure.
+fn print_report(info: &SystemInfo, options: &Options) {
+    let colored = style::stdout_supports_color();
+    let width = options.width.or_else(terminal::width);
+    let _ = write_report(&mut io::stdout().lock(), info, options, colored, width);
+}
+
+/// Lays out the disk section of the console report for `layout`.
+///
+/// Returns each line with whether it belongs to the disk at index `root`.
+/// The table layouts shrink the mount column to fit `width`; the stacked
+/// layout shortens long mount points to a line of their own.
+fn disk_lines(
+    disks: &[DiskInfo],
+    root: Option<usize>,
+    layout: Layout,
+    width: Option<usize>,
+    min_unit: usize,
+    locale: Locale,
+) -> Vec<(String, bool)> {
+    let mount = |index: usize, disk: &DiskInfo| {
+        if Some(index) == root {
+            format!("{} (root)", disk.name)
+        } else {
+            disk.name.clone()
+        }
+    };
+    if layout == Layout::Stacked {
+        let mut lines = Vec::with_capacity(disks.len() * 2);
+        for (index, disk) in disks.iter().enumerate() {
+            let name = match width {
+                Some(width) => table::ellipsize(&mount(index, disk), width.saturating_sub(2)),
+                None => mount(index, disk),
+            };
+            let usage = format!(
+                "    {} of {} used ({}), {} available",
+                format_bytes(
+                    disk.total_space.saturating_sub(disk.available_space),
+                    min_unit,
+                    locale
+                ),
+                format_bytes(disk.total_space, min_unit, locale),
+                format_percent(disk.usage_percent(), locale),
+                format_bytes(disk.available_space, min_unit, locale)
+            );
+            let is_root = Some(index) == root;
+            lines.push((format!("  {}", name), is_root));
+            lines.push((usage, is_root));
+        }
+        return lines;
+    }
+
+    // The file system and mount option columns are the first to go on
+    // narrow terminals
+    let has_options = disks
+        .iter()
+        .any(|disk| disk.mount_options.as_ref().is_some_and(|o| !o.is_empty()));
+    let headers: &[&str] = match layout {
+        Layout::Narrow => &["Mount", "Used", "Avail", "Total", "Use%"],
+        _ if has_options => &["Mount", "Used", "Avail", "Total", "Use%", "FS", "Options"],
+        _ => &["Mount", "Used", "Avail", "Total", "Use%", "FS"],
+    };
+    let aligns = [
+        Align::Left,
+        Align::Right,
+        Align::Right,
+        Align::Right,
+        Align::Right,
+        Align::Left,
+        Align::Left,
+    ];
+    let mut table = Table::new(headers, &aligns[..headers.len()]);
+    for (index, disk) in disks.iter().enumerate() {
+        let used_space = disk.total_space.saturating_sub(disk.available_space);
+        let mut row = vec![
+            mount(index, disk),
+            format_bytes(used_space, min_unit, locale),
+            format_bytes(disk.available_space, min_unit, locale),
+            format_bytes(disk.total_space, min_unit, locale),
+            format_percent(disk.usage_percent(), locale),
+        ];
+        if headers.len() > row.len() {
+            row.push(disk.file_system.clone());
+        }
+        if headers.len() > row.len() {
+            row.push(disk.mount_options.as_deref().unwrap_or_default().join(","));
+        }
+        table.push(row);
+    }
+    // Row lines follow the header line
+    table
+        .render("  ", width, 0)
+        .into_iter()
+        .enumerate()
+        .map(|(index, line)| (line, root.is_some_and(|root| index == root + 1)))
+        .collect()
+}
+
+/// Steal time (percent) above which the console highlights CPU times.
+const STEAL_WARN_PERCENT: f64 = 5.0;
+
+/// Writes the human-readable system information report to `out`.
+///
+/// Paging rates above `options.swap_warn_rate()` are highlighted in red, and
+/// steal time above `STEAL_WARN_PERCENT` and a clock offset above
+/// `options.max_clock_offset()` in yellow, when `colored` is set. With a
+/// `width`, tables are fitted to it and the disk and network sections use
+/// the `Layout` for that width.
+fn write_report<W: Write>(
+    out: &mut W,
+    info: &SystemInfo,
+    options: &Options,
+    colored: bool,
+    width: Option<usize>,
+) -> io::Result<()> {
+    let layout = Layout::for_width(width);
+    let locale = options.locale();
+    writeln!(out, "System Information:")?;
+    writeln!(out, "  OS Name: {}", info.os_name)?;
+    writeln!(out, "  OS Version: {}", info.os_version)?;
+    writeln!(out, "  Hostname: {}", info.hostname)?;
+    if let Some(collected_at) = info.collected_at {
+        writeln!(
+            out,
+            "  Collected At: {}",
+            options.timezone.format(collected_at)
+        )?;
+    }
+    if let Some(machine_id) = &info.machine_id {
+        writeln!(out, "  Machine ID: {}", machine_id)?;
+    }
+    if let Some(kubernetes) = &info.kubernetes {
+        let unknown = |value: &Option<String>| value.clone().unwrap_or_else(|| "?".to_string());
+        writeln!(
+            out,
+            "  Kubernetes: pod {} in namespace {} on node {}",
+            unknown(&kubernetes.pod_name),
+            unknown(&kubernetes.namespace),
+            unknown(&kubernetes.node_name)
+        )?;
+    }
+    if let Some(offset) = info.clock_offset_ms {
+        let text = format!("{:+} ms", offset);
+        let skewed = offset.unsigned_abs() > options.max_clock_offset();
+        writeln!(
+            out,
+            "  Clock Offset: {}",
+            style::paint(&text, Color::Yellow, colored && skewed)
+        )?;
+    }
+    writeln!(out, "  CPU Cores: {}", info.cpu_cores)?;
+    if let Some(breakdown) = &info.cpu_breakdown {
+        let parts: Vec<String> = [
+            (breakdown.user, "user"),
+            (breakdown.system, "sys"),
+            (breakdown.iowait, "iowait"),
+            (breakdown.irq, "irq"),
+            (breakdown.steal, "steal"),
+            (breakdown.idle, "idle"),
+        ]
+        .into_iter()
+        .filter_map(|(percent, label)| {
+            let percent = percent?;
+            let text = format!("{} {}", format_percent(percent, locale), label);
+            Some(if label == "steal" && percent > STEAL_WARN_PERCENT {
+                style::paint(&text, Color::Yellow, colored)
+            } else {
+                text
+            })
+        })
+        .collect();
+        if !parts.is_empty() {
+            writeln!(out, "  CPU: {}", parts.join(", "))?;
+        }
+    }
+    if let Some(temperatures) = &info.core_temperatures {
+        writeln!(out, "  Core Temperatures:")?;
+        let mut table = Table::new(
+            &["CPU", "Usage", "Temp"],
+            &[Align::Left, Align::Right, Align::Right],
+        );
+        let per_core = info.cpu_times.as_ref().map(|times| &times.per_core);
+        for (index, celsius) in temperatures.iter().enumerate() {
+            let name = format!("cpu{}", index);
+            let usage = per_core
+                .and_then(|cores| cores.iter().find(|core| core.name == name))
+                .and_then(|core| core.times.idle)
+                .map_or("-".to_string(), |idle| format_percent(100.0 - idle, locale));
+            table.push(vec![name, usage, format!("{:.1} °C", celsius)]);
+        }
+        for line in table.render("    ", width, 0) {
+            writeln!(out, "{}", line)?;
+        }
+    } else if let Some(components) = &info.component_temperatures {
+        let sensors: Vec<String> = components
+            .iter()
+            .map(|component| format!("{} {:.1} °C", component.label, component.celsius))
+            .collect();
+        writeln!(out, "  Temperatures: {}", sensors.join(", "))?;
+    }
+    if let Some(thermal) = &info.thermal {
+        if thermal.throttled {
+            writeln!(
+                out,
+                "  {}",
+                style::paint("THERMAL THROTTLING DETECTED", Color::Red, colored)
+            )?;
+        }
+        let mut parts = Vec::new();
+        if let Some(max) = thermal.max_temp_seen {
+            parts.push(format!("max {:.1} °C seen", max));
+        }
+        if thermal.seconds_above_critical > 0.0 {
+            parts.push(format!(
+                "{:.0}s above critical",
+                thermal.seconds_above_critical
+            ));
+        }
+        if let Some(events) = thermal.throttle_events {
+            parts.push(format!(
+                "{} throttle events since boot",
+                format_count(events, options.thousands_separator())
+            ));
+        }
+        if let Some(ratio) = thermal.frequency_ratio {
+            parts.push(format!(
+                "running at {} of max frequency",
+                format_percent(ratio * 100.0, locale)
+            ));
+        }
+        if !parts.is_empty() {
+            writeln!(out, "  Thermal: {}", parts.join(", "))?;
+        }
+    }
+    if let Some(cache) = &info.cpu_cache {
+        let levels: Vec<String> = cache
+            .levels()
+            .into_iter()
+            .map(|(label, size)| {
+                format!("{} {}", label, format_bytes(size, options.min_unit, locale))
+            })
+            .collect();
+        writeln!(out, "  CPU Cache: {}", levels.join(", "))?;
+    }
+    writeln!(
+        out,
+        "  Total Memory: {}",
+        format_bytes(info.total_memory, options.min_unit, locale)
+    )?;
+    writeln!(
+        out,
+        "  Used Memory: {}",
+        format_bytes(info.used_memory, options.min_unit, locale)
+    )?;
+    if let Some(peak) = info.peak_used_memory {
+        if options.repeating() {
+            writeln!(
+                out,
+                "  Peak Used Memory: {}",
+                format_bytes(peak, options.min_unit, locale)
+            )?;
+        }
+    }
+    writeln!(
+        out,
+        "  Total Swap: {}",
+        format_bytes(info.total_swap, options.min_unit, locale)
+    )?;
+    writeln!(
+        out,
+        "  Used Swap: {}",
+        format_bytes(info.used_swap, options.min_unit, locale)
+    )?;
+    let rate = |value: f64| {
+        let text = format!("{:.1}/s", value);
+        if value > options.swap_warn_rate() {
+            style::paint(&text, Color::Red, colored)
+        } else {
+            text
+        }
+    };
+    if let Some(activity) = &info.memory_activity {
+        writeln!(
+            out,
+            "  Swap Activity: {} in, {} out (pages, sampled over {:.1}s)",
+            rate(activity.swap_in_per_sec),
+            rate(activity.swap_out_per_sec),
+            activity.interval_secs
+        )?;
+        writeln!(
+            out,
+            "  Major Page Faults: {}",
+            rate(activity.major_faults_per_sec)
+        )?;
+    }
+    if let (Some(swap_in), Some(swap_out)) = (
+        info.pages_swapped_in_per_sec,
+        info.pages_swapped_out_per_sec,
+    ) {
+        writeln!(
+            out,
+            "  Swap Activity Since Last Sample: {} in, {} out (pages)",
+            rate(swap_in),
+            rate(swap_out)
+        )?;
+        if swap_in.max(swap_out) > options.swap_warn_rate() {
+            writeln!(
+                out,
+                "  {}",
+                style::paint(
+                    "Warning: the system is actively swapping",
+                    Color::Red,
+                    colored
+                )
+            )?;
+        }
+    }
+    if let Some(faults) = info.major_page_faults_per_sec {
+        writeln!(
+            out,
+            "  Major Page Faults Since Last Sample: {}",
+            rate(faults)
+        )?;
+        if faults > options.swap_warn_rate() {
+            writeln!(
+                out,
+                "  {}",
+                style::paint(
+                    "Warning: high major page fault rate, the system may be thrashing",
+                    Color::Red,
+                    colored
+                )
+            )?;
+        }
+    }
+
+    writeln!(out, "\nDisk Usage:")?;
+    if info.disks.is_empty() {
+        if disks_permission_denied() {
+            writeln!(
+                out,
+                "  No disks detected (possibly insufficient permissions)"
+            )?;
+        } else {
+            writeln!(out, "  No disks detected")?;
+        }
+    } else {
+        let root = info
+            .disks
+            .iter()
+            .position(|disk| disk.name == options.root_disk());
+        // The root disk is colored whole so the escape codes do not disturb
+        // column widths
+        let lines = disk_lines(&info.disks, root, layout, width, options.min_unit, locale);
+        for (index, (line, is_root)) in lines.into_iter().enumerate() {
+            // Notes go on each disk's usage line, below its name when
+            // stacked and below the header in a table
+            let disk = match layout {
+                Layout::Stacked => (index % 2 == 1).then_some(index / 2),
+                _ => index.checked_sub(1),
+            };
+            let note: String = disk
+                .and_then(|disk| info.disks.get(disk))
+                .map(|disk| {
+                    let errors = (disk.recent_fs_errors == Some(true))
+                        .then(|| style::paint("(kernel errors)", Color::Red, colored));
+                    until_full_note(disk, options.days_until_full_warning(), colored)
+                        .into_iter()
+                        .chain(errors)
+                        .map(|note| format!("  {}", note))
+                        .collect()
+                })
+                .unwrap_or_default();
+            writeln!(
+                out,
+                "{}{}",
+                style::paint(&line, Color::Cyan, colored && is_root),
+                note
+            )?;
+        }
+    }
+    if let (Some(read), Some(written)) =
+        (info.total_disk_read_per_sec, info.total_disk_write_per_sec)
+    {
+        writeln!(
+            out,
+            "  Disk Throughput Since Last Sample: {}/s read, {}/s written",
+            format_bytes(read as u64, options.min_unit, locale),
+            format_bytes(written as u64, options.min_unit, locale)
+        )?;
+    }
+
+    if let Some(largest) = &info.largest_paths {
+        writeln!(
+            out,
+            "\nLargest Paths in {} ({} in total):",
+            largest.root,
+            format_bytes(largest.total_size, options.min_unit, locale)
+        )?;
+        for entry in &largest.entries {
+            let mut path = entry.path.clone();
+            if entry.is_dir {
+                path.push(std::path::MAIN_SEPARATOR);
+            }
+            // Leave room for the indent and the size column
+            let path = match width {
+                Some(width) => table::ellipsize(&path, width.saturating_sub(14)),
+                None => path,
+            };
+            writeln!(
+                out,
+                "  {:>10}  {}",
+                format_bytes(entry.size, options.min_unit, locale),
+                path
+            )?;
+        }
+        if largest.unreadable > 0 {
+            writeln!(
+                out,
+                "  {}",
+                style::paint(
+                    &format!(
+                        "{} entries could not be read and are not counted",
+                        largest.unreadable
+                    ),
+                    Color::Yellow,
+                    colored
+                )
+            )?;
+        }
+    }
+
+    writeln!(out, "\nNetwork Interfaces:")?;
+    if info.networks.is_empty() {
+        writeln!(out, "  No network interfaces detected")?;
+    } else {
+        for network in &info.networks {
+            let mut suffix = String::new();
+            if let Some(kind) = network.kind {
+                suffix.push_str(&format!(" [{}]", kind.as_str()));
+            }
+            if let Some(wireless) = &network.wireless {
+                suffix.push_str(&format!(" ({})", wireless.summary()));
+            }
+            // Leave room for the indent, the kind and wireless details and
+            // the colon, but keep enough of the name to recognize it
+            let name = match width {
+                Some(width) => table::ellipsize(
+                    &network.name,
+                    width
+                        .saturating_sub(3 + suffix.chars().count())
+                        .max(MIN_INTERFACE_NAME),
+                ),
+                None => network.name.clone(),
+            };
+            writeln!(out, "  {}{}:", name, suffix)?;
+            if layout == Layout::Stacked {
+                writeln!(
+                    out,
+                    "    RX {} ({} pkts), TX {} ({} pkts)",
+                    format_bytes(network.bytes_received, options.min_unit, locale),
+                    format_count(network.packets_received, options.thousands_separator()),
+                    format_bytes(network.bytes_transmitted, options.min_unit, locale),
+                    format_count(network.packets_transmitted, options.thousands_separator())
+                )?;
+            } else {
+                writeln!(
+                    out,
+                    "    Received: {} ({} packets)",
+                    format_bytes(network.bytes_received, options.min_unit, locale),
+                    format_count(network.packets_received, options.thousands_separator())
+                )?;
+                writeln!(
+                    out,
+                    "    Transmitted: {} ({} packets)",
+                    format_bytes(network.bytes_transmitted, options.min_unit, locale),
+                    format_count(network.packets_transmitted, options.thousands_separator())
+                )?;
+            }
+            if network.faults() > 0 {
+                let drops = |count: Option<u64>| {
+                    count.map_or("n/a".to_string(), |count| {
+                        format_count(count, options.thousands_separator())
+                    })
+                };
+                let text = format!(
+                    "Errors: {} in, {} out; Drops: {} in, {} out",
+                    format_count(network.errors_received, options.thousands_separator()),
+                    format_count(network.errors_transmitted, options.thousands_separator()),
+                    drops(network.drops_received),
+                    drops(network.drops_transmitted)
+                );
+                writeln!(out, "    {}", style::paint(&text, Color::Yellow, colored))?;
+            }
+            if let Some(delta) = &network.since_baseline {
+                writeln!(
+                    out,
+                    "    Since Baseline: {} received, {} transmitted{}",
+                    format_bytes(delta.bytes_received, options.min_unit, locale),
+                    format_bytes(delta.bytes_transmitted, options.min_unit, locale),
+                    if delta.counter_reset {
+                        " (counters reset)"
+                    } else {
+                        ""
+                    }
+                )?;
+            }
+        }
+        writeln!(
+            out,
+            "  Network Totals: {} received, {} transmitted",
+            format_bytes(info.total_bytes_received, options.min_unit, locale),
+            format_bytes(info.total_bytes_transmitted, options.min_unit, locale)
+        )?;
+    }
+    writeln!(
+        out,
+        "  Default gateway: {}",
+        info.default_gateway.as_deref().unwrap_or("none")
+    )?;
+    if info.dns_servers.is_empty() {
+        writeln!(out, "  DNS servers: none")?;
+    } else {
+        writeln!(out, "  DNS servers: {}", info.dns_servers.join(", "))?;
+    }
+
+    if let Some(rates) = &info.rates {
+        let per_sec = |rate: f64| {
+            let sign = if rate < 0.0 { "-" } else { "" };
+            format!(
+                "{}{}/s",
+                sign,
+                format_bytes(rate.abs() as u64, options.min_unit, locale)
+            )
+        };
+        match (rates.interval_secs, &rates.note) {
+            (Some(seconds), _) => {
+                writeln!(out, "\nRates Since Previous Run ({:.1}s):", seconds)?;
+                for (name, network) in &rates.networks {
+                    writeln!(
+                        out,
+                        "  {}: {} received, {} transmitted",
+                        name,
+                        per_sec(network.bytes_received_per_sec),
+                        per_sec(network.bytes_transmitted_per_sec)
+                    )?;
+                }
+                for (name, disk) in &rates.disks {
+                    writeln!(
+                        out,
+                        "  Disk {}: {} used",
+                        name,
+                        per_sec(disk.used_bytes_per_sec)
+                    )?;
+                }
+            }
+            (None, note) => {
+                writeln!(out, "\nRates Since Previous Run:")?;
+                writeln!(out, "  none: {}", note.as_deref().unwrap_or("unknown"))?;
+            }
+        }
+    }
+
+    if let Some(sysctls) = info.sysctls.as_ref().filter(|sysctls| !sysctls.is_empty()) {
+        writeln!(out, "\nKernel Parameters:")?;
+        for (key, value) in sysctls {
+            writeln!(out, "  {} = {}", key, value)?;
+        }
+    }
+
+    if let Some(updates) = &info.updates {
+        writeln!(out, "\nUpdates:")?;
+        let reboot = match updates.reboot_required {
+            Some(true) => style::paint("yes", Color::Yellow, colored),
+            Some(false) => "no".to_string(),
+            None => "unknown".to_string(),
+        };
+        writeln!(out, "  Reboot Required: {}", reboot)?;
+        match updates.pending_updates {
+            Some(count) => writeln!(
+                out,
+                "  Pending Updates: {}",
+                format_count(count, options.thousands_separator())
+            )?,
+            None => writeln!(out, "  Pending Updates: unknown")?,
+        }
+    }
+
+    if let Some(security) = &info.security {
+        writeln!(out, "\nSecurity:")?;
+        for (label, check) in [
+            ("Root Volume Encrypted", &security.root_encryption),
+            ("Secure Boot", &security.secure_boot),
+        ] {
+            let summary = check.summary();
+            let disabled = check.enabled == Some(false);
+            writeln!(
+                out,
+                "  {}: {}",
+                label,
+                style::paint(&summary, Color::Yellow, colored && disabled)
+            )?;
+        }
+    }
+
+    if let Some(sockets) = &info.sockets {
+        let separator = options.thousands_separator();
+        writeln!(out, "\nSockets:")?;
+        let states: Vec<String> = sockets
+            .tcp_states
+            .iter()
+            .map(|(state, count)| format!("{} {}", state, format_count(*count, separator)))
+            .collect();
+        if states.is_empty() {
+            writeln!(out, "  TCP: none")?;
+        } else {
+            writeln!(out, "  TCP: {}", states.join(", "))?;
+        }
+        writeln!(
+            out,
+            "  UDP: {}",
+            format_count(sockets.udp_sockets, separator)
+        )?;
+        if !sockets.top_remote_addresses.is_empty() {
+            writeln!(out, "  Top Remote Addresses:")?;
+            for remote in &sockets.top_remote_addresses {
+                writeln!(
+                    out,
+                    "    {}: {} connections",
+                    remote.address,
+                    format_count(remote.connections, separator)
+                )?;
+            }
+        }
+    }
+
+    if let Some(states) = &info.process_states {
+        let separator = options.thousands_separator();
+        let counts: Vec<String> = processes::by_count(states)
+            .into_iter()
+            .map(|(state, count)| {
+                let text = format!("{} {}", format_count(count as u64, separator), state);
+                // Zombies pile up when a parent does not reap its children
+                style::paint(&text, Color::Yellow, colored && state == "zombie")
+            })
+            .collect();
+        writeln!(out, "\nProcesses: {}", counts.join(", "))?;
+    }
+
+    if let Some(kernel_errors) = &info.kernel_errors {
+        let summary = match kernel_errors.seconds_since_last_error {
+            _ if kernel_errors.total_matches == 0 => "none".to_string(),
+            Some(seconds) => format!("{} (last {:.0}s ago)", kernel_errors.total_matches, seconds),
+            None => kernel_errors.total_matches.to_string(),
+        };
+        writeln!(
+            out,
+            "\nKernel Storage Errors: {} in the last {} log records",
+            style::paint(
+                &summary,
+                Color::Red,
+                colored && kernel_errors.total_matches > 0
+            ),
+            kernel_errors.records_scanned
+        )?;
+        for error in &kernel_errors.recent {
+            writeln!(
+                out,
+                "  [{:12.6}] {}",
+                error.seconds_since_boot, error.message
+            )?;
+        }
+    }
+
+    if let Some(topology) = &info.storage_topology {
+        writeln!(out, "\nStorage Topology:")?;
+        if topology.md_arrays.is_empty() && topology.volume_groups.is_empty() {
+            writeln!(out, "  No RAID arrays or LVM volumes")?;
+        }
+        for array in &topology.md_arrays {
+            let mut details = vec![array.level.clone().unwrap_or_else(|| array.state.clone())];
+            if let Some(size) = array.size {
+                details.push(format_bytes(size, options.min_unit, locale));
+            }
+            details.push(array.devices.join(", "));
+            let mut problems = Vec::new();
+            if array.degraded {
+                problems.push("degraded".to_string());
+            }
+            if !array.failed_devices.is_empty() {
+                problems.push(format!("failed: {}", array.failed_devices.join(", ")));
+            }
+            if !array.spare_devices.is_empty() {
+                details.push(format!("spare: {}", array.spare_devices.join(", ")));
+            }
+            let problems = if problems.is_empty() {
+                String::new()
+            } else {
+                format!(
+                    " {}",
+                    style::paint(&format!("[{}]", problems.join(", ")), Color::Red, colored)
+                )
+            };
+            writeln!(out, "  {}: {}{}", array.name, details.join(", "), problems)?;
+        }
+        for group in &topology.volume_groups {
+            writeln!(
+                out,
+                "  Volume Group {} on {}:",
+                group.name,
+                group.physical_volumes.join(", ")
+            )?;
+            for volume in &group.logical_volumes {
+                let size = volume
+                    .size
+                    .map(|size| format!(", {}", format_bytes(size, options.min_unit, locale)))
+                    .unwrap_or_default();
+                writeln!(
+                    out,
+                    "    {} ({}{}) on {}",
+                    volume.name,
+                    volume.device,
+                    size,
+                    volume.backing_devices.join(", ")
+                )?;
+            }
+        }
+    }
+
+    if let Some(containers) = &info.containers {
+        writeln!(
+            out,
+            "\nContainers: {} running, {} stopped",
+            containers.running.len(),
+            containers.stopped
+        )?;
+        for container in &containers.running {
+            let mut usage = Vec::new();
+            if let Some(percent) = container.cpu_percent {
+                // 100% is one whole CPU, so a busy container exceeds 100%
+                usage.push(format!(
+                    "CPU {}",
+                    locale.decimal(format!("{:.1}%", percent))
+                ));
+            }
+            if let Some(used) = container.memory_usage {
+                let mut memory = format!("Memory {}", format_bytes(used, 0, locale));
+                if let Some(limit) = container.memory_limit {
+                    memory.push_str(&format!(" of {}", format_bytes(limit, 0, locale)));
+                }
+                usage.push(memory);
+            }
+            if let (Some(received), Some(transmitted)) = (
+                container.network_bytes_received,
+                container.network_bytes_transmitted,
+            ) {
+                usage.push(format!(
+                    "Network {} in, {} out",
+                    format_bytes(received, 0, locale),
+                    format_bytes(transmitted, 0, locale)
+                ));
+            }
+            if usage.is_empty() {
+                usage.push("usage unavailable".to_string());
+            }
+            writeln!(
+                out,
+                "  {} ({}): {}",
+                container.name,
+                container.image,
+                usage.join(", ")
+            )?;
+        }
+    }
+
+    if let Some(limits) = &info.limits_usage {
+        let separator = options.thousands_separator();
+        writeln!(out, "\nKernel Limits:")?;
+        for (label, usage) in [
+            ("Open Files", &limits.open_files),
+            ("Processes", &limits.processes),
+            ("Threads", &limits.threads),
+        ] {
+            let Some(usage) = usage else { continue };
+            let mut text = format_count(usage.used, separator);
+            if let Some(limit) = usage.limit {
+                text.push_str(&format!(" of {}", format_count(limit, separator)));
+            }
+            if let Some(percent) = usage.percent {
+                text.push_str(&format!(" ({})", format_percent(percent, locale)));
+            }
+            let high = label == "Open Files"
+                && options
+                    .warn_fd_global_pct
+                    .zip(usage.percent)
+                    .is_some_and(|(threshold, percent)| percent > threshold);
+            writeln!(
+                out,
+                "  {}: {}",
+                label,
+                style::paint(&text, Color::Yellow, colored && high)
+            )?;
+        }
+    }
+
+    if let Some(entropy) = &info.entropy {
+        writeln!(out, "\nEntropy:")?;
+        let starved = options
+            .warn_entropy
+            .is_some_and(|threshold| entropy.starved(threshold).is_some());
+        if let Some(bits) = entropy.entropy_avail {
+            let mut text = format!("{} bits", bits);
+            if let Some(size) = entropy.pool_size {
+                text.push_str(&format!(" of {}", size));
+            }
+            if entropy.fixed_estimate {
+                text.push_str(" (fixed estimate)");
+            }
+            writeln!(
+                out,
+                "  Pool: {}",
+                style::paint(&text, Color::Yellow, colored && starved)
+            )?;
+        }
+        if let Some(blocks) = entropy.getrandom_would_block {
+            let text = if blocks { "would block" } else { "ready" };
+            writeln!(
+                out,
+                "  getrandom: {}",
+                style::paint(text, Color::Yellow, colored && blocks)
+            )?;
+        }
+        writeln!(
+            out,
+            "  Hardware RNG: {}",
+            entropy.hw_rng.as_deref().unwrap_or("none")
+        )?;
+    }
+
+    if let Some(plugins) = &info.plugins {
+        writeln!(out, "\nPlugins:")?;
+        if plugins.is_empty() {
+            writeln!(out, "  No plugins found")?;
+        }
+        for (name, result) in plugins {
+            match &result.error {
+                Some(error) => writeln!(
+                    out,
+                    "  {}: {} ({} ms)",
+                    name,
+                    style::paint(error, Color::Red, colored),
+                    result.duration_ms
+                )?,
+                None => writeln!(out, "  {}: ok ({} ms)", name, result.duration_ms)?,
+            }
+        }
+    }
+
+    if options.advise {
+        writeln!(out, "\nAdvice:")?;
+        let advice = advice::advise(info, options.swap_warn_rate());
+        if advice.is_empty() {
+            writeln!(out, "  Nothing stands out")?;
+        }
+        for advice in advice {
+            writeln!(
+                out,
+                "  {}",
+                style::paint(&advice.message, Color::Yellow, colored)
+            )?;
+        }
+    }
+
+    if !info.collection_errors.is_empty() {
+        writeln!(out, "\nCollection Errors:")?;
+        for error in &info.collection_errors {
+            writeln!(out, "  {}", style::paint(error, Color::Yellow, colored))?;
+        }
+    }
+    Ok(())
+}
+
+/// Core application logic for collecting and outputting system information.
+///
+/// Gathers system metrics using the sysinfo crate, displays them in a
+/// human-readable format to the console, and exports the raw data as JSON.
+/// With `--interval`, repeats this every interval until interrupted or, with
+/// `--count`, until that many samples have been written; with `--watch-file`,
+/// repeats it whenever that file is touched. When webhook events
+/// are batched, an interrupt ends the loop gracefully so queued events are
+/// still delivered.
+///
+/// # Returns
+///
+/// * `Ok(())` - If system information was successfully collected and saved
+/// * `Err(AppError)` - If a required collection is empty, or file creation,
+///   writing, or JSON serialization fails
+///
+/// # Errors
+///
+/// This function will return an error if:
+/// * `--require-disks` or `--require-networks` was given and none were detected
+/// * `--get` names a key that does not exist
+/// * The output JSON file cannot be created
+/// * Writing to the JSON file fails
+/// * System information cannot be serialized to JSON
+fn run(options: &Options) -> Result<(), AppError> {
+    #[cfg(not(unix))]
+    if options.mode.is_some() {
+        eprintln!("Warning: --mode is not supported on this platform and is ignored");
+    }
+    let _pid_file = match &options.pid_file {
+        Some(path) => {
+            // Stop on SIGTERM between samples, so the file is removed again
+            shutdown::install();
+            Some(pidfile::PidFile::create(path).map_err(AppError::FileCreation)?)
+        }
+        None => None,
+    };
+    let mut sys = System::new();
+    if let Some(max) = options.splay {
+        let delay = if options.splay_random {
+            splay::random_delay(max)
+        } else {
+            splay::delay_for_host(&SystemProvider::host_name(&sys).unwrap_or_default(), max)
+        };
+        // Installed before the delay is announced, so a SIGTERM sent once
+        // the message is seen always ends the wait cleanly
+        shutdown::install();
+        if options.debug_meta {
+            eprintln!(
+                "Waiting {:.1}s before collecting (--splay)",
+                delay.as_secs_f64()
+            );
+        }
+        if !shutdown::sleep(delay) {
+            return Ok(());
+        }
+    }
+    let mut samples: u64 = 0;
+    let mut watch = match &options.events {
+        Some(target) => {
+            let sink = events::Sink::open(target, options).map_err(AppError::FileCreation)?;
+            let batcher = options.batching().map(batch::Batcher::new);
+            if batcher.is_some() {
+                shutdown::install();
+            }
+            Some((events::Monitor::new(options.thresholds()), sink, batcher))
+        }
+        None => None,
+    };
+    #[cfg(feature = "serve")]
+    let server = start_server(options)?;
+    #[cfg(feature = "mqtt")]
+    let mut mqtt = start_mqtt(options, &sys)?;
+    #[cfg(feature = "serve")]
+    let shm = match &options.shm {
+        Some(path) => {
+            Some(shm::Writer::create(path, shm::DEFAULT_CAPACITY).map_err(AppError::SharedMemory)?)
+        }
+        None => None,
+    };
+    let mut array = if options.array {
+        let file =
+            create_file(Path::new(OUTPUT_FILE), options.mode).map_err(AppError::FileCreation)?;
+        Some(array::ArrayFile::new(file).map_err(AppError::FileCreation)?)
+    } else {
+        None
+    };
+    let mut previous_paging: Option<(memory::PagingCounters, Instant)> = None;
+    let mut previous_disk_io: Option<(diskio::Counters, Instant)> = None;
+    let mut peak_used_memory = 0;
+    #[cfg(feature = "sensors")]
+    let mut thermal = options.repeating().then(thermal::Tracker::new);
+    #[cfg(feature = "history")]
+    let mut network_baseline = match &options.network_baseline {
+        Some(path) => Some(baseline::Baseline::load(path)?),
+        None => None,
+    };
+    #[cfg(feature = "history")]
+    let mut state_file = options.state_file.as_deref().map(state::StateFile::load);
+    let rules = match &options.compare_thresholds {
+        Some(path) => Some(rules::load(path)?),
+        None => None,
+    };
+
+    let mut trigger = options.watch_file.as_deref().map(|path| {
+        // Stop on Ctrl-C while waiting, so queued events and sockets are
+        // cleaned up
+        shutdown::install();
+        trigger::Trigger::new(path)
+    });
+
+    let mut partial = false;
+    // Worst severity and names of the rules matched in any sample
+    let mut raised: Option<expr::Severity> = None;
+    let mut matched: Vec<String> = Vec::new();
+    // Samples are due at fixed multiples of the interval from the first one,
+    // however long each takes to collect
+    let mut due = Instant::now();
+    loop {
+        let mut info = collect(&mut sys, options)?;
+        partial |= info.partial;
+        let paging = memory::read_counters().map(|counters| (counters, Instant::now()));
+        if let (Some((previous, then)), Some((current, now))) = (previous_paging, paging) {
+            let rate = |previous, current| Some(rates::counter_rate(previous, current, now - then));
+            info.major_page_faults_per_sec = rate(previous.major_faults, current.major_faults);
+            info.pages_swapped_in_per_sec = rate(previous.swap_in, current.swap_in);
+            info.pages_swapped_out_per_sec = rate(previous.swap_out, current.swap_out);
+        }
+        previous_paging = paging;
+        let disk_io = diskio::read_counters().map(|counters| (counters, Instant::now()));
+        if let (Some((previous, then)), Some((current, now))) = (&previous_disk_io, &disk_io) {
+            let (read, written) = diskio::total_rates(previous, current, *now - *then);
+            info.total_disk_read_per_sec = Some(read);
+            info.total_disk_write_per_sec = Some(written);
+        }
+        previous_disk_io = disk_io;
+        peak_used_memory = peak_used_memory.max(info.used_memory);
+        info.peak_used_memory = Some(peak_used_memory);
+        #[cfg(feature = "sensors")]
+        {
+            info.thermal = thermal.as_mut().map(thermal::Tracker::observe);
+        }
+        #[cfg(feature = "history")]
+        if let Some(network_baseline) = &mut network_baseline {
+            network_baseline.apply(&mut info)?;
+        }
+        #[cfg(feature = "history")]
+        if let Some(state_file) = &mut state_file {
+            state_file.apply(&mut info, SystemProvider::boot_time(&sys))?;
+        }
+        redact::redact(&mut info, &options.redact);
+        if let Some(rules) = &rules {
+            let value = serde_json::to_value(&info).map_err(AppError::JsonSerialization)?;
+            let alerts = rules::evaluate(rules, &value);
+            for alert in &alerts {
+                raised = raised.max(Some(alert.severity));
+                if !matched.contains(&alert.rule) {
+                    matched.push(alert.rule.clone());
+                }
+            }
+            info.alerts = Some(alerts);
+        }
+        if options.advise_json {
+            info.advice = Some(advice::advise(&info, options.swap_warn_rate()));
+        }
+        #[cfg(feature = "serve")]
+        if let Some(server) = &server {
+            server.publish(serde_json::to_value(&info).map_err(AppError::JsonSerialization)?);
+        }
+        #[cfg(feature = "serve")]
+        if let Some(shm) = &shm {
+            let json = serde_json::to_vec(&info).map_err(AppError::JsonSerialization)?;
+            let timestamp_ms = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |d| d.as_millis() as u64);
+            shm.publish(&json, timestamp_ms)
+                .map_err(AppError::SharedMemory)?;
+        }
+        #[cfg(feature = "mqtt")]
+        if let Some(mqtt) = &mut mqtt {
+            let json = serde_json::to_vec(&info).map_err(AppError::JsonSerialization)?;
+            mqtt.publish(&json).map_err(AppError::Mqtt)?;
+        }
+        match &mut watch {
+            Some((monitor, sink, batcher)) => {
+                emit_events(monitor, sink, batcher.as_mut(), info, options)?
+            }
+            None => output(&info, options, array.as_mut())?,
+        }
+        samples += 1;
+
+        if options.count.is_some_and(|count| samples >= count) {
+            break;
+        }
+        let next = match (&mut trigger, options.interval) {
+            (Some(trigger), _) => trigger.wait(),
+            (None, Some(interval)) => {
+                due = next_due(due, interval, Instant::now());
+                shutdown::sleep_until(due)
+            }
+            (None, None) => break,
+        };
+        if !next {
+            break;
+        }
+    }
+
+    if let Some((_, sink, Some(batcher))) = &mut watch {
+        flush_events(batcher, sink);
+    }
+    outcome(options.timeout, partial, raised, matched)
+}
+
+/// Returns how a run whose samples were all written ends: with
+/// `AppError::Timeout` if a sample is partial because collection hit the
+/// `--timeout`, otherwise with `AppError::AlertsRaised` if `--compare-thresholds`
+/// rules of the worst `raised` severity matched.
+fn outcome(
+    timeout: Option<std::time::Duration>,
+    partial: bool,
+    raised: Option<expr::Severity>,
+    matched: Vec<String>,
+) -> Result<(), AppError> {
+    match (timeout, raised) {
+        (Some(timeout), _) if partial => Err(AppError::Timeout(timeout)),
+        (_, Some(severity)) => Err(AppError::AlertsRaised {
+            severity,
+            rules: matched,
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Returns when the sample after the one due at `due` is due, `interval`
+/// later. If collection overran one or more intervals, the missed samples
+/// are skipped rather than taken back to back, so later samples stay on the
+/// same schedule.
+fn next_due(due: Instant, interval: std::time::Duration, now: Instant) -> Instant {
+    let next = due + interval;
+    if next >= now {
+        return next;
+    }
+    let missed = ((now - next).as_nanos() / interval.as_nanos()) as u32 + 1;
+    next + interval * missed
+}
+
+/// Starts the local query server selected by `--listen-unix` or `--listen-pipe`.
+///
+/// # Errors
+///
+/// Returns `AppError::Listen` if the server cannot be started, or
+/// `AppError::InvalidArgument` if the transport is not available on this
+/// platform.
+#[cfg(feature = "serve")]
+fn start_server(options: &Options) -> Result<Option<server::Server>, AppError> {
+    #[cfg(unix)]
+    if let Some(path) = &options.listen_unix {
+        let access = server::SocketAccess {
+            mode: options.socket_mode,
+            group: options.socket_group.clone(),
+        };
+        return server::listen_unix(path, &access)
+            .map(Some)
+            .map_err(AppError::Listen);
+    }
+    #[cfg(windows)]
+    if let Some(name) = &options.listen_pipe {
+        return server::listen_pipe(name)
+            .map(Some)
+            .map_err(AppError::Listen);
+    }
+
+    if options.listen_unix.is_some() {
+        return Err(AppError::InvalidArgument(
+            "--listen-unix is not supported on this platform".to_string(),
+        ));
+    }
+    if options.listen_pipe.is_some() {
+        return Err(AppError::InvalidArgument(
+            "--listen-pipe is only supported on Windows".to_string(),
+        ));
+    }
+    Ok(None)
+}
+
+/// Creates the `--mqtt-url` publisher; it connects on the first sample.
+///
+/// The keep-alive covers two sampling intervals, so the broker does not
+/// drop the connection between samples.
+///
+/// # Errors
+///
+/// Returns `AppError::InvalidArgument` if the broker URL is malformed.
+#[cfg(feature = "mqtt")]
+fn start_mqtt(options: &Options, sys: &System) -> Result<Option<mqtt::Publisher>, AppError> {
+    let Some(url) = &options.mqtt_url else {
+        return Ok(None);
+    };
+    let hostname = SystemProvider::host_name(sys).unwrap_or_default();
+    let template = options.mqtt_topic.as_deref().unwrap_or(mqtt::DEFAULT_TOPIC);
+    // 0 disables keep-alive, for intervals too long to announce and for
+    // --watch-file, which may wait for a touch indefinitely
+    let keep_alive_secs = match options.interval {
+        Some(interval) => u16::try_from((interval.as_secs() * 2).max(60)).unwrap_or(0),
+        None if options.watch_file.is_some() => 0,
+        None => 60,
+    };
+    let settings = mqtt::Settings {
+        topic: mqtt::expand_topic(template, &hostname),
+        qos: options.mqtt_qos,
+        retain: options.mqtt_retain,
+        keep_alive_secs,
+        ca_file: options
+            .mqtt_ca
+            .clone()
+            .or_else(|| options.ca_bundle.clone()),
+        cert_file: options.mqtt_cert.clone(),
+        key_file: options.mqtt_key.clone(),
+    };
+    mqtt::Publisher::new(url, settings, &hostname)
+        .map(Some)
+        .map_err(AppError::InvalidArgument)
+}
+
+/// Feeds one collected sample to the `--events` monitor and delivers the
+/// resulting events.
+///
+/// With a `batcher` the events are queued instead, and the queue is
+/// delivered once a batch is due. Webhook delivery failures are reported on
+/// stderr and do not stop the loop, so a receiver outage does not end
+/// monitoring.
+fn emit_events(
+    monitor: &mut events::Monitor,
+    sink: &mut events::Sink,
+    batcher: Option<&mut batch::Batcher<events::Event>>,
+    info: SystemInfo,
+    options: &Options,
+) -> Result<(), AppError> {
+    check_required(&info, options)?;
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let events = monitor.observe(info, timestamp);
+
+    if let Some(batcher) = batcher {
+        let now = Instant::now();
+        for event in events {
+            batcher.push(event, now);
+        }
+        if batcher.due(now) {
+            flush_events(batcher, sink);
+        }
+        return Ok(());
+    }
+
+    for event in events {
+        match sink.send(&event) {
+            Ok(()) => {}
+            Err(e) if sink.is_webhook() => {
+                eprintln!("Warning: failed to deliver event: {}", e)
+            }
+            Err(e) => return Err(AppError::FileWrite(e)),
+        }
+    }
+    Ok(())
+}
+
+/// Delivers the events queued in `batcher` to `sink` as one batch.
+///
+/// A failed delivery is reported on stderr; the events stay queued for the
+/// next attempt.
+fn flush_events(batcher: &mut batch::Batcher<events::Event>, sink: &mut events::Sink) {
+    if let Err(e) = batcher.flush(|batch| sink.send_batch(batch)) {
+        eprintln!("Warning: failed to deliver event batch: {}", e);
+    }
+}
+
+/// Fails if a collection required by `--require-disks` or
+/// `--require-networks` is empty, or an interface has more errors and drops
+/// than `--fail-on-nic-errors` allows. Warns if allocated file handles
+/// exceed `--warn-fd-global-pct` of the global limit, if the kernel's RNG
+/// is starved by `--warn-entropy`, and if a disk is projected to be full
+/// within `--warn-days-until-full`.
+fn check_required(info: &SystemInfo, options: &Options) -> Result<(), AppError> {
+    if options.require_disks && info.disks.is_empty() {
+        if disks_permission_denied() {
+            return Err(AppError::EmptyCollection(
+                "disks (possibly insufficient permissions)",
+            ));
+        }
+        return Err(AppError::EmptyCollection("disks"));
+    }
+    if options.require_networks && info.networks.is_empty() {
+        return Err(AppError::EmptyCollection("network interfaces"));
+    }
+    if let Some(threshold) = options.fail_on_nic_errors {
+        if let Some(network) = info
+            .networks
+            .iter()
+            .find(|network| network.faults() > threshold)
+        {
+            return Err(AppError::NicErrors(format!(
+                "{} has {} errors and drops (threshold {})",
+                network.name,
+                network.faults(),
+                threshold
+            )));
+        }
+    }
+    if let (Some(threshold), Some(percent)) = (options.warn_fd_global_pct, fd_global_percent(info))
+    {
+        if percent > threshold {
+            let locale = options.locale();
+            eprintln!(
+                "Warning: {} of the global file handle limit is allocated (threshold {})",
+                format_percent(percent, locale),
+                format_percent(threshold, locale)
+            );
+        }
+    }
+    if let Some((threshold, entropy)) = options.warn_entropy.zip(info.entropy.as_ref()) {
+        if let Some(reason) = entropy.starved(threshold) {
+            eprintln!(
+                "Warning: the entropy pool is starved: {} (threshold {} bits)",
+                reason, threshold
+            );
+        }
+    }
+    if let Some(threshold) = options.warn_days_until_full {
+        for disk in &info.disks {
+            if let Some(days) = disk
+                .estimated_days_until_full
+                .filter(|&days| days < threshold)
+            {
+                eprintln!(
+                    "Warning: {} is projected to be full in {:.1} days (threshold {})",
+                    disk.name, days, threshold
+                );
+            }
+        }
+    }
+    if !options.checks.is_empty() {
+        let value = serde_json::to_value(info).map_err(AppError::JsonSerialization)?;
+        let mut critical = Vec::new();
+        for check in &options.checks {
+            match check.evaluate(&value) {
+                Ok(false) => {}
+                Ok(true) if check.severity == expr::Severity::Critical => {
+                    critical.push(check.source.as_str())
+                }
+                Ok(true) => eprintln!("Warning: check failed: {}", check.source),
+                Err(reason) => eprintln!("Warning: skipping check '{}': {}", check.source, reason),
+            }
+        }
+        if !critical.is_empty() {
+            return Err(AppError::CheckFailed(critical.join("; ")));
+        }
+    }
+    Ok(())
+}
+
+/// Allocated file handles as a percentage of the global limit, if known.
+fn fd_global_percent(info: &SystemInfo) -> Option<f64> {
+    info.limits_usage.as_ref()?.open_files.as_ref()?.percent
+}
+
+/// Name of the JSON file each sample is saved to.
+const OUTPUT_FILE: &str = "system_info.json";
+
+/// Checks, displays, and saves one collected sample.
+///
+/// The sample replaces the contents of `OUTPUT_FILE`, or with `--array` is
+/// appended to `array`.
+fn output(
+    info: &SystemInfo,
+    options: &Options,
+    array: Option<&mut array::ArrayFile>,
+) -> Result<(), AppError> {
+    check_required(info, options)?;
+    // One timestamp for every output of this sample
+    let timestamp_ns = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos());
+
+    if let Some(key) = &options.get {
+        let value = serde_json::to_value(info).map_err(AppError::JsonSerialization)?;
+        let found = flat::lookup(&value, key).ok_or_else(|| AppError::KeyNotFound(key.clone()))?;
+        println!("{}", found);
+        return Ok(());
+    }
+
+    // Renaming goes through a `keys::Node` tree, which keeps the struct
+    // field order; the default convention serializes directly.
+    let json = if options.json_keys == KeyCase::Snake {
+        serde_json::to_string_pretty(info)
+    } else {
+        let node = keys::to_node(info).map_err(AppError::JsonSerialization)?;
+        let node = keys::rename_keys(node, options.json_keys).map_err(AppError::KeyConversion)?;
+        serde_json::to_string_pretty(&node)
+    }
+    .map_err(AppError::JsonSerialization)?;
+
+    if !options.quiet {
+        match options.format {
+            OutputFormat::Text => print_report(info, options),
+            OutputFormat::Flat => print!("{}", render(info, options, FileFormat::Flat, "", 0)?),
+            OutputFormat::Influx => {
+                print!(
+                    "{}",
+                    render(info, options, FileFormat::Influx, "", timestamp_ns)?
+                )
+            }
+            OutputFormat::Markdown => {
+                print!("{}", render(info, options, FileFormat::Markdown, &json, 0)?)
+            }
+            OutputFormat::Json => println!("{}", json),
+        }
+    }
+
+    match array {
+        Some(array) => array.append(&json).map_err(AppError::FileWrite)?,
+        None => {
+            let mut file = create_file(Path::new(OUTPUT_FILE), options.mode)
+                .map_err(AppError::FileCreation)?;
+            file.write_all(json.as_bytes())
+                .map_err(AppError::FileWrite)?;
+        }
+    }
+
+    if !options.quiet && options.format == OutputFormat::Text {
+        println!("System information saved to {}", OUTPUT_FILE);
+    }
+
+    if options.copy_json {
+        clipboard::copy(&json);
+    } else if options.copy {
+        clipboard::copy(&render(
+            info,
+            options,
+            FileFormat::Text,
+            &json,
+            timestamp_ns,
+        )?);
+    }
+
+    if let Some(url) = &options.s3_url {
+        let object_url = upload_s3(url, options, &info.hostname, json.as_bytes())?;
+        if !options.quiet && options.format == OutputFormat::Text {
+            println!("System information uploaded to {}", object_url);
+        }
+    }
+
+    for (format, path) in &options.outputs {
+        let contents = render(info, options, *format, &json, timestamp_ns)?;
+        write_atomically(path, contents.as_bytes(), options.mode).map_err(AppError::FileWrite)?;
+        if !options.quiet && options.format == OutputFormat::Text {
+            println!("System information saved to {}", path.display());
+        }
+    }
+
+    if let Some(path) = &options.bundle {
+        let mut report = Vec::new();
+        write_report(&mut report, info, options, false, options.width)
+            .map_err(AppError::FileWrite)?;
+        let file = create_file(path, options.mode).map_err(AppError::FileCreation)?;
+        bundle::write_bundle(
+            io::BufWriter::new(file),
+            &json,
+            &String::from_utf8_lossy(&report),
+            !options.redact.is_empty(),
+        )
+        .map_err(AppError::FileWrite)?;
+        if !options.quiet && options.format == OutputFormat::Text {
+            println!("Support bundle saved to {}", path.display());
+        }
+    }
+    Ok(())
+}
+
+/// Uploads `json` to the `--s3-url` object `url`.
+///
+/// # Errors
+///
+/// Returns an error if the upload fails, or if the binary was built without
+/// the `s3` feature.
+fn upload_s3(
+    url: &str,
+    options: &Options,
+    hostname: &str,
+    json: &[u8],
+) -> Result<String, AppError> {
+    #[cfg(feature = "s3")]
+    {
+        s3::upload(
+            url,
+            options.s3_endpoint.as_deref(),
+            hostname,
+            json,
+            &options.http_client(),
+        )
+    }
+
+    #[cfg(not(feature = "s3"))]
+    {
+        let _ = (url, options, hostname, json);
+        Err(AppError::InvalidArgument(
+            "--s3-url is unavailable: compiled without feature 's3'".to_string(),
+        ))
+    }
+}
+
+/// Renders `info` in a file `format`.
+///
+/// `json` is the already serialized JSON document, returned as is for
+/// `FileFormat::Json`; `timestamp_ns` is the sample time used by
+/// `FileFormat::Influx`.
+fn render(
+    info: &SystemInfo,
+    options: &Options,
+    format: FileFormat,
+    json: &str,
+    timestamp_ns: u128,
+) -> Result<String, AppError> {
+    Ok(match format {
+        FileFormat::Json => json.to_string(),
+        FileFormat::Text => {
+            let mut report = Vec::new();
+            write_report(&mut report, info, options, false, options.width)
+                .map_err(AppError::FileWrite)?;
+            String::from_utf8_lossy(&report).into_owned()
+        }
+        FileFormat::Flat => {
+            let value = serde_json::to_value(info).map_err(AppError::JsonSerialization)?;
+            flat::render(&value, options.metric_prefix())
+        }
+        FileFormat::Influx => influx::render(info, options.metric_prefix(), timestamp_ns),
+        FileFormat::Markdown => {
+            markdown::render(info, options, json).map_err(AppError::JsonSerialization)?
+        }
+    })
+}
+
+/// Creates (or truncates) the file at `path` for writing.
+///
+/// With a `mode` (Unix permission bits such as `0o600`, from `--mode`), the
+/// file gets exactly those permissions, even if it already existed or the
+/// umask is more permissive; it is never readable with looser ones in
+/// between. Without one, new files get the umask-derived permissions.
+/// Other platforms ignore `mode`.
+fn create_file(path: &Path, mode: Option<u32>) -> io::Result<File> {
+    #[cfg(unix)]
+    if let Some(mode) = mode {
+        use std::fs::{OpenOptions, Permissions};
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(mode)
+            .open(path)?;
+        // `mode` only applies to new files, and the umask may clear bits
+        file.set_permissions(Permissions::from_mode(mode))?;
+        return Ok(file);
+    }
+    #[cfg(not(unix))]
+    let _ = mode;
+    File::create(path)
+}
+
+/// Replaces the file at `path` with `contents` in one step, creating it
+/// with `mode` as in `create_file`.
+///
+/// The contents are written to a temporary file next to `path` and renamed
+/// over it, so readers never see a partially written file.
+fn write_atomically(path: &Path, contents: &[u8], mode: Option<u32>) -> io::Result<()> {
+    let mut temporary = path.as_os_str().to_os_string();
+    temporary.push(".tmp");
+    let temporary = Path::new(&temporary);
+    let result = create_file(temporary, mode)
+        .and_then(|mut file| file.write_all(contents))
+        .and_then(|()| std::fs::rename(temporary, path));
+    if result.is_err() {
+        let _ = std::fs::remove_file(temporary);
+    }
+    result
+}
+
+/// Runs the `self-update` subcommand selected by `options`.
+///
+/// # Returns
+///
+/// The process exit code reported by the update check.
+///
+/// # Errors
+///
+/// Returns an error if the update fails, or if the binary was built without
+/// the `self-update` feature.
+fn run_self_update(options: &Options) -> Result<i32, AppError> {
+    #[cfg(feature = "self-update")]
+    {
+        let url = options
+            .update_url
+            .as_deref()
+            .unwrap_or(update::DEFAULT_UPDATE_URL);
+        update::self_update(url, options.check_only, &options.http_client())
+    }
+
+    #[cfg(not(feature = "self-update"))]
+    {
+        let _ = options;
+        Err(AppError::InvalidArgument(
+            "self-update is unavailable: compiled without feature 'self-update'".to_string(),
+        ))
+    }
+}
+
+/// Runs `--benchmark`: times repeated collections and prints statistics.
+///
+/// Each run refreshes the system data and builds a snapshot exactly as a
+/// normal run does, including the `--sample-interval` wait, but nothing is
+/// printed or written per run.
+///
+/// # Returns
+///
+/// Exit code `0`.
+fn run_benchmark(options: &Options) -> Result<i32, AppError> {
+    let runs = options.benchmark.unwrap_or(1);
+    let mut sys = System::new();
+    let mut durations = Vec::new();
+    for _ in 0..runs {
+        let start = Instant::now();
+        sys.refresh();
+        collect_system_info(&sys, options, None)?;
+        durations.push(start.elapsed());
+    }
+    durations.sort();
+
+    let total: std::time::Duration = durations.iter().sum();
+    let middle = durations.len() / 2;
+    let median = if durations.len() % 2 == 0 {
+        (durations[middle - 1] + durations[middle]) / 2
+    } else {
+        durations[middle]
+    };
+    let ms = |duration: std::time::Duration| format!("{:.1} ms", duration.as_secs_f64() * 1000.0);
+
+    println!("Collected {} times:", runs);
+    println!("  Min: {}", ms(durations[0]));
+    println!("  Max: {}", ms(durations[durations.len() - 1]));
+    println!("  Mean: {}", ms(total.div_f64(runs as f64)));
+    println!("  Median: {}", ms(median));
+    println!(
+        "  (includes the {}s --sample-interval)",
+        options.sample_interval().as_secs_f64()
+    );
+    Ok(0)
+}
+
+/// Runs `--self-test`: collects once and checks the data is plausible.
+///
+/// Prints one line per check and a summary, like the `verify` command.
+///
+/// # Returns
+///
+/// Exit code `1` if any check failed, `0` otherwise.
+fn run_self_test(options: &Options) -> Result<i32, AppError> {
+    let info = collect(&mut System::new(), options)?;
+    let results = selftest::run(&info);
+
+    for result in &results {
+        let status = match result.status {
+            selftest::Status::Pass => "PASS",
+            selftest::Status::Fail => "FAIL",
+            selftest::Status::Skip => "SKIP",
+        };
+        println!("{}  {}: {}", status, result.name, result.detail);
+    }
+    let count = |status| results.iter().filter(|r| r.status == status).count();
+    let failed = count(selftest::Status::Fail);
+    println!(
+        "{} passed, {} failed, {} skipped",
+        count(selftest::Status::Pass),
+        failed,
+        count(selftest::Status::Skip)
+    );
+
+    Ok(if failed > 0 { 1 } else { 0 })
+}
+
+/// Optional cargo features compiled into this build, by name.
+fn compiled_features() -> Vec<&'static str> {
+    [
+        ("containers", cfg!(feature = "containers")),
+        ("ffi", cfg!(feature = "ffi")),
+        ("history", cfg!(feature = "history")),
+        ("host-details", cfg!(feature = "host-details")),
+        ("mqtt", cfg!(feature = "mqtt")),
+        ("net-sinks", cfg!(feature = "net-sinks")),
+        ("probes", cfg!(feature = "probes")),
+        ("processes", cfg!(feature = "processes")),
+        ("s3", cfg!(feature = "s3")),
+        ("self-update", cfg!(feature = "self-update")),
+        ("sensors", cfg!(feature = "sensors")),
+        ("serve", cfg!(feature = "serve")),
+    ]
+    .into_iter()
+    .filter_map(|(name, enabled)| enabled.then_some(name))
+    .collect()
+}
+
+/// Prints the version, or with `--format json` the version and the
+/// compiled-in features, e.g. `{"version":"0.1.0","features":["s3"]}`.
+///
+/// # Returns
+///
+/// Exit code `0`.
+fn run_version(options: &Options) -> Result<i32, AppError> {
+    let version = env!("CARGO_PKG_VERSION");
+    if options.format == OutputFormat::Json {
+        #[derive(Serialize)]
+        struct Version {
+            version: &'static str,
+            features: Vec<&'static str>,
+        }
+        let json = serde_json::to_string(&Version {
+            version,
+            features: compiled_features(),
+        })
+        .map_err(AppError::JsonSerialization)?;
+        println!("{}", json);
+    } else {
+        println!("RustGetSystemInfo {}", version);
+    }
+    Ok(0)
+}
+
+/// Prints the fleet overview for the snapshots in the `--aggregate` directory.
+///
+/// # Returns
+///
+/// Exit code `0`.
+///
+/// # Errors
+///
+/// Returns an error if the directory cannot be read or holds no snapshots.
+fn run_aggregate(options: &Options) -> Result<i32, AppError> {
+    let dir = options
+        .aggregate
+        .as_deref()
+        .ok_or_else(|| AppError::InvalidArgument("--aggregate requires a directory".to_string()))?;
+    let hosts = aggregate::load(dir)?;
+    for line in aggregate::render(&hosts, options.width, options.locale()) {
+        println!("{}", line);
+    }
+    Ok(0)
+}
+
+/// Runs the `quick` subcommand, printing the value of one metric.
+///
+/// # Returns
+///
+/// Exit code `0`.
+///
+/// # Errors
+///
+/// Returns an error if the metric is unknown or cannot be read.
+fn run_quick(options: &Options) -> Result<i32, AppError> {
+    let metric = options.metric.as_deref().unwrap_or_default();
+    println!("{}", quick::query(metric, options.human)?);
+    Ok(0)
+}
+
+/// Runs the `read-shm` subcommand, printing the snapshot published with `--shm`.
+///
+/// Demonstrates the reader side of the shared-memory layout documented in
+/// the `shm` module; the sequence number and timestamp are printed to stderr.
+///
+/// # Returns
+///
+/// Exit code `0`.
+///
+/// # Errors
+///
+/// Returns an error if the file is not a snapshot file, nothing has been
+/// published yet, or no consistent snapshot could be read.
+fn run_read_shm(options: &Options) -> Result<i32, AppError> {
+    #[cfg(feature = "serve")]
+    {
+        let path = options.shm.as_deref().ok_or_else(|| {
+            AppError::InvalidArgument("read-shm requires --shm <PATH>".to_string())
+        })?;
+        let reader = shm::Reader::open(path).map_err(AppError::SharedMemory)?;
+        let snapshot = reader
+            .read()
+            .map_err(AppError::SharedMemory)?
+            .ok_or(AppError::EmptyCollection("published snapshot"))?;
+        eprintln!(
+            "sequence {}, published at {} ms",
+            snapshot.sequence, snapshot.timestamp_ms
+        );
+        println!("{}", String::from_utf8_lossy(&snapshot.payload));
+        Ok(0)
+    }
+
+    #[cfg(not(feature = "serve"))]
+    {
+        let _ = options;
+        Err(AppError::InvalidArgument(
+            "read-shm is unavailable: compiled without feature 'serve'".to_string(),
+        ))
+    }
+}
+
+/// Runs the `processes` subcommand, printing the process list as JSON, or
+/// as folded stacks for flame graph tools with `--folded`.
+///
+/// Write errors are ignored, since the output is usually piped into
+/// another tool that may stop reading early.
+///
+/// # Returns
+///
+/// Exit code `0`.
+///
+/// # Errors
+///
+/// Returns an error if the process list cannot be serialized.
+fn run_processes(options: &Options) -> Result<i32, AppError> {
+    #[cfg(feature = "processes")]
+    {
+        let list = processes::list(options.sample_interval());
+        let output = if options.folded {
+            processes::folded(&list, options.process_metric.unwrap_or_default())
+        } else {
+            serde_json::to_string_pretty(&list).map_err(AppError::JsonSerialization)? + "\n"
+        };
+        let _ = io::stdout().lock().write_all(output.as_bytes());
+        Ok(0)
+    }
+
+    #[cfg(not(feature = "processes"))]
+    {
+        let _ = options;
+        Err(AppError::InvalidArgument(
+            "processes is unavailable: compiled without feature 'processes'".to_string(),
+        ))
+    }
+}
+
+/// Runs the `verify` subcommand, checking this system against a spec file.
+///
+/// Prints one PASS/FAIL line per expectation followed by a summary.
+///
+/// # Returns
+///
+/// Exit code `0` if every expectation passed, `1` otherwise.
+///
+/// # Errors
+///
+/// Returns an error if no spec was given or the spec cannot be read or parsed.
+fn run_verify(options: &Options) -> Result<i32, AppError> {
+    let path = options
+        .spec
+        .as_deref()
+        .ok_or_else(|| AppError::InvalidArgument("verify requires --spec <FILE>".to_string()))?;
+    let text = std::fs::read_to_string(path).map_err(AppError::FileRead)?;
+    let spec = verify::Spec::parse(&text).map_err(AppError::InvalidSpec)?;
+
+    let info = collect(&mut System::new(), options)?;
+    let results = verify::verify(&spec, &info);
+
+    for result in &results {
+        println!(
+            "{}  {}: expected {}, observed {}",
+            if result.passed { "PASS" } else { "FAIL" },
+            result.name,
+            result.expected,
+            result.observed
+        );
+    }
+    let failed = results.iter().filter(|r| !r.passed).count();
+    println!("{} passed, {} failed", results.len() - failed, failed);
+
+    Ok(if failed > 0 { 1 } else { 0 })
+}
+
+/// Application entry point, called by the `RustGetSystemInfo` binary.
+///
+/// Parses the command line, runs the selected command, and exits with its
+/// status code. If an error occurs during system information collection,
+/// file operations, or updating, it prints the error message to stderr and
+/// exits with the error's exit code.
+pub fn main() {
+    let result = Options::parse(std::env::args().skip(1)).and_then(|options| {
+        if options.insecure_skip_tls_verify {
+            eprintln!(
+                "Warning: --insecure-skip-tls-verify is set: TLS certificates are NOT verified, \
+                 so anyone on the network path can read and alter the traffic"
+            );
+        }
+        match options.command {
+            Command::Report if options.version => run_version(&options),
+            Command::Report if options.explain => {
+                print!("{}", explain::render(options.json_keys));
+                Ok(0)
+            }
+            Command::Report if options.aggregate.is_some() => run_aggregate(&options),
+            Command::Report if options.benchmark.is_some() => run_benchmark(&options),
+            Command::Report if options.self_test => run_self_test(&options),
+            Command::Report => run(&options).map(|()| 0),
+            Command::SelfUpdate => run_self_update(&options),
+            Command::Verify => run_verify(&options),
+            Command::ReadShm => run_read_shm(&options),
+            Command::Quick => run_quick(&options),
+            Command::Processes => run_processes(&options),
+            Command::Service(action) => service::run(action, &options.service_args),
+        }
+    });
+    match result {
+        Ok(code) => std::process::exit(code),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(e.exit_code());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_app_error_exit_code() {
+        assert_eq!(AppError::UnknownMetric("load".to_string()).exit_code(), 2);
+        let alerts = |severity| AppError::AlertsRaised {
+            severity,
+            rules: vec!["swapping".to_string()],
+        };
+        assert_eq!(alerts(expr::Severity::Warning).exit_code(), 3);
+        assert_eq!(alerts(expr::Severity::Critical).exit_code(), 4);
+        let missing = AppError::NotSelected {
+            what: "mount point",
+            pattern: "/data".to_string(),
+            available: vec![],
+        };
+        assert_eq!(missing.exit_code(), 6);
+        assert_eq!(AppError::KeyNotFound("uptime".to_string()).exit_code(), 5);
+        // A critical check is a failed run, not an alert
+        assert_eq!(
+            AppError::CheckFailed("used_swap > 0".to_string()).exit_code(),
+            1
+        );
+        assert_eq!(
+            AppError::Timeout(std::time::Duration::from_secs(10)).exit_code(),
+            7
+        );
+        assert_eq!(AppError::EmptyCollection("disks").exit_code(), 1);
+    }
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(0, 0, Locale::default()), "0 B");
+        assert_eq!(format_bytes(1023, 0, Locale::default()), "1023 B");
+        assert_eq!(format_bytes(1024, 0, Locale::default()), "1.00 KB");
+        assert_eq!(format_bytes(1536, 0, Locale::default()), "1.50 KB");
+        assert_eq!(format_bytes(1048576, 0, Locale::default()), "1.00 MB");
+        assert_eq!(format_bytes(1073741824, 0, Locale::default()), "1.00 GB");
+        assert_eq!(format_bytes(1099511627776, 0, Locale::default()), "1.00 TB");
+        assert_eq!(
+            format_bytes(1125899906842624, 0, Locale::default()),
+            "1.00 PB"
+        );
+        assert_eq!(
+            format_bytes(1152921504606846976, 0, Locale::default()),
+            "1.00 EB"
+        );
+        assert_eq!(format_bytes(u64::MAX, 0, Locale::default()), "16.00 EB");
+        // just below a boundary rounds up into the next unit, not "1024.00 KB"
+        assert_eq!(format_bytes(1048575, 0, Locale::default()), "1.00 MB");
+        assert_eq!(format_bytes(1048570, 0, Locale::default()), "1023.99 KB");
+        assert_eq!(
+            format_bytes(1125899906842623, 0, Locale::default()),
+            "1.00 PB"
+        );
+        assert_eq!(format_bytes(512, 1, Locale::default()), "0.50 KB");
+        assert_eq!(format_bytes(1536, 2, Locale::default()), "0.00 MB");
+        assert_eq!(format_bytes(1073741824, 1, Locale::default()), "1.00 GB");
+        assert_eq!(format_bytes(1024, 6, Locale::default()), "0.00 EB");
+        assert_eq!(format_bytes(1024, 99, Locale::default()), "0.00 EB");
+    }
+
+    #[test]
+    fn test_format_bytes_boundaries() {
+        // Every power of 1024 picks its own unit, whatever the float error
+        // of the division
+        for (power, unit) in BYTE_UNITS.iter().enumerate().skip(1) {
+            let boundary = 1u64 << (10 * power);
+            assert_eq!(
+                format_bytes(boundary, 0, Locale::default()),
+                format!("1.00 {}", unit)
+            );
+            assert_eq!(
+                format_bytes(boundary + 1, 0, Locale::default()),
+                format!("1.00 {}", unit)
+            );
+            if power > 1 {
+                assert_eq!(
+                    format_bytes(boundary / 1024 * 1023, 0, Locale::default()),
+                    format!("1023.00 {}", BYTE_UNITS[power - 1])
+                );
+                // Rounds into this unit rather than printing "1024.00"
+                assert_eq!(
+                    format_bytes(boundary - 1, 0, Locale::default()),
+                    format!("1.00 {}", unit)
+                );
+            }
+        }
+        assert_eq!(format_bytes(1023, 0, Locale::default()), "1023 B");
+    }
+
+    #[test]
+    fn test_format_count() {
+        assert_eq!(format_count(0, ","), "0");
+        assert_eq!(format_count(999, ","), "999");
+        assert_eq!(format_count(1000, ","), "1,000");
+        assert_eq!(format_count(182934718234, "."), "182.934.718.234");
+        assert_eq!(format_count(1000000, "\u{2009}"), "1\u{2009}000\u{2009}000");
+        assert_eq!(format_count(u64::MAX, ","), "18,446,744,073,709,551,615");
+        assert_eq!(format_count(u64::MAX, ""), "18446744073709551615");
+    }
+
+    #[test]
+    fn test_format_percent() {
+        assert_eq!(format_percent(0.0, Locale::default()), "0.0%");
+        assert_eq!(format_percent(12.345, Locale::default()), "12.3%");
+        assert_eq!(format_percent(100.0, Locale::default()), "100.0%");
+        assert_eq!(format_percent(100.04, Locale::default()), "100.0%");
+        assert_eq!(format_percent(-3.0, Locale::default()), "0.0%");
+        assert_eq!(format_percent(-0.01, Locale::default()), "0.0%");
+        assert_eq!(format_percent(f64::NAN, Locale::default()), "n/a");
+        assert_eq!(format_percent(f64::INFINITY, Locale::default()), "n/a");
+        assert_eq!(format_percent(f64::NEG_INFINITY, Locale::default()), "n/a");
+        assert_eq!(format_percent(f64::MAX, Locale::default()), "100.0%");
+        assert_eq!(format_percent(f64::MIN, Locale::default()), "0.0%");
+    }
+
+    #[test]
+    fn test_write_report_locale() {
+        let de = Locale::parse("de_DE.UTF-8").unwrap();
+        assert_eq!(format_bytes(1536, 0, de), "1,50 KB");
+        assert_eq!(format_percent(12.345, de), "12,3%");
+
+        let info = mock::snapshot(&mock::MockProvider::default());
+        let report = |locale| {
+            let mut options = mock::options();
+            options.locale = locale;
+            let mut report = Vec::new();
+            write_report(&mut report, &info, &options, false, Some(80)).unwrap();
+            String::from_utf8(report).unwrap()
+        };
+        let german = report(Some(de));
+        assert!(german.contains("8,00 GB"));
+        assert!(german.contains("25,0%"));
+        assert!(!german.contains("8.00 GB"));
+        // Formatting for one locale leaves the others alone
+        let english = report(None);
+        assert!(english.contains("8.00 GB") && !english.contains("8,00 GB"));
+        assert_eq!(format_bytes(1536, 0, Locale::default()), "1.50 KB");
+    }
+
+    #[test]
+    fn test_write_report_counts() {
+        let mut eth0 = mock::network("eth0", 0, 0);
+        eth0.packets_received = u64::MAX;
+        eth0.packets_transmitted = 999;
+        eth0.errors_received = 1000;
+        let sys = mock::MockProvider {
+            networks: vec![eth0],
+            ..mock::MockProvider::default()
+        };
+        let info = mock::snapshot(&sys);
+        let report = |separator| {
+            let mut options = mock::options();
+            options.thousands_separator = separator;
+            let mut report = Vec::new();
+            write_report(&mut report, &info, &options, false, Some(120)).unwrap();
+            String::from_utf8(report).unwrap()
+        };
+
+        let report = report(Some("."));
+        assert!(report.contains("(18.446.744.073.709.551.615 packets)"));
+        assert!(report.contains("(999 packets)"));
+        assert!(report.contains("Errors: 1.000 in, 0 out; Drops: n/a in, n/a out"));
+    }
+
+    #[test]
+    fn test_check_required_nic_errors() {
+        let mut eth1 = mock::network("eth1", 1000, 1000);
+        eth1.errors_received = 3;
+        let sys = mock::MockProvider {
+            networks: vec![mock::network("eth0", 1000, 1000), eth1],
+            ..mock::MockProvider::default()
+        };
+        let mut info = mock::snapshot(&sys);
+        info.networks[1].drops_transmitted = Some(2);
+        assert_eq!(info.networks[0].faults(), 0);
+        assert_eq!(info.networks[1].faults(), 5);
+
+        let mut options = mock::options();
+        assert!(check_required(&info, &options).is_ok());
+        options.fail_on_nic_errors = Some(5);
+        assert!(check_required(&info, &options).is_ok());
+        options.fail_on_nic_errors = Some(4);
+        match check_required(&info, &options) {
+            Err(AppError::NicErrors(message)) => {
+                assert_eq!(message, "eth1 has 5 errors and drops (threshold 4)")
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        // Counters near the limit saturate rather than wrap
+        info.networks[1].errors_transmitted = u64::MAX;
+        assert_eq!(info.networks[1].faults(), u64::MAX);
+
+        // The console only shows the line for the interface with faults
+        let mut report = Vec::new();
+        write_report(&mut report, &info, &mock::options(), false, Some(120)).unwrap();
+        let report = String::from_utf8(report).unwrap();
+        assert_eq!(report.matches("Errors: ").count(), 1);
+        assert!(
+            report.contains("Errors: 3 in, 18,446,744,073,709,551,615 out; Drops: n/a in, 2 out")
+        );
+    }
+
+    #[test]
+    fn test_write_report_thermal() {
+        let mut info = mock::snapshot(&mock::MockProvider::default());
+        info.thermal = Some(thermal::ThermalInfo {
+            throttled: true,
+            throttle_events: Some(12),
+            max_temp_seen: Some(98.5),
+            seconds_above_critical: 30.0,
+            frequency_ratio: Some(0.6),
+        });
+        let report = |info: &SystemInfo| {
+            let mut report = Vec::new();
+            write_report(&mut report, info, &mock::options(), false, Some(120)).unwrap();
+            String::from_utf8(report).unwrap()
+        };
+        assert!(report(&info).contains(
+            "  THERMAL THROTTLING DETECTED\n  Thermal: max 98.5 °C seen, 30s above critical, \
+             12 throttle events since boot, running at 60.0% of max frequency\n"
+        ));
+
+        info.thermal.as_mut().unwrap().throttled = false;
+        let text = report(&info);
+        assert!(!text.contains("THROTTLING"));
+        assert!(text.contains("  Thermal: max 98.5 °C seen"));
+    }
+
+    #[test]
+    fn test_check_required_expressions() {
+        let sys = mock::MockProvider {
+            disks: vec![mock::disk("/var", "ext4", 100 << 30, 2 << 30)],
+            ..mock::MockProvider::default()
+        };
+        let info = mock::snapshot(&sys);
+        let options = |checks: &[&str]| {
+            let args = checks
+                .iter()
+                .flat_map(|check| ["--check-expr".to_string(), check.to_string()]);
+            Options::parse(args).unwrap()
+        };
+
+        // Warnings, unknown results and conditions that do not hold pass
+        let passing = options(&[
+            "warn: disks[\"/var\"].available_space < 5GB",
+            "crit: disks[\"/data\"].available_space < 5GB",
+            "crit: disks[\"/var\"].available_space < 1GB",
+        ]);
+        assert!(check_required(&info, &passing).is_ok());
+
+        // Every critical condition that holds is named
+        let failing = options(&[
+            "crit: disks[\"/var\"].available_space < 5GB",
+            "warn: cpu_cores > 0",
+            "crit: disks[0].total_space >= 100GiB and not disks[0].name == \"/\"",
+        ]);
+        match check_required(&info, &failing) {
+            Err(AppError::CheckFailed(message)) => assert_eq!(
+                message,
+                "disks[\"/var\"].available_space < 5GB; \
+                 disks[0].total_space >= 100GiB and not disks[0].name == \"/\""
+            ),
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        // Parse errors point at the problem
+        let error = Options::parse(["--check-expr".to_string(), "warn: cpu_cores >".to_string()])
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Invalid argument: --check-expr 'warn: cpu_cores >': at column 18: expected a number, \
+             a string, true, false or a field name, found end of expression"
+        );
+    }
+
+    #[test]
+    fn test_parse_bytes() {
+        assert_eq!(parse_bytes("512"), Some(512));
+        assert_eq!(parse_bytes("1.5 KB"), Some(1536));
+        assert_eq!(parse_bytes("64gib"), Some(68719476736));
+        assert_eq!(parse_bytes("1.5 GiB"), Some(1610612736));
+        assert_eq!(parse_bytes("2 PB"), Some(2251799813685248));
+        assert_eq!(parse_bytes("1EiB"), Some(1152921504606846976));
+        assert_eq!(parse_bytes("15.5 EB"), Some(17870283321406128128));
+        assert_eq!(parse_bytes("16 EB"), None);
+        assert_eq!(parse_bytes("10 parsecs"), None);
+    }
+
+    #[test]
+    fn test_parse_bytes_boundaries() {
+        // Every spelling of every unit, in any case, with or without a space
+        let units = [
+            ["b", "b", "b"],
+            ["k", "kb", "kib"],
+            ["m", "mb", "mib"],
+            ["g", "gb", "gib"],
+            ["t", "tb", "tib"],
+            ["p", "pb", "pib"],
+            ["e", "eb", "eib"],
+        ];
+        for (power, spellings) in units.iter().enumerate() {
+            let unit = 1u64 << (10 * power);
+            for spelling in spellings {
+                for text in [
+                    format!("1{}", spelling),
+                    format!("1 {}", spelling.to_uppercase()),
+                    format!(" 1\t{} ", spelling),
+                ] {
+                    assert_eq!(parse_bytes(&text), Some(unit), "{:?}", text);
+                }
+                assert_eq!(
+                    parse_bytes(&format!("1023{}", spelling)),
+                    unit.checked_mul(1023)
+                );
+            }
+            // format_bytes output parses back to the value it shows
+            assert_eq!(
+                parse_bytes(&format_bytes(unit, 0, Locale::default())),
+                Some(unit)
+            );
+        }
+        assert_eq!(parse_bytes("1KiB"), Some(1024));
+        assert_eq!(parse_bytes("1.00 KB"), Some(1024));
+
+        // The top of the range, exact for whole byte counts
+        assert_eq!(parse_bytes("0"), Some(0));
+        assert_eq!(parse_bytes("18446744073709551615"), Some(u64::MAX));
+        assert_eq!(parse_bytes("18446744073709551615 B"), Some(u64::MAX));
+        assert_eq!(parse_bytes("9007199254740993"), Some(9007199254740993));
+        assert_eq!(parse_bytes("18446744073709551616"), None);
+        assert_eq!(parse_bytes("15.99 EiB"), Some(18435214858663483392));
+        assert_eq!(parse_bytes("16 EiB"), None);
+        assert_eq!(parse_bytes("16384 PB"), None);
+        assert_eq!(parse_bytes("0.5"), Some(1));
+        assert_eq!(parse_bytes("0.4 B"), Some(0));
+
+        for invalid in [
+            "", " ", "KB", ".", "-1", "-1 KB", "1e3", "1,5 KB", "1 KB B", "NaN", "inf",
+        ] {
+            assert_eq!(parse_bytes(invalid), None, "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_collect_system_info() {
+        // Disks, interfaces and sensors are sorted whatever order the
+        // provider lists them in
+        use crate::mock::{disk, network, MockProvider};
+        use crate::thermal::ComponentTemperature;
+
+        let sensor = |label: &str, celsius| ComponentTemperature {
+            label: label.to_string(),
+            celsius,
+        };
+        let mut sys = MockProvider {
+            disks: vec![
+                disk("/srv/mock", "ext4", 100, 50),
+                disk("/", "ext4", 200, 100),
+                disk("/srv/mock", "overlay", 300, 150),
+                disk("/boot/efi", "vfat", 400, 200),
+            ],
+            networks: vec![
+                network("mock1", 10, 20),
+                network("mock0", 10, 20),
+                network("lo-mock", 10, 20),
+            ],
+            components: vec![
+                sensor("nvme Composite", 38.0),
+                sensor("acpitz", 41.0),
+                sensor("acpitz", 27.8),
+            ],
+            ..MockProvider::default()
+        };
+        let sections = |info: &SystemInfo| {
+            serde_json::to_string(&(&info.disks, &info.networks, &info.component_temperatures))
+                .unwrap()
+        };
+        let first = sections(&mock::snapshot(&sys));
+        for round in 0..5 {
+            sys.disks.rotate_left(1);
+            sys.networks.rotate_left(1);
+            sys.components.rotate_left(1);
+            if round % 2 == 1 {
+                sys.disks.reverse();
+                sys.networks.reverse();
+                sys.components.reverse();
+            }
+            assert_eq!(sections(&mock::snapshot(&sys)), first);
+        }
+        let info = mock::snapshot(&sys);
+        let mounts: Vec<&str> = info.disks.iter().map(|disk| disk.name.as_str()).collect();
+        assert_eq!(mounts, ["/", "/boot/efi", "/srv/mock", "/srv/mock"]);
+        assert_eq!(info.networks[0].name, "lo-mock");
+        assert_eq!(info.root_disk_usage_percent, Some(50.0));
+        assert_eq!(info.total_bytes_received, 30);
+
+        // Memory is core data
+        sys.total_memory = 0;
+        assert!(matches!(
+            collect_system_info(&sys, &mock::options(), None),
+            Err(AppError::CoreCollection("memory"))
+        ));
+    }
+
+    #[test]
+    fn test_snapshot_byte_identical() {
+        // Whole snapshots and reports of an unchanged host do not depend on
+        // the order the provider lists things in
+        use crate::mock::{disk, network, MockProvider};
+        use crate::thermal::ComponentTemperature;
+
+        let sensor = |label: &str, celsius| ComponentTemperature {
+            label: label.to_string(),
+            celsius,
+        };
+        let mut sys = MockProvider {
+            disks: (0..8)
+                .map(|index| {
+                    disk(
+                        &format!("/mnt/{}", (index * 5) % 8),
+                        "ext4",
+                        1 << 30,
+                        1 << 29,
+                    )
+                })
+                .collect(),
+            networks: (0..8)
+                .map(|index| network(&format!("eth{}", (index * 3) % 8), 100, 200))
+                .collect(),
+            components: (0..8)
+                .map(|index| sensor(&format!("core{}", (index * 7) % 8), 40.0))
+                .collect(),
+            ..MockProvider::default()
+        };
+        let output = |sys: &MockProvider| {
+            let info = mock::snapshot(sys);
+            let mut report = Vec::new();
+            write_report(&mut report, &info, &mock::options(), false, Some(100)).unwrap();
+            (serde_json::to_vec_pretty(&info).unwrap(), report)
+        };
+        let first = output(&sys);
+        // A fixed shuffle: each step swaps pairs further apart
+        for step in 1..8 {
+            for index in 0..8 - step {
+                if (index / step) % 2 == 0 {
+                    sys.disks.swap(index, index + step);
+                    sys.networks.swap(index, index + step);
+                    sys.components.swap(8 - step - index - 1, 8 - index - 1);
+                }
+            }
+            assert!(output(&sys) == first, "differs after step {}", step);
+        }
+    }
+
+    #[test]
+    fn test_collect_system_info_no_disks() {
+        let sys = mock::MockProvider {
+            disks: Vec::new(),
+            ..mock::MockProvider::default()
+        };
+        let info = mock::snapshot(&sys);
+        assert!(info.disks.is_empty());
+        assert_eq!(info.root_disk_usage_percent, None);
+
+        let mut report = Vec::new();
+        write_report(&mut report, &info, &mock::options(), false, Some(80)).unwrap();
+        let no_disks = if disks_permission_denied() {
+            "No disks detected (possibly insufficient permissions)"
+        } else {
+            "No disks detected"
+        };
+        assert_eq!(
+            String::from_utf8(report).unwrap(),
+            format!(
+                "System Information:
+  OS Name: Linux
+  OS Version: 6.1
+  Hostname: mockhost
+  CPU Cores: 4
+  Total Memory: 8.00 GB
+  Used Memory: 2.00 GB
+  Total Swap: 0 B
+  Used Swap: 0 B
+
+Disk Usage:
+  {}
+
+Network Interfaces:
+  mock0:
+    Received: 1000 B (10 packets)
+    Transmitted: 1.95 KB (20 packets)
+  Network Totals: 1000 B received, 1.95 KB transmitted
+  Default gateway: none
+  DNS servers: none
+",
+                no_disks
+            )
+        );
+
+        let json = serde_json::to_value(&info).unwrap();
+        assert_eq!(json["disks"], json!([]));
+        assert_eq!(json["root_disk_usage_percent"], json!(null));
+        assert_eq!(json["total_bytes_received"], json!(1000));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_collect_system_info_lossy_names() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let mut disk = mock::disk("/", "ext4", 100 << 30, 75 << 30);
+        disk.mount_point = std::ffi::OsStr::from_bytes(b"/srv/caf\xe9").into();
+        let mut network = mock::network("mock0", 1000, 2000);
+        network.name = std::ffi::OsStr::from_bytes(b"eth\xff").into();
+        let sys = mock::MockProvider {
+            disks: vec![disk, mock::disk("/boot", "vfat", 1 << 30, 1 << 29)],
+            networks: vec![network, mock::network("lo", 10, 10)],
+            ..mock::MockProvider::default()
+        };
+        let info = mock::snapshot(&sys);
+
+        let names: Vec<(&str, Option<&str>, bool)> = info
+            .disks
+            .iter()
+            .map(|disk| {
+                (
+                    disk.name.as_str(),
+                    disk.name_raw.as_deref(),
+                    disk.path_is_lossy,
+                )
+            })
+            .collect();
+        assert_eq!(
+            names,
+            [
+                ("/boot", None, false),
+                ("/srv/caf\u{FFFD}", Some("/srv/caf%E9"), true),
+            ]
+        );
+        let names: Vec<(&str, Option<&str>, bool)> = info
+            .networks
+            .iter()
+            .map(|network| {
+                let raw = network.name_raw.as_deref();
+                (network.name.as_str(), raw, network.name_is_lossy)
+            })
+            .collect();
+        assert_eq!(
+            names,
+            [("eth\u{FFFD}", Some("eth%FF"), true), ("lo", None, false)]
+        );
+
+        // The raw forms survive a JSON round trip
+        let json = serde_json::to_string(&info).unwrap();
+        let read: SystemInfo = serde_json::from_str(&json).unwrap();
+        assert_eq!(read.disks[1].name_raw.as_deref(), Some("/srv/caf%E9"));
+        assert!(read.disks[1].path_is_lossy);
+        assert_eq!(read.networks[0].name_raw.as_deref(), Some("eth%FF"));
+        assert!(read.networks[0].name_is_lossy);
+        assert!(!read.networks[1].name_is_lossy);
+
+        // The console shows the lossy forms
+        let mut report = Vec::new();
+        write_report(&mut report, &info, &mock::options(), false, Some(80)).unwrap();
+        let report = String::from_utf8(report).unwrap();
+        assert!(report.contains("/srv/caf\u{FFFD}"));
+        assert!(report.contains("eth\u{FFFD}"));
+        assert!(!report.contains("%E9"));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_collect_system_info_cpu_sampling() {
+        let sys = mock::MockProvider::default();
+        let mut options = mock::options();
+        options.sample_interval = Some(std::time::Duration::from_millis(200));
+
+        // A plain run reads the tick counters without waiting
+        let start = Instant::now();
+        let info = collect_system_info(&sys, &options, None).unwrap();
+        assert!(start.elapsed() < std::time::Duration::from_millis(200));
+        let times = info.cpu_times.unwrap();
+        assert!(times.ticks.is_some());
+        assert_eq!(times.interval_secs, None);
+        assert_eq!(times.total, CpuBreakdown::default());
+        assert_eq!(info.cpu_breakdown, None);
+        assert!(times.per_core.is_empty());
+
+        // Asked for, the breakdown is sampled next to the counters
+        let asks: [fn(&mut Options); 3] = [
+            |options| options.cpu_breakdown = true,
+            |options| options.memory_activity = true,
+            |options| options.interval = Some(std::time::Duration::from_secs(60)),
+        ];
+        for ask in asks {
+            let mut sampled = mock::options();
+            sampled.sample_interval = options.sample_interval;
+            ask(&mut sampled);
+            let info = collect_system_info(&sys, &sampled, None).unwrap();
+            assert_eq!(info.cpu_breakdown, info.cpu_times.as_ref().map(|t| t.total));
+            let times = info.cpu_times.unwrap();
+            assert!(times.ticks.is_some());
+            assert!(times.interval_secs.unwrap() >= 0.2);
+            assert!(times.total.idle.is_some());
+            assert!(!times.per_core.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_collect_timeout() {
+        // A refresh that hangs far past the deadline is abandoned on time
+        let mut sys = mock::MockProvider {
+            refresh_delay: std::time::Duration::from_secs(30),
+            ..mock::MockProvider::default()
+        };
+        let mut options = mock::options();
+        options.quiet = true;
+        options.timeout = Some(std::time::Duration::from_millis(200));
+        let start = Instant::now();
+        let Err(error) = collect(&mut sys, &options) else {
+            panic!("a hung refresh was waited for");
+        };
+        let elapsed = start.elapsed();
+        assert!(elapsed >= std::time::Duration::from_millis(200));
+        assert!(elapsed < std::time::Duration::from_secs(1), "{:?}", elapsed);
+        assert_eq!(error.exit_code(), 7);
+        assert_eq!(error.to_string(), "Collection timed out after 0.2s");
+
+        // The CPU sample would take seconds past the deadline: the rest of
+        // the snapshot is written without it
+        let mut sys = mock::MockProvider::default();
+        options.cpu_breakdown = true;
+        options.sample_interval = Some(std::time::Duration::from_secs(5));
+        let start = Instant::now();
+        let info = collect(&mut sys, &options).unwrap();
+        assert!(start.elapsed() < std::time::Duration::from_secs(1));
+        assert_eq!(sys.refreshes, 1);
+        assert!(info.partial);
+        assert!(info
+            .collection_errors
+            .contains(&"cpu_times: timed out".to_string()));
+
+        // The core data is all there, the abandoned sections are null
+        let value = serde_json::to_value(&info).unwrap();
+        assert_eq!(value["total_memory"], 8u64 << 30);
+        assert_eq!(value["disks"][0]["name"], "/");
+        assert_eq!(value["networks"][0]["name"], "mock0");
+        assert!(value["cpu_times"].is_null() && value["cpu_breakdown"].is_null());
+        assert!(value.get("partial").is_none());
+
+        // A partial sample ends the run with exit code 7, even over alerts
+        let ended = outcome(
+            options.timeout,
+            true,
+            Some(expr::Severity::Critical),
+            vec![],
+        );
+        assert_eq!(ended.unwrap_err().exit_code(), 7);
+        assert!(outcome(options.timeout, false, None, vec![]).is_ok());
+        let ended = outcome(None, false, Some(expr::Severity::Warning), vec![]);
+        assert_eq!(ended.unwrap_err().exit_code(), 3);
+
+        // Without a timeout, collection waits for the sample
+        options.timeout = None;
+        options.sample_interval = Some(std::time::Duration::from_millis(100));
+        sys.refresh_delay = std::time::Duration::from_millis(300);
+        let info = collect(&mut sys, &options).unwrap();
+        assert_eq!(sys.refreshes, 2);
+        assert!(!info.partial);
+        assert!(info.cpu_breakdown.is_some());
+    }
+
+    #[test]
+    fn test_collect_system_info_saturated_counters() {
+        let sys = mock::MockProvider {
+            networks: vec![
+                mock::network("mock0", u64::MAX, u64::MAX),
+                mock::network("mock1", 1, u64::MAX - 1),
+            ],
+            ..mock::MockProvider::default()
+        };
+        let info = mock::snapshot(&sys);
+        assert_eq!(info.total_bytes_received, u64::MAX);
+        assert_eq!(info.total_bytes_transmitted, u64::MAX);
+
+        let mut report = Vec::new();
+        write_report(&mut report, &info, &mock::options(), false, Some(80)).unwrap();
+        let report = String::from_utf8(report).unwrap();
+        let networks = &report[report.find("Network Interfaces:").unwrap()..];
+        assert_eq!(
+            networks,
+            "Network Interfaces:
+  mock0:
+    Received: 16.00 EB (184,467,440,737,095,516 packets)
+    Transmitted: 16.00 EB (184,467,440,737,095,516 packets)
+  mock1:
+    Received: 1 B (0 packets)
+    Transmitted: 16.00 EB (184,467,440,737,095,516 packets)
+  Network Totals: 16.00 EB received, 16.00 EB transmitted
+  Default gateway: none
+  DNS servers: none
+"
+        );
+
+        // Counters keep all 64 bits in JSON
+        let json = serde_json::to_string(&info).unwrap();
+        assert!(json.contains(r#""bytes_received":18446744073709551615"#));
+        assert!(json.contains(r#""bytes_transmitted":18446744073709551614"#));
+        assert!(json.contains(r#""total_bytes_received":18446744073709551615"#));
+        let parsed: SystemInfo = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.networks[0].bytes_received, u64::MAX);
+    }
+
+    #[test]
+    fn test_write_report_memory_activity() {
+        let mut info = mock::snapshot(&mock::MockProvider::default());
+        info.memory_activity = Some(MemoryActivity {
+            interval_secs: 1.0,
+            swap_in_per_sec: 250.0,
+            swap_out_per_sec: 0.0,
+            major_faults_per_sec: 12.5,
+        });
+        let report = |info: &SystemInfo, colored| {
+            let mut report = Vec::new();
+            write_report(&mut report, info, &mock::options(), colored, Some(80)).unwrap();
+            let report = String::from_utf8(report).unwrap();
+            let start = report.find("  Swap Activity").unwrap();
+            let end = report.find("\n\nDisk Usage:").unwrap();
+            report[start..end].to_string()
+        };
+        assert_eq!(
+            report(&info, false),
+            "  Swap Activity: 250.0/s in, 0.0/s out (pages, sampled over 1.0s)\n  \
+             Major Page Faults: 12.5/s"
+        );
+        // Only the rate above --swap-warn-rate is highlighted
+        assert_eq!(
+            report(&info, true),
+            "  Swap Activity: \x1b[31m250.0/s\x1b[0m in, 0.0/s out (pages, sampled over 1.0s)\n  \
+             Major Page Faults: 12.5/s"
+        );
+
+        // Rates between interval samples, with the thrashing warnings
+        info.memory_activity = None;
+        info.pages_swapped_in_per_sec = Some(0.0);
+        info.pages_swapped_out_per_sec = Some(150.0);
+        info.major_page_faults_per_sec = Some(400.0);
+        let mut report = Vec::new();
+        write_report(&mut report, &info, &mock::options(), false, Some(80)).unwrap();
+        let report = String::from_utf8(report).unwrap();
+        assert!(report.contains(
+            "  Swap Activity Since Last Sample: 0.0/s in, 150.0/s out (pages)\n  \
+             Warning: the system is actively swapping\n  \
+             Major Page Faults Since Last Sample: 400.0/s\n  \
+             Warning: high major page fault rate, the system may be thrashing\n"
+        ));
+    }
+
+    #[test]
+    fn test_write_report_cpu_breakdown() {
+        let mut info = mock::snapshot(&mock::MockProvider::default());
+        info.cpu_breakdown = Some(CpuBreakdown {
+            user: Some(42.0),
+            system: Some(11.0),
+            idle: Some(35.0),
+            iowait: Some(3.0),
+            steal: Some(9.0),
+            ..CpuBreakdown::default()
+        });
+        let report = |info: &SystemInfo, colored| {
+            let mut report = Vec::new();
+            write_report(&mut report, info, &mock::options(), colored, Some(80)).unwrap();
+            String::from_utf8(report).unwrap()
+        };
+        // Shares the platform does not report (irq here) are left out
+        assert!(report(&info, false).contains(
+            "  CPU Cores: 4\n  CPU: 42.0% user, 11.0% sys, 3.0% iowait, 9.0% steal, 35.0% idle\n"
+        ));
+        assert!(report(&info, true).contains("\x1b[33m9.0% steal\x1b[0m"));
+
+        info.cpu_breakdown = None;
+        assert!(!report(&info, false).contains("  CPU: "));
+    }
+
+    #[test]
+    fn test_write_report_limits() {
+        let mut info = mock::snapshot(&mock::MockProvider::default());
+        info.limits_usage = Some(limits::LimitsUsage {
+            open_files: Some(limits::Usage::new(90_000, Some(100_000))),
+            processes: Some(limits::Usage::new(412, Some(4_194_304))),
+            // Windows has no limits to compare against
+            threads: Some(limits::Usage::new(2_048, None)),
+        });
+        assert_eq!(fd_global_percent(&info), Some(90.0));
+        let report = |info: &SystemInfo, options: &Options, colored| {
+            let mut report = Vec::new();
+            write_report(&mut report, info, options, colored, Some(80)).unwrap();
+            String::from_utf8(report).unwrap()
+        };
+
+        let mut options = mock::options();
+        assert!(report(&info, &options, false).contains(
+            "\nKernel Limits:\n  \
+             Open Files: 90,000 of 100,000 (90.0%)\n  \
+             Processes: 412 of 4,194,304 (0.0%)\n  \
+             Threads: 2,048\n"
+        ));
+        // Only open files past --warn-fd-global-pct are highlighted
+        options.warn_fd_global_pct = Some(95.0);
+        assert!(!report(&info, &options, true).contains("\x1b[33m"));
+        options.warn_fd_global_pct = Some(89.5);
+        assert!(report(&info, &options, true).contains("\x1b[33m90,000 of 100,000 (90.0%)\x1b[0m"));
+        // A threshold is only a warning, never a failure
+        assert!(check_required(&info, &options).is_ok());
+
+        info.limits_usage = None;
+        assert_eq!(fd_global_percent(&info), None);
+        assert!(!report(&info, &options, false).contains("Kernel Limits:"));
+    }
+
+    #[test]
+    fn test_write_report_entropy() {
+        let mut info = mock::snapshot(&mock::MockProvider::default());
+        info.entropy = Some(entropy::Entropy {
+            entropy_avail: Some(180),
+            pool_size: Some(4096),
+            fixed_estimate: false,
+            getrandom_would_block: Some(false),
+            hw_rng: Some("tpm-rng-0".to_string()),
+        });
+        let report = |info: &SystemInfo, options: &Options, colored| {
+            let mut report = Vec::new();
+            write_report(&mut report, info, options, colored, Some(80)).unwrap();
+            String::from_utf8(report).unwrap()
+        };
+
+        let mut options = mock::options();
+        assert!(report(&info, &options, false).contains(
+            "\nEntropy:\n  \
+             Pool: 180 bits of 4096\n  \
+             getrandom: ready\n  \
+             Hardware RNG: tpm-rng-0\n"
+        ));
+        // The pool is highlighted only below --warn-entropy
+        assert!(!report(&info, &options, true).contains("\x1b[33m180 bits"));
+        options.warn_entropy = Some(128);
+        assert!(!report(&info, &options, true).contains("\x1b[33m180 bits"));
+        options.warn_entropy = Some(256);
+        assert!(report(&info, &options, true).contains("\x1b[33m180 bits of 4096\x1b[0m"));
+        // A starved pool is only a warning, never a failure
+        assert!(check_required(&info, &options).is_ok());
+
+        // Newer kernels pin the estimate, which is never starved
+        info.entropy = Some(entropy::Entropy {
+            entropy_avail: Some(256),
+            pool_size: Some(256),
+            fixed_estimate: true,
+            getrandom_would_block: Some(false),
+            hw_rng: None,
+        });
+        options.warn_entropy = Some(1024);
+        let text = report(&info, &options, true);
+        assert!(text.contains("  Pool: 256 bits of 256 (fixed estimate)\n"));
+        assert!(text.contains("  Hardware RNG: none\n"));
+
+        info.entropy = None;
+        assert!(!report(&info, &options, false).contains("Entropy:"));
+    }
+
+    #[test]
+    fn test_write_report_advice() {
+        let mut info = mock::snapshot(&mock::MockProvider::default());
+        info.root_disk_usage_percent = Some(98.0);
+        let report = |info: &SystemInfo, options: &Options| {
+            let mut report = Vec::new();
+            write_report(&mut report, info, options, false, Some(80)).unwrap();
+            String::from_utf8(report).unwrap()
+        };
+
+        let mut options = mock::options();
+        assert!(!report(&info, &options).contains("Advice:"));
+        options.advise = true;
+        assert!(report(&info, &options).contains(
+            "\nAdvice:\n  The root filesystem has only 2.0% free; services fail in odd ways \
+             when it fills up\n"
+        ));
+        info.root_disk_usage_percent = Some(50.0);
+        assert!(report(&info, &options).contains("\nAdvice:\n  Nothing stands out\n"));
+    }
+
+    #[test]
+    fn test_next_due() {
+        let start = Instant::now();
+        let interval = std::time::Duration::from_secs(10);
+        let at = |ms: u64| start + std::time::Duration::from_millis(ms);
+        // A quick collection sleeps off the rest of the interval
+        assert_eq!(next_due(start, interval, at(2_500)), at(10_000));
+        assert_eq!(next_due(at(10_000), interval, at(10_100)), at(20_000));
+        // An overrun skips the missed samples and keeps the schedule
+        assert_eq!(next_due(start, interval, at(10_000)), at(10_000));
+        assert_eq!(next_due(start, interval, at(10_500)), at(20_000));
+        assert_eq!(next_due(start, interval, at(34_000)), at(40_000));
+    }
+
+    #[test]
+    fn test_select() {
+        let mut mounts = vec!["/", "/srv/a", "/srv/b", "/var"];
+        select(
+            &mut mounts,
+            &["/srv/*".to_string(), "/".to_string()],
+            "mount point",
+            |m| m,
+        )
+        .unwrap();
+        assert_eq!(mounts, ["/", "/srv/a", "/srv/b"]);
+        select(&mut mounts, &[], "mount point", |m| m).unwrap();
+        assert_eq!(mounts.len(), 3);
+
+        let error = select(&mut mounts, &["/data".to_string()], "mount point", |m| m).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "No mount point matches '/data' (available: /, /srv/a, /srv/b)"
+        );
+        assert_eq!(mounts.len(), 3);
+    }
+
+    #[test]
+    fn test_collect_system_info_selected() {
+        let sys = mock::MockProvider {
+            disks: vec![
+                mock::disk("/", "ext4", 100 << 30, 75 << 30),
+                mock::disk("/srv/a", "xfs", 10 << 30, 1 << 30),
+                mock::disk("/var", "ext4", 10 << 30, 0),
+            ],
+            networks: vec![
+                mock::network("eth0", 1000, 2000),
+                mock::network("eth1", 10, 20),
+                mock::network("lo", 5000, 5000),
+            ],
+            ..mock::MockProvider::default()
+        };
+        let mut options = mock::options();
+        options.disks = vec!["/srv/*".to_string(), "/".to_string()];
+        options.interfaces = vec!["eth*".to_string()];
+        let info = mock::snapshot_with(&sys, &options);
+        let names = |info: &SystemInfo| -> Vec<String> {
+            let disks = info.disks.iter().map(|disk| disk.name.clone());
+            disks
+                .chain(info.networks.iter().map(|network| network.name.clone()))
+                .collect()
+        };
+        assert_eq!(names(&info), ["/", "/srv/a", "eth0", "eth1"]);
+        assert_eq!(info.root_disk_usage_percent, Some(25.0));
+        // Totals cover the selected interfaces only
+        assert_eq!(info.total_bytes_received, 1010);
+        assert_eq!(info.total_bytes_transmitted, 2020);
+
+        // Checks only see the subset, so a full /var goes unnoticed
+        let checks = |checks: &[&str]| {
+            let args = checks
+                .iter()
+                .flat_map(|check| ["--check-expr".to_string(), check.to_string()]);
+            let mut checks = Options::parse(args).unwrap();
+            checks.disks = options.disks.clone();
+            checks
+        };
+        assert!(check_required(
+            &info,
+            &checks(&["crit: disks[\"/var\"].available_space < 1GB"])
+        )
+        .is_ok());
+        assert!(matches!(
+            check_required(
+                &info,
+                &checks(&["crit: disks[\"/srv/a\"].available_space < 2GB"])
+            ),
+            Err(AppError::CheckFailed(_))
+        ));
+
+        // A pattern that matches nothing lists what there is and exits 6
+        options.interfaces = vec!["wlan0".to_string()];
+        let Err(error) = collect_system_info(&sys, &options, None) else {
+            panic!("an unmatched interface was accepted");
+        };
+        assert_eq!(
+            error.to_string(),
+            "No network interface matches 'wlan0' (available: eth0, eth1, lo)"
+        );
+        assert_eq!(error.exit_code(), 6);
+        options.interfaces.clear();
+        options.disks = vec!["/data".to_string()];
+        assert!(matches!(
+            collect_system_info(&sys, &options, None),
+            Err(AppError::NotSelected {
+                what: "mount point",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_write_report_networks() {
+        let mut wifi = mock::network(
+            "Intel(R) Wi-Fi 6E AX211 160MHz Wireless Network Adapter #2 - Virtual Switch",
+            52_428_800,
+            10_485_760,
+        );
+        wifi.errors_received = 2;
+        let sys = mock::MockProvider {
+            networks: vec![mock::network("eth0", 1_073_741_824, 536_870_912), wifi],
+            ..mock::MockProvider::default()
+        };
+        let mut info = mock::snapshot(&sys);
+        info.networks[0].wireless = Some(WirelessInfo {
+            ssid: Some("Office".to_string()),
+            signal_dbm: Some(-52),
+            ..WirelessInfo::default()
+        });
+        let section = |width| {
+            let mut report = Vec::new();
+            write_report(&mut report, &info, &mock::options(), false, Some(width)).unwrap();
+            let report = String::from_utf8(report).unwrap();
+            let start = report.find("Network Interfaces:\n").unwrap();
+            let section = &report[start..];
+            section[..section.find("\n\n").unwrap_or(section.len())]
+                .trim_end()
+                .to_string()
+        };
+        let tail = "  eth0:
+    Received: 1.00 GB (10,737,418 packets)
+    Transmitted: 512.00 MB (5,368,709 packets)
+  Network Totals: 1.05 GB received, 522.00 MB transmitted
+  Default gateway: none
+  DNS servers: none";
+        assert_eq!(
+            section(120),
+            format!(
+                "Network Interfaces:
+  Intel(R) Wi-Fi 6E AX211 160MHz Wireless Network Adapter #2 - Virtual Switch (WiFi: Office, -52 dBm):
+    Received: 50.00 MB (524,288 packets)
+    Transmitted: 10.00 MB (104,857 packets)
+    Errors: 2 in, 0 out; Drops: n/a in, n/a out
+{}",
+                tail
+            )
+        );
+        // Names are shortened so the whole line fits, details included
+        assert_eq!(
+            section(80),
+            format!(
+                "Network Interfaces:
+  Intel(R) Wi-Fi 6E AX211 16…dapter #2 - Virtual Switch (WiFi: Office, -52 dBm):
+    Received: 50.00 MB (524,288 packets)
+    Transmitted: 10.00 MB (104,857 packets)
+    Errors: 2 in, 0 out; Drops: n/a in, n/a out
+{}",
+                tail
+            )
+        );
+        assert_eq!(
+            section(60),
+            "Network Interfaces:
+  Intel(R) Wi-Fi 6…- Virtual Switch (WiFi: Office, -52 dBm):
+    RX 50.00 MB (524,288 pkts), TX 10.00 MB (104,857 pkts)
+    Errors: 2 in, 0 out; Drops: n/a in, n/a out
+  eth0:
+    RX 1.00 GB (10,737,418 pkts), TX 512.00 MB (5,368,709 pkts)
+  Network Totals: 1.05 GB received, 522.00 MB transmitted
+  Default gateway: none
+  DNS servers: none"
+        );
+        // Interface headers always fit; at 80 columns every line does
+        assert!(section(60)
+            .lines()
+            .filter(|line| line.ends_with(':'))
+            .all(|line| line.chars().count() <= 60));
+        assert!(section(80).lines().all(|line| line.chars().count() <= 80));
+    }
+
+    #[test]
+    fn test_until_full_note() {
+        let mut info = mock::snapshot(&mock::MockProvider::default());
+        let note = |disk: &DiskInfo, colored| until_full_note(disk, 14.0, colored);
+        let disk = &mut info.disks[0];
+        assert_eq!(note(disk, false), None);
+        for (days, text) in [
+            (0.4, "(< 1 day until full)"),
+            (1.4, "(~1 day until full)"),
+            (8.6, "(~9 days until full)"),
+            (3649.0, "(~3649 days until full)"),
+        ] {
+            disk.estimated_days_until_full = Some(days);
+            assert_eq!(note(disk, false).as_deref(), Some(text));
+        }
+        // At the cap the disk is as good as not filling up
+        disk.estimated_days_until_full = Some(growth::MAX_DAYS_UNTIL_FULL);
+        assert_eq!(note(disk, true), None);
+        // Red only below the threshold
+        disk.estimated_days_until_full = Some(13.9);
+        assert_eq!(
+            note(disk, true).as_deref(),
+            Some("\x1b[31m(~14 days until full)\x1b[0m")
+        );
+        disk.estimated_days_until_full = Some(14.0);
+        assert_eq!(note(disk, true).as_deref(), Some("(~14 days until full)"));
+
+        // The note follows the disk's usage line
+        disk.estimated_days_until_full = Some(8.6);
+        let mut report = Vec::new();
+        write_report(&mut report, &info, &mock::options(), false, Some(80)).unwrap();
+        let report = String::from_utf8(report).unwrap();
+        let line = report
+            .lines()
+            .find(|line| line.contains("days until full"))
+            .unwrap();
+        assert!(line.contains("75.00 GB") && line.ends_with("  (~9 days until full)"));
+    }
+
+    #[test]
+    fn test_disk_lines() {
+        let disks: Vec<DiskInfo> = serde_json::from_str(r#"[
+    {"name": "/", "file_system": "ext4", "total_space": 53687091200, "available_space": 40265318400},
+    {"name": "/mnt/backups/nightly-snapshots/postgres-primary-replica-eu-west-02",
+     "file_system": "xfs",
+     "total_space": 1099511627776, "available_space": 109951162777}
+]"#).unwrap();
+        let lines = |width| -> Vec<String> {
+            disk_lines(
+                &disks,
+                Some(0),
+                Layout::for_width(Some(width)),
+                Some(width),
+                0,
+                Locale::default(),
+            )
+            .into_iter()
+            .map(|(line, _)| line)
+            .collect()
+        };
+        assert_eq!(lines(120), [
+    "  Mount                                                                    Used      Avail     Total   Use%  FS",
+    "  / (root)                                                             12.50 GB   37.50 GB  50.00 GB  25.0%  ext4",
+    "  /mnt/backups/nightly-snapshots/postgres-primary-replica-eu-west-02  921.60 GB  102.40 GB   1.00 TB  90.0%  xfs",
+]);
+        // 80 columns: no file system column, mount points shortened to fit
+        assert_eq!(
+            lines(80),
+            [
+                "  Mount                                         Used      Avail     Total   Use%",
+                "  / (root)                                  12.50 GB   37.50 GB  50.00 GB  25.0%",
+                "  /mnt/backups/nightl…-replica-eu-west-02  921.60 GB  102.40 GB   1.00 TB  90.0%",
+            ]
+        );
+        assert_eq!(
+            lines(60),
+            [
+                "  / (root)",
+                "    12.50 GB of 50.00 GB used (25.0%), 37.50 GB available",
+                "  /mnt/backups/nightly-snapsho…es-primary-replica-eu-west-02",
+                "    921.60 GB of 1.00 TB used (90.0%), 102.40 GB available",
+            ]
+        );
+        assert!(
+            disk_lines(
+                &disks,
+                Some(0),
+                Layout::Stacked,
+                Some(60),
+                0,
+                Locale::default()
+            )[1]
+            .1
+        );
+
+        // Mount options follow the file system where known
+        let disks: Vec<DiskInfo> = serde_json::from_str(r#"[
+    {"name": "/", "file_system": "ext4", "mount_options": ["rw", "noatime"],
+     "total_space": 53687091200, "available_space": 40265318400},
+    {"name": "/boot", "file_system": "vfat", "total_space": 1073741824, "available_space": 805306368}
+]"#).unwrap();
+        let lines: Vec<String> =
+            disk_lines(&disks, Some(0), Layout::Wide, None, 0, Locale::default())
+                .into_iter()
+                .map(|(line, _)| line)
+                .collect();
+        assert_eq!(
+            lines,
+            [
+                "  Mount          Used      Avail     Total   Use%  FS    Options",
+                "  / (root)   12.50 GB   37.50 GB  50.00 GB  25.0%  ext4  rw,noatime",
+                "  /boot     256.00 MB  768.00 MB   1.00 GB  25.0%  vfat",
+            ]
+        );
+    }
+}
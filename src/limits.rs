@@ -0,0 +1,225 @@
+//! System-wide usage of kernel object limits.
+//!
+//! "Too many open files" and fork failures are often caused by the whole
+//! host running out of file handles, process IDs or threads rather than by
+//! one process's limits. On Linux the totals are read from `/proc` and
+//! compared with `fs.file-max`, `kernel.pid_max` and `kernel.threads-max`;
+//! on Windows the totals come from performance counters, which have no
+//! corresponding limits. Other platforms report `None`. The totals are only
+//! read with the `host-details` feature.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "host-details")]
+use std::path::Path;
+
+/// Global totals of open files, processes and threads.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct LimitsUsage {
+    /// Allocated file handles (Linux) or open handles (Windows)
+    pub open_files: Option<Usage>,
+    /// Processes, against `kernel.pid_max` on Linux
+    pub processes: Option<Usage>,
+    /// Threads, against `kernel.threads-max` on Linux
+    pub threads: Option<Usage>,
+}
+
+/// A total and the limit it counts against, where the platform has one.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct Usage {
+    pub used: u64,
+    pub limit: Option<u64>,
+    /// `used` as a percentage of `limit`
+    pub percent: Option<f64>,
+}
+
+impl Usage {
+    /// Creates a usage of `used` out of `limit`.
+    #[cfg(any(test, feature = "host-details"))]
+    pub fn new(used: u64, limit: Option<u64>) -> Usage {
+        Usage {
+            used,
+            limit,
+            percent: limit
+                .filter(|&limit| limit > 0)
+                .map(|limit| used as f64 / limit as f64 * 100.0),
+        }
+    }
+}
+
+/// Reads the global totals and limits.
+#[cfg(all(feature = "host-details", target_os = "linux"))]
+pub fn query() -> Option<LimitsUsage> {
+    read_limits(Path::new("/proc"))
+}
+
+/// Reads the global totals from the `Process(_Total)` and `System`
+/// performance counters.
+#[cfg(all(feature = "host-details", windows))]
+pub fn query() -> Option<LimitsUsage> {
+    use std::process::Command;
+
+    let output = Command::new("typeperf")
+        .args([
+            "\\Process(_Total)\\Handle Count",
+            "\\System\\Processes",
+            "\\System\\Threads",
+            "-sc",
+            "1",
+        ])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let values = crate::memory::parse_typeperf(&String::from_utf8_lossy(&output.stdout))?;
+    let usage = |index: usize| Some(Usage::new(*values.get(index)? as u64, None));
+    Some(LimitsUsage {
+        open_files: usage(0),
+        processes: usage(1),
+        threads: usage(2),
+    })
+}
+
+/// Reads the global totals and limits.
+///
+/// Not available on this platform, so this always returns `None`.
+#[cfg(all(feature = "host-details", not(any(target_os = "linux", windows))))]
+pub fn query() -> Option<LimitsUsage> {
+    None
+}
+
+/// Reads the global totals and limits from the procfs tree at `root`.
+///
+/// Processes are counted from the numeric entries of `root`; threads come
+/// from the fourth field of `loadavg`. A total whose file is missing or
+/// malformed is `None`, and so is a limit.
+///
+/// # Returns
+///
+/// `None` if none of the totals can be read.
+#[cfg(feature = "host-details")]
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub fn read_limits(root: &Path) -> Option<LimitsUsage> {
+    let read = |path: &str| std::fs::read_to_string(root.join(path)).ok();
+    let number = |path: &str| read(path)?.trim().parse().ok();
+
+    let open_files = read("sys/fs/file-nr")
+        .and_then(|text| parse_file_nr(&text))
+        .map(|(allocated, max)| Usage::new(allocated, Some(max)));
+    let processes =
+        count_processes(root).map(|count| Usage::new(count, number("sys/kernel/pid_max")));
+    let threads = read("loadavg")
+        .and_then(|text| parse_loadavg_threads(&text))
+        .map(|count| Usage::new(count, number("sys/kernel/threads-max")));
+
+    if open_files.is_none() && processes.is_none() && threads.is_none() {
+        return None;
+    }
+    Some(LimitsUsage {
+        open_files,
+        processes,
+        threads,
+    })
+}
+
+/// Parses `/proc/sys/fs/file-nr` into the allocated handles and the maximum.
+///
+/// The file holds the allocated, free and maximum handle counts; the free
+/// count has been 0 since Linux 2.6, so allocated handles are in use.
+#[cfg(feature = "host-details")]
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_file_nr(text: &str) -> Option<(u64, u64)> {
+    let mut fields = text.split_whitespace().map(|field| field.parse().ok());
+    let allocated = fields.next()??;
+    let _free: u64 = fields.next()??;
+    Some((allocated, fields.next()??))
+}
+
+/// Parses the number of threads from `/proc/loadavg`, whose fourth field
+/// is "running/total" kernel scheduling entities.
+#[cfg(feature = "host-details")]
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_loadavg_threads(text: &str) -> Option<u64> {
+    let (_running, total) = text.split_whitespace().nth(3)?.split_once('/')?;
+    total.parse().ok()
+}
+
+/// Counts the process directories (numeric names) in the procfs at `root`.
+#[cfg(feature = "host-details")]
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn count_processes(root: &Path) -> Option<u64> {
+    let count = std::fs::read_dir(root)
+        .ok()?
+        .filter_map(Result::ok)
+        .filter(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            !name.is_empty() && name.bytes().all(|byte| byte.is_ascii_digit())
+        })
+        .count();
+    Some(count as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_usage_new() {
+        let usage = Usage::new(2_048, Some(8_192));
+        assert_eq!(usage.percent, Some(25.0));
+        assert_eq!(Usage::new(5, None).percent, None);
+        assert_eq!(Usage::new(5, Some(0)).percent, None);
+    }
+
+    #[test]
+    #[cfg(feature = "host-details")]
+    fn test_read_limits() {
+        let root = std::env::temp_dir().join(format!("limits-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        for dir in ["sys/fs", "sys/kernel", "1", "2", "self"] {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        std::fs::write(
+            root.join("sys/fs/file-nr"),
+            "4128\t0\t9223372036854775807\n",
+        )
+        .unwrap();
+        std::fs::write(root.join("sys/kernel/pid_max"), "4194304\n").unwrap();
+        std::fs::write(root.join("sys/kernel/threads-max"), "126858\n").unwrap();
+        std::fs::write(root.join("loadavg"), "0.08 0.03 0.01 2/412 9021\n").unwrap();
+
+        let limits = read_limits(&root).unwrap();
+        assert_eq!(limits.open_files.unwrap().used, 4128);
+        assert_eq!(limits.processes, Some(Usage::new(2, Some(4_194_304))));
+        assert_eq!(limits.threads, Some(Usage::new(412, Some(126_858))));
+        assert_eq!(read_limits(&root.join("missing")), None);
+
+        // Missing limits and totals are left out one by one
+        std::fs::remove_file(root.join("sys/fs/file-nr")).unwrap();
+        std::fs::remove_file(root.join("sys/kernel/threads-max")).unwrap();
+        std::fs::write(root.join("sys/kernel/pid_max"), "many\n").unwrap();
+        let limits = read_limits(&root).unwrap();
+        assert_eq!(limits.open_files, None);
+        assert_eq!(limits.processes, Some(Usage::new(2, None)));
+        assert_eq!(limits.threads, Some(Usage::new(412, None)));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "host-details")]
+    fn test_parse_file_nr() {
+        assert_eq!(parse_file_nr("4128\t0\t815360\n"), Some((4128, 815_360)));
+        assert_eq!(parse_file_nr("4128 0"), None);
+    }
+
+    #[test]
+    #[cfg(feature = "host-details")]
+    fn test_parse_loadavg_threads() {
+        assert_eq!(
+            parse_loadavg_threads("0.08 0.03 0.01 2/412 9021\n"),
+            Some(412)
+        );
+        assert_eq!(parse_loadavg_threads("0.08 0.03 0.01"), None);
+    }
+}
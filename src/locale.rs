@@ -0,0 +1,135 @@
+//! Locale-aware number formatting in the console (`--locale`).
+//!
+//! Only human-readable output is affected: sizes and percentages use the
+//! locale's decimal separator and counts its digit grouping, e.g.
+//! "1.234.567" and "1,50 GB" for German. JSON and the other machine formats
+//! keep raw numbers. Without `--locale` the output stays English-style
+//! whatever the environment says; `--locale auto` follows `LC_ALL`,
+//! `LC_NUMERIC` and `LANG`, in the order C programs do.
+//!
+//! The locale is passed to each formatting function rather than kept in a
+//! global, so one caller of the library never changes another's output.
+
+/// Thin space, as `--thousands-separator space` uses.
+const THIN_SPACE: &str = "\u{2009}";
+
+/// Languages writing "1.234,5".
+const PERIOD_GROUPING: &[&str] = &[
+    "ca", "da", "de", "el", "es", "hr", "id", "is", "it", "nl", "ro", "sl", "sr", "tr", "vi",
+];
+
+/// Languages writing "1 234,5".
+const SPACE_GROUPING: &[&str] = &[
+    "be", "bg", "cs", "et", "fi", "fr", "hu", "kk", "lt", "lv", "nb", "nn", "no", "pl", "pt", "ru",
+    "sk", "sv", "uk",
+];
+
+/// Separators of a locale.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Locale {
+    /// Whether decimals are written with a comma
+    pub decimal_comma: bool,
+    /// Separator between groups of three digits
+    pub thousands: &'static str,
+}
+
+impl Default for Locale {
+    /// English-style "1,234.5", the output without `--locale`.
+    fn default() -> Locale {
+        Locale {
+            decimal_comma: false,
+            thousands: ",",
+        }
+    }
+}
+
+impl Locale {
+    /// Parses a POSIX locale name such as `de_DE.UTF-8`, `fr` or `C`.
+    ///
+    /// Languages without a known convention get the English one.
+    ///
+    /// # Returns
+    ///
+    /// `None` if `name` is not a locale name.
+    pub fn parse(name: &str) -> Option<Locale> {
+        let name = name.split(['.', '@']).next().unwrap_or_default();
+        if name == "C" || name == "POSIX" {
+            return Some(Locale {
+                decimal_comma: false,
+                thousands: "",
+            });
+        }
+        let (language, region) = name.split_once('_').unwrap_or((name, ""));
+        let valid = (2..=3).contains(&language.len())
+            && language.bytes().all(|byte| byte.is_ascii_lowercase())
+            && region.bytes().all(|byte| byte.is_ascii_alphanumeric());
+        if !valid {
+            return None;
+        }
+        let (decimal_comma, thousands) = match (language, region) {
+            ("de", "CH" | "LI") => (false, "'"),
+            ("es", "MX" | "US") => (false, ","),
+            ("pt", "BR") => (true, "."),
+            ("en", "ZA") => (true, THIN_SPACE),
+            _ if PERIOD_GROUPING.contains(&language) => (true, "."),
+            _ if SPACE_GROUPING.contains(&language) => (true, THIN_SPACE),
+            _ => (false, ","),
+        };
+        Some(Locale {
+            decimal_comma,
+            thousands,
+        })
+    }
+
+    /// Returns the locale of `LC_ALL`, `LC_NUMERIC` or `LANG`, the first
+    /// one set, or the default one.
+    pub fn from_env() -> Locale {
+        ["LC_ALL", "LC_NUMERIC", "LANG"]
+            .into_iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|value| Locale::parse(&value))
+            .unwrap_or_default()
+    }
+
+    /// Writes the decimal point of a formatted number with this locale's
+    /// separator.
+    pub fn decimal(self, text: String) -> String {
+        if self.decimal_comma {
+            text.replace('.', ",")
+        } else {
+            text
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locale_parse() {
+        let de = Locale::parse("de_DE.UTF-8").unwrap();
+        assert_eq!((de.decimal_comma, de.thousands), (true, "."));
+        assert_eq!(Locale::parse("fr").unwrap().thousands, "\u{2009}");
+        assert_eq!(Locale::parse("pt_BR.UTF-8@x").unwrap().thousands, ".");
+        assert_eq!(
+            Locale::parse("de_CH").unwrap(),
+            Locale {
+                decimal_comma: false,
+                thousands: "'"
+            }
+        );
+        assert_eq!(Locale::parse("ja_JP.eucJP"), Some(Locale::default()));
+        assert_eq!(Locale::parse("C.UTF-8").unwrap().thousands, "");
+        assert_eq!(Locale::parse("german"), None);
+        assert_eq!(Locale::parse(""), None);
+    }
+
+    #[test]
+    fn test_locale_decimal() {
+        let de = Locale::parse("de_DE").unwrap();
+        assert_eq!(de.decimal("1.50 GB".to_string()), "1,50 GB");
+        assert_eq!(Locale::default().decimal("1.50 GB".to_string()), "1.50 GB");
+    }
+}
@@ -0,0 +1,47 @@
+//! Host identity that sysinfo does not provide.
+//!
+//! The machine ID is the stable per-installation identifier: `/etc/machine-id`
+//! (falling back to `/var/lib/dbus/machine-id`) on Linux, and the
+//! `MachineGuid` registry value on Windows. Other platforms report `None`.
+
+/// Reads the machine ID.
+///
+/// Returns `None` if no machine ID is configured or it cannot be read.
+#[cfg(target_os = "linux")]
+pub fn machine_id() -> Option<String> {
+    ["/etc/machine-id", "/var/lib/dbus/machine-id"]
+        .iter()
+        .filter_map(|path| std::fs::read_to_string(path).ok())
+        .map(|id| id.trim().to_string())
+        .find(|id| !id.is_empty())
+}
+
+/// Reads the machine ID.
+///
+/// Returns `None` if the registry value cannot be read.
+#[cfg(windows)]
+pub fn machine_id() -> Option<String> {
+    let output = std::process::Command::new("reg")
+        .args([
+            "query",
+            "HKLM\\SOFTWARE\\Microsoft\\Cryptography",
+            "/v",
+            "MachineGuid",
+        ])
+        .output()
+        .ok()?;
+    // The value line looks like "    MachineGuid    REG_SZ    <guid>".
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find(|line| line.trim_start().starts_with("MachineGuid"))
+        .and_then(|line| line.split_whitespace().nth(2))
+        .map(str::to_string)
+}
+
+/// Reads the machine ID.
+///
+/// Not available on this platform, so this always returns `None`.
+#[cfg(not(any(target_os = "linux", windows)))]
+pub fn machine_id() -> Option<String> {
+    None
+}
@@ -0,0 +1,421 @@
+//! The `markdown` output format, for pasting into wikis and tickets.
+//!
+//! The snapshot becomes a heading with the hostname, a bullet list of the
+//! top-level scalar fields in key order, one table each for disks and network interfaces
+//! with sizes in human-readable units, and the raw JSON in a collapsed
+//! `<details>` block. Text from the system is escaped, so a `|` or `*` in a
+//! mount point or interface name cannot break a table or start emphasis.
+
+use crate::cli::Options;
+use crate::locale::Locale;
+use crate::table::Align;
+use crate::{format_bytes, format_count, format_percent, SystemInfo};
+use serde_json::Value;
+
+/// Renders `info` as a Markdown document, with `json`, the snapshot as
+/// written to `system_info.json`, in its `<details>` block.
+///
+/// # Errors
+///
+/// Returns an error if `info` cannot be serialized for the summary.
+pub fn render(
+    info: &SystemInfo,
+    options: &Options,
+    json: &str,
+) -> Result<String, serde_json::Error> {
+    let locale = options.locale();
+    let mut out = format!("# System Information: {}\n", escape_cell(&info.hostname));
+
+    out.push_str("\n## Summary\n\n");
+    if let Value::Object(fields) = serde_json::to_value(info)? {
+        for (key, value) in &fields {
+            let value = match (key.as_str(), info.collected_at) {
+                ("collected_at", Some(time)) => options.timezone.format(time),
+                _ => match format_scalar(key, value, options.min_unit, locale) {
+                    Some(value) => value,
+                    None => continue,
+                },
+            };
+            out.push_str(&format!("- `{}`: {}\n", key, escape_cell(&value)));
+        }
+    }
+
+    out.push_str("\n## Disks\n\n");
+    {
+        let rows: Vec<Vec<String>> = info
+            .disks
+            .iter()
+            .map(|disk| {
+                vec![
+                    escape_cell(&disk.name),
+                    escape_cell(&disk.file_system),
+                    format_bytes(
+                        disk.total_space.saturating_sub(disk.available_space),
+                        options.min_unit,
+                        locale,
+                    ),
+                    format_bytes(disk.total_space, options.min_unit, locale),
+                    format_percent(disk.usage_percent(), locale),
+                ]
+            })
+            .collect();
+        out.push_str(&table(
+            &["Mount", "File System", "Used", "Total", "Use%"],
+            &[
+                Align::Left,
+                Align::Left,
+                Align::Right,
+                Align::Right,
+                Align::Right,
+            ],
+            &rows,
+        ));
+    }
+
+    out.push_str("\n## Network Interfaces\n\n");
+    {
+        let separator = options.thousands_separator();
+        let rows: Vec<Vec<String>> = info
+            .networks
+            .iter()
+            .map(|network| {
+                vec![
+                    escape_cell(&network.name),
+                    format_bytes(network.bytes_received, options.min_unit, locale),
+                    format_bytes(network.bytes_transmitted, options.min_unit, locale),
+                    format_count(network.packets_received, separator),
+                    format_count(network.packets_transmitted, separator),
+                    format_count(network.faults(), separator),
+                ]
+            })
+            .collect();
+        out.push_str(&table(
+            &[
+                "Interface",
+                "Received",
+                "Transmitted",
+                "Packets In",
+                "Packets Out",
+                "Errors/Drops",
+            ],
+            &[
+                Align::Left,
+                Align::Right,
+                Align::Right,
+                Align::Right,
+                Align::Right,
+                Align::Right,
+            ],
+            &rows,
+        ));
+    }
+
+    // A fence longer than any run of backticks in the JSON
+    let longest_run = json.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest_run.max(2) + 1);
+    out.push_str(&format!(
+        "\n<details>\n<summary>Raw JSON</summary>\n\n{}json\n{}\n{}\n\n</details>\n",
+        fence,
+        json.trim_end(),
+        fence
+    ));
+    Ok(out)
+}
+
+/// Formats a top-level scalar field for the summary table.
+///
+/// Byte counts (fields about memory, swap or bytes) get units, percentages a
+/// `%` sign, and other fractions two decimals.
+///
+/// # Returns
+///
+/// `None` for null, arrays and objects.
+fn format_scalar(key: &str, value: &Value, min_unit: usize, locale: Locale) -> Option<String> {
+    Some(match value {
+        Value::String(text) => text.clone(),
+        Value::Bool(value) => value.to_string(),
+        Value::Number(number) => {
+            let is_bytes = ["memory", "swap", "bytes"]
+                .iter()
+                .any(|word| key.contains(word));
+            match number.as_u64() {
+                _ if key.ends_with("_percent") => format_percent(number.as_f64()?, locale),
+                Some(bytes) if is_bytes => format_bytes(bytes, min_unit, locale),
+                Some(count) => count.to_string(),
+                None => match number.as_i64() {
+                    Some(integer) => integer.to_string(),
+                    None => format!("{:.2}", number.as_f64()?),
+                },
+            }
+        }
+        _ => return None,
+    })
+}
+
+/// Renders a Markdown table. Cells must already be escaped. Without rows,
+/// the table has one saying that none were detected.
+fn table(headers: &[&str], aligns: &[Align], rows: &[Vec<String>]) -> String {
+    let mut out = format!("| {} |\n", headers.join(" | "));
+    let rule: Vec<&str> = aligns
+        .iter()
+        .map(|align| match align {
+            Align::Left => "---",
+            Align::Right => "---:",
+        })
+        .collect();
+    out.push_str(&format!("| {} |\n", rule.join(" | ")));
+    for row in rows {
+        out.push_str(&format!("| {} |\n", row.join(" | ")));
+    }
+    if rows.is_empty() {
+        let mut empty = vec![String::new(); headers.len()];
+        empty[0] = "_None detected_".to_string();
+        out.push_str(&format!("| {} |\n", empty.join(" | ")));
+    }
+    out
+}
+
+/// Escapes text for a table cell or list item.
+///
+/// Pipes and backslashes are escaped and line breaks become spaces, since
+/// either would end the cell or the row. Characters that start emphasis,
+/// code, links or HTML are escaped too, so they show as written.
+fn escape_cell(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '|' | '\\' | '*' | '_' | '`' | '[' | ']' | '<' | '>' | '~' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\r' | '\n' => escaped.push(' '),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a snapshot from the JSON fields in `value`.
+    fn snapshot(value: serde_json::Value) -> SystemInfo {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_render() {
+        let info = snapshot(serde_json::json!({
+            "os_name": "Ubuntu",
+            "os_version": "24.04",
+            "hostname": "db_1",
+            "cpu_cores": 8,
+            "total_memory": 17179869184u64,
+            "used_memory": 4294967296u64,
+            "disks": [{
+                "name": "/",
+                "file_system": "ext4",
+                "total_space": 53687091200u64,
+                "available_space": 40265318400u64
+            }],
+            "networks": [{
+                "name": "eth0",
+                "bytes_received": 1536,
+                "bytes_transmitted": 512,
+                "packets_received": 12000,
+                "packets_transmitted": 800,
+                "errors_received": 2
+            }]
+        }));
+        let markdown = render(&info, &Options::default(), "{\"hostname\": \"db_1\"}").unwrap();
+        assert_eq!(
+            markdown,
+            "\
+# System Information: db\\_1
+
+## Summary
+
+- `cpu_cores`: 8
+- `hostname`: db\\_1
+- `os_name`: Ubuntu
+- `os_version`: 24.04
+- `total_bytes_received`: 0 B
+- `total_bytes_transmitted`: 0 B
+- `total_memory`: 16.00 GB
+- `total_swap`: 0 B
+- `used_memory`: 4.00 GB
+- `used_swap`: 0 B
+
+## Disks
+
+| Mount | File System | Used | Total | Use% |
+| --- | --- | ---: | ---: | ---: |
+| / | ext4 | 12.50 GB | 50.00 GB | 25.0% |
+
+## Network Interfaces
+
+| Interface | Received | Transmitted | Packets In | Packets Out | Errors/Drops |
+| --- | ---: | ---: | ---: | ---: | ---: |
+| eth0 | 1.50 KB | 512 B | 12,000 | 800 | 2 |
+
+<details>
+<summary>Raw JSON</summary>
+
+```json
+{\"hostname\": \"db_1\"}
+```
+
+</details>
+"
+        );
+    }
+
+    #[test]
+    fn test_render_empty() {
+        // Empty collections get a row saying so rather than an empty table
+        let info = snapshot(serde_json::json!({"hostname": "h"}));
+        let markdown = render(&info, &Options::default(), "{}").unwrap();
+        assert_eq!(
+            markdown,
+            "\
+# System Information: h
+
+## Summary
+
+- `cpu_cores`: 0
+- `hostname`: h
+- `os_name`: 
+- `os_version`: 
+- `total_bytes_received`: 0 B
+- `total_bytes_transmitted`: 0 B
+- `total_memory`: 0 B
+- `total_swap`: 0 B
+- `used_memory`: 0 B
+- `used_swap`: 0 B
+
+## Disks
+
+| Mount | File System | Used | Total | Use% |
+| --- | --- | ---: | ---: | ---: |
+| _None detected_ |  |  |  |  |
+
+## Network Interfaces
+
+| Interface | Received | Transmitted | Packets In | Packets Out | Errors/Drops |
+| --- | ---: | ---: | ---: | ---: | ---: |
+| _None detected_ |  |  |  |  |  |
+
+<details>
+<summary>Raw JSON</summary>
+
+```json
+{}
+```
+
+</details>
+"
+        );
+    }
+
+    #[test]
+    fn test_render_escaping() {
+        let info = snapshot(serde_json::json!({
+            "hostname": "<b>host</b>",
+            "disks": [{
+                "name": "/mnt/a|b*",
+                "file_system": "fuse.s`3`",
+                "total_space": 1073741824,
+                "available_space": 1073741824
+            }],
+            "networks": [{
+                "name": "br_[lan]|~1",
+                "bytes_received": 0,
+                "bytes_transmitted": 0,
+                "packets_received": 0,
+                "packets_transmitted": 0
+            }]
+        }));
+        let markdown = render(&info, &Options::default(), "{\"os_name\": \"```\"}").unwrap();
+        assert!(markdown.starts_with("# System Information: \\<b\\>host\\</b\\>\n"));
+        assert!(markdown.contains("- `hostname`: \\<b\\>host\\</b\\>\n"));
+        assert!(markdown.contains("\n| /mnt/a\\|b\\* | fuse.s\\`3\\` | 0 B | 1.00 GB | 0.0% |\n"));
+        assert!(markdown.contains("\n| br\\_\\[lan\\]\\|\\~1 | 0 B | 0 B | 0 | 0 | 0 |\n"));
+        // The fence outgrows the backticks in the JSON
+        assert!(markdown.contains("\n````json\n{\"os_name\": \"```\"}\n````\n"));
+    }
+
+    #[test]
+    fn test_format_scalar() {
+        use serde_json::json;
+        assert_eq!(
+            format_scalar("used_memory", &json!(1536), 0, Locale::default()).as_deref(),
+            Some("1.50 KB")
+        );
+        assert_eq!(
+            format_scalar("total_bytes_received", &json!(0), 0, Locale::default()).as_deref(),
+            Some("0 B")
+        );
+        assert_eq!(
+            format_scalar(
+                "root_disk_usage_percent",
+                &json!(68.494),
+                0,
+                Locale::default()
+            )
+            .as_deref(),
+            Some("68.5%")
+        );
+        assert_eq!(
+            format_scalar("root_disk_usage_percent", &json!(100), 0, Locale::default()).as_deref(),
+            Some("100.0%")
+        );
+        assert_eq!(
+            format_scalar("clock_offset_ms", &json!(-12), 0, Locale::default()).as_deref(),
+            Some("-12")
+        );
+        assert_eq!(
+            format_scalar(
+                "major_page_faults_per_sec",
+                &json!(0.127),
+                0,
+                Locale::default()
+            )
+            .as_deref(),
+            Some("0.13")
+        );
+        assert_eq!(
+            format_scalar("cpu_cores", &json!(8), 0, Locale::default()).as_deref(),
+            Some("8")
+        );
+        assert_eq!(
+            format_scalar("hostname", &json!("db-1"), 0, Locale::default()).as_deref(),
+            Some("db-1")
+        );
+        assert_eq!(
+            format_scalar("default_gateway", &json!(null), 0, Locale::default()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_table() {
+        let rows = vec![vec!["/".to_string(), "42.0%".to_string()]];
+        assert_eq!(
+            table(&["Mount", "Use%"], &[Align::Left, Align::Right], &rows),
+            "| Mount | Use% |\n| --- | ---: |\n| / | 42.0% |\n"
+        );
+    }
+
+    #[test]
+    fn test_escape_cell() {
+        assert_eq!(escape_cell("/mnt/a|b"), "/mnt/a\\|b");
+        assert_eq!(escape_cell("C:\\"), "C:\\\\");
+        assert_eq!(escape_cell("two\nlines"), "two lines");
+        assert_eq!(
+            escape_cell("/srv/*_[old]_<tmp>`~"),
+            "/srv/\\*\\_\\[old\\]\\_\\<tmp\\>\\`\\~"
+        );
+        assert_eq!(escape_cell("plain"), "plain");
+    }
+}
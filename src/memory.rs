@@ -0,0 +1,359 @@
+//! Memory paging activity sampled over an interval, and the memory limit
+//! of this process's cgroup.
+//!
+//! Swap usage alone says little about memory pressure; active swapping and
+//! major page faults do. On Linux the counters come from two reads of
+//! `/proc/vmstat`; on Windows from the `\Memory` performance counters via
+//! `typeperf`. Other platforms report `None`. The cgroup is only read with
+//! the `host-details` feature.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "host-details")]
+use std::path::Path;
+use std::time::Duration;
+
+/// cgroup v1 reports an unlimited cgroup as a limit near `i64::MAX`.
+#[cfg(feature = "host-details")]
+const CGROUP_V1_UNLIMITED: u64 = 1 << 62;
+
+/// Paging rates measured over a sampling interval.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct MemoryActivity {
+    /// Length of the sampling interval in seconds
+    pub interval_secs: f64,
+    /// Pages swapped in from disk per second
+    pub swap_in_per_sec: f64,
+    /// Pages swapped out to disk per second
+    pub swap_out_per_sec: f64,
+    /// Major page faults (faults requiring disk I/O) per second
+    pub major_faults_per_sec: f64,
+}
+
+/// Cumulative paging counters since boot.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PagingCounters {
+    /// Pages swapped in from disk
+    pub swap_in: u64,
+    /// Pages swapped out to disk
+    pub swap_out: u64,
+    /// Major page faults
+    pub major_faults: u64,
+}
+
+/// Reads the cumulative paging counters since boot.
+///
+/// Used in interval mode to compute rates between consecutive samples
+/// without blocking. Returns `None` if the counters cannot be read.
+#[cfg(target_os = "linux")]
+pub fn read_counters() -> Option<PagingCounters> {
+    let vmstat = parse_vmstat(&std::fs::read_to_string("/proc/vmstat").ok()?);
+    Some(PagingCounters {
+        swap_in: *vmstat.get("pswpin")?,
+        swap_out: *vmstat.get("pswpout")?,
+        major_faults: *vmstat.get("pgmajfault")?,
+    })
+}
+
+/// Reads the cumulative paging counters since boot.
+///
+/// Not available on this platform, so this always returns `None`.
+#[cfg(not(target_os = "linux"))]
+pub fn read_counters() -> Option<PagingCounters> {
+    None
+}
+
+/// Samples paging activity over `interval`, blocking for its duration.
+///
+/// Returns `None` if the counters cannot be read.
+#[cfg(target_os = "linux")]
+pub fn sample_memory_activity(interval: Duration) -> Option<MemoryActivity> {
+    use crate::rates::counter_rate;
+    use std::time::Instant;
+
+    let read = || std::fs::read_to_string("/proc/vmstat").ok();
+
+    let before = parse_vmstat(&read()?);
+    let start = Instant::now();
+    std::thread::sleep(interval);
+    let after = parse_vmstat(&read()?);
+    let elapsed = start.elapsed();
+
+    let rate = |key: &str| -> Option<f64> {
+        Some(counter_rate(*before.get(key)?, *after.get(key)?, elapsed))
+    };
+
+    Some(MemoryActivity {
+        interval_secs: elapsed.as_secs_f64(),
+        swap_in_per_sec: rate("pswpin")?,
+        swap_out_per_sec: rate("pswpout")?,
+        major_faults_per_sec: rate("pgmajfault")?,
+    })
+}
+
+/// Samples paging activity over `interval`, blocking for its duration.
+///
+/// Returns `None` if the performance counters cannot be read.
+#[cfg(windows)]
+pub fn sample_memory_activity(interval: Duration) -> Option<MemoryActivity> {
+    use std::process::Command;
+
+    let seconds = interval.as_secs().max(1);
+    let output = Command::new("typeperf")
+        .args([
+            "\\Memory\\Pages Input/sec",
+            "\\Memory\\Pages Output/sec",
+            "\\Memory\\Page Reads/sec",
+            "-si",
+            &seconds.to_string(),
+            "-sc",
+            "2",
+        ])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let values = parse_typeperf(&String::from_utf8_lossy(&output.stdout))?;
+    Some(MemoryActivity {
+        interval_secs: seconds as f64,
+        swap_in_per_sec: *values.first()?,
+        swap_out_per_sec: *values.get(1)?,
+        major_faults_per_sec: *values.get(2)?,
+    })
+}
+
+/// Samples paging activity over `interval`.
+///
+/// Not available on this platform, so this always returns `None`.
+#[cfg(not(any(target_os = "linux", windows)))]
+pub fn sample_memory_activity(_interval: Duration) -> Option<MemoryActivity> {
+    None
+}
+
+/// Memory use of the cgroup this process runs in, e.g. a container's.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct CgroupMemory {
+    /// Bytes in use, without the inactive page cache the kernel reclaims
+    /// before it kills anything
+    pub usage: u64,
+    /// Limit in bytes, or `None` if the cgroup has none
+    pub limit: Option<u64>,
+}
+
+/// Reads the memory use and limit of this process's cgroup.
+///
+/// Returns `None` if the memory controller is not available.
+#[cfg(all(feature = "host-details", target_os = "linux"))]
+pub fn cgroup_memory() -> Option<CgroupMemory> {
+    let proc_cgroup = std::fs::read_to_string("/proc/self/cgroup").ok()?;
+    read_cgroup_memory(
+        |path| std::fs::read_to_string(Path::new("/sys/fs/cgroup").join(path)).ok(),
+        &proc_cgroup,
+    )
+}
+
+/// Reads the memory use and limit of this process's cgroup.
+///
+/// Not available on this platform, so this always returns `None`.
+#[cfg(all(feature = "host-details", not(target_os = "linux")))]
+pub fn cgroup_memory() -> Option<CgroupMemory> {
+    None
+}
+
+/// Reads the memory use and limit of the cgroup that `proc_cgroup` (the
+/// contents of `/proc/self/cgroup`) names, with `read` returning a file
+/// below the cgroup mount: `memory.current` and `memory.max` for cgroup v2,
+/// else `memory.usage_in_bytes` and `memory.limit_in_bytes` of the v1
+/// memory controller. Without a cgroup namespace a container sees the
+/// host's path for its cgroup, but only its own cgroup is mounted, so the
+/// top of the mount is tried when the path does not exist.
+#[cfg(feature = "host-details")]
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn read_cgroup_memory(
+    read: impl Fn(&Path) -> Option<String>,
+    proc_cgroup: &str,
+) -> Option<CgroupMemory> {
+    // The cgroup v2 line is `0::PATH`, v1 lines `ID:CONTROLLERS:PATH`
+    let path = |controller: &str| {
+        proc_cgroup.lines().find_map(|line| {
+            let mut fields = line.splitn(3, ':');
+            let (_, controllers, path) = (fields.next()?, fields.next()?, fields.next()?);
+            (controllers.split(',').any(|name| name == controller))
+                .then(|| path.trim_start_matches('/'))
+        })
+    };
+    let number =
+        |dir: &Path, file: &str| -> Option<u64> { read(&dir.join(file))?.trim().parse().ok() };
+    let inactive_file = |dir: &Path, key: &str| -> u64 {
+        read(&dir.join("memory.stat"))
+            .and_then(|stat| {
+                stat.lines().find_map(|line| {
+                    let (name, value) = line.split_once(' ')?;
+                    (name == key).then(|| value.trim().parse().ok()).flatten()
+                })
+            })
+            .unwrap_or(0)
+    };
+
+    let candidates = |mount: &Path, path: Option<&str>| -> Vec<std::path::PathBuf> {
+        path.into_iter()
+            .map(|path| mount.join(path))
+            .chain(std::iter::once(mount.to_path_buf()))
+            .collect()
+    };
+    for dir in candidates(Path::new(""), path("")) {
+        if let Some(current) = number(&dir, "memory.current") {
+            let limit = read(&dir.join("memory.max")).and_then(|max| max.trim().parse().ok());
+            return Some(CgroupMemory {
+                usage: current.saturating_sub(inactive_file(&dir, "inactive_file")),
+                limit,
+            });
+        }
+    }
+    for dir in candidates(Path::new("memory"), path("memory")) {
+        if let Some(usage) = number(&dir, "memory.usage_in_bytes") {
+            let limit =
+                number(&dir, "memory.limit_in_bytes").filter(|&limit| limit < CGROUP_V1_UNLIMITED);
+            return Some(CgroupMemory {
+                usage: usage.saturating_sub(inactive_file(&dir, "total_inactive_file")),
+                limit,
+            });
+        }
+    }
+    None
+}
+
+/// Parses `/proc/vmstat` into a map of counter name to value.
+///
+/// Lines that are not `name value` pairs with a numeric value are skipped,
+/// so counters added by newer kernels are tolerated.
+#[cfg(target_os = "linux")]
+fn parse_vmstat(contents: &str) -> std::collections::HashMap<String, u64> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.split_once(' ')?;
+            Some((key.to_string(), value.trim().parse().ok()?))
+        })
+        .collect()
+}
+
+/// Extracts the counter values from the last sample row of `typeperf` CSV output.
+///
+/// Rows look like `"10/14/2026 19:20:01.123","12.5","0.0","3.0"`; the first
+/// column is the timestamp. The first sample of a rate counter is often
+/// empty, which is why two samples are taken and the last one used.
+#[cfg(windows)]
+pub fn parse_typeperf(output: &str) -> Option<Vec<f64>> {
+    let row = output
+        .lines()
+        .filter(|line| line.starts_with('"') && !line.starts_with("\"(PDH-CSV"))
+        .last()?;
+    row.split(',')
+        .skip(1)
+        .map(|field| field.trim().trim_matches('"').parse().ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_parse_vmstat() {
+        let vmstat = parse_vmstat(
+            "nr_free_pages 812345\npswpin 1024\npswpout 2048\npgmajfault 77\n\
+             nr_unknown_counter abc\nmalformed\n",
+        );
+        assert_eq!(vmstat["pswpin"], 1024);
+        assert_eq!(vmstat["pswpout"], 2048);
+        assert_eq!(vmstat["pgmajfault"], 77);
+        assert!(!vmstat.contains_key("nr_unknown_counter"));
+        assert_eq!(vmstat.len(), 4);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_sample_memory_activity() {
+        let activity = sample_memory_activity(Duration::from_millis(20)).unwrap();
+        assert!(activity.interval_secs >= 0.02);
+        assert!(activity.swap_in_per_sec >= 0.0);
+        assert!(activity.major_faults_per_sec >= 0.0);
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn test_parse_typeperf() {
+        let output = "\r\n\"(PDH-CSV 4.0)\",\"\\\\HOST\\Memory\\Pages Input/sec\",\"\\\\HOST\\Memory\\Pages Output/sec\",\"\\\\HOST\\Memory\\Page Reads/sec\"\r\n\
+                      \"10/14/2026 19:20:00.120\",\" \",\" \",\" \"\r\n\
+                      \"10/14/2026 19:20:01.123\",\"12.5\",\"0.000000\",\"3.0\"\r\n\
+                      The command completed successfully.\r\n";
+        assert_eq!(parse_typeperf(output), Some(vec![12.5, 0.0, 3.0]));
+        assert_eq!(parse_typeperf("no samples"), None);
+    }
+
+    #[test]
+    #[cfg(feature = "host-details")]
+    fn test_read_cgroup_memory() {
+        let files = |files: &'static [(&'static str, &'static str)]| {
+            move |path: &Path| {
+                files
+                    .iter()
+                    .find(|(name, _)| Path::new(name) == path)
+                    .map(|(_, contents)| contents.to_string())
+            }
+        };
+
+        // cgroup v2, 1.7 GB used of which 0.2 GB is reclaimable, 2 GB limit
+        let v2 = files(&[
+            ("system.slice/app.service/memory.current", "1700000000\n"),
+            ("system.slice/app.service/memory.max", "2000000000\n"),
+            (
+                "system.slice/app.service/memory.stat",
+                "anon 1400000000\ninactive_file 200000000\n",
+            ),
+        ]);
+        assert_eq!(
+            read_cgroup_memory(v2, "0::/system.slice/app.service\n"),
+            Some(CgroupMemory {
+                usage: 1_500_000_000,
+                limit: Some(2_000_000_000)
+            })
+        );
+
+        // A container without a cgroup namespace, and no limit
+        let v2 = files(&[("memory.current", "52428800\n"), ("memory.max", "max\n")]);
+        assert_eq!(
+            read_cgroup_memory(v2, "0::/kubepods.slice/kubepods-pod1f2e.slice\n"),
+            Some(CgroupMemory {
+                usage: 52_428_800,
+                limit: None
+            })
+        );
+
+        // cgroup v1
+        let v1 = files(&[
+            ("memory/docker/9ab3/memory.usage_in_bytes", "314572800\n"),
+            (
+                "memory/docker/9ab3/memory.limit_in_bytes",
+                "9223372036854771712\n",
+            ),
+            (
+                "memory/docker/9ab3/memory.stat",
+                "cache 0\ntotal_inactive_file 4194304\n",
+            ),
+        ]);
+        let proc_cgroup = "12:cpu,cpuacct:/docker/9ab3\n11:memory:/docker/9ab3\n";
+        assert_eq!(
+            read_cgroup_memory(v1, proc_cgroup),
+            Some(CgroupMemory {
+                usage: 310_378_496,
+                limit: None
+            })
+        );
+
+        assert_eq!(read_cgroup_memory(files(&[]), "0::/\n"), None);
+    }
+}
@@ -0,0 +1,179 @@
+//! Scripted system data for tests.
+//!
+//! `MockProvider` implements `SystemProvider` with plain fields, so a test
+//! sets up exactly the disks, interfaces and sensors it needs, and
+//! `snapshot` turns it into a `SystemInfo` holding only that data.
+
+use crate::cli::Options;
+use crate::provider::{DiskData, NetworkData, SystemProvider};
+use crate::thermal::ComponentTemperature;
+use crate::{collect_system_info, SystemInfo};
+use std::time::Duration;
+
+/// A `SystemProvider` returning the values in its fields.
+///
+/// The default describes a small Linux host with one disk mounted at `/`
+/// and one interface.
+#[derive(Debug, Clone)]
+pub struct MockProvider {
+    pub os_name: Option<String>,
+    pub os_version: Option<String>,
+    pub host_name: Option<String>,
+    pub physical_core_count: Option<usize>,
+    pub total_memory: u64,
+    pub used_memory: u64,
+    pub total_swap: u64,
+    pub used_swap: u64,
+    pub disks: Vec<DiskData>,
+    pub networks: Vec<NetworkData>,
+    pub components: Vec<ComponentTemperature>,
+    #[cfg(feature = "history")]
+    pub boot_time: u64,
+    /// How long each `refresh` blocks, to stand in for a slow system
+    pub refresh_delay: Duration,
+    /// Number of `refresh` calls so far
+    pub refreshes: usize,
+}
+
+impl Default for MockProvider {
+    fn default() -> MockProvider {
+        MockProvider {
+            os_name: Some("Linux".to_string()),
+            os_version: Some("6.1".to_string()),
+            host_name: Some("mockhost".to_string()),
+            physical_core_count: Some(4),
+            total_memory: 8 << 30,
+            used_memory: 2 << 30,
+            total_swap: 0,
+            used_swap: 0,
+            disks: vec![disk("/", "ext4", 100 << 30, 75 << 30)],
+            networks: vec![network("mock0", 1000, 2000)],
+            components: Vec::new(),
+            #[cfg(feature = "history")]
+            boot_time: 1_700_000_000,
+            refresh_delay: Duration::ZERO,
+            refreshes: 0,
+        }
+    }
+}
+
+impl SystemProvider for MockProvider {
+    fn refresh(&mut self) {
+        std::thread::sleep(self.refresh_delay);
+        self.refreshes += 1;
+    }
+    fn os_name(&self) -> Option<String> {
+        self.os_name.clone()
+    }
+    fn os_version(&self) -> Option<String> {
+        self.os_version.clone()
+    }
+    fn host_name(&self) -> Option<String> {
+        self.host_name.clone()
+    }
+    fn physical_core_count(&self) -> Option<usize> {
+        self.physical_core_count
+    }
+    fn total_memory(&self) -> u64 {
+        self.total_memory
+    }
+    fn used_memory(&self) -> u64 {
+        self.used_memory
+    }
+    fn total_swap(&self) -> u64 {
+        self.total_swap
+    }
+    fn used_swap(&self) -> u64 {
+        self.used_swap
+    }
+    fn disks(&self) -> Vec<DiskData> {
+        self.disks.clone()
+    }
+    fn networks(&self) -> Vec<NetworkData> {
+        self.networks.clone()
+    }
+    fn components(&self) -> Vec<ComponentTemperature> {
+        self.components.clone()
+    }
+    #[cfg(feature = "history")]
+    fn boot_time(&self) -> u64 {
+        self.boot_time
+    }
+}
+
+/// Returns a disk mounted at `mount` from a device that does not exist, so
+/// no label or UUID is found for it.
+pub fn disk(mount: &str, file_system: &str, total_space: u64, available_space: u64) -> DiskData {
+    DiskData {
+        device: format!("/dev/mock-{}", mount.trim_matches('/').replace('/', "-")).into(),
+        mount_point: mount.into(),
+        file_system: file_system.as_bytes().to_vec(),
+        total_space,
+        available_space,
+    }
+}
+
+/// Returns an interface with the given byte counters and one packet for
+/// every 100 bytes.
+pub fn network(name: &str, bytes_received: u64, bytes_transmitted: u64) -> NetworkData {
+    NetworkData {
+        name: name.into(),
+        bytes_received,
+        bytes_transmitted,
+        packets_received: bytes_received / 100,
+        packets_transmitted: bytes_transmitted / 100,
+        errors_received: 0,
+        errors_transmitted: 0,
+    }
+}
+
+/// Options that keep the collectors sampling over time short.
+pub fn options() -> Options {
+    Options {
+        sample_interval: Some(Duration::from_millis(1)),
+        ..Options::default()
+    }
+}
+
+/// Collects a snapshot from `sys` with `options` and keeps only the data
+/// the provider supplied, dropping what the collectors read from this host.
+///
+/// # Panics
+///
+/// Panics if collection fails.
+pub fn snapshot_with(sys: &MockProvider, options: &Options) -> SystemInfo {
+    let info = collect_system_info(sys, options, None).expect("collection failed");
+    let mut disks = info.disks;
+    for disk in &mut disks {
+        disk.mount_options = None;
+        disk.recent_fs_errors = None;
+    }
+    let mut networks = info.networks;
+    for network in &mut networks {
+        network.drops_received = None;
+        network.drops_transmitted = None;
+        network.wireless = None;
+    }
+    SystemInfo {
+        os_name: info.os_name,
+        os_version: info.os_version,
+        hostname: info.hostname,
+        cpu_cores: info.cpu_cores,
+        total_memory: info.total_memory,
+        used_memory: info.used_memory,
+        total_swap: info.total_swap,
+        used_swap: info.used_swap,
+        component_temperatures: info.component_temperatures,
+        disks,
+        root_disk_usage_percent: info.root_disk_usage_percent,
+        networks,
+        total_bytes_received: info.total_bytes_received,
+        total_bytes_transmitted: info.total_bytes_transmitted,
+        ..SystemInfo::default()
+    }
+}
+
+/// Collects a snapshot from `sys` with `options()`; see `snapshot_with`.
+pub fn snapshot(sys: &MockProvider) -> SystemInfo {
+    snapshot_with(sys, &options())
+}
@@ -0,0 +1,108 @@
+//! Mount options of mounted file systems, e.g. `ro`, `noatime` or `nodev`.
+//!
+//! On Linux they come from `/proc/mounts`. Other platforms report none.
+
+use std::collections::HashMap;
+use std::ffi::OsString;
+
+/// Mount options by mount point, as the OS reports the mount point.
+pub type MountOptions = HashMap<OsString, Vec<String>>;
+
+/// Reads the options of every mounted file system.
+///
+/// # Returns
+///
+/// `None` if the mount table cannot be read.
+#[cfg(target_os = "linux")]
+pub fn read() -> Option<MountOptions> {
+    std::fs::read_to_string("/proc/mounts")
+        .ok()
+        .map(|mounts| parse(&mounts))
+}
+
+/// Reads the options of every mounted file system.
+///
+/// # Returns
+///
+/// Always `None`: only Linux mount tables are read.
+#[cfg(not(target_os = "linux"))]
+pub fn read() -> Option<MountOptions> {
+    None
+}
+
+/// Parses the contents of `/proc/mounts`.
+///
+/// Where several file systems are mounted on one point, the last one is
+/// on top and its options win.
+#[cfg(target_os = "linux")]
+fn parse(mounts: &str) -> MountOptions {
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let _device = fields.next()?;
+            let mount_point = unescape(fields.next()?);
+            let _file_system = fields.next()?;
+            let options = fields.next()?.split(',').map(str::to_string).collect();
+            Some((mount_point, options))
+        })
+        .collect()
+}
+
+/// Decodes the octal escapes (`\040` for a space) the kernel writes for
+/// whitespace and backslashes in mount points.
+#[cfg(target_os = "linux")]
+fn unescape(field: &str) -> OsString {
+    use std::os::unix::ffi::OsStringExt;
+
+    let bytes = field.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] == b'\\' {
+            let byte = field
+                .get(index + 1..index + 4)
+                .and_then(|octal| u8::from_str_radix(octal, 8).ok());
+            if let Some(byte) = byte {
+                decoded.push(byte);
+                index += 4;
+                continue;
+            }
+        }
+        decoded.push(bytes[index]);
+        index += 1;
+    }
+    OsString::from_vec(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let mounts = "/dev/sda1 / ext4 rw,relatime,errors=remount-ro 0 0\n\
+              tmpfs /tmp tmpfs rw,nosuid,nodev,noatime 0 0\n\
+              /dev/sdb1 /mnt/usb\\040drive vfat ro,nosuid 0 0\n\
+              overlay /tmp overlay ro 0 0\n\
+              garbage\n";
+        let options = parse(mounts);
+        assert_eq!(
+            options[&OsString::from("/")],
+            ["rw", "relatime", "errors=remount-ro"]
+        );
+        assert_eq!(options[&OsString::from("/mnt/usb drive")], ["ro", "nosuid"]);
+        assert_eq!(options[&OsString::from("/tmp")], ["ro"]);
+        assert_eq!(options.len(), 3);
+    }
+
+    #[test]
+    fn test_unescape() {
+        assert_eq!(
+            unescape("/mnt/My\\040Files"),
+            OsString::from("/mnt/My Files")
+        );
+        assert_eq!(unescape("/mnt/a\\134b"), OsString::from("/mnt/a\\b"));
+        assert_eq!(unescape("/mnt/odd\\04"), OsString::from("/mnt/odd\\04"));
+    }
+}
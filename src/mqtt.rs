@@ -0,0 +1,513 @@
+//! Publishing snapshots to an MQTT broker (`--mqtt-url`).
+//!
+//! A minimal MQTT 3.1.1 client: it connects with a clean session, publishes
+//! at QoS 0, 1 or 2, optionally as the topic's retained message, and
+//! disconnects when done. In interval mode one connection serves every
+//! sample; a connection that broke is re-established on the next publish.
+//! Connecting is retried with exponential backoff before publishing fails.
+//!
+//! `mqtt://` URLs use plain TCP (port 1883 by default). For `mqtts://`
+//! (port 8883), TLS is handled by the system `openssl s_client`, like HTTPS
+//! is by `curl` elsewhere, so no TLS dependency is needed; the broker
+//! certificate is verified against the system trust store or `--mqtt-ca`.
+
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::path::PathBuf;
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::time::Duration;
+
+/// Topic snapshots are published to when `--mqtt-topic` is not given.
+pub const DEFAULT_TOPIC: &str = "sysinfo/{hostname}";
+
+/// Connection attempts before publishing fails.
+const ATTEMPTS: u32 = 5;
+
+/// Wait before the first retry; doubled for every further one.
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Longest wait between retries.
+const MAX_BACKOFF: Duration = Duration::from_secs(8);
+
+/// Time a TCP connect, read or write may take.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Longest client identifier every MQTT 3.1.1 broker must accept.
+const MAX_CLIENT_ID_LEN: usize = 23;
+
+/// How and where snapshots are published.
+#[derive(Debug, Clone, Default)]
+pub struct Settings {
+    /// Topic, with `{hostname}` already expanded
+    pub topic: String,
+    /// Quality of service level: 0, 1 or 2
+    pub qos: u8,
+    /// Whether the broker keeps the message as the topic's latest
+    pub retain: bool,
+    /// Keep-alive interval announced to the broker, in seconds
+    pub keep_alive_secs: u16,
+    /// CA certificates to verify an `mqtts://` broker with
+    pub ca_file: Option<PathBuf>,
+    /// Client certificate for `mqtts://`
+    pub cert_file: Option<PathBuf>,
+    /// Private key of the client certificate
+    pub key_file: Option<PathBuf>,
+}
+
+/// Expands `{hostname}` in a topic template.
+pub fn expand_topic(template: &str, hostname: &str) -> String {
+    let hostname: String = hostname
+        .chars()
+        .map(|c| if matches!(c, '+' | '#' | '/') { '_' } else { c })
+        .collect();
+    template.replace("{hostname}", &hostname)
+}
+
+/// Host, port and transport of a broker URL.
+#[derive(Debug, Clone, PartialEq)]
+struct Broker {
+    host: String,
+    port: u16,
+    tls: bool,
+}
+
+impl Broker {
+    /// Parses `mqtt://host[:port]` or `mqtts://host[:port]`.
+    fn parse(url: &str) -> Result<Broker, String> {
+        let (rest, tls, default_port) = if let Some(rest) = url.strip_prefix("mqtt://") {
+            (rest, false, 1883)
+        } else if let Some(rest) = url.strip_prefix("mqtts://") {
+            (rest, true, 8883)
+        } else {
+            return Err(format!("expected mqtt:// or mqtts:// URL, got '{}'", url));
+        };
+        let authority = rest.trim_end_matches('/');
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) if !host.ends_with(':') || host.starts_with('[') => {
+                let port = port
+                    .parse()
+                    .map_err(|_| format!("invalid port in '{}'", url))?;
+                (host, port)
+            }
+            _ => (authority, default_port),
+        };
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        if host.is_empty() {
+            return Err(format!("missing host in '{}'", url));
+        }
+        Ok(Broker {
+            host: host.to_string(),
+            port,
+            tls,
+        })
+    }
+}
+
+/// A byte stream to the broker.
+enum Connection {
+    Tcp(TcpStream),
+    /// An `openssl s_client` process tunneling the TLS connection
+    Tls {
+        child: Child,
+        stdin: ChildStdin,
+        stdout: ChildStdout,
+    },
+}
+
+impl Read for Connection {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Connection::Tcp(stream) => stream.read(buffer),
+            Connection::Tls { stdout, .. } => stdout.read(buffer),
+        }
+    }
+}
+
+impl Write for Connection {
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        match self {
+            Connection::Tcp(stream) => stream.write(buffer),
+            Connection::Tls { stdin, .. } => stdin.write(buffer),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Connection::Tcp(stream) => stream.flush(),
+            Connection::Tls { stdin, .. } => stdin.flush(),
+        }
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        // Best effort: the broker drops the session either way
+        let _ = self.write_all(&[0xe0, 0x00]);
+        if let Connection::Tls { child, .. } = self {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+/// Publishes snapshots to one broker over a long-lived connection.
+pub struct Publisher {
+    broker: Broker,
+    settings: Settings,
+    client_id: String,
+    connection: Option<Connection>,
+    packet_id: u16,
+}
+
+impl Publisher {
+    /// Creates a publisher for the broker at `url`; the connection is made
+    /// by the first `publish`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `url` is not an `mqtt://` or `mqtts://` URL.
+    pub fn new(url: &str, settings: Settings, hostname: &str) -> Result<Publisher, String> {
+        let mut client_id = format!("sysinfo-{}-{}", std::process::id(), hostname);
+        while client_id.len() > MAX_CLIENT_ID_LEN {
+            client_id.pop();
+        }
+        Ok(Publisher {
+            broker: Broker::parse(url)?,
+            settings,
+            client_id,
+            connection: None,
+            packet_id: 0,
+        })
+    }
+
+    /// Publishes `payload` to the topic, reconnecting if the connection
+    /// broke since the previous publish.
+    ///
+    /// # Errors
+    ///
+    /// Returns why the last attempt failed if the broker could not be
+    /// reached in `ATTEMPTS` tries, or refused the connection.
+    pub fn publish(&mut self, payload: &[u8]) -> Result<(), String> {
+        if let Some(connection) = &mut self.connection {
+            let packet_id = next_packet_id(&mut self.packet_id);
+            if send_publish(connection, &self.settings, packet_id, payload).is_ok() {
+                return Ok(());
+            }
+            self.connection = None;
+        }
+
+        let mut backoff = INITIAL_BACKOFF;
+        let mut attempt = 1;
+        loop {
+            let result = self.connect().and_then(|mut connection| {
+                let packet_id = next_packet_id(&mut self.packet_id);
+                send_publish(&mut connection, &self.settings, packet_id, payload)
+                    .map_err(|e| e.to_string())?;
+                Ok(connection)
+            });
+            match result {
+                Ok(connection) => {
+                    self.connection = Some(connection);
+                    return Ok(());
+                }
+                Err(e) if attempt >= ATTEMPTS => {
+                    return Err(format!(
+                        "{}:{}: {} (after {} attempts)",
+                        self.broker.host, self.broker.port, e, ATTEMPTS
+                    ))
+                }
+                Err(_) => {
+                    std::thread::sleep(backoff);
+                    backoff = (backoff * 2).min(MAX_BACKOFF);
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    /// Opens a connection and completes the MQTT handshake.
+    fn connect(&self) -> Result<Connection, String> {
+        let mut connection = if self.broker.tls {
+            self.spawn_tls()
+                .map_err(|e| format!("cannot run openssl: {}", e))?
+        } else {
+            let address = (self.broker.host.as_str(), self.broker.port);
+            let stream = std::net::ToSocketAddrs::to_socket_addrs(&address)
+                .and_then(|mut addresses| {
+                    addresses
+                        .next()
+                        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no address"))
+                })
+                .and_then(|address| TcpStream::connect_timeout(&address, TIMEOUT))
+                .map_err(|e| e.to_string())?;
+            let _ = stream.set_read_timeout(Some(TIMEOUT));
+            let _ = stream.set_write_timeout(Some(TIMEOUT));
+            Connection::Tcp(stream)
+        };
+        connection
+            .write_all(&connect_packet(
+                &self.client_id,
+                self.settings.keep_alive_secs,
+            ))
+            .map_err(|e| e.to_string())?;
+        let connack = read_packet(&mut connection).map_err(|e| match e.kind() {
+            // openssl exits without output if it cannot connect or verify
+            io::ErrorKind::UnexpectedEof if self.broker.tls => {
+                "TLS connection failed (is the broker certificate trusted?)".to_string()
+            }
+            io::ErrorKind::UnexpectedEof => "connection closed by the broker".to_string(),
+            _ => e.to_string(),
+        })?;
+        match connack {
+            (0x20, body) if body.len() == 2 && body[1] == 0 => Ok(connection),
+            (0x20, body) if body.len() == 2 => Err(connack_reason(body[1]).to_string()),
+            (header, _) => Err(format!(
+                "unexpected packet 0x{:02x} instead of CONNACK",
+                header
+            )),
+        }
+    }
+
+    /// Starts `openssl s_client` connected to the broker.
+    fn spawn_tls(&self) -> io::Result<Connection> {
+        let mut command = Command::new("openssl");
+        command
+            .args(["s_client", "-quiet", "-verify_return_error", "-connect"])
+            .arg(format!("{}:{}", self.broker.host, self.broker.port))
+            .args(["-servername", &self.broker.host])
+            .args(["-verify_hostname", &self.broker.host]);
+        for (flag, path) in [
+            ("-CAfile", &self.settings.ca_file),
+            ("-cert", &self.settings.cert_file),
+            ("-key", &self.settings.key_file),
+        ] {
+            if let Some(path) = path {
+                command.arg(flag).arg(path);
+            }
+        }
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = child.stdout.take().expect("stdout is piped");
+        Ok(Connection::Tls {
+            child,
+            stdin,
+            stdout,
+        })
+    }
+}
+
+/// Returns the next packet identifier; 0 is not a valid one.
+fn next_packet_id(counter: &mut u16) -> u16 {
+    *counter = counter.checked_add(1).unwrap_or(1);
+    *counter
+}
+
+/// Sends a PUBLISH and completes the acknowledgement flow of its QoS.
+fn send_publish(
+    connection: &mut Connection,
+    settings: &Settings,
+    packet_id: u16,
+    payload: &[u8],
+) -> io::Result<()> {
+    let packet = publish_packet(
+        &settings.topic,
+        payload,
+        settings.qos,
+        settings.retain,
+        packet_id,
+    );
+    connection.write_all(&packet)?;
+    connection.flush()?;
+    let id = packet_id.to_be_bytes();
+    match settings.qos {
+        0 => Ok(()),
+        1 => expect_ack(connection, 0x40, id),
+        _ => {
+            expect_ack(connection, 0x50, id)?;
+            connection.write_all(&[0x62, 0x02, id[0], id[1]])?;
+            expect_ack(connection, 0x70, id)
+        }
+    }
+}
+
+/// Reads the acknowledgement packet `header` for packet identifier `id`.
+fn expect_ack(connection: &mut Connection, header: u8, id: [u8; 2]) -> io::Result<()> {
+    let (received, body) = read_packet(connection)?;
+    if received == header && body == id {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("unexpected packet 0x{:02x} from the broker", received),
+        ))
+    }
+}
+
+/// Encodes a CONNECT packet for a clean session.
+fn connect_packet(client_id: &str, keep_alive_secs: u16) -> Vec<u8> {
+    let mut body = Vec::new();
+    push_string(&mut body, "MQTT");
+    // Protocol level 4 (3.1.1), clean session
+    body.extend_from_slice(&[4, 0x02]);
+    body.extend_from_slice(&keep_alive_secs.to_be_bytes());
+    push_string(&mut body, client_id);
+    packet(0x10, &body)
+}
+
+/// Encodes a PUBLISH packet; `packet_id` is only sent for QoS 1 and 2.
+fn publish_packet(topic: &str, payload: &[u8], qos: u8, retain: bool, packet_id: u16) -> Vec<u8> {
+    let mut body = Vec::with_capacity(topic.len() + payload.len() + 4);
+    push_string(&mut body, topic);
+    if qos > 0 {
+        body.extend_from_slice(&packet_id.to_be_bytes());
+    }
+    body.extend_from_slice(payload);
+    packet(0x30 | (qos.min(2) << 1) | u8::from(retain), &body)
+}
+
+/// Prefixes `body` with the fixed header: `header` and the remaining length.
+fn packet(header: u8, body: &[u8]) -> Vec<u8> {
+    let mut packet = vec![header];
+    let mut length = body.len();
+    loop {
+        let mut byte = (length % 128) as u8;
+        length /= 128;
+        if length > 0 {
+            byte |= 0x80;
+        }
+        packet.push(byte);
+        if length == 0 {
+            break;
+        }
+    }
+    packet.extend_from_slice(body);
+    packet
+}
+
+/// Appends a length-prefixed UTF-8 string.
+fn push_string(out: &mut Vec<u8>, text: &str) {
+    out.extend_from_slice(&(text.len() as u16).to_be_bytes());
+    out.extend_from_slice(text.as_bytes());
+}
+
+/// Reads one packet, returning its first header byte and its body.
+fn read_packet(stream: &mut impl Read) -> io::Result<(u8, Vec<u8>)> {
+    let mut byte = [0];
+    stream.read_exact(&mut byte)?;
+    let header = byte[0];
+    let mut length = 0usize;
+    for shift in (0..28).step_by(7) {
+        stream.read_exact(&mut byte)?;
+        length |= usize::from(byte[0] & 0x7f) << shift;
+        if byte[0] & 0x80 == 0 {
+            let mut body = vec![0; length];
+            stream.read_exact(&mut body)?;
+            return Ok((header, body));
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "malformed packet length",
+    ))
+}
+
+/// Describes a CONNACK return code that refused the connection.
+fn connack_reason(code: u8) -> &'static str {
+    match code {
+        1 => "the broker does not support MQTT 3.1.1",
+        2 => "the broker rejected the client identifier",
+        3 => "the broker is unavailable",
+        4 => "bad user name or password",
+        5 => "not authorized",
+        _ => "the broker refused the connection",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_topic() {
+        assert_eq!(
+            expand_topic("fleet/{hostname}/sysinfo", "edge-7"),
+            "fleet/edge-7/sysinfo"
+        );
+        // `+` and `#` are wildcards in subscriptions and must not be published to
+        assert_eq!(expand_topic("fleet/{hostname}", "a+b#c"), "fleet/a_b_c");
+    }
+
+    #[test]
+    fn test_broker_parse() {
+        let broker = Broker::parse("mqtt://broker.internal").unwrap();
+        assert_eq!(
+            (broker.host.as_str(), broker.port, broker.tls),
+            ("broker.internal", 1883, false)
+        );
+        let broker = Broker::parse("mqtts://10.0.0.5:8884/").unwrap();
+        assert_eq!(
+            (broker.host.as_str(), broker.port, broker.tls),
+            ("10.0.0.5", 8884, true)
+        );
+        assert_eq!(Broker::parse("mqtt://[::1]:1884").unwrap().host, "::1");
+        assert!(Broker::parse("http://broker").is_err());
+        assert!(Broker::parse("mqtt://broker:x").is_err());
+    }
+
+    #[test]
+    fn test_publisher_publish() {
+        // With a fake broker that accepts the connection and returns the first
+        // packet published:
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("mqtt://{}", listener.local_addr().unwrap());
+        let broker = std::thread::spawn(move || {
+            let (mut client, _) = listener.accept().unwrap();
+            assert_eq!(read_packet(&mut client).unwrap().0, 0x10); // CONNECT
+            client.write_all(&[0x20, 2, 0, 0]).unwrap(); // CONNACK: accepted
+            read_packet(&mut client).unwrap()
+        });
+        let settings = Settings {
+            topic: expand_topic("fleet/{hostname}/sysinfo", "edge-7"),
+            retain: true,
+            ..Settings::default()
+        };
+        let mut publisher = Publisher::new(&url, settings, "edge-7").unwrap();
+        publisher.publish(b"{}").unwrap();
+
+        let (header, body) = broker.join().unwrap();
+        assert_eq!(header, 0x31); // PUBLISH at QoS 0, retained
+        assert_eq!(body, b"\0\x14fleet/edge-7/sysinfo{}");
+    }
+
+    #[test]
+    fn test_connect_packet() {
+        assert_eq!(
+            connect_packet("a", 60),
+            [0x10, 13, 0, 4, b'M', b'Q', b'T', b'T', 4, 0x02, 0, 60, 0, 1, b'a']
+        );
+    }
+
+    #[test]
+    fn test_publish_packet() {
+        assert_eq!(
+            publish_packet("t", b"x", 0, false, 1),
+            [0x30, 4, 0, 1, b't', b'x']
+        );
+        assert_eq!(
+            publish_packet("t", b"x", 1, true, 258),
+            [0x33, 6, 0, 1, b't', 1, 2, b'x']
+        );
+        assert_eq!(publish_packet("t", b"x", 2, false, 1)[0], 0x34);
+        // bodies of 128 bytes and more take two length bytes
+        assert_eq!(
+            publish_packet("t", &[0; 200], 0, false, 1)[..3],
+            [0x30, 0xcb, 0x01]
+        );
+    }
+}
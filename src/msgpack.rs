@@ -0,0 +1,132 @@
+//! MessagePack encoding of JSON values, for the C interface.
+//!
+//! Only the types a snapshot serializes to are needed: nil, booleans,
+//! integers, 64-bit floats, strings, arrays and maps. Every value uses the
+//! smallest representation the format allows, as reference encoders do.
+
+use serde_json::Value;
+
+/// Encodes `value` as MessagePack.
+pub fn encode(value: &Value) -> Vec<u8> {
+    let mut out = Vec::new();
+    write_value(&mut out, value);
+    out
+}
+
+fn write_value(out: &mut Vec<u8>, value: &Value) {
+    match value {
+        Value::Null => out.push(0xc0),
+        Value::Bool(false) => out.push(0xc2),
+        Value::Bool(true) => out.push(0xc3),
+        Value::Number(number) => {
+            if let Some(unsigned) = number.as_u64() {
+                write_unsigned(out, unsigned);
+            } else if let Some(signed) = number.as_i64() {
+                write_signed(out, signed);
+            } else {
+                out.push(0xcb);
+                out.extend_from_slice(&number.as_f64().unwrap_or(0.0).to_be_bytes());
+            }
+        }
+        Value::String(text) => {
+            write_header(out, text.len(), 0xa0, 32, [0xd9, 0xda, 0xdb]);
+            out.extend_from_slice(text.as_bytes());
+        }
+        Value::Array(items) => {
+            write_header(out, items.len(), 0x90, 16, [0, 0xdc, 0xdd]);
+            for item in items {
+                write_value(out, item);
+            }
+        }
+        Value::Object(fields) => {
+            write_header(out, fields.len(), 0x80, 16, [0, 0xde, 0xdf]);
+            for (key, item) in fields {
+                write_value(out, &Value::String(key.clone()));
+                write_value(out, item);
+            }
+        }
+    }
+}
+
+/// Writes a non-negative integer.
+fn write_unsigned(out: &mut Vec<u8>, value: u64) {
+    match value {
+        0..=0x7f => out.push(value as u8),
+        0x80..=0xff => out.extend_from_slice(&[0xcc, value as u8]),
+        0x100..=0xffff => {
+            out.push(0xcd);
+            out.extend_from_slice(&(value as u16).to_be_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            out.push(0xce);
+            out.extend_from_slice(&(value as u32).to_be_bytes());
+        }
+        _ => {
+            out.push(0xcf);
+            out.extend_from_slice(&value.to_be_bytes());
+        }
+    }
+}
+
+/// Writes a negative integer.
+fn write_signed(out: &mut Vec<u8>, value: i64) {
+    if value >= -32 {
+        out.push(value as u8);
+    } else if value >= i8::MIN.into() {
+        out.extend_from_slice(&[0xd0, value as u8]);
+    } else if value >= i16::MIN.into() {
+        out.push(0xd1);
+        out.extend_from_slice(&(value as i16).to_be_bytes());
+    } else if value >= i32::MIN.into() {
+        out.push(0xd2);
+        out.extend_from_slice(&(value as i32).to_be_bytes());
+    } else {
+        out.push(0xd3);
+        out.extend_from_slice(&value.to_be_bytes());
+    }
+}
+
+/// Writes the header of a string, array or map of `length` elements: the
+/// `fixed` marker with the length added below `fixed_limit`, and otherwise
+/// the 8-, 16- or 32-bit length `markers` (0 where the type has none).
+fn write_header(out: &mut Vec<u8>, length: usize, fixed: u8, fixed_limit: usize, markers: [u8; 3]) {
+    if length < fixed_limit {
+        out.push(fixed | length as u8);
+    } else if length <= 0xff && markers[0] != 0 {
+        out.extend_from_slice(&[markers[0], length as u8]);
+    } else if length <= 0xffff {
+        out.push(markers[1]);
+        out.extend_from_slice(&(length as u16).to_be_bytes());
+    } else {
+        out.push(markers[2]);
+        out.extend_from_slice(&(length as u32).to_be_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        use serde_json::json;
+        assert_eq!(encode(&json!(null)), [0xc0]);
+        assert_eq!(encode(&json!(true)), [0xc3]);
+        assert_eq!(encode(&json!(7)), [0x07]);
+        assert_eq!(encode(&json!(200)), [0xcc, 200]);
+        assert_eq!(encode(&json!(65536)), [0xce, 0, 1, 0, 0]);
+        assert_eq!(
+            encode(&json!(u64::MAX)),
+            [0xcf, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]
+        );
+        assert_eq!(encode(&json!(-1)), [0xff]);
+        assert_eq!(encode(&json!(-33)), [0xd0, 0xdf]);
+        assert_eq!(encode(&json!(-40000)), [0xd2, 0xff, 0xff, 0x63, 0xc0]);
+        assert_eq!(encode(&json!(1.5)), [0xcb, 0x3f, 0xf8, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(encode(&json!("ext4")), [0xa4, b'e', b'x', b't', b'4']);
+        assert_eq!(encode(&json!("x".repeat(40)))[..2], [0xd9, 40]);
+        assert_eq!(encode(&json!([1, "a"])), [0x92, 0x01, 0xa1, b'a']);
+        assert_eq!(encode(&json!({"a": null})), [0x81, 0xa1, b'a', 0xc0]);
+        assert_eq!(encode(&json!(vec![0; 16]))[..3], [0xdc, 0, 16]);
+    }
+}
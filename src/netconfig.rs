@@ -0,0 +1,179 @@
+//! Host-wide network configuration: the default gateway and DNS servers.
+//!
+//! On Linux the gateway is read from the IPv4 routing table in
+//! `/proc/net/route`; on Windows it comes from `route print`. DNS servers are
+//! read from `/etc/resolv.conf` on Unix-like systems and from `ipconfig /all`
+//! on Windows. Hosts without a default route or resolver configuration
+//! report `None` and an empty list rather than an error.
+
+/// Returns the gateway of the primary IPv4 default route.
+///
+/// When several default routes exist, the one with the lowest metric wins.
+#[cfg(target_os = "linux")]
+pub fn default_gateway() -> Option<String> {
+    let table = std::fs::read_to_string("/proc/net/route").ok()?;
+    parse_proc_route(&table)
+}
+
+/// Returns the gateway of the primary IPv4 default route.
+///
+/// When several default routes exist, the one with the lowest metric wins.
+#[cfg(windows)]
+pub fn default_gateway() -> Option<String> {
+    let output = std::process::Command::new("route")
+        .args(["print", "-4", "0.0.0.0"])
+        .output()
+        .ok()?;
+    parse_route_print(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Returns the gateway of the primary IPv4 default route.
+///
+/// Not supported on this platform; always `None`.
+#[cfg(not(any(target_os = "linux", windows)))]
+pub fn default_gateway() -> Option<String> {
+    None
+}
+
+/// Returns the configured DNS servers in resolver order.
+#[cfg(unix)]
+pub fn dns_servers() -> Vec<String> {
+    std::fs::read_to_string("/etc/resolv.conf")
+        .map(|conf| parse_resolv_conf(&conf))
+        .unwrap_or_default()
+}
+
+/// Returns the configured DNS servers in resolver order.
+#[cfg(windows)]
+pub fn dns_servers() -> Vec<String> {
+    std::process::Command::new("ipconfig")
+        .arg("/all")
+        .output()
+        .map(|output| parse_ipconfig_dns(&String::from_utf8_lossy(&output.stdout)))
+        .unwrap_or_default()
+}
+
+/// Returns the configured DNS servers in resolver order.
+///
+/// Not supported on this platform; always empty.
+#[cfg(not(any(unix, windows)))]
+pub fn dns_servers() -> Vec<String> {
+    Vec::new()
+}
+
+/// Extracts the default gateway from the contents of `/proc/net/route`.
+///
+/// Addresses in the table are hexadecimal in host (little-endian) byte order.
+#[cfg(target_os = "linux")]
+fn parse_proc_route(table: &str) -> Option<String> {
+    const RTF_UP: u32 = 0x1;
+    const RTF_GATEWAY: u32 = 0x2;
+
+    table
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let destination = u32::from_str_radix(fields.get(1)?, 16).ok()?;
+            let gateway = u32::from_str_radix(fields.get(2)?, 16).ok()?;
+            let flags = u32::from_str_radix(fields.get(3)?, 16).ok()?;
+            let metric: u32 = fields.get(6)?.parse().ok()?;
+            let mask = u32::from_str_radix(fields.get(7)?, 16).ok()?;
+            let is_default = destination == 0 && mask == 0;
+            let usable = flags & RTF_UP != 0 && flags & RTF_GATEWAY != 0;
+            (is_default && usable).then_some((metric, gateway))
+        })
+        .min_by_key(|&(metric, _)| metric)
+        .map(|(_, gateway)| std::net::Ipv4Addr::from(gateway.to_le_bytes()).to_string())
+}
+
+/// Extracts the default gateway from `route print` output.
+///
+/// Default routes appear in the active routes table as lines of the form
+/// `0.0.0.0  0.0.0.0  <gateway>  <interface>  <metric>`; on-link routes,
+/// which have no gateway address, are skipped.
+#[cfg(windows)]
+fn parse_route_print(output: &str) -> Option<String> {
+    use std::net::Ipv4Addr;
+
+    output
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 5 || fields[0] != "0.0.0.0" || fields[1] != "0.0.0.0" {
+                return None;
+            }
+            let gateway: Ipv4Addr = fields[2].parse().ok()?;
+            let metric: u32 = fields[4].parse().ok()?;
+            Some((metric, gateway))
+        })
+        .min_by_key(|&(metric, _)| metric)
+        .map(|(_, gateway)| gateway.to_string())
+}
+
+/// Extracts `nameserver` addresses from the contents of `/etc/resolv.conf`.
+#[cfg(unix)]
+fn parse_resolv_conf(conf: &str) -> Vec<String> {
+    conf.lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            match fields.next() {
+                Some("nameserver") => fields.next().map(str::to_string),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Extracts DNS server addresses from `ipconfig /all` output.
+///
+/// Each adapter lists its servers as `DNS Servers . . . : <address>` followed
+/// by continuation lines that hold only an address. Servers shared between
+/// adapters are reported once.
+#[cfg(windows)]
+fn parse_ipconfig_dns(output: &str) -> Vec<String> {
+    use std::net::IpAddr;
+
+    let mut servers: Vec<String> = Vec::new();
+    let mut in_list = false;
+    for line in output.lines() {
+        let address = if let Some((label, value)) = line.split_once(" : ") {
+            in_list = label.trim_start().starts_with("DNS Servers");
+            value
+        } else if in_list {
+            line
+        } else {
+            continue;
+        };
+        // Drop IPv6 zone suffixes such as "%12" before parsing.
+        let address = address.trim().split('%').next().unwrap_or("");
+        match address.parse::<IpAddr>() {
+            Ok(ip) if in_list => {
+                let ip = ip.to_string();
+                if !servers.contains(&ip) {
+                    servers.push(ip);
+                }
+            }
+            _ => in_list = false,
+        }
+    }
+    servers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_proc_route() {
+        let table = "Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\n\
+             eth0\t00000000\t010200C0\t0003\t0\t0\t100\t00000000\n";
+        assert_eq!(parse_proc_route(table), Some("192.0.2.1".to_string()));
+    }
+
+    #[test]
+    fn test_parse_resolv_conf() {
+        let conf = "# generated\nsearch example.com\nnameserver 10.0.0.53\nnameserver ::1\n";
+        assert_eq!(parse_resolv_conf(conf), vec!["10.0.0.53", "::1"]);
+    }
+}
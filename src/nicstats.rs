@@ -0,0 +1,81 @@
+//! Per-interface drop counters from sysfs.
+//!
+//! sysinfo reports receive and transmit errors on every platform but no
+//! dropped packets, so on Linux those are read from
+//! `/sys/class/net/<interface>/statistics`. Other platforms report no drops.
+
+use std::ffi::OsStr;
+use std::path::Path;
+
+/// Root of the per-interface sysfs tree.
+#[cfg(target_os = "linux")]
+const SYS_CLASS_NET: &str = "/sys/class/net";
+
+/// Dropped packet counters of one interface since boot.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Drops {
+    pub received: u64,
+    pub transmitted: u64,
+}
+
+/// Reads the drop counters of `interface`.
+#[cfg(target_os = "linux")]
+pub fn query(interface: &OsStr) -> Option<Drops> {
+    read_drops(Path::new(SYS_CLASS_NET), interface)
+}
+
+/// Reads the drop counters of `interface`.
+///
+/// Not available on this platform, so always `None`.
+#[cfg(not(target_os = "linux"))]
+pub fn query(_interface: &OsStr) -> Option<Drops> {
+    None
+}
+
+/// Reads the drop counters of `interface` from the sysfs tree at `root`.
+///
+/// # Returns
+///
+/// `None` if either counter is missing or malformed.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub fn read_drops(root: &Path, interface: &OsStr) -> Option<Drops> {
+    let statistics = root.join(interface).join("statistics");
+    let counter = |name: &str| -> Option<u64> {
+        std::fs::read_to_string(statistics.join(name))
+            .ok()?
+            .trim()
+            .parse()
+            .ok()
+    };
+    Some(Drops {
+        received: counter("rx_dropped")?,
+        transmitted: counter("tx_dropped")?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_drops() {
+        let root = std::env::temp_dir().join(format!("nicstats-{}", std::process::id()));
+        let statistics = root.join("eth0/statistics");
+        std::fs::create_dir_all(&statistics).unwrap();
+        std::fs::write(statistics.join("rx_dropped"), "12\n").unwrap();
+        std::fs::write(statistics.join("tx_dropped"), "0\n").unwrap();
+
+        let drops = read_drops(&root, OsStr::new("eth0"));
+        assert_eq!(
+            drops,
+            Some(Drops {
+                received: 12,
+                transmitted: 0
+            })
+        );
+        assert_eq!(read_drops(&root, OsStr::new("eth1")), None);
+        std::fs::write(statistics.join("tx_dropped"), "n/a\n").unwrap();
+        assert_eq!(read_drops(&root, OsStr::new("eth0")), None);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,160 @@
+//! Clock skew check against an NTP server (`--check-clock`).
+//!
+//! A single SNTP (RFC 4330) request is sent over UDP and the local clock's
+//! offset is computed from the four timestamps of the exchange, the same way
+//! `ntpdate -q` does, so network delay in either direction cancels out as long
+//! as it is symmetric.
+
+use std::io;
+use std::net::{ToSocketAddrs, UdpSocket};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Port used when the server is given without one.
+const NTP_PORT: u16 = 123;
+
+/// Time to wait for the server's reply.
+const TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Seconds from the NTP epoch (1900) to the Unix epoch (1970).
+const NTP_UNIX_OFFSET: i128 = 2_208_988_800;
+
+/// Queries `server` ("host" or "host:port") and returns how far the local
+/// clock is ahead of it in milliseconds; negative if it is behind.
+///
+/// # Errors
+///
+/// Returns an error if the name does not resolve, no reply arrives within
+/// `TIMEOUT`, or the reply is not a valid server response.
+pub fn clock_offset_ms(server: &str) -> io::Result<i64> {
+    // "host:port" resolves as is, a bare host or IPv6 address gets the
+    // default port
+    let address = server
+        .to_socket_addrs()
+        .or_else(|_| (server.trim_matches(['[', ']']), NTP_PORT).to_socket_addrs())?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no address found"))?;
+
+    let socket = UdpSocket::bind(if address.is_ipv4() {
+        "0.0.0.0:0"
+    } else {
+        "[::]:0"
+    })?;
+    socket.set_read_timeout(Some(TIMEOUT))?;
+    socket.connect(address)?;
+
+    // LI = 0, version 4, mode 3 (client); the transmit timestamp is echoed
+    // back as the originate timestamp
+    let mut request = [0u8; 48];
+    request[0] = 0x23;
+    let sent = now_ns();
+    request[40..48].copy_from_slice(&to_timestamp(sent));
+    socket.send(&request)?;
+
+    let mut reply = [0u8; 48];
+    let length = loop {
+        match socket.recv(&mut reply) {
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "no reply"))
+            }
+            result => break result?,
+        }
+    };
+    let received = now_ns();
+
+    let invalid = |reason: &str| io::Error::new(io::ErrorKind::InvalidData, reason.to_string());
+    if length < 48 || reply[0] & 0x07 != 4 {
+        return Err(invalid("not an NTP server reply"));
+    }
+    if reply[1] == 0 {
+        return Err(invalid("the server refused the request"));
+    }
+    if reply[24..32] != request[40..48] {
+        return Err(invalid("the reply does not match the request"));
+    }
+
+    Ok(offset_ms(
+        sent,
+        from_timestamp(&reply[32..40]),
+        from_timestamp(&reply[40..48]),
+        received,
+    ))
+}
+
+/// Computes the local clock's offset from the client send time `t1`, server
+/// receive time `t2`, server send time `t3` and client receive time `t4`, all
+/// in nanoseconds since the Unix epoch.
+///
+/// # Returns
+///
+/// The offset in milliseconds, positive when the local clock is ahead.
+fn offset_ms(t1: i128, t2: i128, t3: i128, t4: i128) -> i64 {
+    let server_ahead = ((t2 - t1) + (t3 - t4)) / 2;
+    (-server_ahead as f64 / 1_000_000.0).round() as i64
+}
+
+/// Returns the current time in nanoseconds since the Unix epoch.
+fn now_ns() -> i128 {
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(since) => since.as_nanos() as i128,
+        Err(e) => -(e.duration().as_nanos() as i128),
+    }
+}
+
+/// Encodes nanoseconds since the Unix epoch as an NTP timestamp.
+fn to_timestamp(ns: i128) -> [u8; 8] {
+    let seconds = ns.div_euclid(1_000_000_000) + NTP_UNIX_OFFSET;
+    let fraction = (ns.rem_euclid(1_000_000_000) << 32) / 1_000_000_000;
+    (((seconds as u64 & 0xffff_ffff) << 32) | fraction as u64).to_be_bytes()
+}
+
+/// Decodes an NTP timestamp into nanoseconds since the Unix epoch.
+///
+/// Seconds with the high bit clear are taken to be in the era that starts in
+/// 2036, as RFC 4330 recommends.
+fn from_timestamp(bytes: &[u8]) -> i128 {
+    let mut seconds = i128::from(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]));
+    if seconds & 0x8000_0000 == 0 {
+        seconds += 1 << 32;
+    }
+    let fraction = i128::from(u32::from_be_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]));
+    (seconds - NTP_UNIX_OFFSET) * 1_000_000_000 + ((fraction * 1_000_000_000 + (1 << 31)) >> 32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offset_ms() {
+        // server 250 ms behind, 20 ms each way
+        assert_eq!(
+            offset_ms(1_000_000_000, 770_000_000, 771_000_000, 1_041_000_000),
+            250
+        );
+        assert_eq!(offset_ms(0, 0, 0, 0), 0);
+        assert_eq!(offset_ms(0, 1_500_000_000, 1_500_000_000, 0), -1500);
+    }
+
+    #[test]
+    fn test_to_timestamp() {
+        assert_eq!(to_timestamp(0), [0x83, 0xaa, 0x7e, 0x80, 0, 0, 0, 0]);
+        assert_eq!(
+            from_timestamp(&to_timestamp(1_700_000_000_500_000_000)),
+            1_700_000_000_500_000_000
+        );
+    }
+
+    #[test]
+    fn test_from_timestamp() {
+        assert_eq!(
+            from_timestamp(&[0x83, 0xaa, 0x7e, 0x80, 0x80, 0, 0, 0]),
+            500_000_000
+        );
+        // 2036-02-07T06:28:16Z, the start of era 1
+        assert_eq!(
+            from_timestamp(&[0, 0, 0, 0, 0, 0, 0, 0]),
+            2_085_978_496_000_000_000
+        );
+    }
+}
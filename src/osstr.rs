@@ -0,0 +1,115 @@
+//! Lossless handling of OS strings that are not valid UTF-8.
+//!
+//! Paths such as mount points are not guaranteed to be UTF-8 on Unix, and
+//! `to_string_lossy` replaces invalid bytes with U+FFFD, so the displayed name
+//! no longer matches the real path. For such strings a percent-encoded copy
+//! of the exact bytes is kept alongside the display form. On Unix the bytes
+//! are the raw path bytes; on Windows they are the WTF-8 encoding of the wide
+//! string, which only differs from UTF-8 for unpaired surrogates.
+
+use std::ffi::OsStr;
+
+/// Converts `value` to a display string and, when the conversion was lossy,
+/// a percent-encoded lossless copy.
+///
+/// # Returns
+///
+/// The display string, and `Some(raw)` only if `value` is not valid Unicode.
+pub fn lossless(value: &OsStr) -> (String, Option<String>) {
+    match value.to_str() {
+        Some(text) => (text.to_string(), None),
+        None => (
+            value.to_string_lossy().into_owned(),
+            Some(percent_encode(value.as_encoded_bytes())),
+        ),
+    }
+}
+
+/// Percent-encodes every byte that is not printable ASCII, and `%` itself.
+pub fn percent_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len());
+    for &byte in bytes {
+        match byte {
+            b'%' => encoded.push_str("%25"),
+            0x21..=0x7E => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Decodes `percent_encode` output back into bytes.
+    fn percent_decode(encoded: &str) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut rest = encoded.as_bytes();
+        while let Some((&byte, tail)) = rest.split_first() {
+            if byte == b'%' {
+                let hex = std::str::from_utf8(&tail[..2]).unwrap();
+                bytes.push(u8::from_str_radix(hex, 16).unwrap());
+                rest = &tail[2..];
+            } else {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+        bytes
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_lossless() {
+        use std::os::unix::ffi::OsStrExt;
+
+        assert_eq!(
+            lossless(OsStr::new("/mnt/café")),
+            ("/mnt/café".to_string(), None)
+        );
+
+        // Latin-1 and a lone continuation byte, as written by old clients
+        for raw in [&b"/srv/caf\xe9 100%"[..], b"eth\x80"] {
+            let (name, encoded) = lossless(OsStr::from_bytes(raw));
+            assert!(name.contains('\u{FFFD}'));
+            assert_eq!(percent_decode(&encoded.unwrap()), raw);
+        }
+        assert_eq!(
+            lossless(OsStr::from_bytes(b"/srv/caf\xe9 100%")),
+            (
+                "/srv/caf\u{FFFD} 100%".to_string(),
+                Some("/srv/caf%E9%20100%25".to_string())
+            )
+        );
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn test_lossless() {
+        use std::ffi::OsString;
+        use std::os::windows::ffi::OsStringExt;
+
+        // Wide strings that are valid UTF-16 convert exactly
+        let path: Vec<u16> = "C:\\Données\\日本".encode_utf16().collect();
+        assert_eq!(
+            lossless(&OsString::from_wide(&path)),
+            ("C:\\Données\\日本".to_string(), None)
+        );
+
+        // An unpaired surrogate is kept in its WTF-8 form
+        let (name, encoded) = lossless(&OsString::from_wide(&[0x43, 0x3A, 0xD800]));
+        assert_eq!(name, "C:\u{FFFD}");
+        assert_eq!(percent_decode(&encoded.unwrap()), b"C:\xED\xA0\x80");
+    }
+
+    #[test]
+    fn test_percent_encode() {
+        assert_eq!(percent_encode(b"/mnt/data"), "/mnt/data");
+        assert_eq!(percent_encode(b"/mnt/caf\xe9"), "/mnt/caf%E9");
+        assert_eq!(
+            percent_encode(b"/mnt/my disk 100%"),
+            "/mnt/my%20disk%20100%25"
+        );
+    }
+}
@@ -0,0 +1,277 @@
+//! Pending OS updates and reboot-required state (`--updates`).
+//!
+//! On Linux the reboot marker is `/var/run/reboot-required` (Debian/Ubuntu)
+//! or the exit status of `needs-restarting -r` (RHEL and derivatives), and
+//! pending updates are counted from `apt-get -s upgrade` or
+//! `dnf check-update`. On Windows a reboot is pending when the
+//! `PendingFileRenameOperations` registry value exists, and updates are
+//! counted through the Windows Update COM API. Every probe runs with
+//! `PROBE_TIMEOUT`; a probe that fails or times out leaves its field `None`
+//! and prints a warning on stderr.
+
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::process::{Command, Output};
+use std::time::Duration;
+
+/// Longest a single probe command may run before it is killed.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Patch state of the host.
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct UpdateStatus {
+    /// Whether a reboot is needed to finish installing updates
+    pub reboot_required: Option<bool>,
+    /// Number of updates available but not yet installed
+    pub pending_updates: Option<u64>,
+}
+
+/// Checks for pending updates and whether a reboot is required.
+///
+/// The two probes run concurrently since package-manager queries can take
+/// several seconds.
+pub fn query() -> UpdateStatus {
+    std::thread::scope(|scope| {
+        let reboot = scope.spawn(reboot_required);
+        let pending_updates = pending_updates();
+        UpdateStatus {
+            reboot_required: reboot.join().unwrap_or(None),
+            pending_updates,
+        }
+    })
+}
+
+/// Returns whether a reboot is required to finish installing updates.
+#[cfg(target_os = "linux")]
+fn reboot_required() -> Option<bool> {
+    use std::path::Path;
+
+    if Path::new("/var/run/reboot-required").exists() {
+        return Some(true);
+    }
+    match run(Command::new("needs-restarting").arg("-r")) {
+        // needs-restarting exits with 1 when a reboot is needed
+        Ok(output) => match output.status.code() {
+            Some(0) => return Some(false),
+            Some(1) => return Some(true),
+            _ => warn("needs-restarting -r", "unexpected exit status"),
+        },
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => warn("needs-restarting -r", &e.to_string()),
+    }
+    // Debian-based systems only create the marker when a reboot is needed
+    if Path::new("/var/lib/dpkg").exists() {
+        return Some(false);
+    }
+    None
+}
+
+/// Returns whether a reboot is required to finish installing updates.
+#[cfg(windows)]
+fn reboot_required() -> Option<bool> {
+    let key = r"HKLM\SYSTEM\CurrentControlSet\Control\Session Manager";
+    match run(Command::new("reg").args(["query", key, "/v", "PendingFileRenameOperations"])) {
+        // reg query exits with 1 when the value does not exist
+        Ok(output) => match output.status.code() {
+            Some(0) => Some(true),
+            Some(1) => Some(false),
+            _ => {
+                warn("reg query", "unexpected exit status");
+                None
+            }
+        },
+        Err(e) => {
+            warn("reg query", &e.to_string());
+            None
+        }
+    }
+}
+
+/// Returns whether a reboot is required to finish installing updates.
+///
+/// Not supported on this platform; always `None`.
+#[cfg(not(any(target_os = "linux", windows)))]
+fn reboot_required() -> Option<bool> {
+    None
+}
+
+/// Returns the number of updates available from the system package manager.
+#[cfg(target_os = "linux")]
+fn pending_updates() -> Option<u64> {
+    match run(Command::new("apt-get").args(["-s", "-q", "upgrade"])) {
+        Ok(output) if output.status.success() => {
+            return Some(count_apt_upgrades(&String::from_utf8_lossy(&output.stdout)))
+        }
+        Ok(_) => {
+            warn("apt-get -s upgrade", "unexpected exit status");
+            return None;
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => {
+            warn("apt-get -s upgrade", &e.to_string());
+            return None;
+        }
+    }
+    match run(Command::new("dnf").args(["check-update", "-q"])) {
+        // dnf check-update exits with 100 when updates are available
+        Ok(output) => match output.status.code() {
+            Some(0) => Some(0),
+            Some(100) => Some(count_dnf_updates(&String::from_utf8_lossy(&output.stdout))),
+            _ => {
+                warn("dnf check-update", "unexpected exit status");
+                None
+            }
+        },
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => {
+            warn("dnf check-update", &e.to_string());
+            None
+        }
+    }
+}
+
+/// Returns the number of updates offered by Windows Update.
+#[cfg(windows)]
+fn pending_updates() -> Option<u64> {
+    let script = "(New-Object -ComObject Microsoft.Update.Session)\
+                  .CreateUpdateSearcher().Search('IsInstalled=0 and IsHidden=0')\
+                  .Updates.Count";
+    match run(Command::new("powershell").args([
+        "-NoProfile",
+        "-NonInteractive",
+        "-Command",
+        script,
+    ])) {
+        Ok(output) if output.status.success() => {
+            let count = String::from_utf8_lossy(&output.stdout).trim().parse().ok();
+            if count.is_none() {
+                warn("Windows Update search", "unexpected output");
+            }
+            count
+        }
+        Ok(_) => {
+            warn("Windows Update search", "the search failed");
+            None
+        }
+        Err(e) => {
+            warn("Windows Update search", &e.to_string());
+            None
+        }
+    }
+}
+
+/// Returns the number of updates available from the system package manager.
+///
+/// Not supported on this platform; always `None`.
+#[cfg(not(any(target_os = "linux", windows)))]
+fn pending_updates() -> Option<u64> {
+    None
+}
+
+/// Counts the packages `apt-get -s upgrade` would install.
+///
+/// Each simulated installation is reported on an `Inst` line.
+#[cfg(target_os = "linux")]
+fn count_apt_upgrades(output: &str) -> u64 {
+    output
+        .lines()
+        .filter(|line| line.starts_with("Inst "))
+        .count() as u64
+}
+
+/// Counts the packages listed by `dnf check-update`.
+///
+/// Package lines have three columns (`name.arch version repository`). A
+/// name too long for its column is printed alone, with the other two
+/// columns on an indented continuation line; such a package counts once.
+/// Blank lines, other messages, and the "Obsoleting Packages" section that
+/// follows the updates are not counted.
+#[cfg(target_os = "linux")]
+fn count_dnf_updates(output: &str) -> u64 {
+    let mut lines = output
+        .lines()
+        .take_while(|line| !line.starts_with("Obsoleting Packages"))
+        .peekable();
+    let mut count = 0;
+    while let Some(line) = lines.next() {
+        let columns: Vec<&str> = line.split_whitespace().collect();
+        let package = match columns.len() {
+            3 => columns[0].contains('.'),
+            1 if columns[0].contains('.') => lines
+                .next_if(|next| {
+                    next.starts_with(char::is_whitespace) && next.split_whitespace().count() == 2
+                })
+                .is_some(),
+            _ => false,
+        };
+        count += u64::from(package);
+    }
+    count
+}
+
+/// Runs a probe command with `PROBE_TIMEOUT`.
+fn run(command: &mut Command) -> io::Result<Output> {
+    crate::command::run_with_timeout(command, PROBE_TIMEOUT)
+}
+
+/// Prints a warning that `probe` could not be completed.
+fn warn(probe: &str, reason: &str) {
+    eprintln!(
+        "Warning: could not check updates with {}: {}",
+        probe, reason
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_count_apt_upgrades() {
+        let output = "Reading package lists...\n\
+              Building dependency tree...\n\
+              Reading state information...\n\
+              Calculating upgrade...\n\
+              The following packages will be upgraded:\n  \
+              libc6 openssl\n\
+              2 upgraded, 0 newly installed, 0 to remove and 0 not upgraded.\n\
+              Inst libc6 [2.35-0ubuntu3.6] (2.35-0ubuntu3.7 Ubuntu:22.04/jammy-updates [amd64])\n\
+              Inst openssl [3.0.2-0ubuntu1.14] (3.0.2-0ubuntu1.15 Ubuntu:22.04/jammy-security [amd64])\n\
+              Conf libc6 (2.35-0ubuntu3.7 Ubuntu:22.04/jammy-updates [amd64])\n\
+              Conf openssl (3.0.2-0ubuntu1.15 Ubuntu:22.04/jammy-security [amd64])\n";
+        assert_eq!(count_apt_upgrades(output), 2);
+
+        let up_to_date = "Reading package lists...\n\
+              Building dependency tree...\n\
+              Reading state information...\n\
+              Calculating upgrade...\n\
+              0 upgraded, 0 newly installed, 0 to remove and 0 not upgraded.\n";
+        assert_eq!(count_apt_upgrades(up_to_date), 0);
+        assert_eq!(count_apt_upgrades(""), 0);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_count_dnf_updates() {
+        let output = "\n\
+              kernel.x86_64        5.14.0-427.el9      baseos\n\
+              openssl.x86_64       1:3.0.7-27.el9      baseos\n\
+              Obsoleting Packages\n\
+              grub2-tools.x86_64   1:2.06-77.el9       baseos\n";
+        assert_eq!(count_dnf_updates(output), 2);
+
+        // A long package name wraps the other columns onto the next line;
+        // the metadata line some versions print is not a package
+        let wrapped = concat!(
+            "Last metadata expiration check: 0:41:12 ago on Wed 14 Oct 2026 09:12:03 AM UTC.\n",
+            "\n",
+            "NetworkManager.x86_64                1:1.46.0-19.el9     baseos\n",
+            "python3-unbound-resolver-plugins.noarch\n",
+            "                                     1.16.2-3.el9        appstream\n",
+            "tzdata.noarch                        2026a-1.el9         baseos\n",
+        );
+        assert_eq!(count_dnf_updates(wrapped), 3);
+        assert_eq!(count_dnf_updates(""), 0);
+    }
+}
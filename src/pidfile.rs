@@ -0,0 +1,107 @@
+//! The `--pid-file` option.
+//!
+//! The file holds the process ID followed by a newline, as init systems and
+//! supervisors expect. An existing file whose process is no longer running
+//! is left over from a previous run and is overwritten; one naming another
+//! running process means that instance is still active, and is left alone.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use sysinfo::{Pid, System, SystemExt};
+
+/// A PID file that is removed again when dropped.
+pub struct PidFile {
+    path: PathBuf,
+}
+
+impl PidFile {
+    /// Writes the current process ID to `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AlreadyExists` if `path` names another running process, or
+    /// an error if the file cannot be written.
+    pub fn create(path: &Path) -> io::Result<PidFile> {
+        Self::create_with(path, |pid| {
+            System::new().refresh_process(Pid::from(pid as usize))
+        })
+    }
+
+    /// Like `create`, with `running` telling whether a process ID is in use.
+    fn create_with(path: &Path, running: impl Fn(u32) -> bool) -> io::Result<PidFile> {
+        let holder = std::fs::read_to_string(path)
+            .ok()
+            .and_then(|text| text.trim().parse::<u32>().ok());
+        if let Some(pid) = holder.filter(|&pid| pid != std::process::id() && running(pid)) {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} belongs to running process {}", path.display(), pid),
+            ));
+        }
+        std::fs::write(path, format!("{}\n", std::process::id()))?;
+        Ok(PidFile {
+            path: path.to_path_buf(),
+        })
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_file(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("sysinfo-{}-{}.pid", name, std::process::id()))
+    }
+
+    #[test]
+    fn test_create() {
+        let path = temp_file("create");
+        let _ = std::fs::remove_file(&path);
+        let pid_file = PidFile::create(&path).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            format!("{}\n", std::process::id())
+        );
+        drop(pid_file);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_create_held() {
+        let path = temp_file("held");
+        std::fs::write(&path, "4242\n").unwrap();
+        let error = PidFile::create_with(&path, |pid| pid == 4242)
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+        // The running instance keeps its file
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "4242\n");
+
+        // Left over from a process that has exited
+        let pid_file = PidFile::create_with(&path, |_| false).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            format!("{}\n", std::process::id())
+        );
+        drop(pid_file);
+        assert!(!path.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_create_running() {
+        // The test runner's parent is running; no process has ID u32::MAX
+        let path = temp_file("running");
+        std::fs::write(&path, format!("{}\n", std::os::unix::process::parent_id())).unwrap();
+        assert!(PidFile::create(&path).is_err());
+        std::fs::write(&path, format!("{}\n", u32::MAX)).unwrap();
+        drop(PidFile::create(&path).unwrap());
+        assert!(!path.exists());
+    }
+}
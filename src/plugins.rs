@@ -0,0 +1,198 @@
+//! Collection plugins (`--plugin-dir`).
+//!
+//! Every executable file in the plugin directory is run directly (never
+//! through a shell) with a cleared environment, apart from the variables in
+//! `ENV_ALLOWLIST`, and must print one JSON object on stdout. The object is
+//! reported under `plugins.<file name>` together with the plugin's exit
+//! status and run time. A plugin that cannot be started, times out, exits
+//! unsuccessfully, or prints anything but a JSON object gets an `error`
+//! instead of `data`; plugin failures never fail the run.
+//!
+//! Plugins run concurrently, so the whole section takes about as long as the
+//! slowest plugin, and at most the `--plugin-timeout`.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::time::{Duration, Instant};
+
+/// Timeout per plugin when `--plugin-timeout` is not given.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Largest plugin output accepted.
+const MAX_OUTPUT_BYTES: usize = 64 * 1024;
+
+/// Environment variables passed through to plugins.
+const ENV_ALLOWLIST: &[&str] = &["PATH", "LANG", "LC_ALL", "TZ", "SYSTEMROOT", "TEMP", "TMP"];
+
+/// The outcome of running one plugin.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct PluginResult {
+    /// Exit code, or `None` if the plugin did not exit normally
+    pub exit_code: Option<i32>,
+    /// Wall-clock run time in milliseconds
+    pub duration_ms: u64,
+    /// The JSON object the plugin printed, if it succeeded
+    pub data: Option<Value>,
+    /// Why the plugin's data is missing, if it failed
+    pub error: Option<String>,
+}
+
+/// Runs every plugin in `dir`, keyed by file name.
+///
+/// # Errors
+///
+/// Returns an error if the directory cannot be read. Problems with
+/// individual plugins are reported in their results instead.
+pub fn run_all(dir: &Path, timeout: Duration) -> io::Result<BTreeMap<String, PluginResult>> {
+    // Plugins run in their own directory, so a relative path to one would no
+    // longer resolve
+    let dir = dir.canonicalize()?;
+    let mut plugins: Vec<(String, PathBuf)> = Vec::new();
+    for entry in std::fs::read_dir(&dir)? {
+        let path = entry?.path();
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        if !name.starts_with('.') && is_executable(&path) {
+            plugins.push((name.to_string(), path));
+        }
+    }
+
+    Ok(std::thread::scope(|scope| {
+        let handles: Vec<_> = plugins
+            .into_iter()
+            .map(|(name, path)| (name, scope.spawn(move || run(&path, timeout))))
+            .collect();
+        handles
+            .into_iter()
+            .map(|(name, handle)| {
+                let result = handle.join().unwrap_or_else(|_| PluginResult {
+                    error: Some("plugin runner panicked".to_string()),
+                    ..PluginResult::default()
+                });
+                (name, result)
+            })
+            .collect()
+    }))
+}
+
+/// Runs one plugin and validates its output.
+fn run(path: &Path, timeout: Duration) -> PluginResult {
+    let mut command = Command::new(path);
+    command.env_clear();
+    for key in ENV_ALLOWLIST {
+        if let Some(value) = std::env::var_os(key) {
+            command.env(key, value);
+        }
+    }
+    if let Some(dir) = path.parent() {
+        command.current_dir(dir);
+    }
+
+    let start = Instant::now();
+    let output = crate::command::run_limited(&mut command, timeout, MAX_OUTPUT_BYTES);
+    let duration_ms = start.elapsed().as_millis() as u64;
+    evaluate(output, duration_ms)
+}
+
+/// Turns the outcome of running a plugin into its reported result.
+fn evaluate(output: io::Result<Output>, duration_ms: u64) -> PluginResult {
+    let mut result = PluginResult {
+        duration_ms,
+        ..PluginResult::default()
+    };
+    let output = match output {
+        Ok(output) => output,
+        Err(e) => {
+            result.error = Some(e.to_string());
+            return result;
+        }
+    };
+    result.exit_code = output.status.code();
+
+    // Checked first: a plugin stopped for writing too much usually dies of
+    // the broken pipe
+    if output.stdout.len() > MAX_OUTPUT_BYTES {
+        result.error = Some(format!("output exceeds {} KiB", MAX_OUTPUT_BYTES / 1024));
+    } else if !output.status.success() {
+        result.error = Some(match output.status.code() {
+            Some(code) => format!("exited with status {}", code),
+            None => "terminated by a signal".to_string(),
+        });
+    } else {
+        match serde_json::from_slice::<Value>(&output.stdout) {
+            Ok(data) if data.is_object() => result.data = Some(data),
+            Ok(_) => result.error = Some("output is not a JSON object".to_string()),
+            Err(e) => result.error = Some(format!("invalid JSON: {}", e)),
+        }
+    }
+    result
+}
+
+/// Returns whether `path` is a file the current platform can execute.
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    std::fs::metadata(path)
+        .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
+
+/// Returns whether `path` is a file the current platform can execute.
+///
+/// Only `.exe` files are run, since scripts would need an interpreter or
+/// shell to start them.
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+        && path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("exe"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+
+    #[test]
+    #[cfg(unix)]
+    fn test_evaluate() {
+        use std::os::unix::process::ExitStatusExt;
+        let output = |code: i32, stdout: &str| Output {
+            status: std::process::ExitStatus::from_raw(code << 8),
+            stdout: stdout.as_bytes().to_vec(),
+            stderr: Vec::new(),
+        };
+
+        let ok = evaluate(Ok(output(0, r#"{"gpu_jobs": 3}"#)), 12);
+        assert_eq!(ok.data, Some(json!({"gpu_jobs": 3})));
+        assert_eq!((ok.exit_code, ok.error), (Some(0), None));
+
+        let failed = evaluate(Ok(output(2, "")), 5);
+        assert_eq!(failed.error.as_deref(), Some("exited with status 2"));
+
+        let not_object = evaluate(Ok(output(0, "[1, 2]")), 5);
+        assert_eq!(
+            not_object.error.as_deref(),
+            Some("output is not a JSON object")
+        );
+
+        let timed_out = evaluate(
+            Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "timed out after 10s",
+            )),
+            10000,
+        );
+        assert_eq!(
+            (timed_out.exit_code, timed_out.error.as_deref()),
+            (None, Some("timed out after 10s"))
+        );
+    }
+}
@@ -0,0 +1,368 @@
+//! The process list and its folded-stack rendering (`processes`).
+//!
+//! `processes --folded` prints one line per process path in the process
+//! tree, in the folded format of Brendan Gregg's `flamegraph.pl` and of
+//! `inferno-flamegraph`:
+//!
+//! ```text
+//! root;systemd;nginx;nginx 123456789
+//! ```
+//!
+//! The weight is the process's own resident memory in bytes, or its CPU
+//! usage in hundredths of a percent with `--metric cpu`, so a flame graph
+//! shows where memory or CPU time sits in the process hierarchy.
+//!
+//! `--process-states` counts the processes in each scheduler state for the
+//! snapshot, where a growing number of zombies points at a parent that does
+//! not reap its children.
+
+#[cfg(feature = "processes")]
+use serde::Serialize;
+use std::collections::BTreeMap;
+#[cfg(feature = "processes")]
+use std::collections::{HashMap, HashSet};
+#[cfg(feature = "processes")]
+use std::time::Duration;
+#[cfg(feature = "processes")]
+use sysinfo::PidExt;
+use sysinfo::{ProcessExt, ProcessRefreshKind, ProcessStatus, System, SystemExt};
+
+/// Frame every stack starts with.
+#[cfg(feature = "processes")]
+const ROOT_FRAME: &str = "root";
+
+/// Frame kernel threads are grouped under, below the root.
+#[cfg(feature = "processes")]
+const KERNEL_FRAME: &str = "[kernel]";
+
+/// A running process.
+#[cfg(feature = "processes")]
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Process {
+    pub pid: u32,
+    /// Parent process ID, if the process has one
+    pub parent: Option<u32>,
+    pub name: String,
+    /// Resident memory in bytes
+    pub rss: u64,
+    /// CPU usage in percent of one core over the sampling interval
+    pub cpu_percent: f32,
+    /// Whether this is a kernel thread rather than a user process
+    pub kernel_thread: bool,
+}
+
+/// Weight of each process in folded output.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Metric {
+    /// Resident memory in bytes (the default)
+    #[default]
+    Rss,
+    /// CPU usage in hundredths of a percent
+    Cpu,
+}
+
+impl Metric {
+    /// Parses a `--metric` value.
+    pub fn parse(text: &str) -> Option<Metric> {
+        match text {
+            "rss" => Some(Metric::Rss),
+            "cpu" => Some(Metric::Cpu),
+            _ => None,
+        }
+    }
+
+    /// Returns the weight of `process`.
+    #[cfg(feature = "processes")]
+    fn weight(self, process: &Process) -> u64 {
+        match self {
+            Metric::Rss => process.rss,
+            Metric::Cpu => (f64::from(process.cpu_percent) * 100.0).round() as u64,
+        }
+    }
+}
+
+/// Lists the running processes, sorted by PID.
+///
+/// CPU usage is measured over `interval`, blocking for its duration.
+/// Kernel threads are recognized as `kthreadd` and its children (Linux).
+#[cfg(feature = "processes")]
+pub fn list(interval: Duration) -> Vec<Process> {
+    let mut sys = System::new();
+    sys.refresh_processes();
+    std::thread::sleep(interval.max(System::MINIMUM_CPU_UPDATE_INTERVAL));
+    sys.refresh_processes();
+
+    let kthreadd = sys
+        .processes()
+        .values()
+        .find(|process| process.name() == "kthreadd")
+        .map(|process| process.pid().as_u32());
+    let mut processes: Vec<Process> = sys
+        .processes()
+        .values()
+        .map(|process| {
+            let pid = process.pid().as_u32();
+            let parent = process.parent().map(|parent| parent.as_u32());
+            Process {
+                pid,
+                parent,
+                name: process.name().to_string(),
+                rss: process.memory(),
+                cpu_percent: process.cpu_usage(),
+                kernel_thread: kthreadd.is_some() && (Some(pid) == kthreadd || parent == kthreadd),
+            }
+        })
+        .collect();
+    processes.sort_by_key(|process| process.pid);
+    processes
+}
+
+/// Renders `processes` as folded stacks weighted by `metric`.
+///
+/// Every process contributes its own weight to the stack of names from the
+/// root down to it; identical stacks are merged and processes with no
+/// weight are left out. A process whose parent is not in the list, e.g.
+/// because the parent exited, hangs directly below the root, as the init
+/// process adopts orphans. Kernel threads hang below a `[kernel]` frame.
+#[cfg(feature = "processes")]
+pub fn folded(processes: &[Process], metric: Metric) -> String {
+    let by_pid: HashMap<u32, &Process> = processes
+        .iter()
+        .map(|process| (process.pid, process))
+        .collect();
+
+    let mut stacks: BTreeMap<String, u64> = BTreeMap::new();
+    for process in processes {
+        let weight = metric.weight(process);
+        if weight == 0 {
+            continue;
+        }
+        let mut frames = vec![frame(&process.name)];
+        if process.kernel_thread {
+            frames.push(KERNEL_FRAME.to_string());
+        } else {
+            // PID reuse can make a parent look like its own descendant
+            let mut seen = HashSet::from([process.pid]);
+            let mut parent = process.parent;
+            while let Some(ancestor) = parent.and_then(|pid| by_pid.get(&pid)) {
+                if ancestor.kernel_thread || !seen.insert(ancestor.pid) {
+                    break;
+                }
+                frames.push(frame(&ancestor.name));
+                parent = ancestor.parent;
+            }
+        }
+        frames.push(ROOT_FRAME.to_string());
+        frames.reverse();
+        *stacks.entry(frames.join(";")).or_default() += weight;
+    }
+
+    stacks
+        .into_iter()
+        .map(|(stack, weight)| format!("{} {}\n", stack, weight))
+        .collect()
+}
+
+/// Makes a process name usable as a frame: semicolons separate frames and
+/// whitespace separates the weight, so both become underscores.
+#[cfg(feature = "processes")]
+fn frame(name: &str) -> String {
+    if name.is_empty() {
+        return "[unknown]".to_string();
+    }
+    name.chars()
+        .map(|c| {
+            if c == ';' || c.is_whitespace() {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+/// Counts the processes in each state, by the names of `state_name`.
+///
+/// Returns `None` on Windows, where every process is reported as running,
+/// and where no processes can be listed.
+#[cfg(not(windows))]
+pub fn count_states() -> Option<BTreeMap<String, usize>> {
+    let mut sys = System::new();
+    // The state is read with the process itself, no extra data is needed
+    sys.refresh_processes_specifics(ProcessRefreshKind::new());
+    let mut states: BTreeMap<String, usize> = BTreeMap::new();
+    for process in sys.processes().values() {
+        *states
+            .entry(state_name(process.status()).to_string())
+            .or_default() += 1;
+    }
+    (!states.is_empty()).then_some(states)
+}
+
+/// Counts the processes in each state.
+///
+/// Windows reports every process as running, so this always returns `None`.
+#[cfg(windows)]
+pub fn count_states() -> Option<BTreeMap<String, usize>> {
+    None
+}
+
+/// Returns the name a process state is counted under.
+#[cfg_attr(windows, allow(dead_code))]
+fn state_name(status: ProcessStatus) -> &'static str {
+    match status {
+        ProcessStatus::Run => "running",
+        ProcessStatus::Sleep => "sleeping",
+        ProcessStatus::UninterruptibleDiskSleep => "disk_sleep",
+        ProcessStatus::Idle => "idle",
+        ProcessStatus::Stop => "stopped",
+        ProcessStatus::Tracing => "traced",
+        ProcessStatus::Zombie => "zombie",
+        ProcessStatus::Dead => "dead",
+        _ => "other",
+    }
+}
+
+/// Orders state counts the way the console lists them, the most common
+/// state first, e.g. "430 sleeping, 3 running, 1 zombie".
+pub fn by_count(states: &BTreeMap<String, usize>) -> Vec<(&str, usize)> {
+    let mut states: Vec<(&str, usize)> = states
+        .iter()
+        .map(|(state, count)| (state.as_str(), *count))
+        .collect();
+    // Ties keep the alphabetical order of the map
+    states.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+    states
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "processes")]
+    #[test]
+    fn test_folded() {
+        let process = |pid, parent, name: &str, rss| Process {
+            pid,
+            parent,
+            name: name.to_string(),
+            rss,
+            cpu_percent: 0.0,
+            kernel_thread: false,
+        };
+        let mut kworker = process(40, Some(2), "kworker/0:1", 0);
+        kworker.cpu_percent = 1.5;
+        kworker.kernel_thread = true;
+        let processes = [
+            process(1, None, "systemd", 12_000),
+            process(10, Some(1), "nginx", 5_000),
+            process(11, Some(10), "nginx", 30_000),
+            process(12, Some(10), "nginx", 20_000),
+            // the parent exited
+            process(20, Some(999), "backup job", 7_000),
+            process(21, Some(1), "a;b", 1),
+            kworker,
+        ];
+        assert_eq!(
+            folded(&processes, Metric::Rss),
+            "root;backup_job 7000\n\
+     root;systemd 12000\n\
+     root;systemd;a_b 1\n\
+     root;systemd;nginx 5000\n\
+     root;systemd;nginx;nginx 50000\n"
+        );
+        assert_eq!(
+            folded(&processes, Metric::Cpu),
+            "root;[kernel];kworker/0:1 150\n"
+        );
+    }
+
+    #[cfg(feature = "processes")]
+    #[test]
+    fn test_folded_reparenting() {
+        let process = |pid, parent, name: &str, kernel_thread| Process {
+            pid,
+            parent,
+            name: name.to_string(),
+            rss: 100,
+            cpu_percent: 0.0,
+            kernel_thread,
+        };
+        let processes = [
+            // PIDs reused so that each looks like the other's parent
+            process(30, Some(31), "a", false),
+            process(31, Some(30), "b", false),
+            // a user process started by a kernel thread, e.g. a usermode helper
+            process(2, None, "kthreadd", true),
+            process(50, Some(2), "modprobe", false),
+            process(60, Some(1), "", false),
+        ];
+        assert_eq!(
+            folded(&processes, Metric::Rss),
+            "root;[kernel];kthreadd 100\n\
+     root;[unknown] 100\n\
+     root;a;b 100\n\
+     root;b;a 100\n\
+     root;modprobe 100\n"
+        );
+        assert_eq!(folded(&[], Metric::Rss), "");
+    }
+
+    #[cfg(feature = "processes")]
+    #[test]
+    fn test_metric() {
+        assert_eq!(Metric::parse("rss"), Some(Metric::Rss));
+        assert_eq!(Metric::parse("cpu"), Some(Metric::Cpu));
+        assert_eq!(Metric::parse("RSS"), None);
+        let mut process = Process {
+            pid: 1,
+            parent: None,
+            name: "init".to_string(),
+            rss: 4_096,
+            cpu_percent: 12.345,
+            kernel_thread: false,
+        };
+        assert_eq!(Metric::Rss.weight(&process), 4_096);
+        assert_eq!(Metric::Cpu.weight(&process), 1_235);
+        process.cpu_percent = 0.004;
+        assert_eq!(Metric::Cpu.weight(&process), 0);
+    }
+
+    #[cfg(feature = "processes")]
+    #[test]
+    fn test_frame() {
+        assert_eq!(frame("Web Content"), "Web_Content");
+        assert_eq!(frame("a;b\tc"), "a_b_c");
+        assert_eq!(frame(""), "[unknown]");
+    }
+
+    #[test]
+    fn test_state_name() {
+        assert_eq!(state_name(ProcessStatus::Run), "running");
+        assert_eq!(
+            state_name(ProcessStatus::UninterruptibleDiskSleep),
+            "disk_sleep"
+        );
+        assert_eq!(state_name(ProcessStatus::Zombie), "zombie");
+        assert_eq!(state_name(ProcessStatus::Unknown(0)), "other");
+    }
+
+    #[test]
+    fn test_by_count() {
+        let states = BTreeMap::from([
+            ("running".to_string(), 3),
+            ("sleeping".to_string(), 430),
+            ("zombie".to_string(), 1),
+            ("stopped".to_string(), 1),
+        ]);
+        assert_eq!(
+            by_count(&states),
+            [
+                ("sleeping", 430),
+                ("running", 3),
+                ("stopped", 1),
+                ("zombie", 1)
+            ]
+        );
+    }
+}
@@ -0,0 +1,333 @@
+//! The source of the core system metrics.
+//!
+//! Collection reads the OS, memory, disk, and network figures through the
+//! `SystemProvider` trait rather than from sysinfo directly, so the
+//! conversion into `SystemInfo` and everything downstream of it can be fed
+//! scripted data. The real implementation is sysinfo's `System`.
+
+use crate::thermal::ComponentTemperature;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use sysinfo::{ComponentExt, DiskExt, NetworkExt, NetworksExt, RefreshKind, System, SystemExt};
+
+/// One disk as reported by a provider.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiskData {
+    /// Device the disk is mounted from, e.g. "/dev/sda1"
+    pub device: OsString,
+    /// Mount point, exactly as the OS reports it
+    pub mount_point: OsString,
+    /// File system name as raw bytes
+    pub file_system: Vec<u8>,
+    /// Total size in bytes
+    pub total_space: u64,
+    /// Available space in bytes
+    pub available_space: u64,
+}
+
+/// One network interface as reported by a provider.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetworkData {
+    /// Interface name, exactly as the OS reports it
+    pub name: OsString,
+    /// Bytes received since boot
+    pub bytes_received: u64,
+    /// Bytes transmitted since boot
+    pub bytes_transmitted: u64,
+    /// Packets received since boot
+    pub packets_received: u64,
+    /// Packets transmitted since boot
+    pub packets_transmitted: u64,
+    /// Receive errors since boot
+    pub errors_received: u64,
+    /// Transmit errors since boot
+    pub errors_transmitted: u64,
+}
+
+/// How a provider refreshes its data, recorded with `--debug-meta`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RefreshInfo {
+    /// Whether some kinds of data were left out instead of refreshing
+    /// everything
+    pub selective: bool,
+    /// Each sysinfo `RefreshKind` flag and whether it was set, e.g.
+    /// "memory" or "cpu.frequency"
+    pub refresh_kind: BTreeMap<String, bool>,
+    /// The sysinfo refresh calls those flags make, in order
+    pub calls: Vec<String>,
+}
+
+impl RefreshInfo {
+    /// Describes a `refresh_specifics` call with `kind`.
+    pub fn new(kind: RefreshKind) -> RefreshInfo {
+        let cpu = kind.cpu();
+        let processes = kind.processes();
+        let refresh_kind = [
+            ("memory", kind.memory()),
+            ("cpu", cpu.is_some()),
+            ("cpu.cpu_usage", cpu.is_some_and(|cpu| cpu.cpu_usage())),
+            ("cpu.frequency", cpu.is_some_and(|cpu| cpu.frequency())),
+            ("components", kind.components()),
+            ("components_list", kind.components_list()),
+            ("networks", kind.networks()),
+            ("networks_list", kind.networks_list()),
+            ("processes", processes.is_some()),
+            ("processes.cpu", processes.is_some_and(|p| p.cpu())),
+            (
+                "processes.disk_usage",
+                processes.is_some_and(|p| p.disk_usage()),
+            ),
+            ("processes.user", processes.is_some_and(|p| p.user())),
+            ("disks", kind.disks()),
+            ("disks_list", kind.disks_list()),
+            ("users_list", kind.users_list()),
+        ]
+        .into_iter()
+        .map(|(flag, set)| (flag.to_string(), set))
+        .collect();
+
+        // The calls `SystemExt::refresh_specifics` makes; a list refresh
+        // also refreshes the items in it
+        let calls = [
+            kind.memory().then_some("refresh_memory"),
+            cpu.map(|_| "refresh_cpu_specifics"),
+            if kind.components_list() {
+                Some("refresh_components_list")
+            } else {
+                kind.components().then_some("refresh_components")
+            },
+            if kind.networks_list() {
+                Some("refresh_networks_list")
+            } else {
+                kind.networks().then_some("refresh_networks")
+            },
+            processes.map(|_| "refresh_processes_specifics"),
+            if kind.disks_list() {
+                Some("refresh_disks_list")
+            } else {
+                kind.disks().then_some("refresh_disks")
+            },
+            kind.users_list().then_some("refresh_users_list"),
+        ]
+        .into_iter()
+        .flatten()
+        .map(str::to_string)
+        .collect();
+
+        RefreshInfo {
+            selective: kind != RefreshKind::everything(),
+            refresh_kind,
+            calls,
+        }
+    }
+}
+
+/// The system queries collection depends on.
+///
+/// Values are read from the state captured by the last `refresh`. Queries
+/// return `None` where the platform does not report a value.
+pub trait SystemProvider {
+    /// Re-reads all values, including the lists of disks and interfaces.
+    fn refresh(&mut self);
+    /// Operating system name, e.g. "Debian GNU/Linux"
+    fn os_name(&self) -> Option<String>;
+    /// Operating system version, e.g. "12"
+    fn os_version(&self) -> Option<String>;
+    /// Host name of the machine
+    fn host_name(&self) -> Option<String>;
+    /// Number of physical CPU cores
+    fn physical_core_count(&self) -> Option<usize>;
+    /// Total memory in bytes
+    fn total_memory(&self) -> u64;
+    /// Used memory in bytes
+    fn used_memory(&self) -> u64;
+    /// Total swap in bytes
+    fn total_swap(&self) -> u64;
+    /// Used swap in bytes
+    fn used_swap(&self) -> u64;
+    /// Mounted disks
+    fn disks(&self) -> Vec<DiskData>;
+    /// Network interfaces
+    fn networks(&self) -> Vec<NetworkData>;
+    /// Temperature sensors
+    fn components(&self) -> Vec<ComponentTemperature>;
+    /// Boot time in seconds since the Unix epoch
+    #[cfg(feature = "history")]
+    fn boot_time(&self) -> u64;
+    /// How `refresh` reads the data; `None` for providers not backed by
+    /// sysinfo
+    fn refresh_info(&self) -> Option<RefreshInfo> {
+        None
+    }
+}
+
+/// The data `System::refresh` reads.
+fn refresh_kind() -> RefreshKind {
+    let kind = RefreshKind::everything();
+    // Temperature sensors are only read with the `sensors` feature
+    if cfg!(feature = "sensors") {
+        kind
+    } else {
+        kind.without_components().without_components_list()
+    }
+}
+
+impl SystemProvider for System {
+    fn refresh(&mut self) {
+        self.refresh_specifics(refresh_kind());
+    }
+
+    fn os_name(&self) -> Option<String> {
+        SystemExt::name(self)
+    }
+
+    fn os_version(&self) -> Option<String> {
+        SystemExt::os_version(self)
+    }
+
+    fn host_name(&self) -> Option<String> {
+        SystemExt::host_name(self)
+    }
+
+    fn physical_core_count(&self) -> Option<usize> {
+        SystemExt::physical_core_count(self)
+    }
+
+    fn total_memory(&self) -> u64 {
+        SystemExt::total_memory(self)
+    }
+
+    fn used_memory(&self) -> u64 {
+        SystemExt::used_memory(self)
+    }
+
+    fn total_swap(&self) -> u64 {
+        SystemExt::total_swap(self)
+    }
+
+    fn used_swap(&self) -> u64 {
+        SystemExt::used_swap(self)
+    }
+
+    fn disks(&self) -> Vec<DiskData> {
+        SystemExt::disks(self)
+            .iter()
+            .map(|disk| DiskData {
+                device: disk.name().to_os_string(),
+                mount_point: disk.mount_point().as_os_str().to_os_string(),
+                file_system: disk.file_system().to_vec(),
+                total_space: disk.total_space(),
+                available_space: disk.available_space(),
+            })
+            .collect()
+    }
+
+    fn networks(&self) -> Vec<NetworkData> {
+        // sysinfo converts interface names lossily; recover the exact name
+        // where the OS lists one that converts to it
+        let raw_names = raw_interface_names();
+        SystemExt::networks(self)
+            .iter()
+            .map(|(name, network)| NetworkData {
+                name: raw_name(name, &raw_names),
+                bytes_received: network.total_received(),
+                bytes_transmitted: network.total_transmitted(),
+                packets_received: network.total_packets_received(),
+                packets_transmitted: network.total_packets_transmitted(),
+                errors_received: network.total_errors_on_received(),
+                errors_transmitted: network.total_errors_on_transmitted(),
+            })
+            .collect()
+    }
+
+    fn components(&self) -> Vec<ComponentTemperature> {
+        SystemExt::components(self)
+            .iter()
+            .map(|component| ComponentTemperature {
+                label: component.label().to_string(),
+                celsius: component.temperature(),
+            })
+            .collect()
+    }
+
+    #[cfg(feature = "history")]
+    fn boot_time(&self) -> u64 {
+        SystemExt::boot_time(self)
+    }
+
+    fn refresh_info(&self) -> Option<RefreshInfo> {
+        Some(RefreshInfo::new(refresh_kind()))
+    }
+}
+
+/// Lists the interface names the OS reports, exactly as it reports them.
+#[cfg(target_os = "linux")]
+fn raw_interface_names() -> Vec<OsString> {
+    std::fs::read_dir("/sys/class/net")
+        .map(|entries| entries.flatten().map(|entry| entry.file_name()).collect())
+        .unwrap_or_default()
+}
+
+/// Lists the interface names the OS reports.
+///
+/// sysinfo's names are exact on this platform, so none are needed.
+#[cfg(not(target_os = "linux"))]
+fn raw_interface_names() -> Vec<OsString> {
+    Vec::new()
+}
+
+/// Returns the name in `raw_names` that converts to `name`, or `name`
+/// itself if there is none.
+fn raw_name(name: &str, raw_names: &[OsString]) -> OsString {
+    raw_names
+        .iter()
+        .find(|raw| raw.to_string_lossy() == name)
+        .cloned()
+        .unwrap_or_else(|| name.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(unix)]
+    fn test_raw_name() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let raw_names = [
+            OsString::from("eth0"),
+            std::ffi::OsStr::from_bytes(b"wlan\xff").to_os_string(),
+        ];
+        assert_eq!(raw_name("wlan\u{FFFD}", &raw_names), raw_names[1]);
+        assert_eq!(raw_name("eth0", &raw_names), raw_names[0]);
+        assert_eq!(raw_name("tun0", &raw_names), OsString::from("tun0"));
+    }
+
+    #[test]
+    fn test_refresh_info_new() {
+        let info = RefreshInfo::new(RefreshKind::new().with_memory().with_disks_list());
+        assert!(info.selective);
+        assert_eq!(info.calls, ["refresh_memory", "refresh_disks_list"]);
+        assert!(info.refresh_kind["memory"]);
+        assert!(!info.refresh_kind["cpu"]);
+        assert_eq!(info.refresh_kind.len(), 15);
+
+        let info = RefreshInfo::new(RefreshKind::everything());
+        assert!(!info.selective);
+        assert_eq!(
+            info.calls,
+            [
+                "refresh_memory",
+                "refresh_cpu_specifics",
+                "refresh_components_list",
+                "refresh_networks_list",
+                "refresh_processes_specifics",
+                "refresh_disks_list",
+                "refresh_users_list",
+            ]
+        );
+        assert!(info.refresh_kind.values().all(|&set| set));
+    }
+}
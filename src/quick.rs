@@ -0,0 +1,225 @@
+//! The `quick` subcommand: one metric, printed as a bare value.
+//!
+//! Each metric refreshes only the sysinfo data it reads, so a query costs a
+//! fraction of a full collection. Values are printed without units (bytes,
+//! percent, seconds) for shell scripts; `--human` formats them for people.
+//! Adding a metric means adding an entry to `METRICS`.
+
+use crate::locale::Locale;
+use crate::AppError;
+use sysinfo::{DiskExt, System, SystemExt};
+
+/// A metric value, which determines how it is printed.
+#[derive(Debug, PartialEq)]
+enum Value {
+    Bytes(u64),
+    Percent(f64),
+    Count(u64),
+    Seconds(u64),
+    Number(f64),
+}
+
+impl Value {
+    /// Formats the value bare, or with units when `human` is set.
+    fn format(&self, human: bool) -> String {
+        match (self, human) {
+            (Value::Bytes(bytes), false) => bytes.to_string(),
+            (Value::Bytes(bytes), true) => crate::format_bytes(*bytes, 0, Locale::default()),
+            (Value::Percent(percent), false) => format!("{:.1}", percent),
+            (Value::Percent(percent), true) => crate::format_percent(*percent, Locale::default()),
+            (Value::Count(count), false) => count.to_string(),
+            (Value::Count(count), true) => crate::format_count(*count, ","),
+            (Value::Seconds(seconds), false) => seconds.to_string(),
+            (Value::Seconds(seconds), true) => format!(
+                "{}d {}h {}m",
+                seconds / 86_400,
+                seconds % 86_400 / 3600,
+                seconds % 3600 / 60
+            ),
+            (Value::Number(number), _) => format!("{:.2}", number),
+        }
+    }
+}
+
+/// A metric that can be queried by name.
+struct Metric {
+    name: &'static str,
+    /// Placeholder for the value after `name:`, for metrics that take one
+    argument: Option<&'static str>,
+    /// Refreshes the data `read` needs
+    refresh: fn(&mut System),
+    /// Reads the value, given the argument (empty if none), or explains why
+    /// it is unavailable
+    read: fn(&System, &str) -> Result<Value, String>,
+}
+
+/// Every metric, in the order they are listed.
+const METRICS: &[Metric] = &[
+    Metric {
+        name: "mem-used-pct",
+        argument: None,
+        refresh: System::refresh_memory,
+        read: |sys, _| {
+            if sys.total_memory() == 0 {
+                return Err("total memory is unknown".to_string());
+            }
+            Ok(Value::Percent(
+                sys.used_memory() as f64 / sys.total_memory() as f64 * 100.0,
+            ))
+        },
+    },
+    Metric {
+        name: "mem-total",
+        argument: None,
+        refresh: System::refresh_memory,
+        read: |sys, _| Ok(Value::Bytes(sys.total_memory())),
+    },
+    Metric {
+        name: "mem-available",
+        argument: None,
+        refresh: System::refresh_memory,
+        read: |sys, _| Ok(Value::Bytes(sys.available_memory())),
+    },
+    Metric {
+        name: "swap-used",
+        argument: None,
+        refresh: System::refresh_memory,
+        read: |sys, _| Ok(Value::Bytes(sys.used_swap())),
+    },
+    Metric {
+        name: "disk-free",
+        argument: Some("<mount>"),
+        refresh: System::refresh_disks_list,
+        read: |sys, mount| {
+            sys.disks()
+                .iter()
+                .find(|disk| disk.mount_point().as_os_str() == mount)
+                .map(|disk| Value::Bytes(disk.available_space()))
+                .ok_or_else(|| format!("no disk is mounted at {}", mount))
+        },
+    },
+    Metric {
+        name: "cpu-count",
+        argument: None,
+        refresh: |_| {},
+        read: |sys, _| {
+            sys.physical_core_count()
+                .map(|count| Value::Count(count as u64))
+                .ok_or_else(|| "the core count is unknown".to_string())
+        },
+    },
+    Metric {
+        name: "uptime-seconds",
+        argument: None,
+        refresh: |_| {},
+        read: |sys, _| Ok(Value::Seconds(sys.uptime())),
+    },
+    Metric {
+        name: "load1",
+        argument: None,
+        refresh: |_| {},
+        read: |sys, _| {
+            if cfg!(windows) {
+                return Err("Windows has no load average".to_string());
+            }
+            Ok(Value::Number(sys.load_average().one))
+        },
+    },
+];
+
+/// Returns the names of all metrics, e.g. "disk-free:<mount>".
+pub fn metric_names() -> Vec<String> {
+    METRICS
+        .iter()
+        .map(|metric| match metric.argument {
+            Some(argument) => format!("{}:{}", metric.name, argument),
+            None => metric.name.to_string(),
+        })
+        .collect()
+}
+
+/// Queries the metric `query`, e.g. "mem-used-pct" or "disk-free:/data".
+///
+/// # Returns
+///
+/// The formatted value.
+///
+/// # Errors
+///
+/// Returns `AppError::UnknownMetric` if no metric matches `query`, and
+/// `AppError::MetricUnavailable` if the value cannot be read.
+pub fn query(query: &str, human: bool) -> Result<String, AppError> {
+    let (name, argument) = match query.split_once(':') {
+        Some((name, argument)) => (name, Some(argument)),
+        None => (query, None),
+    };
+    let metric = METRICS
+        .iter()
+        .find(|metric| {
+            metric.name == name
+                && metric.argument.is_some() == argument.is_some_and(|arg| !arg.is_empty())
+        })
+        .ok_or_else(|| AppError::UnknownMetric(query.to_string()))?;
+
+    let mut sys = System::new();
+    (metric.refresh)(&mut sys);
+    let value = (metric.read)(&sys, argument.unwrap_or_default())
+        .map_err(|reason| AppError::MetricUnavailable(format!("{}: {}", query, reason)))?;
+    Ok(value.format(human))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_value_format() {
+        assert_eq!(Value::Bytes(1536).format(false), "1536");
+        assert_eq!(Value::Bytes(1536).format(true), "1.50 KB");
+        assert_eq!(Value::Percent(72.44).format(false), "72.4");
+        assert_eq!(Value::Percent(72.44).format(true), "72.4%");
+        assert_eq!(Value::Seconds(93784).format(true), "1d 2h 3m");
+        assert_eq!(Value::Number(0.5).format(false), "0.50");
+        assert_eq!(Value::Count(1234567).format(false), "1234567");
+        assert_eq!(Value::Count(1234567).format(true), "1,234,567");
+        assert_eq!(Value::Percent(-0.0).format(true), "0.0%");
+    }
+
+    #[test]
+    fn test_metric_names() {
+        let names = metric_names();
+        assert_eq!(names[0], "mem-used-pct");
+        assert!(names.contains(&"disk-free:<mount>".to_string()));
+        let mut unique = names.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), names.len());
+    }
+
+    #[test]
+    fn test_query() {
+        // Every metric without an argument reads a bare number on this host
+        for metric in METRICS.iter().filter(|metric| metric.argument.is_none()) {
+            if cfg!(windows) && metric.name == "load1" {
+                continue;
+            }
+            let value = query(metric.name, false).unwrap();
+            assert!(value.parse::<f64>().is_ok(), "{}: {}", metric.name, value);
+        }
+        assert!(query("mem-total", true).unwrap().ends_with('B'));
+
+        for unknown in ["mem-free", "mem-total:/", "disk-free", "disk-free:", ""] {
+            match query(unknown, false) {
+                Err(AppError::UnknownMetric(name)) => assert_eq!(name, unknown),
+                other => panic!("{}: unexpected result {:?}", unknown, other),
+            }
+        }
+        match query("disk-free:/nonexistent/mount", false) {
+            Err(AppError::MetricUnavailable(message)) => assert_eq!(
+                message,
+                "disk-free:/nonexistent/mount: no disk is mounted at /nonexistent/mount"
+            ),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,45 @@
+//! Helpers for turning monotonically increasing counters into rates.
+
+use std::time::Duration;
+
+/// Returns the increase of a counter between two samples.
+///
+/// Counters can go backwards when they wrap or are reset (for example when an
+/// interface is re-created), so a decrease is clamped to zero rather than
+/// producing a huge bogus delta.
+pub fn counter_delta(previous: u64, current: u64) -> u64 {
+    current.saturating_sub(previous)
+}
+
+/// Returns the per-second rate of a counter sampled `elapsed` apart.
+///
+/// The delta is clamped as in `counter_delta`; a zero interval yields 0.
+pub fn counter_rate(previous: u64, current: u64, elapsed: Duration) -> f64 {
+    let seconds = elapsed.as_secs_f64();
+    if seconds > 0.0 {
+        counter_delta(previous, current) as f64 / seconds
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counter_delta() {
+        assert_eq!(counter_delta(100, 250), 150);
+        assert_eq!(counter_delta(250, 250), 0);
+        // A reset counter yields no increase rather than a wrapped one
+        assert_eq!(counter_delta(250, 10), 0);
+        assert_eq!(counter_delta(0, u64::MAX), u64::MAX);
+    }
+
+    #[test]
+    fn test_counter_rate() {
+        assert_eq!(counter_rate(0, 500, Duration::from_millis(500)), 1000.0);
+        assert_eq!(counter_rate(500, 0, Duration::from_secs(1)), 0.0);
+        assert_eq!(counter_rate(0, 500, Duration::ZERO), 0.0);
+    }
+}
@@ -0,0 +1,258 @@
+//! Redaction of identifying values (`--redact`).
+//!
+//! Redaction is applied to the collected `SystemInfo` before it is displayed
+//! or serialized, so every output (console, JSON, flat, events, bundles) sees
+//! the same redacted values. Values that were not collected stay absent.
+//!
+//! Kernel log lines (`kernel_errors.recent[].message`) can name hosts,
+//! hardware addresses and IPs anywhere in free text, so with any field
+//! selected their messages are replaced whole.
+
+use crate::{AppError, SystemInfo};
+
+/// Replacement text for redacted values.
+pub const REDACTED: &str = "REDACTED";
+
+/// A category of identifying values that can be redacted.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Field {
+    /// The `hostname` field, and the node and pod names of the
+    /// `kubernetes` section, which usually contain a host name
+    Hostname,
+    /// Network addresses: `default_gateway`, every entry of `dns_servers`,
+    /// the `sockets.top_remote_addresses`, and the access point hardware
+    /// address (`bssid`) of wireless interfaces
+    Ip,
+    /// The `machine_id` field
+    MachineId,
+}
+
+impl Field {
+    /// Parses a comma-separated `--redact` list such as `hostname,ip`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::InvalidArgument` for an unknown or empty name.
+    pub fn parse_list(list: &str) -> Result<Vec<Field>, AppError> {
+        list.split(',')
+            .map(|name| match name.trim() {
+                "hostname" => Ok(Field::Hostname),
+                "ip" => Ok(Field::Ip),
+                "machine_id" => Ok(Field::MachineId),
+                other => Err(AppError::InvalidArgument(format!(
+                    "unknown --redact field '{}' (expected hostname, ip or machine_id)",
+                    other
+                ))),
+            })
+            .collect()
+    }
+}
+
+/// Replaces the values selected by `fields` with `REDACTED`.
+pub fn redact(info: &mut SystemInfo, fields: &[Field]) {
+    let hide = |value: &mut Option<String>| {
+        if value.is_some() {
+            *value = Some(REDACTED.to_string());
+        }
+    };
+
+    for field in fields {
+        match field {
+            Field::Hostname => {
+                info.hostname = REDACTED.to_string();
+                if let Some(kubernetes) = &mut info.kubernetes {
+                    hide(&mut kubernetes.node_name);
+                    hide(&mut kubernetes.pod_name);
+                }
+            }
+            Field::Ip => {
+                hide(&mut info.default_gateway);
+                for server in &mut info.dns_servers {
+                    *server = REDACTED.to_string();
+                }
+                // The connection counts stay, as they carry no address
+                if let Some(sockets) = &mut info.sockets {
+                    for remote in &mut sockets.top_remote_addresses {
+                        remote.address = REDACTED.to_string();
+                    }
+                }
+                for network in &mut info.networks {
+                    if let Some(wireless) = &mut network.wireless {
+                        hide(&mut wireless.bssid);
+                    }
+                }
+            }
+            Field::MachineId => hide(&mut info.machine_id),
+        }
+    }
+    if !fields.is_empty() {
+        // The kind and device of each error stay, as they carry no identity
+        if let Some(kernel_errors) = &mut info.kernel_errors {
+            for error in &mut kernel_errors.recent {
+                error.message = REDACTED.to_string();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kmsg::{KernelError, KernelErrors};
+    use crate::kubernetes::KubernetesInfo;
+    use crate::sockets::{RemoteCount, SocketSummary};
+
+    /// A snapshot with a value in every field `redact` covers.
+    fn snapshot() -> SystemInfo {
+        let sys = crate::mock::MockProvider {
+            networks: vec![
+                crate::mock::network("eth0", 1000, 1000),
+                crate::mock::network("wlan0", 1000, 1000),
+            ],
+            ..crate::mock::MockProvider::default()
+        };
+        let mut info = crate::mock::snapshot(&sys);
+        info.hostname = "db-1.example.com".to_string();
+        info.machine_id = Some("3d1219c7a4b94f0e".to_string());
+        info.default_gateway = Some("192.0.2.1".to_string());
+        info.dns_servers = vec!["192.0.2.53".to_string(), "2001:db8::53".to_string()];
+        info.sockets = Some(SocketSummary {
+            udp_sockets: 4,
+            top_remote_addresses: vec![
+                RemoteCount {
+                    address: "198.51.100.7".to_string(),
+                    connections: 12,
+                },
+                RemoteCount {
+                    address: "127.0.0.1".to_string(),
+                    connections: 3,
+                },
+            ],
+            ..SocketSummary::default()
+        });
+        info.networks[1].wireless = Some(crate::wireless::WirelessInfo {
+            ssid: Some("Office".to_string()),
+            bssid: Some("aa:bb:cc:dd:ee:ff".to_string()),
+            ..crate::wireless::WirelessInfo::default()
+        });
+        info.kubernetes = Some(KubernetesInfo {
+            node_name: Some("db-1.example.com".to_string()),
+            pod_name: Some("sysinfo-db-1-x7k2p".to_string()),
+            namespace: Some("monitoring".to_string()),
+            cgroup_driver: Some("systemd".to_string()),
+        });
+        info.kernel_errors = Some(KernelErrors {
+            records_scanned: 2000,
+            total_matches: 1,
+            seconds_since_last_error: Some(12.5),
+            devices: ["sda1".to_string()].into(),
+            recent: vec![KernelError {
+                seconds_since_boot: 4711.2,
+                kind: "buffer_io".to_string(),
+                device: "sda1".to_string(),
+                message: "Buffer I/O error on dev sda1, nfs: server db-1.example.com \
+                          (192.0.2.9) not responding"
+                    .to_string(),
+            }],
+        });
+        info
+    }
+
+    #[test]
+    fn test_parse_list() {
+        assert_eq!(
+            Field::parse_list("hostname, ip,machine_id").unwrap(),
+            [Field::Hostname, Field::Ip, Field::MachineId]
+        );
+        assert!(Field::parse_list("hostname,mac").is_err());
+        assert!(Field::parse_list("").is_err());
+    }
+
+    #[test]
+    fn test_redact_ip() {
+        let mut info = snapshot();
+        redact(&mut info, &[Field::Ip]);
+        assert_eq!(info.default_gateway.as_deref(), Some(REDACTED));
+        assert_eq!(info.dns_servers, [REDACTED, REDACTED]);
+        let sockets = info.sockets.as_ref().unwrap();
+        assert_eq!(
+            sockets.top_remote_addresses,
+            [
+                RemoteCount {
+                    address: REDACTED.to_string(),
+                    connections: 12,
+                },
+                RemoteCount {
+                    address: REDACTED.to_string(),
+                    connections: 3,
+                },
+            ]
+        );
+        let wireless = info.networks[1].wireless.as_ref().unwrap();
+        assert_eq!(wireless.bssid.as_deref(), Some(REDACTED));
+        assert_eq!(wireless.ssid.as_deref(), Some("Office"));
+        assert_eq!(info.hostname, "db-1.example.com");
+
+        // No address survives anywhere in the JSON snapshot
+        let json = serde_json::to_string(&info).unwrap();
+        for address in [
+            "192.0.2",
+            "2001:db8",
+            "198.51.100.7",
+            "127.0.0.1",
+            "aa:bb:cc",
+        ] {
+            assert!(!json.contains(address), "{} in {}", address, json);
+        }
+    }
+
+    #[test]
+    fn test_redact_absent() {
+        // Values that were not collected stay absent
+        let mut info = crate::mock::snapshot(&crate::mock::MockProvider::default());
+        info.default_gateway = None;
+        info.machine_id = None;
+        info.sockets = None;
+        redact(&mut info, &[Field::Ip, Field::MachineId]);
+        assert_eq!(info.default_gateway, None);
+        assert_eq!(info.machine_id, None);
+        assert_eq!(info.sockets, None);
+        info.kubernetes = Some(KubernetesInfo::default());
+        redact(&mut info, &[Field::Hostname]);
+        assert_eq!(info.kubernetes, Some(KubernetesInfo::default()));
+
+        let mut info = snapshot();
+        redact(&mut info, &[Field::Hostname, Field::MachineId]);
+        assert_eq!(info.hostname, REDACTED);
+        let kubernetes = info.kubernetes.as_ref().unwrap();
+        assert_eq!(kubernetes.node_name.as_deref(), Some(REDACTED));
+        assert_eq!(kubernetes.pod_name.as_deref(), Some(REDACTED));
+        assert_eq!(kubernetes.namespace.as_deref(), Some("monitoring"));
+        assert!(!serde_json::to_string(&info).unwrap().contains("db-1"));
+        assert_eq!(info.machine_id.as_deref(), Some(REDACTED));
+        assert_eq!(info.default_gateway.as_deref(), Some("192.0.2.1"));
+    }
+
+    #[test]
+    fn test_redact_kernel_errors() {
+        for field in [Field::Hostname, Field::Ip, Field::MachineId] {
+            let mut info = snapshot();
+            redact(&mut info, &[field]);
+            let kernel_errors = info.kernel_errors.as_ref().unwrap();
+            let error = &kernel_errors.recent[0];
+            assert_eq!(error.message, REDACTED);
+            assert_eq!(
+                (error.kind.as_str(), error.device.as_str()),
+                ("buffer_io", "sda1")
+            );
+            assert_eq!(kernel_errors.total_matches, 1);
+        }
+
+        // Without --redact the lines are kept
+        let mut info = snapshot();
+        redact(&mut info, &[]);
+        assert!(info.kernel_errors.unwrap().recent[0]
+            .message
+            .contains("db-1.example.com"));
+    }
+}
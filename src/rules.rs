@@ -0,0 +1,234 @@
+//! Named alert rules from a file (`--compare-thresholds <FILE>`).
+//!
+//! The file holds a JSON list of rules, each comparing one snapshot field
+//! with a value:
+//!
+//! ```text
+//! {"rules": [
+//!   {"name": "low-var", "field": "disks[\"/var\"].available_space", "op": "<", "value": "5GB", "severity": "crit"},
+//!   {"name": "swapping", "field": "used_swap", "op": ">", "value": 0, "severity": "warn"}
+//! ]}
+//! ```
+//!
+//! A rule is a `--check-expr` comparison with a name: fields use the same
+//! references, string values that read as sizes such as `"5GB"` are
+//! numbers, and a field that is missing or null skips the rule with a
+//! warning. Rules that match are recorded in the snapshot's `alerts`.
+
+use crate::expr::{self, Check, Severity};
+use crate::AppError;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::io;
+use std::path::Path;
+
+/// A rule as written in the file.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleSpec {
+    name: String,
+    field: String,
+    op: String,
+    value: Value,
+    severity: Severity,
+}
+
+/// The rules file.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RulesFile {
+    rules: Vec<RuleSpec>,
+}
+
+/// A parsed rule.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+    pub name: String,
+    check: Check,
+}
+
+/// A rule that matched a snapshot.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Alert {
+    /// Name of the rule
+    pub rule: String,
+    pub severity: Severity,
+    /// The comparison that held, e.g. `used_swap > 0`
+    pub condition: String,
+}
+
+impl Rule {
+    /// Builds the rule `name`, comparing `field` with `value` using `op`.
+    ///
+    /// # Errors
+    ///
+    /// Returns why the rule is invalid: an unknown operator, a value that is
+    /// not a number, string or boolean, or a malformed field reference.
+    pub fn new(
+        name: &str,
+        field: &str,
+        op: &str,
+        value: &Value,
+        severity: Severity,
+    ) -> Result<Rule, String> {
+        if !expr::OPERATORS.contains(&op) {
+            return Err(format!(
+                "unknown operator '{}' (expected one of {})",
+                op,
+                expr::OPERATORS.join(", ")
+            ));
+        }
+        let literal = match value {
+            Value::Number(number) => number.to_string(),
+            Value::Bool(value) => value.to_string(),
+            // Sizes such as "5GB" compare as numbers; "22.04" stays a string
+            Value::String(text)
+                if !text.ends_with(|c: char| c.is_ascii_digit())
+                    && expr::parse_number(text).is_some() =>
+            {
+                text.clone()
+            }
+            Value::String(text) => {
+                format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+            }
+            _ => return Err("value must be a number, a string or true/false".to_string()),
+        };
+        let prefix = match severity {
+            Severity::Warning => "warn:",
+            Severity::Critical => "crit:",
+        };
+        let condition = format!("{} {} {}", field.trim(), op, literal);
+        let check = Check::parse(&format!("{} {}", prefix, condition))
+            .map_err(|e| format!("'{}': {}", condition, e))?;
+        Ok(Rule {
+            name: name.to_string(),
+            check,
+        })
+    }
+
+    /// Evaluates the rule against a snapshot serialized with snake_case
+    /// keys, as `Check::evaluate` does.
+    pub fn evaluate(&self, info: &Value) -> Result<bool, String> {
+        self.check.evaluate(info)
+    }
+}
+
+/// Loads the rules in `path`.
+///
+/// # Errors
+///
+/// Returns `AppError::FileRead` if the file cannot be read or is not a
+/// rules file, and `AppError::InvalidArgument` for an invalid or duplicate
+/// rule.
+pub fn load(path: &Path) -> Result<Vec<Rule>, AppError> {
+    let text = std::fs::read_to_string(path).map_err(AppError::FileRead)?;
+    let file: RulesFile = serde_json::from_str(&text).map_err(|e| {
+        AppError::FileRead(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} is not a rules file: {}", path.display(), e),
+        ))
+    })?;
+    let mut names = HashSet::new();
+    file.rules
+        .iter()
+        .map(|spec| {
+            if !names.insert(spec.name.as_str()) {
+                return Err(format!("duplicate rule name '{}'", spec.name));
+            }
+            Rule::new(
+                &spec.name,
+                &spec.field,
+                &spec.op,
+                &spec.value,
+                spec.severity,
+            )
+            .map_err(|e| format!("rule '{}': {}", spec.name, e))
+        })
+        .collect::<Result<Vec<Rule>, String>>()
+        .map_err(|e| AppError::InvalidArgument(format!("{}: {}", path.display(), e)))
+}
+
+/// Evaluates `rules` against the snapshot `info`, returning those that
+/// matched.
+///
+/// Each match is reported on stderr; rules whose result is unknown are
+/// skipped with a warning.
+pub fn evaluate(rules: &[Rule], info: &Value) -> Vec<Alert> {
+    let mut alerts = Vec::new();
+    for rule in rules {
+        match rule.evaluate(info) {
+            Ok(false) => {}
+            Ok(true) => {
+                let label = match rule.check.severity {
+                    Severity::Warning => "Warning",
+                    Severity::Critical => "Critical",
+                };
+                eprintln!(
+                    "{}: rule '{}' matched: {}",
+                    label, rule.name, rule.check.source
+                );
+                alerts.push(Alert {
+                    rule: rule.name.clone(),
+                    severity: rule.check.severity,
+                    condition: rule.check.source.clone(),
+                });
+            }
+            Err(reason) => eprintln!("Warning: skipping rule '{}': {}", rule.name, reason),
+        }
+    }
+    alerts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rule_new() {
+        let info = serde_json::json!({
+            "used_swap": 1024,
+            "os_name": "Ubuntu \"Noble\"",
+            "disks": [{"name": "/var", "available_space": 2147483648u64}],
+        });
+        let rule = |field, op, value| Rule::new("r", field, op, &value, Severity::Warning);
+
+        let low_var = rule(
+            "disks[\"/var\"].available_space",
+            "<",
+            serde_json::json!("5GB"),
+        )
+        .unwrap();
+        assert_eq!(low_var.evaluate(&info), Ok(true));
+        assert_eq!(
+            rule("used_swap", ">", serde_json::json!(0))
+                .unwrap()
+                .evaluate(&info),
+            Ok(true)
+        );
+        assert_eq!(
+            rule("used_swap", "<=", serde_json::json!(0.5))
+                .unwrap()
+                .evaluate(&info),
+            Ok(false)
+        );
+        let os = rule("os_name", "==", serde_json::json!("Ubuntu \"Noble\"")).unwrap();
+        assert_eq!(os.evaluate(&info), Ok(true));
+        assert_eq!(
+            rule("thermal.max_temp_seen", ">", serde_json::json!(90))
+                .unwrap()
+                .evaluate(&info),
+            Err("thermal does not exist".to_string())
+        );
+
+        assert_eq!(
+            rule("used_swap", "=>", serde_json::json!(0)).unwrap_err(),
+            "unknown operator '=>' (expected one of <=, >=, ==, !=, <, >)"
+        );
+        assert_eq!(
+            rule("used_swap", ">", serde_json::json!([1])).unwrap_err(),
+            "value must be a number, a string or true/false"
+        );
+        assert!(rule("used swap", ">", serde_json::json!(0)).is_err());
+    }
+}
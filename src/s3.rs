@@ -0,0 +1,364 @@
+//! Uploading snapshots to S3-compatible object storage (`--s3-url`).
+//!
+//! Requests are signed with AWS Signature Version 4 by the system `curl`
+//! binary (7.75 or later), like the other network code, so no TLS or AWS
+//! dependencies are needed. Credentials and the region are looked up the way
+//! the AWS CLI does: `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and
+//! `AWS_SESSION_TOKEN` first, then the `AWS_PROFILE` (or `default`) profile
+//! in `~/.aws/credentials`; the region comes from `AWS_REGION`,
+//! `AWS_DEFAULT_REGION` or `~/.aws/config`. Secrets are passed to curl on
+//! stdin, never on its command line.
+//!
+//! Without `--s3-endpoint` objects go to `https://<bucket>.s3.<region>.amazonaws.com`;
+//! with it (e.g. for MinIO) path-style URLs below the endpoint are used.
+
+use crate::http;
+use crate::AppError;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Upload attempts before giving up.
+const ATTEMPTS: u32 = 3;
+
+/// Wait before the first retry; doubled for every further one.
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Region used when none is configured.
+const DEFAULT_REGION: &str = "us-east-1";
+
+/// AWS credentials.
+struct Credentials {
+    access_key_id: String,
+    secret_access_key: String,
+    session_token: Option<String>,
+}
+
+/// Uploads `body` as a JSON object to the key `url` expands to.
+///
+/// `url` is an `s3://bucket/key` URL whose key may contain the placeholders
+/// described in `expand`. The request is made with `client`'s proxy and TLS
+/// settings.
+///
+/// # Returns
+///
+/// The URL of the uploaded object.
+///
+/// # Errors
+///
+/// Returns `AppError::S3Upload` if no credentials are configured or every
+/// attempt fails.
+pub fn upload(
+    url: &str,
+    endpoint: Option<&str>,
+    hostname: &str,
+    body: &[u8],
+    client: &http::Client,
+) -> Result<String, AppError> {
+    let (bucket, template) = split_url(url).ok_or_else(|| {
+        AppError::InvalidArgument(format!("'{}' is not an s3://bucket/key URL", url))
+    })?;
+    let key = expand(template, hostname, SystemTime::now());
+    let error = |message: String| AppError::S3Upload {
+        bucket: bucket.to_string(),
+        key: key.clone(),
+        message,
+    };
+
+    let credentials = credentials().ok_or_else(|| {
+        error("no AWS credentials found in the environment or ~/.aws/credentials".to_string())
+    })?;
+    let region = region();
+    let object_url = match endpoint {
+        Some(endpoint) => format!(
+            "{}/{}/{}",
+            endpoint.trim_end_matches('/'),
+            bucket,
+            encode_key(&key)
+        ),
+        None => format!(
+            "https://{}.s3.{}.amazonaws.com/{}",
+            bucket,
+            region,
+            encode_key(&key)
+        ),
+    };
+    let config = curl_config(&credentials, body);
+
+    let mut backoff = INITIAL_BACKOFF;
+    let mut attempt = 1;
+    loop {
+        match put(client, &object_url, &region, &config) {
+            Ok(()) => return Ok(object_url),
+            Err(message) if attempt == ATTEMPTS => {
+                return Err(error(format!("{} (after {} attempts)", message, ATTEMPTS)))
+            }
+            Err(message) => {
+                eprintln!(
+                    "Warning: upload to s3://{}/{} failed, retrying in {}s: {}",
+                    bucket,
+                    key,
+                    backoff.as_secs(),
+                    message
+                );
+                std::thread::sleep(backoff);
+                backoff *= 2;
+                attempt += 1;
+            }
+        }
+    }
+}
+
+/// Splits an `s3://bucket/key` URL into the bucket and the key.
+pub fn split_url(url: &str) -> Option<(&str, &str)> {
+    let (bucket, key) = url.strip_prefix("s3://")?.split_once('/')?;
+    (!bucket.is_empty() && !key.is_empty()).then_some((bucket, key))
+}
+
+/// Expands the placeholders in an object key.
+///
+/// `{hostname}` becomes `hostname`, `{date}` the UTC date of `now` as
+/// `YYYY-MM-DD`, and `{timestamp}` its Unix time in seconds.
+pub fn expand(template: &str, hostname: &str, now: SystemTime) -> String {
+    let seconds = now.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let (year, month, day) = civil_date(seconds / 86_400);
+    template
+        .replace("{hostname}", hostname)
+        .replace("{date}", &format!("{:04}-{:02}-{:02}", year, month, day))
+        .replace("{timestamp}", &seconds.to_string())
+}
+
+/// Converts days since the Unix epoch to a (year, month, day) date.
+///
+/// Uses Howard Hinnant's civil-from-days algorithm.
+fn civil_date(days: u64) -> (i64, i64, i64) {
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    (yoe + era * 400 + i64::from(month <= 2), month, day)
+}
+
+/// Percent-encodes an object key for use in a URL path, keeping `/`.
+fn encode_key(key: &str) -> String {
+    let mut encoded = String::with_capacity(key.len());
+    for byte in key.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// PUTs the object described by the curl `config` to `url`.
+fn put(client: &http::Client, url: &str, region: &str, config: &str) -> Result<(), String> {
+    let mut child = client
+        .curl(url)
+        .args(["-fsS", "-X", "PUT", "-H", "Content-Type: application/json"])
+        .args(["--aws-sigv4", &format!("aws:amz:{}:s3", region)])
+        .args(["--config", "-", url])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("could not run curl: {}", e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(config.as_bytes())
+            .map_err(|e| format!("could not pass the request to curl: {}", e))?;
+    }
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("could not run curl: {}", e))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
+
+/// Builds the curl config holding the credentials and the request body.
+fn curl_config(credentials: &Credentials, body: &[u8]) -> String {
+    let mut config = format!(
+        "user = \"{}:{}\"\n",
+        quote(&credentials.access_key_id),
+        quote(&credentials.secret_access_key)
+    );
+    if let Some(token) = &credentials.session_token {
+        config.push_str(&format!(
+            "header = \"x-amz-security-token: {}\"\n",
+            quote(token)
+        ));
+    }
+    // S3 requires the payload hash, which curl only adds itself for some
+    // request types
+    let payload_hash = crate::sha256::hex_digest(body).unwrap_or_default();
+    config.push_str(&format!(
+        "header = \"x-amz-content-sha256: {}\"\n",
+        payload_hash
+    ));
+    config.push_str(&format!(
+        "data-binary = \"{}\"\n",
+        quote(&String::from_utf8_lossy(body))
+    ));
+    config
+}
+
+/// Escapes `text` for a double-quoted curl config value.
+fn quote(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => quoted.push_str("\\\\"),
+            '"' => quoted.push_str("\\\""),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted
+}
+
+/// Looks up credentials in the environment, then in the shared credentials file.
+fn credentials() -> Option<Credentials> {
+    let env = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+    if let (Some(access_key_id), Some(secret_access_key)) =
+        (env("AWS_ACCESS_KEY_ID"), env("AWS_SECRET_ACCESS_KEY"))
+    {
+        return Some(Credentials {
+            access_key_id,
+            secret_access_key,
+            session_token: env("AWS_SESSION_TOKEN"),
+        });
+    }
+
+    let path = env("AWS_SHARED_CREDENTIALS_FILE")
+        .map(PathBuf::from)
+        .or_else(|| aws_dir().map(|dir| dir.join("credentials")))?;
+    let text = std::fs::read_to_string(path).ok()?;
+    let section = profile();
+    Some(Credentials {
+        access_key_id: ini_value(&text, &section, "aws_access_key_id")?,
+        secret_access_key: ini_value(&text, &section, "aws_secret_access_key")?,
+        session_token: ini_value(&text, &section, "aws_session_token"),
+    })
+}
+
+/// Looks up the region in the environment, then in the shared config file.
+fn region() -> String {
+    for name in ["AWS_REGION", "AWS_DEFAULT_REGION"] {
+        if let Ok(region) = std::env::var(name) {
+            if !region.is_empty() {
+                return region;
+            }
+        }
+    }
+    let path = std::env::var_os("AWS_CONFIG_FILE")
+        .map(PathBuf::from)
+        .or_else(|| aws_dir().map(|dir| dir.join("config")));
+    let profile = profile();
+    // The config file names every profile but the default one "profile <name>"
+    let section = if profile == "default" {
+        profile
+    } else {
+        format!("profile {}", profile)
+    };
+    path.and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|text| ini_value(&text, &section, "region"))
+        .unwrap_or_else(|| DEFAULT_REGION.to_string())
+}
+
+/// Returns the selected AWS profile name.
+fn profile() -> String {
+    std::env::var("AWS_PROFILE")
+        .ok()
+        .filter(|profile| !profile.is_empty())
+        .unwrap_or_else(|| "default".to_string())
+}
+
+/// Returns the `.aws` directory in the user's home directory.
+fn aws_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(|home| PathBuf::from(home).join(".aws"))
+}
+
+/// Returns the value of `key` in the `[section]` of an INI-style file.
+fn ini_value(text: &str, section: &str, key: &str) -> Option<String> {
+    let mut in_section = false;
+    for line in text.lines().map(str::trim) {
+        if let Some(name) = line
+            .strip_prefix('[')
+            .and_then(|line| line.strip_suffix(']'))
+        {
+            in_section = name.trim() == section;
+        } else if in_section {
+            if let Some((name, value)) = line.split_once('=') {
+                if name.trim() == key {
+                    return Some(value.trim().to_string());
+                }
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_url() {
+        assert_eq!(
+            split_url("s3://metrics/hosts/{hostname}.json"),
+            Some(("metrics", "hosts/{hostname}.json"))
+        );
+        assert_eq!(split_url("s3://metrics/"), None);
+        assert_eq!(split_url("https://metrics/a.json"), None);
+    }
+
+    #[test]
+    fn test_expand() {
+        let now = UNIX_EPOCH + Duration::from_secs(1760400000);
+        assert_eq!(
+            expand("reports/{hostname}/{date}-{timestamp}.json", "db-1", now),
+            "reports/db-1/2025-10-14-1760400000.json"
+        );
+    }
+
+    #[test]
+    fn test_encode_key() {
+        assert_eq!(encode_key("a b/c+d~e.json"), "a%20b/c%2Bd~e.json");
+    }
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(
+            quote("{\"a\": \"b\\\\c\"}\n"),
+            "{\\\"a\\\": \\\"b\\\\\\\\c\\\"}\\n"
+        );
+    }
+
+    #[test]
+    fn test_ini_value() {
+        let text = "[default]\nregion = eu-west-1\n\n[profile ci]\n# comment\nregion=us-east-2\n";
+        assert_eq!(
+            ini_value(text, "profile ci", "region").as_deref(),
+            Some("us-east-2")
+        );
+        assert_eq!(
+            ini_value(text, "default", "region").as_deref(),
+            Some("eu-west-1")
+        );
+        assert_eq!(ini_value(text, "other", "region"), None);
+    }
+}
@@ -0,0 +1,449 @@
+//! Disk encryption and secure boot state (`--security`).
+//!
+//! On Linux the root file system's device is looked up in `/proc/mounts` and
+//! `lsblk -s` lists the devices it is stacked on, so a LUKS container under
+//! LVM is found as well; secure boot is read from the `SecureBoot` EFI
+//! variable. On Windows the system drive's BitLocker protection comes from
+//! `manage-bde -status` and secure boot from the `SecureBoot\State` registry
+//! key. On macOS FileVault is read from `fdesetup status`. A probe that lacks
+//! the privileges or tools it needs reports `None` together with the reason.
+
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::process::{Command, Output};
+use std::time::Duration;
+
+/// Longest a single probe command may run before it is killed.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Security posture of the host.
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct SecurityStatus {
+    /// Whether the volume holding the root file system (the system drive on
+    /// Windows) is encrypted
+    pub root_encryption: Check,
+    /// Whether the firmware enforces secure boot
+    pub secure_boot: Check,
+}
+
+/// The outcome of one probe.
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+pub struct Check {
+    /// Whether the feature is enabled, or `None` if that could not be
+    /// determined
+    pub enabled: Option<bool>,
+    /// How it is implemented, e.g. "LUKS" or "BitLocker"
+    pub method: Option<String>,
+    /// Why `enabled` is `None`
+    pub reason: Option<String>,
+}
+
+impl Check {
+    fn known(enabled: bool, method: Option<&str>) -> Check {
+        Check {
+            enabled: Some(enabled),
+            method: method.filter(|_| enabled).map(str::to_string),
+            reason: None,
+        }
+    }
+
+    fn unknown(reason: impl Into<String>) -> Check {
+        Check {
+            reason: Some(reason.into()),
+            ..Check::default()
+        }
+    }
+
+    /// Describes the outcome for the console, e.g. "yes (LUKS)".
+    pub fn summary(&self) -> String {
+        match (self.enabled, &self.method, &self.reason) {
+            (Some(true), Some(method), _) => format!("yes ({})", method),
+            (Some(true), None, _) => "yes".to_string(),
+            (Some(false), _, _) => "no".to_string(),
+            (None, _, Some(reason)) => format!("unknown ({})", reason),
+            (None, _, None) => "unknown".to_string(),
+        }
+    }
+}
+
+/// Checks root volume encryption and secure boot.
+pub fn query() -> SecurityStatus {
+    SecurityStatus {
+        root_encryption: root_encryption(),
+        secure_boot: secure_boot(),
+    }
+}
+
+/// Checks whether the root file system is on an encrypted device.
+#[cfg(target_os = "linux")]
+fn root_encryption() -> Check {
+    let mounts = match std::fs::read_to_string("/proc/mounts") {
+        Ok(mounts) => mounts,
+        Err(e) => return Check::unknown(format!("could not read /proc/mounts: {}", e)),
+    };
+    let Some(device) = root_device(&mounts) else {
+        return Check::unknown("the root file system is not in /proc/mounts");
+    };
+    if !device.starts_with("/dev/") {
+        return Check::unknown(format!(
+            "the root file system is not on a block device ({})",
+            device
+        ));
+    }
+
+    match run(Command::new("lsblk")
+        .args(["-s", "-n", "-r", "-o", "TYPE,FSTYPE"])
+        .arg(device))
+    {
+        Ok(output) if output.status.success() => {
+            let method = encryption_in_lsblk(&String::from_utf8_lossy(&output.stdout));
+            Check::known(method.is_some(), method)
+        }
+        Ok(_) => Check::unknown(format!("lsblk could not inspect {}", device)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Check::unknown("lsblk is not installed"),
+        Err(e) => Check::unknown(format!("could not run lsblk: {}", e)),
+    }
+}
+
+/// Returns the device of the root file system listed in `/proc/mounts`.
+///
+/// The last entry for `/` wins, since it is the one mounted on top.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn root_device(mounts: &str) -> Option<&str> {
+    mounts.lines().rev().find_map(|line| {
+        let mut fields = line.split_whitespace();
+        let device = fields.next()?;
+        (fields.next()? == "/").then_some(device)
+    })
+}
+
+/// Finds the encryption layer in `lsblk -s -n -r -o TYPE,FSTYPE` output,
+/// which lists a device followed by every device below it.
+///
+/// # Returns
+///
+/// "LUKS" if a LUKS container is found, "dm-crypt" for a plain dm-crypt
+/// mapping, or `None` if no device is encrypted.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn encryption_in_lsblk(output: &str) -> Option<&'static str> {
+    let mut crypt = false;
+    for line in output.lines() {
+        let mut fields = line.split_whitespace();
+        let kind = fields.next().unwrap_or_default();
+        let fstype = fields.next().unwrap_or_default();
+        if fstype == "crypto_LUKS" {
+            return Some("LUKS");
+        }
+        crypt |= kind == "crypt";
+    }
+    crypt.then_some("dm-crypt")
+}
+
+/// Checks whether secure boot is enabled.
+#[cfg(target_os = "linux")]
+fn secure_boot() -> Check {
+    secure_boot_in(std::path::Path::new("/sys/firmware/efi"))
+}
+
+/// Checks whether secure boot is enabled, given the firmware directory
+/// `efi` (normally `/sys/firmware/efi`).
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn secure_boot_in(efi: &std::path::Path) -> Check {
+    if !efi.exists() {
+        // Secure boot requires UEFI; this is a legacy BIOS boot
+        return Check::known(false, None);
+    }
+    let efivars = efi.join("efivars");
+    match std::fs::read(efivars.join("SecureBoot-8be4df61-93ca-11d2-aa0d-00e098032b8c")) {
+        // 4 bytes of attributes, then the value
+        Ok(data) => match data.get(4) {
+            Some(&value) => Check::known(value == 1, None),
+            None => Check::unknown("the SecureBoot variable is malformed"),
+        },
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            let mounted = std::fs::read_dir(&efivars).is_ok_and(|mut dir| dir.next().is_some());
+            if mounted {
+                // Firmware without secure boot support has no variable
+                Check::known(false, None)
+            } else {
+                Check::unknown("efivarfs is not mounted")
+            }
+        }
+        Err(e) => Check::unknown(format!("could not read the SecureBoot variable: {}", e)),
+    }
+}
+
+/// Checks whether BitLocker protects the system drive.
+#[cfg(windows)]
+fn root_encryption() -> Check {
+    let drive = std::env::var("SystemDrive").unwrap_or_else(|_| "C:".to_string());
+    match run(Command::new("manage-bde").args(["-status", &drive])) {
+        Ok(output) if output.status.success() => {
+            match bitlocker_protection(&String::from_utf8_lossy(&output.stdout)) {
+                Some(on) => Check::known(on, Some("BitLocker")),
+                None => Check::unknown("unrecognized manage-bde output"),
+            }
+        }
+        Ok(_) => Check::unknown("manage-bde failed; it requires administrator rights"),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            Check::unknown("manage-bde is not available")
+        }
+        Err(e) => Check::unknown(format!("could not run manage-bde: {}", e)),
+    }
+}
+
+/// Reads the protection status from `manage-bde -status` output.
+///
+/// # Returns
+///
+/// Whether protection is on, or `None` if the status line is missing (e.g.
+/// localized output).
+#[cfg_attr(not(windows), allow(dead_code))]
+fn bitlocker_protection(output: &str) -> Option<bool> {
+    output
+        .lines()
+        .filter_map(|line| line.trim().split_once(':'))
+        .find(|(key, _)| key.trim() == "Protection Status")
+        .map(|(_, value)| value.trim() == "Protection On")
+}
+
+/// Checks whether secure boot is enabled.
+#[cfg(windows)]
+fn secure_boot() -> Check {
+    let key = r"HKLM\SYSTEM\CurrentControlSet\Control\SecureBoot\State";
+    match run(Command::new("reg").args(["query", key, "/v", "UEFISecureBootEnabled"])) {
+        Ok(output) if output.status.success() => {
+            match registry_dword(
+                &String::from_utf8_lossy(&output.stdout),
+                "UEFISecureBootEnabled",
+            ) {
+                Some(value) => Check::known(value == 1, None),
+                None => Check::unknown("unrecognized reg query output"),
+            }
+        }
+        // The value only exists on UEFI systems; this is a legacy BIOS boot
+        Ok(output) if output.status.code() == Some(1) => Check::known(false, None),
+        Ok(_) => Check::unknown("reg query failed"),
+        Err(e) => Check::unknown(format!("could not run reg query: {}", e)),
+    }
+}
+
+/// Reads the DWORD value `name` from `reg query` output.
+#[cfg_attr(not(windows), allow(dead_code))]
+fn registry_dword(output: &str, name: &str) -> Option<u32> {
+    output.lines().find_map(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        match fields.as_slice() {
+            [key, "REG_DWORD", value] if *key == name => {
+                u32::from_str_radix(value.trim_start_matches("0x"), 16).ok()
+            }
+            _ => None,
+        }
+    })
+}
+
+/// Checks whether FileVault protects the startup disk.
+#[cfg(target_os = "macos")]
+fn root_encryption() -> Check {
+    match run(Command::new("fdesetup").arg("status")) {
+        Ok(output) if output.status.success() => {
+            match filevault_status(&String::from_utf8_lossy(&output.stdout)) {
+                Some(on) => Check::known(on, Some("FileVault")),
+                None => Check::unknown("unrecognized fdesetup output"),
+            }
+        }
+        Ok(_) => Check::unknown("fdesetup status failed"),
+        Err(e) => Check::unknown(format!("could not run fdesetup: {}", e)),
+    }
+}
+
+/// Reads whether FileVault is on from `fdesetup status` output.
+///
+/// Encryption or decryption in progress counts as on, since the disk is (at
+/// least partly) encrypted and the key is in use.
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn filevault_status(output: &str) -> Option<bool> {
+    let line = output.lines().next()?.trim();
+    if line.starts_with("FileVault is On") {
+        Some(true)
+    } else if line.starts_with("FileVault is Off") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// Checks whether the root volume is encrypted.
+///
+/// Not supported on this platform.
+#[cfg(not(any(target_os = "linux", windows, target_os = "macos")))]
+fn root_encryption() -> Check {
+    Check::unknown("not supported on this platform")
+}
+
+/// Checks whether secure boot is enabled.
+///
+/// Not supported on this platform.
+#[cfg(not(any(target_os = "linux", windows)))]
+fn secure_boot() -> Check {
+    Check::unknown("not supported on this platform")
+}
+
+/// Runs a probe command with `PROBE_TIMEOUT`.
+#[cfg_attr(
+    not(any(target_os = "linux", windows, target_os = "macos")),
+    allow(dead_code)
+)]
+fn run(command: &mut Command) -> io::Result<Output> {
+    crate::command::run_with_timeout(command, PROBE_TIMEOUT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_root_device() {
+        let mounts = "rootfs / rootfs rw 0 0\n\
+              /dev/mapper/vg-root / ext4 rw,relatime 0 0\n\
+              /dev/nvme0n1p1 /boot/efi vfat rw 0 0\n";
+        assert_eq!(root_device(mounts), Some("/dev/mapper/vg-root"));
+        assert_eq!(root_device("overlay / overlay rw 0 0\n"), Some("overlay"));
+        assert_eq!(root_device("/dev/sda1 /data ext4 rw 0 0\n"), None);
+    }
+
+    #[test]
+    fn test_encryption_in_lsblk() {
+        // ext4 on LVM on LUKS
+        let output = "lvm ext4\ncrypt LVM2_member\npart crypto_LUKS\ndisk \n";
+        assert_eq!(encryption_in_lsblk(output), Some("LUKS"));
+        assert_eq!(encryption_in_lsblk("crypt ext4\ndisk \n"), Some("dm-crypt"));
+        assert_eq!(encryption_in_lsblk("part ext4\ndisk \n"), None);
+        assert_eq!(encryption_in_lsblk(""), None);
+
+        // `lsblk -s -n -r -o TYPE,FSTYPE /dev/mapper/luks-…` on a laptop
+        // with btrfs directly on LUKS on NVMe; the disk has no file system,
+        // so its line ends in the separator
+        let output = "crypt btrfs\npart crypto_LUKS\ndisk \n";
+        assert_eq!(encryption_in_lsblk(output), Some("LUKS"));
+        // A plain dm-crypt swap mapping has no LUKS header
+        assert_eq!(
+            encryption_in_lsblk("crypt swap\npart \ndisk \n"),
+            Some("dm-crypt")
+        );
+        // RAID without encryption
+        assert_eq!(
+            encryption_in_lsblk(
+                "raid1 ext4\npart linux_raid_member\ndisk \npart linux_raid_member\ndisk \n"
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_secure_boot_in() {
+        let root = std::env::temp_dir().join(format!("security-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let efi = root.join("efi");
+        let efivars = efi.join("efivars");
+        let variable = efivars.join("SecureBoot-8be4df61-93ca-11d2-aa0d-00e098032b8c");
+
+        // Legacy BIOS boot
+        assert_eq!(secure_boot_in(&efi), Check::known(false, None));
+
+        std::fs::create_dir_all(&efivars).unwrap();
+        assert_eq!(
+            secure_boot_in(&efi),
+            Check::unknown("efivarfs is not mounted")
+        );
+        std::fs::write(
+            efivars.join("BootOrder-8be4df61-93ca-11d2-aa0d-00e098032b8c"),
+            [7, 0, 0, 0, 1, 0],
+        )
+        .unwrap();
+        assert_eq!(secure_boot_in(&efi), Check::known(false, None));
+
+        // Attributes, then the value
+        std::fs::write(&variable, [6, 0, 0, 0, 1]).unwrap();
+        assert_eq!(secure_boot_in(&efi), Check::known(true, None));
+        std::fs::write(&variable, [6, 0, 0, 0, 0]).unwrap();
+        assert_eq!(secure_boot_in(&efi), Check::known(false, None));
+        std::fs::write(&variable, [6, 0, 0, 0]).unwrap();
+        assert_eq!(
+            secure_boot_in(&efi),
+            Check::unknown("the SecureBoot variable is malformed")
+        );
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_check_summary() {
+        assert_eq!(Check::known(true, Some("LUKS")).summary(), "yes (LUKS)");
+        assert_eq!(Check::known(true, None).summary(), "yes");
+        // The method is only kept for enabled checks
+        assert_eq!(
+            Check::known(false, Some("BitLocker")),
+            Check::known(false, None)
+        );
+        assert_eq!(Check::known(false, Some("BitLocker")).summary(), "no");
+        assert_eq!(
+            Check::unknown("lsblk is not installed").summary(),
+            "unknown (lsblk is not installed)"
+        );
+        assert_eq!(Check::default().summary(), "unknown");
+    }
+
+    #[test]
+    fn test_bitlocker_protection() {
+        let output = "Volume C: [OS]\n\
+              [OS Volume]\n\
+              \n    Conversion Status:    Fully Encrypted\n\
+              \n    Percentage Encrypted: 100.0%\n\
+              \n    Protection Status:    Protection On\n";
+        assert_eq!(bitlocker_protection(output), Some(true));
+        assert_eq!(
+            bitlocker_protection("    Protection Status:    Protection Off\n"),
+            Some(false)
+        );
+        assert_eq!(
+            bitlocker_protection("    Schutzstatus:    Der Schutz ist aktiviert.\n"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_registry_dword() {
+        let output =
+            "\r\nHKEY_LOCAL_MACHINE\\SYSTEM\\CurrentControlSet\\Control\\SecureBoot\\State\r\n\
+              \x20   UEFISecureBootEnabled    REG_DWORD    0x1\r\n\r\n";
+        assert_eq!(registry_dword(output, "UEFISecureBootEnabled"), Some(1));
+        assert_eq!(
+            registry_dword(
+                "    UEFISecureBootEnabled    REG_DWORD    0x0\r\n",
+                "UEFISecureBootEnabled"
+            ),
+            Some(0)
+        );
+        assert_eq!(
+            registry_dword("    Other    REG_DWORD    0x1\r\n", "UEFISecureBootEnabled"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_filevault_status() {
+        assert_eq!(filevault_status("FileVault is On.\n"), Some(true));
+        assert_eq!(filevault_status("FileVault is Off.\n"), Some(false));
+        assert_eq!(
+            filevault_status("FileVault is Off, but will be enabled after the next restart.\n"),
+            Some(false)
+        );
+        assert_eq!(
+            filevault_status(
+                "FileVault is On.\nEncryption in progress: Percent completed = 42.0\n"
+            ),
+            Some(true)
+        );
+        assert_eq!(filevault_status(""), None);
+    }
+}
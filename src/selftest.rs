@@ -0,0 +1,325 @@
+//! `--self-test`: sanity checks on a live snapshot.
+//!
+//! Runs the collectors once and checks that what they return is plausible
+//! (memory is reported, there is at least one CPU, percentages lie between
+//! 0 and 100, and so on), so a new platform or a deployment pipeline can
+//! confirm the tool works there. Each check is reported on its own; data the
+//! platform does not provide is skipped rather than failed.
+
+use crate::cpu::CpuBreakdown;
+use crate::SystemInfo;
+
+/// The outcome of one check.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Status {
+    Pass,
+    Fail,
+    /// The data is not available on this platform
+    Skip,
+}
+
+/// One check and what was observed.
+#[derive(Debug, PartialEq)]
+pub struct CheckResult {
+    /// What was checked, e.g. "memory" or "cpu_times"
+    pub name: &'static str,
+    pub status: Status,
+    /// The observed value, or why the check failed or was skipped
+    pub detail: String,
+}
+
+impl CheckResult {
+    fn new(name: &'static str, passed: bool, detail: String) -> CheckResult {
+        let status = if passed { Status::Pass } else { Status::Fail };
+        CheckResult {
+            name,
+            status,
+            detail,
+        }
+    }
+
+    fn skip(name: &'static str, reason: &str) -> CheckResult {
+        CheckResult {
+            name,
+            status: Status::Skip,
+            detail: reason.to_string(),
+        }
+    }
+}
+
+/// Checks every section of `info`.
+///
+/// # Returns
+///
+/// One result per check, core data first.
+pub fn run(info: &SystemInfo) -> Vec<CheckResult> {
+    let mut results = vec![
+        CheckResult::new(
+            "os",
+            info.os_name != "N/A",
+            format!("{} {}", info.os_name, info.os_version),
+        ),
+        CheckResult::new(
+            "hostname",
+            !info.hostname.is_empty() && info.hostname != "N/A",
+            info.hostname.clone(),
+        ),
+        CheckResult::new(
+            "cpu_cores",
+            info.cpu_cores >= 1,
+            format!("{} logical CPUs", info.cpu_cores),
+        ),
+        CheckResult::new(
+            "memory",
+            info.total_memory > 0 && info.used_memory <= info.total_memory,
+            format!("{} of {} bytes used", info.used_memory, info.total_memory),
+        ),
+        CheckResult::new(
+            "swap",
+            info.used_swap <= info.total_swap,
+            format!("{} of {} bytes used", info.used_swap, info.total_swap),
+        ),
+    ];
+
+    results.push(match &info.cpu_times {
+        Some(times) => {
+            let problems: Vec<String> = std::iter::once(("total", &times.total))
+                .chain(
+                    times
+                        .per_core
+                        .iter()
+                        .map(|core| (core.name.as_str(), &core.times)),
+                )
+                .filter_map(|(name, times)| {
+                    breakdown_problem(times).map(|problem| format!("{}: {}", name, problem))
+                })
+                .collect();
+            let detail = if problems.is_empty() {
+                format!("{} CPUs sampled", times.per_core.len())
+            } else {
+                problems.join("; ")
+            };
+            CheckResult::new("cpu_times", problems.is_empty(), detail)
+        }
+        None => CheckResult::skip("cpu_times", "not reported on this platform"),
+    });
+
+    results.push(match &info.memory_activity {
+        Some(activity) => {
+            let rates = [
+                activity.swap_in_per_sec,
+                activity.swap_out_per_sec,
+                activity.major_faults_per_sec,
+            ];
+            CheckResult::new(
+                "memory_activity",
+                rates.iter().all(|rate| rate.is_finite() && *rate >= 0.0),
+                format!("{:.1} major faults/s", activity.major_faults_per_sec),
+            )
+        }
+        None => CheckResult::skip("memory_activity", "not reported on this platform"),
+    });
+
+    let bad_disks: Vec<&str> = info
+        .disks
+        .iter()
+        .filter(|disk| disk.available_space > disk.total_space)
+        .map(|disk| disk.name.as_str())
+        .collect();
+    results.push(if info.disks.is_empty() {
+        CheckResult::new("disks", false, "no disks detected".to_string())
+    } else if !bad_disks.is_empty() {
+        CheckResult::new(
+            "disks",
+            false,
+            format!(
+                "more space available than in total: {}",
+                bad_disks.join(", ")
+            ),
+        )
+    } else {
+        CheckResult::new("disks", true, format!("{} disks", info.disks.len()))
+    });
+    results.push(match info.root_disk_usage_percent {
+        Some(percent) => CheckResult::new(
+            "root_disk_usage_percent",
+            is_percent(percent),
+            format!("{:.1}%", percent),
+        ),
+        None => CheckResult::skip("root_disk_usage_percent", "root disk not found"),
+    });
+
+    let bad_networks: Vec<&str> = info
+        .networks
+        .iter()
+        .filter(|network| {
+            network.bytes_received > 0 && network.packets_received == 0
+                || network.bytes_transmitted > 0 && network.packets_transmitted == 0
+        })
+        .map(|network| network.name.as_str())
+        .collect();
+    results.push(if info.networks.is_empty() {
+        CheckResult::new(
+            "networks",
+            false,
+            "no network interfaces detected".to_string(),
+        )
+    } else if !bad_networks.is_empty() {
+        CheckResult::new(
+            "networks",
+            false,
+            format!("bytes without packets: {}", bad_networks.join(", ")),
+        )
+    } else {
+        CheckResult::new(
+            "networks",
+            true,
+            format!("{} interfaces", info.networks.len()),
+        )
+    });
+
+    results.push(match &info.core_temperatures {
+        Some(temperatures) if !temperatures.is_empty() => {
+            let plausible = temperatures
+                .iter()
+                .all(|&celsius| (-40.0..=150.0).contains(&celsius));
+            let highest = temperatures.iter().copied().fold(f32::MIN, f32::max);
+            CheckResult::new(
+                "core_temperatures",
+                plausible,
+                format!("{} sensors, highest {:.1}°C", temperatures.len(), highest),
+            )
+        }
+        _ => CheckResult::skip("core_temperatures", "no CPU temperature sensors"),
+    });
+
+    results.push(match &info.limits_usage {
+        Some(limits) => {
+            let usages = [
+                ("open_files", &limits.open_files),
+                ("processes", &limits.processes),
+                ("threads", &limits.threads),
+            ];
+            let bad: Vec<&str> = usages
+                .iter()
+                .filter(|(_, usage)| {
+                    usage
+                        .as_ref()
+                        .and_then(|usage| usage.percent)
+                        .is_some_and(|percent| !is_percent(percent))
+                })
+                .map(|(name, _)| *name)
+                .collect();
+            let detail = if bad.is_empty() {
+                "percentages within 0-100".to_string()
+            } else {
+                format!("percentage out of range: {}", bad.join(", "))
+            };
+            CheckResult::new("limits_usage", bad.is_empty(), detail)
+        }
+        None => CheckResult::skip("limits_usage", "not reported on this platform"),
+    });
+
+    results.push(match &info.process_states {
+        Some(states) => {
+            let total: usize = states.values().sum();
+            CheckResult::new("process_states", total > 0, format!("{} processes", total))
+        }
+        None => CheckResult::skip("process_states", "not reported on this platform"),
+    });
+
+    results.push(match &info.sockets {
+        Some(sockets) => CheckResult::new(
+            "sockets",
+            true,
+            format!(
+                "{} TCP, {} UDP",
+                sockets.tcp_states.values().sum::<u64>(),
+                sockets.udp_sockets
+            ),
+        ),
+        None => CheckResult::skip("sockets", "not reported on this platform"),
+    });
+
+    results.push(CheckResult::new(
+        "collection_errors",
+        info.collection_errors.is_empty(),
+        if info.collection_errors.is_empty() {
+            "none".to_string()
+        } else {
+            info.collection_errors.join("; ")
+        },
+    ));
+
+    let round_trip = serde_json::to_string(info)
+        .map_err(|e| e.to_string())
+        .and_then(|json| {
+            serde_json::from_str::<SystemInfo>(&json)
+                .map(|_| json.len())
+                .map_err(|e| e.to_string())
+        });
+    results.push(match round_trip {
+        Ok(bytes) => CheckResult::new("json", true, format!("{} bytes round-trip", bytes)),
+        Err(e) => CheckResult::new("json", false, e),
+    });
+
+    results
+}
+
+/// Returns whether `value` is a percentage between 0 and 100.
+fn is_percent(value: f64) -> bool {
+    (0.0..=100.0).contains(&value)
+}
+
+/// Describes what is implausible about a CPU time breakdown: a share
+/// outside 0-100%, or shares that do not add up to about 100%.
+fn breakdown_problem(times: &CpuBreakdown) -> Option<String> {
+    let shares = [
+        ("user", times.user),
+        ("nice", times.nice),
+        ("system", times.system),
+        ("idle", times.idle),
+        ("iowait", times.iowait),
+        ("irq", times.irq),
+        ("softirq", times.softirq),
+        ("steal", times.steal),
+    ];
+    if let Some((name, share)) = shares
+        .iter()
+        .find_map(|&(name, share)| share.filter(|&share| !is_percent(share)).map(|s| (name, s)))
+    {
+        return Some(format!("{} is {:.1}%", name, share));
+    }
+    let known: Vec<f64> = shares.iter().filter_map(|&(_, share)| share).collect();
+    let sum: f64 = known.iter().sum();
+    // Shares are rounded, and a CPU may be counted a tick apart
+    (!known.is_empty() && (sum - 100.0).abs() > 2.0)
+        .then(|| format!("shares add up to {:.1}%", sum))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breakdown_problem() {
+        let breakdown = |user, system, idle| CpuBreakdown {
+            user: Some(user),
+            system: Some(system),
+            idle: Some(idle),
+            ..CpuBreakdown::default()
+        };
+        assert_eq!(breakdown_problem(&breakdown(12.5, 4.0, 83.5)), None);
+        assert_eq!(breakdown_problem(&breakdown(12.5, 4.0, 82.0)), None);
+        assert_eq!(
+            breakdown_problem(&breakdown(120.0, 4.0, 0.0)),
+            Some("user is 120.0%".to_string())
+        );
+        assert_eq!(
+            breakdown_problem(&breakdown(12.5, 4.0, 40.0)),
+            Some("shares add up to 56.5%".to_string())
+        );
+        // An idle sample with nothing counted has no shares to add up
+        assert_eq!(breakdown_problem(&CpuBreakdown::default()), None);
+    }
+}
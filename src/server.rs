@@ -0,0 +1,407 @@
+//! Local query server (`--listen-unix` and `--listen-pipe`).
+//!
+//! While running in interval mode, the latest snapshot is cached and served
+//! to local clients over a Unix domain socket or, on Windows, a named pipe.
+//! Each connection receives one JSON document and is then closed. A client
+//! may first send a single request line:
+//!
+//! ```text
+//! GET            the whole snapshot (same as sending nothing)
+//! GET disks      only the `disks` section
+//! ```
+//!
+//! A client that sends nothing within `REQUEST_TIMEOUT` gets the whole
+//! snapshot. Unknown sections and malformed requests are answered with
+//! `{"error": "..."}`.
+
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How long to wait for a client's optional request line.
+const REQUEST_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// Longest request line read from a client.
+const MAX_REQUEST_BYTES: u64 = 256;
+
+/// The snapshot shared between the collection loop and connection handlers.
+type Snapshot = Arc<Mutex<Option<Value>>>;
+
+/// A running server. The listener thread lives as long as the process.
+pub struct Server {
+    snapshot: Snapshot,
+    #[cfg(unix)]
+    socket_path: Option<std::path::PathBuf>,
+}
+
+impl Server {
+    /// Replaces the cached snapshot served to new connections.
+    pub fn publish(&self, snapshot: Value) {
+        if let Ok(mut cached) = self.snapshot.lock() {
+            *cached = Some(snapshot);
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Server {
+    fn drop(&mut self) {
+        if let Some(path) = &self.socket_path {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// Builds the response to `request` (the trimmed request line, empty if none).
+fn respond(snapshot: Option<&Value>, request: &str) -> String {
+    let Some(snapshot) = snapshot else {
+        return json!({"error": "no snapshot collected yet"}).to_string();
+    };
+    let mut words = request.split_whitespace();
+    let response = match (words.next(), words.next(), words.next()) {
+        (None, _, _) | (Some("GET"), None, _) => Ok(snapshot),
+        (Some("GET"), Some(section), None) => snapshot
+            .get(section)
+            .ok_or_else(|| format!("unknown section '{}'", section)),
+        _ => Err(format!(
+            "malformed request '{}' (expected GET [SECTION])",
+            request
+        )),
+    };
+    match response {
+        Ok(value) => value.to_string(),
+        Err(message) => json!({ "error": message }).to_string(),
+    }
+}
+
+/// Serves one connection: reads the optional request line and writes the response.
+fn serve<S: std::io::Read + std::io::Write>(mut stream: S, snapshot: &Snapshot) {
+    use std::io::{BufRead, BufReader, Read};
+
+    // A timeout or error while reading just means no request was sent.
+    let mut line = String::new();
+    let _ = BufReader::new((&mut stream).take(MAX_REQUEST_BYTES)).read_line(&mut line);
+
+    let response = match snapshot.lock() {
+        Ok(cached) => respond(cached.as_ref(), line.trim()),
+        Err(_) => json!({"error": "snapshot unavailable"}).to_string(),
+    };
+    let _ = stream.write_all(response.as_bytes());
+    let _ = stream.write_all(b"\n");
+    let _ = stream.flush();
+}
+
+/// Socket ownership and permissions applied after binding.
+#[cfg(unix)]
+#[derive(Debug, Default, Clone)]
+pub struct SocketAccess {
+    /// Permission bits, e.g. `0o660`
+    pub mode: Option<u32>,
+    /// Group name or numeric ID to own the socket
+    pub group: Option<String>,
+}
+
+/// Starts serving on the Unix domain socket at `path`.
+///
+/// A stale socket file left by a previous run is removed first. A path that
+/// exists but is not a socket, or a socket another process is still
+/// listening on, is an error rather than being replaced. The socket is bound
+/// in a private directory next to `path` and renamed into place after
+/// `access` is applied.
+///
+/// # Errors
+///
+/// Returns a description of the failure if the socket cannot be prepared,
+/// bound, or given the requested permissions.
+#[cfg(unix)]
+pub fn listen_unix(path: &std::path::Path, access: &SocketAccess) -> Result<Server, String> {
+    use std::os::unix::fs::{DirBuilderExt, FileTypeExt};
+    use std::os::unix::net::UnixStream;
+
+    let describe = |e: std::io::Error| format!("{}: {}", path.display(), e);
+
+    if let Ok(metadata) = std::fs::symlink_metadata(path) {
+        if !metadata.file_type().is_socket() {
+            return Err(format!("{}: exists and is not a socket", path.display()));
+        }
+        if UnixStream::connect(path).is_ok() {
+            return Err(format!(
+                "{}: another process is already listening",
+                path.display()
+            ));
+        }
+        std::fs::remove_file(path).map_err(describe)?;
+    }
+
+    // Bind inside a directory only this user can enter and move the socket
+    // into place once its owner and mode are set, so it is never reachable
+    // with the permissions the umask gave it
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => std::path::Path::new("."),
+    };
+    let staging = parent.join(format!(".{}.tmp", std::process::id()));
+    std::fs::DirBuilder::new()
+        .mode(0o700)
+        .create(&staging)
+        .map_err(|e| format!("{}: {}", staging.display(), e))?;
+    let staged = staging.join("s");
+    let bound = bind_staged(&staged, access).and_then(|listener| {
+        std::fs::rename(&staged, path).map_err(describe)?;
+        Ok(listener)
+    });
+    let _ = std::fs::remove_file(&staged);
+    let _ = std::fs::remove_dir(&staging);
+    let listener = bound?;
+    let server = Server {
+        snapshot: Snapshot::default(),
+        socket_path: Some(path.to_path_buf()),
+    };
+
+    let snapshot = Arc::clone(&server.snapshot);
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let _ = stream.set_read_timeout(Some(REQUEST_TIMEOUT));
+            serve(stream, &snapshot);
+        }
+    });
+    Ok(server)
+}
+
+/// Binds a socket at `path` and applies `access` to it.
+#[cfg(unix)]
+fn bind_staged(
+    path: &std::path::Path,
+    access: &SocketAccess,
+) -> Result<std::os::unix::net::UnixListener, String> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let describe = |e: std::io::Error| format!("{}: {}", path.display(), e);
+    let listener = std::os::unix::net::UnixListener::bind(path).map_err(describe)?;
+    if let Some(group) = &access.group {
+        let gid = resolve_group(group)?;
+        std::os::unix::fs::chown(path, None, Some(gid)).map_err(describe)?;
+    }
+    if let Some(mode) = access.mode {
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode)).map_err(describe)?;
+    }
+    Ok(listener)
+}
+
+/// Resolves a group name or numeric ID to a group ID using `/etc/group`.
+#[cfg(unix)]
+fn resolve_group(group: &str) -> Result<u32, String> {
+    if let Ok(gid) = group.parse() {
+        return Ok(gid);
+    }
+    let groups = std::fs::read_to_string("/etc/group")
+        .map_err(|e| format!("cannot read /etc/group: {}", e))?;
+    groups
+        .lines()
+        .find_map(|line| {
+            let mut fields = line.split(':');
+            if fields.next()? != group {
+                return None;
+            }
+            fields.nth(1)?.parse().ok()
+        })
+        .ok_or_else(|| format!("unknown group '{}'", group))
+}
+
+/// Starts serving on the Windows named pipe `name`, e.g. `\\.\pipe\sysinfo`.
+///
+/// # Errors
+///
+/// Returns a description of the failure if the first pipe instance cannot
+/// be created.
+#[cfg(windows)]
+pub fn listen_pipe(name: &str) -> Result<Server, String> {
+    let first = pipe::create(name, true).map_err(|e| format!("{}: {}", name, e))?;
+    let server = Server {
+        snapshot: Snapshot::default(),
+    };
+
+    let snapshot = Arc::clone(&server.snapshot);
+    let name = name.to_string();
+    std::thread::spawn(move || {
+        let mut instance = Some(first);
+        loop {
+            let pipe = match instance
+                .take()
+                .map_or_else(|| pipe::create(&name, false), Ok)
+            {
+                Ok(pipe) => pipe,
+                Err(e) => {
+                    eprintln!("Warning: named pipe {}: {}", name, e);
+                    return;
+                }
+            };
+            if let Ok(connection) = pipe::accept(pipe) {
+                serve(connection, &snapshot);
+            }
+        }
+    });
+    Ok(server)
+}
+
+/// Minimal bindings to the Win32 named pipe API in kernel32.
+#[cfg(windows)]
+mod pipe {
+    use std::ffi::c_void;
+    use std::fs::File;
+    use std::io::{self, Read, Write};
+    use std::os::windows::ffi::OsStrExt;
+    use std::os::windows::io::{AsRawHandle, FromRawHandle, RawHandle};
+    use std::time::Instant;
+
+    const PIPE_ACCESS_DUPLEX: u32 = 0x0000_0003;
+    const FILE_FLAG_FIRST_PIPE_INSTANCE: u32 = 0x0008_0000;
+    const PIPE_TYPE_BYTE: u32 = 0x0000_0000;
+    const PIPE_REJECT_REMOTE_CLIENTS: u32 = 0x0000_0008;
+    const PIPE_UNLIMITED_INSTANCES: u32 = 255;
+    const ERROR_PIPE_CONNECTED: i32 = 535;
+    const BUFFER_SIZE: u32 = 64 * 1024;
+
+    extern "system" {
+        fn CreateNamedPipeW(
+            name: *const u16,
+            open_mode: u32,
+            pipe_mode: u32,
+            max_instances: u32,
+            out_buffer_size: u32,
+            in_buffer_size: u32,
+            default_timeout: u32,
+            security_attributes: *mut c_void,
+        ) -> RawHandle;
+        fn ConnectNamedPipe(pipe: RawHandle, overlapped: *mut c_void) -> i32;
+        fn DisconnectNamedPipe(pipe: RawHandle) -> i32;
+        fn FlushFileBuffers(file: RawHandle) -> i32;
+        fn PeekNamedPipe(
+            pipe: RawHandle,
+            buffer: *mut c_void,
+            buffer_size: u32,
+            bytes_read: *mut u32,
+            total_bytes_available: *mut u32,
+            bytes_left_this_message: *mut u32,
+        ) -> i32;
+    }
+
+    /// A pipe instance that owns its handle.
+    pub struct Pipe(File);
+
+    /// A connected client. Reads give up after `REQUEST_TIMEOUT` without data.
+    pub struct Connection(File);
+
+    /// Creates a new instance of the pipe `name`. With `first`, fails if
+    /// another process already owns a pipe of that name.
+    pub fn create(name: &str, first: bool) -> io::Result<Pipe> {
+        let wide: Vec<u16> = std::ffi::OsStr::new(name)
+            .encode_wide()
+            .chain(Some(0))
+            .collect();
+        let mut open_mode = PIPE_ACCESS_DUPLEX;
+        if first {
+            open_mode |= FILE_FLAG_FIRST_PIPE_INSTANCE;
+        }
+        // SAFETY: `wide` is a NUL-terminated UTF-16 string that outlives the
+        // call, and a null security descriptor selects the default ACL.
+        let handle = unsafe {
+            CreateNamedPipeW(
+                wide.as_ptr(),
+                open_mode,
+                PIPE_TYPE_BYTE | PIPE_REJECT_REMOTE_CLIENTS,
+                PIPE_UNLIMITED_INSTANCES,
+                BUFFER_SIZE,
+                BUFFER_SIZE,
+                0,
+                std::ptr::null_mut(),
+            )
+        };
+        if handle as isize == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: the handle was just created and is owned by nothing else.
+        Ok(Pipe(unsafe { File::from_raw_handle(handle) }))
+    }
+
+    /// Waits for a client to connect to `pipe`.
+    pub fn accept(pipe: Pipe) -> io::Result<Connection> {
+        // SAFETY: the handle is a valid pipe handle owned by `pipe`.
+        let connected = unsafe { ConnectNamedPipe(pipe.0.as_raw_handle(), std::ptr::null_mut()) };
+        if connected == 0 {
+            let error = io::Error::last_os_error();
+            if error.raw_os_error() != Some(ERROR_PIPE_CONNECTED) {
+                return Err(error);
+            }
+        }
+        Ok(Connection(pipe.0))
+    }
+
+    impl Read for Connection {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let deadline = Instant::now() + super::REQUEST_TIMEOUT;
+            loop {
+                let mut available = 0u32;
+                // SAFETY: only the total-available out pointer is used;
+                // the others may be null.
+                let ok = unsafe {
+                    PeekNamedPipe(
+                        self.0.as_raw_handle(),
+                        std::ptr::null_mut(),
+                        0,
+                        std::ptr::null_mut(),
+                        &mut available,
+                        std::ptr::null_mut(),
+                    )
+                };
+                if ok == 0 {
+                    // The client closed its end.
+                    return Ok(0);
+                }
+                if available > 0 {
+                    return self.0.read(buf);
+                }
+                if Instant::now() >= deadline {
+                    return Err(io::ErrorKind::TimedOut.into());
+                }
+                std::thread::sleep(std::time::Duration::from_millis(10));
+            }
+        }
+    }
+
+    impl Write for Connection {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            // SAFETY: the handle is a valid pipe handle owned by `self`.
+            unsafe { FlushFileBuffers(self.0.as_raw_handle()) };
+            Ok(())
+        }
+    }
+
+    impl Drop for Connection {
+        fn drop(&mut self) {
+            // SAFETY: the handle is a valid pipe handle owned by `self`; the
+            // `File` closes it afterwards.
+            unsafe { DisconnectNamedPipe(self.0.as_raw_handle()) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_respond() {
+        let snapshot = json!({"cpu_cores": 8, "disks": [{"name": "/"}]});
+        assert_eq!(respond(Some(&snapshot), ""), snapshot.to_string());
+        assert_eq!(respond(Some(&snapshot), "GET disks"), r#"[{"name":"/"}]"#);
+        assert_eq!(
+            respond(Some(&snapshot), "GET gpus"),
+            r#"{"error":"unknown section 'gpus'"}"#
+        );
+    }
+}
@@ -0,0 +1,630 @@
+//! Running the interval loop as a Windows service (`service` subcommand).
+//!
+//! `service install ARGS...` registers the executable with the Service
+//! Control Manager (SCM) to start at boot as `RustGetSystemInfo.exe service
+//! run ARGS...`; `uninstall`, `start` and `stop` manage the registered
+//! service. `service run` is the entry point the SCM starts: it reports the
+//! service running, collects like a normal `--interval` run, and on a stop or
+//! shutdown request finishes the current sample, delivers anything still
+//! queued, and reports the service stopped. Other platforms have their own
+//! service managers, so the subcommand is only supported on Windows.
+
+use crate::cli::{Command, Options};
+use crate::AppError;
+
+/// Name the service is registered under.
+pub const SERVICE_NAME: &str = "RustGetSystemInfo";
+
+/// How long a stop may take before the service gives up on the current
+/// sample; the SCM waits 30 seconds by default.
+pub const STOP_DEADLINE: std::time::Duration = std::time::Duration::from_secs(25);
+
+/// Exit code for the subcommand on platforms other than Windows.
+#[cfg(not(windows))]
+pub const EXIT_UNSUPPORTED: i32 = 2;
+
+/// What the `service` subcommand does.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    /// Register the service with the given arguments
+    Install,
+    /// Remove the registered service
+    Uninstall,
+    /// Start the registered service
+    Start,
+    /// Stop the running service
+    Stop,
+    /// Run as the service; started by the SCM
+    Run,
+}
+
+impl Action {
+    /// Parses an action name.
+    pub fn parse(name: &str) -> Option<Action> {
+        match name {
+            "install" => Some(Action::Install),
+            "uninstall" => Some(Action::Uninstall),
+            "start" => Some(Action::Start),
+            "stop" => Some(Action::Stop),
+            "run" => Some(Action::Run),
+            _ => None,
+        }
+    }
+}
+
+/// Parses the arguments the service runs with.
+///
+/// # Errors
+///
+/// Returns `AppError::InvalidArgument` if the arguments do not parse, or do
+/// not describe an `--interval` report loop, which is what a service runs.
+pub fn daemon_options(args: &[String]) -> Result<Options, AppError> {
+    let options = Options::parse(args.iter().cloned())?;
+    if options.command != Command::Report || options.interval.is_none() {
+        return Err(AppError::InvalidArgument(
+            "a service runs the report loop: pass --interval and no subcommand".to_string(),
+        ));
+    }
+    Ok(options)
+}
+
+/// Builds the command line the SCM starts the service with: the executable
+/// followed by `service run` and `args`.
+///
+/// The executable is always quoted, since the SCM would otherwise try each
+/// space-separated prefix of an unquoted path. Arguments are quoted the way
+/// `CommandLineToArgvW` and the C runtime split them again: those with
+/// spaces, tabs or quotes are wrapped in quotes, inner quotes are escaped,
+/// and backslashes are doubled only where they precede a quote.
+pub fn command_line(executable: &str, args: &[String]) -> String {
+    // Paths cannot contain quotes
+    let mut line = format!("\"{}\"", executable);
+    for arg in ["service", "run"]
+        .into_iter()
+        .chain(args.iter().map(String::as_str))
+    {
+        line.push(' ');
+        line.push_str(&quote(arg));
+    }
+    line
+}
+
+/// Quotes one argument for `command_line`.
+fn quote(arg: &str) -> String {
+    if !arg.is_empty() && !arg.contains([' ', '\t', '"']) {
+        return arg.to_string();
+    }
+    let mut quoted = String::from('"');
+    let mut backslashes = 0;
+    for c in arg.chars() {
+        match c {
+            '\\' => backslashes += 1,
+            '"' => {
+                // Escape the backslashes before the quote, then the quote
+                quoted.push_str(&"\\".repeat(backslashes * 2 + 1));
+                backslashes = 0;
+            }
+            _ => {
+                quoted.push_str(&"\\".repeat(backslashes));
+                backslashes = 0;
+            }
+        }
+        if c != '\\' {
+            quoted.push(c);
+        }
+    }
+    // Backslashes before the closing quote are doubled
+    quoted.push_str(&"\\".repeat(backslashes * 2));
+    quoted.push('"');
+    quoted
+}
+
+/// Runs `action` with `args`, the service's arguments for `install` and
+/// `run`.
+///
+/// # Returns
+///
+/// The exit code.
+///
+/// # Errors
+///
+/// Returns `AppError::InvalidArgument` for invalid service arguments and
+/// `AppError::Service` if the SCM refuses the request.
+#[cfg(windows)]
+pub fn run(action: Action, args: &[String]) -> Result<i32, AppError> {
+    match action {
+        Action::Install => {
+            daemon_options(args)?;
+            let executable = std::env::current_exe().map_err(AppError::Service)?;
+            let line = command_line(&executable.to_string_lossy(), args);
+            scm::install(&line).map_err(AppError::Service)?;
+            println!("Installed service {}: {}", SERVICE_NAME, line);
+        }
+        Action::Uninstall => {
+            scm::uninstall().map_err(AppError::Service)?;
+            println!("Removed service {}", SERVICE_NAME);
+        }
+        Action::Start => {
+            scm::start().map_err(AppError::Service)?;
+            println!("Started service {}", SERVICE_NAME);
+        }
+        Action::Stop => {
+            scm::stop().map_err(AppError::Service)?;
+            println!("Stopped service {}", SERVICE_NAME);
+        }
+        Action::Run => {
+            let options = daemon_options(args)?;
+            // Services start in the system directory; relative output paths
+            // are kept next to the executable instead
+            if let Some(dir) = std::env::current_exe()
+                .ok()
+                .and_then(|path| path.parent().map(std::path::Path::to_path_buf))
+            {
+                std::env::set_current_dir(dir).map_err(AppError::Service)?;
+            }
+            return dispatcher::run(options);
+        }
+    }
+    Ok(0)
+}
+
+/// Reports that the subcommand is only supported on Windows.
+///
+/// # Returns
+///
+/// `EXIT_UNSUPPORTED`.
+#[cfg(not(windows))]
+pub fn run(action: Action, args: &[String]) -> Result<i32, AppError> {
+    let _ = (action, args);
+    eprintln!(
+        "Error: the service subcommand is only supported on Windows; use the platform's \
+         service manager (e.g. a systemd unit) to run the collector with --interval"
+    );
+    Ok(EXIT_UNSUPPORTED)
+}
+
+/// Win32 service types, states and controls.
+#[cfg(windows)]
+mod win32 {
+    pub type Handle = *mut std::ffi::c_void;
+
+    #[repr(C)]
+    #[derive(Default)]
+    pub struct ServiceStatus {
+        pub service_type: u32,
+        pub current_state: u32,
+        pub controls_accepted: u32,
+        pub win32_exit_code: u32,
+        pub service_specific_exit_code: u32,
+        pub check_point: u32,
+        pub wait_hint: u32,
+    }
+
+    #[repr(C)]
+    pub struct ServiceTableEntry {
+        pub name: *mut u16,
+        pub main: Option<extern "system" fn(u32, *mut *mut u16)>,
+    }
+
+    pub const SERVICE_WIN32_OWN_PROCESS: u32 = 0x10;
+    pub const SERVICE_AUTO_START: u32 = 2;
+    pub const SERVICE_ERROR_NORMAL: u32 = 1;
+
+    pub const SERVICE_STOPPED: u32 = 1;
+    pub const SERVICE_START_PENDING: u32 = 2;
+    pub const SERVICE_STOP_PENDING: u32 = 3;
+    pub const SERVICE_RUNNING: u32 = 4;
+
+    pub const SERVICE_ACCEPT_STOP: u32 = 1;
+    pub const SERVICE_ACCEPT_SHUTDOWN: u32 = 4;
+
+    pub const SERVICE_CONTROL_STOP: u32 = 1;
+    pub const SERVICE_CONTROL_INTERROGATE: u32 = 4;
+    pub const SERVICE_CONTROL_SHUTDOWN: u32 = 5;
+
+    pub const SC_MANAGER_CONNECT: u32 = 0x1;
+    pub const SC_MANAGER_CREATE_SERVICE: u32 = 0x2;
+    pub const SERVICE_QUERY_STATUS: u32 = 0x4;
+    pub const SERVICE_START: u32 = 0x10;
+    pub const SERVICE_STOP: u32 = 0x20;
+    pub const SERVICE_ALL_ACCESS: u32 = 0xF01FF;
+    pub const DELETE: u32 = 0x10000;
+
+    pub const NO_ERROR: u32 = 0;
+    pub const ERROR_CALL_NOT_IMPLEMENTED: u32 = 120;
+    pub const ERROR_SERVICE_SPECIFIC_ERROR: u32 = 1066;
+
+    #[link(name = "advapi32")]
+    extern "system" {
+        pub fn OpenSCManagerW(machine: *const u16, database: *const u16, access: u32) -> Handle;
+        pub fn CreateServiceW(
+            manager: Handle,
+            name: *const u16,
+            display_name: *const u16,
+            access: u32,
+            service_type: u32,
+            start_type: u32,
+            error_control: u32,
+            binary_path: *const u16,
+            load_order_group: *const u16,
+            tag_id: *mut u32,
+            dependencies: *const u16,
+            start_name: *const u16,
+            password: *const u16,
+        ) -> Handle;
+        pub fn OpenServiceW(manager: Handle, name: *const u16, access: u32) -> Handle;
+        pub fn DeleteService(service: Handle) -> i32;
+        pub fn StartServiceW(service: Handle, argc: u32, argv: *const *const u16) -> i32;
+        pub fn ControlService(service: Handle, control: u32, status: *mut ServiceStatus) -> i32;
+        pub fn QueryServiceStatus(service: Handle, status: *mut ServiceStatus) -> i32;
+        pub fn CloseServiceHandle(handle: Handle) -> i32;
+        pub fn StartServiceCtrlDispatcherW(table: *const ServiceTableEntry) -> i32;
+        pub fn RegisterServiceCtrlHandlerExW(
+            name: *const u16,
+            handler: extern "system" fn(u32, u32, Handle, Handle) -> u32,
+            context: Handle,
+        ) -> Handle;
+        pub fn SetServiceStatus(status_handle: Handle, status: *const ServiceStatus) -> i32;
+    }
+
+    /// Encodes `text` as a NUL-terminated UTF-16 string.
+    pub fn wide(text: &str) -> Vec<u16> {
+        text.encode_utf16().chain(std::iter::once(0)).collect()
+    }
+}
+
+/// Registering and controlling the service through the SCM.
+#[cfg(windows)]
+mod scm {
+    use super::win32::*;
+    use super::{SERVICE_NAME, STOP_DEADLINE};
+    use std::io;
+    use std::time::{Duration, Instant};
+
+    /// An SCM handle, closed on drop.
+    struct Owned(Handle);
+
+    impl Owned {
+        fn new(handle: Handle) -> io::Result<Owned> {
+            if handle.is_null() {
+                Err(io::Error::last_os_error())
+            } else {
+                Ok(Owned(handle))
+            }
+        }
+    }
+
+    impl Drop for Owned {
+        fn drop(&mut self) {
+            // SAFETY: the handle is open and closed only here.
+            unsafe {
+                CloseServiceHandle(self.0);
+            }
+        }
+    }
+
+    fn manager(access: u32) -> io::Result<Owned> {
+        // SAFETY: null selects the local machine's active database.
+        Owned::new(unsafe { OpenSCManagerW(std::ptr::null(), std::ptr::null(), access) })
+    }
+
+    fn service(access: u32) -> io::Result<Owned> {
+        let manager = manager(SC_MANAGER_CONNECT)?;
+        let name = wide(SERVICE_NAME);
+        // SAFETY: `name` is NUL-terminated and `manager` is open.
+        Owned::new(unsafe { OpenServiceW(manager.0, name.as_ptr(), access) })
+    }
+
+    fn check(ok: i32) -> io::Result<()> {
+        if ok == 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(())
+        }
+    }
+
+    fn state(service: &Owned) -> io::Result<u32> {
+        let mut status = ServiceStatus::default();
+        // SAFETY: `service` is open and `status` is a valid output.
+        check(unsafe { QueryServiceStatus(service.0, &mut status) })?;
+        Ok(status.current_state)
+    }
+
+    /// Registers the service to start at boot with `command_line`.
+    pub fn install(command_line: &str) -> io::Result<()> {
+        let manager = manager(SC_MANAGER_CONNECT | SC_MANAGER_CREATE_SERVICE)?;
+        let name = wide(SERVICE_NAME);
+        let binary_path = wide(command_line);
+        // SAFETY: the strings are NUL-terminated; null optional arguments
+        // select the defaults, running as LocalSystem.
+        Owned::new(unsafe {
+            CreateServiceW(
+                manager.0,
+                name.as_ptr(),
+                name.as_ptr(),
+                SERVICE_ALL_ACCESS,
+                SERVICE_WIN32_OWN_PROCESS,
+                SERVICE_AUTO_START,
+                SERVICE_ERROR_NORMAL,
+                binary_path.as_ptr(),
+                std::ptr::null(),
+                std::ptr::null_mut(),
+                std::ptr::null(),
+                std::ptr::null(),
+                std::ptr::null(),
+            )
+        })
+        .map(drop)
+    }
+
+    /// Marks the service for deletion once it has stopped.
+    pub fn uninstall() -> io::Result<()> {
+        let service = service(DELETE)?;
+        // SAFETY: `service` is open with DELETE access.
+        check(unsafe { DeleteService(service.0) })
+    }
+
+    pub fn start() -> io::Result<()> {
+        let service = service(SERVICE_START)?;
+        // SAFETY: `service` is open with SERVICE_START access; no arguments.
+        check(unsafe { StartServiceW(service.0, 0, std::ptr::null()) })
+    }
+
+    /// Asks the service to stop and waits until it has.
+    pub fn stop() -> io::Result<()> {
+        let service = service(SERVICE_STOP | SERVICE_QUERY_STATUS)?;
+        let mut status = ServiceStatus::default();
+        // SAFETY: `service` is open with SERVICE_STOP access.
+        check(unsafe { ControlService(service.0, SERVICE_CONTROL_STOP, &mut status) })?;
+        // The service's own deadline plus time to report it
+        let deadline = Instant::now() + STOP_DEADLINE + Duration::from_secs(5);
+        while state(&service)? != SERVICE_STOPPED {
+            if Instant::now() >= deadline {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "the service did not stop in time",
+                ));
+            }
+            std::thread::sleep(Duration::from_millis(250));
+        }
+        Ok(())
+    }
+}
+
+/// The service entry point and its control handler.
+#[cfg(windows)]
+mod dispatcher {
+    use super::win32::*;
+    use super::{SERVICE_NAME, STOP_DEADLINE};
+    use crate::cli::Options;
+    use crate::AppError;
+    use std::sync::atomic::{AtomicPtr, AtomicU32, Ordering};
+    use std::sync::{Mutex, OnceLock};
+
+    /// Options of the report loop, handed to `service_main`.
+    static OPTIONS: OnceLock<Options> = OnceLock::new();
+    /// Status handle from `RegisterServiceCtrlHandlerExW`.
+    static STATUS_HANDLE: AtomicPtr<std::ffi::c_void> = AtomicPtr::new(std::ptr::null_mut());
+    /// Current state, so an interrogation can repeat it.
+    static STATE: AtomicU32 = AtomicU32::new(SERVICE_START_PENDING);
+    /// Error the report loop returned, if any.
+    static ERROR: Mutex<Option<AppError>> = Mutex::new(None);
+
+    /// Connects to the SCM and runs the report loop as the service.
+    pub fn run(options: Options) -> Result<i32, AppError> {
+        let _ = OPTIONS.set(options);
+        let mut name = wide(SERVICE_NAME);
+        let table = [
+            ServiceTableEntry {
+                name: name.as_mut_ptr(),
+                main: Some(service_main),
+            },
+            ServiceTableEntry {
+                name: std::ptr::null_mut(),
+                main: None,
+            },
+        ];
+        // SAFETY: the table is terminated by a null entry and outlives the
+        // call, which returns once the service has stopped.
+        if unsafe { StartServiceCtrlDispatcherW(table.as_ptr()) } == 0 {
+            return Err(AppError::Service(std::io::Error::last_os_error()));
+        }
+        match ERROR.lock().unwrap_or_else(|e| e.into_inner()).take() {
+            Some(error) => Err(error),
+            None => Ok(0),
+        }
+    }
+
+    /// Reports `state` to the SCM, with the controls accepted while running
+    /// and `exit_code` once stopped.
+    fn report(state: u32, exit_code: u32, wait_hint: u32) {
+        STATE.store(state, Ordering::SeqCst);
+        let status = ServiceStatus {
+            service_type: SERVICE_WIN32_OWN_PROCESS,
+            current_state: state,
+            controls_accepted: if state == SERVICE_RUNNING {
+                SERVICE_ACCEPT_STOP | SERVICE_ACCEPT_SHUTDOWN
+            } else {
+                0
+            },
+            win32_exit_code: if exit_code == 0 {
+                NO_ERROR
+            } else {
+                ERROR_SERVICE_SPECIFIC_ERROR
+            },
+            service_specific_exit_code: exit_code,
+            check_point: u32::from(state == SERVICE_START_PENDING || state == SERVICE_STOP_PENDING),
+            wait_hint,
+        };
+        // SAFETY: the handle came from RegisterServiceCtrlHandlerExW and
+        // `status` is a valid SERVICE_STATUS.
+        unsafe {
+            SetServiceStatus(STATUS_HANDLE.load(Ordering::SeqCst), &status);
+        }
+    }
+
+    extern "system" fn service_main(_argc: u32, _argv: *mut *mut u16) {
+        let name = wide(SERVICE_NAME);
+        // SAFETY: `name` is NUL-terminated and `handle_control` lives for the
+        // whole process.
+        let handle = unsafe {
+            RegisterServiceCtrlHandlerExW(name.as_ptr(), handle_control, std::ptr::null_mut())
+        };
+        if handle.is_null() {
+            return;
+        }
+        STATUS_HANDLE.store(handle, Ordering::SeqCst);
+        report(SERVICE_START_PENDING, 0, 5_000);
+        let Some(options) = OPTIONS.get() else {
+            report(SERVICE_STOPPED, 1, 0);
+            return;
+        };
+        report(SERVICE_RUNNING, 0, 0);
+        let error = crate::run(options).err();
+        let exit_code = error.as_ref().map_or(0, |e| e.exit_code() as u32);
+        *ERROR.lock().unwrap_or_else(|e| e.into_inner()) = error;
+        report(SERVICE_STOPPED, exit_code, 0);
+    }
+
+    extern "system" fn handle_control(
+        control: u32,
+        _event: u32,
+        _data: Handle,
+        _context: Handle,
+    ) -> u32 {
+        match control {
+            SERVICE_CONTROL_STOP | SERVICE_CONTROL_SHUTDOWN => {
+                report(SERVICE_STOP_PENDING, 0, STOP_DEADLINE.as_millis() as u32);
+                // The loop finishes its sample, flushes and returns
+                crate::shutdown::request();
+                // A sample still stuck at the deadline is abandoned, and the
+                // stop reported here rather than left for the SCM to time out
+                std::thread::spawn(|| {
+                    std::thread::sleep(STOP_DEADLINE);
+                    if STATE.load(Ordering::SeqCst) != SERVICE_STOPPED {
+                        report(SERVICE_STOPPED, 1, 0);
+                        std::process::exit(1);
+                    }
+                });
+                NO_ERROR
+            }
+            SERVICE_CONTROL_INTERROGATE => {
+                report(STATE.load(Ordering::SeqCst), 0, 0);
+                NO_ERROR
+            }
+            _ => ERROR_CALL_NOT_IMPLEMENTED,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_action_parse() {
+        assert_eq!(Action::parse("install"), Some(Action::Install));
+        assert_eq!(Action::parse("run"), Some(Action::Run));
+        assert_eq!(Action::parse("restart"), None);
+    }
+
+    #[test]
+    fn test_daemon_options() {
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        let options = daemon_options(&args(&["--interval", "60", "--redact", "hostname"])).unwrap();
+        assert_eq!(options.interval, Some(std::time::Duration::from_secs(60)));
+        assert_eq!(options.redact, [crate::redact::Field::Hostname]);
+        assert!(daemon_options(&args(&["-q"])).is_err());
+        assert!(daemon_options(&args(&["quick", "load1", "--interval", "5"])).is_err());
+        assert!(daemon_options(&args(&["--interval"])).is_err());
+    }
+
+    #[test]
+    fn test_command_line() {
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            command_line(
+                r"C:\Program Files\sysinfo\RustGetSystemInfo.exe",
+                &args(&["--interval", "60"])
+            ),
+            r#""C:\Program Files\sysinfo\RustGetSystemInfo.exe" service run --interval 60"#
+        );
+        assert_eq!(
+            command_line(
+                r"C:\tools\RustGetSystemInfo.exe",
+                &args(&[
+                    "--output-json",
+                    r"C:\ProgramData\sysinfo\out dir\",
+                    "--check-expr",
+                    r#"warn: disks["C:\"].available_space < 5GB"#,
+                    "",
+                ])
+            ),
+            r#""C:\tools\RustGetSystemInfo.exe" service run --output-json "C:\ProgramData\sysinfo\out dir\\" --check-expr "warn: disks[\"C:\\\"].available_space < 5GB" """#
+        );
+    }
+
+    /// Splits a command line the way `CommandLineToArgvW` does for the
+    /// arguments after the executable.
+    fn split(line: &str) -> Vec<String> {
+        let mut args = Vec::new();
+        let mut chars = line.chars().peekable();
+        loop {
+            while chars.next_if(|c| *c == ' ' || *c == '\t').is_some() {}
+            if chars.peek().is_none() {
+                return args;
+            }
+            let (mut arg, mut quoted) = (String::new(), false);
+            while let Some(c) = chars.next() {
+                match c {
+                    '\\' => {
+                        let mut backslashes = 1;
+                        while chars.next_if_eq(&'\\').is_some() {
+                            backslashes += 1;
+                        }
+                        if chars.peek() == Some(&'"') {
+                            arg.push_str(&"\\".repeat(backslashes / 2));
+                            if backslashes % 2 == 1 {
+                                arg.push(chars.next().unwrap());
+                            }
+                        } else {
+                            arg.push_str(&"\\".repeat(backslashes));
+                        }
+                    }
+                    '"' => quoted = !quoted,
+                    ' ' | '\t' if !quoted => break,
+                    _ => arg.push(c),
+                }
+            }
+            args.push(arg);
+        }
+    }
+
+    #[test]
+    fn test_command_line_round_trip() {
+        let args: Vec<String> = [
+            "--interval",
+            "60",
+            "",
+            " ",
+            "a\tb",
+            r"C:\dir\",
+            r"C:\dir with space\",
+            r#"say "hi""#,
+            r#"\"quoted\""#,
+            r"\\server\share",
+            r#"trailing\\"#,
+            "ünïcödé",
+        ]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+        let line = command_line(r"C:\Program Files\sysinfo\RustGetSystemInfo.exe", &args);
+        let rest = line
+            .strip_prefix(r#""C:\Program Files\sysinfo\RustGetSystemInfo.exe" "#)
+            .unwrap();
+        let mut expected = vec!["service".to_string(), "run".to_string()];
+        expected.extend(args);
+        assert_eq!(split(rest), expected);
+    }
+}
@@ -0,0 +1,454 @@
+//! Memory-mapped snapshot for high-frequency local readers (`--shm <PATH>`).
+//!
+//! In interval mode the latest snapshot is published into a fixed-size file,
+//! normally on a tmpfs such as `/dev/shm`, that readers map into memory and
+//! poll without any syscalls or parsing of a transport. The file layout is:
+//!
+//! ```text
+//! offset  size  field
+//!      0     8  magic "SYSINFO\0"
+//!      8     4  schema version (currently 1)
+//!     12     4  header size in bytes (64)
+//!     16     8  sequence number
+//!     24     8  timestamp of the snapshot, milliseconds since the Unix epoch
+//!     32     4  payload length in bytes
+//!     36     4  payload capacity in bytes
+//!     40    24  reserved (zero)
+//!     64     N  payload: the snapshot as compact JSON
+//! ```
+//!
+//! Integers are in native byte order. Updates use a seqlock: the writer makes
+//! the sequence number odd, writes the timestamp, length and payload, then
+//! makes it even again. A reader loads the sequence number, skips the attempt
+//! if it is odd, copies the fields, and loads it again; the copy is only
+//! consistent if both loads saw the same even value. Otherwise it retries.
+//! The sequence number only ever increases, including when a new writer
+//! takes over an existing file. A timestamp of zero means nothing has been
+//! published yet.
+//!
+//! Mapping uses `mmap` from the C library directly and is available on 64-bit
+//! Unix platforms only.
+
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{fence, AtomicU32, AtomicU64, AtomicU8, Ordering};
+
+const MAGIC: &[u8; 8] = b"SYSINFO\0";
+const SCHEMA_VERSION: u32 = 1;
+const HEADER_SIZE: usize = 64;
+
+const VERSION_OFFSET: usize = 8;
+const HEADER_SIZE_OFFSET: usize = 12;
+const SEQUENCE_OFFSET: usize = 16;
+const TIMESTAMP_OFFSET: usize = 24;
+const LENGTH_OFFSET: usize = 32;
+const CAPACITY_OFFSET: usize = 36;
+
+/// Payload capacity of files created by `--shm`.
+pub const DEFAULT_CAPACITY: usize = 1024 * 1024;
+
+/// How many torn or in-progress reads a reader tolerates before giving up.
+const MAX_READ_ATTEMPTS: u32 = 10_000;
+
+/// A snapshot read back from the shared file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    /// Sequence number the snapshot was published under (always even)
+    pub sequence: u64,
+    /// Milliseconds since the Unix epoch when it was published
+    pub timestamp_ms: u64,
+    /// The snapshot as JSON
+    pub payload: Vec<u8>,
+}
+
+/// A shared mapping of a snapshot file.
+struct Region {
+    ptr: *mut u8,
+    len: usize,
+    // Kept open for the lifetime of the mapping.
+    _file: File,
+}
+
+// SAFETY: the mapping is only accessed through atomics, so it can be shared
+// and sent between threads like any other atomic memory.
+unsafe impl Send for Region {}
+unsafe impl Sync for Region {}
+
+impl Region {
+    fn u32_at(&self, offset: usize) -> &AtomicU32 {
+        debug_assert!(offset.is_multiple_of(4) && offset + 4 <= self.len);
+        // SAFETY: the offset is in bounds and aligned (the mapping is page
+        // aligned), and the memory lives as long as `self`.
+        unsafe { &*(self.ptr.add(offset) as *const AtomicU32) }
+    }
+
+    fn u64_at(&self, offset: usize) -> &AtomicU64 {
+        debug_assert!(offset.is_multiple_of(8) && offset + 8 <= self.len);
+        // SAFETY: as for `u32_at`.
+        unsafe { &*(self.ptr.add(offset) as *const AtomicU64) }
+    }
+
+    fn bytes(&self, offset: usize, len: usize) -> &[AtomicU8] {
+        assert!(offset + len <= self.len);
+        // SAFETY: the range is in bounds and the memory lives as long as `self`.
+        unsafe { std::slice::from_raw_parts(self.ptr.add(offset) as *const AtomicU8, len) }
+    }
+}
+
+impl Drop for Region {
+    fn drop(&mut self) {
+        sys::unmap(self.ptr, self.len);
+    }
+}
+
+/// Publishes snapshots into a shared file.
+pub struct Writer {
+    region: Region,
+    capacity: usize,
+}
+
+impl Writer {
+    /// Creates (or takes over) the snapshot file at `path` with room for
+    /// `capacity` payload bytes, and initializes its header.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be created, sized, or mapped.
+    pub fn create(path: &Path, capacity: usize) -> io::Result<Writer> {
+        let capacity_field = u32::try_from(capacity)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "capacity too large"))?;
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        let len = HEADER_SIZE + capacity;
+        file.set_len(len as u64)?;
+        let region = sys::map(file, len, true)?;
+
+        // Mark the contents as in progress while the header is rewritten.
+        let sequence = region.u64_at(SEQUENCE_OFFSET);
+        let start = sequence.load(Ordering::Relaxed) | 1;
+        sequence.store(start, Ordering::Relaxed);
+        fence(Ordering::Release);
+        for (byte, &value) in region.bytes(0, MAGIC.len()).iter().zip(MAGIC) {
+            byte.store(value, Ordering::Relaxed);
+        }
+        region
+            .u32_at(VERSION_OFFSET)
+            .store(SCHEMA_VERSION, Ordering::Relaxed);
+        region
+            .u32_at(HEADER_SIZE_OFFSET)
+            .store(HEADER_SIZE as u32, Ordering::Relaxed);
+        region
+            .u32_at(CAPACITY_OFFSET)
+            .store(capacity_field, Ordering::Relaxed);
+        region.u32_at(LENGTH_OFFSET).store(0, Ordering::Relaxed);
+        region.u64_at(TIMESTAMP_OFFSET).store(0, Ordering::Relaxed);
+        sequence.store(start + 1, Ordering::Release);
+
+        Ok(Writer { region, capacity })
+    }
+
+    /// Publishes `payload` with `timestamp_ms`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error, leaving the previous snapshot in place, if the
+    /// payload is larger than the file's capacity.
+    pub fn publish(&self, payload: &[u8], timestamp_ms: u64) -> io::Result<()> {
+        if payload.len() > self.capacity {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "snapshot is {} bytes but the file holds at most {}",
+                    payload.len(),
+                    self.capacity
+                ),
+            ));
+        }
+
+        let sequence = self.region.u64_at(SEQUENCE_OFFSET);
+        let odd = sequence.load(Ordering::Relaxed) + 1;
+        sequence.store(odd, Ordering::Relaxed);
+        fence(Ordering::Release);
+
+        self.region
+            .u64_at(TIMESTAMP_OFFSET)
+            .store(timestamp_ms, Ordering::Relaxed);
+        self.region
+            .u32_at(LENGTH_OFFSET)
+            .store(payload.len() as u32, Ordering::Relaxed);
+        for (byte, &value) in self
+            .region
+            .bytes(HEADER_SIZE, payload.len())
+            .iter()
+            .zip(payload)
+        {
+            byte.store(value, Ordering::Relaxed);
+        }
+
+        sequence.store(odd + 1, Ordering::Release);
+        Ok(())
+    }
+}
+
+/// Reads a consistent snapshot from a shared file.
+pub struct Reader {
+    region: Region,
+}
+
+impl Reader {
+    /// Maps the snapshot file at `path` for reading.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be opened or mapped, or is not a
+    /// snapshot file of a supported schema version.
+    pub fn open(path: &Path) -> io::Result<Reader> {
+        let file = File::open(path)?;
+        let len = file.metadata()?.len() as usize;
+        let invalid =
+            |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+        if len < HEADER_SIZE {
+            return Err(invalid("file is too small to be a snapshot file"));
+        }
+        let region = sys::map(file, len, false)?;
+
+        let magic: Vec<u8> = region
+            .bytes(0, MAGIC.len())
+            .iter()
+            .map(|byte| byte.load(Ordering::Relaxed))
+            .collect();
+        if magic != MAGIC {
+            return Err(invalid("not a snapshot file"));
+        }
+        let version = region.u32_at(VERSION_OFFSET).load(Ordering::Relaxed);
+        if version != SCHEMA_VERSION {
+            return Err(invalid(&format!("unsupported schema version {}", version)));
+        }
+        let capacity = region.u32_at(CAPACITY_OFFSET).load(Ordering::Relaxed) as usize;
+        if HEADER_SIZE + capacity > len {
+            return Err(invalid("capacity exceeds the file size"));
+        }
+        Ok(Reader { region })
+    }
+
+    /// Reads the current snapshot, retrying while the writer is mid-update.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(None)` if nothing has been published yet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no consistent copy could be taken after
+    /// `MAX_READ_ATTEMPTS` tries, or the header is corrupt.
+    pub fn read(&self) -> io::Result<Option<Snapshot>> {
+        let sequence = self.region.u64_at(SEQUENCE_OFFSET);
+        let capacity = self.region.u32_at(CAPACITY_OFFSET).load(Ordering::Relaxed) as usize;
+
+        for _ in 0..MAX_READ_ATTEMPTS {
+            let before = sequence.load(Ordering::Acquire);
+            if before % 2 == 1 {
+                std::thread::yield_now();
+                continue;
+            }
+
+            let timestamp_ms = self.region.u64_at(TIMESTAMP_OFFSET).load(Ordering::Relaxed);
+            // A torn length is caught by the sequence check below, but it
+            // must not index out of bounds first.
+            let length =
+                (self.region.u32_at(LENGTH_OFFSET).load(Ordering::Relaxed) as usize).min(capacity);
+            let payload: Vec<u8> = self
+                .region
+                .bytes(HEADER_SIZE, length)
+                .iter()
+                .map(|byte| byte.load(Ordering::Relaxed))
+                .collect();
+
+            fence(Ordering::Acquire);
+            if sequence.load(Ordering::Relaxed) == before {
+                if timestamp_ms == 0 {
+                    return Ok(None);
+                }
+                return Ok(Some(Snapshot {
+                    sequence: before,
+                    timestamp_ms,
+                    payload,
+                }));
+            }
+        }
+        Err(io::Error::new(
+            io::ErrorKind::WouldBlock,
+            "snapshot kept changing while being read",
+        ))
+    }
+}
+
+/// `mmap` bindings for 64-bit Unix, where `off_t` is 64 bits wide.
+#[cfg(all(unix, target_pointer_width = "64"))]
+mod sys {
+    use super::Region;
+    use std::ffi::c_void;
+    use std::fs::File;
+    use std::io;
+    use std::os::unix::io::AsRawFd;
+
+    const PROT_READ: i32 = 0x1;
+    const PROT_WRITE: i32 = 0x2;
+    const MAP_SHARED: i32 = 0x1;
+
+    extern "C" {
+        fn mmap(
+            addr: *mut c_void,
+            len: usize,
+            prot: i32,
+            flags: i32,
+            fd: i32,
+            offset: i64,
+        ) -> *mut c_void;
+        fn munmap(addr: *mut c_void, len: usize) -> i32;
+    }
+
+    pub fn map(file: File, len: usize, writable: bool) -> io::Result<Region> {
+        let prot = if writable {
+            PROT_READ | PROT_WRITE
+        } else {
+            PROT_READ
+        };
+        // SAFETY: a fresh shared mapping of an open file descriptor; the
+        // result is checked before use.
+        let ptr = unsafe {
+            mmap(
+                std::ptr::null_mut(),
+                len,
+                prot,
+                MAP_SHARED,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if ptr as isize == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Region {
+            ptr: ptr as *mut u8,
+            len,
+            _file: file,
+        })
+    }
+
+    pub fn unmap(ptr: *mut u8, len: usize) {
+        // SAFETY: `ptr` and `len` describe a mapping created by `map` that is
+        // no longer referenced.
+        unsafe { munmap(ptr as *mut c_void, len) };
+    }
+}
+
+/// Fallback for platforms without the `mmap` bindings.
+#[cfg(not(all(unix, target_pointer_width = "64")))]
+mod sys {
+    use super::Region;
+    use std::fs::File;
+    use std::io;
+
+    pub fn map(_file: File, _len: usize, _writable: bool) -> io::Result<Region> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "memory-mapped snapshots are only supported on 64-bit Unix",
+        ))
+    }
+
+    pub fn unmap(_ptr: *mut u8, _len: usize) {}
+}
+
+#[cfg(all(test, unix, target_pointer_width = "64"))]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Arc;
+
+    /// The payload published as snapshot `n`: its length and every byte
+    /// depend on `n`, so a mix of two snapshots is detectable.
+    fn payload(n: u64) -> Vec<u8> {
+        vec![n as u8; 100 + (n % 1000) as usize]
+    }
+
+    #[test]
+    fn test_writer_reader() {
+        let path = std::env::temp_dir().join(format!("shm-basic-{}", std::process::id()));
+        let writer = Writer::create(&path, 4096).unwrap();
+        let reader = Reader::open(&path).unwrap();
+        assert_eq!(reader.read().unwrap(), None);
+
+        writer
+            .publish(b"{\"cpu_cores\":8}", 1_700_000_000_000)
+            .unwrap();
+        let snapshot = reader.read().unwrap().unwrap();
+        assert_eq!(snapshot.payload, b"{\"cpu_cores\":8}");
+        assert_eq!(snapshot.timestamp_ms, 1_700_000_000_000);
+        assert_eq!(snapshot.sequence % 2, 0);
+
+        // Too large a payload leaves the previous snapshot in place
+        assert!(writer.publish(&[b'x'; 4097], 1).is_err());
+        assert_eq!(reader.read().unwrap().unwrap(), snapshot);
+
+        // A new writer keeps the sequence number increasing
+        drop(writer);
+        let writer = Writer::create(&path, 4096).unwrap();
+        assert_eq!(reader.read().unwrap(), None);
+        writer.publish(b"{}", 2).unwrap();
+        assert!(reader.read().unwrap().unwrap().sequence > snapshot.sequence);
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(Reader::open(&path).is_err());
+    }
+
+    #[test]
+    fn test_concurrent_readers() {
+        const PUBLISHES: u64 = 50_000;
+
+        let path = std::env::temp_dir().join(format!("shm-stress-{}", std::process::id()));
+        let writer = Writer::create(&path, 4096).unwrap();
+        let done = Arc::new(AtomicBool::new(false));
+
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let reader = Reader::open(&path).unwrap();
+                let done = Arc::clone(&done);
+                std::thread::spawn(move || {
+                    let mut last_sequence = 0;
+                    while !done.load(Ordering::Relaxed) {
+                        match reader.read() {
+                            Ok(Some(snapshot)) => {
+                                assert_eq!(snapshot.sequence % 2, 0);
+                                assert!(snapshot.sequence >= last_sequence);
+                                assert_eq!(snapshot.payload, payload(snapshot.timestamp_ms));
+                                last_sequence = snapshot.sequence;
+                            }
+                            Ok(None) => {}
+                            // A reader may lose the race many times in a row
+                            Err(e) => assert_eq!(e.kind(), io::ErrorKind::WouldBlock),
+                        }
+                    }
+                    // Once the writer is done the last snapshot is stable
+                    let last = reader.read().unwrap().unwrap();
+                    assert_eq!(last.timestamp_ms, PUBLISHES);
+                    assert!(last.sequence >= last_sequence);
+                })
+            })
+            .collect();
+
+        for n in 1..=PUBLISHES {
+            writer.publish(&payload(n), n).unwrap();
+        }
+        done.store(true, Ordering::Relaxed);
+        for reader in readers {
+            reader.join().unwrap();
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,99 @@
+//! Graceful shutdown on Ctrl-C and SIGTERM.
+//!
+//! Once `install` has been called, an interrupt only sets a flag, so the
+//! interval loop can finish its current sample, deliver anything still
+//! queued, and return normally. Without `install` the default handlers stay
+//! in place and an interrupt ends the process immediately.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// Longest `sleep` waits between checks of the shutdown flag.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Returns whether a shutdown has been requested.
+pub fn requested() -> bool {
+    REQUESTED.load(Ordering::SeqCst)
+}
+
+/// Requests a shutdown, as an interrupt would; used by the Windows service
+/// control handler.
+#[cfg_attr(not(windows), allow(dead_code))]
+pub fn request() {
+    REQUESTED.store(true, Ordering::SeqCst);
+}
+
+/// Sleeps for `duration`, waking early if a shutdown is requested.
+///
+/// # Returns
+///
+/// `false` if the sleep was cut short by a shutdown request.
+pub fn sleep(duration: Duration) -> bool {
+    sleep_until(Instant::now() + duration)
+}
+
+/// Sleeps until `deadline`, waking early if a shutdown is requested.
+///
+/// # Returns
+///
+/// `false` if the sleep was cut short by a shutdown request.
+pub fn sleep_until(deadline: Instant) -> bool {
+    loop {
+        if requested() {
+            return false;
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            return true;
+        }
+        std::thread::sleep((deadline - now).min(POLL_INTERVAL));
+    }
+}
+
+/// Routes SIGINT and SIGTERM to the shutdown flag.
+#[cfg(unix)]
+pub fn install() {
+    const SIGINT: i32 = 2;
+    const SIGTERM: i32 = 15;
+
+    extern "C" {
+        fn signal(signum: i32, handler: extern "C" fn(i32)) -> usize;
+    }
+
+    extern "C" fn handle(_signum: i32) {
+        // Storing to an atomic is async-signal-safe
+        REQUESTED.store(true, Ordering::SeqCst);
+    }
+
+    // SAFETY: `handle` only performs an atomic store.
+    unsafe {
+        signal(SIGINT, handle);
+        signal(SIGTERM, handle);
+    }
+}
+
+/// Routes Ctrl-C, Ctrl-Break and console close to the shutdown flag.
+#[cfg(windows)]
+pub fn install() {
+    extern "system" {
+        fn SetConsoleCtrlHandler(handler: extern "system" fn(u32) -> i32, add: i32) -> i32;
+    }
+
+    extern "system" fn handle(_event: u32) -> i32 {
+        REQUESTED.store(true, Ordering::SeqCst);
+        1
+    }
+
+    // SAFETY: `handle` only performs an atomic store.
+    unsafe {
+        SetConsoleCtrlHandler(handle, 1);
+    }
+}
+
+/// Routes interrupts to the shutdown flag.
+///
+/// Not supported on this platform, so interrupts keep ending the process.
+#[cfg(not(any(unix, windows)))]
+pub fn install() {}
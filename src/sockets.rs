@@ -0,0 +1,386 @@
+//! Socket state summary (`--sockets-summary`).
+//!
+//! Counts TCP sockets per state, UDP sockets, and the remote addresses with
+//! the most TCP connections, without listing individual connections. On
+//! Linux the tables come from `/proc/net/tcp`, `tcp6`, `udp` and `udp6`; on
+//! Windows from `GetExtendedTcpTable` and `GetExtendedUdpTable`. Other
+//! platforms report no summary.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::net::IpAddr;
+
+/// Number of remote addresses listed in `top_remote_addresses`.
+const TOP_REMOTES: usize = 5;
+
+/// Socket counts for the whole host.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct SocketSummary {
+    /// TCP sockets (IPv4 and IPv6) by state, e.g. "ESTABLISHED" or "TIME_WAIT"
+    pub tcp_states: BTreeMap<String, u64>,
+    /// UDP sockets (IPv4 and IPv6)
+    pub udp_sockets: u64,
+    /// Remote addresses with the most TCP connections, most first
+    pub top_remote_addresses: Vec<RemoteCount>,
+}
+
+/// The number of TCP connections to one remote address.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RemoteCount {
+    /// Remote IP address, without the port
+    pub address: String,
+    pub connections: u64,
+}
+
+/// Accumulates sockets as the tables are read.
+#[derive(Default)]
+struct Tally {
+    states: BTreeMap<String, u64>,
+    udp_sockets: u64,
+    remotes: HashMap<IpAddr, u64>,
+}
+
+impl Tally {
+    /// Counts one TCP socket in `state` connected to `remote`.
+    ///
+    /// Listening sockets and sockets without a remote address (`0.0.0.0` or
+    /// `::`) do not count towards the remote addresses.
+    fn add_tcp(&mut self, state: &str, remote: IpAddr) {
+        *self.states.entry(state.to_string()).or_default() += 1;
+        if state != "LISTEN" && !remote.is_unspecified() {
+            *self.remotes.entry(remote).or_default() += 1;
+        }
+    }
+
+    /// Finishes the summary, ranking remote addresses by connection count
+    /// and then by address.
+    fn finish(self) -> SocketSummary {
+        let mut remotes: Vec<(IpAddr, u64)> = self.remotes.into_iter().collect();
+        remotes.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        SocketSummary {
+            tcp_states: self.states,
+            udp_sockets: self.udp_sockets,
+            top_remote_addresses: remotes
+                .into_iter()
+                .take(TOP_REMOTES)
+                .map(|(address, connections)| RemoteCount {
+                    address: address.to_string(),
+                    connections,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Summarizes the sockets of the host.
+#[cfg(target_os = "linux")]
+pub fn query() -> Option<SocketSummary> {
+    let mut tally = Tally::default();
+    let mut found = false;
+    for name in ["tcp", "tcp6"] {
+        if let Ok(table) = std::fs::read_to_string(format!("/proc/net/{}", name)) {
+            parse_proc_tcp(&table, &mut tally);
+            found = true;
+        }
+    }
+    for name in ["udp", "udp6"] {
+        if let Ok(table) = std::fs::read_to_string(format!("/proc/net/{}", name)) {
+            // One socket per line after the header
+            tally.udp_sockets += table
+                .lines()
+                .skip(1)
+                .filter(|l| !l.trim().is_empty())
+                .count() as u64;
+            found = true;
+        }
+    }
+    found.then(|| tally.finish())
+}
+
+/// TCP state names, indexed by the state number in `/proc/net/tcp`.
+#[cfg(target_os = "linux")]
+const PROC_TCP_STATES: &[&str] = &[
+    "UNKNOWN",
+    "ESTABLISHED",
+    "SYN_SENT",
+    "SYN_RECV",
+    "FIN_WAIT1",
+    "FIN_WAIT2",
+    "TIME_WAIT",
+    "CLOSE",
+    "CLOSE_WAIT",
+    "LAST_ACK",
+    "LISTEN",
+    "CLOSING",
+    "NEW_SYN_RECV",
+];
+
+/// Counts the sockets in the contents of `/proc/net/tcp` or `tcp6`.
+#[cfg(target_os = "linux")]
+fn parse_proc_tcp(table: &str, tally: &mut Tally) {
+    for line in table.lines().skip(1) {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let (Some(remote), Some(state)) = (fields.get(2), fields.get(3)) else {
+            continue;
+        };
+        let Some(remote) = remote.split(':').next().and_then(parse_hex_address) else {
+            continue;
+        };
+        let state = usize::from_str_radix(state, 16)
+            .ok()
+            .and_then(|state| PROC_TCP_STATES.get(state))
+            .unwrap_or(&"UNKNOWN");
+        tally.add_tcp(state, remote);
+    }
+}
+
+/// Parses an address as printed in `/proc/net/tcp` (8 hex digits) or
+/// `/proc/net/tcp6` (32 hex digits).
+///
+/// The kernel prints the address as 32-bit words in host byte order, so on
+/// little-endian machines the bytes of each word appear reversed:
+/// 127.0.0.1 is `0100007F`, not `7F000001`. IPv4-mapped IPv6 addresses are
+/// returned as IPv4, so both tables group by the same address.
+#[cfg(target_os = "linux")]
+fn parse_hex_address(hex: &str) -> Option<IpAddr> {
+    if !(hex.len() == 8 || hex.len() == 32) || !hex.is_ascii() {
+        return None;
+    }
+    let mut bytes = Vec::with_capacity(16);
+    for word in (0..hex.len()).step_by(8) {
+        let word = u32::from_str_radix(&hex[word..word + 8], 16).ok()?;
+        bytes.extend_from_slice(&word.to_ne_bytes());
+    }
+    Some(match <[u8; 16]>::try_from(bytes.as_slice()) {
+        Ok(octets) => {
+            let address = std::net::Ipv6Addr::from(octets);
+            match address.to_ipv4_mapped() {
+                Some(v4) => IpAddr::V4(v4),
+                None => IpAddr::V6(address),
+            }
+        }
+        Err(_) => IpAddr::from([bytes[0], bytes[1], bytes[2], bytes[3]]),
+    })
+}
+
+/// Summarizes the sockets of the host.
+#[cfg(windows)]
+pub fn query() -> Option<SocketSummary> {
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    /// `MIB_TCP_STATE` names, indexed by state number.
+    const STATES: &[&str] = &[
+        "UNKNOWN",
+        "CLOSE",
+        "LISTEN",
+        "SYN_SENT",
+        "SYN_RECV",
+        "ESTABLISHED",
+        "FIN_WAIT1",
+        "FIN_WAIT2",
+        "CLOSE_WAIT",
+        "CLOSING",
+        "LAST_ACK",
+        "TIME_WAIT",
+        "DELETE_TCB",
+    ];
+    const AF_INET: u32 = 2;
+    const AF_INET6: u32 = 23;
+    const TCP_TABLE_OWNER_PID_ALL: u32 = 5;
+    const UDP_TABLE_BASIC: u32 = 0;
+
+    let state_name = |state: u32| *STATES.get(state as usize).unwrap_or(&"UNKNOWN");
+    let mut tally = Tally::default();
+
+    // MIB_TCPROW_OWNER_PID: state, local address and port, remote address
+    // and port, and process ID
+    let table = extended_table(true, AF_INET, TCP_TABLE_OWNER_PID_ALL)?;
+    for row in rows(&table, 6) {
+        let remote = Ipv4Addr::from(row[3].to_ne_bytes());
+        tally.add_tcp(state_name(row[0]), IpAddr::V4(remote));
+    }
+    // MIB_TCP6ROW_OWNER_PID: local address (16 bytes), scope and port,
+    // remote address (16 bytes), scope and port, state, and process ID
+    if let Some(table) = extended_table(true, AF_INET6, TCP_TABLE_OWNER_PID_ALL) {
+        for row in rows(&table, 14) {
+            let mut octets = [0u8; 16];
+            for (index, word) in row[6..10].iter().enumerate() {
+                octets[index * 4..index * 4 + 4].copy_from_slice(&word.to_ne_bytes());
+            }
+            tally.add_tcp(state_name(row[12]), IpAddr::V6(Ipv6Addr::from(octets)));
+        }
+    }
+    for family in [AF_INET, AF_INET6] {
+        if let Some(table) = extended_table(false, family, UDP_TABLE_BASIC) {
+            tally.udp_sockets += u64::from(table.first().copied().unwrap_or(0));
+        }
+    }
+    Some(tally.finish())
+}
+
+/// Returns the rows of a table from `extended_table`, each `width` words.
+#[cfg(windows)]
+fn rows(table: &[u32], width: usize) -> impl Iterator<Item = &[u32]> {
+    let count = table.first().copied().unwrap_or(0) as usize;
+    table
+        .get(1..)
+        .unwrap_or_default()
+        .chunks_exact(width)
+        .take(count)
+}
+
+/// Reads a TCP (`tcp`) or UDP table with `GetExtendedTcpTable` or
+/// `GetExtendedUdpTable`, as 32-bit words starting with the entry count.
+#[cfg(windows)]
+fn extended_table(tcp: bool, family: u32, class: u32) -> Option<Vec<u32>> {
+    #[link(name = "iphlpapi")]
+    extern "system" {
+        fn GetExtendedTcpTable(
+            table: *mut u32,
+            size: *mut u32,
+            order: i32,
+            family: u32,
+            class: u32,
+            reserved: u32,
+        ) -> u32;
+        fn GetExtendedUdpTable(
+            table: *mut u32,
+            size: *mut u32,
+            order: i32,
+            family: u32,
+            class: u32,
+            reserved: u32,
+        ) -> u32;
+    }
+    const NO_ERROR: u32 = 0;
+    const ERROR_INSUFFICIENT_BUFFER: u32 = 122;
+
+    let mut size = 0u32;
+    // The table can grow between the size query and the read, so retry
+    for _ in 0..5 {
+        let mut buffer = vec![0u32; (size as usize).div_ceil(4).max(1)];
+        size = (buffer.len() * 4) as u32;
+        // SAFETY: `buffer` holds `size` bytes and is 4-byte aligned, as the
+        // tables require.
+        let status = unsafe {
+            if tcp {
+                GetExtendedTcpTable(buffer.as_mut_ptr(), &mut size, 0, family, class, 0)
+            } else {
+                GetExtendedUdpTable(buffer.as_mut_ptr(), &mut size, 0, family, class, 0)
+            }
+        };
+        match status {
+            NO_ERROR => return Some(buffer),
+            ERROR_INSUFFICIENT_BUFFER => continue,
+            _ => return None,
+        }
+    }
+    None
+}
+
+/// Summarizes the sockets of the host.
+///
+/// Not supported on this platform; always `None`.
+#[cfg(not(any(target_os = "linux", windows)))]
+pub fn query() -> Option<SocketSummary> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_proc_tcp() {
+        let table = "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode\n\
+   0: 00000000:0016 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 1 1\n\
+   1: 0F02000A:0016 0501000A:D2F0 01 00000000:00000000 00:00000000 00000000     0        0 2 1\n\
+   2: 0F02000A:0016 0501000A:D2F2 01 00000000:00000000 00:00000000 00000000     0        0 3 1\n\
+   3: 0F02000A:9C40 0101A8C0:01BB 06 00000000:00000000 00:00000000 00000000     0        0 0 1\n";
+        let mut tally = Tally::default();
+        parse_proc_tcp(table, &mut tally);
+        let summary = tally.finish();
+        assert_eq!(summary.tcp_states["LISTEN"], 1);
+        assert_eq!(summary.tcp_states["ESTABLISHED"], 2);
+        assert_eq!(summary.tcp_states["TIME_WAIT"], 1);
+        assert_eq!(
+            summary.top_remote_addresses[0],
+            RemoteCount {
+                address: "10.0.1.5".to_string(),
+                connections: 2
+            }
+        );
+        assert_eq!(summary.top_remote_addresses[1].address, "192.168.1.1");
+        assert_eq!(summary.top_remote_addresses.len(), 2);
+    }
+
+    #[test]
+    fn test_tally_finish() {
+        let mut tally = Tally::default();
+        // Seven remotes, with 7 down to 1 connections; .3 and .4 tie
+        for (last, count) in [(1, 7), (2, 6), (4, 5), (3, 5), (5, 3), (6, 2), (7, 1)] {
+            for _ in 0..count {
+                tally.add_tcp("ESTABLISHED", IpAddr::from([10, 0, 0, last]));
+            }
+        }
+        tally.add_tcp("LISTEN", IpAddr::from([10, 0, 0, 9]));
+        tally.add_tcp("CLOSE", "::".parse().unwrap());
+        tally.udp_sockets = 3;
+
+        let summary = tally.finish();
+        let top: Vec<(&str, u64)> = summary
+            .top_remote_addresses
+            .iter()
+            .map(|remote| (remote.address.as_str(), remote.connections))
+            .collect();
+        assert_eq!(
+            top,
+            [
+                ("10.0.0.1", 7),
+                ("10.0.0.2", 6),
+                ("10.0.0.3", 5),
+                ("10.0.0.4", 5),
+                ("10.0.0.5", 3)
+            ]
+        );
+        assert_eq!(summary.tcp_states["ESTABLISHED"], 29);
+        assert_eq!(summary.tcp_states["LISTEN"], 1);
+        assert_eq!(summary.tcp_states["CLOSE"], 1);
+        assert_eq!(summary.udp_sockets, 3);
+    }
+
+    #[test]
+    fn test_parse_hex_address() {
+        // On a little-endian host:
+        use std::net::IpAddr;
+        assert_eq!(
+            parse_hex_address("0100007F"),
+            Some(IpAddr::from([127, 0, 0, 1]))
+        );
+        assert_eq!(
+            parse_hex_address("0101A8C0"),
+            Some(IpAddr::from([192, 168, 1, 1]))
+        );
+        assert_eq!(
+            parse_hex_address("00000000"),
+            Some(IpAddr::from([0, 0, 0, 0]))
+        );
+        // ::1
+        assert_eq!(
+            parse_hex_address("00000000000000000000000001000000"),
+            Some("::1".parse().unwrap())
+        );
+        // 2001:db8::8a2e:370:7334
+        assert_eq!(
+            parse_hex_address("B80D0120000000002E8A000034737003"),
+            Some("2001:db8::8a2e:370:7334".parse().unwrap())
+        );
+        // ::ffff:10.0.1.5
+        assert_eq!(
+            parse_hex_address("0000000000000000FFFF00000501000A"),
+            Some(IpAddr::from([10, 0, 1, 5]))
+        );
+        assert_eq!(parse_hex_address("0100007"), None);
+        assert_eq!(parse_hex_address("0100007G"), None);
+    }
+}
@@ -0,0 +1,76 @@
+//! Console progress indicator for slow collections.
+//!
+//! Refreshing every sysinfo subsystem can take several seconds on large
+//! machines, so a spinner is drawn on stdout from a background thread while
+//! the main thread waits on the refresh.
+
+use std::io::{self, IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// Animation frames cycled through while the spinner is running.
+const FRAMES: &[char] = &['|', '/', '-', '\\'];
+
+/// Delay between animation frames.
+const FRAME_INTERVAL: Duration = Duration::from_millis(100);
+
+/// An animated spinner with a message, drawn on a single console line.
+///
+/// The line is cleared when the spinner is stopped or dropped so that
+/// subsequent output starts at the beginning of an empty line.
+pub struct Spinner {
+    running: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Spinner {
+    /// Starts a spinner showing `message`.
+    ///
+    /// Returns `None` without drawing anything when stdout is not a terminal,
+    /// so redirected output and logs never contain spinner characters.
+    pub fn start(message: &str) -> Option<Spinner> {
+        if !io::stdout().is_terminal() {
+            return None;
+        }
+
+        let running = Arc::new(AtomicBool::new(true));
+        let thread_running = Arc::clone(&running);
+        let message = message.to_string();
+
+        let handle = thread::spawn(move || {
+            let mut stdout = io::stdout();
+            for frame in FRAMES.iter().cycle() {
+                if !thread_running.load(Ordering::Relaxed) {
+                    break;
+                }
+                let _ = write!(stdout, "\r{} {}", frame, message);
+                let _ = stdout.flush();
+                thread::park_timeout(FRAME_INTERVAL);
+            }
+            let _ = write!(stdout, "\r{}\r", " ".repeat(message.chars().count() + 2));
+            let _ = stdout.flush();
+        });
+
+        Some(Spinner {
+            running,
+            handle: Some(handle),
+        })
+    }
+
+    /// Stops the spinner and clears its line.
+    pub fn stop(self) {
+        drop(self);
+    }
+}
+
+impl Drop for Spinner {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            handle.thread().unpark();
+            let _ = handle.join();
+        }
+    }
+}
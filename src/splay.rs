@@ -0,0 +1,93 @@
+//! Start-up delay for fleets (`--splay`).
+//!
+//! Hosts started by the same schedule wait a different time before
+//! collecting, so their reports do not all arrive at once. By default the
+//! delay is derived from the hostname, so each host keeps the same slot from
+//! run to run; `--splay-random` picks a new delay every run instead.
+
+use std::time::Duration;
+
+/// Returns the delay in `[0, max)` for `hostname`.
+///
+/// Uses FNV-1a rather than the standard library's hasher, whose output may
+/// change between Rust releases and would move every host to a new slot.
+pub fn delay_for_host(hostname: &str, max: Duration) -> Duration {
+    scale(fnv1a(hostname.as_bytes()), max)
+}
+
+/// Returns a delay in `[0, max)` that differs from run to run.
+pub fn random_delay(max: Duration) -> Duration {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos());
+    let mut seed = nanos.to_le_bytes().to_vec();
+    seed.extend_from_slice(&std::process::id().to_le_bytes());
+    scale(fnv1a(&seed), max)
+}
+
+/// Maps `hash` onto `[0, max)` with millisecond resolution.
+fn scale(hash: u64, max: Duration) -> Duration {
+    let max_ms = max.as_millis().min(u64::MAX as u128) as u64;
+    if max_ms == 0 {
+        return Duration::ZERO;
+    }
+    Duration::from_millis(hash % max_ms)
+}
+
+/// 64-bit FNV-1a hash of `bytes`.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    bytes.iter().fold(OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(PRIME)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delay_for_host() {
+        let max = Duration::from_secs(300);
+        assert_eq!(delay_for_host("web-1", max), delay_for_host("web-1", max));
+        assert_ne!(delay_for_host("web-1", max), delay_for_host("web-2", max));
+        assert!(delay_for_host("web-1", max) < max);
+        assert_eq!(delay_for_host("web-1", Duration::ZERO), Duration::ZERO);
+        // Below the millisecond resolution there is no room to spread
+        assert_eq!(
+            delay_for_host("web-1", Duration::from_micros(999)),
+            Duration::ZERO
+        );
+        // The slot is part of the interface: it must not move between releases
+        assert_eq!(
+            delay_for_host("web-1", max),
+            Duration::from_millis(fnv1a(b"web-1") % 300_000)
+        );
+
+        let hosts: Vec<String> = (0..1000).map(|n| format!("node-{:04}", n)).collect();
+        let delays: Vec<Duration> = hosts.iter().map(|host| delay_for_host(host, max)).collect();
+        assert!(delays.iter().all(|delay| *delay < max));
+        // The fleet spreads over the whole window: every minute gets hosts
+        for minute in 0..5 {
+            let slot = Duration::from_secs(minute * 60)..Duration::from_secs((minute + 1) * 60);
+            assert!(delays.iter().filter(|delay| slot.contains(delay)).count() > 100);
+        }
+    }
+
+    #[test]
+    fn test_random_delay() {
+        let max = Duration::from_secs(300);
+        assert!((0..100).all(|_| random_delay(max) < max));
+        assert_eq!(random_delay(Duration::ZERO), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_fnv1a() {
+        // Reference values of the 64-bit FNV-1a hash
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(fnv1a(b"foobar"), 0x8594_4171_f739_67e8);
+    }
+}
@@ -0,0 +1,460 @@
+//! Rates across separate runs (`--state-file <FILE>`).
+//!
+//! A single run has no previous sample to compute rates from, so cron-based
+//! collection never sees throughput. With a state file, every run saves its
+//! raw counters and the time, and the next run reports the rates over the
+//! wall time in between: bytes and packets per second for every interface,
+//! and the growth of used space for every disk. No rates are reported after
+//! a reboot or when the clock went backwards; `note` says why instead.
+//!
+//! The available space of every disk over the last `GROWTH_SAMPLES` runs is
+//! kept too, for the growth trend and days-until-full projection of each
+//! disk (see `growth`). Disk space survives reboots, so the history does.
+
+#[cfg(feature = "history")]
+use crate::{growth, AppError, SystemInfo};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+#[cfg(feature = "history")]
+use std::io;
+#[cfg(feature = "history")]
+use std::path::{Path, PathBuf};
+#[cfg(feature = "history")]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Boot times further apart than this (in seconds) mean the host rebooted.
+///
+/// Some platforms derive the boot time from the uptime and the current time,
+/// so it can shift slightly between runs without a reboot.
+#[cfg(feature = "history")]
+const BOOT_TIME_TOLERANCE_SECS: u64 = 60;
+
+/// Runs the disk growth trend is fitted to, this one included.
+#[cfg(feature = "history")]
+const GROWTH_SAMPLES: usize = 30;
+
+/// The counters of one run, as saved in the state file.
+#[cfg(feature = "history")]
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct Sample {
+    /// When the sample was taken, in milliseconds since the Unix epoch
+    pub timestamp_ms: u64,
+    /// Boot time of the host in seconds since the Unix epoch
+    pub boot_time: u64,
+    /// Bytes received and transmitted and packets received and transmitted,
+    /// by interface name
+    pub networks: BTreeMap<String, [u64; 4]>,
+    /// Used space in bytes, by mount point
+    pub disks: BTreeMap<String, u64>,
+    /// `[timestamp_ms, available_space]` of this and earlier runs, oldest
+    /// first, by mount point
+    #[serde(default)]
+    pub disk_history: BTreeMap<String, Vec<[u64; 2]>>,
+}
+
+#[cfg(feature = "history")]
+impl Sample {
+    /// Takes the counters of `info`, collected at `timestamp_ms` on a host
+    /// booted at `boot_time`.
+    pub fn of(info: &SystemInfo, boot_time: u64, timestamp_ms: u64) -> Sample {
+        Sample {
+            timestamp_ms,
+            boot_time,
+            networks: info
+                .networks
+                .iter()
+                .map(|network| {
+                    let counters = [
+                        network.bytes_received,
+                        network.bytes_transmitted,
+                        network.packets_received,
+                        network.packets_transmitted,
+                    ];
+                    (network.name.clone(), counters)
+                })
+                .collect(),
+            disks: info
+                .disks
+                .iter()
+                .map(|disk| {
+                    let used_space = disk.total_space.saturating_sub(disk.available_space);
+                    (disk.name.clone(), used_space)
+                })
+                .collect(),
+            disk_history: BTreeMap::new(),
+        }
+    }
+}
+
+/// Rates since the previous run.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct Rates {
+    /// Wall time since the previous run in seconds, or `None` if no rates
+    /// were computed
+    pub interval_secs: Option<f64>,
+    /// Why no rates were computed, if they were not
+    pub note: Option<String>,
+    /// Traffic by interface name
+    pub networks: BTreeMap<String, NetworkRates>,
+    /// Used space growth by mount point
+    pub disks: BTreeMap<String, DiskRates>,
+}
+
+#[cfg(feature = "history")]
+impl Rates {
+    /// Rates that could not be computed, for the reason in `note`.
+    fn skipped(note: &str) -> Rates {
+        Rates {
+            note: Some(note.to_string()),
+            ..Rates::default()
+        }
+    }
+}
+
+/// Traffic on one interface, per second.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct NetworkRates {
+    pub bytes_received_per_sec: f64,
+    pub bytes_transmitted_per_sec: f64,
+    pub packets_received_per_sec: f64,
+    pub packets_transmitted_per_sec: f64,
+}
+
+/// Change of one disk's used space.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct DiskRates {
+    /// Bytes per second, negative when space was freed
+    pub used_bytes_per_sec: f64,
+}
+
+/// Computes the rates from the `previous` run's sample to `current`.
+///
+/// Interfaces and disks must be in both samples. An interface whose counters
+/// went backwards without a reboot, e.g. because it was re-created, is left
+/// out rather than reported with a bogus rate.
+#[cfg(feature = "history")]
+pub fn compute(previous: &Sample, current: &Sample) -> Rates {
+    if previous.boot_time.abs_diff(current.boot_time) > BOOT_TIME_TOLERANCE_SECS {
+        return Rates::skipped("the host rebooted since the previous run");
+    }
+    if current.timestamp_ms <= previous.timestamp_ms {
+        return Rates::skipped("the clock went backwards since the previous run");
+    }
+    let seconds = (current.timestamp_ms - previous.timestamp_ms) as f64 / 1000.0;
+
+    let networks = current
+        .networks
+        .iter()
+        .filter_map(|(name, now)| {
+            let then = previous.networks.get(name)?;
+            if now.iter().zip(then).any(|(now, then)| now < then) {
+                return None;
+            }
+            let rate = |index: usize| (now[index] - then[index]) as f64 / seconds;
+            let rates = NetworkRates {
+                bytes_received_per_sec: rate(0),
+                bytes_transmitted_per_sec: rate(1),
+                packets_received_per_sec: rate(2),
+                packets_transmitted_per_sec: rate(3),
+            };
+            Some((name.clone(), rates))
+        })
+        .collect();
+    let disks = current
+        .disks
+        .iter()
+        .filter_map(|(name, &now)| {
+            let then = *previous.disks.get(name)?;
+            let rates = DiskRates {
+                used_bytes_per_sec: (now as f64 - then as f64) / seconds,
+            };
+            Some((name.clone(), rates))
+        })
+        .collect();
+    Rates {
+        interval_secs: Some(seconds),
+        note: None,
+        networks,
+        disks,
+    }
+}
+
+/// The previous run's sample, loaded from a state file.
+#[cfg(feature = "history")]
+pub struct StateFile {
+    path: PathBuf,
+    previous: Option<Sample>,
+}
+
+#[cfg(feature = "history")]
+impl StateFile {
+    /// Loads the sample saved in `path`.
+    ///
+    /// A missing file starts fresh. So does a file that cannot be read or is
+    /// not a state file, with a warning on stderr, since it is overwritten
+    /// by the next sample anyway.
+    pub fn load(path: &Path) -> StateFile {
+        let previous = match std::fs::read_to_string(path) {
+            Ok(text) => match serde_json::from_str(&text) {
+                Ok(sample) => Some(sample),
+                Err(e) => {
+                    eprintln!(
+                        "Warning: ignoring state file {}, which is not valid: {}",
+                        path.display(),
+                        e
+                    );
+                    None
+                }
+            },
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => {
+                eprintln!("Warning: ignoring state file {}: {}", path.display(), e);
+                None
+            }
+        };
+        StateFile {
+            path: path.to_path_buf(),
+            previous,
+        }
+    }
+
+    /// Fills in `info.rates` from the previous sample and the growth trend
+    /// of every disk from the history, then saves the counters of `info` as
+    /// the new previous sample.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the state file cannot be written.
+    pub fn apply(&mut self, info: &mut SystemInfo, boot_time: u64) -> Result<(), AppError> {
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as u64);
+        let mut current = Sample::of(info, boot_time, timestamp_ms);
+        info.rates = Some(match &self.previous {
+            Some(previous) => compute(previous, &current),
+            None => Rates::skipped("no previous run in the state file"),
+        });
+        for disk in &mut info.disks {
+            let mut history = self
+                .previous
+                .as_mut()
+                .and_then(|previous| previous.disk_history.remove(&disk.name))
+                .unwrap_or_default();
+            // Samples from after a clock that went backwards would skew the trend
+            history.retain(|&[then, _]| then < timestamp_ms);
+            history.push([timestamp_ms, disk.available_space]);
+            let excess = history.len().saturating_sub(GROWTH_SAMPLES);
+            history.drain(..excess);
+            if let Some(projection) = growth::project(&history) {
+                disk.growth_bytes_per_day = Some(projection.growth_bytes_per_day);
+                disk.estimated_days_until_full = projection.days_until_full;
+            }
+            current.disk_history.insert(disk.name.clone(), history);
+        }
+        let json = serde_json::to_string_pretty(&current).map_err(AppError::JsonSerialization)?;
+        crate::write_atomically(&self.path, json.as_bytes(), None).map_err(AppError::FileWrite)?;
+        self.previous = Some(current);
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "history"))]
+mod tests {
+    use super::*;
+    use crate::mock::{self, disk, network, MockProvider};
+
+    #[test]
+    fn test_compute() {
+        let sample = |timestamp_ms, boot_time, received, used| Sample {
+            timestamp_ms,
+            boot_time,
+            networks: [("eth0".to_string(), [received, 0, received / 1000, 0])].into(),
+            disks: [("/".to_string(), used)].into(),
+            disk_history: BTreeMap::new(),
+        };
+        let previous = sample(1_000_000, 500, 1_000_000, 4_000_000);
+
+        // 5 minutes later: 3 MB received, 600 KB freed
+        let rates = compute(&previous, &sample(1_300_000, 500, 4_000_000, 3_400_000));
+        assert_eq!(rates.interval_secs, Some(300.0));
+        assert_eq!(rates.note, None);
+        assert_eq!(rates.networks["eth0"].bytes_received_per_sec, 10_000.0);
+        assert_eq!(rates.networks["eth0"].packets_received_per_sec, 10.0);
+        assert_eq!(rates.disks["/"].used_bytes_per_sec, -2_000.0);
+
+        // a boot time shifted by a few seconds is not a reboot
+        assert!(compute(&previous, &sample(1_300_000, 503, 4_000_000, 0))
+            .note
+            .is_none());
+
+        // rebooted: counters started over
+        let rates = compute(&previous, &sample(1_300_000, 1_200, 10, 0));
+        assert_eq!(
+            rates.note.as_deref(),
+            Some("the host rebooted since the previous run")
+        );
+        assert!(rates.networks.is_empty() && rates.interval_secs.is_none());
+
+        // the clock went backwards, or did not move
+        let rates = compute(&previous, &sample(900_000, 500, 4_000_000, 0));
+        assert_eq!(
+            rates.note.as_deref(),
+            Some("the clock went backwards since the previous run")
+        );
+        assert!(compute(&previous, &sample(1_000_000, 500, 4_000_000, 0))
+            .note
+            .is_some());
+
+        // the interface was re-created: its counters went backwards
+        let rates = compute(&previous, &sample(1_300_000, 500, 10, 4_000_000));
+        assert!(rates.networks.is_empty());
+        assert_eq!(rates.disks["/"].used_bytes_per_sec, 0.0);
+
+        // interfaces and disks that appeared since are left out
+        let mut current = sample(1_300_000, 500, 4_000_000, 0);
+        current.networks.insert("wg0".to_string(), [1, 1, 1, 1]);
+        assert!(!compute(&previous, &current).networks.contains_key("wg0"));
+    }
+
+    #[test]
+    fn test_state_file_load() {
+        let dir = std::env::temp_dir().join(format!("state-load-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        // missing and corrupt files start fresh
+        assert!(StateFile::load(&dir.join("missing.json"))
+            .previous
+            .is_none());
+        let corrupt = dir.join("corrupt.json");
+        std::fs::write(&corrupt, "{\"timestamp_ms\": ").unwrap();
+        assert!(StateFile::load(&corrupt).previous.is_none());
+        std::fs::write(&corrupt, "[1, 2, 3]").unwrap();
+        assert!(StateFile::load(&corrupt).previous.is_none());
+        // a directory cannot be read
+        assert!(StateFile::load(&dir).previous.is_none());
+
+        // files from before the disk history was kept still load
+        let old = dir.join("old.json");
+        std::fs::write(
+            &old,
+            r#"{"timestamp_ms": 5, "boot_time": 7, "networks": {"eth0": [1, 2, 3, 4]}, "disks": {"/": 9}}"#,
+        )
+        .unwrap();
+        let previous = StateFile::load(&old).previous.unwrap();
+        assert_eq!(previous.networks["eth0"], [1, 2, 3, 4]);
+        assert!(previous.disk_history.is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_state_file_apply() {
+        let dir = std::env::temp_dir().join(format!("state-apply-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("state.json");
+        let sys = |received, available| MockProvider {
+            disks: vec![disk("/", "ext4", 100 << 30, available)],
+            networks: vec![network("eth0", received, 0)],
+            ..MockProvider::default()
+        };
+        let boot_time = 1_700_000_000;
+
+        // the first run has nothing to compare with, but saves its counters
+        let mut info = mock::snapshot(&sys(1_000_000, 60 << 30));
+        StateFile::load(&path).apply(&mut info, boot_time).unwrap();
+        let rates = info.rates.unwrap();
+        assert_eq!(
+            rates.note.as_deref(),
+            Some("no previous run in the state file")
+        );
+        let saved: Sample = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(saved.networks["eth0"], [1_000_000, 0, 10_000, 0]);
+        assert_eq!(saved.disks["/"], 40 << 30);
+        assert_eq!(saved.disk_history["/"].len(), 1);
+
+        // pretend the first run was 100 seconds ago
+        let mut earlier = saved.clone();
+        earlier.timestamp_ms -= 100_000;
+        earlier.disk_history.get_mut("/").unwrap()[0][0] -= 100_000;
+        std::fs::write(&path, serde_json::to_string(&earlier).unwrap()).unwrap();
+
+        let mut info = mock::snapshot(&sys(2_000_000, 59 << 30));
+        StateFile::load(&path).apply(&mut info, boot_time).unwrap();
+        let rates = info.rates.unwrap();
+        assert!(rates.note.is_none());
+        let interval = rates.interval_secs.unwrap();
+        assert!((100.0..110.0).contains(&interval));
+        let received = rates.networks["eth0"].bytes_received_per_sec;
+        assert!((received - 1_000_000.0 / interval).abs() < 1e-6);
+        assert!(rates.disks["/"].used_bytes_per_sec > 0.0);
+        // the history keeps both runs
+        let saved: Sample = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(saved.disk_history["/"].len(), 2);
+        assert_eq!(saved.disk_history["/"][1][1], 59 << 30);
+
+        // after a reboot, no rates, but the disk history carries on
+        let mut info = mock::snapshot(&sys(10, 59 << 30));
+        StateFile::load(&path)
+            .apply(&mut info, boot_time + 3600)
+            .unwrap();
+        assert_eq!(
+            info.rates.unwrap().note.as_deref(),
+            Some("the host rebooted since the previous run")
+        );
+        let saved: Sample = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(saved.boot_time, boot_time + 3600);
+        assert_eq!(saved.networks["eth0"][0], 10);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_state_file_growth() {
+        let dir = std::env::temp_dir().join(format!("state-growth-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("state.json");
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+        const DAY: u64 = 86_400_000;
+
+        // 40 earlier daily runs losing 1 GiB a day, and one from the future
+        let mut history: Vec<[u64; 2]> = (1..=40)
+            .rev()
+            .map(|days| [now - days * DAY, (50 + days) << 30])
+            .collect();
+        history.push([now + DAY, 1]);
+        let previous = Sample {
+            timestamp_ms: now - DAY,
+            boot_time: 1_700_000_000,
+            disk_history: [("/".to_string(), history)].into(),
+            ..Sample::default()
+        };
+        std::fs::write(&path, serde_json::to_string(&previous).unwrap()).unwrap();
+
+        let sys = MockProvider {
+            disks: vec![disk("/", "ext4", 100 << 30, 50 << 30)],
+            ..MockProvider::default()
+        };
+        let mut info = mock::snapshot(&sys);
+        StateFile::load(&path)
+            .apply(&mut info, 1_700_000_000)
+            .unwrap();
+        let growth = info.disks[0].growth_bytes_per_day.unwrap();
+        assert!((growth - (1u64 << 30) as f64).abs() < 1e3);
+        assert_eq!(
+            info.disks[0].estimated_days_until_full.map(f64::round),
+            Some(50.0)
+        );
+
+        // only the latest runs are kept, and none from after this one
+        let saved: Sample = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        let history = &saved.disk_history["/"];
+        assert_eq!(history.len(), GROWTH_SAMPLES);
+        assert_eq!(history[GROWTH_SAMPLES - 1][1], 50 << 30);
+        assert!(history.windows(2).all(|pair| pair[0][0] < pair[1][0]));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,39 @@
+//! Console text styling.
+//!
+//! Callers decide whether to color; for stdout, colors should only be used
+//! when it is a terminal and the `NO_COLOR` environment variable is unset, so
+//! redirected output stays plain text.
+
+use std::io::{self, IsTerminal};
+
+/// Colors used to highlight values in the console report.
+#[derive(Debug, Clone, Copy)]
+pub enum Color {
+    Red,
+    Yellow,
+    Cyan,
+}
+
+impl Color {
+    fn ansi_code(self) -> &'static str {
+        match self {
+            Color::Red => "31",
+            Color::Yellow => "33",
+            Color::Cyan => "36",
+        }
+    }
+}
+
+/// Wraps `text` in the escape sequences for `color` if `enabled` is set.
+pub fn paint(text: &str, color: Color, enabled: bool) -> String {
+    if enabled {
+        format!("\x1b[{}m{}\x1b[0m", color.ansi_code(), text)
+    } else {
+        text.to_string()
+    }
+}
+
+/// Returns true if output written to stdout should be colored.
+pub fn stdout_supports_color() -> bool {
+    std::env::var_os("NO_COLOR").is_none() && io::stdout().is_terminal()
+}
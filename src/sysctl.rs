@@ -0,0 +1,61 @@
+//! Kernel parameters (sysctls) for tuning diagnostics.
+//!
+//! A curated set of parameters, plus any named with `--sysctl`, is read from
+//! `/proc/sys` on Linux. Other platforms report none.
+
+use std::collections::BTreeMap;
+
+/// Parameters always reported.
+pub const DEFAULT_KEYS: &[&str] = &["vm.swappiness", "vm.overcommit_memory", "fs.file-max"];
+
+/// Returns whether `key` is a well-formed sysctl name such as "vm.swappiness".
+pub fn is_valid_key(key: &str) -> bool {
+    key.split('.').all(|segment| {
+        !segment.is_empty()
+            && segment
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    })
+}
+
+/// Reads the default parameters and `extra`, keyed by name.
+///
+/// Parameters that do not exist or cannot be read are left out.
+#[cfg(target_os = "linux")]
+pub fn read(extra: &[String]) -> Option<BTreeMap<String, String>> {
+    let keys = DEFAULT_KEYS
+        .iter()
+        .copied()
+        .chain(extra.iter().map(String::as_str));
+    Some(
+        keys.filter_map(|key| {
+            let path = format!("/proc/sys/{}", key.replace('.', "/"));
+            let value = std::fs::read_to_string(path).ok()?;
+            // Multi-value parameters separate their fields with tabs
+            let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+            Some((key.to_string(), value))
+        })
+        .collect(),
+    )
+}
+
+/// Reads the default parameters and `extra`, keyed by name.
+///
+/// Not available on this platform, so always `None`.
+#[cfg(not(target_os = "linux"))]
+pub fn read(_extra: &[String]) -> Option<BTreeMap<String, String>> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_valid_key() {
+        assert!(is_valid_key("net.ipv4.tcp_fin_timeout"));
+        assert!(is_valid_key("fs.file-max"));
+        assert!(!is_valid_key("vm..swappiness"));
+        assert!(!is_valid_key("../etc/passwd"));
+    }
+}
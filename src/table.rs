@@ -0,0 +1,156 @@
+//! Column-aligned tables for the console report.
+//!
+//! Column widths are computed from the longest cell in each column, including
+//! the header. When a maximum line width is given and the table would exceed
+//! it, one designated column is shortened, keeping the start and end of each
+//! cell.
+
+/// Horizontal alignment of a column.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Align {
+    Left,
+    Right,
+}
+
+/// Spaces between adjacent columns.
+const GAP: usize = 2;
+
+/// Narrowest a shrinkable column is made, including the `…` that
+/// `ellipsize` puts in shortened cells; a longer header raises it.
+const MIN_SHRUNK_WIDTH: usize = 8;
+
+/// A table of text cells.
+pub struct Table<'a> {
+    headers: &'a [&'a str],
+    aligns: &'a [Align],
+    rows: Vec<Vec<String>>,
+}
+
+impl<'a> Table<'a> {
+    /// Creates an empty table. `aligns` must have one entry per header.
+    pub fn new(headers: &'a [&'a str], aligns: &'a [Align]) -> Table<'a> {
+        debug_assert_eq!(headers.len(), aligns.len());
+        Table {
+            headers,
+            aligns,
+            rows: Vec::new(),
+        }
+    }
+
+    /// Appends a row. Missing trailing cells are left blank.
+    pub fn push(&mut self, row: Vec<String>) {
+        self.rows.push(row);
+    }
+
+    /// Renders the header and rows as lines, each prefixed with `indent`.
+    ///
+    /// With `width`, the column at index `shrink` is narrowed (down to a
+    /// minimum) so that lines fit; narrowed cells are shortened with
+    /// `ellipsize`. Trailing spaces are trimmed from every line.
+    pub fn render(&self, indent: &str, width: Option<usize>, shrink: usize) -> Vec<String> {
+        let mut widths: Vec<usize> = self.headers.iter().map(|h| h.chars().count()).collect();
+        for row in &self.rows {
+            for (column, cell) in row.iter().enumerate().take(widths.len()) {
+                widths[column] = widths[column].max(cell.chars().count());
+            }
+        }
+
+        if let Some(width) = width {
+            let total = indent.len() + widths.iter().sum::<usize>() + GAP * (widths.len() - 1);
+            if total > width && shrink < widths.len() {
+                let floor = MIN_SHRUNK_WIDTH.max(self.headers[shrink].chars().count());
+                let excess = total - width;
+                widths[shrink] = widths[shrink].saturating_sub(excess).max(floor);
+            }
+        }
+
+        let mut lines = Vec::with_capacity(self.rows.len() + 1);
+        let headers: Vec<String> = self.headers.iter().map(|h| h.to_string()).collect();
+        for row in std::iter::once(&headers).chain(&self.rows) {
+            let mut line = indent.to_string();
+            for (column, &column_width) in widths.iter().enumerate() {
+                let cell = row.get(column).map_or("", String::as_str);
+                let cell = ellipsize(cell, column_width);
+                let padding = column_width.saturating_sub(cell.chars().count());
+                if column > 0 {
+                    line.push_str(&" ".repeat(GAP));
+                }
+                match self.aligns[column] {
+                    Align::Left => {
+                        line.push_str(&cell);
+                        line.push_str(&" ".repeat(padding));
+                    }
+                    Align::Right => {
+                        line.push_str(&" ".repeat(padding));
+                        line.push_str(&cell);
+                    }
+                }
+            }
+            lines.push(line.trim_end().to_string());
+        }
+        lines
+    }
+}
+
+/// Shortens `text` to `width` characters by replacing its middle with `…`,
+/// keeping the start and the (slightly longer) end, e.g. both the top-level
+/// directory and the last component of a mount point.
+pub fn ellipsize(text: &str, width: usize) -> String {
+    let length = text.chars().count();
+    if length <= width || width == 0 {
+        return text.to_string();
+    }
+    let head = (width - 1) / 2;
+    let tail = width - 1 - head;
+    let start: String = text.chars().take(head).collect();
+    let end: String = text.chars().skip(length - tail).collect();
+    format!("{}…{}", start, end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table_render() {
+        let mut table = Table::new(&["Mount", "Used"], &[Align::Left, Align::Right]);
+        table.push(vec!["/".to_string(), "1.0 GB".to_string()]);
+        table.push(vec!["/data".to_string(), "512.0 MB".to_string()]);
+        assert_eq!(
+            table.render("  ", None, 0),
+            vec![
+                "  Mount      Used",
+                "  /        1.0 GB",
+                "  /data  512.0 MB"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_table_render_shrink() {
+        let mut table = Table::new(&["Mount", "Used"], &[Align::Left, Align::Right]);
+        table.push(vec![
+            "/mnt/backups/nightly".to_string(),
+            "1.0 GB".to_string(),
+        ]);
+        // Shrunk just enough to fit, the ellipsis included
+        assert_eq!(
+            table.render("", Some(20), 0),
+            vec!["Mount           Used", "/mnt/…ightly  1.0 GB"]
+        );
+        // Never below MIN_SHRUNK_WIDTH, even if the line stays too long
+        assert_eq!(
+            table.render("", Some(10), 0),
+            vec!["Mount       Used", "/mn…htly  1.0 GB"]
+        );
+        assert_eq!(table.render("", Some(10), 0)[1].chars().count(), 16);
+    }
+
+    #[test]
+    fn test_ellipsize() {
+        assert_eq!(ellipsize("/mnt/backups/nightly", 10), "/mnt…ghtly");
+        assert_eq!(ellipsize("/mnt/backups/nightly", 11), "/mnt/…ghtly");
+        assert_eq!(ellipsize("/data", 10), "/data");
+        assert_eq!(ellipsize("/data", 0), "/data");
+    }
+}
@@ -0,0 +1,133 @@
+//! Terminal width detection and the console report layout chosen from it.
+//!
+//! On narrow terminals, such as 80-column serial consoles, the disk table
+//! first loses its file system column, and below that the disk and network
+//! sections switch to a stacked layout of two lines per item.
+
+use std::io::{self, IsTerminal};
+
+/// Width below which the disk table drops its file system column.
+const NARROW_BELOW: usize = 100;
+
+/// Width below which disks and interfaces are stacked, two lines each.
+const STACKED_BELOW: usize = 72;
+
+/// How the disk and network sections of the console report are laid out.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Layout {
+    /// Disk table with every column
+    Wide,
+    /// Disk table without the file system column
+    Narrow,
+    /// A name line and a usage line per disk and per interface
+    Stacked,
+}
+
+impl Layout {
+    /// Chooses the layout for a console `width`; without one, the report
+    /// is not width-limited and uses the wide layout.
+    pub fn for_width(width: Option<usize>) -> Layout {
+        match width {
+            Some(width) if width < STACKED_BELOW => Layout::Stacked,
+            Some(width) if width < NARROW_BELOW => Layout::Narrow,
+            _ => Layout::Wide,
+        }
+    }
+}
+
+/// Returns the width of the terminal stdout is attached to, in columns.
+///
+/// Returns `None` when stdout is not a terminal, so piped output is never
+/// narrowed unless `--width` asks for it. The `COLUMNS` environment variable
+/// is used when the terminal cannot be queried.
+pub fn width() -> Option<usize> {
+    if !io::stdout().is_terminal() {
+        return None;
+    }
+    query_width().or_else(|| {
+        std::env::var("COLUMNS")
+            .ok()?
+            .parse()
+            .ok()
+            .filter(|&columns| columns > 0)
+    })
+}
+
+/// Asks the terminal driver for stdout's window size.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn query_width() -> Option<usize> {
+    use std::os::unix::io::AsRawFd;
+
+    #[repr(C)]
+    #[derive(Default)]
+    struct WindowSize {
+        rows: u16,
+        columns: u16,
+        x_pixels: u16,
+        y_pixels: u16,
+    }
+    extern "C" {
+        fn ioctl(fd: i32, request: std::os::raw::c_ulong, ...) -> i32;
+    }
+    #[cfg(target_os = "linux")]
+    const TIOCGWINSZ: std::os::raw::c_ulong = 0x5413;
+    #[cfg(target_os = "macos")]
+    const TIOCGWINSZ: std::os::raw::c_ulong = 0x4008_7468;
+
+    let mut size = WindowSize::default();
+    // SAFETY: TIOCGWINSZ writes a `struct winsize`, which `WindowSize`
+    // matches, and `size` outlives the call.
+    let result = unsafe { ioctl(io::stdout().as_raw_fd(), TIOCGWINSZ, &mut size) };
+    (result == 0 && size.columns > 0).then_some(size.columns as usize)
+}
+
+/// Asks the console for the width of its visible window.
+#[cfg(windows)]
+fn query_width() -> Option<usize> {
+    #[repr(C)]
+    #[derive(Default)]
+    struct ScreenBufferInfo {
+        size: [i16; 2],
+        cursor_position: [i16; 2],
+        attributes: u16,
+        /// Left, top, right and bottom of the visible window
+        window: [i16; 4],
+        maximum_window_size: [i16; 2],
+    }
+    extern "system" {
+        fn GetStdHandle(std_handle: u32) -> *mut std::ffi::c_void;
+        fn GetConsoleScreenBufferInfo(
+            console: *mut std::ffi::c_void,
+            info: *mut ScreenBufferInfo,
+        ) -> i32;
+    }
+    const STD_OUTPUT_HANDLE: u32 = -11i32 as u32;
+
+    let mut info = ScreenBufferInfo::default();
+    // SAFETY: `info` matches CONSOLE_SCREEN_BUFFER_INFO and outlives the
+    // call; an invalid handle makes the call fail rather than write.
+    let ok = unsafe { GetConsoleScreenBufferInfo(GetStdHandle(STD_OUTPUT_HANDLE), &mut info) };
+    let columns = i32::from(info.window[2]) - i32::from(info.window[0]) + 1;
+    (ok != 0 && columns > 0).then_some(columns as usize)
+}
+
+/// Asks the terminal for its width; not supported on this platform.
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+fn query_width() -> Option<usize> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layout_for_width() {
+        assert_eq!(Layout::for_width(Some(120)), Layout::Wide);
+        assert_eq!(Layout::for_width(Some(100)), Layout::Wide);
+        assert_eq!(Layout::for_width(Some(80)), Layout::Narrow);
+        assert_eq!(Layout::for_width(Some(72)), Layout::Narrow);
+        assert_eq!(Layout::for_width(Some(60)), Layout::Stacked);
+        assert_eq!(Layout::for_width(None), Layout::Wide);
+    }
+}
@@ -0,0 +1,530 @@
+//! CPU core temperatures and thermal throttling.
+//!
+//! On Linux the Intel `coretemp` hwmon driver reports one temperature per
+//! physical core ("Core N", per package "Package id P"). Each logical CPU is
+//! matched to its core through `topology/physical_package_id` and
+//! `topology/core_id`, so hyperthreads share their core's temperature.
+//! AMD's `k10temp` only reports per-die (Tctl) and per-CCD (Tccd) values,
+//! which cannot be attributed to single cores; like any other system where a
+//! CPU cannot be matched, it falls back to the generic sensor list from
+//! sysinfo.
+//!
+//! With `--interval`, a `Tracker` follows the hottest sensor across samples
+//! and reports throttling, from the kernel's `thermal_throttle` counters and
+//! from the CPU running well below its maximum frequency while near its
+//! critical temperature.
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// A temperature sensor reported by sysinfo.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ComponentTemperature {
+    /// Sensor label, e.g. "k10temp Tctl"
+    pub label: String,
+    /// Temperature in degrees Celsius
+    pub celsius: f32,
+}
+
+/// Reads the temperature of every logical CPU, in CPU number order.
+///
+/// Returns `None` unless every CPU could be matched to a core sensor.
+#[cfg(target_os = "linux")]
+pub fn core_temperatures() -> Option<Vec<f32>> {
+    read_core_temperatures(Path::new("/sys"))
+}
+
+/// Reads the temperature of every logical CPU.
+///
+/// Not available on this platform, so always `None`.
+#[cfg(not(target_os = "linux"))]
+pub fn core_temperatures() -> Option<Vec<f32>> {
+    None
+}
+
+/// Reads the temperature of every logical CPU from the sysfs tree at `root`.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub fn read_core_temperatures(root: &Path) -> Option<Vec<f32>> {
+    use std::collections::HashMap;
+    use std::fs;
+
+    let read = |path: &Path| {
+        fs::read_to_string(path)
+            .ok()
+            .map(|text| text.trim().to_string())
+    };
+
+    // (package, core) -> degrees Celsius
+    let mut cores: HashMap<(u32, u32), f32> = HashMap::new();
+    for hwmon in fs::read_dir(root.join("class/hwmon")).ok()?.flatten() {
+        let hwmon = hwmon.path();
+        if read(&hwmon.join("name")).as_deref() != Some("coretemp") {
+            continue;
+        }
+        let mut package = 0;
+        let mut temperatures = Vec::new();
+        for entry in fs::read_dir(&hwmon).ok()?.flatten() {
+            let file_name = entry.file_name();
+            let Some(sensor) = file_name
+                .to_str()
+                .and_then(|name| name.strip_suffix("_label"))
+            else {
+                continue;
+            };
+            let Some(label) = read(&entry.path()) else {
+                continue;
+            };
+            let Some(celsius) = read(&hwmon.join(format!("{}_input", sensor)))
+                .and_then(|input| input.parse::<f32>().ok())
+                .map(|millidegrees| millidegrees / 1000.0)
+            else {
+                continue;
+            };
+            if let Some(id) = label.strip_prefix("Package id ") {
+                package = id.parse().ok()?;
+            } else if let Some(core) = label.strip_prefix("Core ") {
+                temperatures.push((core.parse().ok()?, celsius));
+            }
+        }
+        for (core, celsius) in temperatures {
+            cores.insert((package, core), celsius);
+        }
+    }
+    if cores.is_empty() {
+        return None;
+    }
+
+    let mut cpus: Vec<(u32, std::path::PathBuf)> = fs::read_dir(root.join("devices/system/cpu"))
+        .ok()?
+        .flatten()
+        .filter_map(|entry| {
+            let number = entry
+                .file_name()
+                .to_str()?
+                .strip_prefix("cpu")?
+                .parse()
+                .ok()?;
+            Some((number, entry.path()))
+        })
+        .collect();
+    if cpus.is_empty() {
+        return None;
+    }
+    cpus.sort_by_key(|(number, _)| *number);
+    cpus.iter()
+        .map(|(_, path)| {
+            let package = read(&path.join("topology/physical_package_id"))?
+                .parse()
+                .ok()?;
+            let core = read(&path.join("topology/core_id"))?.parse().ok()?;
+            cores.get(&(package, core)).copied()
+        })
+        .collect()
+}
+
+/// How close to its critical temperature (in degrees Celsius) a sensor
+/// counts as near critical.
+#[cfg(feature = "sensors")]
+const NEAR_CRITICAL_MARGIN: f32 = 5.0;
+
+/// Current-to-maximum CPU frequency ratio below which a hot CPU is assumed to
+/// be throttled.
+#[cfg(feature = "sensors")]
+const THROTTLED_FREQUENCY_RATIO: f64 = 0.8;
+
+/// Thermal state over an `--interval` session.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(default)]
+pub struct ThermalInfo {
+    /// Whether the CPU was throttled for temperature since the last sample
+    pub throttled: bool,
+    /// Thermal throttle events counted by the kernel since boot, where
+    /// available
+    pub throttle_events: Option<u64>,
+    /// Highest sensor temperature seen in this session, in degrees Celsius
+    pub max_temp_seen: Option<f32>,
+    /// Time any sensor spent at or above its critical temperature in this
+    /// session, in seconds
+    pub seconds_above_critical: f64,
+    /// Average current-to-maximum CPU frequency ratio, where available
+    pub frequency_ratio: Option<f64>,
+}
+
+/// Follows temperatures and throttling across the samples of a session.
+#[cfg(feature = "sensors")]
+pub struct Tracker {
+    sys: sysinfo::System,
+    info: ThermalInfo,
+    /// Whether a sensor was at or above critical, and when, at the last sample
+    previous: Option<(bool, std::time::Instant)>,
+}
+
+#[cfg(feature = "sensors")]
+impl Tracker {
+    pub fn new() -> Tracker {
+        use sysinfo::SystemExt;
+
+        let mut sys = sysinfo::System::new();
+        sys.refresh_components_list();
+        Tracker {
+            sys,
+            info: ThermalInfo::default(),
+            previous: None,
+        }
+    }
+
+    /// Takes a reading and returns the updated session state.
+    pub fn observe(&mut self) -> ThermalInfo {
+        use sysinfo::{ComponentExt, SystemExt};
+
+        self.sys.refresh_components();
+        let sensors: Vec<(f32, Option<f32>)> = self
+            .sys
+            .components()
+            .iter()
+            .map(|component| (component.temperature(), component.critical()))
+            .collect();
+        self.record(
+            &sensors,
+            throttle_events(),
+            frequency_ratio(),
+            std::time::Instant::now(),
+        )
+    }
+
+    /// Updates the session state with one reading taken at `now`: the
+    /// temperature and critical temperature of every sensor, the kernel's
+    /// throttle event count, and the CPU frequency ratio.
+    fn record(
+        &mut self,
+        sensors: &[(f32, Option<f32>)],
+        events: Option<u64>,
+        frequency_ratio: Option<f64>,
+        now: std::time::Instant,
+    ) -> ThermalInfo {
+        let mut hottest: Option<(f32, Option<f32>)> = None;
+        let mut above_critical = false;
+        for &(temperature, critical) in sensors {
+            if !temperature.is_finite() {
+                continue;
+            }
+            above_critical |= critical.is_some_and(|critical| temperature >= critical);
+            if hottest.is_none_or(|(hottest, _)| temperature > hottest) {
+                hottest = Some((temperature, critical));
+            }
+        }
+        if let Some((true, then)) = self.previous {
+            self.info.seconds_above_critical += (now - then).as_secs_f64();
+        }
+        self.previous = Some((above_critical, now));
+
+        if let Some((temperature, _)) = hottest {
+            self.info.max_temp_seen = Some(
+                self.info
+                    .max_temp_seen
+                    .map_or(temperature, |max| max.max(temperature)),
+            );
+        }
+        let new_events = match (self.info.throttle_events, events) {
+            (Some(previous), Some(current)) => current > previous,
+            _ => false,
+        };
+        self.info.throttle_events = events;
+        self.info.frequency_ratio = frequency_ratio;
+        self.info.throttled = new_events
+            || hottest.is_some_and(|(temperature, critical)| {
+                is_throttled(self.info.frequency_ratio, temperature, critical)
+            });
+        self.info.clone()
+    }
+}
+
+/// Decides whether a CPU running at `frequency_ratio` of its maximum speed
+/// while its hottest sensor reads `temperature` is being throttled.
+///
+/// That is the case when the frequency is below `THROTTLED_FREQUENCY_RATIO`
+/// while the sensor is within `NEAR_CRITICAL_MARGIN` of its `critical`
+/// temperature. Without a frequency or a critical temperature there is no
+/// evidence of throttling.
+#[cfg(feature = "sensors")]
+pub fn is_throttled(frequency_ratio: Option<f64>, temperature: f32, critical: Option<f32>) -> bool {
+    frequency_ratio.is_some_and(|ratio| ratio < THROTTLED_FREQUENCY_RATIO)
+        && critical.is_some_and(|critical| temperature >= critical - NEAR_CRITICAL_MARGIN)
+}
+
+/// Reads the kernel's thermal throttle event count.
+#[cfg(all(feature = "sensors", target_os = "linux"))]
+fn throttle_events() -> Option<u64> {
+    read_throttle_events(Path::new("/sys"))
+}
+
+/// Reads the kernel's thermal throttle event count.
+///
+/// Not available on this platform, so always `None`.
+#[cfg(all(feature = "sensors", not(target_os = "linux")))]
+fn throttle_events() -> Option<u64> {
+    None
+}
+
+/// Reads the average current-to-maximum CPU frequency ratio.
+#[cfg(all(feature = "sensors", target_os = "linux"))]
+fn frequency_ratio() -> Option<f64> {
+    read_frequency_ratio(Path::new("/sys"))
+}
+
+/// Reads the average current-to-maximum CPU frequency ratio.
+///
+/// Not available on this platform, so always `None`.
+#[cfg(all(feature = "sensors", not(target_os = "linux")))]
+fn frequency_ratio() -> Option<f64> {
+    None
+}
+
+/// Returns the `cpuN` directories below `root/devices/system/cpu`.
+#[cfg(feature = "sensors")]
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn cpu_dirs(root: &Path) -> Vec<std::path::PathBuf> {
+    let Ok(entries) = std::fs::read_dir(root.join("devices/system/cpu")) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter(|entry| {
+            entry
+                .file_name()
+                .to_str()
+                .and_then(|name| name.strip_prefix("cpu"))
+                .is_some_and(|number| number.parse::<u32>().is_ok())
+        })
+        .map(|entry| entry.path())
+        .collect()
+}
+
+/// Reads a sysfs file holding one number.
+#[cfg(feature = "sensors")]
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn read_number(path: &Path) -> Option<u64> {
+    std::fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// Sums the thermal throttle counters in the sysfs tree at `root`.
+///
+/// Each core's `core_throttle_count` is counted once, although every logical
+/// CPU of the core reports it, and each package's `package_throttle_count`
+/// likewise.
+///
+/// # Returns
+///
+/// `None` if no CPU has throttle counters (e.g. AMD or virtual CPUs).
+#[cfg(feature = "sensors")]
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub fn read_throttle_events(root: &Path) -> Option<u64> {
+    use std::collections::HashMap;
+
+    let mut cores: HashMap<(u64, u64), u64> = HashMap::new();
+    let mut packages: HashMap<u64, u64> = HashMap::new();
+    for cpu in cpu_dirs(root) {
+        let throttle = cpu.join("thermal_throttle");
+        let Some(core_count) = read_number(&throttle.join("core_throttle_count")) else {
+            continue;
+        };
+        let package = read_number(&cpu.join("topology/physical_package_id")).unwrap_or(0);
+        let core = read_number(&cpu.join("topology/core_id")).unwrap_or(0);
+        cores.insert((package, core), core_count);
+        if let Some(count) = read_number(&throttle.join("package_throttle_count")) {
+            packages.insert(package, count);
+        }
+    }
+    if cores.is_empty() {
+        return None;
+    }
+    Some(cores.values().chain(packages.values()).sum())
+}
+
+/// Averages the current-to-maximum frequency ratio of all CPUs in the sysfs
+/// tree at `root`.
+#[cfg(feature = "sensors")]
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub fn read_frequency_ratio(root: &Path) -> Option<f64> {
+    let ratios: Vec<f64> = cpu_dirs(root)
+        .iter()
+        .filter_map(|cpu| {
+            let current = read_number(&cpu.join("cpufreq/scaling_cur_freq"))?;
+            let max = read_number(&cpu.join("cpufreq/cpuinfo_max_freq"))?;
+            (max > 0).then(|| current as f64 / max as f64)
+        })
+        .collect();
+    if ratios.is_empty() {
+        return None;
+    }
+    Some(ratios.iter().sum::<f64>() / ratios.len() as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// Creates an empty directory for a fake sysfs tree named `name`.
+    fn fake_tree(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("thermal-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+        root
+    }
+
+    /// Writes `contents` to `path` below `root`, creating its directories.
+    fn write(root: &Path, path: &str, contents: &str) {
+        let path = root.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+
+    #[test]
+    fn test_read_core_temperatures() {
+        // Package 0 with cores 0 and 1; cpu0 and cpu2 are on core 0, cpu1
+        // and cpu3 on core 1
+        let root = fake_tree("cores");
+        write(&root, "class/hwmon/hwmon0/name", "acpitz\n");
+        write(&root, "class/hwmon/hwmon1/name", "coretemp\n");
+        for (sensor, label, input) in [
+            ("temp1", "Package id 0", "60000"),
+            ("temp2", "Core 0", "52000"),
+            ("temp3", "Core 1", "58000"),
+        ] {
+            write(
+                &root,
+                &format!("class/hwmon/hwmon1/{}_label", sensor),
+                label,
+            );
+            write(
+                &root,
+                &format!("class/hwmon/hwmon1/{}_input", sensor),
+                input,
+            );
+        }
+        for (cpu, core) in [(0, 0), (1, 1), (2, 0), (3, 1)] {
+            let topology = format!("devices/system/cpu/cpu{}/topology", cpu);
+            write(&root, &format!("{}/physical_package_id", topology), "0\n");
+            write(
+                &root,
+                &format!("{}/core_id", topology),
+                &format!("{}\n", core),
+            );
+        }
+        assert_eq!(
+            read_core_temperatures(&root),
+            Some(vec![52.0, 58.0, 52.0, 58.0])
+        );
+
+        // Without a coretemp sensor, e.g. on AMD
+        std::fs::remove_dir_all(root.join("class/hwmon/hwmon1")).unwrap();
+        assert_eq!(read_core_temperatures(&root), None);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[cfg(feature = "sensors")]
+    #[test]
+    fn test_is_throttled() {
+        assert!(is_throttled(Some(0.5), 98.0, Some(100.0)));
+        // The boundaries: exactly 80% is not throttled, exactly 5 °C below
+        // critical is near it
+        assert!(!is_throttled(Some(0.8), 98.0, Some(100.0)));
+        assert!(is_throttled(Some(0.79), 95.0, Some(100.0)));
+        assert!(!is_throttled(Some(0.79), 94.9, Some(100.0)));
+        assert!(!is_throttled(Some(0.95), 98.0, Some(100.0)));
+        assert!(!is_throttled(Some(0.5), 60.0, Some(100.0)));
+        assert!(!is_throttled(None, 98.0, Some(100.0)));
+        assert!(!is_throttled(Some(0.5), 98.0, None));
+    }
+
+    #[cfg(feature = "sensors")]
+    #[test]
+    fn test_tracker_record() {
+        use std::time::{Duration, Instant};
+
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
+        let mut tracker = Tracker::new();
+
+        // Cool and at full speed; unreadable sensors are ignored
+        let info = tracker.record(
+            &[(45.0, Some(100.0)), (f32::NAN, None)],
+            Some(7),
+            Some(1.0),
+            at(0),
+        );
+        assert!(!info.throttled);
+        assert_eq!(info.max_temp_seen, Some(45.0));
+        assert_eq!(info.throttle_events, Some(7));
+        assert_eq!(info.seconds_above_critical, 0.0);
+
+        // Hot and slowed down: throttled, and the time until the next
+        // reading counts as above critical
+        let info = tracker.record(
+            &[(101.0, Some(100.0)), (50.0, None)],
+            Some(7),
+            Some(0.6),
+            at(10),
+        );
+        assert!(info.throttled);
+        assert_eq!(info.max_temp_seen, Some(101.0));
+        let info = tracker.record(&[(99.0, Some(100.0))], Some(7), Some(0.9), at(25));
+        assert!(!info.throttled);
+        assert_eq!(info.seconds_above_critical, 15.0);
+        assert_eq!(info.max_temp_seen, Some(101.0));
+
+        // New kernel throttle events alone mark the sample as throttled
+        let info = tracker.record(&[(60.0, Some(100.0))], Some(9), Some(1.0), at(30));
+        assert!(info.throttled);
+        assert_eq!(info.seconds_above_critical, 15.0);
+        let info = tracker.record(&[(60.0, Some(100.0))], Some(9), Some(1.0), at(40));
+        assert!(!info.throttled);
+    }
+
+    #[cfg(feature = "sensors")]
+    #[test]
+    fn test_read_throttle_events() {
+        // cpu0 and cpu1 share core 0 of package 0, so its counters are
+        // counted once
+        let root = fake_tree("throttle");
+        assert_eq!(read_throttle_events(&root), None);
+        for cpu in ["cpu0", "cpu1"] {
+            let dir = format!("devices/system/cpu/{}", cpu);
+            write(
+                &root,
+                &format!("{}/thermal_throttle/core_throttle_count", dir),
+                "3\n",
+            );
+            write(
+                &root,
+                &format!("{}/thermal_throttle/package_throttle_count", dir),
+                "2\n",
+            );
+            write(
+                &root,
+                &format!("{}/topology/physical_package_id", dir),
+                "0\n",
+            );
+            write(&root, &format!("{}/topology/core_id", dir), "0\n");
+        }
+        write(&root, "devices/system/cpu/cpufreq/policy0", "");
+        assert_eq!(read_throttle_events(&root), Some(5));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[cfg(feature = "sensors")]
+    #[test]
+    fn test_read_frequency_ratio() {
+        // cpu0 at 1200000 of 2400000 kHz and cpu1 at 2400000 of 2400000 kHz
+        let root = fake_tree("frequency");
+        assert_eq!(read_frequency_ratio(&root), None);
+        for (cpu, current) in [("cpu0", "1200000"), ("cpu1", "2400000")] {
+            let dir = format!("devices/system/cpu/{}/cpufreq", cpu);
+            write(&root, &format!("{}/scaling_cur_freq", dir), current);
+            write(&root, &format!("{}/cpuinfo_max_freq", dir), "2400000");
+        }
+        assert_eq!(read_frequency_ratio(&root), Some(0.75));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,634 @@
+//! Snapshot timestamps and the `--timezone` they are shown in.
+//!
+//! The snapshot records when it was collected as an RFC 3339 time in UTC.
+//! The console and Markdown reports show it in UTC, the local time zone or
+//! a named IANA zone such as "America/New_York". Zones come from the
+//! system's compiled tz database (`/usr/share/zoneinfo`, or `$TZDIR`): the
+//! TZif file's transitions cover the past, and the POSIX TZ rule in its
+//! footer covers the years after the last transition.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Directory of the compiled tz database when `$TZDIR` is not set.
+const ZONEINFO_DIR: &str = "/usr/share/zoneinfo";
+
+/// A point in time, in whole seconds since the Unix epoch. Serialized as an
+/// RFC 3339 time in UTC, e.g. "2026-10-15T12:34:56Z".
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Timestamp(pub i64);
+
+impl Timestamp {
+    /// Returns the current time.
+    pub fn now() -> Timestamp {
+        Timestamp(
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs() as i64),
+        )
+    }
+
+    /// Formats the time as RFC 3339 in UTC.
+    pub fn rfc3339(self) -> String {
+        format!("{}Z", civil_time(self.0).replace(' ', "T"))
+    }
+
+    /// Parses an RFC 3339 time with a `Z` or `+hh:mm` offset; fractional
+    /// seconds are dropped.
+    pub fn parse(text: &str) -> Option<Timestamp> {
+        let number = |range: std::ops::Range<usize>| -> Option<i64> {
+            let digits = text.get(range)?;
+            digits
+                .bytes()
+                .all(|byte| byte.is_ascii_digit())
+                .then(|| digits.parse().ok())?
+        };
+        let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+        let (hour, minute, second) = (number(11..13)?, number(14..16)?, number(17..19)?);
+        let separators = [(4, b'-'), (7, b'-'), (13, b':'), (16, b':')];
+        if text.len() < 20
+            || !separators
+                .iter()
+                .all(|&(at, byte)| text.as_bytes()[at] == byte)
+            || !matches!(text.as_bytes()[10], b'T' | b't' | b' ')
+            || !(1..=12).contains(&month)
+            || !(1..=days_in_month(year, month)).contains(&day)
+            || hour > 23
+            || minute > 59
+            || second > 60
+        {
+            return None;
+        }
+        let rest = text[19..].trim_start_matches(|c: char| c == '.' || c.is_ascii_digit());
+        let offset = match rest {
+            "Z" | "z" => 0,
+            _ => {
+                let sign = match rest.as_bytes().first()? {
+                    b'+' => 1,
+                    b'-' => -1,
+                    _ => return None,
+                };
+                let (hours, minutes) = rest[1..].split_once(':')?;
+                if hours.len() != 2 || minutes.len() != 2 {
+                    return None;
+                }
+                sign * (hours.parse::<i64>().ok()? * 3600 + minutes.parse::<i64>().ok()? * 60)
+            }
+        };
+        let seconds =
+            days_from_civil(year, month, day) * 86_400 + hour * 3600 + minute * 60 + second.min(59);
+        Some(Timestamp(seconds - offset))
+    }
+}
+
+impl Serialize for Timestamp {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.rfc3339())
+    }
+}
+
+impl<'de> Deserialize<'de> for Timestamp {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Timestamp, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        Timestamp::parse(&text)
+            .ok_or_else(|| serde::de::Error::custom(format!("invalid RFC 3339 time '{}'", text)))
+    }
+}
+
+/// A time zone to show times in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeZone {
+    /// As given to `--timezone`, or "UTC"
+    name: String,
+    rules: Rules,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Rules {
+    Utc,
+    Tzif(Tzif),
+}
+
+/// The parts of a TZif file needed to find the offset at a time.
+#[derive(Debug, Clone, PartialEq)]
+struct Tzif {
+    /// Transition times with the index of the local time type from then on
+    transitions: Vec<(i64, usize)>,
+    types: Vec<LocalType>,
+    /// Rule for the times after the last transition
+    footer: Option<PosixRule>,
+}
+
+/// An offset from UTC and its abbreviation, e.g. -18000 and "EST".
+#[derive(Debug, Clone, PartialEq)]
+struct LocalType {
+    /// Seconds east of UTC
+    offset: i64,
+    abbreviation: String,
+}
+
+/// A POSIX TZ rule such as `EST5EDT,M3.2.0,M11.1.0`.
+#[derive(Debug, Clone, PartialEq)]
+struct PosixRule {
+    standard: LocalType,
+    /// Daylight saving time and the transitions into and out of it
+    daylight: Option<(LocalType, Transition, Transition)>,
+}
+
+/// A yearly transition `Mm.w.d/time`: weekday `d` (0 is Sunday) of week
+/// `w` (5 is the last) of month `m`, at `time` seconds after local midnight.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Transition {
+    month: i64,
+    week: i64,
+    weekday: i64,
+    time: i64,
+}
+
+impl Default for TimeZone {
+    fn default() -> TimeZone {
+        TimeZone {
+            name: "UTC".to_string(),
+            rules: Rules::Utc,
+        }
+    }
+}
+
+impl TimeZone {
+    /// Loads the zone `name`: "UTC", "local" for the system's zone (`$TZ`,
+    /// else `/etc/localtime`), or an IANA name from the tz database.
+    ///
+    /// # Errors
+    ///
+    /// Returns why the zone cannot be loaded, e.g. an unknown name.
+    pub fn load(name: &str) -> Result<TimeZone, String> {
+        let path = match name {
+            "UTC" | "Etc/UTC" | "Z" => return Ok(TimeZone::default()),
+            "local" => match std::env::var("TZ") {
+                Ok(tz) if !tz.is_empty() => {
+                    let tz = tz.trim_start_matches(':');
+                    if Path::new(tz).is_absolute() {
+                        PathBuf::from(tz)
+                    } else {
+                        zoneinfo_path(tz)?
+                    }
+                }
+                _ => PathBuf::from("/etc/localtime"),
+            },
+            _ => zoneinfo_path(name)?,
+        };
+        let bytes = std::fs::read(&path)
+            .map_err(|e| format!("unknown time zone '{}' ({}: {})", name, path.display(), e))?;
+        let tzif = parse_tzif(&bytes)
+            .ok_or_else(|| format!("{} is not a valid TZif file", path.display()))?;
+        Ok(TimeZone {
+            name: name.to_string(),
+            rules: Rules::Tzif(tzif),
+        })
+    }
+
+    /// Formats `time` in this zone for people, e.g.
+    /// "2026-10-15 08:34:56 EDT (-04:00)", or "2026-10-15 12:34:56 UTC".
+    pub fn format(&self, time: Timestamp) -> String {
+        if let Rules::Utc = self.rules {
+            return format!("{} UTC", civil_time(time.0));
+        }
+        let local = self.local_type(time.0);
+        let sign = if local.offset < 0 { '-' } else { '+' };
+        let offset = local.offset.abs();
+        format!(
+            "{} {} ({}{:02}:{:02})",
+            civil_time(time.0 + local.offset),
+            local.abbreviation,
+            sign,
+            offset / 3600,
+            offset % 3600 / 60
+        )
+    }
+
+    /// Returns the offset from UTC and its abbreviation at `time`.
+    fn local_type(&self, time: i64) -> LocalType {
+        let utc = LocalType {
+            offset: 0,
+            abbreviation: "UTC".to_string(),
+        };
+        let Rules::Tzif(tzif) = &self.rules else {
+            return utc;
+        };
+        let after = tzif.transitions.partition_point(|&(at, _)| at <= time);
+        let from_footer = after == tzif.transitions.len();
+        match (&tzif.footer, after.checked_sub(1)) {
+            (Some(footer), _) if from_footer => footer.local_type(time),
+            (_, Some(last)) => tzif.types.get(tzif.transitions[last].1).cloned(),
+            // Before the first transition
+            (_, None) => tzif.types.first().cloned(),
+        }
+        .unwrap_or(utc)
+    }
+}
+
+impl std::fmt::Display for TimeZone {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+impl PosixRule {
+    /// Returns the offset from UTC and its abbreviation at `time`.
+    fn local_type(&self, time: i64) -> Option<LocalType> {
+        let Some((daylight, start, end)) = &self.daylight else {
+            return Some(self.standard.clone());
+        };
+        let (year, _, _) = civil_date((time + self.standard.offset).div_euclid(86_400));
+        // Into daylight saving time at a standard time, out of it at a
+        // daylight saving time
+        let start = start.at(year) - self.standard.offset;
+        let end = end.at(year) - daylight.offset;
+        let in_daylight = if start < end {
+            start <= time && time < end
+        } else {
+            !(end <= time && time < start)
+        };
+        Some(if in_daylight {
+            daylight.clone()
+        } else {
+            self.standard.clone()
+        })
+    }
+}
+
+impl Transition {
+    /// Returns the transition in `year` in local seconds since the epoch.
+    fn at(&self, year: i64) -> i64 {
+        let first = days_from_civil(year, self.month, 1);
+        // 1970-01-01 was a Thursday
+        let first_weekday = (first + 4).rem_euclid(7);
+        let mut day = 1 + (self.weekday - first_weekday).rem_euclid(7) + (self.week - 1) * 7;
+        while day > days_in_month(year, self.month) {
+            day -= 7;
+        }
+        (first + day - 1) * 86_400 + self.time
+    }
+}
+
+/// Returns the path of the zone `name` in the tz database.
+fn zoneinfo_path(name: &str) -> Result<PathBuf, String> {
+    let valid = !name.is_empty()
+        && Path::new(name)
+            .components()
+            .all(|component| matches!(component, std::path::Component::Normal(_)));
+    if !valid {
+        return Err(format!("invalid time zone name '{}'", name));
+    }
+    let dir = std::env::var_os("TZDIR").map_or_else(|| PathBuf::from(ZONEINFO_DIR), PathBuf::from);
+    Ok(dir.join(name))
+}
+
+/// Parses a TZif file (RFC 8536), using the 64-bit data and footer of
+/// version 2 and later files.
+fn parse_tzif(bytes: &[u8]) -> Option<Tzif> {
+    let header = |at: usize| -> Option<[usize; 6]> {
+        if bytes.get(at..at + 4)? != b"TZif" {
+            return None;
+        }
+        let mut counts = [0; 6];
+        for (index, count) in counts.iter_mut().enumerate() {
+            let start = at + 20 + index * 4;
+            *count = u32::from_be_bytes(bytes.get(start..start + 4)?.try_into().ok()?) as usize;
+        }
+        Some(counts)
+    };
+    // isutcnt, isstdcnt, leapcnt, timecnt, typecnt, charcnt
+    let block_len = |[isut, isstd, leap, time, types, chars]: [usize; 6], time_size: usize| {
+        time * time_size + time + types * 6 + chars + leap * (time_size + 4) + isstd + isut
+    };
+
+    let counts = header(0)?;
+    let version = *bytes.get(4)?;
+    let (at, counts, time_size) = if version >= b'2' {
+        let at = 44 + block_len(counts, 4);
+        (at, header(at)?, 8)
+    } else {
+        (0, counts, 4)
+    };
+    let [_, _, _, time_count, type_count, char_count] = counts;
+    let data = bytes.get(at + 44..at + 44 + block_len(counts, time_size))?;
+
+    let times = &data[..time_count * time_size];
+    let indices = &data[time_count * time_size..time_count * (time_size + 1)];
+    let types_at = time_count * (time_size + 1);
+    let chars = &data[types_at + type_count * 6..types_at + type_count * 6 + char_count];
+
+    let transitions = times
+        .chunks_exact(time_size)
+        .zip(indices)
+        .map(|(time, &index)| {
+            let time = match time_size {
+                8 => i64::from_be_bytes(time.try_into().ok()?),
+                _ => i64::from(i32::from_be_bytes(time.try_into().ok()?)),
+            };
+            Some((time, usize::from(index)))
+        })
+        .collect::<Option<Vec<_>>>()?;
+    let types = data[types_at..types_at + type_count * 6]
+        .chunks_exact(6)
+        .map(|entry| {
+            let offset = i64::from(i32::from_be_bytes(entry[..4].try_into().ok()?));
+            let name = chars.get(usize::from(entry[5])..)?;
+            let name = &name[..name.iter().position(|&byte| byte == 0)?];
+            Some(LocalType {
+                offset,
+                abbreviation: String::from_utf8_lossy(name).into_owned(),
+            })
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    let footer = (time_size == 8)
+        .then(|| {
+            let rest = bytes.get(at + 44 + block_len(counts, 8)..)?;
+            let text = std::str::from_utf8(rest).ok()?.trim_matches('\n');
+            parse_posix_rule(text)
+        })
+        .flatten();
+    Some(Tzif {
+        transitions,
+        types,
+        footer,
+    })
+}
+
+/// Parses a POSIX TZ rule. Only `Mm.w.d` transitions are understood, which
+/// is what the tz database uses.
+fn parse_posix_rule(text: &str) -> Option<PosixRule> {
+    let mut rest = text;
+    let standard_name = take_name(&mut rest)?;
+    let standard_offset = -take_offset(&mut rest)?;
+    let standard = LocalType {
+        offset: standard_offset,
+        abbreviation: standard_name,
+    };
+    if rest.is_empty() {
+        return Some(PosixRule {
+            standard,
+            daylight: None,
+        });
+    }
+    let daylight_name = take_name(&mut rest)?;
+    let daylight_offset = match rest.starts_with(',') {
+        true => standard_offset + 3600,
+        false => -take_offset(&mut rest)?,
+    };
+    let (start, end) = rest.strip_prefix(',')?.split_once(',')?;
+    Some(PosixRule {
+        standard,
+        daylight: Some((
+            LocalType {
+                offset: daylight_offset,
+                abbreviation: daylight_name,
+            },
+            parse_transition(start)?,
+            parse_transition(end)?,
+        )),
+    })
+}
+
+/// Takes a zone abbreviation, alphabetic or quoted in `<>`, off `text`.
+fn take_name(text: &mut &str) -> Option<String> {
+    let (name, rest) = match text.strip_prefix('<') {
+        Some(quoted) => {
+            let (name, rest) = quoted.split_once('>')?;
+            (name, rest)
+        }
+        None => {
+            let end = text
+                .find(|c: char| !c.is_ascii_alphabetic())
+                .unwrap_or(text.len());
+            text.split_at(end)
+        }
+    };
+    *text = rest;
+    (name.len() >= 3).then(|| name.to_string())
+}
+
+/// Takes a `[+-]hh[:mm[:ss]]` offset off `text`, in seconds.
+fn take_offset(text: &mut &str) -> Option<i64> {
+    let end = text
+        .find(|c: char| !(c.is_ascii_digit() || matches!(c, ':' | '+' | '-')))
+        .unwrap_or(text.len());
+    let (offset, rest) = text.split_at(end);
+    *text = rest;
+    parse_time(offset)
+}
+
+/// Parses `[+-]hh[:mm[:ss]]` into seconds.
+fn parse_time(text: &str) -> Option<i64> {
+    let (sign, digits) = match text.strip_prefix('-') {
+        Some(digits) => (-1, digits),
+        None => (1, text.strip_prefix('+').unwrap_or(text)),
+    };
+    let mut seconds = 0;
+    let mut parts = 0;
+    for (part, scale) in digits.split(':').zip([3600, 60, 1]) {
+        seconds += part.parse::<i64>().ok()? * scale;
+        parts += 1;
+    }
+    (parts == digits.split(':').count()).then_some(sign * seconds)
+}
+
+/// Parses `Mm.w.d[/time]`; the time defaults to 02:00.
+fn parse_transition(text: &str) -> Option<Transition> {
+    let (date, time) = match text.split_once('/') {
+        Some((date, time)) => (date, parse_time(time)?),
+        None => (text, 7200),
+    };
+    let mut fields = date.strip_prefix('M')?.split('.');
+    let mut field = || fields.next()?.parse::<i64>().ok();
+    let (month, week, weekday) = (field()?, field()?, field()?);
+    ((1..=12).contains(&month) && (1..=5).contains(&week) && (0..=6).contains(&weekday)).then_some(
+        Transition {
+            month,
+            week,
+            weekday,
+            time,
+        },
+    )
+}
+
+/// Formats `seconds` since the epoch as "YYYY-MM-DD hh:mm:ss".
+fn civil_time(seconds: i64) -> String {
+    let (year, month, day) = civil_date(seconds.div_euclid(86_400));
+    let of_day = seconds.rem_euclid(86_400);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        of_day / 3600,
+        of_day % 3600 / 60,
+        of_day % 60
+    )
+}
+
+/// Converts days since the Unix epoch to a (year, month, day) date.
+///
+/// Uses Howard Hinnant's civil-from-days algorithm.
+fn civil_date(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    (yoe + era * 400 + i64::from(month <= 2), month, day)
+}
+
+/// Converts a (year, month, day) date to days since the Unix epoch.
+///
+/// Uses Howard Hinnant's days-from-civil algorithm.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Returns the number of days in `month` of `year`.
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timestamp_rfc3339() {
+        assert_eq!(Timestamp(0).rfc3339(), "1970-01-01T00:00:00Z");
+        assert_eq!(Timestamp(1_760_400_000).rfc3339(), "2025-10-14T00:00:00Z");
+        assert_eq!(Timestamp(1_709_210_096).rfc3339(), "2024-02-29T12:34:56Z");
+    }
+
+    #[test]
+    fn test_timestamp_parse() {
+        assert_eq!(
+            Timestamp::parse("2025-10-14T00:00:00Z"),
+            Some(Timestamp(1_760_400_000))
+        );
+        assert_eq!(
+            Timestamp::parse("2025-10-13T20:00:00.250-04:00"),
+            Some(Timestamp(1_760_400_000))
+        );
+        assert_eq!(
+            Timestamp::parse("2025-10-14 05:30:00+05:30"),
+            Some(Timestamp(1_760_400_000))
+        );
+        assert_eq!(Timestamp::parse("2025-10-14"), None);
+        assert_eq!(Timestamp::parse("2025-13-14T00:00:00Z"), None);
+    }
+
+    #[test]
+    fn test_time_zone_load() {
+        assert_eq!(TimeZone::load("UTC").unwrap(), TimeZone::default());
+        assert!(TimeZone::load("Mars/Olympus_Mons").is_err());
+        assert!(TimeZone::load("../../etc/passwd").is_err());
+    }
+
+    #[test]
+    fn test_time_zone_format() {
+        let new_york = TimeZone {
+            name: "America/New_York".to_string(),
+            rules: Rules::Tzif(Tzif {
+                transitions: Vec::new(),
+                types: Vec::new(),
+                footer: parse_posix_rule("EST5EDT,M3.2.0,M11.1.0"),
+            }),
+        };
+        // Daylight saving time from 2026-03-08 07:00 UTC to 2026-11-01 06:00 UTC
+        assert_eq!(
+            new_york.format(Timestamp(1_792_067_696)),
+            "2026-10-15 08:34:56 EDT (-04:00)"
+        );
+        assert_eq!(
+            new_york.format(Timestamp(1_793_512_799)),
+            "2026-11-01 01:59:59 EDT (-04:00)"
+        );
+        assert_eq!(
+            new_york.format(Timestamp(1_793_512_800)),
+            "2026-11-01 01:00:00 EST (-05:00)"
+        );
+        assert_eq!(
+            new_york.format(Timestamp(1_772_953_199)),
+            "2026-03-08 01:59:59 EST (-05:00)"
+        );
+        assert_eq!(
+            new_york.format(Timestamp(1_772_953_200)),
+            "2026-03-08 03:00:00 EDT (-04:00)"
+        );
+
+        // Southern hemisphere: daylight saving time spans the new year
+        let sydney = TimeZone {
+            name: "Australia/Sydney".to_string(),
+            rules: Rules::Tzif(Tzif {
+                transitions: Vec::new(),
+                types: Vec::new(),
+                footer: parse_posix_rule("AEST-10AEDT,M10.1.0,M4.1.0/3"),
+            }),
+        };
+        assert_eq!(
+            sydney.format(Timestamp(1_767_225_600)),
+            "2026-01-01 11:00:00 AEDT (+11:00)"
+        );
+        assert_eq!(
+            sydney.format(Timestamp(1_781_136_000)),
+            "2026-06-11 10:00:00 AEST (+10:00)"
+        );
+
+        assert_eq!(
+            TimeZone::default().format(Timestamp(1_792_067_696)),
+            "2026-10-15 12:34:56 UTC"
+        );
+    }
+
+    #[test]
+    fn test_parse_posix_rule() {
+        let rule = parse_posix_rule("CET-1CEST,M3.5.0,M10.5.0/3").unwrap();
+        assert_eq!(
+            rule.standard,
+            LocalType {
+                offset: 3600,
+                abbreviation: "CET".to_string()
+            }
+        );
+        let (daylight, start, end) = rule.daylight.unwrap();
+        assert_eq!(daylight.offset, 7200);
+        assert_eq!(
+            start,
+            Transition {
+                month: 3,
+                week: 5,
+                weekday: 0,
+                time: 7200
+            }
+        );
+        assert_eq!(end.time, 3 * 3600);
+
+        let india = parse_posix_rule("IST-5:30").unwrap();
+        assert_eq!(india.standard.offset, 19_800);
+        assert_eq!(india.daylight, None);
+        assert_eq!(
+            parse_posix_rule("<-03>3").unwrap().standard.abbreviation,
+            "-03"
+        );
+        assert_eq!(parse_posix_rule(""), None);
+    }
+}
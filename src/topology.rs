@@ -0,0 +1,477 @@
+//! `--storage-topology`: software RAID arrays and LVM volumes (Linux only).
+//!
+//! A file system on `/dev/mapper/vg0-root` says nothing about which disks
+//! hold it. Software RAID arrays come from `/proc/mdstat`, with their level,
+//! members and whether they run degraded. LVM logical volumes are the
+//! device mapper devices in `/sys/block` whose `dm/uuid` starts with "LVM-";
+//! the volume group is part of their `dm/name`, and the physical volumes are
+//! the devices in their `slaves` directories. LVM's internal layers, such as
+//! the `-real` and `-cow` devices of a snapshot or a thin pool's `-tpool`,
+//! are followed to the devices below them but not listed.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+/// Size of the blocks `/proc/mdstat` counts in.
+const MDSTAT_BLOCK_SIZE: u64 = 1024;
+
+/// Size of the sectors `/sys/block/*/size` counts in.
+const SECTOR_SIZE: u64 = 512;
+
+/// Length of the volume group and logical volume UUIDs after "LVM-" in a
+/// device mapper UUID; anything after them marks an internal layer.
+const LVM_UUID_LEN: usize = 64;
+
+/// RAID arrays and LVM volume groups.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct StorageTopology {
+    /// Software RAID arrays, by name
+    pub md_arrays: Vec<MdArray>,
+    /// LVM volume groups with active logical volumes, by name
+    pub volume_groups: Vec<VolumeGroup>,
+}
+
+/// A software RAID array.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MdArray {
+    /// Kernel name, e.g. "md0"
+    pub name: String,
+    /// "active" or "inactive"
+    pub state: String,
+    /// RAID level, e.g. "raid1", or `None` while the array is inactive
+    pub level: Option<String>,
+    /// Usable size in bytes
+    pub size: Option<u64>,
+    /// Working members, e.g. "sda1", in the order of their role number
+    pub devices: Vec<String>,
+    /// Members the kernel marked as failed
+    pub failed_devices: Vec<String>,
+    /// Spare members
+    pub spare_devices: Vec<String>,
+    /// Whether fewer members are in sync than the array has slots
+    pub degraded: bool,
+}
+
+/// An LVM volume group.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct VolumeGroup {
+    pub name: String,
+    /// Devices the active logical volumes are stored on, e.g. "sda2",
+    /// "md0" or a "dm-N" encrypted device
+    pub physical_volumes: Vec<String>,
+    /// Active logical volumes, by name
+    pub logical_volumes: Vec<LogicalVolume>,
+}
+
+/// An active LVM logical volume.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LogicalVolume {
+    pub name: String,
+    /// Kernel name, e.g. "dm-0"
+    pub device: String,
+    /// Size in bytes
+    pub size: Option<u64>,
+    /// Physical volumes this logical volume has extents on
+    pub backing_devices: Vec<String>,
+}
+
+/// A device mapper device in `/sys/block`.
+#[derive(Debug)]
+struct MapperDevice {
+    name: String,
+    uuid: String,
+    size: Option<u64>,
+    slaves: Vec<String>,
+}
+
+/// Reads the RAID arrays and LVM volumes.
+///
+/// # Errors
+///
+/// Returns why `/sys/block` cannot be read. A missing `/proc/mdstat`, as
+/// without the md driver, means there are no arrays.
+#[cfg(target_os = "linux")]
+pub fn query() -> Result<StorageTopology, String> {
+    let mdstat = std::fs::read_to_string("/proc/mdstat").unwrap_or_default();
+    let volume_groups = read_volume_groups(Path::new("/sys/block"))
+        .map_err(|e| format!("could not read /sys/block: {}", e))?;
+    Ok(StorageTopology {
+        md_arrays: parse_mdstat(&mdstat),
+        volume_groups,
+    })
+}
+
+/// Reads the RAID arrays and LVM volumes.
+///
+/// Not available on this platform, so this always returns an error.
+#[cfg(not(target_os = "linux"))]
+pub fn query() -> Result<StorageTopology, String> {
+    Err("the storage topology is only read on Linux".to_string())
+}
+
+/// Parses the arrays in the contents of `/proc/mdstat`.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_mdstat(text: &str) -> Vec<MdArray> {
+    let mut arrays = Vec::new();
+    let mut lines = text.lines().peekable();
+    while let Some(line) = lines.next() {
+        let Some((name, rest)) = line.split_once(" : ") else {
+            continue;
+        };
+        let name = name.trim();
+        if !name.starts_with("md") {
+            continue;
+        }
+        // "active", then flags such as "(auto-read-only)"
+        let mut words = rest
+            .split_whitespace()
+            .filter(|word| !word.starts_with('('));
+        let state = words.next().unwrap_or_default().to_string();
+        let mut level = None;
+        let mut members = Vec::new();
+        for word in words {
+            match word.split_once('[') {
+                Some((device, role)) => {
+                    let (number, flags) = role.split_once(']').unwrap_or((role, ""));
+                    members.push((number.parse().unwrap_or(usize::MAX), device, flags));
+                }
+                None if level.is_none() && members.is_empty() => level = Some(word.to_string()),
+                None => {}
+            }
+        }
+        members.sort();
+
+        let mut array = MdArray {
+            name: name.to_string(),
+            state,
+            level,
+            size: None,
+            devices: Vec::new(),
+            failed_devices: Vec::new(),
+            spare_devices: Vec::new(),
+            degraded: false,
+        };
+        for (_, device, flags) in members {
+            let list = if flags.contains("(F)") {
+                &mut array.failed_devices
+            } else if flags.contains("(S)") {
+                &mut array.spare_devices
+            } else {
+                &mut array.devices
+            };
+            list.push(device.to_string());
+        }
+        // e.g. "1046528 blocks super 1.2 [2/1] [U_]"
+        if let Some(status) = lines.next_if(|line| line.starts_with(char::is_whitespace)) {
+            let words: Vec<&str> = status.split_whitespace().collect();
+            if let Some(blocks) = words
+                .windows(2)
+                .find(|pair| pair[1] == "blocks")
+                .and_then(|pair| pair[0].parse::<u64>().ok())
+            {
+                array.size = Some(blocks.saturating_mul(MDSTAT_BLOCK_SIZE));
+            }
+            array.degraded = words.iter().any(|word| {
+                let counts = word
+                    .strip_prefix('[')
+                    .and_then(|word| word.strip_suffix(']'));
+                match counts.and_then(|counts| counts.split_once('/')) {
+                    Some((slots, in_sync)) => matches!(
+                        (slots.parse::<usize>(), in_sync.parse::<usize>()),
+                        (Ok(slots), Ok(in_sync)) if in_sync < slots
+                    ),
+                    None => false,
+                }
+            });
+        }
+        arrays.push(array);
+    }
+    arrays.sort_by(|a, b| a.name.cmp(&b.name));
+    arrays
+}
+
+/// Reads the active LVM volumes from the device mapper devices in the
+/// `sys_block` directory (normally `/sys/block`).
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn read_volume_groups(sys_block: &Path) -> std::io::Result<Vec<VolumeGroup>> {
+    let mut mapper = BTreeMap::new();
+    for entry in std::fs::read_dir(sys_block)? {
+        let path = entry?.path();
+        let read = |name: &str| -> Option<String> {
+            let text = std::fs::read_to_string(path.join(name)).ok()?;
+            Some(text.trim().to_string())
+        };
+        let (Some(name), Some(uuid)) = (read("dm/name"), read("dm/uuid")) else {
+            continue;
+        };
+        let slaves = match std::fs::read_dir(path.join("slaves")) {
+            Ok(entries) => entries
+                .filter_map(|entry| Some(entry.ok()?.file_name().to_string_lossy().into_owned()))
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        let device = path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned();
+        mapper.insert(
+            device,
+            MapperDevice {
+                name,
+                size: read("size")
+                    .and_then(|sectors| sectors.parse::<u64>().ok())
+                    .map(|sectors| sectors.saturating_mul(SECTOR_SIZE)),
+                uuid,
+                slaves,
+            },
+        );
+    }
+
+    let mut groups: BTreeMap<String, VolumeGroup> = BTreeMap::new();
+    for (device, mapper_device) in &mapper {
+        let Some(layer) = mapper_device.uuid.strip_prefix("LVM-") else {
+            continue;
+        };
+        if layer.len() != LVM_UUID_LEN {
+            continue;
+        }
+        let Some((vg_name, lv_name)) = split_lvm_name(&mapper_device.name) else {
+            continue;
+        };
+        let mut backing = BTreeSet::new();
+        backing_devices(&mapper, device, &mut backing);
+        let group = groups
+            .entry(vg_name.clone())
+            .or_insert_with(|| VolumeGroup {
+                name: vg_name,
+                physical_volumes: Vec::new(),
+                logical_volumes: Vec::new(),
+            });
+        group.logical_volumes.push(LogicalVolume {
+            name: lv_name,
+            device: device.clone(),
+            size: mapper_device.size,
+            backing_devices: backing.into_iter().collect(),
+        });
+    }
+    Ok(groups
+        .into_values()
+        .map(|mut group| {
+            group.logical_volumes.sort_by(|a, b| a.name.cmp(&b.name));
+            let physical: BTreeSet<&String> = group
+                .logical_volumes
+                .iter()
+                .flat_map(|lv| &lv.backing_devices)
+                .collect();
+            group.physical_volumes = physical.into_iter().cloned().collect();
+            group
+        })
+        .collect())
+}
+
+/// Adds the devices below the LVM device `device` to `backing`, following
+/// the slaves that are LVM devices themselves, such as a snapshot's origin.
+fn backing_devices(
+    mapper: &BTreeMap<String, MapperDevice>,
+    device: &str,
+    backing: &mut BTreeSet<String>,
+) {
+    let Some(mapper_device) = mapper.get(device) else {
+        return;
+    };
+    for slave in &mapper_device.slaves {
+        let is_lvm = mapper
+            .get(slave)
+            .is_some_and(|slave| slave.uuid.starts_with("LVM-"));
+        // A device that is its own slave would otherwise loop forever
+        if is_lvm && slave != device {
+            backing_devices(mapper, slave, backing);
+        } else {
+            backing.insert(slave.clone());
+        }
+    }
+}
+
+/// Splits a device mapper name into volume group and logical volume names.
+/// LVM doubles the hyphens within each name and joins them with a single
+/// hyphen.
+fn split_lvm_name(name: &str) -> Option<(String, String)> {
+    let mut vg = String::new();
+    let mut chars = name.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '-' {
+            vg.push(c);
+        } else if chars.next_if_eq(&'-').is_some() {
+            vg.push('-');
+        } else {
+            let lv = chars.collect::<String>().replace("--", "-");
+            return Some((vg, lv));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mdstat() {
+        let mdstat = "Personalities : [raid1] [raid6] [raid5] [raid4]
+md1 : active raid5 sdd1[3](F) sdc1[2] sdb1[1] sde1[4](S)
+      2093056 blocks super 1.2 level 5, 512k chunk, algorithm 2 [3/2] [_UU]
+
+md0 : active (auto-read-only) raid1 sdb2[1] sda2[0]
+      1046528 blocks super 1.2 [2/2] [UU]
+
+md127 : inactive sdf[0](S)
+      1046528 blocks super 1.2
+
+unused devices: <none>
+";
+        let arrays = parse_mdstat(mdstat);
+        assert_eq!(arrays.len(), 3);
+        assert_eq!(
+            arrays[0],
+            MdArray {
+                name: "md0".to_string(),
+                state: "active".to_string(),
+                level: Some("raid1".to_string()),
+                size: Some(1071644672),
+                devices: vec!["sda2".to_string(), "sdb2".to_string()],
+                failed_devices: vec![],
+                spare_devices: vec![],
+                degraded: false,
+            }
+        );
+        assert_eq!(arrays[1].name, "md1");
+        assert_eq!(arrays[1].devices, ["sdb1", "sdc1"]);
+        assert_eq!(arrays[1].failed_devices, ["sdd1"]);
+        assert_eq!(arrays[1].spare_devices, ["sde1"]);
+        assert!(arrays[1].degraded);
+        assert_eq!(arrays[2].state, "inactive");
+        assert_eq!(arrays[2].level, None);
+        assert_eq!(arrays[2].spare_devices, ["sdf"]);
+        assert!(!arrays[2].degraded);
+
+        assert_eq!(
+            parse_mdstat("Personalities : \nunused devices: <none>\n"),
+            []
+        );
+    }
+
+    #[test]
+    fn test_read_volume_groups() {
+        let dir = std::env::temp_dir().join(format!("topology-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let device = |name: &str, dm_name: &str, uuid: &str, sectors: u64, slaves: &[&str]| {
+            let path = dir.join(name);
+            std::fs::create_dir_all(path.join("dm")).unwrap();
+            std::fs::create_dir_all(path.join("slaves")).unwrap();
+            std::fs::write(path.join("dm/name"), format!("{}\n", dm_name)).unwrap();
+            std::fs::write(path.join("dm/uuid"), format!("{}\n", uuid)).unwrap();
+            std::fs::write(path.join("size"), format!("{}\n", sectors)).unwrap();
+            for slave in slaves {
+                std::fs::write(path.join("slaves").join(slave), "").unwrap();
+            }
+        };
+        let vg = "a".repeat(32);
+        let lv = |c: &str| format!("LVM-{}{}", vg, c.repeat(32));
+        // vg--data-root on md0 and sdc1, with a snapshot of vg--data-home
+        device(
+            "dm-0",
+            "vg--data-root",
+            &lv("b"),
+            41943040,
+            &["md0", "sdc1"],
+        );
+        device(
+            "dm-1",
+            "vg--data-home-real",
+            &format!("{}-real", lv("c")),
+            2097152,
+            &["md0"],
+        );
+        device("dm-2", "vg--data-home", &lv("c"), 2097152, &["dm-1"]);
+        device(
+            "dm-3",
+            "vg--data-home--snap",
+            &lv("d"),
+            2097152,
+            &["dm-1", "dm-4"],
+        );
+        device(
+            "dm-4",
+            "vg--data-home--snap-cow",
+            &format!("{}-cow", lv("d")),
+            204800,
+            &["sdc1"],
+        );
+        // A LUKS device, which is not a logical volume
+        device(
+            "dm-5",
+            "luks-1234",
+            "CRYPT-LUKS2-1234-luks-1234",
+            41943040,
+            &["sdb2"],
+        );
+        std::fs::create_dir_all(dir.join("sda")).unwrap();
+
+        let groups = read_volume_groups(&dir).unwrap();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].name, "vg-data");
+        assert_eq!(groups[0].physical_volumes, ["md0", "sdc1"]);
+        let volumes: Vec<(&str, &str, Option<u64>, Vec<String>)> = groups[0]
+            .logical_volumes
+            .iter()
+            .map(|lv| {
+                (
+                    lv.name.as_str(),
+                    lv.device.as_str(),
+                    lv.size,
+                    lv.backing_devices.clone(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            volumes,
+            [
+                ("home", "dm-2", Some(1073741824), vec!["md0".to_string()]),
+                (
+                    "home-snap",
+                    "dm-3",
+                    Some(1073741824),
+                    vec!["md0".to_string(), "sdc1".to_string()]
+                ),
+                (
+                    "root",
+                    "dm-0",
+                    Some(21474836480),
+                    vec!["md0".to_string(), "sdc1".to_string()]
+                ),
+            ]
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(read_volume_groups(&dir).is_err());
+    }
+
+    #[test]
+    fn test_split_lvm_name() {
+        assert_eq!(
+            split_lvm_name("vg0-root"),
+            Some(("vg0".to_string(), "root".to_string()))
+        );
+        assert_eq!(
+            split_lvm_name("ubuntu--vg-ubuntu--lv"),
+            Some(("ubuntu-vg".to_string(), "ubuntu-lv".to_string()))
+        );
+        assert_eq!(
+            split_lvm_name("vg--a-b--"),
+            Some(("vg-a".to_string(), "b-".to_string()))
+        );
+        assert_eq!(split_lvm_name("no--hyphen"), None);
+    }
+}
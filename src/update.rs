@@ -0,0 +1,275 @@
+//! The `self-update` subcommand.
+//!
+//! Checks the GitHub releases API for a newer version, downloads the asset
+//! for the current platform, verifies it against the SHA-256 checksum
+//! published alongside it, and replaces the running executable. HTTP
+//! requests are made through the system `curl` binary so the network code
+//! stays free of TLS dependencies; `--update-url` points the check at a
+//! different releases endpoint, such as a local test server.
+
+use crate::http;
+use crate::sha256;
+use crate::AppError;
+use serde::Deserialize;
+use std::env;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Releases endpoint queried when no `--update-url` is given.
+pub const DEFAULT_UPDATE_URL: &str =
+    "https://api.github.com/repos/zuedev/RustGetSystemInfo/releases/latest";
+
+/// Exit code of `self-update --check-only` when a newer release exists.
+pub const EXIT_UPDATE_AVAILABLE: i32 = 8;
+
+/// The subset of a GitHub release object used for updating.
+#[derive(Deserialize)]
+struct Release {
+    tag_name: String,
+    #[serde(default)]
+    assets: Vec<Asset>,
+}
+
+/// A downloadable file attached to a release.
+#[derive(Deserialize)]
+struct Asset {
+    name: String,
+    browser_download_url: String,
+}
+
+/// Runs the `self-update` subcommand.
+///
+/// With `check_only` set, only reports whether a newer release exists.
+///
+/// # Returns
+///
+/// The process exit code: `0` when already up to date or after a successful
+/// update, `EXIT_UPDATE_AVAILABLE` when `check_only` found a newer release.
+///
+/// # Errors
+///
+/// Returns `AppError::Update` if the release information cannot be fetched,
+/// the platform asset or its checksum is missing, verification fails, or the
+/// executable cannot be replaced.
+pub fn self_update(url: &str, check_only: bool, client: &http::Client) -> Result<i32, AppError> {
+    let body = fetch(client, url, Some("application/vnd.github+json"))?;
+    let release: Release = serde_json::from_slice(&body)
+        .map_err(|e| AppError::Update(format!("invalid release information: {}", e)))?;
+
+    let current = env!("CARGO_PKG_VERSION");
+    if !is_newer(&release.tag_name, current) {
+        println!("RustGetSystemInfo {} is up to date", current);
+        return Ok(0);
+    }
+
+    println!(
+        "Update available: {} (current version {})",
+        release.tag_name, current
+    );
+    if check_only {
+        return Ok(EXIT_UPDATE_AVAILABLE);
+    }
+
+    let asset_name = platform_asset_name();
+    let find_asset = |name: &str| {
+        release
+            .assets
+            .iter()
+            .find(|asset| asset.name == name)
+            .ok_or_else(|| AppError::Update(format!("release has no asset named {}", name)))
+    };
+    let asset = find_asset(&asset_name)?;
+    let checksum_asset = find_asset(&format!("{}.sha256", asset_name))?;
+
+    let checksum =
+        String::from_utf8_lossy(&fetch(client, &checksum_asset.browser_download_url, None)?)
+            .split_whitespace()
+            .next()
+            .map(str::to_lowercase)
+            .ok_or_else(|| AppError::Update("published checksum is empty".to_string()))?;
+
+    let current_exe = env::current_exe()
+        .map_err(|e| AppError::Update(format!("cannot locate running executable: {}", e)))?;
+    let staged = staged_path(&current_exe);
+
+    download(client, &asset.browser_download_url, &staged)?;
+    if let Err(e) = verify(&staged, &checksum) {
+        let _ = fs::remove_file(&staged);
+        return Err(e);
+    }
+    replace_executable(&current_exe, &staged)?;
+
+    println!("Updated to {}", release.tag_name);
+    Ok(0)
+}
+
+/// Fetches `url` and returns the response body, asking for the media type
+/// `accept` if given.
+fn fetch(client: &http::Client, url: &str, accept: Option<&str>) -> Result<Vec<u8>, AppError> {
+    let mut command = curl(client, url);
+    if let Some(accept) = accept {
+        command.args(["-H", &format!("Accept: {}", accept)]);
+    }
+    let output = command
+        .arg(url)
+        .output()
+        .map_err(|e| AppError::Update(format!("could not run curl: {}", e)))?;
+    if !output.status.success() {
+        return Err(AppError::Update(format!(
+            "request to {} failed: {}",
+            url,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(output.stdout)
+}
+
+/// Downloads `url` into the file at `destination`.
+fn download(client: &http::Client, url: &str, destination: &Path) -> Result<(), AppError> {
+    let status = curl(client, url)
+        .arg("-o")
+        .arg(destination)
+        .arg(url)
+        .status()
+        .map_err(|e| AppError::Update(format!("could not run curl: {}", e)))?;
+    if !status.success() {
+        let _ = fs::remove_file(destination);
+        return Err(AppError::Update(format!("download of {} failed", url)));
+    }
+    Ok(())
+}
+
+/// Builds a quiet, redirect-following curl invocation for `url` that fails
+/// on HTTP errors.
+fn curl(client: &http::Client, url: &str) -> Command {
+    let mut command = client.curl(url);
+    command.args([
+        "-fsSL",
+        "-A",
+        concat!("RustGetSystemInfo/", env!("CARGO_PKG_VERSION")),
+    ]);
+    command
+}
+
+/// Checks that the SHA-256 digest of `path` matches `expected`.
+fn verify(path: &Path, expected: &str) -> Result<(), AppError> {
+    let file = File::open(path)
+        .map_err(|e| AppError::Update(format!("cannot read downloaded file: {}", e)))?;
+    let actual = sha256::hex_digest(file)
+        .map_err(|e| AppError::Update(format!("cannot read downloaded file: {}", e)))?;
+    if actual != expected {
+        return Err(AppError::Update(format!(
+            "checksum mismatch: expected {}, got {}",
+            expected, actual
+        )));
+    }
+    Ok(())
+}
+
+/// Atomically moves the verified `staged` file over `current_exe`.
+///
+/// On Windows the running executable is locked against deletion but may be
+/// renamed, so it is first moved aside to `<name>.old`.
+fn replace_executable(current_exe: &Path, staged: &Path) -> Result<(), AppError> {
+    let replace_error = |e: std::io::Error| {
+        let _ = fs::remove_file(staged);
+        AppError::Update(format!("cannot replace executable: {}", e))
+    };
+
+    #[cfg(unix)]
+    {
+        let permissions = fs::metadata(current_exe)
+            .map_err(replace_error)?
+            .permissions();
+        fs::set_permissions(staged, permissions).map_err(replace_error)?;
+    }
+
+    #[cfg(windows)]
+    {
+        let old = current_exe.with_extension("old");
+        let _ = fs::remove_file(&old);
+        fs::rename(current_exe, &old).map_err(replace_error)?;
+    }
+
+    fs::rename(staged, current_exe).map_err(replace_error)
+}
+
+/// Returns the path the new executable is downloaded to before replacement.
+///
+/// It lives next to the current executable so the final rename stays on one
+/// filesystem.
+fn staged_path(current_exe: &Path) -> PathBuf {
+    let name = current_exe
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    current_exe.with_file_name(format!(".{}.new", name))
+}
+
+/// Returns the release asset name for this platform, matching the CI
+/// artifact naming (e.g. `RustGetSystemInfo-windows.exe`).
+fn platform_asset_name() -> String {
+    format!(
+        "RustGetSystemInfo-{}{}",
+        env::consts::OS,
+        env::consts::EXE_SUFFIX
+    )
+}
+
+/// Returns true when release tag `tag` denotes a later version than `current`.
+///
+/// Versions are compared numerically component by component, ignoring a
+/// leading `v` and any pre-release suffix; missing components count as
+/// zero, so `1.0` and `1.0.0` are the same version.
+fn is_newer(tag: &str, current: &str) -> bool {
+    fn components(version: &str) -> Vec<u64> {
+        let mut components: Vec<u64> = version
+            .trim_start_matches('v')
+            .split(['-', '+'])
+            .next()
+            .unwrap_or_default()
+            .split('.')
+            .map(|part| part.parse().unwrap_or(0))
+            .collect();
+        while components.last() == Some(&0) {
+            components.pop();
+        }
+        components
+    }
+
+    components(tag) > components(current)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_newer() {
+        assert!(is_newer("v1.2.0", "1.1.9"));
+        assert!(is_newer("1.10.0", "1.9.0"));
+        assert!(is_newer("2", "1.99.99"));
+        assert!(is_newer("1.0.1", "1.0"));
+        assert!(!is_newer("1.0", "1.0.0"));
+        assert!(!is_newer("v1.0.0", "1.0"));
+        assert!(!is_newer("1.0.0", "1.0.0"));
+        assert!(!is_newer("1.1.0", "1.2.0"));
+        assert!(!is_newer("1.0.0-rc.1", "1.0.0"));
+        assert!(!is_newer("1.0.0+build.5", "1.0.0"));
+    }
+
+    #[test]
+    fn test_platform_asset_name() {
+        let name = platform_asset_name();
+        assert!(name.starts_with("RustGetSystemInfo-"));
+        assert!(name.contains(env::consts::OS));
+        assert!(name.ends_with(env::consts::EXE_SUFFIX));
+    }
+
+    #[test]
+    fn test_staged_path() {
+        let staged = staged_path(Path::new("/usr/local/bin/RustGetSystemInfo"));
+        assert_eq!(staged, Path::new("/usr/local/bin/.RustGetSystemInfo.new"));
+    }
+}
@@ -0,0 +1,549 @@
+//! The `verify` subcommand: compares detected hardware with an inventory spec.
+//!
+//! A spec is a small YAML document describing what a host should have:
+//!
+//! ```yaml
+//! cpu_cores: 8
+//! memory: 64GB ±5%
+//! swap: 8GB ± 1GB
+//! disks:
+//!   count: 2
+//!   min_size:
+//!     /: 100GB
+//!     /data: 1TB
+//! interfaces: [eth0, eth1]
+//! ```
+//!
+//! Tolerances are written after `±` (or `+-`) as an absolute amount or a
+//! percentage of the expected value. Keys missing from the spec are simply
+//! not checked. Only the subset of YAML used by this format is understood:
+//! block mappings, block (`- item`) and flow (`[a, b]`) lists of scalars,
+//! plain or quoted scalars, and `#` comments.
+
+use crate::locale::Locale;
+use crate::{format_bytes, parse_bytes, SystemInfo};
+
+/// Allowed deviation from an expected value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Tolerance {
+    /// A fixed amount in the unit of the value
+    Absolute(f64),
+    /// A percentage of the expected value
+    Percent(f64),
+}
+
+/// Whether a quantity is a plain count or a size in bytes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Unit {
+    Count,
+    Bytes,
+}
+
+/// An expected value with a tolerance, e.g. "64GB ±5%".
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Expectation {
+    expected: f64,
+    tolerance: Tolerance,
+    unit: Unit,
+}
+
+impl Expectation {
+    /// Parses "<value>", "<value> ±<amount>", or "<value> ±<percent>%".
+    fn parse(text: &str, unit: Unit) -> Result<Expectation, String> {
+        let (value, tolerance) = match ["±", "+/-", "+-"]
+            .iter()
+            .find_map(|sep| text.split_once(sep))
+        {
+            Some((value, tolerance)) => (value, Some(tolerance.trim())),
+            None => (text, None),
+        };
+
+        let expected = parse_quantity(value, unit)?;
+        let tolerance = match tolerance {
+            None => Tolerance::Absolute(0.0),
+            Some(t) => match t.strip_suffix('%') {
+                Some(percent) => Tolerance::Percent(
+                    percent
+                        .trim()
+                        .parse()
+                        .map_err(|_| format!("invalid percentage '{}'", t))?,
+                ),
+                None => Tolerance::Absolute(parse_quantity(t, unit)?),
+            },
+        };
+
+        Ok(Expectation {
+            expected,
+            tolerance,
+            unit,
+        })
+    }
+
+    /// Returns the largest allowed distance from the expected value.
+    fn allowed_deviation(&self) -> f64 {
+        match self.tolerance {
+            Tolerance::Absolute(amount) => amount,
+            Tolerance::Percent(percent) => self.expected.abs() * percent / 100.0,
+        }
+    }
+
+    /// Returns true if `observed` lies within the tolerance, boundaries included.
+    fn matches(&self, observed: f64) -> bool {
+        // A relative epsilon keeps values exactly on a percentage boundary from
+        // failing because of rounding in the percentage computation.
+        let epsilon = self.expected.abs() * 1e-12;
+        (observed - self.expected).abs() <= self.allowed_deviation() + epsilon
+    }
+
+    /// Formats a value of this expectation's unit for display.
+    fn format_value(&self, value: f64) -> String {
+        match self.unit {
+            Unit::Count => format!("{}", value),
+            Unit::Bytes => format_bytes(value.round() as u64, 0, Locale::default()),
+        }
+    }
+
+    /// Formats the expectation as written in a spec, e.g. "64.00 GB ±5%".
+    fn describe(&self) -> String {
+        let value = self.format_value(self.expected);
+        match self.tolerance {
+            Tolerance::Absolute(0.0) => value,
+            Tolerance::Absolute(amount) => format!("{} ±{}", value, self.format_value(amount)),
+            Tolerance::Percent(percent) => format!("{} ±{}%", value, percent),
+        }
+    }
+}
+
+/// Parses a number, or a byte size when `unit` is `Unit::Bytes`.
+fn parse_quantity(text: &str, unit: Unit) -> Result<f64, String> {
+    let text = text.trim();
+    let value = match unit {
+        Unit::Count => text.parse().ok(),
+        Unit::Bytes => parse_bytes(text).map(|bytes| bytes as f64),
+    };
+    value.ok_or_else(|| format!("invalid value '{}'", text))
+}
+
+/// The expected hardware inventory of a host.
+#[derive(Debug, Default, PartialEq)]
+pub struct Spec {
+    cpu_cores: Option<Expectation>,
+    memory: Option<Expectation>,
+    swap: Option<Expectation>,
+    disk_count: Option<Expectation>,
+    disk_min_sizes: Vec<(String, u64)>,
+    interfaces: Vec<String>,
+}
+
+impl Spec {
+    /// Parses a spec document.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the problem if the document is not valid
+    /// YAML of the supported subset, contains unknown keys, or has values
+    /// that cannot be parsed.
+    pub fn parse(text: &str) -> Result<Spec, String> {
+        let mut spec = Spec::default();
+
+        for (key, node) in expect_map(parse_yaml(text)?, "spec")? {
+            match key.as_str() {
+                "cpu_cores" => spec.cpu_cores = Some(expectation(&node, &key, Unit::Count)?),
+                "memory" => spec.memory = Some(expectation(&node, &key, Unit::Bytes)?),
+                "swap" => spec.swap = Some(expectation(&node, &key, Unit::Bytes)?),
+                "disks" => {
+                    for (key, node) in expect_map(node, "disks")? {
+                        match key.as_str() {
+                            "count" => {
+                                spec.disk_count =
+                                    Some(expectation(&node, "disks.count", Unit::Count)?)
+                            }
+                            "min_size" => {
+                                for (mount, node) in expect_map(node, "disks.min_size")? {
+                                    let size = match &node {
+                                        Node::Scalar(value) => parse_bytes(value),
+                                        _ => None,
+                                    }
+                                    .ok_or_else(|| {
+                                        format!("disks.min_size.{}: expected a size", mount)
+                                    })?;
+                                    spec.disk_min_sizes.push((mount, size));
+                                }
+                            }
+                            _ => return Err(format!("unknown key 'disks.{}'", key)),
+                        }
+                    }
+                }
+                "interfaces" => {
+                    spec.interfaces = match node {
+                        Node::List(items) => items,
+                        Node::Scalar(item) if !item.is_empty() => vec![item],
+                        _ => return Err("interfaces: expected a list of names".to_string()),
+                    }
+                }
+                _ => return Err(format!("unknown key '{}'", key)),
+            }
+        }
+
+        Ok(spec)
+    }
+}
+
+/// Converts a scalar node to an `Expectation`, naming `key` in errors.
+fn expectation(node: &Node, key: &str, unit: Unit) -> Result<Expectation, String> {
+    match node {
+        Node::Scalar(text) => Expectation::parse(text, unit).map_err(|e| format!("{}: {}", key, e)),
+        _ => Err(format!("{}: expected a value", key)),
+    }
+}
+
+/// Unwraps a mapping node, naming `key` in errors.
+fn expect_map(node: Node, key: &str) -> Result<Vec<(String, Node)>, String> {
+    match node {
+        Node::Map(entries) => Ok(entries),
+        _ => Err(format!("{}: expected a mapping", key)),
+    }
+}
+
+/// The outcome of checking one expectation against the live system.
+#[derive(Debug, PartialEq)]
+pub struct CheckResult {
+    /// What was checked, e.g. "memory" or "disk /data"
+    pub name: String,
+    /// The expectation from the spec, formatted for display
+    pub expected: String,
+    /// The value found on this system, formatted for display
+    pub observed: String,
+    /// Whether the observed value met the expectation
+    pub passed: bool,
+}
+
+/// Compares `info` against every expectation in `spec`.
+///
+/// # Returns
+///
+/// One result per expectation, in spec order: scalar checks first, then
+/// disks, then interfaces.
+pub fn verify(spec: &Spec, info: &SystemInfo) -> Vec<CheckResult> {
+    let mut results = Vec::new();
+
+    let scalar_checks = [
+        ("cpu_cores", spec.cpu_cores, info.cpu_cores as f64),
+        ("memory", spec.memory, info.total_memory as f64),
+        ("swap", spec.swap, info.total_swap as f64),
+        ("disks.count", spec.disk_count, info.disks.len() as f64),
+    ];
+    for (name, expectation, observed) in scalar_checks {
+        if let Some(expectation) = expectation {
+            results.push(CheckResult {
+                name: name.to_string(),
+                expected: expectation.describe(),
+                observed: expectation.format_value(observed),
+                passed: expectation.matches(observed),
+            });
+        }
+    }
+
+    for (mount, min_size) in &spec.disk_min_sizes {
+        let disk = info.disks.iter().find(|disk| &disk.name == mount);
+        results.push(CheckResult {
+            name: format!("disk {}", mount),
+            expected: format!("at least {}", format_bytes(*min_size, 0, Locale::default())),
+            observed: disk.map_or("not found".to_string(), |d| {
+                format_bytes(d.total_space, 0, Locale::default())
+            }),
+            passed: disk.is_some_and(|d| d.total_space >= *min_size),
+        });
+    }
+
+    for interface in &spec.interfaces {
+        let present = info.networks.iter().any(|n| &n.name == interface);
+        results.push(CheckResult {
+            name: format!("interface {}", interface),
+            expected: "present".to_string(),
+            observed: if present { "present" } else { "missing" }.to_string(),
+            passed: present,
+        });
+    }
+
+    results
+}
+
+/// A parsed YAML value of the supported subset.
+#[derive(Debug, PartialEq)]
+enum Node {
+    Scalar(String),
+    List(Vec<String>),
+    Map(Vec<(String, Node)>),
+}
+
+/// A non-blank source line with its comment removed.
+struct Line<'a> {
+    number: usize,
+    indent: usize,
+    text: &'a str,
+}
+
+/// Parses a YAML document of the supported subset into a `Node`.
+fn parse_yaml(text: &str) -> Result<Node, String> {
+    let lines: Vec<Line> = text
+        .lines()
+        .enumerate()
+        .filter_map(|(index, raw)| {
+            let content = strip_comment(raw).trim_end();
+            let trimmed = content.trim_start();
+            (!trimmed.is_empty()).then(|| Line {
+                number: index + 1,
+                indent: content.len() - trimmed.len(),
+                text: trimmed,
+            })
+        })
+        .collect();
+
+    let Some(first) = lines.first() else {
+        return Ok(Node::Map(Vec::new()));
+    };
+    let mut pos = 0;
+    let node = parse_block(&lines, &mut pos, first.indent)?;
+    match lines.get(pos) {
+        Some(line) => Err(format!("line {}: unexpected indentation", line.number)),
+        None => Ok(node),
+    }
+}
+
+/// Parses consecutive lines at exactly `indent` as a list or mapping.
+fn parse_block(lines: &[Line], pos: &mut usize, indent: usize) -> Result<Node, String> {
+    if is_list_item(lines[*pos].text) {
+        let mut items = Vec::new();
+        while let Some(line) = lines.get(*pos) {
+            if line.indent != indent || !is_list_item(line.text) {
+                break;
+            }
+            items.push(unquote(line.text[1..].trim()));
+            *pos += 1;
+        }
+        return Ok(Node::List(items));
+    }
+
+    let mut entries = Vec::new();
+    while let Some(line) = lines.get(*pos) {
+        if line.indent < indent {
+            break;
+        }
+        if line.indent > indent || is_list_item(line.text) {
+            return Err(format!("line {}: unexpected indentation", line.number));
+        }
+        let (key, value) = split_key(line.text)
+            .ok_or_else(|| format!("line {}: expected 'key: value'", line.number))?;
+        *pos += 1;
+
+        let node = if !value.is_empty() {
+            parse_scalar_or_flow(value)
+        } else {
+            match lines.get(*pos) {
+                Some(next) if next.indent > indent => parse_block(lines, pos, next.indent)?,
+                Some(next) if next.indent == indent && is_list_item(next.text) => {
+                    parse_block(lines, pos, indent)?
+                }
+                _ => Node::Scalar(String::new()),
+            }
+        };
+        entries.push((key, node));
+    }
+    Ok(Node::Map(entries))
+}
+
+/// Parses an inline value: a flow list like `[a, b]` or a scalar.
+fn parse_scalar_or_flow(value: &str) -> Node {
+    match value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+        Some(inner) => Node::List(
+            inner
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(unquote)
+                .collect(),
+        ),
+        None => Node::Scalar(unquote(value)),
+    }
+}
+
+/// Returns true if a line is a block list item (`- value`).
+fn is_list_item(text: &str) -> bool {
+    text == "-" || text.starts_with("- ")
+}
+
+/// Splits `key: value` (or `key:`), allowing colons inside the key as in
+/// `C:\: 100GB` and quoted keys such as `'/mnt/a b': 1TB`.
+fn split_key(text: &str) -> Option<(String, &str)> {
+    if let Some(quote) = text.chars().next().filter(|c| *c == '"' || *c == '\'') {
+        let end = text[1..].find(quote)? + 1;
+        let rest = text[end + 1..].trim_start().strip_prefix(':')?;
+        return Some((text[1..end].to_string(), rest.trim()));
+    }
+    if let Some((key, value)) = text.split_once(": ") {
+        return Some((key.trim().to_string(), value.trim()));
+    }
+    text.strip_suffix(':')
+        .map(|key| (key.trim().to_string(), ""))
+}
+
+/// Removes surrounding single or double quotes from a scalar.
+fn unquote(value: &str) -> String {
+    let quoted = value.len() >= 2
+        && ((value.starts_with('"') && value.ends_with('"'))
+            || (value.starts_with('\'') && value.ends_with('\'')));
+    if quoted {
+        value[1..value.len() - 1].to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+/// Removes a trailing `#` comment, which must start the line or follow whitespace.
+fn strip_comment(line: &str) -> &str {
+    if line.trim_start().starts_with('#') {
+        return "";
+    }
+    match line.find(" #") {
+        Some(index) => &line[..index],
+        None => line,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{self, disk, network, MockProvider};
+
+    const SPEC: &str = "\
+# Web tier
+cpu_cores: 4
+memory: 8GB ±5%
+swap: 2GB +- 1GB
+disks:
+  count: 2
+  min_size:
+    /: 50GB
+    '/data': 1TB
+interfaces:
+  - mock0
+  - mock1
+";
+
+    #[test]
+    fn test_expectation_parse() {
+        let parse = |text| Expectation::parse(text, Unit::Bytes).unwrap();
+        assert_eq!(
+            parse("64GB ±5%"),
+            Expectation {
+                expected: (64u64 << 30) as f64,
+                tolerance: Tolerance::Percent(5.0),
+                unit: Unit::Bytes,
+            }
+        );
+        assert_eq!(
+            parse("8GB +/- 1GB").tolerance,
+            Tolerance::Absolute((1u64 << 30) as f64)
+        );
+        assert_eq!(parse("1TB").tolerance, Tolerance::Absolute(0.0));
+        assert!(Expectation::parse("8 ±x%", Unit::Count).is_err());
+        assert!(Expectation::parse("eight", Unit::Count).is_err());
+    }
+
+    #[test]
+    fn test_expectation_matches() {
+        let memory = Expectation::parse("100GB ±5%", Unit::Bytes).unwrap();
+        let gb = |value: f64| value * (1u64 << 30) as f64;
+        // Both boundaries are inside the tolerance
+        assert!(memory.matches(gb(95.0)));
+        assert!(memory.matches(gb(105.0)));
+        assert!(!memory.matches(gb(94.9)));
+        assert!(!memory.matches(gb(105.1)));
+        assert_eq!(memory.describe(), "100.00 GB ±5%");
+
+        let cores = Expectation::parse("8", Unit::Count).unwrap();
+        assert!(cores.matches(8.0));
+        assert!(!cores.matches(7.0));
+        assert_eq!(cores.describe(), "8");
+    }
+
+    #[test]
+    fn test_spec_parse() {
+        let spec = Spec::parse(SPEC).unwrap();
+        assert_eq!(spec.cpu_cores.unwrap().expected, 4.0);
+        assert_eq!(
+            spec.swap.unwrap().tolerance,
+            Tolerance::Absolute((1u64 << 30) as f64)
+        );
+        assert_eq!(
+            spec.disk_min_sizes,
+            [("/".to_string(), 50 << 30), ("/data".to_string(), 1 << 40)]
+        );
+        assert_eq!(spec.interfaces, ["mock0", "mock1"]);
+
+        // Flow lists, Windows mount points and an empty document
+        let spec =
+            Spec::parse("interfaces: [eth0, \"Wi-Fi\"]\ndisks:\n  min_size:\n    C:\\: 100GB\n")
+                .unwrap();
+        assert_eq!(spec.interfaces, ["eth0", "Wi-Fi"]);
+        assert_eq!(spec.disk_min_sizes, [("C:\\".to_string(), 100 << 30)]);
+        assert_eq!(
+            Spec::parse("# nothing to check\n").unwrap(),
+            Spec::default()
+        );
+
+        let error = |text| Spec::parse(text).unwrap_err();
+        assert_eq!(error("gpu: 1"), "unknown key 'gpu'");
+        assert_eq!(error("disks:\n  size: 1TB"), "unknown key 'disks.size'");
+        assert_eq!(error("memory: lots"), "memory: invalid value 'lots'");
+        assert_eq!(error("disks: 2"), "disks: expected a mapping");
+        assert_eq!(
+            error("cpu_cores: 4\n    swap: 1GB"),
+            "line 2: unexpected indentation"
+        );
+    }
+
+    #[test]
+    fn test_verify() {
+        let sys = MockProvider {
+            total_swap: 2 << 30,
+            disks: vec![
+                disk("/", "ext4", 100 << 30, 50 << 30),
+                disk("/data", "xfs", 512 << 30, 256 << 30),
+            ],
+            networks: vec![network("mock0", 0, 0)],
+            ..MockProvider::default()
+        };
+        let results = verify(&Spec::parse(SPEC).unwrap(), &mock::snapshot(&sys));
+        let summary: Vec<(&str, &str, &str, bool)> = results
+            .iter()
+            .map(|r| {
+                (
+                    r.name.as_str(),
+                    r.expected.as_str(),
+                    r.observed.as_str(),
+                    r.passed,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("cpu_cores", "4", "4", true),
+                ("memory", "8.00 GB ±5%", "8.00 GB", true),
+                ("swap", "2.00 GB ±1.00 GB", "2.00 GB", true),
+                ("disks.count", "2", "2", true),
+                ("disk /", "at least 50.00 GB", "100.00 GB", true),
+                ("disk /data", "at least 1.00 TB", "512.00 GB", false),
+                ("interface mock0", "present", "present", true),
+                ("interface mock1", "present", "missing", false),
+            ]
+        );
+
+        // Only the keys in the spec are checked
+        let results = verify(&Spec::parse("cpu_cores: 8").unwrap(), &mock::snapshot(&sys));
+        assert_eq!(results.len(), 1);
+        assert!(!results[0].passed);
+    }
+}
@@ -0,0 +1,296 @@
+//! File system labels and UUIDs of mounted disks.
+//!
+//! On Linux they come from the udev symlinks in `/dev/disk/by-label` and
+//! `/dev/disk/by-uuid`, matched to the mounted device by resolving both sides
+//! to the real device node, so `/dev/mapper/vg-root` and the `dm-0` the links
+//! point at are recognized as the same device. On Windows the label and the
+//! volume serial number come from `GetVolumeInformationW`, and on macOS from
+//! `diskutil info`. Other platforms, and volumes that cannot be queried
+//! (e.g. for lack of privileges), report `None`.
+
+use std::ffi::OsStr;
+use std::path::Path;
+
+/// The stable identifiers of one volume.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct VolumeIds {
+    /// File system label
+    pub label: Option<String>,
+    /// File system UUID, or the volume serial number on Windows
+    pub uuid: Option<String>,
+}
+
+/// Looks up volume identifiers, reading any shared tables once.
+#[cfg(target_os = "linux")]
+pub struct Identifier {
+    labels: std::collections::HashMap<std::path::PathBuf, String>,
+    uuids: std::collections::HashMap<std::path::PathBuf, String>,
+}
+
+#[cfg(target_os = "linux")]
+impl Identifier {
+    pub fn new() -> Identifier {
+        Identifier::with_root(Path::new("/dev/disk"))
+    }
+
+    /// Reads the `by-label` and `by-uuid` directories below `root`.
+    pub fn with_root(root: &Path) -> Identifier {
+        Identifier {
+            labels: read_links(&root.join("by-label")),
+            uuids: read_links(&root.join("by-uuid")),
+        }
+    }
+
+    /// Returns the identifiers of the volume on `device`, e.g. "/dev/sda1".
+    pub fn ids(&self, device: &OsStr, _mount_point: &Path) -> VolumeIds {
+        let Ok(device) = std::fs::canonicalize(device) else {
+            return VolumeIds::default();
+        };
+        VolumeIds {
+            label: self.labels.get(&device).cloned(),
+            uuid: self.uuids.get(&device).cloned(),
+        }
+    }
+}
+
+/// Maps the resolved target of every symlink in `dir` to the link's
+/// unescaped name.
+#[cfg(target_os = "linux")]
+fn read_links(dir: &Path) -> std::collections::HashMap<std::path::PathBuf, String> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Default::default();
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let target = std::fs::canonicalize(entry.path()).ok()?;
+            Some((target, unescape(&entry.file_name().to_string_lossy())))
+        })
+        .collect()
+}
+
+/// Decodes the `\xHH` escapes udev uses in link names.
+#[cfg(target_os = "linux")]
+fn unescape(name: &str) -> String {
+    let bytes = name.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index..].starts_with(b"\\x") {
+            let byte = name
+                .get(index + 2..index + 4)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+            if let Some(byte) = byte {
+                decoded.push(byte);
+                index += 4;
+                continue;
+            }
+        }
+        decoded.push(bytes[index]);
+        index += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Looks up volume identifiers.
+#[cfg(windows)]
+pub struct Identifier;
+
+#[cfg(windows)]
+impl Identifier {
+    pub fn new() -> Identifier {
+        Identifier
+    }
+
+    /// Returns the label and serial number of the volume at `mount_point`.
+    pub fn ids(&self, _device: &OsStr, mount_point: &Path) -> VolumeIds {
+        use std::os::windows::ffi::OsStrExt;
+
+        extern "system" {
+            fn GetVolumeInformationW(
+                root_path: *const u16,
+                volume_name: *mut u16,
+                volume_name_size: u32,
+                serial_number: *mut u32,
+                max_component_length: *mut u32,
+                file_system_flags: *mut u32,
+                file_system_name: *mut u16,
+                file_system_name_size: u32,
+            ) -> i32;
+        }
+
+        // The root path must end with a backslash, e.g. "C:\"
+        let mut root: Vec<u16> = mount_point.as_os_str().encode_wide().collect();
+        if root.last() != Some(&u16::from(b'\\')) {
+            root.push(u16::from(b'\\'));
+        }
+        root.push(0);
+        let mut name = [0u16; 261];
+        let mut serial = 0u32;
+
+        // SAFETY: `root` is NUL-terminated, `name` is as large as passed, and
+        // the optional outputs that are not needed are null.
+        let ok = unsafe {
+            GetVolumeInformationW(
+                root.as_ptr(),
+                name.as_mut_ptr(),
+                name.len() as u32,
+                &mut serial,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                0,
+            )
+        };
+        if ok == 0 {
+            return VolumeIds::default();
+        }
+        let length = name.iter().position(|&c| c == 0).unwrap_or(name.len());
+        let label = String::from_utf16_lossy(&name[..length]);
+        VolumeIds {
+            label: (!label.is_empty()).then_some(label),
+            uuid: Some(format!("{:04X}-{:04X}", serial >> 16, serial & 0xFFFF)),
+        }
+    }
+}
+
+/// Looks up volume identifiers.
+#[cfg(target_os = "macos")]
+pub struct Identifier;
+
+#[cfg(target_os = "macos")]
+impl Identifier {
+    pub fn new() -> Identifier {
+        Identifier
+    }
+
+    /// Returns the name and UUID of the volume at `mount_point`.
+    pub fn ids(&self, _device: &OsStr, mount_point: &Path) -> VolumeIds {
+        let mut command = std::process::Command::new("diskutil");
+        command.arg("info").arg(mount_point);
+        match crate::command::run_with_timeout(&mut command, std::time::Duration::from_secs(5)) {
+            Ok(output) if output.status.success() => {
+                parse_diskutil(&String::from_utf8_lossy(&output.stdout))
+            }
+            _ => VolumeIds::default(),
+        }
+    }
+}
+
+/// Extracts the volume name and UUID from `diskutil info` output.
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn parse_diskutil(output: &str) -> VolumeIds {
+    let field = |name: &str| {
+        output
+            .lines()
+            .filter_map(|line| line.trim().split_once(':'))
+            .find(|(key, _)| key.trim() == name)
+            .map(|(_, value)| value.trim().to_string())
+            .filter(|value| !value.is_empty() && !value.starts_with("Not applicable"))
+    };
+    VolumeIds {
+        label: field("Volume Name"),
+        uuid: field("Volume UUID"),
+    }
+}
+
+/// Looks up volume identifiers.
+///
+/// Not supported on this platform, so every volume reports `None`.
+#[cfg(not(any(target_os = "linux", windows, target_os = "macos")))]
+pub struct Identifier;
+
+#[cfg(not(any(target_os = "linux", windows, target_os = "macos")))]
+impl Identifier {
+    pub fn new() -> Identifier {
+        Identifier
+    }
+
+    pub fn ids(&self, _device: &OsStr, _mount_point: &Path) -> VolumeIds {
+        VolumeIds::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_identifier_ids() {
+        use std::os::unix::fs::symlink;
+
+        // A synthetic tree where `dev/mapper/vg-root` links to `../dm-0`,
+        // and `dev/disk/by-uuid/0a1b-2c3d` and `dev/disk/by-label/Data\x20Disk`
+        // link to `../../dm-0`
+        let root = std::env::temp_dir().join(format!("volume-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        for dir in ["dev/mapper", "dev/disk/by-uuid", "dev/disk/by-label"] {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        std::fs::write(root.join("dev/dm-0"), "").unwrap();
+        symlink("../dm-0", root.join("dev/mapper/vg-root")).unwrap();
+        symlink("../../dm-0", root.join("dev/disk/by-uuid/0a1b-2c3d")).unwrap();
+        symlink("../../dm-0", root.join("dev/disk/by-label/Data\\x20Disk")).unwrap();
+
+        let identifier = Identifier::with_root(&root.join("dev/disk"));
+        let ids = identifier.ids(
+            root.join("dev/mapper/vg-root").as_os_str(),
+            Path::new("/data"),
+        );
+        assert_eq!(ids.uuid.as_deref(), Some("0a1b-2c3d"));
+        assert_eq!(ids.label.as_deref(), Some("Data Disk"));
+        // pseudo file systems have no device node
+        assert_eq!(
+            identifier.ids(OsStr::new("tmpfs"), Path::new("/tmp")),
+            VolumeIds::default()
+        );
+
+        // A second device with only a UUID, and a link whose device is gone
+        std::fs::write(root.join("dev/sda1"), "").unwrap();
+        symlink("../../sda1", root.join("dev/disk/by-uuid/77aa-88bb")).unwrap();
+        symlink("../../sdz9", root.join("dev/disk/by-uuid/dead-beef")).unwrap();
+        let identifier = Identifier::with_root(&root.join("dev/disk"));
+        assert_eq!(
+            identifier.ids(root.join("dev/sda1").as_os_str(), Path::new("/boot")),
+            VolumeIds {
+                label: None,
+                uuid: Some("77aa-88bb".to_string()),
+            }
+        );
+        // The device-mapper volume keeps its own identifiers
+        let ids = identifier.ids(root.join("dev/dm-0").as_os_str(), Path::new("/data"));
+        assert_eq!(ids.uuid.as_deref(), Some("0a1b-2c3d"));
+
+        // Without the udev directories (e.g. in a container) nothing is known
+        let identifier = Identifier::with_root(&root.join("missing"));
+        assert_eq!(
+            identifier.ids(root.join("dev/sda1").as_os_str(), Path::new("/boot")),
+            VolumeIds::default()
+        );
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_unescape() {
+        assert_eq!(unescape("Data\\x20Disk"), "Data Disk");
+        assert_eq!(unescape("a\\x2fb"), "a/b");
+        assert_eq!(unescape("trailing\\x2"), "trailing\\x2");
+    }
+
+    #[test]
+    fn test_parse_diskutil() {
+        let output = "   Volume Name:               Data\n   Volume UUID:               6F1C2E1A-0B7D-4C1E-9A55-3C0D2B9E8F01\n";
+        let ids = parse_diskutil(output);
+        assert_eq!(ids.label.as_deref(), Some("Data"));
+        assert_eq!(
+            ids.uuid.as_deref(),
+            Some("6F1C2E1A-0B7D-4C1E-9A55-3C0D2B9E8F01")
+        );
+        // Volumes without a name or UUID, e.g. for a disk image being set up
+        let output = "   Volume Name:               \n   Volume UUID:               Not applicable (no file system)\n";
+        assert_eq!(parse_diskutil(output), VolumeIds::default());
+        assert_eq!(parse_diskutil(""), VolumeIds::default());
+    }
+}
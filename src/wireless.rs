@@ -0,0 +1,328 @@
+//! Wireless link details for network interfaces.
+//!
+//! On Linux the details are the nl80211 interface and station data, read
+//! through `iw dev <interface> link` rather than a netlink socket of our own,
+//! which would need a netlink crate such as `neli`; `iw` ships with every
+//! distribution that has wireless support. When `iw` is unavailable or not
+//! permitted, the signal level falls back to `/proc/net/wireless`. On Windows
+//! the details are read from `netsh wlan show interfaces` rather than the
+//! WLAN API. Other platforms report no wireless details.
+
+use serde::{Deserialize, Serialize};
+use std::ffi::OsStr;
+
+/// Link details for a wireless network interface.
+///
+/// Every field is optional because drivers and permissions vary in what they
+/// expose; a field is `None` when it could not be determined.
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct WirelessInfo {
+    /// Network name the interface is associated with
+    pub ssid: Option<String>,
+    /// Hardware address of the access point
+    pub bssid: Option<String>,
+    /// Operating frequency in MHz
+    pub frequency_mhz: Option<u32>,
+    /// Transmit link speed in Mbps
+    pub link_speed_mbps: Option<u32>,
+    /// Received signal strength in dBm
+    pub signal_dbm: Option<i32>,
+}
+
+impl WirelessInfo {
+    /// Formats the details for the console, e.g. "WiFi: MyNetwork, -52 dBm, 866 Mbps".
+    pub fn summary(&self) -> String {
+        let mut parts = vec![self
+            .ssid
+            .clone()
+            .unwrap_or_else(|| "not connected".to_string())];
+        if let Some(signal) = self.signal_dbm {
+            parts.push(format!("{} dBm", signal));
+        }
+        if let Some(speed) = self.link_speed_mbps {
+            parts.push(format!("{} Mbps", speed));
+        }
+        format!("WiFi: {}", parts.join(", "))
+    }
+}
+
+/// Queries wireless details for `interface`.
+///
+/// Returns `None` when the interface is not wireless or the platform offers
+/// no way to query it. Problems that only prevent some fields from being read
+/// are reported as warnings on stderr and yield partial data.
+#[cfg(target_os = "linux")]
+pub fn query(interface: &OsStr) -> Option<WirelessInfo> {
+    use std::path::Path;
+    use std::process::Command;
+
+    let sys_path = Path::new("/sys/class/net").join(interface);
+    if !sys_path.join("wireless").exists() && !sys_path.join("phy80211").exists() {
+        return None;
+    }
+
+    match Command::new("iw")
+        .arg("dev")
+        .arg(interface)
+        .arg("link")
+        .output()
+    {
+        Ok(output) if output.status.success() => {
+            return Some(parse_iw_link(&String::from_utf8_lossy(&output.stdout)));
+        }
+        Ok(output) => eprintln!(
+            "Warning: could not query wireless details for {}: {}",
+            interface.to_string_lossy(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ),
+        Err(e) => eprintln!(
+            "Warning: could not run iw for wireless details of {}: {}",
+            interface.to_string_lossy(),
+            e
+        ),
+    }
+
+    let signal_dbm = std::fs::read_to_string("/proc/net/wireless")
+        .ok()
+        .and_then(|contents| parse_proc_net_wireless(&contents, &interface.to_string_lossy()));
+
+    Some(WirelessInfo {
+        signal_dbm,
+        ..WirelessInfo::default()
+    })
+}
+
+/// Queries wireless details for `interface`.
+///
+/// Returns `None` when the interface is not wireless or the platform offers
+/// no way to query it. Problems that only prevent some fields from being read
+/// are reported as warnings on stderr and yield partial data.
+#[cfg(windows)]
+pub fn query(interface: &OsStr) -> Option<WirelessInfo> {
+    use std::process::Command;
+
+    let output = Command::new("netsh")
+        .args(["wlan", "show", "interfaces"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse_netsh_interfaces(
+        &String::from_utf8_lossy(&output.stdout),
+        &interface.to_string_lossy(),
+    )
+}
+
+/// Queries wireless details for `interface`.
+///
+/// Wireless details are not supported on this platform, so this always
+/// returns `None`.
+#[cfg(not(any(target_os = "linux", windows)))]
+pub fn query(_interface: &OsStr) -> Option<WirelessInfo> {
+    None
+}
+
+/// Parses the output of `iw dev <interface> link`.
+///
+/// A disconnected interface ("Not connected.") yields an empty `WirelessInfo`.
+#[cfg(target_os = "linux")]
+fn parse_iw_link(output: &str) -> WirelessInfo {
+    let mut info = WirelessInfo::default();
+
+    for line in output.lines() {
+        let line = line.trim();
+        if let Some(rest) = line.strip_prefix("Connected to ") {
+            info.bssid = rest.split_whitespace().next().map(str::to_string);
+        } else if let Some(ssid) = line.strip_prefix("SSID: ") {
+            info.ssid = Some(ssid.to_string());
+        } else if let Some(freq) = line.strip_prefix("freq: ") {
+            info.frequency_mhz = leading_number(freq).map(|f| f as u32);
+        } else if let Some(signal) = line.strip_prefix("signal: ") {
+            info.signal_dbm = leading_number(signal).map(|s| s as i32);
+        } else if let Some(rate) = line.strip_prefix("tx bitrate: ") {
+            info.link_speed_mbps = leading_number(rate).map(|r| r as u32);
+        }
+    }
+
+    info
+}
+
+/// Extracts the signal level in dBm for `interface` from `/proc/net/wireless`.
+///
+/// Data lines look like `wlan0: 0000   70.  -40.  -256  ...`, where the
+/// fourth column is the signal level.
+#[cfg(target_os = "linux")]
+fn parse_proc_net_wireless(contents: &str, interface: &str) -> Option<i32> {
+    contents.lines().find_map(|line| {
+        let (name, rest) = line.trim().split_once(':')?;
+        if name != interface {
+            return None;
+        }
+        let level = rest.split_whitespace().nth(2)?;
+        leading_number(level).map(|l| l as i32)
+    })
+}
+
+/// Extracts the block for `interface` from `netsh wlan show interfaces`.
+///
+/// netsh reports signal quality as a percentage, which is mapped onto the
+/// -100..-50 dBm range the WLAN API itself uses for its quality figure.
+#[cfg(windows)]
+fn parse_netsh_interfaces(output: &str, interface: &str) -> Option<WirelessInfo> {
+    let mut in_block = false;
+    let mut found = false;
+    let mut info = WirelessInfo::default();
+    let mut channel = None;
+
+    for line in output.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let key = key.trim();
+        let value = value.trim();
+
+        if key == "Name" {
+            in_block = value == interface;
+            found |= in_block;
+            continue;
+        }
+        if !in_block {
+            continue;
+        }
+
+        match key {
+            "SSID" => info.ssid = Some(value.to_string()),
+            "BSSID" | "AP BSSID" => info.bssid = Some(value.to_string()),
+            "Channel" => channel = value.parse::<u32>().ok(),
+            "Transmit rate (Mbps)" => {
+                info.link_speed_mbps = leading_number(value).map(|r| r as u32)
+            }
+            "Signal" => {
+                info.signal_dbm = leading_number(value).map(|pct| (pct / 2.0 - 100.0) as i32)
+            }
+            _ => {}
+        }
+    }
+
+    info.frequency_mhz = channel.map(|ch| match ch {
+        14 => 2484,
+        1..=13 => 2407 + 5 * ch,
+        _ => 5000 + 5 * ch,
+    });
+
+    found.then_some(info)
+}
+
+/// Parses the numeric prefix of a value such as "-52 dBm" or "866.7 MBit/s".
+#[cfg(any(target_os = "linux", windows))]
+fn leading_number(value: &str) -> Option<f64> {
+    let end = value
+        .char_indices()
+        .find(|&(i, c)| !(c.is_ascii_digit() || c == '.' || (i == 0 && c == '-')))
+        .map_or(value.len(), |(i, _)| i);
+    value[..end].trim_end_matches('.').parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `iw dev wlan0 link` on a laptop associated with a 5 GHz network.
+    #[cfg(target_os = "linux")]
+    const IW_CONNECTED: &str = "Connected to 3c:84:6a:12:34:56 (on wlan0)
+\tSSID: MyNetwork
+\tfreq: 5180
+\tRX: 41857621 bytes (38042 packets)
+\tTX: 5234123 bytes (21337 packets)
+\tsignal: -52 dBm
+\trx bitrate: 780.0 MBit/s VHT-MCS 8 80MHz short GI VHT-NSS 2
+\ttx bitrate: 866.7 MBit/s VHT-MCS 9 80MHz short GI VHT-NSS 2
+
+\tbss flags:\tshort-slot-time
+\tdtim period:\t1
+\tbeacon int:\t100
+";
+
+    /// `/proc/net/wireless` with one associated interface.
+    #[cfg(target_os = "linux")]
+    const PROC_NET_WIRELESS: &str =
+        "Inter-| sta-|   Quality        |   Discarded packets               | Missed | WE
+ face | tus | link level noise |  nwid  crypt   frag  retry   misc | beacon | 22
+ wlan0: 0000   58.  -52.  -256        0      0      0      0     17        0
+";
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_parse_iw_link() {
+        let info = parse_iw_link(IW_CONNECTED);
+        assert_eq!(info.ssid.as_deref(), Some("MyNetwork"));
+        assert_eq!(info.bssid.as_deref(), Some("3c:84:6a:12:34:56"));
+        assert_eq!(info.frequency_mhz, Some(5180));
+        assert_eq!(info.signal_dbm, Some(-52));
+        // The transmit rate, not the receive rate
+        assert_eq!(info.link_speed_mbps, Some(866));
+        assert_eq!(info.summary(), "WiFi: MyNetwork, -52 dBm, 866 Mbps");
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_parse_iw_link_not_connected() {
+        let info = parse_iw_link("Not connected.\n");
+        assert_eq!(info.ssid, None);
+        assert_eq!(info.signal_dbm, None);
+        assert_eq!(info.summary(), "WiFi: not connected");
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_parse_proc_net_wireless() {
+        assert_eq!(
+            parse_proc_net_wireless(PROC_NET_WIRELESS, "wlan0"),
+            Some(-52)
+        );
+        assert_eq!(parse_proc_net_wireless(PROC_NET_WIRELESS, "wlan1"), None);
+        // The header lines have no interface name
+        assert_eq!(parse_proc_net_wireless(PROC_NET_WIRELESS, "face"), None);
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn test_parse_netsh_interfaces() {
+        let output = "
+There are 2 interfaces on the system:
+
+    Name                   : Wi-Fi
+    Description            : Intel(R) Wi-Fi 6 AX201 160MHz
+    State                  : connected
+    SSID                   : MyNetwork
+    AP BSSID               : 3c:84:6a:12:34:56
+    Channel                : 36
+    Receive rate (Mbps)    : 780
+    Transmit rate (Mbps)   : 866.7
+    Signal                 : 96%
+
+    Name                   : Wi-Fi 2
+    State                  : disconnected
+";
+        let info = parse_netsh_interfaces(output, "Wi-Fi").unwrap();
+        assert_eq!(info.ssid.as_deref(), Some("MyNetwork"));
+        assert_eq!(info.bssid.as_deref(), Some("3c:84:6a:12:34:56"));
+        assert_eq!(info.frequency_mhz, Some(5180));
+        assert_eq!(info.link_speed_mbps, Some(866));
+        assert_eq!(info.signal_dbm, Some(-52));
+
+        let disconnected = parse_netsh_interfaces(output, "Wi-Fi 2").unwrap();
+        assert_eq!(disconnected.ssid, None);
+        assert!(parse_netsh_interfaces(output, "Ethernet").is_none());
+    }
+
+    #[test]
+    #[cfg(any(target_os = "linux", windows))]
+    fn test_leading_number() {
+        assert_eq!(leading_number("-52 dBm"), Some(-52.0));
+        assert_eq!(leading_number("866.7 MBit/s"), Some(866.7));
+        assert_eq!(leading_number("58."), Some(58.0));
+        assert_eq!(leading_number("dBm"), None);
+    }
+}
@@ -0,0 +1,359 @@
+//! A minimal streaming ZIP archive writer.
+//!
+//! Entries are stored uncompressed and streamed straight to the output: each
+//! local header is written with the "data descriptor" flag, so the CRC and
+//! size follow the data instead of having to be known up front, and no entry
+//! is ever buffered in memory. Archives are limited to the classic (non-ZIP64)
+//! format, i.e. 4 GiB and 65535 entries.
+
+use std::io::{self, Read, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+const DATA_DESCRIPTOR_SIGNATURE: u32 = 0x0807_4b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x0201_4b50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0605_4b50;
+
+/// Version 2.0 of the specification, the minimum for data descriptors.
+const VERSION: u16 = 20;
+/// General purpose flags: sizes in data descriptor (bit 3), UTF-8 names (bit 11).
+const FLAGS: u16 = 0x0808;
+/// Compression method 0: stored.
+const STORED: u16 = 0;
+
+/// Central directory information kept for each written entry.
+struct Entry {
+    name: String,
+    crc: u32,
+    size: u32,
+    offset: u32,
+}
+
+/// Writes a ZIP archive to `W` one entry at a time.
+pub struct ZipWriter<W: Write> {
+    out: W,
+    position: u64,
+    entries: Vec<Entry>,
+    dos_time: u16,
+    dos_date: u16,
+}
+
+impl<W: Write> ZipWriter<W> {
+    /// Starts a new archive. All entries are stamped with the current time.
+    pub fn new(out: W) -> ZipWriter<W> {
+        let (dos_time, dos_date) = dos_datetime(SystemTime::now());
+        ZipWriter {
+            out,
+            position: 0,
+            entries: Vec::new(),
+            dos_time,
+            dos_date,
+        }
+    }
+
+    /// Adds an entry named `name` containing everything readable from `data`.
+    ///
+    /// # Returns
+    ///
+    /// The number of bytes stored.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading `data` or writing the archive fails, or if
+    /// the archive would exceed the limits of the classic ZIP format.
+    pub fn add<R: Read>(&mut self, name: &str, mut data: R) -> io::Result<u64> {
+        let offset = limit(self.position)?;
+
+        let mut header = Vec::with_capacity(30 + name.len());
+        put_u32(&mut header, LOCAL_HEADER_SIGNATURE);
+        put_u16(&mut header, VERSION);
+        put_u16(&mut header, FLAGS);
+        put_u16(&mut header, STORED);
+        put_u16(&mut header, self.dos_time);
+        put_u16(&mut header, self.dos_date);
+        put_u32(&mut header, 0); // CRC, in data descriptor
+        put_u32(&mut header, 0); // compressed size, in data descriptor
+        put_u32(&mut header, 0); // uncompressed size, in data descriptor
+        put_u16(&mut header, name_length(name)?);
+        put_u16(&mut header, 0); // extra field length
+        header.extend_from_slice(name.as_bytes());
+        self.write(&header)?;
+
+        let mut crc = Crc32::new();
+        let mut size: u64 = 0;
+        let mut buffer = [0u8; 64 * 1024];
+        loop {
+            let read = match data.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            crc.update(&buffer[..read]);
+            self.write(&buffer[..read])?;
+            size += read as u64;
+        }
+        let crc = crc.finish();
+        let size = limit(size)?;
+
+        let mut descriptor = Vec::with_capacity(16);
+        put_u32(&mut descriptor, DATA_DESCRIPTOR_SIGNATURE);
+        put_u32(&mut descriptor, crc);
+        put_u32(&mut descriptor, size);
+        put_u32(&mut descriptor, size);
+        self.write(&descriptor)?;
+
+        self.entries.push(Entry {
+            name: name.to_string(),
+            crc,
+            size,
+            offset,
+        });
+        Ok(size as u64)
+    }
+
+    /// Writes the central directory and returns the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        let directory_offset = limit(self.position)?;
+        let count =
+            u16::try_from(self.entries.len()).map_err(|_| too_large("more than 65535 entries"))?;
+
+        let mut directory = Vec::new();
+        for entry in &self.entries {
+            put_u32(&mut directory, CENTRAL_HEADER_SIGNATURE);
+            put_u16(&mut directory, VERSION); // version made by
+            put_u16(&mut directory, VERSION); // version needed to extract
+            put_u16(&mut directory, FLAGS);
+            put_u16(&mut directory, STORED);
+            put_u16(&mut directory, self.dos_time);
+            put_u16(&mut directory, self.dos_date);
+            put_u32(&mut directory, entry.crc);
+            put_u32(&mut directory, entry.size);
+            put_u32(&mut directory, entry.size);
+            put_u16(&mut directory, name_length(&entry.name)?);
+            put_u16(&mut directory, 0); // extra field length
+            put_u16(&mut directory, 0); // comment length
+            put_u16(&mut directory, 0); // disk number start
+            put_u16(&mut directory, 0); // internal attributes
+            put_u32(&mut directory, 0); // external attributes
+            put_u32(&mut directory, entry.offset);
+            directory.extend_from_slice(entry.name.as_bytes());
+        }
+        let directory_size = limit(directory.len() as u64)?;
+
+        put_u32(&mut directory, END_OF_CENTRAL_DIRECTORY_SIGNATURE);
+        put_u16(&mut directory, 0); // this disk
+        put_u16(&mut directory, 0); // disk with central directory
+        put_u16(&mut directory, count);
+        put_u16(&mut directory, count);
+        put_u32(&mut directory, directory_size);
+        put_u32(&mut directory, directory_offset);
+        put_u16(&mut directory, 0); // comment length
+        self.write(&directory)?;
+
+        self.out.flush()?;
+        Ok(self.out)
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.out.write_all(bytes)?;
+        self.position += bytes.len() as u64;
+        Ok(())
+    }
+}
+
+/// Incremental CRC-32 (IEEE 802.3), as used by ZIP.
+struct Crc32 {
+    value: u32,
+}
+
+impl Crc32 {
+    const TABLE: [u32; 256] = {
+        let mut table = [0u32; 256];
+        let mut i = 0;
+        while i < 256 {
+            let mut c = i as u32;
+            let mut k = 0;
+            while k < 8 {
+                c = if c & 1 != 0 {
+                    0xedb8_8320 ^ (c >> 1)
+                } else {
+                    c >> 1
+                };
+                k += 1;
+            }
+            table[i] = c;
+            i += 1;
+        }
+        table
+    };
+
+    fn new() -> Crc32 {
+        Crc32 { value: 0xffff_ffff }
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.value =
+                Self::TABLE[((self.value ^ byte as u32) & 0xff) as usize] ^ (self.value >> 8);
+        }
+    }
+
+    fn finish(&self) -> u32 {
+        !self.value
+    }
+}
+
+/// Converts a time to the MS-DOS (time, date) pair used in ZIP headers, in UTC.
+///
+/// Times before 1980, which DOS dates cannot represent, map to 1980-01-01.
+fn dos_datetime(time: SystemTime) -> (u16, u16) {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+        .max(315_532_800); // 1980-01-01T00:00:00Z
+    let days = (seconds / 86_400) as i64;
+    let secs_of_day = seconds % 86_400;
+
+    // Civil-from-days conversion (Howard Hinnant's algorithm).
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    let time = ((secs_of_day / 3600) << 11)
+        | (((secs_of_day % 3600) / 60) << 5)
+        | ((secs_of_day % 60) / 2);
+    let date = ((year - 1980) << 9) | (month << 5) | day;
+    (time as u16, date as u16)
+}
+
+fn put_u16(buffer: &mut Vec<u8>, value: u16) {
+    buffer.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u32(buffer: &mut Vec<u8>, value: u32) {
+    buffer.extend_from_slice(&value.to_le_bytes());
+}
+
+/// Converts an offset or size to the 32-bit field used without ZIP64.
+fn limit(value: u64) -> io::Result<u32> {
+    u32::try_from(value).map_err(|_| too_large("archive larger than 4 GiB"))
+}
+
+fn name_length(name: &str) -> io::Result<u16> {
+    u16::try_from(name.len()).map_err(|_| too_large("entry name too long"))
+}
+
+fn too_large(what: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("ZIP limit exceeded: {}", what),
+    )
+}
+
+/// Reads back the entries of an archive written by `ZipWriter`, checking the
+/// central directory against the local headers, data descriptors and CRCs.
+///
+/// # Panics
+///
+/// Panics if the archive is malformed.
+#[cfg(test)]
+pub fn read_archive(bytes: &[u8]) -> Vec<(String, Vec<u8>)> {
+    let u16_at = |at: usize| u16::from_le_bytes([bytes[at], bytes[at + 1]]);
+    let u32_at = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+
+    let end = bytes.len() - 22;
+    assert_eq!(u32_at(end), END_OF_CENTRAL_DIRECTORY_SIGNATURE);
+    let count = u16_at(end + 10) as usize;
+    let directory_size = u32_at(end + 12) as usize;
+    let mut at = u32_at(end + 16) as usize;
+    assert_eq!(at + directory_size, end);
+
+    let mut entries = Vec::new();
+    for _ in 0..count {
+        assert_eq!(u32_at(at), CENTRAL_HEADER_SIGNATURE);
+        assert_eq!(u16_at(at + 10), STORED);
+        let crc = u32_at(at + 16);
+        let size = u32_at(at + 24) as usize;
+        let name_length = u16_at(at + 28) as usize;
+        let offset = u32_at(at + 42) as usize;
+        let name = String::from_utf8(bytes[at + 46..at + 46 + name_length].to_vec()).unwrap();
+        at += 46 + name_length;
+
+        assert_eq!(u32_at(offset), LOCAL_HEADER_SIGNATURE);
+        assert_eq!(
+            &bytes[offset + 30..offset + 30 + name_length],
+            name.as_bytes()
+        );
+        let data_start = offset + 30 + name_length;
+        let data = bytes[data_start..data_start + size].to_vec();
+        let descriptor = data_start + size;
+        assert_eq!(u32_at(descriptor), DATA_DESCRIPTOR_SIGNATURE);
+        assert_eq!(u32_at(descriptor + 4), crc);
+        assert_eq!(u32_at(descriptor + 8) as usize, size);
+        let mut check = Crc32::new();
+        check.update(&data);
+        assert_eq!(check.finish(), crc, "CRC mismatch in {}", name);
+        entries.push((name, data));
+    }
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zip_writer() {
+        let large: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+        let mut zip = ZipWriter::new(Vec::new());
+        assert_eq!(zip.add("empty.txt", io::empty()).unwrap(), 0);
+        assert_eq!(
+            zip.add("dir/hello.txt", &b"hello, world\n"[..]).unwrap(),
+            13
+        );
+        // Larger than the copy buffer, so written in several chunks
+        assert_eq!(zip.add("large.bin", large.as_slice()).unwrap(), 200_000);
+        let archive = zip.finish().unwrap();
+
+        let entries = read_archive(&archive);
+        let names: Vec<&str> = entries.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["empty.txt", "dir/hello.txt", "large.bin"]);
+        assert!(entries[0].1.is_empty());
+        assert_eq!(entries[1].1, b"hello, world\n");
+        assert_eq!(entries[2].1, large);
+
+        let empty = ZipWriter::new(Vec::new()).finish().unwrap();
+        assert!(read_archive(&empty).is_empty());
+    }
+
+    #[test]
+    fn test_crc32() {
+        let mut crc = Crc32::new();
+        crc.update(b"123456789");
+        assert_eq!(crc.finish(), 0xcbf4_3926);
+        // Incremental updates give the same result
+        let mut crc = Crc32::new();
+        crc.update(b"1234");
+        crc.update(b"56789");
+        assert_eq!(crc.finish(), 0xcbf4_3926);
+        assert_eq!(Crc32::new().finish(), 0);
+    }
+
+    #[test]
+    fn test_dos_datetime() {
+        // 2026-10-14T19:20:31Z; DOS times have two-second resolution
+        let time = UNIX_EPOCH + std::time::Duration::from_secs(1_792_005_631);
+        assert_eq!(
+            dos_datetime(time),
+            ((19 << 11) | (20 << 5) | 15, (46 << 9) | (10 << 5) | 14)
+        );
+        assert_eq!(dos_datetime(UNIX_EPOCH), (0, (1 << 5) | 1));
+    }
+}
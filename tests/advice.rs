@@ -0,0 +1,39 @@
+//! `--advise` end to end: the advice is printed below the console report,
+//! and only reaches the JSON snapshot with `--advise-json`.
+
+use std::process::Command;
+
+/// Runs the binary with `args`, returning its stdout.
+fn run(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_RustGetSystemInfo"))
+        .args(args)
+        .output()
+        .expect("failed to run RustGetSystemInfo");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn advice_is_printed_on_the_console() {
+    let report = run(&["--advise"]);
+    let advice = report.split("\nAdvice:\n").nth(1).unwrap();
+    assert!(advice.starts_with("  "));
+}
+
+#[test]
+fn advice_reaches_json_only_when_asked() {
+    let report: serde_json::Value =
+        serde_json::from_str(&run(&["--advise", "--format", "json"])).unwrap();
+    assert!(report["advice"].is_null());
+
+    let report: serde_json::Value =
+        serde_json::from_str(&run(&["--advise-json", "--format", "json"])).unwrap();
+    for advice in report["advice"].as_array().unwrap() {
+        assert!(advice["rule"].is_string());
+        assert!(advice["message"].is_string());
+    }
+}
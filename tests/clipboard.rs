@@ -0,0 +1,40 @@
+//! `--copy` in an SSH session: the OSC 52 sequence goes to the terminal,
+//! never into redirected stdout.
+
+#![cfg(unix)]
+
+use std::process::{Command, Output};
+
+/// Runs the binary as if in an SSH session, with `args` and stdout captured,
+/// in a scratch directory named after `name`.
+fn run_over_ssh(name: &str, args: &[&str]) -> Output {
+    let dir = std::env::temp_dir().join(format!("clipboard-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_RustGetSystemInfo"))
+        .current_dir(&dir)
+        .args(args)
+        .env("SSH_CONNECTION", "192.0.2.1 50000 192.0.2.2 22")
+        .env("SSH_TTY", "/dev/pts/0")
+        .output()
+        .expect("failed to run RustGetSystemInfo");
+    std::fs::remove_dir_all(&dir).unwrap();
+    output
+}
+
+#[test]
+fn redirected_json_stays_clean() {
+    let output = run_over_ssh("json", &["--format", "json", "--copy-json"]);
+    assert!(output.status.success());
+    assert!(!output.stdout.windows(4).any(|bytes| bytes == b"\x1b]52"));
+    let snapshot: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(snapshot["total_memory"].as_u64().unwrap() > 0);
+}
+
+#[test]
+fn redirected_text_stays_clean() {
+    let output = run_over_ssh("text", &["--copy"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(!stdout.contains('\x1b'));
+    assert!(stdout.contains("System information saved to"));
+}
@@ -0,0 +1,87 @@
+//! Container usage from captured Docker API responses: runs the binary with
+//! `--containers` against a Unix socket that answers each request with the
+//! fixture in `tests/containers/`.
+//!
+//! Run with `cargo test --features containers`.
+
+#![cfg(all(feature = "containers", unix))]
+
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixListener;
+use std::process::Command;
+
+/// Fixture served for a request line, by path prefix.
+const ROUTES: &[(&str, &str)] = &[
+    ("/containers/json", include_str!("containers/list.json")),
+    (
+        "/containers/8dfafdbc3a40f3b9c0f5c7c3f1d1f2c6a1a2b3c4d5e6f708192a3b4c5d6e7f80/stats",
+        include_str!("containers/stats-web.json"),
+    ),
+    (
+        "/containers/3c1e7b2a9f8d6c5b4a3928171605f4e3d2c1b0a99887766554433221100ffeed/stats",
+        include_str!("containers/stats-db.json"),
+    ),
+];
+
+#[test]
+fn reports_running_containers_from_api_fixtures() {
+    let dir = std::env::temp_dir().join(format!("sysinfo-containers-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let socket = dir.join("docker.sock");
+    let listener = UnixListener::bind(&socket).unwrap();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut request = String::new();
+            BufReader::new(&stream).read_line(&mut request).unwrap();
+            let path = request.split_whitespace().nth(1).unwrap_or_default();
+            let response = match ROUTES.iter().find(|(route, _)| path.starts_with(route)) {
+                Some((_, body)) => format!(
+                    "HTTP/1.0 200 OK\r\nContent-Type: application/json\r\n\r\n{}",
+                    body
+                ),
+                None => "HTTP/1.0 404 Not Found\r\n\r\n".to_string(),
+            };
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+
+    let status = Command::new(env!("CARGO_BIN_EXE_RustGetSystemInfo"))
+        .args(["-q", "--containers", "--docker-socket"])
+        .arg(&socket)
+        .current_dir(&dir)
+        .status()
+        .expect("failed to run RustGetSystemInfo");
+    let snapshot = std::fs::read_to_string(dir.join("system_info.json"));
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert!(status.success());
+
+    let snapshot: serde_json::Value = serde_json::from_str(&snapshot.unwrap()).unwrap();
+    let containers = &snapshot["containers"];
+    assert_eq!(containers["stopped"], 1);
+    assert_eq!(
+        containers["running"],
+        serde_json::json!([
+            {
+                // cgroup v1 and host networking: CPUs counted from
+                // percpu_usage, no interfaces of its own
+                "name": "db",
+                "image": "postgres:16",
+                "memory_usage": 202375168,
+                "memory_limit": 16624103424u64,
+                "cpu_percent": 60.0,
+                "network_bytes_received": null,
+                "network_bytes_transmitted": null
+            },
+            {
+                "name": "web",
+                "image": "nginx:1.25",
+                "memory_usage": 7684096,
+                "memory_limit": 268435456,
+                "cpu_percent": 4.0,
+                "network_bytes_received": 1843220,
+                "network_bytes_transmitted": 912004
+            }
+        ])
+    );
+}
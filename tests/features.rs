@@ -0,0 +1,55 @@
+//! Feature matrix: checks the crate with `core` alone and with each optional
+//! feature on top of it, warnings denied, as `cargo hack --each-feature`
+//! would, so that code behind a feature compiles cleanly both ways.
+//!
+//! Checking every set takes a while, so this only runs in the minimal
+//! build: `cargo test --no-default-features`.
+
+#![cfg(not(feature = "core"))]
+
+use std::path::Path;
+use std::process::Command;
+
+/// Optional features, each checked together with `core` only.
+const FEATURES: &[&str] = &[
+    "host-details",
+    "sensors",
+    "processes",
+    "serve",
+    "history",
+    "probes",
+    "net-sinks",
+    "s3",
+    "self-update",
+    "mqtt",
+    "containers",
+    "ffi",
+];
+
+#[test]
+fn each_feature_builds_without_warnings() {
+    let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    // A target directory of its own, as the outer build holds the lock on
+    // the default one
+    let target_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("each-feature");
+    let sets = std::iter::once("core".to_string())
+        .chain(FEATURES.iter().map(|feature| format!("core,{}", feature)));
+    let failed: Vec<String> = sets
+        .filter(|features| {
+            let status = Command::new(&cargo)
+                .args(["check", "--quiet", "--all-targets", "--no-default-features"])
+                .args(["--features", features])
+                .current_dir(env!("CARGO_MANIFEST_DIR"))
+                .env("CARGO_TARGET_DIR", &target_dir)
+                .env("RUSTFLAGS", "-D warnings")
+                .status()
+                .expect("failed to run cargo");
+            !status.success()
+        })
+        .collect();
+    assert!(
+        failed.is_empty(),
+        "feature sets with errors or warnings: {}",
+        failed.join("; ")
+    );
+}
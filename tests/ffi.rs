@@ -0,0 +1,56 @@
+//! Round trip through the C interface: compiles `tests/ffi/roundtrip.c`
+//! with the system C compiler against the shared library and runs it.
+//!
+//! Run with `cargo test --features ffi`.
+
+#![cfg(all(feature = "ffi", unix))]
+
+use std::path::Path;
+use std::process::Command;
+
+#[test]
+fn c_program_round_trip() {
+    // The shared library in target/<profile> is replaced by every build,
+    // with or without the feature, so build one of our own next to it.
+    // Test executables are built in target/<profile>/deps.
+    let exe = std::env::current_exe().unwrap();
+    let target = exe.ancestors().nth(3).unwrap().join("ffi");
+    let manifest = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let status = Command::new(env!("CARGO"))
+        .args(["build", "--lib", "--features", "ffi", "--target-dir"])
+        .arg(&target)
+        .arg("--manifest-path")
+        .arg(manifest.join("Cargo.toml"))
+        .status()
+        .expect("failed to run cargo");
+    assert!(status.success(), "building the shared library failed");
+    let libs = target.join("debug");
+    let program = libs.join("ffi_roundtrip");
+
+    let status = Command::new(std::env::var("CC").unwrap_or_else(|_| "cc".to_string()))
+        .arg(manifest.join("tests/ffi/roundtrip.c"))
+        .arg("-I")
+        .arg(manifest.join("include"))
+        .arg("-L")
+        .arg(&libs)
+        .arg(format!("-Wl,-rpath,{}", libs.display()))
+        .arg("-lrust_get_system_info")
+        .arg("-o")
+        .arg(&program)
+        .status()
+        .expect("failed to run the C compiler");
+    assert!(status.success(), "compiling roundtrip.c failed");
+
+    // cargo points LD_LIBRARY_PATH at target/<profile>, which would take
+    // precedence over the rpath
+    let output = Command::new(&program)
+        .env_remove("LD_LIBRARY_PATH")
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "roundtrip failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "ok\n");
+}
@@ -0,0 +1,43 @@
+/* Collects a snapshot through the C interface in both formats. */
+
+#include <stdio.h>
+#include <string.h>
+
+#include "rust_get_system_info.h"
+
+int main(void) {
+    char *json = NULL;
+    int32_t status = sysinfo_collect_json(&json);
+    if (status != SYSINFO_STATUS_OK || json == NULL) {
+        fprintf(stderr, "sysinfo_collect_json failed: %d\n", status);
+        return 1;
+    }
+    if (json[0] != '{' || strstr(json, "\"hostname\":") == NULL) {
+        fprintf(stderr, "unexpected JSON: %.80s\n", json);
+        return 1;
+    }
+    sysinfo_free_string(json);
+
+    uint8_t *buffer = NULL;
+    size_t len = 0;
+    status = sysinfo_collect_msgpack(&buffer, &len);
+    if (status != SYSINFO_STATUS_OK || buffer == NULL || len == 0) {
+        fprintf(stderr, "sysinfo_collect_msgpack failed: %d\n", status);
+        return 1;
+    }
+    /* A snapshot has more than 15 fields, so it is a map16 or map32 */
+    if (buffer[0] != 0xde && buffer[0] != 0xdf) {
+        fprintf(stderr, "unexpected MessagePack marker: 0x%02x\n", buffer[0]);
+        return 1;
+    }
+    sysinfo_free_buffer(buffer, len);
+
+    if (sysinfo_collect_json(NULL) != SYSINFO_STATUS_NULL_POINTER) {
+        fprintf(stderr, "a null output pointer was not rejected\n");
+        return 1;
+    }
+    sysinfo_free_string(NULL);
+
+    puts("ok");
+    return 0;
+}
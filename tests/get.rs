@@ -0,0 +1,29 @@
+//! `--get` end to end: the bare value of one flat key on stdout, and exit
+//! code 5 for a key that does not exist.
+
+use std::process::{Command, Output};
+
+/// Runs the binary with `--get KEY`.
+fn get(key: &str) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_RustGetSystemInfo"))
+        .args(["--get", key])
+        .output()
+        .expect("failed to run RustGetSystemInfo")
+}
+
+#[test]
+fn prints_one_value() {
+    let output = get("total_memory");
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.trim_end().parse::<u64>().unwrap() > 0);
+    assert_eq!(stdout.lines().count(), 1);
+}
+
+#[test]
+fn missing_key_exits_5() {
+    let output = get("no_such_key");
+    assert_eq!(output.status.code(), Some(5));
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("No such key: no_such_key"));
+}
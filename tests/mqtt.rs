@@ -0,0 +1,66 @@
+//! Publishing to an in-process MQTT broker: runs the binary with
+//! `--mqtt-url` against a listener that accepts one connection and records
+//! the PUBLISH packet.
+//!
+//! Run with `cargo test --features mqtt`.
+
+#![cfg(feature = "mqtt")]
+
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::process::Command;
+
+/// Reads one packet, returning its first header byte and its body.
+fn read_packet(stream: &mut TcpStream) -> (u8, Vec<u8>) {
+    let mut byte = [0];
+    stream.read_exact(&mut byte).unwrap();
+    let header = byte[0];
+    let (mut length, mut shift) = (0, 0);
+    loop {
+        stream.read_exact(&mut byte).unwrap();
+        length |= usize::from(byte[0] & 0x7f) << shift;
+        shift += 7;
+        if byte[0] & 0x80 == 0 {
+            break;
+        }
+    }
+    let mut body = vec![0; length];
+    stream.read_exact(&mut body).unwrap();
+    (header, body)
+}
+
+#[test]
+fn publishes_retained_snapshot_to_expanded_topic() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("mqtt://{}", listener.local_addr().unwrap());
+    let broker = std::thread::spawn(move || {
+        let (mut client, _) = listener.accept().unwrap();
+        assert_eq!(read_packet(&mut client).0, 0x10, "expected CONNECT");
+        client.write_all(&[0x20, 2, 0, 0]).unwrap();
+        let (header, body) = read_packet(&mut client);
+        // QoS 1 carries a packet identifier after the topic, to acknowledge
+        let topic_len = usize::from(u16::from_be_bytes([body[0], body[1]]));
+        let id = &body[2 + topic_len..4 + topic_len];
+        client.write_all(&[0x40, 2, id[0], id[1]]).unwrap();
+        (header, body)
+    });
+
+    let dir = std::env::temp_dir().join(format!("sysinfo-mqtt-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let status = Command::new(env!("CARGO_BIN_EXE_RustGetSystemInfo"))
+        .args(["-q", "--mqtt-url", &url, "--mqtt-qos", "1", "--mqtt-retain"])
+        .args(["--mqtt-topic", "fleet/{hostname}/sysinfo"])
+        .current_dir(&dir)
+        .status()
+        .expect("failed to run RustGetSystemInfo");
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert!(status.success());
+
+    let (header, body) = broker.join().unwrap();
+    assert_eq!(header, 0x33, "expected PUBLISH at QoS 1, retained");
+    let topic_len = usize::from(u16::from_be_bytes([body[0], body[1]]));
+    let topic = std::str::from_utf8(&body[2..2 + topic_len]).unwrap();
+    let snapshot: serde_json::Value = serde_json::from_slice(&body[4 + topic_len..]).unwrap();
+    let hostname = snapshot["hostname"].as_str().unwrap();
+    assert_eq!(topic, format!("fleet/{}/sysinfo", hostname));
+}
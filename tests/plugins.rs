@@ -0,0 +1,86 @@
+//! `--plugin-dir` end to end: runs the binary against a directory of fixture
+//! scripts and checks what each one is reported as.
+
+#![cfg(all(unix, feature = "probes"))]
+
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+/// Creates an empty directory for one test to run in.
+fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("plugins-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Writes an executable shell script named `name` to `dir`.
+fn plugin(dir: &Path, name: &str, body: &str) {
+    let path = dir.join(name);
+    std::fs::write(&path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+/// Runs the binary in `cwd` with `args`, returning the `plugins` section of
+/// the JSON report.
+fn run(cwd: &Path, args: &[&str]) -> serde_json::Value {
+    let output = Command::new(env!("CARGO_BIN_EXE_RustGetSystemInfo"))
+        .current_dir(cwd)
+        .args(["--format", "json"])
+        .args(args)
+        .output()
+        .expect("failed to run RustGetSystemInfo");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    report["plugins"].clone()
+}
+
+#[test]
+fn reports_each_plugin_outcome() {
+    let dir = test_dir("outcomes");
+    let plugins = dir.join("plugins.d");
+    std::fs::create_dir(&plugins).unwrap();
+    plugin(&plugins, "ok", r#"echo '{"jobs": 3}'"#);
+    plugin(&plugins, "bad-json", "echo '{\"jobs\": '");
+    plugin(&plugins, "not-object", "echo '[1, 2]'");
+    plugin(&plugins, "fails", "echo '{}'; exit 3");
+    plugin(&plugins, "hangs", "sleep 30");
+    plugin(&plugins, "chatty", "yes");
+    // Not executable, so not run
+    std::fs::write(plugins.join("README"), "notes").unwrap();
+
+    // A relative directory resolves against the working directory
+    let start = Instant::now();
+    let results = run(
+        &dir,
+        &["--plugin-dir", "plugins.d", "--plugin-timeout", "1"],
+    );
+    assert!(start.elapsed() < Duration::from_secs(20));
+
+    let names: Vec<&String> = results.as_object().unwrap().keys().collect();
+    assert_eq!(
+        names,
+        ["bad-json", "chatty", "fails", "hangs", "not-object", "ok"]
+    );
+    assert_eq!(results["ok"]["data"], serde_json::json!({"jobs": 3}));
+    assert_eq!(results["ok"]["exit_code"], 0);
+    assert!(results["ok"]["error"].is_null());
+
+    let error = |name: &str| results[name]["error"].as_str().unwrap().to_string();
+    assert!(error("bad-json").starts_with("invalid JSON"));
+    assert_eq!(error("not-object"), "output is not a JSON object");
+    assert_eq!(error("fails"), "exited with status 3");
+    assert_eq!(results["fails"]["exit_code"], 3);
+    assert!(results["fails"]["data"].is_null());
+    assert_eq!(error("hangs"), "timed out after 1s");
+    assert!(results["hangs"]["exit_code"].is_null());
+    assert_eq!(error("chatty"), "output exceeds 64 KiB");
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
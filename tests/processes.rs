@@ -0,0 +1,59 @@
+//! `processes --folded` end to end: every line of the output is a stack of
+//! frames and a weight, ready for `flamegraph.pl` or `inferno-flamegraph`.
+
+#![cfg(feature = "processes")]
+
+use std::process::Command;
+
+/// Runs `processes --folded` with `args`, returning the stacks.
+fn folded(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_RustGetSystemInfo"))
+        .args(["processes", "--folded"])
+        .args(args)
+        .output()
+        .expect("failed to run RustGetSystemInfo");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn prints_folded_stacks() {
+    let stacks = folded(&[]);
+    // This test process at least has resident memory
+    assert!(!stacks.is_empty());
+    for line in stacks.lines() {
+        let (stack, weight) = line.rsplit_once(' ').unwrap();
+        assert!(weight.parse::<u64>().unwrap() > 0, "{}", line);
+        assert!(stack.starts_with("root;"), "{}", line);
+        assert!(stack.split(';').all(|frame| !frame.is_empty()), "{}", line);
+    }
+    // Identical stacks are merged
+    let mut lines: Vec<&str> = stacks
+        .lines()
+        .map(|line| line.rsplit_once(' ').unwrap().0)
+        .collect();
+    let count = lines.len();
+    lines.dedup();
+    assert_eq!(lines.len(), count);
+}
+
+#[test]
+fn weighs_by_cpu() {
+    for line in folded(&["--metric", "cpu"]).lines() {
+        assert!(line.rsplit_once(' ').unwrap().1.parse::<u64>().is_ok());
+    }
+}
+
+#[test]
+fn rejects_unknown_metric() {
+    let output = Command::new(env!("CARGO_BIN_EXE_RustGetSystemInfo"))
+        .args(["processes", "--folded", "--metric", "vsz"])
+        .output()
+        .expect("failed to run RustGetSystemInfo");
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+}
@@ -0,0 +1,122 @@
+//! Routing `--s3-url` uploads through a local proxy: runs the binary against
+//! a listener that records each request line and answers it, covering plain
+//! HTTP proxying, `CONNECT` tunnels for HTTPS and `NO_PROXY`.
+
+#![cfg(feature = "s3")]
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::process::{Command, ExitStatus};
+use std::thread::JoinHandle;
+
+/// Accepts one connection and returns its request line. Plain requests get
+/// an empty 200 response; a `CONNECT` is accepted and the tunnel closed, so
+/// the TLS handshake fails.
+fn serve_once(listener: TcpListener) -> JoinHandle<String> {
+    std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line).unwrap();
+        let mut content_length = 0;
+        loop {
+            let mut header = String::new();
+            reader.read_line(&mut header).unwrap();
+            if header.trim().is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse().unwrap();
+                }
+            }
+        }
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body).unwrap();
+        let response = if request_line.starts_with("CONNECT ") {
+            "HTTP/1.1 200 Connection established\r\n\r\n"
+        } else {
+            "HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+        };
+        reader.get_mut().write_all(response.as_bytes()).unwrap();
+        request_line.trim_end().to_string()
+    })
+}
+
+/// Uploads a snapshot to `endpoint` with the proxy settings of `configure`,
+/// in a scratch directory and without the caller's proxy variables.
+fn upload(endpoint: &str, configure: impl FnOnce(&mut Command)) -> ExitStatus {
+    let dir = std::env::temp_dir().join(format!(
+        "sysinfo-proxy-{}-{}",
+        std::process::id(),
+        endpoint.rsplit(':').next().unwrap()
+    ));
+    std::fs::create_dir_all(&dir).unwrap();
+    let mut command = Command::new(env!("CARGO_BIN_EXE_RustGetSystemInfo"));
+    command
+        .args([
+            "-q",
+            "--s3-url",
+            "s3://reports/host.json",
+            "--s3-endpoint",
+            endpoint,
+        ])
+        .env("AWS_ACCESS_KEY_ID", "AKIDEXAMPLE")
+        .env("AWS_SECRET_ACCESS_KEY", "secret")
+        .env("AWS_REGION", "us-east-1")
+        .current_dir(&dir);
+    for name in ["http_proxy", "https_proxy", "all_proxy", "no_proxy"] {
+        command.env_remove(name).env_remove(name.to_uppercase());
+    }
+    configure(&mut command);
+    let status = command.status().expect("failed to run RustGetSystemInfo");
+    std::fs::remove_dir_all(&dir).unwrap();
+    status
+}
+
+#[test]
+fn plain_http_goes_through_proxy_option() {
+    let proxy = TcpListener::bind("127.0.0.1:0").unwrap();
+    let proxy_url = format!("http://{}", proxy.local_addr().unwrap());
+    let requests = serve_once(proxy);
+
+    // Nothing listens there: only the proxy can answer
+    let status = upload("http://127.0.0.1:9", |command| {
+        command.args(["--proxy", &proxy_url]);
+    });
+    assert!(status.success());
+    assert_eq!(
+        requests.join().unwrap(),
+        "PUT http://127.0.0.1:9/reports/host.json HTTP/1.1"
+    );
+}
+
+#[test]
+fn https_is_tunneled_with_connect_from_environment() {
+    let proxy = TcpListener::bind("127.0.0.1:0").unwrap();
+    let proxy_url = format!("http://{}", proxy.local_addr().unwrap());
+    let requests = serve_once(proxy);
+
+    let status = upload("https://127.0.0.1:10", |command| {
+        command.env("HTTPS_PROXY", &proxy_url);
+    });
+    // The test proxy closes the tunnel, so the upload itself fails
+    assert!(!status.success());
+    assert_eq!(requests.join().unwrap(), "CONNECT 127.0.0.1:10 HTTP/1.1");
+}
+
+#[test]
+fn no_proxy_range_bypasses_proxy() {
+    let server = TcpListener::bind("127.0.0.1:0").unwrap();
+    let endpoint = format!("http://{}", server.local_addr().unwrap());
+    let requests = serve_once(server);
+
+    let status = upload(&endpoint, |command| {
+        // Nothing listens on the proxy: only a direct request succeeds
+        command
+            .args(["--proxy", "http://127.0.0.1:9"])
+            .env("NO_PROXY", "example.com, 127.0.0.0/8");
+    });
+    assert!(status.success());
+    assert_eq!(requests.join().unwrap(), "PUT /reports/host.json HTTP/1.1");
+}
@@ -0,0 +1,33 @@
+//! The `quick` subcommand end to end: the bare value on stdout, and the
+//! list of metrics with exit code 2 for an unknown one.
+
+use std::process::{Command, Output};
+
+/// Runs `quick` with `args`.
+fn quick(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_RustGetSystemInfo"))
+        .arg("quick")
+        .args(args)
+        .output()
+        .expect("failed to run RustGetSystemInfo")
+}
+
+#[test]
+fn prints_bare_value() {
+    let output = quick(&["mem-total"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.trim_end().bytes().all(|byte| byte.is_ascii_digit()));
+    assert_eq!(stdout.lines().count(), 1);
+}
+
+#[test]
+fn unknown_metric_lists_metrics() {
+    let output = quick(&["mem-free"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Unknown metric 'mem-free'"));
+    assert!(stderr.contains("mem-used-pct, mem-total,"));
+    assert!(stderr.contains("disk-free:<mount>"));
+}
@@ -0,0 +1,185 @@
+//! `--s3-url` against a local endpoint: runs the binary with `--s3-endpoint`
+//! pointing at a listener that records each request, checks the shape of the
+//! signed PUT, and scripts failures to exercise the retries.
+
+#![cfg(all(unix, feature = "s3"))]
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::process::{Command, Output, Stdio};
+use std::thread::JoinHandle;
+
+/// A request seen by the test endpoint.
+struct Request {
+    line: String,
+    headers: HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+/// Answers one request per entry of `statuses` with that status, returning
+/// the requests in the order they arrived.
+fn serve(listener: TcpListener, statuses: Vec<u16>) -> JoinHandle<Vec<Request>> {
+    std::thread::spawn(move || {
+        let mut requests = Vec::new();
+        for status in statuses {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            let mut headers = HashMap::new();
+            loop {
+                let mut header = String::new();
+                reader.read_line(&mut header).unwrap();
+                if header.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = header.split_once(':') {
+                    headers.insert(name.to_ascii_lowercase(), value.trim().to_string());
+                }
+            }
+            let length = headers
+                .get("content-length")
+                .map_or(0, |length| length.parse().unwrap());
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            let response = format!(
+                "HTTP/1.1 {} Test\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                status
+            );
+            reader.get_mut().write_all(response.as_bytes()).unwrap();
+            requests.push(Request {
+                line: line.trim_end().to_string(),
+                headers,
+                body,
+            });
+        }
+        requests
+    })
+}
+
+/// Uploads a snapshot to a local endpoint answering with `statuses`, in a
+/// scratch directory and without the caller's AWS and proxy settings.
+fn upload(name: &str, statuses: Vec<u16>) -> (Output, String, Vec<Request>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let endpoint = format!("http://{}", listener.local_addr().unwrap());
+    let server = serve(listener, statuses);
+
+    let dir = std::env::temp_dir().join(format!("s3-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let mut command = Command::new(env!("CARGO_BIN_EXE_RustGetSystemInfo"));
+    command
+        .args(["--s3-url", "s3://reports/{hostname}/snapshot.json"])
+        .args(["--s3-endpoint", &endpoint])
+        .env("AWS_ACCESS_KEY_ID", "AKIDEXAMPLE")
+        .env("AWS_SECRET_ACCESS_KEY", "secret")
+        .env("AWS_SESSION_TOKEN", "token")
+        .env("AWS_REGION", "eu-west-1")
+        .env_remove("AWS_PROFILE")
+        .current_dir(&dir);
+    for name in ["http_proxy", "https_proxy", "all_proxy", "no_proxy"] {
+        command.env_remove(name).env_remove(name.to_uppercase());
+    }
+    let output = command.output().expect("failed to run RustGetSystemInfo");
+    std::fs::remove_dir_all(&dir).unwrap();
+    (output, endpoint, server.join().unwrap())
+}
+
+/// Returns the hex SHA-256 digest of `data`, computed by `sha256sum`.
+fn sha256(data: &[u8]) -> String {
+    let mut child = Command::new("sha256sum")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("failed to run sha256sum");
+    child.stdin.take().unwrap().write_all(data).unwrap();
+    let output = child.wait_with_output().unwrap();
+    String::from_utf8(output.stdout).unwrap()[..64].to_string()
+}
+
+#[test]
+fn upload_is_signed_put() {
+    let (output, endpoint, requests) = upload("signed", vec![200]);
+    assert!(output.status.success());
+    assert_eq!(requests.len(), 1);
+    let request = &requests[0];
+
+    let path = request
+        .line
+        .strip_prefix("PUT ")
+        .and_then(|line| line.strip_suffix(" HTTP/1.1"))
+        .unwrap();
+    assert!(path.starts_with("/reports/") && path.ends_with("/snapshot.json"));
+    assert!(!path.contains('{'));
+    assert!(String::from_utf8_lossy(&output.stdout).contains(&format!(
+        "System information uploaded to {}{}\n",
+        endpoint, path
+    )));
+
+    let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+    assert!(body["total_memory"].as_u64().unwrap() > 0);
+    let header = |name: &str| request.headers.get(name).map(String::as_str);
+    assert_eq!(header("content-type"), Some("application/json"));
+    assert_eq!(header("x-amz-security-token"), Some("token"));
+    assert_eq!(
+        header("x-amz-content-sha256"),
+        Some(sha256(&request.body).as_str())
+    );
+    let date = header("x-amz-date").unwrap();
+    assert_eq!(date.len(), "20261015T120000Z".len());
+
+    // AWS4-HMAC-SHA256 Credential=<key>/<date>/<region>/s3/aws4_request,
+    // SignedHeaders=<names>, Signature=<hex>
+    let authorization = header("authorization").unwrap();
+    let credential = format!(
+        "Credential=AKIDEXAMPLE/{}/eu-west-1/s3/aws4_request",
+        &date[..8]
+    );
+    assert!(authorization.starts_with(&format!("AWS4-HMAC-SHA256 {}, ", credential)));
+    let signed_headers = authorization
+        .split(", ")
+        .find_map(|part| part.strip_prefix("SignedHeaders="))
+        .unwrap();
+    for name in [
+        "host",
+        "x-amz-content-sha256",
+        "x-amz-date",
+        "x-amz-security-token",
+    ] {
+        assert!(
+            signed_headers.split(';').any(|signed| signed == name),
+            "{}",
+            name
+        );
+    }
+    let signature = authorization.rsplit("Signature=").next().unwrap();
+    assert_eq!(signature.len(), 64);
+    assert!(signature.bytes().all(|byte| byte.is_ascii_hexdigit()));
+    // The secret itself never leaves the machine
+    assert!(!authorization.contains("secret"));
+}
+
+#[test]
+fn failed_upload_is_retried() {
+    let (output, _, requests) = upload("retry", vec![503, 200]);
+    assert!(output.status.success());
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].body, requests[1].body);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Warning: upload to s3://reports/"));
+    assert!(stderr.contains("failed, retrying in 1s"));
+}
+
+#[test]
+fn upload_fails_after_every_attempt() {
+    let (output, _, requests) = upload("fail", vec![500, 500, 500]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(requests.len(), 3);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let error = stderr
+        .lines()
+        .find(|line| line.contains("Upload to s3://reports/"))
+        .unwrap();
+    assert!(error.contains("/snapshot.json failed: "));
+    assert!(error.ends_with("(after 3 attempts)"));
+}
@@ -0,0 +1,49 @@
+//! `--disk` and `--interface` end to end: only the selected mounts and
+//! interfaces reach the JSON, and a pattern that matches nothing exits with
+//! code 6.
+
+use std::process::{Command, Output};
+
+/// Runs the binary with `--format json` and `args`.
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_RustGetSystemInfo"))
+        .args(["--format", "json"])
+        .args(args)
+        .output()
+        .expect("failed to run RustGetSystemInfo")
+}
+
+#[test]
+fn only_selected_names_are_reported() {
+    let output = run(&[]);
+    assert!(output.status.success());
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let Some(network) = report["networks"]
+        .as_array()
+        .and_then(|networks| networks.first())
+    else {
+        // Nothing to select from in this sandbox
+        return;
+    };
+    let name = network["name"].as_str().unwrap();
+
+    let output = run(&["--interface", name]);
+    assert!(output.status.success());
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let networks = report["networks"].as_array().unwrap();
+    assert_eq!(networks.len(), 1);
+    assert_eq!(networks[0]["name"], name);
+    assert_eq!(
+        report["total_bytes_received"],
+        networks[0]["bytes_received"]
+    );
+}
+
+#[test]
+fn unmatched_pattern_lists_available_names() {
+    let output = run(&["--disk", "/no/such/mount/*"]);
+    assert_eq!(output.status.code(), Some(6));
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("No mount point matches '/no/such/mount/*' (available: "));
+}
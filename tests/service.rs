@@ -0,0 +1,42 @@
+//! The `service` subcommand outside Windows: every action explains that it
+//! is only supported there and exits with code 2, without touching the
+//! system.
+
+#![cfg(not(windows))]
+
+use std::process::Command;
+
+#[test]
+fn service_is_unsupported() {
+    for args in [
+        &["install", "--interval", "60"][..],
+        &["uninstall"],
+        &["start"],
+        &["stop"],
+        &["run", "--interval", "60"],
+    ] {
+        let action = args[0];
+        let output = Command::new(env!("CARGO_BIN_EXE_RustGetSystemInfo"))
+            .arg("service")
+            .args(args)
+            .output()
+            .expect("failed to run RustGetSystemInfo");
+        assert_eq!(output.status.code(), Some(2), "{}", action);
+        assert!(output.stdout.is_empty());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.contains("only supported on Windows"),
+            "{}: {}",
+            action,
+            stderr
+        );
+    }
+
+    // Only install and run take the service's arguments
+    let output = Command::new(env!("CARGO_BIN_EXE_RustGetSystemInfo"))
+        .args(["service", "stop", "--interval", "60"])
+        .output()
+        .expect("failed to run RustGetSystemInfo");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("service stop takes no arguments"));
+}
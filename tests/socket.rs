@@ -0,0 +1,107 @@
+//! `--listen-unix` end to end: runs the binary in interval mode and queries
+//! the snapshot it serves over the socket.
+
+#![cfg(all(unix, feature = "serve"))]
+
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// Creates an empty directory for one test to run in.
+fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("socket-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Starts the binary in `dir`, serving on `socket` with `args` added.
+fn start(dir: &Path, socket: &Path, args: &[&str]) -> Child {
+    Command::new(env!("CARGO_BIN_EXE_RustGetSystemInfo"))
+        .current_dir(dir)
+        .args(["--interval", "1", "--listen-unix"])
+        .arg(socket)
+        .args(args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to run RustGetSystemInfo")
+}
+
+/// Sends `request` (nothing if empty) and returns the response line.
+fn query(socket: &Path, request: &str) -> std::io::Result<String> {
+    let mut stream = UnixStream::connect(socket)?;
+    if !request.is_empty() {
+        writeln!(stream, "{}", request)?;
+    }
+    let mut response = String::new();
+    BufReader::new(stream).read_line(&mut response)?;
+    Ok(response)
+}
+
+/// Waits until the server has a snapshot to answer with.
+fn wait_for_snapshot(socket: &Path) {
+    let deadline = Instant::now() + Duration::from_secs(20);
+    loop {
+        if let Ok(response) = query(socket, "") {
+            if !response.contains("no snapshot collected yet") {
+                return;
+            }
+        }
+        assert!(Instant::now() < deadline, "no snapshot served");
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+#[test]
+fn serves_snapshot_with_requested_mode() {
+    let dir = test_dir("serve");
+    let socket = dir.join("sysinfo.sock");
+    let mut child = start(&dir, &socket, &["--count", "3", "--socket-mode", "0600"]);
+    wait_for_snapshot(&socket);
+
+    let mode = std::fs::symlink_metadata(&socket)
+        .unwrap()
+        .permissions()
+        .mode();
+    assert_eq!(mode & 0o777, 0o600);
+    // The private directory the socket was bound in is gone
+    let mut entries: Vec<String> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .filter(|name| name != "system_info.json")
+        .collect();
+    entries.sort();
+    assert_eq!(entries, ["sysinfo.sock"]);
+
+    let snapshot: serde_json::Value = serde_json::from_str(&query(&socket, "").unwrap()).unwrap();
+    assert!(snapshot["total_memory"].as_u64().unwrap() > 0);
+    let cores = query(&socket, "GET cpu_cores").unwrap();
+    assert_eq!(cores.trim(), snapshot["cpu_cores"].to_string());
+    assert_eq!(
+        query(&socket, "GET gpus").unwrap().trim(),
+        r#"{"error":"unknown section 'gpus'"}"#
+    );
+
+    assert!(child.wait().unwrap().success());
+    assert!(!socket.exists());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn refuses_path_that_is_not_a_socket() {
+    let dir = test_dir("occupied");
+    let socket = dir.join("sysinfo.sock");
+    std::fs::write(&socket, "keep me").unwrap();
+
+    let output = start(&dir, &socket, &["--count", "1"])
+        .wait_with_output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("exists and is not a socket"));
+    assert_eq!(std::fs::read_to_string(&socket).unwrap(), "keep me");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,60 @@
+//! `--splay` end to end: the start-up delay ends early and cleanly on
+//! SIGTERM, without collecting, and is only announced with `--debug-meta`.
+
+#![cfg(unix)]
+
+use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// Creates an empty directory for one test to run in.
+fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("splay-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn sigterm_ends_the_delay() {
+    let dir = test_dir("sigterm");
+    let mut child = Command::new(env!("CARGO_BIN_EXE_RustGetSystemInfo"))
+        .current_dir(&dir)
+        .args(["--splay", "3600", "--splay-random", "--debug-meta"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to run RustGetSystemInfo");
+
+    // The delay is announced once the signal handler is installed
+    let mut stderr = BufReader::new(child.stderr.take().unwrap());
+    let mut line = String::new();
+    stderr.read_line(&mut line).unwrap();
+    assert!(line.starts_with("Waiting "), "{}", line);
+
+    let start = Instant::now();
+    let status = Command::new("kill")
+        .args(["-TERM", &child.id().to_string()])
+        .status()
+        .unwrap();
+    assert!(status.success());
+    assert!(child.wait().unwrap().success());
+    assert!(start.elapsed() < Duration::from_secs(10));
+    assert!(!dir.join("system_info.json").exists());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn delay_is_quiet_by_default() {
+    let dir = test_dir("quiet");
+    let output = Command::new(env!("CARGO_BIN_EXE_RustGetSystemInfo"))
+        .current_dir(&dir)
+        .args(["--splay", "0.1", "--splay-random"])
+        .output()
+        .expect("failed to run RustGetSystemInfo");
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!stderr.contains("Waiting "), "{}", stderr);
+}
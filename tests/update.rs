@@ -0,0 +1,158 @@
+//! `self-update` against a local releases server: runs the binary with
+//! `--update-url` pointing at a listener that answers the releases API, the
+//! platform asset and its checksum, and records each request.
+
+#![cfg(feature = "self-update")]
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::process::{Command, Output};
+use std::thread::JoinHandle;
+
+/// A request seen by the test server: its path and `Accept` header.
+#[derive(Debug, PartialEq)]
+struct Request {
+    path: String,
+    accept: String,
+}
+
+/// Answers `count` requests, looking each path up in `routes`, and returns
+/// the requests in the order they arrived. Unknown paths get a 404.
+fn serve(
+    listener: TcpListener,
+    count: usize,
+    routes: Vec<(String, String)>,
+) -> JoinHandle<Vec<Request>> {
+    std::thread::spawn(move || {
+        let mut requests = Vec::new();
+        for _ in 0..count {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let path = request_line
+                .split_whitespace()
+                .nth(1)
+                .unwrap_or_default()
+                .to_string();
+            let mut accept = String::new();
+            loop {
+                let mut header = String::new();
+                reader.read_line(&mut header).unwrap();
+                if header.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = header.split_once(':') {
+                    if name.eq_ignore_ascii_case("accept") {
+                        accept = value.trim().to_string();
+                    }
+                }
+            }
+            let response = match routes.iter().find(|(route, _)| *route == path) {
+                Some((_, body)) => format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                ),
+                None => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                    .to_string(),
+            };
+            reader.get_mut().write_all(response.as_bytes()).unwrap();
+            requests.push(Request { path, accept });
+        }
+        requests
+    })
+}
+
+/// Returns a releases API response for `tag` whose platform asset and
+/// checksum are served by `base`.
+fn release(tag: &str, base: &str) -> String {
+    let asset = format!(
+        "RustGetSystemInfo-{}{}",
+        std::env::consts::OS,
+        std::env::consts::EXE_SUFFIX
+    );
+    format!(
+        r#"{{"tag_name":"{tag}","assets":[
+            {{"name":"{asset}","browser_download_url":"{base}/asset"}},
+            {{"name":"{asset}.sha256","browser_download_url":"{base}/asset.sha256"}}
+        ]}}"#
+    )
+}
+
+/// Runs `self-update` with `args` against the releases API at `url`,
+/// without the caller's proxy variables.
+fn self_update(url: &str, args: &[&str]) -> Output {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_RustGetSystemInfo"));
+    command
+        .args(["self-update", "--update-url", url])
+        .args(args);
+    for name in ["http_proxy", "https_proxy", "all_proxy", "no_proxy"] {
+        command.env_remove(name).env_remove(name.to_uppercase());
+    }
+    command.output().expect("failed to run RustGetSystemInfo")
+}
+
+/// Starts a server answering `count` requests for a release tagged `tag`,
+/// returning the releases API URL and the server thread.
+fn release_server(tag: &str, count: usize, checksum: &str) -> (String, JoinHandle<Vec<Request>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let routes = vec![
+        ("/releases/latest".to_string(), release(tag, &base)),
+        ("/asset".to_string(), "new executable".to_string()),
+        (
+            "/asset.sha256".to_string(),
+            format!("{}  RustGetSystemInfo\n", checksum),
+        ),
+    ];
+    let server = serve(listener, count, routes);
+    (format!("{}/releases/latest", base), server)
+}
+
+#[test]
+fn check_only_reports_newer_release() {
+    let (url, server) = release_server("v999.0.0", 1, "");
+    let output = self_update(&url, &["--check-only"]);
+    assert_eq!(output.status.code(), Some(8));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Update available: v999.0.0"));
+    assert_eq!(
+        server.join().unwrap(),
+        [Request {
+            path: "/releases/latest".to_string(),
+            accept: "application/vnd.github+json".to_string(),
+        }]
+    );
+}
+
+#[test]
+fn check_only_treats_shorter_equal_version_as_current() {
+    let current = env!("CARGO_PKG_VERSION");
+    let tag = format!("v{}", current.strip_suffix(".0").unwrap_or(current));
+    let (url, server) = release_server(&tag, 1, "");
+    let output = self_update(&url, &["--check-only"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("is up to date"));
+    server.join().unwrap();
+}
+
+#[test]
+fn checksum_mismatch_keeps_executable() {
+    let executable = std::fs::read(env!("CARGO_BIN_EXE_RustGetSystemInfo")).unwrap();
+    let (url, server) = release_server("v999.0.0", 3, &"0".repeat(64));
+    let output = self_update(&url, &[]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("checksum mismatch"));
+    assert_eq!(
+        std::fs::read(env!("CARGO_BIN_EXE_RustGetSystemInfo")).unwrap(),
+        executable
+    );
+
+    let requests = server.join().unwrap();
+    let paths: Vec<&str> = requests.iter().map(|r| r.path.as_str()).collect();
+    assert_eq!(paths, ["/releases/latest", "/asset.sha256", "/asset"]);
+    // Only the API request asks for GitHub's JSON media type
+    assert_eq!(requests[0].accept, "application/vnd.github+json");
+    assert_eq!(requests[1].accept, "*/*");
+    assert_eq!(requests[2].accept, "*/*");
+}